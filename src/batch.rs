@@ -0,0 +1,390 @@
+use crate::convert::{convert_file, ConvertOptions, Direction};
+use crate::error::ConvertError;
+use crate::format::JsonFormat;
+use crate::locale::{tr, trf};
+use crate::warning::{self, Warning};
+use crate::worker::{JobToken, Worker};
+use eframe::egui;
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(test)]
+use std::path::Component;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn default_glob(direction: Direction) -> &'static str {
+    match direction {
+        Direction::ToJson => "*.msgpack",
+        Direction::ToMessagePack => "*.json",
+    }
+}
+
+// Where each converted file goes, always next to its input
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum OutputNaming {
+    // queue/a.msgpack → queue/a.json
+    #[default]
+    ReplaceExtension,
+    // queue/a.msgpack → queue/a.msgpack.json
+    AppendExtension,
+}
+
+impl OutputNaming {
+    pub const ALL: [OutputNaming; 2] = [OutputNaming::ReplaceExtension, OutputNaming::AppendExtension];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputNaming::ReplaceExtension => "Replace the extension",
+            OutputNaming::AppendExtension => "Append the extension",
+        }
+    }
+
+    pub fn output_path(self, input: &Path, direction: Direction) -> PathBuf {
+        let extension = direction.output_extension();
+        match self {
+            OutputNaming::ReplaceExtension => input.with_extension(extension),
+            OutputNaming::AppendExtension => {
+                let mut name = input.as_os_str().to_owned();
+                name.push(".");
+                name.push(extension);
+                PathBuf::from(name)
+            }
+        }
+    }
+}
+
+// `*` stands for any run of characters and `?` for any one, everything else matches itself
+pub fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Pattern position after the last `*` and the name position it was tried against
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((after_star, tried)) => {
+                    p = after_star;
+                    n = tried + 1;
+                    backtrack = Some((after_star, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Files directly inside `directory` whose name matches `glob`, sorted by name
+pub fn matching_files(directory: &Path, glob: &str) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(directory).map_err(|e| format!("Failed to read {}: {}", directory.display(), e))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter(|entry| glob_matches(glob, &entry.file_name().to_string_lossy()))
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchEntry {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    // None until the file's turn came, then what went wrong or the warnings, if any
+    pub result: Option<Result<Vec<Warning>, ConvertError>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BatchSummary {
+    pub converted: usize,
+    pub failed: usize,
+}
+
+// Converts every entry in turn, recording each result as it goes so the list can be shown while
+// the batch runs. A failing file doesn't stop the others, only cancelling does.
+pub fn run_batch(
+    entries: &Mutex<Vec<BatchEntry>>,
+    options: &ConvertOptions,
+    token: &JobToken,
+) -> Result<BatchSummary, ConvertError> {
+    let count = entries.lock().unwrap().len();
+    let mut summary = BatchSummary::default();
+    for index in 0..count {
+        token.check()?;
+        let (input, output) = {
+            let entry = &entries.lock().unwrap()[index];
+            (entry.input.clone(), entry.output.clone())
+        };
+        let result = convert_file(&input, &output, options);
+        if result.is_ok() {
+            summary.converted += 1;
+        } else {
+            summary.failed += 1;
+        }
+        entries.lock().unwrap()[index].result = Some(result);
+        token.progress_counter().store(index + 1, Ordering::Relaxed);
+    }
+    Ok(summary)
+}
+
+pub struct BatchState {
+    pub open: bool,
+    directory: String,
+    glob: String,
+    direction: Direction,
+    naming: OutputNaming,
+    // Shared with the worker, which fills in the results
+    entries: Arc<Mutex<Vec<BatchEntry>>>,
+    worker: Worker<BatchSummary>,
+    summary: Option<BatchSummary>,
+    // Why the batch couldn't start or stopped early
+    error: Option<String>,
+}
+
+impl Default for BatchState {
+    fn default() -> Self {
+        BatchState {
+            open: false,
+            directory: String::new(),
+            glob: default_glob(Direction::default()).to_string(),
+            direction: Direction::default(),
+            naming: OutputNaming::default(),
+            entries: Arc::default(),
+            worker: Worker::default(),
+            summary: None,
+            error: None,
+        }
+    }
+}
+
+impl BatchState {
+    fn start(&mut self, ctx: &egui::Context, json_format: &JsonFormat) {
+        self.summary = None;
+        self.error = None;
+        let files = match matching_files(Path::new(self.directory.trim()), self.glob.trim()) {
+            Ok(files) if files.is_empty() => {
+                self.error = Some(trf("No files in the folder match {}", &[&self.glob.trim()]));
+                return;
+            }
+            Ok(files) => files,
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
+        let entries = files.into_iter().map(|input| BatchEntry {
+            output: self.naming.output_path(&input, self.direction),
+            input,
+            result: None,
+        }).collect::<Vec<_>>();
+        let total = entries.len();
+        // A fresh list, so a superseded batch can't write into this one
+        self.entries = Arc::new(Mutex::new(entries));
+        let shared = self.entries.clone();
+        let options = ConvertOptions::default().direction(self.direction).json_format(*json_format);
+        let ctx = ctx.clone();
+        self.worker.start(total, move |token| {
+            run_batch(&shared, &options, token)
+        }, move || ctx.request_repaint());
+    }
+}
+
+// Converts every matching file of a folder on the worker, listing how each one went
+pub fn batch_window(ctx: &egui::Context, state: &mut BatchState, json_format: &JsonFormat) {
+    match state.worker.poll() {
+        Some(Ok(summary)) => state.summary = Some(summary),
+        Some(Err(e)) => state.error = Some(e.to_string()),
+        None => {}
+    }
+    let mut open = state.open;
+    egui::Window::new(tr("Batch conversion"))
+        .id(egui::Id::new("batch"))
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let running = state.worker.is_running();
+            ui.add_enabled_ui(!running, |ui| {
+                egui::Grid::new("batch_grid").num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+                    ui.label(tr("Folder:"));
+                    ui.add(egui::TextEdit::singleline(&mut state.directory).desired_width(320.0).hint_text(tr("Path of the folder")));
+                    ui.end_row();
+
+                    ui.label(tr("Direction:"));
+                    ui.horizontal(|ui| {
+                        for direction in Direction::ALL {
+                            let previous = state.direction;
+                            if ui.radio_value(&mut state.direction, direction, tr(direction.name())).changed()
+                                && state.glob == default_glob(previous)
+                            {
+                                state.glob = default_glob(direction).to_string();
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label(tr("Files:"));
+                    ui.add(egui::TextEdit::singleline(&mut state.glob).desired_width(160.0))
+                        .on_hover_text(tr("* matches any characters, ? a single one"));
+                    ui.end_row();
+
+                    ui.label(tr("Output:"));
+                    ui.horizontal(|ui| {
+                        let example = Path::new(match state.direction {
+                            Direction::ToJson => "a.msgpack",
+                            Direction::ToMessagePack => "a.json",
+                        });
+                        for naming in OutputNaming::ALL {
+                            let output = naming.output_path(example, state.direction);
+                            ui.radio_value(&mut state.naming, naming, tr(naming.name()))
+                                .on_hover_text(format!("{} → {}", example.display(), output.display()));
+                        }
+                    });
+                    ui.end_row();
+                });
+            });
+
+            ui.horizontal(|ui| {
+                let ready = !state.directory.trim().is_empty() && !state.glob.trim().is_empty();
+                if ui.add_enabled(ready && !running, egui::Button::new(tr("Start"))).clicked() {
+                    state.start(ctx, json_format);
+                }
+                if let Some(progress) = state.worker.progress() {
+                    ui.add(egui::ProgressBar::new(progress).desired_width(160.0).animate(true));
+                    if ui.button(tr("Cancel")).clicked() {
+                        state.worker.cancel();
+                        state.error = Some(tr("Cancelled").to_string());
+                    }
+                }
+            });
+            if let Some(error) = &state.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            if let Some(summary) = state.summary {
+                ui.label(trf("{} converted, {} failed", &[&summary.converted, &summary.failed]));
+            }
+
+            let entries = state.entries.lock().unwrap();
+            if !entries.is_empty() {
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(240.0).auto_shrink([false, true]).show(ui, |ui| {
+                    for entry in entries.iter() {
+                        let name = entry.input.file_name().map_or_else(|| entry.input.display().to_string(), |name| name.to_string_lossy().into_owned());
+                        ui.horizontal_wrapped(|ui| {
+                            match &entry.result {
+                                Some(Ok(warnings)) => {
+                                    ui.label("✔");
+                                    ui.label(name);
+                                    if !warnings.is_empty() {
+                                        let text = warnings.iter().map(Warning::to_string).collect::<Vec<_>>().join("\n");
+                                        ui.colored_label(ui.visuals().warn_fg_color, warning::count(warnings)).on_hover_text(text);
+                                    }
+                                }
+                                Some(Err(e)) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, "✖");
+                                    ui.label(name);
+                                    ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                                }
+                                None => {
+                                    ui.weak("·");
+                                    ui.weak(name);
+                                }
+                            }
+                        });
+                    }
+                });
+            }
+            if running {
+                // The list fills in from the worker thread
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+        });
+    state.open = open;
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn temp_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("messagepack_to_json_{}_{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+#[test]
+fn test_glob_matches() {
+    assert!(glob_matches("*.msgpack", "record-1.msgpack"));
+    assert!(!glob_matches("*.msgpack", "record-1.msgpack.json"));
+    assert!(glob_matches("record-?.*", "record-1.msgpack"));
+    assert!(!glob_matches("record-?.*", "record-10.msgpack"));
+    assert!(glob_matches("*a*b*", "xaybzb"));
+    assert!(glob_matches("*", ""));
+    assert!(!glob_matches("a", ""));
+}
+
+#[test]
+fn test_output_naming() {
+    let input = Path::new("queue/a.msgpack");
+    assert_eq!(OutputNaming::ReplaceExtension.output_path(input, Direction::ToJson), Path::new("queue/a.json"));
+    assert_eq!(OutputNaming::AppendExtension.output_path(input, Direction::ToJson), Path::new("queue/a.msgpack.json"));
+    assert_eq!(OutputNaming::ReplaceExtension.output_path(Path::new("b.json"), Direction::ToMessagePack), Path::new("b.msgpack"));
+}
+
+#[test]
+fn test_batch_keeps_going_past_failures() {
+    let directory = temp_directory("batch");
+    fs::write(directory.join("a.msgpack"), [0x81, 0xa1, 0x61, 0x01]).unwrap();
+    // Truncated map
+    fs::write(directory.join("b.msgpack"), [0x82, 0xa1, 0x61]).unwrap();
+    fs::write(directory.join("c.msgpack"), [0x92, 0x01, 0xc3]).unwrap();
+    fs::write(directory.join("notes.txt"), "not part of the batch").unwrap();
+
+    let files = matching_files(&directory, "*.msgpack").unwrap();
+    assert_eq!(files.len(), 3);
+    let entries = Mutex::new(files.into_iter().map(|input| BatchEntry {
+        output: OutputNaming::ReplaceExtension.output_path(&input, Direction::ToJson),
+        input,
+        result: None,
+    }).collect());
+    let summary = run_batch(&entries, &ConvertOptions::default(), &JobToken::default()).unwrap();
+    assert_eq!(summary, BatchSummary { converted: 2, failed: 1 });
+
+    let entries = entries.into_inner().unwrap();
+    assert!(entries[1].result.as_ref().unwrap().is_err());
+    assert_eq!(fs::read_to_string(directory.join("a.json")).unwrap(), "{\n  \"a\": 1\n}");
+    assert_eq!(fs::read_to_string(directory.join("c.json")).unwrap(), "[\n  1,\n  true\n]");
+    assert!(!directory.join("b.json").exists());
+
+    // And back, next to the JSON files
+    let to_messagepack = ConvertOptions { direction: Direction::ToMessagePack, ..Default::default() };
+    convert_file(&directory.join("c.json"), &directory.join("c2.msgpack"), &to_messagepack).unwrap();
+    assert_eq!(fs::read(directory.join("c2.msgpack")).unwrap(), [0x92, 0x01, 0xc3]);
+    assert_eq!(convert_file(&directory.join("c.json"), &directory.join("c.json"), &to_messagepack), Err(ConvertError::SameFile(directory.join("c.json"))));
+    // The same file by another name, relative to the working directory
+    let normal = |path: &Path| path.components().filter(|c| matches!(c, Component::Normal(_))).map(|c| c.as_os_str().to_owned()).collect::<Vec<_>>();
+    let up: PathBuf = normal(&std::env::current_dir().unwrap()).iter().map(|_| "..").collect();
+    let relative = Path::new(".").join(up).join(normal(&directory).iter().collect::<PathBuf>()).join("c.json");
+    assert_eq!(convert_file(&relative, &directory.join("c.json"), &to_messagepack), Err(ConvertError::SameFile(relative.clone())));
+    assert_eq!(fs::read(directory.join("c.json")).unwrap(), b"[\n  1,\n  true\n]");
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_cancelled_batch_stops_before_the_next_file() {
+    let token = JobToken::default();
+    token.cancel();
+    let entries = Mutex::new(vec![BatchEntry { input: PathBuf::from("a.msgpack"), output: PathBuf::from("a.json"), result: None }]);
+    assert!(run_batch(&entries, &ConvertOptions::default(), &token).is_err());
+    assert_eq!(entries.lock().unwrap()[0].result, None);
+}
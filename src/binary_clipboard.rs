@@ -0,0 +1,50 @@
+use crate::locale::{tr, trf};
+
+// Raw bytes from the clipboard, for tools that copy MessagePack as binary rather than as text.
+// Only X11 lets a format be asked for by name, elsewhere reading fails saying so.
+
+// Asked for in this order, the first one the clipboard offers is read
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "android"))))]
+const BINARY_FORMATS: [&str; 4] = ["application/msgpack", "application/x-msgpack", "application/vnd.msgpack", "application/octet-stream"];
+
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "android"))))]
+pub fn paste_binary() -> Result<Vec<u8>, String> {
+    use std::time::Duration;
+    use x11_clipboard::Clipboard;
+
+    // A clipboard owner that doesn't answer by then isn't going to
+    const TIMEOUT: Duration = Duration::from_secs(2);
+    let failed = |e: x11_clipboard::error::Error| trf("Failed to read the clipboard: {}", &[&e]);
+    let clipboard = Clipboard::new().map_err(failed)?;
+    let atoms = &clipboard.getter.atoms;
+    let targets = clipboard.load(atoms.clipboard, atoms.targets, atoms.property, TIMEOUT).map_err(failed)?;
+    let offered = target_atoms(&targets);
+    for name in BINARY_FORMATS {
+        let atom = clipboard.getter.get_atom(name).map_err(failed)?;
+        if offered.contains(&atom) {
+            return clipboard.load(atoms.clipboard, atom, atoms.property, TIMEOUT).map_err(failed);
+        }
+    }
+    Err(tr("The clipboard holds no binary data, paste text into the pane instead").to_string())
+}
+
+#[cfg(not(all(unix, not(any(target_os = "macos", target_os = "android")))))]
+pub fn paste_binary() -> Result<Vec<u8>, String> {
+    Err(tr("Binary clipboard not supported here").to_string())
+}
+
+// The formats the clipboard owner offers, as the 32-bit atoms its TARGETS answer lists
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "android"))))]
+fn target_atoms(targets: &[u8]) -> Vec<u32> {
+    targets.chunks_exact(4).map(|atom| u32::from_ne_bytes([atom[0], atom[1], atom[2], atom[3]])).collect()
+}
+
+
+/* Tests */
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "android"))))]
+#[test]
+fn test_target_atoms() {
+    let targets: Vec<u8> = [31u32, 440, 441].iter().flat_map(|atom| atom.to_ne_bytes()).collect();
+    assert_eq!(target_atoms(&targets), [31, 440, 441]);
+    assert_eq!(target_atoms(&targets[..7]), [31]);
+}
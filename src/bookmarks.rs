@@ -0,0 +1,346 @@
+use crate::convert::Direction;
+use crate::locale::{tr, trf};
+use crate::settings::config_file;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+// Format of the bookmarks file and of exports, raised whenever an older build would read one wrongly
+pub const BOOKMARKS_VERSION: u64 = 1;
+
+// A payload kept under a name, with what it converted to
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct Bookmark {
+    pub name: String,
+    pub notes: String,
+    pub direction: Direction,
+    // JSON for a bookmark towards MessagePack, base64 or hex the other way
+    pub input: String,
+    pub output: String,
+}
+
+// The bookmarks in the order they're listed, each name once
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct Bookmarks {
+    pub version: u64,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    // Missing or unreadable bookmarks leave the list empty, like missing settings
+    pub fn load() -> Bookmarks {
+        bookmarks_path()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|bytes| Bookmarks::read(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = bookmarks_path().ok_or("Failed to locate a config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        fs::write(&path, self.write()?).map_err(|e| format!("Failed to write bookmarks: {}", e))
+    }
+
+    // Pretty-printed JSON, the same for the bookmarks file and an export
+    pub fn write(&self) -> Result<Vec<u8>, String> {
+        let bookmarks = Bookmarks { version: BOOKMARKS_VERSION, bookmarks: self.bookmarks.clone() };
+        serde_json::to_vec_pretty(&bookmarks).map_err(|e| format!("Failed to serialize bookmarks: {}", e))
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Bookmarks, String> {
+        let bookmarks: Bookmarks = serde_json::from_slice(bytes).map_err(|e| e.to_string())?;
+        match bookmarks.version {
+            0 => Err(tr("Not a bookmarks file").to_string()),
+            version if version > BOOKMARKS_VERSION => {
+                Err(trf("Exported in format {}, newer than the {} this version reads", &[&version, &BOOKMARKS_VERSION]))
+            }
+            _ => Ok(bookmarks),
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.bookmarks.iter().any(|bookmark| bookmark.name == name)
+    }
+
+    // `name`, or with the first free " (2)", " (3)"… after it when another bookmark has it
+    fn unique_name(&self, name: &str) -> String {
+        let name = name.trim();
+        let name = if name.is_empty() { tr("Bookmark") } else { name };
+        (1..).map(|n| if n == 1 { name.to_string() } else { format!("{} ({})", name, n) })
+            .find(|candidate| !self.contains(candidate))
+            .unwrap_or_default()
+    }
+
+    // Added at the end under a name no other bookmark has, which is returned
+    pub fn add(&mut self, mut bookmark: Bookmark) -> String {
+        bookmark.name = self.unique_name(&bookmark.name);
+        let name = bookmark.name.clone();
+        self.bookmarks.push(bookmark);
+        name
+    }
+
+    // Unlike adding, renaming to a name that's taken is refused rather than numbered
+    pub fn rename(&mut self, index: usize, name: &str) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(tr("A bookmark needs a name").to_string());
+        }
+        if self.bookmarks.iter().enumerate().any(|(other, bookmark)| other != index && bookmark.name == name) {
+            return Err(trf("There is a bookmark named {} already", &[&name]));
+        }
+        self.bookmarks[index].name = name.to_string();
+        Ok(())
+    }
+
+    // Moves the bookmark at `from` to `to`, the ones in between closing up
+    pub fn move_to(&mut self, from: usize, to: usize) {
+        let bookmark = self.bookmarks.remove(from);
+        self.bookmarks.insert(to.min(self.bookmarks.len()), bookmark);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.bookmarks.remove(index);
+    }
+
+    // Adds the bookmarks of an import this list doesn't have yet, numbering the names of those
+    // that clash with a different bookmark. Returns how many were added.
+    pub fn import(&mut self, imported: Bookmarks) -> usize {
+        let mut added = 0;
+        for bookmark in imported.bookmarks {
+            if !self.bookmarks.contains(&bookmark) {
+                self.add(bookmark);
+                added += 1;
+            }
+        }
+        added
+    }
+}
+
+fn bookmarks_path() -> Option<PathBuf> {
+    config_file("bookmarks.json")
+}
+
+#[derive(Default)]
+pub struct BookmarksState {
+    pub open: bool,
+    // Loaded on first open
+    bookmarks: Option<Bookmarks>,
+    // What Bookmark current takes from the tab
+    direction: Direction,
+    name: String,
+    notes: String,
+    // The bookmark whose name is being edited, and the name so far
+    renaming: Option<(usize, String)>,
+    // Of the file to export to or import from
+    path: String,
+    // What the last action did, or why it failed
+    status: Option<Result<String, String>>,
+}
+
+// Lists the bookmarks and returns the one to load into the active tab. `current` gives the input
+// and output panes of the active tab for a direction.
+pub fn bookmarks_window(ctx: &egui::Context, state: &mut BookmarksState, current: impl Fn(Direction) -> (String, String)) -> Option<Bookmark> {
+    let mut loaded = None;
+    let mut open = state.open;
+    egui::Window::new(tr("Bookmarks"))
+        .id(egui::Id::new("bookmarks"))
+        .open(&mut open)
+        .default_width(420.0)
+        .show(ctx, |ui| {
+            let bookmarks = state.bookmarks.get_or_insert_with(Bookmarks::load);
+            let mut changed = false;
+
+            egui::Grid::new("bookmark_current").num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+                ui.label(tr("Name:"));
+                ui.add(egui::TextEdit::singleline(&mut state.name).desired_width(280.0).hint_text(tr("e.g. login request v2")));
+                ui.end_row();
+                ui.label(tr("Notes:"));
+                ui.add(egui::TextEdit::multiline(&mut state.notes).desired_width(280.0).desired_rows(2));
+                ui.end_row();
+                ui.label(tr("Direction:"));
+                ui.horizontal(|ui| {
+                    for direction in Direction::ALL {
+                        ui.radio_value(&mut state.direction, direction, tr(direction.name()));
+                    }
+                });
+                ui.end_row();
+            });
+            if ui.button(tr("Bookmark current")).on_hover_text(tr("Keep the input and output of this direction in the active tab")).clicked() {
+                let (input, output) = current(state.direction);
+                let bookmark = Bookmark { name: std::mem::take(&mut state.name), notes: std::mem::take(&mut state.notes), direction: state.direction, input, output };
+                let name = bookmarks.add(bookmark);
+                state.status = Some(Ok(trf("Bookmarked as {}", &[&name])));
+                changed = true;
+            }
+            ui.separator();
+
+            if bookmarks.bookmarks.is_empty() {
+                ui.weak(tr("No bookmarks yet"));
+            }
+            let count = bookmarks.bookmarks.len();
+            let mut action = None;
+            egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                for (index, bookmark) in bookmarks.bookmarks.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        match &mut state.renaming {
+                            Some((renaming, name)) if *renaming == index => {
+                                let response = ui.add(egui::TextEdit::singleline(name).desired_width(200.0));
+                                if response.lost_focus() {
+                                    action = Some(BookmarkAction::Rename(index));
+                                }
+                                response.request_focus();
+                            }
+                            _ => {
+                                let hover = match bookmark.notes.is_empty() {
+                                    true => tr(bookmark.direction.name()).to_string(),
+                                    false => format!("{}\n{}", tr(bookmark.direction.name()), bookmark.notes),
+                                };
+                                if ui.button(&bookmark.name).on_hover_text(hover).clicked() {
+                                    action = Some(BookmarkAction::Load(index));
+                                }
+                            }
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.small_button("🗑").on_hover_text(tr("Delete")).clicked() {
+                                action = Some(BookmarkAction::Delete(index));
+                            }
+                            if ui.add_enabled(index + 1 < count, egui::Button::new("⏷").small()).on_hover_text(tr("Move down")).clicked() {
+                                action = Some(BookmarkAction::Move(index, index + 1));
+                            }
+                            if ui.add_enabled(index > 0, egui::Button::new("⏶").small()).on_hover_text(tr("Move up")).clicked() {
+                                action = Some(BookmarkAction::Move(index, index.saturating_sub(1)));
+                            }
+                            if ui.small_button("✏").on_hover_text(tr("Rename")).clicked() {
+                                state.renaming = Some((index, bookmark.name.clone()));
+                            }
+                        });
+                    });
+                }
+            });
+            match action {
+                Some(BookmarkAction::Load(index)) => loaded = Some(bookmarks.bookmarks[index].clone()),
+                Some(BookmarkAction::Rename(index)) => {
+                    if let Some((_, name)) = state.renaming.take() {
+                        match bookmarks.rename(index, &name) {
+                            Ok(()) => changed = true,
+                            Err(e) => state.status = Some(Err(e)),
+                        }
+                    }
+                }
+                Some(BookmarkAction::Move(from, to)) => {
+                    bookmarks.move_to(from, to);
+                    state.renaming = None;
+                    changed = true;
+                }
+                Some(BookmarkAction::Delete(index)) => {
+                    bookmarks.remove(index);
+                    state.renaming = None;
+                    changed = true;
+                }
+                None => {}
+            }
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label(tr("File:"));
+                ui.add(egui::TextEdit::singleline(&mut state.path).desired_width(200.0).hint_text("bookmarks.json"));
+                if ui.button(tr("Export")).on_hover_text(tr("Write every bookmark to the file, to share them")).clicked() {
+                    let path = if state.path.trim().is_empty() { "bookmarks.json" } else { state.path.trim() };
+                    state.status = Some(bookmarks.write().and_then(|bytes| {
+                        fs::write(path, bytes).map_err(|e| e.to_string())?;
+                        Ok(trf("Exported {} bookmarks to {}", &[&bookmarks.bookmarks.len(), &path]))
+                    }));
+                }
+                if ui.button(tr("Import")).on_hover_text(tr("Add the bookmarks of an exported file that aren't here yet")).clicked() {
+                    let imported = fs::read(state.path.trim()).map_err(|e| e.to_string()).and_then(|bytes| Bookmarks::read(&bytes));
+                    state.status = Some(imported.map(|imported| {
+                        let added = bookmarks.import(imported);
+                        changed |= added > 0;
+                        trf(if added == 1 { "{} bookmark added" } else { "{} bookmarks added" }, &[&added])
+                    }));
+                }
+            });
+
+            if changed {
+                if let Err(e) = bookmarks.save() {
+                    state.status = Some(Err(e));
+                }
+            }
+            match &state.status {
+                Some(Ok(message)) => {
+                    ui.weak(message);
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+                None => {}
+            }
+        });
+    state.open = open;
+    loaded
+}
+
+enum BookmarkAction {
+    Load(usize),
+    Rename(usize),
+    Move(usize, usize),
+    Delete(usize),
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn bookmark(name: &str, input: &str) -> Bookmark {
+    Bookmark { name: name.to_string(), input: input.to_string(), direction: Direction::ToJson, ..Default::default() }
+}
+
+#[test]
+fn test_bookmarks_round_trip_through_their_file() {
+    let mut bookmarks = Bookmarks::default();
+    bookmarks.add(Bookmark { notes: "from staging".to_string(), output: "{\"a\": 1}".to_string(), ..bookmark("login request v2", "gaFhAQ==") });
+    bookmarks.add(Bookmark { direction: Direction::ToMessagePack, ..bookmark("broken frame from device 0x4F", "[1]") });
+    let bytes = bookmarks.write().unwrap();
+    let read = Bookmarks::read(&bytes).unwrap();
+    assert_eq!(read.version, BOOKMARKS_VERSION);
+    assert_eq!(read.bookmarks, bookmarks.bookmarks);
+
+    // Fields this build doesn't know are ignored, files from a newer one or of another kind aren't read
+    assert_eq!(Bookmarks::read(br#"{"version": 1, "bookmarks": [{"name": "x", "pinned": true}]}"#).unwrap().bookmarks, [Bookmark { name: "x".to_string(), ..Default::default() }]);
+    assert_eq!(Bookmarks::read(br#"{"version": 2}"#), Err("Exported in format 2, newer than the 1 this version reads".to_string()));
+    assert_eq!(Bookmarks::read(b"{}"), Err("Not a bookmarks file".to_string()));
+    assert!(Bookmarks::read(b"[]").is_err());
+}
+
+#[test]
+fn test_bookmark_names_stay_unique() {
+    let mut bookmarks = Bookmarks::default();
+    assert_eq!(bookmarks.add(bookmark("login", "a")), "login");
+    assert_eq!(bookmarks.add(bookmark(" login ", "b")), "login (2)");
+    assert_eq!(bookmarks.add(bookmark("login", "c")), "login (3)");
+    assert_eq!(bookmarks.add(bookmark("", "d")), "Bookmark");
+
+    assert_eq!(bookmarks.rename(2, "login (2)"), Err("There is a bookmark named login (2) already".to_string()));
+    assert_eq!(bookmarks.rename(2, "  "), Err("A bookmark needs a name".to_string()));
+    assert_eq!(bookmarks.rename(1, "login (2)"), Ok(()));
+    assert_eq!(bookmarks.rename(1, "logout"), Ok(()));
+    assert_eq!(bookmarks.add(bookmark("login", "e")), "login (2)");
+
+    // Imports skip what's already here and number what only shares a name
+    let mut imported = Bookmarks::default();
+    imported.add(bookmark("login", "a"));
+    imported.add(bookmark("logout", "z"));
+    assert_eq!(bookmarks.import(imported), 1);
+    let names: Vec<&str> = bookmarks.bookmarks.iter().map(|bookmark| bookmark.name.as_str()).collect();
+    assert_eq!(names, ["login", "logout", "login (3)", "Bookmark", "login (2)", "logout (2)"]);
+
+    bookmarks.move_to(5, 0);
+    bookmarks.move_to(1, 9);
+    bookmarks.remove(1);
+    let names: Vec<&str> = bookmarks.bookmarks.iter().map(|bookmark| bookmark.name.as_str()).collect();
+    assert_eq!(names, ["logout (2)", "login (3)", "Bookmark", "login (2)", "login"]);
+}
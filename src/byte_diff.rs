@@ -0,0 +1,275 @@
+use crate::explain::describe;
+use crate::locale::{tr, trf};
+use crate::msgpack::{walk, Token};
+use std::ops::Range;
+
+// Which bytes two MessagePack payloads differ in, for the encoding choices (fixstr or str8, the
+// width of an int) that the value diff doesn't see. The bytes are aligned along a shortest edit
+// script, so a byte more on one side only shifts what follows instead of marking all of it.
+
+// Past this many inserted and deleted bytes the edit script costs too much to find, and the
+// bytes in between the common start and end are aligned offset by offset instead
+const MAX_EDITS: usize = 512;
+
+// Bytes per row of the side-by-side dump
+pub const ROW_BYTES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Same,
+    // A byte only the left has
+    Delete,
+    // A byte only the right has
+    Insert,
+}
+
+// A run of bytes that differ, and the byte ranges each side has in it. One of them is empty where
+// the other side has bytes the first lacks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffRange {
+    pub left: Range<usize>,
+    pub right: Range<usize>,
+}
+
+// One position of the dump: a byte of each side, or a gap where that side has none
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub left: Option<u8>,
+    pub right: Option<u8>,
+}
+
+impl Cell {
+    pub fn differs(&self) -> bool {
+        self.left != self.right
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ByteDiff {
+    pub left: Vec<u8>,
+    pub right: Vec<u8>,
+    pub cells: Vec<Cell>,
+    pub ranges: Vec<DiffRange>,
+    // The edit script was too long to find, so the middle was aligned by offset
+    pub by_offset: bool,
+}
+
+impl ByteDiff {
+    pub fn between(left: Vec<u8>, right: Vec<u8>) -> ByteDiff {
+        let prefix = left.iter().zip(&right).take_while(|(a, b)| a == b).count();
+        let suffix = left[prefix..].iter().rev().zip(right[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+        let middle = (&left[prefix..left.len() - suffix], &right[prefix..right.len() - suffix]);
+        let (script, by_offset) = match edit_script(middle.0, middle.1) {
+            Some(script) => (script, false),
+            None => (by_offset(middle.0.len(), middle.1.len()), true),
+        };
+        let edits: Vec<Edit> = std::iter::repeat_n(Edit::Same, prefix)
+            .chain(script)
+            .chain(std::iter::repeat_n(Edit::Same, suffix))
+            .collect();
+        let (cells, ranges) = align(&left, &right, &edits);
+        ByteDiff { left, right, cells, ranges, by_offset }
+    }
+
+    // The rows of the dump, each with the offset its first byte has on either side
+    pub fn rows(&self) -> Vec<(usize, usize, &[Cell])> {
+        let (mut left, mut right) = (0, 0);
+        self.cells.chunks(ROW_BYTES).map(|cells| {
+            let row = (left, right, cells);
+            left += cells.iter().filter(|cell| cell.left.is_some()).count();
+            right += cells.iter().filter(|cell| cell.right.is_some()).count();
+            row
+        }).collect()
+    }
+
+    // What each side encodes where a range starts, e.g.
+    // "0x0002..0x0003 / 0x0002..0x0004: left has uint8 1, right has uint16 1"
+    pub fn describe(&self, range: &DiffRange) -> String {
+        let left = token_at(&self.left, range.left.start).map(|token| describe(&self.left, &token));
+        let right = token_at(&self.right, range.right.start).map(|token| describe(&self.right, &token));
+        let side = |name: &str, bytes: &Range<usize>, token: Option<String>| match (bytes.is_empty(), token) {
+            (true, _) => trf("{} has nothing there", &[&name]),
+            (false, Some(token)) => trf("{} has {}", &[&name, &token]),
+            (false, None) => trf("{} has bytes that don't decode", &[&name]),
+        };
+        format!(
+            "{} / {}: {}, {}",
+            location(&range.left),
+            location(&range.right),
+            side(tr("left"), &range.left, left),
+            side(tr("right"), &range.right, right),
+        )
+    }
+}
+
+// 0x0004 for no bytes before offset 4, 0x0004..0x0006 for two bytes from there
+fn location(range: &Range<usize>) -> String {
+    match range.is_empty() {
+        true => format!("{:#06x}", range.start),
+        false => format!("{:#06x}..{:#06x}", range.start, range.end),
+    }
+}
+
+// The innermost token whose bytes hold `offset`: a string's, not the map's it's a key of
+fn token_at(bytes: &[u8], offset: usize) -> Option<Token> {
+    let mut found = None;
+    // A corrupt side still has the tokens before where it breaks
+    let _ = walk(bytes, |token, _| {
+        if (token.start..token.end).contains(&offset) {
+            found = Some(token.clone());
+        }
+    });
+    found
+}
+
+// Myers' shortest edit script, None when it's longer than MAX_EDITS
+fn edit_script(left: &[u8], right: &[u8]) -> Option<Vec<Edit>> {
+    let (n, m) = (left.len() as isize, right.len() as isize);
+    let max = (n + m).min(MAX_EDITS as isize);
+    // Furthest x reached on each diagonal k = x - y, k stored at k + offset
+    let offset = max + 1;
+    let mut furthest = vec![0isize; 2 * max as usize + 3];
+    let mut trace = Vec::new();
+    for d in 0..=max {
+        trace.push(furthest.clone());
+        for k in (-d..=d).step_by(2) {
+            let down = k == -d || (k != d && furthest[(offset + k - 1) as usize] < furthest[(offset + k + 1) as usize]);
+            let mut x = if down { furthest[(offset + k + 1) as usize] } else { furthest[(offset + k - 1) as usize] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && left[x as usize] == right[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, offset, n, m));
+            }
+        }
+    }
+    None
+}
+
+// The edits, walking the furthest points of each step back from the end
+fn backtrack(trace: &[Vec<isize>], offset: isize, n: isize, m: isize) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let down = k == -d || (k != d && furthest[(offset + k - 1) as usize] < furthest[(offset + k + 1) as usize]);
+        let previous_k = if down { k + 1 } else { k - 1 };
+        let previous_x = if d == 0 { 0 } else { furthest[(offset + previous_k) as usize] };
+        let previous_y = if d == 0 { 0 } else { previous_x - previous_k };
+        while x > previous_x && y > previous_y {
+            edits.push(Edit::Same);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            edits.push(if x == previous_x { Edit::Insert } else { Edit::Delete });
+        }
+        (x, y) = (previous_x, previous_y);
+    }
+    edits.reverse();
+    edits
+}
+
+// Byte n against byte n, the longer side's rest against nothing
+fn by_offset(left: usize, right: usize) -> Vec<Edit> {
+    let common = left.min(right);
+    let mut edits = [Edit::Delete, Edit::Insert].repeat(common);
+    let rest = if left > right { Edit::Delete } else { Edit::Insert };
+    edits.extend(std::iter::repeat_n(rest, left.max(right) - common));
+    edits
+}
+
+// The cells of the dump and the ranges that differ. Within a run of edits the deleted and the
+// inserted bytes are paired up, so a byte that was replaced sits next to its replacement.
+fn align(left: &[u8], right: &[u8], edits: &[Edit]) -> (Vec<Cell>, Vec<DiffRange>) {
+    let (mut cells, mut ranges) = (Vec::new(), Vec::new());
+    let (mut x, mut y) = (0, 0);
+    let mut index = 0;
+    while index < edits.len() {
+        if edits[index] == Edit::Same {
+            cells.push(Cell { left: Some(left[x]), right: Some(right[y]) });
+            x += 1;
+            y += 1;
+            index += 1;
+            continue;
+        }
+        let run = edits[index..].iter().take_while(|edit| **edit != Edit::Same).count();
+        let deleted = edits[index..index + run].iter().filter(|edit| **edit == Edit::Delete).count();
+        let inserted = run - deleted;
+        for slot in 0..deleted.max(inserted) {
+            cells.push(Cell { left: (slot < deleted).then(|| left[x + slot]), right: (slot < inserted).then(|| right[y + slot]) });
+        }
+        ranges.push(DiffRange { left: x..x + deleted, right: y..y + inserted });
+        x += deleted;
+        y += inserted;
+        index += run;
+    }
+    (cells, ranges)
+}
+
+// e.g. "0x0000 81 a1 61 cc | 0x0000 81 a1 61 01", a gap being "--"
+pub fn format_row(left_offset: usize, right_offset: usize, cells: &[Cell]) -> String {
+    let side = |byte: fn(&Cell) -> Option<u8>| {
+        cells.iter().map(|cell| byte(cell).map_or("--".to_string(), |byte| format!("{:02x}", byte))).collect::<Vec<_>>().join(" ")
+    };
+    format!(
+        "{:#06x} {:<width$} | {:#06x} {}",
+        left_offset,
+        side(|cell| cell.left),
+        right_offset,
+        side(|cell| cell.right),
+        width = ROW_BYTES * 3 - 1,
+    )
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn pinned(left: &[u8], right: &[u8]) -> (Vec<String>, Vec<String>) {
+    let diff = ByteDiff::between(left.to_vec(), right.to_vec());
+    let rows = diff.rows().into_iter().map(|(left, right, cells)| format_row(left, right, cells).trim_end().to_string()).collect();
+    (rows, diff.ranges.iter().map(|range| diff.describe(range)).collect())
+}
+
+#[test]
+fn test_equal_values_encoded_differently_differ_where_the_encodings_do() {
+    // {"a": 1, "b": "hi"}, with 1 as a positive fixint and a uint16, "hi" as a fixstr and a str8
+    let left = [0x82, 0xa1, b'a', 0x01, 0xa1, b'b', 0xa2, b'h', b'i'];
+    let right = [0x82, 0xa1, b'a', 0xcd, 0x00, 0x01, 0xa1, b'b', 0xd9, 0x02, b'h', b'i'];
+    let (rows, ranges) = pinned(&left, &right);
+    assert_eq!(rows, ["0x0000 82 a1 61 -- -- 01 a1 62 a2 -- 68 69             | 0x0000 82 a1 61 cd 00 01 a1 62 d9 02 68 69"]);
+    assert_eq!(ranges, [
+        "0x0003 / 0x0003..0x0005: left has nothing there, right has uint16 1",
+        "0x0006..0x0007 / 0x0008..0x000a: left has fixstr(2) \"hi\", right has str8(2) \"hi\"",
+    ]);
+}
+
+#[test]
+fn test_byte_diff_aligns_and_falls_back_to_offsets() {
+    let same: Vec<u8> = (0..20).collect();
+    let diff = ByteDiff::between(same.clone(), same.clone());
+    assert!(diff.ranges.is_empty() && !diff.by_offset);
+    assert_eq!(diff.rows().iter().map(|(left, right, cells)| (*left, *right, cells.len())).collect::<Vec<_>>(), [(0, 0, 16), (16, 16, 4)]);
+
+    // Bytes more on one side shift what follows, values back to back are walked one by one
+    let (rows, ranges) = pinned(&[0x01], &[0xc0, 0x01, 0x02]);
+    assert_eq!(rows, ["0x0000 -- 01 --                                        | 0x0000 c0 01 02"]);
+    assert_eq!(ranges, ["0x0000 / 0x0000..0x0001: left has nothing there, right has nil", "0x0001 / 0x0002..0x0003: left has nothing there, right has positive fixint 2"]);
+    let (_, ranges) = pinned(&[0x01, 0x02], &[0x01, 0xc1]);
+    assert_eq!(ranges, ["0x0001..0x0002 / 0x0001..0x0002: left has positive fixint 2, right has bytes that don't decode"]);
+
+    // Nothing in common at all is still aligned byte for byte once the script gets too long
+    let left = vec![0x00; MAX_EDITS];
+    let right = vec![0x01; MAX_EDITS];
+    let diff = ByteDiff::between(left, right);
+    assert!(diff.by_offset);
+    assert_eq!(diff.ranges, [DiffRange { left: 0..MAX_EDITS, right: 0..MAX_EDITS }]);
+    assert!(diff.cells.iter().all(|cell| cell.left == Some(0x00) && cell.right == Some(0x01)));
+    let diff = ByteDiff::between(vec![0x00; 10], vec![0x01; 12]);
+    assert!(!diff.by_offset);
+    assert_eq!(diff.cells.len(), 12);
+}
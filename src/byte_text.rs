@@ -0,0 +1,235 @@
+use crate::error::ConvertError;
+use crate::files::Encoding;
+use crate::locale::tr;
+
+// MessagePack bytes written out as text to paste somewhere else: as an encoding, as a literal for
+// source code, or as a hexdump to read
+
+// Bytes on a line of the array literals and the hexdump
+const ROW_BYTES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyAs {
+    Base64,
+    Hex,
+    // {0x81, 0xa1, …} for an unsigned char or uint8_t array
+    CArray,
+    // &[0x81, 0xa1, …] for a &[u8]
+    RustSlice,
+    // b'\x81\xa1a\x01'
+    PythonBytes,
+    // As xxd prints it
+    Hexdump,
+}
+
+impl CopyAs {
+    pub const ALL: [CopyAs; 6] = [CopyAs::Base64, CopyAs::Hex, CopyAs::CArray, CopyAs::RustSlice, CopyAs::PythonBytes, CopyAs::Hexdump];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CopyAs::Base64 => "Base64",
+            CopyAs::Hex => "Hex",
+            CopyAs::CArray => tr("C array"),
+            CopyAs::RustSlice => "Rust &[u8]",
+            CopyAs::PythonBytes => tr("Python bytes"),
+            CopyAs::Hexdump => tr("Hexdump (xxd)"),
+        }
+    }
+
+    pub fn write(self, bytes: &[u8]) -> String {
+        match self {
+            CopyAs::Base64 => Encoding::Base64.encode(bytes),
+            CopyAs::Hex => Encoding::Hex.encode(bytes),
+            CopyAs::CArray => array_literal(bytes, "{", "}"),
+            CopyAs::RustSlice => array_literal(bytes, "&[", "]"),
+            CopyAs::PythonBytes => python_bytes(bytes),
+            CopyAs::Hexdump => hexdump(bytes),
+        }
+    }
+}
+
+// All on one line when it fits in a row, otherwise a row to a line, indented, with a trailing
+// comma as rustfmt and clang-format leave them
+fn array_literal(bytes: &[u8], open: &str, close: &str) -> String {
+    let row = |row: &[u8]| row.iter().map(|byte| format!("{:#04x}", byte)).collect::<Vec<_>>().join(", ");
+    if bytes.len() <= ROW_BYTES {
+        return format!("{}{}{}", open, row(bytes), close);
+    }
+    let lines: String = bytes.chunks(ROW_BYTES).map(|bytes| format!("    {},\n", row(bytes))).collect();
+    format!("{}\n{}{}", open, lines, close)
+}
+
+// As Python's repr writes bytes, so it reads back the same in Python and in the input pane
+fn python_bytes(bytes: &[u8]) -> String {
+    let mut out = String::from("b'");
+    for &byte in bytes {
+        match byte {
+            b'\\' => out.push_str(r"\\"),
+            b'\'' => out.push_str(r"\'"),
+            b'\t' => out.push_str(r"\t"),
+            b'\n' => out.push_str(r"\n"),
+            b'\r' => out.push_str(r"\r"),
+            0x20..=0x7e => out.push(byte as char),
+            _ => out.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+// Lines of `00000000: 83a4 6e61 6d65 a541 6c69 6365 a361 6765  ..name.Alice.age`: the offset,
+// the bytes in pairs, and the bytes once more as ASCII with a dot for anything unprintable
+pub fn hexdump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (index, row) in bytes.chunks(ROW_BYTES).enumerate() {
+        let pairs: Vec<String> = row.chunks(2).map(hex::encode).collect();
+        let ascii: String = row.iter().map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }).collect();
+        out.push_str(&format!("{:08x}: {:<39}  {}\n", index * ROW_BYTES, pairs.join(" "), ascii));
+    }
+    out
+}
+
+// The bytes of a hexdump as xxd or hexdump -C print it, with the offsets and the ASCII column left
+// out. Each line's offset has to be the number of bytes before it, which tells a dump apart from
+// hex that happens to be split into lines. A line with only an offset, which hexdump -C ends
+// with, has to be the total. A * line, which hexdump -C and xxd -a print in place of rows that
+// repeat the one before, stands for as many copies of that row as it takes to reach the next
+// offset.
+pub fn read_hexdump(text: &str) -> Result<Vec<u8>, ConvertError> {
+    let mut bytes = Vec::new();
+    let mut row_start = 0;
+    let mut squeezed = None;
+    for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let error = ConvertError::Hexdump { line: index + 1 };
+        let line = line.trim_start();
+        if line.trim_end() == "*" {
+            if squeezed.is_some() || row_start == bytes.len() {
+                return Err(error);
+            }
+            squeezed = Some(error);
+            continue;
+        }
+        let (offset, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let offset = offset.strip_suffix(':').unwrap_or(offset);
+        let offset = match usize::from_str_radix(offset, 16) {
+            Ok(number) if offset.len() >= 4 => number,
+            _ => return Err(error),
+        };
+        if let Some(squeeze_error) = squeezed.take() {
+            let row = bytes[row_start..].to_vec();
+            match offset.checked_sub(bytes.len()) {
+                Some(gap) if gap > 0 && gap % row.len() == 0 => {
+                    for _ in 0..gap / row.len() {
+                        bytes.extend_from_slice(&row);
+                    }
+                }
+                _ => return Err(squeeze_error),
+            }
+        }
+        if offset != bytes.len() {
+            return Err(error);
+        }
+        row_start = bytes.len();
+        // The ASCII column is after a | in hexdump -C, after two spaces in xxd. hexdump -C puts
+        // two spaces halfway along the bytes as well, but always has the |.
+        let rest = match rest.split_once('|') {
+            Some((hex, _)) => hex,
+            None => rest.trim_start().split("  ").next().unwrap_or_default(),
+        };
+        for group in rest.split_whitespace() {
+            bytes.extend(hex::decode(group).map_err(|_| error.clone())?);
+        }
+    }
+    if let Some(squeeze_error) = squeezed {
+        return Err(squeeze_error);
+    }
+    if bytes.is_empty() {
+        return Err(ConvertError::Hexdump { line: 1 });
+    }
+    Ok(bytes)
+}
+
+/* Tests */
+#[test]
+fn test_copy_as_each_representation() {
+    // {"a": 1}
+    let bytes = [0x81, 0xa1, b'a', 0x01];
+    let written = CopyAs::ALL.map(|copy_as| copy_as.write(&bytes));
+    assert_eq!(written, [
+        "gaFhAQ==",
+        "81a16101",
+        "{0x81, 0xa1, 0x61, 0x01}",
+        "&[0x81, 0xa1, 0x61, 0x01]",
+        "b'\\x81\\xa1a\\x01'",
+        "00000000: 81a1 6101                                ..a.\n",
+    ]);
+    assert_eq!(python_bytes(b"'\\\n\x7f"), r"b'\'\\\n\x7f'");
+    assert_eq!(CopyAs::Hexdump.write(&[]), "");
+    // Each reads back in the input pane
+    for text in &written {
+        assert_eq!(crate::files::InputEncoding::Auto.decode(text).unwrap().1, bytes, "{}", text);
+    }
+
+    let bytes: Vec<u8> = (0..18).collect();
+    assert_eq!(CopyAs::CArray.write(&bytes), "{
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+    0x10, 0x11,
+}");
+}
+
+#[test]
+fn test_hexdump_lines_up_like_xxd() {
+    let bytes = b"\x83\xa4name\xa5Alice\xa3age\x1e";
+    assert_eq!(hexdump(bytes), "\
+00000000: 83a4 6e61 6d65 a541 6c69 6365 a361 6765  ..name.Alice.age
+00000010: 1e                                       .
+");
+    assert_eq!(read_hexdump(&hexdump(bytes)).unwrap(), bytes);
+}
+
+#[test]
+fn test_hexdumps_are_read_without_offsets_and_ascii() {
+    let bytes = b"\x83\xa4name\xa5Alice\xa3age\x1e";
+    let canonical = "\
+00000000  83 a4 6e 61 6d 65 a5 41  6c 69 63 65 a3 61 67 65  |..name.Alice.age|
+00000010  1e                                                |.|
+00000011
+";
+    assert_eq!(read_hexdump(canonical).unwrap(), bytes);
+    // ASCII that looks like hex, and a dump cut short
+    assert_eq!(read_hexdump("00000000: 6162 6364  abcd\n").unwrap(), b"abcd");
+    assert_eq!(read_hexdump("00000000: 6162 6364\n00000002: 65").unwrap_err(), ConvertError::Hexdump { line: 2 });
+    assert_eq!(read_hexdump("00000000: 616g").unwrap_err(), ConvertError::Hexdump { line: 1 });
+    // Hex on lines of its own has no offsets
+    assert!(read_hexdump("8381a161\n01").is_err());
+    assert!(read_hexdump("12 34").is_err());
+    assert!(read_hexdump("").is_err());
+}
+
+#[test]
+fn test_squeezed_hexdump_rows_are_repeated() {
+    let mut bytes = vec![0x93];
+    bytes.extend([0; 79]);
+    bytes.extend(b"abc");
+    // hexdump -C and xxd -a of the same bytes
+    let canonical = "\
+00000000  93 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|
+00000010  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|
+*
+00000050  61 62 63                                          |abc|
+00000053
+";
+    assert_eq!(read_hexdump(canonical).unwrap(), bytes);
+    let xxd = "\
+00000000: 9300 0000 0000 0000 0000 0000 0000 0000  ................
+00000010: 0000 0000 0000 0000 0000 0000 0000 0000  ................
+*
+00000050: 6162 63                                  abc
+";
+    assert_eq!(read_hexdump(xxd).unwrap(), bytes);
+    // A * with no row before it, a gap that isn't whole rows, and a * with nothing after it
+    assert_eq!(read_hexdump("*\n00000010  00").unwrap_err(), ConvertError::Hexdump { line: 1 });
+    assert_eq!(read_hexdump("00000000  00 00\n*\n00000005  00").unwrap_err(), ConvertError::Hexdump { line: 2 });
+    assert_eq!(read_hexdump("00000000  00 00\n*\n*\n00000006  00").unwrap_err(), ConvertError::Hexdump { line: 3 });
+    assert_eq!(read_hexdump("00000000  00 00\n*\n").unwrap_err(), ConvertError::Hexdump { line: 2 });
+}
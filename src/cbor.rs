@@ -0,0 +1,443 @@
+use crate::decode::{float, MAX_DEPTH};
+use crate::error::{ConvertError, UnsupportedKind};
+use crate::locale::{tr, trf};
+use crate::schema_check::escape_pointer_token;
+use crate::warning::{Warning, WarningKind};
+use base64::{engine::general_purpose, Engine};
+use serde_json::{Map, Value};
+
+// CBOR tags have no JSON counterpart, so a tagged value becomes {"$tag": 1, "value": ...} and an
+// object of exactly that shape is encoded as a tag again
+pub const TAG_KEY: &str = "$tag";
+pub const TAG_VALUE_KEY: &str = "value";
+
+// Major types, the top three bits of the initial byte
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+
+// Additional information 31, indefinite length, and the byte that ends such an item
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xff;
+
+// Decodes the one CBOR value starting at `offset` and says where it ends. Lossy decoding turns
+// what JSON can't hold into something it can, as MessagePack decoding does: byte strings become
+// base64, other map keys their JSON text, invalid UTF-8 is replaced and unknown simple values
+// become null.
+pub fn decode_cbor_at(bytes: &[u8], offset: usize, lossy: bool) -> Result<(Value, usize), ConvertError> {
+    decode_cbor_with(bytes, offset, lossy, &mut Vec::new())
+}
+
+// Same with a warning in `warnings` for everything lossy decoding changed
+pub fn decode_cbor_with(bytes: &[u8], offset: usize, lossy: bool, warnings: &mut Vec<Warning>) -> Result<(Value, usize), ConvertError> {
+    let mut decoder = Decoder { bytes, position: offset, path: String::new(), lossy, warnings: Vec::new() };
+    let value = decoder.value(0)?;
+    warnings.append(&mut decoder.warnings);
+    Ok((value, decoder.position))
+}
+
+// The whole input as one value, anything after it is an error unless decoding is lossy
+pub fn decode_cbor(bytes: &[u8], lossy: bool) -> Result<Value, ConvertError> {
+    let (value, end) = decode_cbor_at(bytes, 0, lossy)?;
+    if end < bytes.len() && !lossy {
+        return Err(ConvertError::TrailingBytes(bytes.len() - end));
+    }
+    Ok(value)
+}
+
+// Integers take the shortest head that holds them, floats are always written as 64 bits
+pub fn encode_cbor(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_value(value, &mut out);
+    out
+}
+
+// Bytes encode_cbor would write for the value, worked out without writing them
+pub fn cbor_len(value: &Value) -> usize {
+    match value {
+        Value::Null | Value::Bool(_) => 1,
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => head_len(n),
+            (None, Some(n)) => head_len((-1 - n) as u64),
+            _ => 9,
+        },
+        Value::String(s) => head_len(s.len() as u64) + s.len(),
+        Value::Array(items) => head_len(items.len() as u64) + items.iter().map(cbor_len).sum::<usize>(),
+        Value::Object(map) => match as_tag(map) {
+            Some((tag, tagged)) => head_len(tag) + cbor_len(tagged),
+            None => head_len(map.len() as u64) + map.iter().map(|(key, item)| head_len(key.len() as u64) + key.len() + cbor_len(item)).sum::<usize>(),
+        },
+    }
+}
+
+fn head_len(n: u64) -> usize {
+    match n {
+        0..=23 => 1,
+        24..=0xff => 2,
+        0x100..=0xffff => 3,
+        0x1_0000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+// Whether the bytes are one complete CBOR value and nothing more, for telling it apart from
+// MessagePack
+pub fn is_cbor(bytes: &[u8]) -> bool {
+    decode_cbor_at(bytes, 0, true).is_ok_and(|(_, end)| end == bytes.len())
+}
+
+fn encode_value(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(b) => out.push(if *b { 0xf5 } else { 0xf4 }),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => head(UNSIGNED, n, out),
+            (None, Some(n)) => head(NEGATIVE, (-1 - n) as u64, out),
+            _ => {
+                out.push(0xfb);
+                out.extend_from_slice(&n.as_f64().unwrap_or(f64::NAN).to_be_bytes());
+            }
+        },
+        Value::String(s) => {
+            head(TEXT, s.len() as u64, out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            head(ARRAY, items.len() as u64, out);
+            for item in items {
+                encode_value(item, out);
+            }
+        }
+        Value::Object(map) => {
+            if let Some((tag, tagged)) = as_tag(map) {
+                head(TAG, tag, out);
+                return encode_value(tagged, out);
+            }
+            head(MAP, map.len() as u64, out);
+            for (key, item) in map {
+                head(TEXT, key.len() as u64, out);
+                out.extend_from_slice(key.as_bytes());
+                encode_value(item, out);
+            }
+        }
+    }
+}
+
+fn as_tag(map: &Map<String, Value>) -> Option<(u64, &Value)> {
+    if map.len() != 2 {
+        return None;
+    }
+    Some((map.get(TAG_KEY)?.as_u64()?, map.get(TAG_VALUE_KEY)?))
+}
+
+fn head(major: u8, n: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    match n {
+        0..=23 => out.push(major | n as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, n as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(n as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    // JSON Pointer of the node being decoded
+    path: String,
+    lossy: bool,
+    warnings: Vec<Warning>,
+}
+
+impl Decoder<'_> {
+    fn value(&mut self, depth: usize) -> Result<Value, ConvertError> {
+        let start = self.position;
+        if depth > MAX_DEPTH {
+            return Err(self.error(start, trf("Nesting deeper than {} levels", &[&MAX_DEPTH])));
+        }
+        let (major, info) = self.initial_byte()?;
+        Ok(match major {
+            UNSIGNED => Value::from(self.argument(start, info)?),
+            NEGATIVE => {
+                let n = self.argument(start, info)?;
+                // Below i64::MIN only a float comes close
+                i64::try_from(n).map_or_else(|_| float(-1.0 - n as f64), |n| Value::from(-1 - n))
+            }
+            BYTES => {
+                let bytes = self.string_bytes(start, major, info)?;
+                if !self.lossy {
+                    return Err(self.unsupported(start, UnsupportedKind::Binary));
+                }
+                self.warn(start, WarningKind::Base64Payload, tr("binary value as base64").to_string());
+                Value::String(general_purpose::STANDARD.encode(bytes))
+            }
+            TEXT => {
+                let bytes = self.string_bytes(start, major, info)?;
+                Value::String(self.text(start, bytes)?)
+            }
+            ARRAY => {
+                let mut items = Vec::new();
+                let len = self.length(start, info)?;
+                let mut index = 0;
+                while self.more(len, index)? {
+                    items.push(self.child(&index.to_string(), depth)?);
+                    index += 1;
+                }
+                Value::Array(items)
+            }
+            MAP => {
+                let mut map = Map::new();
+                let len = self.length(start, info)?;
+                let mut index = 0;
+                while self.more(len, index)? {
+                    let key_start = self.position;
+                    // Cut off input is reported by decoding the key
+                    let text_key = self.bytes.get(key_start).is_none_or(|byte| byte >> 5 == TEXT);
+                    if !text_key && !self.lossy {
+                        return Err(self.unsupported(key_start, UnsupportedKind::MapKey));
+                    }
+                    let key = match self.value(depth + 1)? {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    if !text_key {
+                        self.warn(key_start, WarningKind::StringifiedKey, trf("map key {} as a string", &[&format_args!("{:?}", key)]));
+                    }
+                    let child = self.child(&key, depth)?;
+                    map.insert(key, child);
+                    index += 1;
+                }
+                Value::Object(map)
+            }
+            TAG => {
+                let tag = self.argument(start, info)?;
+                let tagged = self.child(TAG_VALUE_KEY, depth)?;
+                let mut map = Map::new();
+                map.insert(TAG_KEY.to_string(), Value::from(tag));
+                map.insert(TAG_VALUE_KEY.to_string(), tagged);
+                Value::Object(map)
+            }
+            // Major type 7, simple values and floats
+            _ => match info {
+                20 => Value::Bool(false),
+                21 => Value::Bool(true),
+                // undefined has no place in JSON either
+                22 | 23 => Value::Null,
+                25 => float(half(self.take(start, 2)?)),
+                26 => float(f32::from_be_bytes(self.take(start, 4)?.try_into().unwrap()) as f64),
+                27 => float(f64::from_be_bytes(self.take(start, 8)?.try_into().unwrap())),
+                INDEFINITE => return Err(self.error(start, tr("Unexpected break").to_string())),
+                info => {
+                    let simple = if info == 24 { self.take(start, 1)?[0] } else { info };
+                    if !self.lossy {
+                        return Err(self.error(start, trf("Simple value {} is not supported", &[&simple])));
+                    }
+                    self.warn(start, WarningKind::SimpleValue, trf("simple value {} as null", &[&simple]));
+                    Value::Null
+                }
+            },
+        })
+    }
+
+    fn child(&mut self, token: &str, depth: usize) -> Result<Value, ConvertError> {
+        let parent_len = self.path.len();
+        self.path.push('/');
+        self.path.push_str(&escape_pointer_token(token));
+        let child = self.value(depth + 1);
+        self.path.truncate(parent_len);
+        child
+    }
+
+    fn initial_byte(&mut self) -> Result<(u8, u8), ConvertError> {
+        let byte = self.take(self.position, 1)?[0];
+        Ok((byte >> 5, byte & 0x1f))
+    }
+
+    fn take(&mut self, start: usize, len: usize) -> Result<&[u8], ConvertError> {
+        let end = self.position.checked_add(len).filter(|&end| end <= self.bytes.len());
+        let Some(end) = end else {
+            return Err(self.error(start, tr("Unexpected end of input").to_string()));
+        };
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    // The number that follows the initial byte: a length, a value or a tag
+    fn argument(&mut self, start: usize, info: u8) -> Result<u64, ConvertError> {
+        Ok(match info {
+            0..=23 => info as u64,
+            24 => self.take(start, 1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(start, 2)?.try_into().unwrap()) as u64,
+            26 => u32::from_be_bytes(self.take(start, 4)?.try_into().unwrap()) as u64,
+            27 => u64::from_be_bytes(self.take(start, 8)?.try_into().unwrap()),
+            info => return Err(self.error(start, trf("Reserved additional information {}", &[&info]))),
+        })
+    }
+
+    // The item count of an array or map, None if it runs until a break
+    fn length(&mut self, start: usize, info: u8) -> Result<Option<u64>, ConvertError> {
+        if info == INDEFINITE {
+            return Ok(None);
+        }
+        let len = self.argument(start, info)?;
+        // Every item takes at least a byte, so a larger count can't be right
+        if len > (self.bytes.len() - self.position) as u64 {
+            return Err(self.error(start, tr("Unexpected end of input").to_string()));
+        }
+        Ok(Some(len))
+    }
+
+    // Whether the item at `index` follows, consuming the break of an indefinite length item
+    fn more(&mut self, len: Option<u64>, index: u64) -> Result<bool, ConvertError> {
+        match len {
+            Some(len) => Ok(index < len),
+            None if self.bytes.get(self.position) == Some(&BREAK) => {
+                self.position += 1;
+                Ok(false)
+            }
+            None if self.position >= self.bytes.len() => Err(self.error(self.position, tr("Unexpected end of input").to_string())),
+            None => Ok(true),
+        }
+    }
+
+    // A byte or text string's content. Indefinite ones are made of definite chunks of the same type.
+    fn string_bytes(&mut self, start: usize, major: u8, info: u8) -> Result<Vec<u8>, ConvertError> {
+        if info != INDEFINITE {
+            let len = self.argument(start, info)?;
+            let len = usize::try_from(len).unwrap_or(usize::MAX);
+            return Ok(self.take(start, len)?.to_vec());
+        }
+        let mut content = Vec::new();
+        while self.more(None, 0)? {
+            let chunk_start = self.position;
+            let (chunk_major, chunk_info) = self.initial_byte()?;
+            if chunk_major != major || chunk_info == INDEFINITE {
+                return Err(self.error(chunk_start, tr("Invalid chunk in an indefinite length string").to_string()));
+            }
+            let len = usize::try_from(self.argument(chunk_start, chunk_info)?).unwrap_or(usize::MAX);
+            content.extend_from_slice(self.take(chunk_start, len)?);
+        }
+        Ok(content)
+    }
+
+    fn text(&mut self, start: usize, bytes: Vec<u8>) -> Result<String, ConvertError> {
+        match String::from_utf8(bytes) {
+            Ok(text) => Ok(text),
+            Err(e) if self.lossy => {
+                self.warn(start, WarningKind::LossyUtf8, tr("invalid UTF-8 replaced").to_string());
+                Ok(String::from_utf8_lossy(e.as_bytes()).into_owned())
+            }
+            Err(e) => Err(self.error(start, trf("Invalid UTF-8 in string: {}", &[&e.utf8_error()]))),
+        }
+    }
+
+    fn warn(&mut self, offset: usize, kind: WarningKind, message: String) {
+        self.warnings.push(Warning::new(kind, message).at_path(&self.path).at_offset(offset));
+    }
+
+    fn error(&self, offset: usize, msg: String) -> ConvertError {
+        let msg = if self.path.is_empty() { msg } else { trf("{} at {}", &[&msg, &self.path]) };
+        ConvertError::CborDecode { offset, msg }
+    }
+
+    fn unsupported(&self, offset: usize, what: UnsupportedKind) -> ConvertError {
+        self.error(offset, what.to_string())
+    }
+}
+
+// IEEE 754 half precision, which CBOR encoders use for floats that fit
+fn half(bytes: &[u8]) -> f64 {
+    let bits = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let fraction = (bits & 0x3ff) as f64;
+    sign * match exponent {
+        0 => fraction * 2f64.powi(-24),
+        31 if fraction == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        exponent => (1.0 + fraction / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_cbor_round_trips() {
+    let value: Value = serde_json::from_str(r#"{"a": [1, -2, 3.5, null, true, "x"], "b": {"c": 18446744073709551615, "d": -9223372036854775808}}"#).unwrap();
+    assert_eq!(decode_cbor(&encode_cbor(&value), false).unwrap(), value);
+
+    // {"name": "Alice", "age": 30}, as RFC 8949 encoders write it
+    let alice = hex::decode("a2646e616d6565416c69636563616765181e").unwrap();
+    let value = decode_cbor(&alice, false).unwrap();
+    assert_eq!(value, serde_json::json!({"name": "Alice", "age": 30}));
+    assert_eq!(encode_cbor(&value), alice);
+}
+
+#[test]
+fn test_cbor_tags_and_floats() {
+    // Tag 1 (epoch time) on 1363896240, half 1.5, single 100000.0 and double 1.1
+    let bytes = hex::decode("84c11a514b67b0f93e00fa47c35000fb3ff199999999999a").unwrap();
+    let value = decode_cbor(&bytes, false).unwrap();
+    assert_eq!(value, serde_json::json!([{"$tag": 1, "value": 1363896240}, 1.5, 100000.0, 1.1]));
+    // The tag goes back, the floats are widened
+    assert_eq!(encode_cbor(&value)[..7], bytes[..7]);
+    assert_eq!(decode_cbor(&encode_cbor(&value), false).unwrap(), value);
+    // Not exactly the tag shape, an ordinary map
+    assert_eq!(encode_cbor(&serde_json::json!({"$tag": "x", "value": 1}))[0], 0xa2);
+}
+
+#[test]
+fn test_cbor_indefinite_lengths() {
+    // [_ (_ "ab" "c"), {_ "a": [_ 1]}]
+    let bytes = hex::decode("9f7f6261626163ffbf61619f01ffffff").unwrap();
+    assert_eq!(decode_cbor(&bytes, false).unwrap(), serde_json::json!(["abc", {"a": [1]}]));
+    // A byte string chunk in a text string
+    assert!(decode_cbor(&[0x7f, 0x41, 0x00, 0xff], false).is_err());
+    // Missing break
+    assert!(decode_cbor(&[0x9f, 0x01], false).is_err());
+}
+
+#[test]
+fn test_cbor_errors_carry_offsets() {
+    // {"a": [h'00']}
+    let err = decode_cbor(&[0xa1, 0x61, b'a', 0x81, 0x41, 0x00], false).unwrap_err();
+    assert_eq!(err, ConvertError::CborDecode { offset: 4, msg: "Binary values are not supported at /a/0".to_string() });
+    assert_eq!(err.to_string(), "Failed to decode CBOR: Binary values are not supported at /a/0 at offset 0x4");
+    assert_eq!(decode_cbor(&[0xa1, 0x61, b'a', 0x81, 0x41, 0x00], true).unwrap(), serde_json::json!({"a": ["AA=="]}));
+
+    // Integer map key, and a string cut short
+    assert!(matches!(decode_cbor(&[0xa1, 0x01, 0x02], false), Err(ConvertError::CborDecode { offset: 1, .. })));
+    assert!(matches!(decode_cbor(&[0x82, 0x01, 0x64, b'a'], false), Err(ConvertError::CborDecode { offset: 2, .. })));
+    assert_eq!(decode_cbor(&[0x01, 0x02], false), Err(ConvertError::TrailingBytes(1)));
+    assert!(is_cbor(&[0xa1, 0x61, b'a', 0x01]));
+    // A MessagePack map of one, which CBOR reads as an array cut short
+    assert!(!is_cbor(&[0x81, 0xa1, b'a', 0x01]));
+}
+
+#[test]
+fn test_cbor_len_matches_the_encoding() {
+    let values = [
+        serde_json::json!(null),
+        serde_json::json!([0, 23, 24, 255, 256, 65_535, 65_536, 4_294_967_296u64, -1, -25, -4_294_967_297i64, 1.5]),
+        serde_json::json!({"k": "x".repeat(300), "nested": {"list": [true, false, {}], "": []}}),
+        serde_json::json!({TAG_KEY: 1, TAG_VALUE_KEY: 1_700_000_000}),
+    ];
+    for value in values {
+        assert_eq!(cbor_len(&value), encode_cbor(&value).len(), "{}", value);
+    }
+}
@@ -0,0 +1,115 @@
+use std::ops::Range;
+
+// What changed from one conversion's output to the next, for tinting the JSON lines and
+// summing up the MessagePack bytes
+
+// Above this many line pairs left once the common start and end are taken off, the lines in
+// between are all taken as changed instead of aligned
+const MAX_ALIGNMENT_CELLS: usize = 1_000_000;
+
+// Byte ranges of the lines of `after` that aren't in `before`, without their line breaks. Lines
+// are matched up along their longest common subsequence, so one inserted line doesn't mark
+// everything below it.
+pub fn changed_lines(before: &str, after: &str) -> Vec<Range<usize>> {
+    let old: Vec<&str> = before.lines().collect();
+    let new = line_ranges(after);
+    let new_line = |range: &Range<usize>| &after[range.clone()];
+
+    let prefix = old.iter().zip(&new).take_while(|(old, new)| **old == new_line(new)).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| **old == new_line(new))
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+    if old.is_empty() || old.len().saturating_mul(new.len()) > MAX_ALIGNMENT_CELLS {
+        return new.to_vec();
+    }
+
+    // lengths[i][j]: longest common subsequence of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new_line(&new[j]) {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+    let mut changed = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while j < new.len() {
+        if i < old.len() && old[i] == new_line(&new[j]) {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            changed.push(new[j].clone());
+            j += 1;
+        }
+    }
+    changed
+}
+
+// Same lines as str::lines, as byte ranges
+fn line_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.strip_suffix('\n').map_or(line, |line| line.strip_suffix('\r').unwrap_or(line));
+        ranges.push(start..start + content.len());
+        start += line.len();
+    }
+    ranges
+}
+
+// How two MessagePack payloads differ byte for byte, offset by offset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteChanges {
+    // Bytes that differ at the same offset, plus those only one of them is long enough to have
+    pub differing: usize,
+    // None when the payloads are the same
+    pub first: Option<usize>,
+}
+
+impl ByteChanges {
+    pub fn between(before: &[u8], after: &[u8]) -> ByteChanges {
+        let common = before.len().min(after.len());
+        let mismatched = before.iter().zip(after).filter(|(old, new)| old != new).count();
+        let first = before.iter().zip(after).position(|(old, new)| old != new)
+            .or((before.len() != after.len()).then_some(common));
+        ByteChanges { differing: mismatched + before.len().max(after.len()) - common, first }
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_changed_lines_marks_only_what_changed() {
+    let before = "{\n  \"a\": 1,\n  \"b\": 2,\n  \"c\": 3\n}";
+    let after = "{\n  \"a\": 1,\n  \"b\": 20,\n  \"c\": 3\n}";
+    let changed: Vec<&str> = changed_lines(before, after).into_iter().map(|range| &after[range]).collect();
+    assert_eq!(changed, ["  \"b\": 20,"]);
+
+    // An inserted line leaves the ones after it alone, a removed one marks nothing
+    let inserted = "{\n  \"a\": 1,\n  \"x\": 0,\n  \"b\": 2,\n  \"c\": 3\n}";
+    let changed: Vec<&str> = changed_lines(before, inserted).into_iter().map(|range| &inserted[range]).collect();
+    assert_eq!(changed, ["  \"x\": 0,"]);
+    assert!(changed_lines(inserted, before).is_empty());
+    assert!(changed_lines(before, before).is_empty());
+
+    // Lines moved around are matched along the longest run that stays in order
+    let changed: Vec<&str> = changed_lines("a\nb\nc\nd", "b\na\nc\nd\ne").into_iter().map(|range| &"b\na\nc\nd\ne"[range]).collect();
+    assert_eq!(changed, ["a", "e"]);
+    assert_eq!(changed_lines("", "x\r\ny"), [0..1, 3..4]);
+}
+
+#[test]
+fn test_byte_changes_count_and_locate_differences() {
+    assert_eq!(ByteChanges::between(&[1, 2, 3], &[1, 2, 3]), ByteChanges { differing: 0, first: None });
+    assert_eq!(ByteChanges::between(&[1, 2, 3, 4], &[1, 9, 3, 8]), ByteChanges { differing: 2, first: Some(1) });
+    assert_eq!(ByteChanges::between(&[1, 2], &[1, 2, 3, 4]), ByteChanges { differing: 2, first: Some(2) });
+    assert_eq!(ByteChanges::between(&[1, 2, 3], &[7]), ByteChanges { differing: 3, first: Some(0) });
+}
@@ -0,0 +1,482 @@
+use crate::convert::{convert_file, convert_stream, same_file, BinaryFormat, ConvertOptions, Direction, Floats, TextFormat};
+use crate::error::ConvertError;
+use crate::ext_types::{BinForm, ExtRegistry, KeyForm};
+use crate::files::{read_failed, write_failed, write_file, Encoding};
+use crate::framing::Framing;
+use crate::serve::{serve, DEFAULT_MAX_BODY};
+use crate::warning::Warning;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read};
+use std::path::{Path, PathBuf};
+
+// Stands for stdin as the input and stdout as the output
+const STANDARD_STREAM: &str = "-";
+
+pub const USAGE: &str = "\
+Usage: messagepack_to_json [PATH | encode|decode|convert OPTIONS | --serve ADDRESS [--max-body BYTES]]
+
+Without arguments the converter window opens. With a PATH it opens with that file loaded and
+converted: JSON into the JSON input, anything else as MessagePack bytes.
+
+encode [PATH]            JSON to MessagePack, the same as convert --from json --to msgpack
+decode [PATH]            MessagePack to JSON, the same as convert --from msgpack --to json
+                         Both take the options of convert, e.g. --from yaml or --to cbor to
+                         change their text or binary side
+convert                  Converts a file without opening the window
+  --from json|yaml|msgpack|cbor
+                         What the input is
+  --to json|yaml|msgpack|cbor
+                         What the output should be, one side JSON or YAML and the other
+                         MessagePack or CBOR
+  --input PATH           File to read, - or left out for stdin. A lone PATH works as well
+  --output PATH          File to write, - or left out for stdout
+  --encoding raw|base64|base64url|hex
+                         How the MessagePack or CBOR side is stored, raw bytes by default.
+                         Base64 is read with or without padding, base64url written without
+  --pretty, --compact    Indented JSON or YAML (the default) or JSON all on one line
+  --strict, --lossy      Fail on what JSON can't hold (the default), or turn binary, extension
+                         values and non-string keys into strings and ignore trailing bytes
+  --stream               Any number of values: concatenated MessagePack, one JSON document per
+                         line or YAML documents separated by ---
+  --floats double|single  With --to msgpack, floats as float 64 (the default), or as float 32
+                         where that holds them exactly
+  --max-depth LEVELS     Fail on arrays and maps nested deeper than this
+  --ext-types PATH       MessagePack ext types to render by their decoders, as objects with the
+                         $ext type and its value, which encode back to them. The file has one
+                         type = decoder per line, e.g. 2 = uuid, or is a JSON object of them
+  --tag-unknown-exts     Ext values of the other types in the same form, with a base64 value,
+                         instead of failing or turning into strings with --lossy
+  --bins tagged|base64|hex|numbers
+                         MessagePack bins as objects with the $bin in base64, which encode
+                         back to bins, or as base64 or hex strings or arrays of byte numbers
+  --other-keys pairs|stringify
+                         MessagePack maps with keys other than strings as objects with the $map
+                         in [key, value] pairs, which encode back to maps, or with the keys as
+                         their JSON text
+  --framing none|u16be|u32be|u32le|varint
+                         With --stream, each MessagePack or CBOR record behind its length:
+                         2 or 4 bytes big- or little-endian, or a LEB128 varint
+  --schema               With --to json or yaml, a JSON Schema (draft 2020-12) the value fits,
+                         or every record of a --stream, in place of the value
+  --rpc                  With --to json or yaml, msgpack-RPC requests, responses and
+                         notifications as objects naming their parts. In a --stream a response
+                         also names the method of its request.
+  --ndjson               With --to json, a --stream (implied) as one minified record per line.
+                         With --lossy a record that fails to decode gets a line too, an
+                         object with the $error and the $offset the record starts at
+
+--serve ADDRESS          Answers conversion requests over HTTP, e.g. on 127.0.0.1:8080
+  POST /to-json          MessagePack body, raw or base64 text (Content-Type: text/plain)
+  POST /to-msgpack       JSON body, answered with raw bytes or base64 (Accept: text/plain)
+  GET /healthz           Whether the server is up
+                         ?encoding=raw|base64|base64url|hex, ?compact, ?lossy and ?stream work as above,
+                         ?format=cbor converts CBOR instead of MessagePack
+  --max-body BYTES       Largest request body accepted, 16 MiB by default
+";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Convert { input: PathBuf, output: PathBuf, options: ConvertOptions },
+    Serve { address: String, max_body: usize },
+    Help,
+    // The window, with this file in it
+    Open(PathBuf),
+}
+
+// What main goes on to do once the arguments are handled
+#[derive(Debug, PartialEq)]
+pub enum Launch {
+    Exit(i32),
+    // The window, and the file to load into it if one was named
+    Window(Option<PathBuf>),
+}
+
+// None when there is nothing to do headless and the window should open
+pub fn parse(args: &[String]) -> Result<Option<Command>, String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+    match command.as_str() {
+        "convert" => parse_convert(rest, None, None).map(Some),
+        "encode" => parse_convert(rest, Some(Format::Text(TextFormat::Json)), Some(Format::Binary(BinaryFormat::MessagePack))).map(Some),
+        "decode" => parse_convert(rest, Some(Format::Binary(BinaryFormat::MessagePack)), Some(Format::Text(TextFormat::Json))).map(Some),
+        "--serve" => parse_serve(rest).map(Some),
+        serve if serve.starts_with("--serve=") => {
+            let mut args = vec!["--serve".to_string(), serve["--serve=".len()..].to_string()];
+            args.extend_from_slice(rest);
+            parse(&args)
+        }
+        "help" | "--help" | "-h" => Ok(Some(Command::Help)),
+        path if rest.is_empty() && !path.starts_with('-') => Ok(Some(Command::Open(PathBuf::from(path)))),
+        other => Err(format!("Unknown command {}", other)),
+    }
+}
+
+// `from` and `to` are what encode and decode start out with, which --from and --to override
+fn parse_convert(args: &[String], from: Option<Format>, to: Option<Format>) -> Result<Command, String> {
+    let (mut from, mut to) = (from, to);
+    let mut input = None;
+    let mut output = None;
+    let mut options = ConvertOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        // Both `--flag value` and `--flag=value`
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || inline.clone().or_else(|| args.next().cloned()).ok_or_else(|| format!("{} needs a value", flag));
+        match flag {
+            "--from" => from = Some(format_name(&value()?)?),
+            "--to" => to = Some(format_name(&value()?)?),
+            "--input" => input = Some(PathBuf::from(value()?)),
+            "--output" => output = Some(PathBuf::from(value()?)),
+            "--encoding" => {
+                options.encoding = match value()?.as_str() {
+                    "raw" => None,
+                    "base64" => Some(Encoding::Base64),
+                    "base64url" => Some(Encoding::Base64Url),
+                    "hex" => Some(Encoding::Hex),
+                    other => return Err(format!("Unknown encoding {}, expected raw, base64, base64url or hex", other)),
+                }
+            }
+            "--pretty" => options.compact = false,
+            "--compact" => options.compact = true,
+            "--strict" => options.lossy = false,
+            "--lossy" => options.lossy = true,
+            "--stream" => options.stream = true,
+            "--schema" => options.schema = true,
+            "--rpc" => options.rpc = true,
+            "--ndjson" => options.ndjson = true,
+            "--floats" => {
+                options.floats = match value()?.as_str() {
+                    "double" => Floats::Double,
+                    "single" => Floats::Single,
+                    other => return Err(format!("Unknown floats {}, expected double or single", other)),
+                }
+            }
+            "--ext-types" => {
+                let path = PathBuf::from(value()?);
+                let text = fs::read_to_string(&path).map_err(|e| format!("--ext-types: {}", read_failed(&path, e)))?;
+                let mut registry = ExtRegistry::from_file(&text).and_then(|lines| ExtRegistry::parse(&lines))
+                    .map_err(|e| format!("--ext-types {}: {}", path.display(), e))?;
+                registry.tag_unknown = options.ext_registry.tag_unknown;
+                options.ext_registry = registry;
+            }
+            "--tag-unknown-exts" => options.ext_registry.tag_unknown = true,
+            "--bins" => {
+                options.bin_form = Some(match value()?.as_str() {
+                    "tagged" => BinForm::Tagged,
+                    "base64" => BinForm::Base64,
+                    "hex" => BinForm::Hex,
+                    "numbers" => BinForm::Numbers,
+                    other => return Err(format!("Unknown bins {}, expected tagged, base64, hex or numbers", other)),
+                })
+            }
+            "--other-keys" => {
+                options.key_form = Some(match value()?.as_str() {
+                    "pairs" => KeyForm::Pairs,
+                    "stringify" => KeyForm::Stringify,
+                    other => return Err(format!("Unknown other keys {}, expected pairs or stringify", other)),
+                })
+            }
+            "--max-depth" => {
+                let value = value()?;
+                options.max_depth = Some(value.parse().map_err(|_| format!("--max-depth expects a number of levels, not {}", value))?);
+            }
+            "--framing" => {
+                options.framing = match value()?.as_str() {
+                    "none" => Framing::None,
+                    "u16be" => Framing::U16Be,
+                    "u32be" => Framing::U32Be,
+                    "u32le" => Framing::U32Le,
+                    "varint" => Framing::Varint,
+                    other => return Err(format!("Unknown framing {}, expected none, u16be, u32be, u32le or varint", other)),
+                }
+            }
+            path if input.is_none() && !path.starts_with('-') => input = Some(PathBuf::from(path)),
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    (options.direction, options.format, options.text) = match (from, to) {
+        (Some(Format::Binary(format)), Some(Format::Text(text))) => (Direction::ToJson, format, text),
+        (Some(Format::Text(text)), Some(Format::Binary(format))) => (Direction::ToMessagePack, format, text),
+        (Some(from), Some(to)) if from == to => return Err("--from and --to must differ".to_string()),
+        (Some(Format::Text(_)), Some(Format::Text(_))) => return Err("One of --from and --to has to be msgpack or cbor".to_string()),
+        (Some(_), Some(_)) => return Err("One of --from and --to has to be json or yaml".to_string()),
+        _ => return Err("convert needs both --from and --to".to_string()),
+    };
+    if options.schema && options.direction == Direction::ToMessagePack {
+        return Err("--schema needs --to json or yaml".to_string());
+    }
+    if options.floats != Floats::Double && (options.direction, options.format) != (Direction::ToMessagePack, BinaryFormat::MessagePack) {
+        return Err("--floats needs --to msgpack".to_string());
+    }
+    if options.ext_registry != ExtRegistry::default() && options.format != BinaryFormat::MessagePack {
+        return Err("--ext-types and --tag-unknown-exts need msgpack, CBOR has no ext types".to_string());
+    }
+    if options.bin_form.is_some() && options.format != BinaryFormat::MessagePack {
+        return Err("--bins needs msgpack".to_string());
+    }
+    if options.key_form.is_some() && options.format != BinaryFormat::MessagePack {
+        return Err("--other-keys needs msgpack".to_string());
+    }
+    if options.rpc && options.direction == Direction::ToMessagePack {
+        return Err("--rpc needs --to json or yaml".to_string());
+    }
+    if options.ndjson && (options.direction, options.text) != (Direction::ToJson, TextFormat::Json) {
+        return Err("--ndjson needs --to json".to_string());
+    }
+    if options.ndjson && options.schema {
+        return Err("--ndjson and --schema can't be used together".to_string());
+    }
+    options.stream |= options.ndjson;
+    if options.framing != Framing::None && !options.stream {
+        return Err("--framing needs --stream".to_string());
+    }
+    let input = input.unwrap_or_else(|| PathBuf::from(STANDARD_STREAM));
+    let output = output.unwrap_or_else(|| PathBuf::from(STANDARD_STREAM));
+    Ok(Command::Convert { input, output, options })
+}
+
+fn parse_serve(args: &[String]) -> Result<Command, String> {
+    let Some((address, rest)) = args.split_first().filter(|(address, _)| !address.starts_with("--")) else {
+        return Err("--serve needs an address, e.g. 127.0.0.1:8080".to_string());
+    };
+    let mut max_body = DEFAULT_MAX_BODY;
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        match flag {
+            "--max-body" => {
+                let value = inline.or_else(|| rest.next().cloned()).ok_or("--max-body needs a value")?;
+                max_body = value.parse().map_err(|_| format!("--max-body expects a number of bytes, not {}", value))?;
+            }
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    Ok(Command::Serve { address: address.clone(), max_body })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text(TextFormat),
+    Binary(BinaryFormat),
+}
+
+fn format_name(name: &str) -> Result<Format, String> {
+    match name {
+        "json" => Ok(Format::Text(TextFormat::Json)),
+        "yaml" | "yml" => Ok(Format::Text(TextFormat::Yaml)),
+        "msgpack" | "messagepack" => Ok(Format::Binary(BinaryFormat::MessagePack)),
+        "cbor" => Ok(Format::Binary(BinaryFormat::Cbor)),
+        other => Err(format!("Unknown format {}, expected json, yaml, msgpack or cbor", other)),
+    }
+}
+
+// Runs a headless command to its exit code, or says to open the window. Usage mistakes exit
+// with 2 and failed conversions with 1.
+pub fn run(args: &[String]) -> Launch {
+    let command = match parse(args) {
+        Ok(Some(command)) => command,
+        Ok(None) => return Launch::Window(None),
+        Err(e) => {
+            eprintln!("messagepack_to_json: {}\n\n{}", e, USAGE);
+            return Launch::Exit(2);
+        }
+    };
+    match command {
+        Command::Help => {
+            print!("{}", USAGE);
+            Launch::Exit(0)
+        }
+        Command::Open(path) => Launch::Window(Some(path)),
+        Command::Serve { address, max_body } => match serve(&address, max_body) {
+            Ok(()) => Launch::Exit(0),
+            Err(e) => {
+                eprintln!("messagepack_to_json: {}", e);
+                Launch::Exit(1)
+            }
+        },
+        Command::Convert { input, output, options } => match convert_paths(&input, &output, &options) {
+            Ok(warnings) => {
+                for warning in warnings {
+                    eprintln!("messagepack_to_json: warning: {}", warning);
+                }
+                Launch::Exit(0)
+            }
+            Err(e) => {
+                eprintln!("messagepack_to_json: {}", e);
+                Launch::Exit(1)
+            }
+        },
+    }
+}
+
+// Only the converted output goes to stdout, so it can be piped on even when it is binary. A
+// file is written once the conversion succeeded, unless records are streamed into it. Warnings
+// are left to the caller to print on stderr.
+fn convert_paths(input: &Path, output: &Path, options: &ConvertOptions) -> Result<Vec<Warning>, ConvertError> {
+    let stdin = input == Path::new(STANDARD_STREAM);
+    let stdout = output == Path::new(STANDARD_STREAM);
+    if !stdin && !stdout && !options.stream {
+        return convert_file(input, output, options);
+    }
+    if !stdin && same_file(input, output) {
+        return Err(ConvertError::SameFile(input.to_path_buf()));
+    }
+    let reader: Box<dyn Read> = if stdin {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(input).map_err(|e| read_failed(input, e))?)
+    };
+    if stdout {
+        return convert_stream(reader, io::stdout().lock(), options);
+    }
+    if options.stream {
+        let file = File::create(output).map_err(|e| write_failed(output, e))?;
+        return convert_stream(reader, BufWriter::new(file), options);
+    }
+    let mut converted = Vec::new();
+    let warnings = convert_stream(reader, &mut converted, options)?;
+    write_file(output, &converted)?;
+    Ok(warnings)
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(str::to_string).collect()
+}
+
+#[test]
+fn test_parse_convert() {
+    assert_eq!(parse(&[]), Ok(None));
+    let Ok(Some(Command::Convert { input, output, .. })) = parse(&args("convert --from msgpack --to json --input -")) else {
+        panic!("not a conversion");
+    };
+    assert_eq!((input, output), (PathBuf::from("-"), PathBuf::from("-")));
+    assert_eq!(parse(&args("--help")), Ok(Some(Command::Help)));
+    let Ok(Some(Command::Convert { input, output, options })) =
+        parse(&args("convert --from json --to msgpack --input file.json --output=file.bin --encoding hex --compact"))
+    else {
+        panic!("not a conversion");
+    };
+    assert_eq!(input, PathBuf::from("file.json"));
+    assert_eq!(output, PathBuf::from("file.bin"));
+    assert_eq!(options, ConvertOptions {
+        direction: Direction::ToMessagePack,
+        encoding: Some(Encoding::Hex),
+        compact: true,
+        ..Default::default()
+    });
+
+    let Ok(Some(Command::Convert { options, .. })) =
+        parse(&args("convert --from msgpack --to json --input a --output b --lossy --stream --schema"))
+    else {
+        panic!("not a conversion");
+    };
+    assert_eq!(options, ConvertOptions { lossy: true, stream: true, schema: true, ..Default::default() });
+
+    let Ok(Some(Command::Convert { options, .. })) = parse(&args("convert --from yml --to cbor")) else {
+        panic!("not a conversion");
+    };
+    assert_eq!((options.direction, options.format, options.text), (Direction::ToMessagePack, BinaryFormat::Cbor, TextFormat::Yaml));
+
+    let Ok(Some(Command::Convert { options, .. })) = parse(&args("convert --from msgpack --to json --stream --framing=u32be --rpc")) else {
+        panic!("not a conversion");
+    };
+    assert_eq!((options.framing, options.rpc), (Framing::U32Be, true));
+
+    let Ok(Some(Command::Convert { options, .. })) = parse(&args("convert --from cbor --to json --ndjson --lossy")) else {
+        panic!("not a conversion");
+    };
+    assert_eq!((options.ndjson, options.stream), (true, true));
+
+    let Ok(Some(Command::Convert { options, .. })) = parse(&args("encode --floats single --max-depth 4")) else {
+        panic!("not a conversion");
+    };
+    assert_eq!(options, ConvertOptions::to_messagepack().floats(Floats::Single).max_depth(4));
+
+    let Ok(Some(Command::Convert { options, .. })) = parse(&args("decode --bins hex --other-keys=pairs")) else {
+        panic!("not a conversion");
+    };
+    assert_eq!(options, ConvertOptions::default().bin_form(Some(BinForm::Hex)).key_form(Some(KeyForm::Pairs)));
+}
+
+#[test]
+fn test_encode_and_decode_preset_the_formats() {
+    let Ok(Some(Command::Convert { input, output, options })) = parse(&args("encode data.json --compact")) else {
+        panic!("not a conversion");
+    };
+    assert_eq!((input, output), (PathBuf::from("data.json"), PathBuf::from("-")));
+    assert_eq!(options, ConvertOptions { direction: Direction::ToMessagePack, compact: true, ..Default::default() });
+
+    let Ok(Some(Command::Convert { input, options, .. })) = parse(&args("decode --encoding base64")) else {
+        panic!("not a conversion");
+    };
+    assert_eq!(input, PathBuf::from("-"));
+    assert_eq!(options, ConvertOptions { encoding: Some(Encoding::Base64), ..Default::default() });
+    let Ok(Some(Command::Convert { options, .. })) = parse(&args("decode --encoding base64url")) else {
+        panic!("not a conversion");
+    };
+    assert_eq!(options.encoding, Some(Encoding::Base64Url));
+
+    let Ok(Some(Command::Convert { options, .. })) = parse(&args("encode --from yaml --to cbor")) else {
+        panic!("not a conversion");
+    };
+    assert_eq!((options.direction, options.format, options.text), (Direction::ToMessagePack, BinaryFormat::Cbor, TextFormat::Yaml));
+
+    // The preset side can't be turned around into a second text format
+    assert!(parse(&args("decode --from yaml")).is_err());
+    assert!(parse(&args("decode a.msgpack b.msgpack")).is_err());
+    assert!(parse(&args("decode --input a.msgpack b.msgpack")).is_err());
+}
+
+#[test]
+fn test_parse_rejects_mistakes() {
+    assert!(parse(&args("frobnicate --now")).is_err());
+    assert!(parse(&args("--frobnicate")).is_err());
+    assert!(parse(&args("convert --from json --to json --input a --output b")).is_err());
+    assert!(parse(&args("convert --from json --to yaml --input a --output b")).is_err());
+    assert!(parse(&args("convert --from json --input a")).is_err());
+    assert!(parse(&args("convert --from json --to msgpack --input a --output b --encoding octal")).is_err());
+    assert!(parse(&args("convert --from json --to msgpack --input a --output b --verbose")).is_err());
+    assert!(parse(&args("convert --from json --to msgpack --schema")).is_err());
+    assert!(parse(&args("convert --from msgpack --to json --framing varint")).is_err());
+    assert!(parse(&args("convert --from yaml --to msgpack --rpc")).is_err());
+    assert!(parse(&args("convert --from msgpack --to json --stream --framing u64be")).is_err());
+    assert!(parse(&args("convert --from msgpack --to yaml --ndjson")).is_err());
+    assert!(parse(&args("convert --from json --to msgpack --ndjson")).is_err());
+    assert!(parse(&args("decode --floats single")).is_err());
+    assert!(parse(&args("encode --to cbor --floats single")).is_err());
+    assert!(parse(&args("encode --max-depth deep")).is_err());
+    assert!(parse(&args("decode --to yaml --from cbor --tag-unknown-exts")).is_err());
+    assert!(parse(&args("decode --ext-types /nonexistent/ext_types.toml")).is_err());
+    assert!(parse(&args("decode --bins octal")).is_err());
+    assert!(parse(&args("encode --to cbor --bins tagged")).is_err());
+    assert!(parse(&args("decode --other-keys numbers")).is_err());
+    assert!(parse(&args("decode --from cbor --other-keys stringify")).is_err());
+    assert_eq!(parse(&args("convert --from")), Err("--from needs a value".to_string()));
+}
+
+#[test]
+fn test_parse_serve() {
+    assert_eq!(parse(&args("--serve 127.0.0.1:8080")), Ok(Some(Command::Serve { address: "127.0.0.1:8080".to_string(), max_body: DEFAULT_MAX_BODY })));
+    assert_eq!(parse(&args("--serve=0.0.0.0:9000 --max-body 1024")), Ok(Some(Command::Serve { address: "0.0.0.0:9000".to_string(), max_body: 1024 })));
+    assert!(parse(&args("--serve")).is_err());
+    assert!(parse(&args("--serve 127.0.0.1:8080 --max-body lots")).is_err());
+}
+
+#[test]
+fn test_a_bare_path_opens_the_window_with_it() {
+    assert_eq!(parse(&args("capture.msgpack")), Ok(Some(Command::Open(PathBuf::from("capture.msgpack")))));
+    assert_eq!(run(&args("capture.msgpack")), Launch::Window(Some(PathBuf::from("capture.msgpack"))));
+    assert_eq!(run(&[]), Launch::Window(None));
+    // Commands still win over files of the same name
+    assert_eq!(parse(&args("help")), Ok(Some(Command::Help)));
+}
@@ -0,0 +1,128 @@
+use crate::error::ConvertError;
+use crate::locale::trf;
+use crate::stats::group_thousands;
+use crate::zstd;
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+pub const DEFAULT_MAX_DECOMPRESSED_MB: usize = 256;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zlib,
+    Zstd,
+}
+
+impl Compression {
+    pub const ALL: [Compression; 3] = [Compression::Gzip, Compression::Zlib, Compression::Zstd];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zlib => "zlib",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    // gzip and zstd say so in three and four bytes. A zlib header is two bytes MessagePack can
+    // start with too, 78 9c is 120 followed by an array, so it only counts once the rest inflates
+    // as well.
+    pub fn detect(bytes: &[u8]) -> Option<Compression> {
+        match bytes {
+            [0x1f, 0x8b, 0x08, ..] => Some(Compression::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Compression::Zstd),
+            [cmf, flg, ..] if cmf & 0x0f == 8 && cmf >> 4 <= 7 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0 => Some(Compression::Zlib),
+            _ => None,
+        }
+    }
+}
+
+// Sizes on both sides of a compression, for the stats line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Compressed {
+    pub compression: Compression,
+    pub compressed_bytes: usize,
+    pub uncompressed_bytes: usize,
+}
+
+impl Compressed {
+    // How many times smaller the compressed side is
+    pub fn ratio(&self) -> f64 {
+        self.uncompressed_bytes as f64 / self.compressed_bytes.max(1) as f64
+    }
+}
+
+// The bytes inside a gzip, zlib or zstd wrapper, None when there is none. Inflating to more than
+// `max_size` bytes is an error instead of a way to run out of memory.
+pub fn decompress(bytes: &[u8], max_size: usize) -> Result<Option<(Compression, Vec<u8>)>, ConvertError> {
+    let Some(compression) = Compression::detect(bytes) else {
+        return Ok(None);
+    };
+    let reader: Box<dyn Read + '_> = match compression {
+        Compression::Gzip => Box::new(MultiGzDecoder::new(bytes)),
+        Compression::Zlib => Box::new(ZlibDecoder::new(bytes)),
+        Compression::Zstd => return zstd::decompress(bytes, max_size).map(|inflated| Some((compression, inflated))),
+    };
+    let mut inflated = Vec::new();
+    match reader.take(max_size as u64 + 1).read_to_end(&mut inflated) {
+        Ok(_) if inflated.len() > max_size => Err(over_limit(max_size)),
+        Ok(_) => Ok(Some((compression, inflated))),
+        // Bytes that only looked like zlib
+        Err(_) if compression == Compression::Zlib => Ok(None),
+        Err(e) => Err(ConvertError::Decompress(e.to_string())),
+    }
+}
+
+pub fn over_limit(max_size: usize) -> ConvertError {
+    ConvertError::Decompress(trf("Decompresses to more than the limit of {} bytes", &[&group_thousands(max_size)]))
+}
+
+// The level only matters to zstd, gzip and zlib use their default
+pub fn compress(bytes: &[u8], compression: Compression, level: i32) -> Result<Vec<u8>, ConvertError> {
+    let written = match compression {
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(bytes).and_then(|()| encoder.finish())
+        }
+        Compression::Zlib => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(bytes).and_then(|()| encoder.finish())
+        }
+        Compression::Zstd => Ok(zstd::compress(bytes, level)),
+    };
+    written.map_err(|e| ConvertError::Write(e.to_string()))
+}
+
+
+/* Tests */
+#[test]
+fn test_gzip_zlib_and_zstd_are_unwrapped() {
+    let messagepack = [0x81, 0xa1, 0x61, 0x01];
+    let gzipped = compress(&messagepack, Compression::Gzip, 0).unwrap();
+    assert_eq!(decompress(&gzipped, 1024).unwrap(), Some((Compression::Gzip, messagepack.to_vec())));
+
+    let zlib = compress(&messagepack, Compression::Zlib, 0).unwrap();
+    assert_eq!(zlib[..2], [0x78, 0x9c]);
+    assert_eq!(decompress(&zlib, 1024).unwrap(), Some((Compression::Zlib, messagepack.to_vec())));
+    let zstd = compress(&messagepack, Compression::Zstd, 3).unwrap();
+    assert_eq!(decompress(&zstd, 1024).unwrap(), Some((Compression::Zstd, messagepack.to_vec())));
+
+    // 120 followed by an empty array16 has a zlib header, but it's MessagePack
+    assert_eq!(decompress(&[0x78, 0x9c, 0x00, 0x00], 1024).unwrap(), None);
+    assert_eq!(decompress(&messagepack, 1024).unwrap(), None);
+    assert!(matches!(decompress(&gzipped[..gzipped.len() - 4], 1024), Err(ConvertError::Decompress(_))));
+}
+
+#[test]
+fn test_decompression_stops_at_the_limit() {
+    for compression in [Compression::Gzip, Compression::Zstd] {
+        let bomb = compress(&vec![0; 10 * 1024 * 1024], compression, 1).unwrap();
+        assert!(bomb.len() < 20 * 1024);
+        let err = decompress(&bomb, 1024 * 1024).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to decompress: Decompresses to more than the limit of 1,048,576 bytes");
+        assert_eq!(decompress(&bomb, 10 * 1024 * 1024).unwrap().unwrap().1.len(), 10 * 1024 * 1024);
+    }
+}
@@ -0,0 +1,1067 @@
+use crate::cbor::{decode_cbor_with, encode_cbor, is_cbor};
+use crate::decode::{decode_value_at, decode_value_with, decode_with_spans_until, SpanMap};
+use crate::ext_types::{BinForm, ExtRegistry, KeyForm, WithExtTypes};
+use crate::error::ConvertError;
+use crate::files::{read_file, write_file, Encoding};
+use crate::format::JsonFormat;
+use crate::framing::{decode_frames, Framing};
+use crate::locale::trf;
+use crate::msgpack::value_end;
+use crate::nested;
+use crate::rpc::{message, Pairing};
+use crate::schema::json_schema;
+use crate::schema_check::escape_pointer_token;
+use crate::template::Template;
+use crate::timestamp;
+use crate::warning::{Warning, WarningKind};
+use crate::worker::{Checkpoint, JobToken};
+use crate::yaml::{parse_yaml, to_yaml};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+// How much is asked of the reader at a time while streaming
+const READ_CHUNK: usize = 64 * 1024;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Direction {
+    #[default]
+    ToJson,
+    ToMessagePack,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 2] = [Direction::ToJson, Direction::ToMessagePack];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::ToJson => "MessagePack → JSON",
+            Direction::ToMessagePack => "JSON → MessagePack",
+        }
+    }
+
+    pub fn output_extension(self) -> &'static str {
+        match self {
+            Direction::ToJson => "json",
+            Direction::ToMessagePack => "msgpack",
+        }
+    }
+}
+
+// What the binary side of a conversion is
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BinaryFormat {
+    #[default]
+    MessagePack,
+    Cbor,
+}
+
+impl BinaryFormat {
+    pub const ALL: [BinaryFormat; 2] = [BinaryFormat::MessagePack, BinaryFormat::Cbor];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BinaryFormat::MessagePack => "MessagePack",
+            BinaryFormat::Cbor => "CBOR",
+        }
+    }
+
+    // Bytes are taken as MessagePack unless only CBOR reads them as one whole value
+    pub fn detect(bytes: &[u8]) -> BinaryFormat {
+        let messagepack = decode_value_at(bytes, 0, true).is_ok_and(|(_, end)| end == bytes.len());
+        if !messagepack && is_cbor(bytes) {
+            BinaryFormat::Cbor
+        } else {
+            BinaryFormat::MessagePack
+        }
+    }
+
+    // The one value starting at `offset` and where it ends
+    pub fn decode_at(self, bytes: &[u8], offset: usize, lossy: bool) -> Result<(Value, usize), ConvertError> {
+        ConvertOptions::default().format(self).lossy(lossy).decode(bytes, offset, &mut Vec::new())
+    }
+
+    pub fn encode(self, value: &Value) -> Result<Vec<u8>, ConvertError> {
+        match self {
+            BinaryFormat::MessagePack => rmp_serde::to_vec(value).map_err(|e| ConvertError::SerializeMessagePack(e.to_string())),
+            BinaryFormat::Cbor => Ok(encode_cbor(value)),
+        }
+    }
+}
+
+// How floats are written to MessagePack. CBOR floats are always 64 bits.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Floats {
+    // As float 64 every time, which is what a JSON number is read as
+    #[default]
+    Double,
+    // As float 32 where that holds the number exactly, in 5 bytes instead of 9
+    Single,
+}
+
+// What the text side of a conversion is
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TextFormat {
+    #[default]
+    Json,
+    Yaml,
+}
+
+impl TextFormat {
+    pub const ALL: [TextFormat; 2] = [TextFormat::Json, TextFormat::Yaml];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TextFormat::Json => "JSON",
+            TextFormat::Yaml => "YAML",
+        }
+    }
+
+    // The one value in `text`, keys ordered the way `json_format` says
+    pub fn parse(self, text: &str, json_format: &JsonFormat, lossy: bool) -> Result<Value, ConvertError> {
+        match self {
+            TextFormat::Json => json_format.parse(text),
+            TextFormat::Yaml => {
+                let mut value = parse_yaml(text, lossy)?.single()?;
+                json_format.order_keys(&mut value);
+                Ok(value)
+            }
+        }
+    }
+
+    // JSON on one line is YAML as well, so that's what compact YAML is
+    pub fn write(self, value: &Value, json_format: &JsonFormat, compact: bool) -> Result<String, ConvertError> {
+        match (self, compact) {
+            (_, true) => json_format.minified(value),
+            (TextFormat::Json, false) => json_format.pretty(value),
+            (TextFormat::Yaml, false) => Ok(to_yaml(value, json_format.indent)),
+        }
+    }
+
+    // One record of a stream: a line of JSON, or a YAML document with its --- marker
+    fn record(self, value: &Value, options: &ConvertOptions) -> Result<String, ConvertError> {
+        Ok(match (self, options.compact) {
+            (TextFormat::Json, _) => format!("{}\n", options.json_format.minified(value)?),
+            (TextFormat::Yaml, true) => format!("--- {}\n", options.json_format.minified(value)?),
+            (TextFormat::Yaml, false) => format!("---\n{}", to_yaml(value, options.json_format.indent)),
+        })
+    }
+}
+
+// Everything about a conversion besides its input, shared by batch conversion and the command line
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConvertOptions {
+    pub direction: Direction,
+    // The binary side, which the directions call MessagePack whichever it is
+    pub format: BinaryFormat,
+    // The text side, which the directions call JSON whichever it is
+    pub text: TextFormat,
+    // Text form of the binary side, raw bytes when None
+    pub encoding: Option<Encoding>,
+    pub json_format: JsonFormat,
+    // Minified JSON instead of pretty-printed
+    pub compact: bool,
+    // What JSON can't hold becomes strings instead of errors, and bytes after the value are ignored
+    pub lossy: bool,
+    // Towards JSON, bytes after the one value are ignored with a warning even without lossy
+    // decoding, the way the first value of several is all rmp_serde reads
+    pub ignore_trailing: bool,
+    // Any number of values one after the other: concatenated MessagePack on one side and one
+    // JSON document per line, or YAML documents, on the other
+    pub stream: bool,
+    // Each record of a stream behind a length prefix instead of back to back. Without a stream
+    // the records are the elements of the one array, or the one value if it's something else.
+    pub framing: Framing,
+    // Towards JSON, a JSON Schema the value fits, or all the records of a stream, instead of it
+    pub schema: bool,
+    // Towards JSON, msgpack-RPC messages as objects naming their parts, responses in a stream
+    // with the method of their request
+    pub rpc: bool,
+    // Towards JSON, a stream as NDJSON: one minified record per line, and with lossy decoding a
+    // record that fails to decode still gets its line, saying why. Without framing the next
+    // record is looked for from the byte after the start of the bad one.
+    pub ndjson: bool,
+    pub floats: Floats,
+    // What MessagePack bins decode to, and with the tagged form the tagged bins that encode back
+    // to bins. None leaves them to strict or lossy decoding.
+    pub bin_form: Option<BinForm>,
+    // Likewise for MessagePack maps with keys other than strings, and the pairs that encode back
+    // to them
+    pub key_form: Option<KeyForm>,
+    // How deeply arrays and maps may nest, on top of the decoders' own limits. A value this many
+    // levels down is fine, one below it fails the conversion.
+    pub max_depth: Option<usize>,
+    // Ext types rendered by their decoders in MessagePack and encoded back from their tagged form
+    pub ext_registry: ExtRegistry,
+    // Towards JSON, names for the fields of structs written as arrays, which every record of a
+    // stream follows on its own
+    pub template: Option<Template>,
+    // Strings holding MessagePack of their own expanded in place towards JSON, and collapsed back
+    // into their strings towards MessagePack
+    pub nested: bool,
+}
+
+// Built up from one of the directions, e.g.
+// ConvertOptions::to_json().compact(true).sort_keys(false), for callers that would rather not
+// name every field
+impl ConvertOptions {
+    pub fn to_json() -> ConvertOptions {
+        ConvertOptions { direction: Direction::ToJson, ..ConvertOptions::default() }
+    }
+
+    pub fn to_messagepack() -> ConvertOptions {
+        ConvertOptions { direction: Direction::ToMessagePack, ..ConvertOptions::default() }
+    }
+
+    pub fn direction(self, direction: Direction) -> ConvertOptions {
+        ConvertOptions { direction, ..self }
+    }
+
+    pub fn format(self, format: BinaryFormat) -> ConvertOptions {
+        ConvertOptions { format, ..self }
+    }
+
+    pub fn text(self, text: TextFormat) -> ConvertOptions {
+        ConvertOptions { text, ..self }
+    }
+
+    pub fn encoding(self, encoding: Option<Encoding>) -> ConvertOptions {
+        ConvertOptions { encoding, ..self }
+    }
+
+    pub fn json_format(self, json_format: JsonFormat) -> ConvertOptions {
+        ConvertOptions { json_format, ..self }
+    }
+
+    pub fn indent(self, indent: usize) -> ConvertOptions {
+        ConvertOptions { json_format: JsonFormat { indent, ..self.json_format }, ..self }
+    }
+
+    pub fn sort_keys(self, sort_keys: bool) -> ConvertOptions {
+        ConvertOptions { json_format: JsonFormat { sort_keys, ..self.json_format }, ..self }
+    }
+
+    pub fn compact(self, compact: bool) -> ConvertOptions {
+        ConvertOptions { compact, ..self }
+    }
+
+    pub fn lossy(self, lossy: bool) -> ConvertOptions {
+        ConvertOptions { lossy, ..self }
+    }
+
+    pub fn ignore_trailing(self, ignore_trailing: bool) -> ConvertOptions {
+        ConvertOptions { ignore_trailing, ..self }
+    }
+
+    pub fn stream(self, stream: bool) -> ConvertOptions {
+        ConvertOptions { stream, ..self }
+    }
+
+    pub fn framing(self, framing: Framing) -> ConvertOptions {
+        ConvertOptions { framing, ..self }
+    }
+
+    pub fn schema(self, schema: bool) -> ConvertOptions {
+        ConvertOptions { schema, ..self }
+    }
+
+    pub fn rpc(self, rpc: bool) -> ConvertOptions {
+        ConvertOptions { rpc, ..self }
+    }
+
+    pub fn ndjson(self, ndjson: bool) -> ConvertOptions {
+        ConvertOptions { ndjson, ..self }
+    }
+
+    pub fn floats(self, floats: Floats) -> ConvertOptions {
+        ConvertOptions { floats, ..self }
+    }
+
+    pub fn bin_form(self, bin_form: Option<BinForm>) -> ConvertOptions {
+        ConvertOptions { bin_form, ..self }
+    }
+
+    pub fn key_form(self, key_form: Option<KeyForm>) -> ConvertOptions {
+        ConvertOptions { key_form, ..self }
+    }
+
+    pub fn max_depth(self, max_depth: usize) -> ConvertOptions {
+        ConvertOptions { max_depth: Some(max_depth), ..self }
+    }
+
+    pub fn ext_registry(self, ext_registry: ExtRegistry) -> ConvertOptions {
+        ConvertOptions { ext_registry, ..self }
+    }
+
+    pub fn template(self, template: Option<Template>) -> ConvertOptions {
+        ConvertOptions { template, ..self }
+    }
+
+    pub fn nested(self, nested: bool) -> ConvertOptions {
+        ConvertOptions { nested, ..self }
+    }
+}
+
+impl ConvertOptions {
+    // A decoded value ready to be written out, the `record`th of a stream if it's one of those.
+    // What the template didn't fit goes in `warnings`.
+    fn prepared(&self, value: Value, record: Option<usize>, pairing: &mut Pairing, warnings: &mut Vec<Warning>) -> Value {
+        let mut value = if self.rpc { pairing.label(value) } else { value };
+        if let Some(template) = &self.template {
+            let (named, unfit) = template.apply(value);
+            value = named;
+            warnings.extend(unfit.into_iter().map(|warning| in_record(warning, record)));
+        }
+        if self.nested {
+            nested::expand(&mut value, self);
+        }
+        self.json_format.order_keys(&mut value);
+        value
+    }
+
+    // The decoded `record`th record, or in lossy NDJSON the object that takes the line of one that
+    // didn't decode, so the lines still count the records
+    fn recovered(&self, decoded: Result<Value, ConvertError>, offset: usize, record: usize, pairing: &mut Pairing, warnings: &mut Vec<Warning>) -> Result<Value, ConvertError> {
+        match decoded.and_then(|value| self.check_depth(&value).map(|()| value)) {
+            Ok(value) => Ok(self.prepared(value, Some(record), pairing, warnings)),
+            Err(e) if self.ndjson && self.lossy => Ok(json!({"$error": e.to_string(), "$offset": offset})),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn check_depth(&self, value: &Value) -> Result<(), ConvertError> {
+        let Some(limit) = self.max_depth else {
+            return Ok(());
+        };
+        match too_deep(value, 0, limit, &mut String::new()) {
+            Some(path) => Err(ConvertError::DepthLimit { path, limit }),
+            None => Ok(()),
+        }
+    }
+
+    // The one value of the binary format starting at `offset` and where it ends, with a warning in
+    // `warnings` for everything lossy decoding changed. The ext registry and the bin and key
+    // forms are MessagePack's, CBOR has none of them.
+    pub fn decode(&self, bytes: &[u8], offset: usize, warnings: &mut Vec<Warning>) -> Result<(Value, usize), ConvertError> {
+        match self.format {
+            BinaryFormat::MessagePack => decode_value_with(bytes, offset, self, warnings),
+            BinaryFormat::Cbor => decode_cbor_with(bytes, offset, self.lossy, warnings),
+        }
+    }
+
+    // One record of a MessagePack stream, `at` bytes into it
+    fn decode_record(&self, record: &[u8], at: usize, warnings: &mut Vec<Warning>) -> Result<Value, ConvertError> {
+        let mut record_warnings = Vec::new();
+        let decoded = decode_value_with(record, 0, self, &mut record_warnings).map(|(value, _)| value).map_err(|e| e.shifted(at));
+        warnings.extend(record_warnings.into_iter().map(|warning| warning.shifted(at)));
+        decoded
+    }
+
+    // The value in the binary format, unless it nests deeper than allowed
+    fn encode(&self, value: &Value) -> Result<Vec<u8>, ConvertError> {
+        self.check_depth(value)?;
+        match self.format {
+            BinaryFormat::MessagePack => {
+                let value = WithExtTypes::new(value, &self.ext_registry)
+                    .single_floats(self.floats == Floats::Single)
+                    .bin_form(self.bin_form)
+                    .key_form(self.key_form);
+                rmp_serde::to_vec(&value).map_err(|e| ConvertError::SerializeMessagePack(e.to_string()))
+            }
+            format => format.encode(value),
+        }
+    }
+
+    // A parsed value, the `record`th of a stream if it's one of those, added to `output` in the
+    // binary format with its length prefix if there is framing. Hands back the value as encoded,
+    // with nested MessagePack collapsed into its strings.
+    fn encode_record(&self, mut value: Value, record: Option<usize>, output: &mut Vec<u8>, warnings: &mut Vec<Warning>) -> Result<Value, ConvertError> {
+        if self.nested {
+            nested::collapse(&mut value, self)?;
+        }
+        if self.ext_registry.timestamps && self.format == BinaryFormat::MessagePack {
+            warnings.extend(timestamp::warnings(&value).into_iter().map(|warning| in_record(warning, record)));
+        }
+        self.framing.frame(&self.encode(&value)?, output)?;
+        Ok(value)
+    }
+}
+
+fn in_record(warning: Warning, record: Option<usize>) -> Warning {
+    match record {
+        Some(record) => warning.in_record(record),
+        None => warning,
+    }
+}
+
+// Where a conversion keeps what it found out. Only with a token is it a job someone waits on,
+// which is checked for cancellation and progress along the way and keeps the values and spans.
+#[derive(Default)]
+struct Job<'a> {
+    token: Option<&'a JobToken>,
+    values: Vec<Value>,
+    spans: SpanMap,
+    warnings: Vec<Warning>,
+}
+
+impl Job<'_> {
+    fn check(&self) -> Result<(), ConvertError> {
+        self.token.map_or(Ok(()), JobToken::check)
+    }
+
+    // The job got `offset` bytes into the input
+    fn reached(&self, offset: usize) -> Result<(), ConvertError> {
+        if let Some(token) = self.token {
+            token.progress_counter().store(offset, Ordering::Relaxed);
+        }
+        self.check()
+    }
+
+    fn keep(&mut self, values: impl IntoIterator<Item = Value>) {
+        if self.token.is_some() {
+            self.values.extend(values);
+        }
+    }
+
+    // The input, read through a checkpoint that fails once cancelled and counts progress if
+    // there is a token
+    fn reader<'b>(&self, input: &'b [u8]) -> Box<dyn Read + 'b> {
+        match self.token {
+            Some(token) => Box::new(io::BufReader::new(Checkpoint::new(input, token))),
+            None => Box::new(input),
+        }
+    }
+}
+
+// JSON Pointer of the first value more than `limit` levels down
+fn too_deep(value: &Value, depth: usize, limit: usize, path: &mut String) -> Option<String> {
+    if depth > limit {
+        return Some(path.clone());
+    }
+    let mut child = |token: &str, item: &Value| {
+        let len = path.len();
+        path.push('/');
+        path.push_str(&escape_pointer_token(token));
+        let found = too_deep(item, depth + 1, limit, path);
+        path.truncate(len);
+        found
+    };
+    match value {
+        Value::Array(items) => items.iter().enumerate().find_map(|(index, item)| child(&index.to_string(), item)),
+        Value::Object(object) => object.iter().find_map(|(key, item)| child(key, item)),
+        _ => None,
+    }
+}
+
+// The output, and what lossy decoding and YAML input changed along the way
+pub fn convert(input: &[u8], options: &ConvertOptions) -> Result<(Vec<u8>, Vec<Warning>), ConvertError> {
+    let mut output = Vec::new();
+    let warnings = convert_stream(input, &mut output, options)?;
+    Ok((output, warnings))
+}
+
+// What convert_until made of its input
+#[derive(Debug, Default)]
+pub struct Conversion {
+    pub output: Vec<u8>,
+    // The JSON side: what was written out towards JSON and read in towards MessagePack, one
+    // value for each record of a stream or frame
+    pub values: Vec<Value>,
+    // Where each node of the one MessagePack value towards JSON was in the input, empty for
+    // anything else and once the template or msgpack-RPC labels changed the paths
+    pub spans: SpanMap,
+    pub warnings: Vec<Warning>,
+}
+
+// Same as convert for a job off the UI thread, which hands back the values along with the output.
+// Gives up with a "Cancelled" error soon after `token` is cancelled and keeps its progress at
+// roughly how many input bytes were read. Streams are converted all in memory.
+pub fn convert_until(input: &[u8], options: &ConvertOptions, token: &JobToken) -> Result<Conversion, ConvertError> {
+    let mut job = Job { token: Some(token), ..Job::default() };
+    let output = match options.direction {
+        Direction::ToJson => to_json(input, options, &mut job)?,
+        Direction::ToMessagePack => to_messagepack(input, options, &mut job)?,
+    };
+    Ok(Conversion { output, values: job.values, spans: job.spans, warnings: job.warnings })
+}
+
+// Streams of raw MessagePack or JSON are converted record by record as they arrive, so a pipe
+// that stays open keeps producing output. Everything else, CBOR, YAML and framed MessagePack
+// records too, is read in full first.
+pub fn convert_stream(mut reader: impl Read, mut writer: impl Write, options: &ConvertOptions) -> Result<Vec<Warning>, ConvertError> {
+    let records_as_they_come = match options.direction {
+        Direction::ToJson => options.format == BinaryFormat::MessagePack && options.framing == Framing::None,
+        Direction::ToMessagePack => options.text == TextFormat::Json,
+    };
+    // A schema of a stream needs every record before it can be written
+    let mut warnings = Vec::new();
+    if options.stream && options.encoding.is_none() && records_as_they_come && !options.schema {
+        match options.direction {
+            Direction::ToJson => messagepack_records_to_json(reader, writer, options, &mut warnings)?,
+            Direction::ToMessagePack => json_records_to_messagepack(reader, writer, options, &mut warnings)?,
+        }
+        return Ok(warnings);
+    }
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input).map_err(|e| ConvertError::Read(e.to_string()))?;
+    let mut job = Job::default();
+    let output = match options.direction {
+        Direction::ToJson => to_json(&input, options, &mut job)?,
+        Direction::ToMessagePack => to_messagepack(&input, options, &mut job)?,
+    };
+    writer.write_all(&output).and_then(|()| writer.flush()).map_err(|e| ConvertError::Write(e.to_string()))?;
+    Ok(job.warnings)
+}
+
+// Bytes after the value or the frame's value, which lossy decoding ignores and strict fails on
+pub fn trailing_bytes(end: usize, len: usize, lossy: bool, warnings: &mut Vec<Warning>) -> Result<(), ConvertError> {
+    match (end < len, lossy) {
+        (false, _) => Ok(()),
+        (true, false) => Err(ConvertError::TrailingBytes(len - end)),
+        (true, true) => {
+            let message = trf("{} bytes after the value ignored", &[&(len - end)]);
+            warnings.push(Warning::new(WarningKind::TrailingBytes, message).at_offset(end));
+            Ok(())
+        }
+    }
+}
+
+fn to_json(input: &[u8], options: &ConvertOptions, job: &mut Job) -> Result<Vec<u8>, ConvertError> {
+    let decoded;
+    let bytes = match options.encoding {
+        None => input,
+        Some(encoding) => {
+            decoded = encoding.decode(input)?;
+            &decoded[..]
+        }
+    };
+    if options.schema {
+        return schema_of_records(bytes, options, &mut job.warnings);
+    }
+    if options.stream || options.framing != Framing::None {
+        let records = Value::Array(decoded_records(bytes, options, job)?);
+        // Without a stream the records are written as the one array they were framed from
+        let output = match options.stream {
+            true => records.as_array().into_iter().flatten().map(|record| options.text.record(record, options)).collect::<Result<String, _>>()?,
+            false => options.text.write(&records, &options.json_format, options.compact)?,
+        };
+        if let Value::Array(records) = records {
+            job.keep(records);
+        }
+        return Ok(output.into_bytes());
+    }
+    let (value, end) = match (options.format, job.token) {
+        (BinaryFormat::MessagePack, Some(token)) => {
+            let (value, end, spans) = decode_with_spans_until(bytes, options, token.cancel_flag(), token.progress_counter(), &mut job.warnings)?;
+            // Named fields and the parts of a message leave the spans with paths that are no longer there
+            if options.template.is_none() && !(options.rpc && message(&value).is_some()) {
+                job.spans = spans;
+            }
+            (value, end)
+        }
+        _ => options.decode(bytes, 0, &mut job.warnings)?,
+    };
+    trailing_bytes(end, bytes.len(), options.lossy || options.ignore_trailing, &mut job.warnings)?;
+    options.check_depth(&value)?;
+    let value = options.prepared(value, None, &mut Pairing::default(), &mut job.warnings);
+    let output = options.text.write(&value, &options.json_format, options.compact)?;
+    job.check()?;
+    job.keep([value]);
+    Ok(output.into_bytes())
+}
+
+fn to_messagepack(input: &[u8], options: &ConvertOptions, job: &mut Job) -> Result<Vec<u8>, ConvertError> {
+    let records = match (options.text, options.stream) {
+        (TextFormat::Yaml, stream) => {
+            let mut parsed = parse_yaml(&utf8(input)?, options.lossy)?;
+            let mut documents = if stream {
+                job.warnings.append(&mut parsed.warnings);
+                parsed.documents
+            } else if options.lossy {
+                // Several documents become an array, with a warning saying so
+                let (value, mut warnings) = parsed.combined();
+                job.warnings.append(&mut warnings);
+                vec![value]
+            } else {
+                job.warnings.append(&mut parsed.warnings);
+                vec![parsed.single()?]
+            };
+            for value in &mut documents {
+                options.json_format.order_keys(value);
+            }
+            documents
+        }
+        (TextFormat::Json, true) => {
+            let parsed: Result<Vec<Value>, _> = serde_json::Deserializer::from_reader(job.reader(input)).into_iter::<Value>().collect();
+            // A cancelled read fails the parse, which is reported as cancelled instead
+            job.check()?;
+            let mut records = parsed.map_err(|e| ConvertError::parse_json(&e))?;
+            for value in &mut records {
+                options.json_format.order_keys(value);
+            }
+            records
+        }
+        (TextFormat::Json, false) => {
+            let parsed = options.json_format.parse_reader(job.reader(input));
+            job.check()?;
+            vec![parsed?]
+        }
+    };
+    let records = match (options.stream, options.framing) {
+        (false, Framing::None) | (true, _) => records,
+        (false, _) => records.into_iter().flat_map(|value| match value {
+            Value::Array(items) => items,
+            value => vec![value],
+        }).collect(),
+    };
+    let several = options.stream || options.framing != Framing::None;
+    let mut messagepack = Vec::new();
+    let mut encoded = Vec::with_capacity(records.len());
+    for (index, value) in records.into_iter().enumerate() {
+        job.check()?;
+        encoded.push(options.encode_record(value, several.then_some(index), &mut messagepack, &mut job.warnings)?);
+    }
+    job.keep(encoded);
+    Ok(match options.encoding {
+        None => messagepack,
+        Some(encoding) => encoding.encode(&messagepack).into_bytes(),
+    })
+}
+
+fn utf8(input: &[u8]) -> Result<String, ConvertError> {
+    String::from_utf8(input.to_vec()).map_err(|e| ConvertError::Read(e.to_string()))
+}
+
+// Concatenated MessagePack values to one JSON document per line, each written as soon as all of
+// its bytes came in
+fn messagepack_records_to_json(reader: impl Read, mut writer: impl Write, options: &ConvertOptions, warnings: &mut Vec<Warning>) -> Result<(), ConvertError> {
+    let mut pairing = Pairing::default();
+    let mut records = 0;
+    split_records(reader, options.ndjson && options.lossy, |record, at| {
+        let decoded = options.decode_record(record, at, warnings);
+        let value = options.recovered(decoded, at, records, &mut pairing, warnings)?;
+        records += 1;
+        let line = options.text.record(&value, options)?;
+        writer.write_all(line.as_bytes()).and_then(|()| writer.flush()).map_err(|e| ConvertError::Write(e.to_string()))
+    })
+}
+
+// The records of a stream or of length-prefixed frames, all in memory, each ready to be written
+// out. Where a CBOR record fails to decode there's no telling where the next one starts, so it's
+// looked for from the next byte on.
+fn decoded_records(bytes: &[u8], options: &ConvertOptions, job: &mut Job) -> Result<Vec<Value>, ConvertError> {
+    let mut records = Vec::new();
+    let mut pairing = Pairing::default();
+    if options.framing != Framing::None {
+        for frame in options.framing.split(bytes)? {
+            let (start, end) = (frame.start, frame.end);
+            let decoded = decode_frames(bytes, &[frame], options, &mut job.warnings).map(|mut values| values.remove(0));
+            records.push(options.recovered(decoded, start, records.len(), &mut pairing, &mut job.warnings)?);
+            job.reached(end)?;
+        }
+    } else if options.format == BinaryFormat::Cbor {
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let decoded = decode_cbor_with(bytes, offset, options.lossy, &mut job.warnings);
+            let end = decoded.as_ref().map_or(offset + 1, |(_, end)| *end);
+            records.push(options.recovered(decoded.map(|(value, _)| value), offset, records.len(), &mut pairing, &mut job.warnings)?);
+            job.reached(end)?;
+            offset = end;
+        }
+    } else {
+        split_records(bytes, options.ndjson && options.lossy, |record, at| {
+            let decoded = options.decode_record(record, at, &mut job.warnings);
+            records.push(options.recovered(decoded, at, records.len(), &mut pairing, &mut job.warnings)?);
+            job.reached(at + record.len())
+        })?;
+    }
+    Ok(records)
+}
+
+// The schema of the one value, or of every record of a stream together
+fn schema_of_records(bytes: &[u8], options: &ConvertOptions, warnings: &mut Vec<Warning>) -> Result<Vec<u8>, ConvertError> {
+    let mut records = Vec::new();
+    if options.stream && options.framing != Framing::None {
+        records = decode_frames(bytes, &options.framing.split(bytes)?, options, warnings)?;
+    } else if options.stream {
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let (value, end) = options.decode(bytes, offset, warnings)?;
+            records.push(value);
+            offset = end;
+        }
+    } else {
+        let (value, end) = options.decode(bytes, 0, warnings)?;
+        trailing_bytes(end, bytes.len(), options.lossy || options.ignore_trailing, warnings)?;
+        records.push(value);
+    }
+    for value in &mut records {
+        options.json_format.order_keys(value);
+    }
+    Ok(options.text.write(&json_schema(&records), &options.json_format, options.compact)?.into_bytes())
+}
+
+// Hands each complete MessagePack value in a stream to `each`, with its offset in the stream, as
+// soon as its last byte is read. Bytes that aren't a whole value, a corrupt header or a value cut
+// short at the end, go to `each` as one last record, which then fails to decode where it should.
+// With `resync` a corrupt header isn't the end: the bytes from it go to `each` all the same, and
+// the next record is looked for from the byte after it.
+pub fn split_records(mut reader: impl Read, resync: bool, mut each: impl FnMut(&[u8], usize) -> Result<(), ConvertError>) -> Result<(), ConvertError> {
+    let mut buffer = Vec::new();
+    let mut offset = 0;
+    // Bytes already dropped from the front of the buffer, so offsets are into the stream
+    let mut consumed = 0;
+    let mut at_end = false;
+    loop {
+        match value_end(&buffer, offset) {
+            Ok(Some(end)) => {
+                each(&buffer[offset..end], consumed + offset)?;
+                offset = end;
+            }
+            Ok(None) if at_end => {
+                if offset < buffer.len() {
+                    each(&buffer[offset..], consumed + offset)?;
+                }
+                return Ok(());
+            }
+            Err(_) if resync => {
+                each(&buffer[offset..], consumed + offset)?;
+                offset += 1;
+            }
+            Err(_) => return each(&buffer[offset..], consumed + offset),
+            Ok(None) => {
+                buffer.drain(..offset);
+                consumed += offset;
+                offset = 0;
+                // Growing with the buffer keeps a large value from being rescanned for every chunk
+                let chunk = READ_CHUNK.max(buffer.len());
+                let filled = buffer.len();
+                buffer.resize(filled + chunk, 0);
+                let read = loop {
+                    match reader.read(&mut buffer[filled..]) {
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        result => break result,
+                    }
+                };
+                let read = read.map_err(|e| ConvertError::Read(e.to_string()))?;
+                buffer.truncate(filled + read);
+                at_end = read == 0;
+            }
+        }
+    }
+}
+
+// Any number of JSON documents, on lines of their own or not, to concatenated MessagePack or CBOR,
+// each record with its length prefix if there is framing
+fn json_records_to_messagepack(reader: impl Read, mut writer: impl Write, options: &ConvertOptions, warnings: &mut Vec<Warning>) -> Result<(), ConvertError> {
+    let records = serde_json::Deserializer::from_reader(io::BufReader::new(reader)).into_iter::<Value>();
+    for (index, value) in records.enumerate() {
+        let mut value = value.map_err(|e| ConvertError::parse_json(&e))?;
+        options.json_format.order_keys(&mut value);
+        let mut messagepack = Vec::new();
+        options.encode_record(value, Some(index), &mut messagepack, warnings)?;
+        writer.write_all(&messagepack).and_then(|()| writer.flush())
+            .map_err(|e| ConvertError::Write(e.to_string()))?;
+    }
+    Ok(())
+}
+
+// Whether the two paths name one file, like "./a.json" and "a.json" do. An output that doesn't
+// exist yet can't be the input.
+pub fn same_file(input: &Path, output: &Path) -> bool {
+    match (fs::canonicalize(input), fs::canonicalize(output)) {
+        (Ok(input), Ok(output)) => input == output,
+        _ => input == output,
+    }
+}
+
+// Converts one file into another
+pub fn convert_file(input: &Path, output: &Path, options: &ConvertOptions) -> Result<Vec<Warning>, ConvertError> {
+    if same_file(input, output) {
+        return Err(ConvertError::SameFile(input.to_path_buf()));
+    }
+    let (converted, warnings) = convert(&read_file(input)?, options)?;
+    write_file(output, &converted)?;
+    Ok(warnings)
+}
+
+
+/* Tests */
+#[test]
+fn test_convert_encodings_and_layout() {
+    let to_messagepack = ConvertOptions { direction: Direction::ToMessagePack, encoding: Some(Encoding::Hex), ..Default::default() };
+    assert_eq!(convert(br#"{"b": 1, "a": [true]}"#, &to_messagepack).unwrap().0, b"82a16191c3a16201");
+
+    let to_json = ConvertOptions { encoding: Some(Encoding::Hex), compact: true, ..Default::default() };
+    assert_eq!(convert(b"82a16191c3\na16201\n", &to_json).unwrap().0, br#"{"a":[true],"b":1}"#);
+    let to_json = ConvertOptions { encoding: Some(Encoding::Base64), ..Default::default() };
+    assert_eq!(convert(b"gaFhAQ==", &to_json).unwrap().0, b"{\n  \"a\": 1\n}");
+    assert!(matches!(convert(b"gaFhAQ", &ConvertOptions { encoding: Some(Encoding::Hex), ..Default::default() }), Err(ConvertError::HexDecode(hex::FromHexError::InvalidHexCharacter { c: 'g', index: 0 }))));
+}
+
+#[test]
+fn test_builder_floats_and_depth_limit() {
+    let options = ConvertOptions::to_json().compact(true).sort_keys(false).encoding(Some(Encoding::Hex));
+    assert_eq!(options, ConvertOptions {
+        compact: true,
+        json_format: JsonFormat { sort_keys: false, ..JsonFormat::default() },
+        encoding: Some(Encoding::Hex),
+        ..Default::default()
+    });
+
+    // 1.5 fits float 32 exactly, 0.1 doesn't, and integers stay integers
+    let single = ConvertOptions::to_messagepack().floats(Floats::Single);
+    assert_eq!(convert(b"[1.5, 0.1, 2]", &single).unwrap().0, [&[0x93, 0xca][..], &1.5f32.to_be_bytes(), &[0xcb], &0.1f64.to_be_bytes(), &[0x02]].concat());
+    assert_eq!(convert(b"[1.5]", &ConvertOptions::to_messagepack()).unwrap().0, [&[0x91, 0xcb][..], &1.5f64.to_be_bytes()].concat());
+
+    // [[1]], which has a value two levels down
+    let nested = [0x91, 0x91, 0x01];
+    assert!(convert(&nested, &ConvertOptions::to_json().max_depth(2)).is_ok());
+    assert_eq!(convert(&nested, &ConvertOptions::to_json().max_depth(1)), Err(ConvertError::DepthLimit { path: "/0/0".to_string(), limit: 1 }));
+    assert_eq!(convert(br#"{"a": [1]}"#, &ConvertOptions::to_messagepack().max_depth(1)), Err(ConvertError::DepthLimit { path: "/a/0".to_string(), limit: 1 }));
+    // Lossy NDJSON gives a record that's too deep its line too
+    let lines = convert(&[&nested[..], &[0x01]].concat(), &ConvertOptions::to_json().max_depth(1).stream(true).lossy(true).ndjson(true)).unwrap().0;
+    assert_eq!(lines, b"{\"$error\":\"Nesting deeper than 1 levels at /0/0\",\"$offset\":0}\n1\n");
+}
+
+#[test]
+fn test_convert_strict_lossy_and_stream() {
+    // Two values back to back, the second one binary
+    let bytes = [0x81, 0xa1, 0x61, 0x01, 0xc4, 0x02, b'a', b'b'];
+    assert_eq!(convert(&bytes, &ConvertOptions::default()), Err(ConvertError::TrailingBytes(4)));
+    assert_eq!(convert(&bytes, &ConvertOptions { lossy: true, compact: true, ..Default::default() }).unwrap().0, br#"{"a":1}"#);
+    let (json, warnings) = convert(&bytes, &ConvertOptions::to_json().compact(true).ignore_trailing(true)).unwrap();
+    assert_eq!((&json[..], warnings[0].kind, warnings[0].offset), (&br#"{"a":1}"#[..], WarningKind::TrailingBytes, Some(4)));
+    assert!(convert(&bytes, &ConvertOptions { stream: true, ..Default::default() }).is_err());
+    let lines = convert(&bytes, &ConvertOptions { stream: true, lossy: true, ..Default::default() }).unwrap().0;
+    assert_eq!(lines, b"{\"a\":1}\n\"YWI=\"\n");
+
+    let stream = ConvertOptions { direction: Direction::ToMessagePack, stream: true, ..Default::default() };
+    assert_eq!(convert(b"{\"a\":1}\n\"ab\"\n", &stream).unwrap().0, [0x81, 0xa1, 0x61, 0x01, 0xa2, b'a', b'b']);
+    assert!(convert(b"{\"a\":1} {", &stream).is_err());
+}
+
+#[test]
+fn test_stream_writes_each_record_as_it_arrives() {
+    // Hands out one byte per read, like a slow pipe
+    struct Trickle<'a>(&'a [u8]);
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((&first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+    let bytes = [0x81, 0xa1, 0x61, 0x01, 0x92, 0xc3, 0xc0, 0x82];
+    let mut lines = Vec::new();
+    let options = ConvertOptions { stream: true, ..Default::default() };
+    let err = convert_stream(Trickle(&bytes), &mut lines, &options).unwrap_err();
+    // The records before the truncated map still came out, its missing key is reported
+    // where the stream ended
+    assert_eq!(lines, b"{\"a\":1}\n[true,null]\n");
+    assert!(matches!(err, ConvertError::MsgpackDecode { offset: 8, .. }));
+}
+
+#[test]
+fn test_convert_cbor() {
+    let to_cbor = ConvertOptions { direction: Direction::ToMessagePack, format: BinaryFormat::Cbor, encoding: Some(Encoding::Hex), ..Default::default() };
+    let hex = convert(br#"{"name": "Alice", "age": 30}"#, &to_cbor).unwrap().0;
+    assert_eq!(hex, b"a263616765181e646e616d6565416c696365");
+    let to_json = ConvertOptions { format: BinaryFormat::Cbor, encoding: Some(Encoding::Hex), compact: true, ..Default::default() };
+    assert_eq!(convert(&hex, &to_json).unwrap().0, br#"{"age":30,"name":"Alice"}"#);
+
+    let stream = ConvertOptions { format: BinaryFormat::Cbor, stream: true, ..Default::default() };
+    assert_eq!(convert(&[0x01, 0x82, 0xf5, 0xf6], &stream).unwrap().0, b"1\n[true,null]\n");
+    assert_eq!(BinaryFormat::detect(&[0xa1, 0x61, b'a', 0x01]), BinaryFormat::Cbor);
+    assert_eq!(BinaryFormat::detect(&[0x81, 0xa1, b'a', 0x01]), BinaryFormat::MessagePack);
+}
+
+#[test]
+fn test_convert_yaml() {
+    let from_yaml = ConvertOptions { direction: Direction::ToMessagePack, text: TextFormat::Yaml, encoding: Some(Encoding::Hex), ..Default::default() };
+    assert_eq!(convert(b"b: 1\na: [true]\n", &from_yaml).unwrap().0, b"82a16191c3a16201");
+    assert!(matches!(convert(b"1\n--- 2\n", &from_yaml), Err(ConvertError::YamlParse { line: 2, .. })));
+    let stream = ConvertOptions { stream: true, ..from_yaml };
+    assert_eq!(convert(b"1\n--- 2\n", &stream).unwrap().0, b"0102");
+
+    let to_yaml = ConvertOptions { text: TextFormat::Yaml, encoding: Some(Encoding::Hex), ..Default::default() };
+    assert_eq!(convert(b"82a16191c3a16201", &to_yaml).unwrap().0, b"a:\n  - true\nb: 1\n");
+    assert_eq!(convert(b"82a16191c3a16201", &ConvertOptions { compact: true, ..to_yaml }).unwrap().0, br#"{"a":[true],"b":1}"#);
+    let records = ConvertOptions { text: TextFormat::Yaml, stream: true, ..Default::default() };
+    assert_eq!(convert(&[0x01, 0x81, 0xa1, b'a', 0x02], &records).unwrap().0, b"---\n1\n---\na: 2\n");
+}
+
+#[test]
+fn test_schema_of_a_stream_matches_the_golden_file() {
+    let to_messagepack = ConvertOptions { direction: Direction::ToMessagePack, stream: true, ..Default::default() };
+    let records = convert(include_bytes!("../tests/golden/records.jsonl"), &to_messagepack).unwrap().0;
+    let schema = convert(&records, &ConvertOptions { stream: true, schema: true, ..Default::default() }).unwrap().0;
+    assert_eq!(String::from_utf8(schema).unwrap() + "\n", include_str!("../tests/golden/records.schema.json"));
+    // Only the first record, so nothing can be left out of it and nothing repeats
+    let first = convert(&records, &ConvertOptions { schema: true, lossy: true, ..Default::default() }).unwrap().0;
+    let first: Value = serde_json::from_slice(&first).unwrap();
+    assert_eq!(first["required"], serde_json::json!(["id", "level", "message", "tags", "took"]));
+    assert_eq!(first["properties"]["level"], serde_json::json!({"type": "string"}));
+    assert!(matches!(convert(&records, &ConvertOptions { schema: true, ..Default::default() }), Err(ConvertError::TrailingBytes(_))));
+}
+
+#[test]
+fn test_framed_stream() {
+    for framing in [Framing::U16Be, Framing::U32Be, Framing::U32Le, Framing::Varint] {
+        let to_messagepack = ConvertOptions { direction: Direction::ToMessagePack, stream: true, framing, ..Default::default() };
+        let framed = convert(b"{\"a\":1}\n[true,null]\n", &to_messagepack).unwrap().0;
+        let to_json = ConvertOptions { stream: true, framing, ..Default::default() };
+        assert_eq!(convert(&framed, &to_json).unwrap().0, b"{\"a\":1}\n[true,null]\n", "{:?}", framing);
+        let yaml = ConvertOptions { text: TextFormat::Yaml, ..to_json };
+        assert_eq!(convert(&framed, &yaml).unwrap().0, b"---\na: 1\n---\n- true\n- null\n");
+    }
+    let to_json = ConvertOptions { stream: true, framing: Framing::U32Be, ..Default::default() };
+    assert_eq!(convert(&[0, 0, 0, 1, 0xc3, 0, 0, 0, 4, 0x92, 0xc3], &to_json), Err(ConvertError::ShortFrame { index: 1, offset: 5, start: 9, declared: 4, available: 2 }));
+}
+
+#[test]
+fn test_ndjson_keeps_a_line_for_every_record() {
+    let ndjson = ConvertOptions { stream: true, ndjson: true, lossy: true, ..Default::default() };
+    // Two records, then a map header with nothing after it
+    let lines = convert(&[0x81, 0xa1, 0x61, 0x01, 0xc3, 0x81], &ndjson).unwrap().0;
+    let lines: Vec<Value> = String::from_utf8(lines).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[..2], [json!({"a": 1}), json!(true)]);
+    assert_eq!(lines[2]["$offset"], 5);
+    assert!(lines[2]["$error"].is_string());
+
+    // A broken frame takes its own line and the frames after it still decode
+    let mut framed = Vec::new();
+    for record in [&[0x01][..], &[0xc1], &[0x92, 0x01], &[0xa1, 0x62]] {
+        Framing::U16Be.frame(record, &mut framed).unwrap();
+    }
+    let lines = convert(&framed, &ConvertOptions { framing: Framing::U16Be, ..ndjson.clone() }).unwrap().0;
+    let lines: Vec<Value> = String::from_utf8(lines).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!((&lines[0], &lines[3]), (&json!(1), &json!("b")));
+    assert_eq!((&lines[1]["$offset"], &lines[2]["$offset"]), (&json!(5), &json!(8)));
+
+    // Strict decoding still fails on the first bad record
+    assert!(convert(&framed, &ConvertOptions { framing: Framing::U16Be, lossy: false, ..ndjson.clone() }).is_err());
+
+    // Without framing the records after a bad byte are found again
+    let lines = convert(&[0x01, 0xc1, 0x02], &ndjson).unwrap().0;
+    let lines: Vec<Value> = String::from_utf8(lines).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!((&lines[0], &lines[1]["$offset"], &lines[2]), (&json!(1), &json!(1), &json!(2)));
+    let cbor = ConvertOptions { format: BinaryFormat::Cbor, ..ndjson.clone() };
+    let lines = convert(&[0x01, 0xff, 0x02], &cbor).unwrap().0;
+    let lines: Vec<Value> = String::from_utf8(lines).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!((&lines[0], &lines[1]["$offset"], &lines[2]), (&json!(1), &json!(1), &json!(2)));
+    assert!(convert(&[0x01, 0xc1, 0x02], &ConvertOptions { lossy: false, ..ndjson }).is_err());
+}
+
+#[test]
+fn test_rpc_stream_pairs_responses_with_requests() {
+    let to_messagepack = ConvertOptions { direction: Direction::ToMessagePack, stream: true, ..Default::default() };
+    let messages = convert(b"[0, 1, \"add\", [1, 2]]\n[2, \"log\", []]\n[1, 1, null, 3]\n[1, 2]\n", &to_messagepack).unwrap().0;
+    // Keys in the order the labels give them
+    let json_format = JsonFormat { sort_keys: false, ..Default::default() };
+    let lines = convert(&messages, &ConvertOptions { stream: true, rpc: true, json_format, ..Default::default() }).unwrap().0;
+    assert_eq!(String::from_utf8(lines).unwrap(), concat!(
+        "{\"type\":\"request\",\"msgid\":1,\"method\":\"add\",\"params\":[1,2]}\n",
+        "{\"type\":\"notification\",\"method\":\"log\",\"params\":[]}\n",
+        "{\"type\":\"response\",\"msgid\":1,\"method\":\"add\",\"error\":null,\"result\":3}\n",
+        "[1,2]\n",
+    ));
+}
+
+#[test]
+fn test_lossy_conversions_warn_about_what_they_changed() {
+    let lossy = ConvertOptions { lossy: true, compact: true, ..Default::default() };
+    // {"a": bin [1], 1: "\xff"} and a trailing nil
+    let bytes = [0x82, 0xa1, b'a', 0xc4, 0x01, 0x01, 0x01, 0xa1, 0xff, 0xc0];
+    let (json, warnings) = convert(&bytes, &lossy).unwrap();
+    assert_eq!(json, "{\"1\":\"\u{fffd}\",\"a\":\"AQ==\"}".as_bytes());
+    let kinds: Vec<WarningKind> = warnings.iter().map(|warning| warning.kind).collect();
+    assert_eq!(kinds, [WarningKind::Base64Payload, WarningKind::StringifiedKey, WarningKind::LossyUtf8, WarningKind::TrailingBytes]);
+    let messages: Vec<String> = warnings.iter().map(Warning::to_string).collect();
+    assert_eq!(messages, [
+        "/a at offset 0x3: binary value as base64",
+        "(root) at offset 0x6: map key \"1\" as a string",
+        "/1 at offset 0x7: invalid UTF-8 replaced",
+        "At offset 0x9: 1 bytes after the value ignored",
+    ]);
+    assert!(convert(&bytes[..9], &ConvertOptions::default()).is_err());
+    assert!(convert(&[0x81, 0xa1, b'a', 0x01], &lossy).unwrap().1.is_empty());
+
+    // Offsets are into the whole stream, not the record
+    let stream = ConvertOptions { stream: true, ..lossy.clone() };
+    let (_, warnings) = convert(&[0x01, 0x91, 0xd4, 0x05, 0x00], &stream).unwrap();
+    assert_eq!(warnings, [Warning::new(WarningKind::Base64Payload, "ext type 5 value as base64".to_string()).at_path("/0").at_offset(2)]);
+
+    // CBOR's simple values, and YAML's hazards on the way in
+    let cbor = ConvertOptions { format: BinaryFormat::Cbor, ..lossy };
+    let (json, warnings) = convert(&[0x82, 0xf0, 0x41, 0x00], &cbor).unwrap();
+    assert_eq!(json, br#"[null,"AA=="]"#);
+    assert_eq!(warnings.iter().map(|warning| warning.kind).collect::<Vec<_>>(), [WarningKind::SimpleValue, WarningKind::Base64Payload]);
+    let from_yaml = ConvertOptions { direction: Direction::ToMessagePack, text: TextFormat::Yaml, lossy: true, ..Default::default() };
+    let (_, warnings) = convert(b"200: ok", &from_yaml).unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].kind, WarningKind::Yaml);
+}
+
+#[test]
+fn test_registered_ext_types_convert_both_ways() {
+    use crate::ext_types::{ExtDecoder, Primitive};
+    let registry = ExtRegistry::default().with_decoder(7, ExtDecoder::Primitive(Primitive::I128));
+    // [ext 7 -5, ext 7 i128::MIN] as 16-byte payloads
+    let bytes = [&[0x92, 0xd8, 0x07][..], &(-5i128).to_be_bytes(), &[0xd8, 0x07], &i128::MIN.to_be_bytes()].concat();
+    let to_json = ConvertOptions::to_json().compact(true).ext_registry(registry.clone());
+    let (json, warnings) = convert(&bytes, &to_json).unwrap();
+    assert_eq!(json, br#"[{"$ext":7,"value":-5},{"$ext":7,"value":"-170141183460469231731687303715884105728"}]"#);
+    assert!(warnings.is_empty());
+    assert_eq!(convert(&json, &ConvertOptions::to_messagepack().ext_registry(registry.clone())).unwrap().0, bytes);
+
+    // Records of a stream, framed or not, go through the registry as well
+    let stream = ConvertOptions { stream: true, ..to_json.clone() };
+    assert_eq!(convert(&bytes[1..], &stream).unwrap().0, b"{\"$ext\":7,\"value\":-5}\n{\"$ext\":7,\"value\":\"-170141183460469231731687303715884105728\"}\n");
+    let mut framed = Vec::new();
+    Framing::U16Be.frame(&bytes[1..19], &mut framed).unwrap();
+    assert_eq!(convert(&framed, &ConvertOptions { framing: Framing::U16Be, ..stream }).unwrap().0, b"{\"$ext\":7,\"value\":-5}\n");
+    // Without the registry the ext values don't make it into JSON
+    assert!(convert(&bytes, &ConvertOptions::to_json()).is_err());
+}
+
+#[test]
+fn test_convert_until_hands_back_the_values_and_spans() {
+    let token = JobToken::default();
+    // {"a": [1, 2]}
+    let bytes = [0x81, 0xa1, b'a', 0x92, 0x01, 0x02];
+    let conversion = convert_until(&bytes, &ConvertOptions::to_json().compact(true), &token).unwrap();
+    assert_eq!(conversion.output, br#"{"a":[1,2]}"#);
+    assert_eq!(conversion.values, [json!({"a": [1, 2]})]);
+    assert_eq!(conversion.spans.get("/a/1"), Some(&(5..6)));
+    assert_eq!(token.progress(), bytes.len());
+    // Named fields aren't where the spans say
+    let template = Template::parse(r#"{"a": ["x", "y"]}"#).unwrap();
+    let conversion = convert_until(&bytes, &ConvertOptions::to_json().template(Some(template)), &token).unwrap();
+    assert_eq!(conversion.values, [json!({"a": {"x": 1, "y": 2}})]);
+    assert!(conversion.spans.is_empty());
+
+    // Framed without a stream, the records are the elements of one array
+    let framed = ConvertOptions::to_messagepack().framing(Framing::U16Be);
+    let conversion = convert_until(b"[true, {\"b\": null}]", &framed, &token).unwrap();
+    assert_eq!(conversion.output, [0, 1, 0xc3, 0, 4, 0x81, 0xa1, b'b', 0xc0]);
+    assert_eq!(conversion.values.len(), 2);
+    let back = ConvertOptions::to_json().framing(Framing::U16Be).compact(true);
+    assert_eq!(convert(&conversion.output, &back).unwrap().0, br#"[true,{"b":null}]"#);
+    // And as a stream, a line for each
+    let lines = convert_until(&conversion.output, &back.stream(true), &token).unwrap();
+    assert_eq!((&lines.output[..], lines.values.len()), (&b"true\n{\"b\":null}\n"[..], 2));
+
+    token.cancel();
+    assert_eq!(convert_until(&bytes, &ConvertOptions::to_json(), &token).unwrap_err(), ConvertError::Cancelled);
+    assert_eq!(convert_until(b"[1]", &ConvertOptions::to_messagepack(), &token).unwrap_err(), ConvertError::Cancelled);
+}
@@ -0,0 +1,57 @@
+use crate::find::text_fingerprint;
+use crate::locale::trf;
+use crate::stats::group_thousands;
+
+// Live size readout under a pane, recomputed only when the text changes
+#[derive(Default)]
+pub struct PaneCounter {
+    counted: Option<(usize, u64)>,
+    chars: usize,
+    // None while the text doesn't decode, e.g. halfway through a paste
+    decoded_bytes: Option<usize>,
+}
+
+impl PaneCounter {
+    // Character count for a JSON pane
+    pub fn text(&mut self, text: &str) -> String {
+        self.update(text, None::<fn(&str) -> Option<usize>>);
+        trf("{} chars", &[&group_thousands(self.chars)])
+    }
+
+    // Character count plus the length of the bytes the text decodes to, for a base64/hex pane
+    pub fn encoded(&mut self, text: &str, decoded_len: impl Fn(&str) -> Option<usize>) -> String {
+        self.update(text, Some(decoded_len));
+        let bytes = match self.decoded_bytes {
+            Some(n) => group_thousands(n),
+            None => "—".to_string(),
+        };
+        trf("{} chars · {} bytes decoded", &[&group_thousands(self.chars), &bytes])
+    }
+
+    fn update(&mut self, text: &str, decoded_len: Option<impl Fn(&str) -> Option<usize>>) {
+        let key = (text.len(), text_fingerprint(text));
+        if self.counted == Some(key) {
+            return;
+        }
+        self.chars = text.chars().count();
+        self.decoded_bytes = decoded_len.and_then(|decoded_len| decoded_len(text));
+        self.counted = Some(key);
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_counter_counts_chars_not_bytes() {
+    let mut counter = PaneCounter::default();
+    assert_eq!(counter.text(r#"{"é": 1}"#), "8 chars");
+    assert_eq!(counter.text(&"a".repeat(12345)), "12,345 chars");
+}
+
+#[test]
+fn test_counter_shows_dash_until_the_text_decodes() {
+    let decoded_len = |text: &str| hex::decode(text).ok().map(|bytes| bytes.len());
+    let mut counter = PaneCounter::default();
+    assert_eq!(counter.encoded("c0c", decoded_len), "3 chars · — bytes decoded");
+    assert_eq!(counter.encoded("c0c3", decoded_len), "4 chars · 2 bytes decoded");
+}
@@ -0,0 +1,565 @@
+use crate::convert::ConvertOptions;
+use crate::error::{ConvertError, UnsupportedKind};
+use crate::ext_types::{BinForm, ExtRegistry, KeyForm, MAP_KEY};
+use crate::locale::{tr, trf};
+use crate::msgpack::{read_token, TokenKind};
+use crate::schema_check::escape_pointer_token;
+use crate::warning::{Warning, WarningKind};
+use base64::{engine::general_purpose, Engine};
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// Deeper nesting than this is rejected instead of risking a stack overflow
+pub const MAX_DEPTH: usize = 512;
+
+// Byte range of the full encoding of every node (children included), keyed by JSON Pointer
+pub type SpanMap = BTreeMap<String, Range<usize>>;
+
+// How many values are decoded between looks at the cancel flag and progress updates
+const CANCEL_CHECK_INTERVAL: usize = 4096;
+
+#[cfg(test)]
+pub fn decode_with_spans(bytes: &[u8]) -> Result<(Value, SpanMap), ConvertError> {
+    let decoded = decode_with_spans_until(bytes, &ConvertOptions::default(), &AtomicBool::new(false), &AtomicUsize::new(0), &mut Vec::new());
+    decoded.map(|(value, _, spans)| (value, spans))
+}
+
+// The value at the start of `bytes` decoded as `decode_value_with` does, where it ends, and the
+// spans of its nodes. Gives up with a "Cancelled" error soon after `cancelled` is set, and keeps
+// `progress` at roughly the number of bytes decoded so far.
+pub fn decode_with_spans_until(
+    bytes: &[u8],
+    options: &ConvertOptions,
+    cancelled: &AtomicBool,
+    progress: &AtomicUsize,
+    warnings: &mut Vec<Warning>,
+) -> Result<(Value, usize, SpanMap), ConvertError> {
+    let mut decoder = Decoder::with_options(bytes, Some(SpanMap::new()), cancelled, options);
+    decoder.progress = Some(progress);
+    let value = decoder.value(0)?;
+    progress.store(decoder.position, Ordering::Relaxed);
+    warnings.append(&mut decoder.warnings);
+    Ok((value, decoder.position, decoder.spans.unwrap_or_default()))
+}
+
+// Decodes the one value starting at `offset`, without spans, and says where it ends. Lossy
+// decoding turns what JSON can't hold into strings instead of failing: binary and extension
+// payloads become base64, other map keys their JSON text and invalid UTF-8 is replaced.
+pub fn decode_value_at(bytes: &[u8], offset: usize, lossy: bool) -> Result<(Value, usize), ConvertError> {
+    decode_value_with(bytes, offset, &ConvertOptions::default().lossy(lossy), &mut Vec::new())
+}
+
+// Same as lossy or not as `options` say, with their registered ext types rendered by their
+// decoders, bins in their bin form and maps with other keys in their key form if there is one,
+// and a warning in `warnings` for everything lossy decoding made a string of
+pub fn decode_value_with(bytes: &[u8], offset: usize, options: &ConvertOptions, warnings: &mut Vec<Warning>) -> Result<(Value, usize), ConvertError> {
+    let cancelled = AtomicBool::new(false);
+    let mut decoder = Decoder::with_options(bytes, None, &cancelled, options);
+    decoder.position = offset;
+    let value = decoder.value(0)?;
+    warnings.append(&mut decoder.warnings);
+    Ok((value, decoder.position))
+}
+
+// Smallest node whose encoding contains the given offset, i.e. the most specific JSON path for it
+pub fn path_at_offset(spans: &SpanMap, offset: usize) -> Option<&str> {
+    spans.iter()
+        .filter(|(_, range)| range.contains(&offset))
+        .min_by_key(|(_, range)| range.len())
+        .map(|(path, _)| path.as_str())
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    // JSON Pointer of the node being decoded
+    path: String,
+    spans: Option<SpanMap>,
+    cancelled: &'a AtomicBool,
+    progress: Option<&'a AtomicUsize>,
+    decoded_values: usize,
+    lossy: bool,
+    ext_types: Option<&'a ExtRegistry>,
+    bin_form: Option<BinForm>,
+    key_form: Option<KeyForm>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8], spans: Option<SpanMap>, cancelled: &'a AtomicBool) -> Self {
+        Decoder { bytes, position: 0, path: String::new(), spans, cancelled, progress: None, decoded_values: 0, lossy: false, ext_types: None, bin_form: None, key_form: None, warnings: Vec::new() }
+    }
+
+    fn with_options(bytes: &'a [u8], spans: Option<SpanMap>, cancelled: &'a AtomicBool, options: &'a ConvertOptions) -> Self {
+        Decoder {
+            lossy: options.lossy,
+            ext_types: Some(&options.ext_registry),
+            bin_form: options.bin_form,
+            key_form: options.key_form,
+            ..Decoder::new(bytes, spans, cancelled)
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, ConvertError> {
+        let start = self.position;
+        if depth > MAX_DEPTH {
+            return Err(ConvertError::TooDeep { offset: start, depth: MAX_DEPTH });
+        }
+        self.decoded_values += 1;
+        if self.decoded_values.is_multiple_of(CANCEL_CHECK_INTERVAL) {
+            if self.cancelled.load(Ordering::Relaxed) {
+                return Err(ConvertError::Cancelled);
+            }
+            if let Some(progress) = self.progress {
+                progress.store(start, Ordering::Relaxed);
+            }
+        }
+        let token = read_token(self.bytes, start)?;
+        self.position = token.end;
+
+        let value = match token.kind {
+            TokenKind::Nil => Value::Null,
+            TokenKind::Bool(b) => Value::Bool(b),
+            TokenKind::Uint(n) => Value::from(n),
+            TokenKind::Int(n) => Value::from(n),
+            TokenKind::F32(n) => float(n as f64),
+            TokenKind::F64(n) => float(n),
+            TokenKind::Str(range) => Value::String(self.string(start, range)?),
+            TokenKind::Bin(range) => self.bin(start, range)?,
+            TokenKind::Ext(ext_type, range) => self.ext(start, ext_type, range)?,
+            TokenKind::Array(len) => {
+                let mut items = Vec::with_capacity(len.min(self.remaining()));
+                for index in 0..len {
+                    let child = self.child(&index.to_string(), depth)?;
+                    items.push(child);
+                }
+                Value::Array(items)
+            }
+            TokenKind::Map(len) => self.map(len, depth)?,
+        };
+
+        if let Some(spans) = &mut self.spans {
+            spans.insert(self.path.clone(), start..self.position);
+        }
+        Ok(value)
+    }
+
+    // Kept out of `value` so deep nesting doesn't pay for its locals in every frame. With the pairs
+    // form the map turns into pairs at the first key that isn't a string.
+    fn map(&mut self, len: usize, depth: usize) -> Result<Value, ConvertError> {
+        let warnings_start = self.warnings.len();
+        let mut entries = Vec::with_capacity(len.min(self.remaining()));
+        for index in 0..len {
+            let key_start = self.position;
+            let other_key = || !matches!(read_token(self.bytes, key_start), Ok(token) if matches!(token.kind, TokenKind::Str(_)));
+            if self.key_form == Some(KeyForm::Pairs) && other_key() {
+                return self.pairs(entries, warnings_start, index..len, depth);
+            }
+            let key = self.key(depth)?;
+            let key_span = key_start..self.position;
+            let child = self.child(&key, depth)?;
+            entries.push((key, key_span, child));
+        }
+        Ok(Value::Object(entries.into_iter().map(|(key, _, child)| (key, child)).collect()))
+    }
+
+    fn key(&mut self, depth: usize) -> Result<String, ConvertError> {
+        let key_start = self.position;
+        let key_token = read_token(self.bytes, key_start)?;
+        match key_token.kind {
+            TokenKind::Str(range) => {
+                self.position = key_token.end;
+                self.string(key_start, range)
+            }
+            _ if self.lossy || self.key_form == Some(KeyForm::Stringify) => {
+                let key = match self.value(depth + 1)? {
+                    Value::String(key) => key,
+                    key => key.to_string(),
+                };
+                if self.key_form != Some(KeyForm::Stringify) {
+                    self.warn(key_start, WarningKind::StringifiedKey, trf("map key {} as a string", &[&format_args!("{:?}", key)]));
+                }
+                Ok(key)
+            }
+            _ => Err(self.unsupported(key_start, UnsupportedKind::MapKey)),
+        }
+    }
+
+    // A map in the pairs form, each entry as [key, value] at /$map/index. The entries `decoded`
+    // before it came to a key other than a string move there with their spans and warnings, and
+    // the `rest` are decoded as pairs.
+    fn pairs(&mut self, decoded: Vec<(String, Range<usize>, Value)>, warnings_start: usize, rest: Range<usize>, depth: usize) -> Result<Value, ConvertError> {
+        // Backwards, so of two entries with the same key the later one gets the spans it made
+        for (index, (key, key_span, _)) in decoded.iter().enumerate().rev() {
+            let pair = self.pair_path(index);
+            self.move_node(&format!("{}/{}", self.path, escape_pointer_token(key)), &format!("{}/1", pair), warnings_start);
+            if let Some(spans) = &mut self.spans {
+                spans.insert(format!("{}/0", pair), key_span.clone());
+            }
+        }
+        let mut pairs = Vec::with_capacity(rest.end.min(decoded.len() + self.remaining()));
+        pairs.extend(decoded.into_iter().map(|(key, _, child)| Value::Array(vec![Value::String(key), child])));
+        for index in rest {
+            let parent_len = self.path.len();
+            self.path = self.pair_path(index);
+            let pair = self.child("0", depth + 1).and_then(|key| Ok(Value::Array(vec![key, self.child("1", depth + 1)?])));
+            self.path.truncate(parent_len);
+            pairs.push(pair?);
+        }
+        Ok(Value::Object(Map::from_iter([(MAP_KEY.to_string(), Value::Array(pairs))])))
+    }
+
+    fn pair_path(&self, index: usize) -> String {
+        format!("{}/{}/{}", self.path, escape_pointer_token(MAP_KEY), index)
+    }
+
+    // Gives the node at `from` and everything below it the path `to`, in the spans and in the
+    // warnings from `warnings_start` on
+    fn move_node(&mut self, from: &str, to: &str, warnings_start: usize) {
+        let moved = |path: &str| path.strip_prefix(from).filter(|rest| rest.is_empty() || rest.starts_with('/')).map(|rest| format!("{}{}", to, rest));
+        if let Some(spans) = &mut self.spans {
+            let below: Vec<String> = spans.range(from.to_string()..).map(|(path, _)| path).take_while(|path| path.starts_with(from))
+                .filter(|path| moved(path).is_some()).cloned().collect();
+            for path in below {
+                if let (Some(span), Some(to)) = (spans.remove(&path), moved(&path)) {
+                    spans.insert(to, span);
+                }
+            }
+        }
+        for warning in &mut self.warnings[warnings_start..] {
+            if let Some(to) = warning.path.as_deref().and_then(moved) {
+                warning.path = Some(to);
+            }
+        }
+    }
+
+    fn child(&mut self, token: &str, depth: usize) -> Result<Value, ConvertError> {
+        let parent_len = self.path.len();
+        self.path.push('/');
+        self.path.push_str(&escape_pointer_token(token));
+        let child = self.value(depth + 1);
+        self.path.truncate(parent_len);
+        child
+    }
+
+    fn string(&mut self, start: usize, range: Range<usize>) -> Result<String, ConvertError> {
+        match std::str::from_utf8(&self.bytes[range.clone()]) {
+            Ok(text) => Ok(text.to_owned()),
+            Err(_) if self.lossy => {
+                self.warn(start, WarningKind::LossyUtf8, tr("invalid UTF-8 replaced").to_string());
+                Ok(String::from_utf8_lossy(&self.bytes[range]).into_owned())
+            }
+            Err(e) => Err(ConvertError::MsgpackDecode { offset: start, msg: trf("Invalid UTF-8 in string: {}", &[&e]) }),
+        }
+    }
+
+    // A 16-byte bin is a UUID string if UUIDs are on, otherwise bins take the bin form if there is
+    // one, or are base64 when it's lossy
+    fn bin(&mut self, start: usize, range: Range<usize>) -> Result<Value, ConvertError> {
+        let data = &self.bytes[range];
+        let uuid = self.ext_types.and_then(|types| types.uuid(None, data));
+        match uuid.or_else(|| self.bin_form.map(|bin_form| bin_form.decode(data))) {
+            Some(value) => Ok(value),
+            None if self.lossy => {
+                let encoded = general_purpose::STANDARD.encode(data);
+                self.warn(start, WarningKind::Base64Payload, tr("binary value as base64").to_string());
+                Ok(Value::String(encoded))
+            }
+            None => Err(self.unsupported(start, UnsupportedKind::Binary)),
+        }
+    }
+
+    // The UUID ext type gives plain UUID strings, timestamps a date-time or a number, registered
+    // types go through their decoder. A payload it can't read fails the decoding like any other
+    // ext value does, unless it's lossy and the payload becomes base64.
+    fn ext(&mut self, start: usize, ext_type: i8, range: Range<usize>) -> Result<Value, ConvertError> {
+        let data = &self.bytes[range];
+        let known = self.ext_types.and_then(|types| types.uuid(Some(ext_type), data).or_else(|| types.timestamp(ext_type, data)));
+        if let Some(value) = known {
+            return Ok(value);
+        }
+        match self.ext_types.and_then(|types| types.decode(ext_type, data)) {
+            Some(Ok(value)) => Ok(value),
+            _ if self.lossy => {
+                let encoded = general_purpose::STANDARD.encode(data);
+                self.warn(start, WarningKind::Base64Payload, trf("ext type {} value as base64", &[&ext_type]));
+                Ok(Value::String(encoded))
+            }
+            Some(Err(msg)) => Err(ConvertError::MsgpackDecode { offset: start, msg }),
+            None => Err(self.unsupported(start, UnsupportedKind::Extension(ext_type))),
+        }
+    }
+
+    fn warn(&mut self, offset: usize, kind: WarningKind, message: String) {
+        self.warnings.push(Warning::new(kind, message).at_path(&self.path).at_offset(offset));
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    fn unsupported(&self, offset: usize, what: UnsupportedKind) -> ConvertError {
+        ConvertError::Unsupported { offset, path: self.path.clone(), what }
+    }
+}
+
+// JSON has no NaN or infinity, serde_json maps them to null as well
+pub(crate) fn float(n: f64) -> Value {
+    Number::from_f64(n).map(Value::Number).unwrap_or(Value::Null)
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn decode(bytes: &[u8]) -> Result<Value, ConvertError> {
+    Decoder::new(bytes, None, &AtomicBool::new(false)).value(0)
+}
+
+#[cfg(test)]
+fn alice_bytes() -> Vec<u8> {
+    hex::decode("83a36167651ea463697479aa576f6e6465726c616e64a46e616d65a5416c696365").unwrap()
+}
+
+#[cfg(test)]
+fn options(lossy: bool, ext_types: &ExtRegistry, bin_form: Option<BinForm>, key_form: Option<KeyForm>) -> ConvertOptions {
+    ConvertOptions::default().lossy(lossy).ext_registry(ext_types.clone()).bin_form(bin_form).key_form(key_form)
+}
+
+#[test]
+fn test_decode_matches_rmp_serde() {
+    let bytes = alice_bytes();
+    let expected: Value = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(decode(&bytes).unwrap(), expected);
+
+    let value: Value = serde_json::from_str(r#"{"a": [1, -2, 3.5, null, true, "x"], "b": {"c": 18446744073709551615}}"#).unwrap();
+    let bytes = rmp_serde::to_vec(&value).unwrap();
+    assert_eq!(decode(&bytes).unwrap(), value);
+}
+
+#[test]
+fn test_registered_ext_types_decode_through_their_decoders() {
+    let ext_types = ExtRegistry::parse("2 = \"string\"\n7 = \"struct x:u8 y:u8\"").unwrap();
+    // [ext 2 "hi", ext 7 [1, 2], ext 5 "a"]
+    let bytes = [0x93, 0xd5, 0x02, b'h', b'i', 0xd5, 0x07, 0x01, 0x02, 0xd4, 0x05, b'a'];
+    let (value, end) = decode_value_with(&bytes, 0, &options(true, &ext_types, None, None), &mut Vec::new()).unwrap();
+    assert_eq!(value, serde_json::json!([{"$ext": 2, "value": "hi"}, {"$ext": 7, "value": {"x": 1, "y": 2}}, "YQ=="]));
+    assert_eq!(end, bytes.len());
+    // Unregistered types are as unsupported as ever
+    let err = decode_value_with(&bytes, 0, &options(false, &ext_types, None, None), &mut Vec::new()).unwrap_err();
+    assert!(matches!(err, ConvertError::Unsupported { offset: 9, what: UnsupportedKind::Extension(5), .. }), "{:?}", err);
+
+    // ext 7 with one byte where the struct takes two
+    let short = [0xd4, 0x07, 0x01];
+    assert!(matches!(decode_value_with(&short, 0, &options(false, &ext_types, None, None), &mut Vec::new()), Err(ConvertError::MsgpackDecode { offset: 0, .. })));
+    assert_eq!(decode_value_with(&short, 0, &options(true, &ext_types, None, None), &mut Vec::new()).unwrap().0, Value::from("AQ=="));
+    assert!(decode_value_at(&bytes, 0, false).is_err());
+}
+
+#[test]
+fn test_uuids_decode_from_16_byte_bins_and_their_ext_type() {
+    let uuids = ExtRegistry::default().with_uuids(true, Some(3));
+    // [bin 16 of 0x00, ext 3 of 16 0xff, bin 2]
+    let bytes = [&[0x93, 0xc4, 0x10][..], &[0; 16], &[0xd8, 0x03], &[0xff; 16], &[0xc4, 0x02, 0x01, 0x02]].concat();
+    let (value, _) = decode_value_with(&bytes, 0, &options(true, &uuids, None, None), &mut Vec::new()).unwrap();
+    assert_eq!(value, serde_json::json!(["00000000-0000-0000-0000-000000000000", "ffffffff-ffff-ffff-ffff-ffffffffffff", "AQI="]));
+    // Other bins are still not for strict decoding
+    let err = decode_value_with(&bytes, 0, &options(false, &uuids, None, None), &mut Vec::new()).unwrap_err();
+    assert!(matches!(err, ConvertError::Unsupported { offset: 37, what: UnsupportedKind::Binary, .. }), "{:?}", err);
+    assert!(decode_value_with(&bytes[..37], 0, &options(false, &ExtRegistry::default(), None, None), &mut Vec::new()).is_err());
+}
+
+#[test]
+fn test_bins_decode_to_the_chosen_form() {
+    use crate::ext_types::WithExtTypes;
+    // [bin "\x81\xa1a", bin 16 of 0xff, bin 0]
+    let bytes = [&[0x93, 0xc4, 0x03, 0x81, 0xa1, b'a', 0xc4, 0x10][..], &[0xff; 16], &[0xc4, 0x00]].concat();
+    let decoded = |bin_form, uuids| {
+        let registry = ExtRegistry::default().with_uuids(uuids, None);
+        decode_value_with(&bytes, 0, &options(false, &registry, bin_form, None), &mut Vec::new()).map(|(value, _)| value)
+    };
+    assert_eq!(decoded(Some(BinForm::Base64), false).unwrap(), serde_json::json!(["gaFh", "/////////////////////w==", ""]));
+    assert_eq!(decoded(Some(BinForm::Hex), false).unwrap(), serde_json::json!(["81a161", "ff".repeat(16), ""]));
+    assert_eq!(decoded(Some(BinForm::Numbers), true).unwrap(), serde_json::json!([[129, 161, 97], "ffffffff-ffff-ffff-ffff-ffffffffffff", []]));
+    assert!(matches!(decoded(None, false), Err(ConvertError::Unsupported { offset: 1, what: UnsupportedKind::Binary, .. })));
+
+    // Only the tagged form encodes back to bins
+    let tagged = decoded(Some(BinForm::Tagged), false).unwrap();
+    assert_eq!(tagged, serde_json::json!([{"$bin": "gaFh"}, {"$bin": "/////////////////////w=="}, {"$bin": ""}]));
+    let registry = ExtRegistry::default();
+    let encoded = |value: &serde_json::Value| rmp_serde::to_vec(&WithExtTypes::new(value, &registry).bin_form(Some(BinForm::Tagged)));
+    assert_eq!(encoded(&tagged).unwrap(), bytes);
+    let err = encoded(&serde_json::json!({"$bin": "*"})).unwrap_err();
+    assert!(err.to_string().starts_with("$bin: "), "{}", err);
+    // An object with more than the tag, or with the form off, stays a map
+    let other = serde_json::json!({"$bin": "gaFh", "name": "x"});
+    assert_eq!(encoded(&other).unwrap(), rmp_serde::to_vec(&other).unwrap());
+    let hex = WithExtTypes::new(&tagged[0], &registry).bin_form(Some(BinForm::Hex));
+    assert_eq!(rmp_serde::to_vec(&hex).unwrap(), rmp_serde::to_vec(&tagged[0]).unwrap());
+}
+
+#[test]
+fn test_other_map_keys_decode_to_pairs_or_strings() {
+    use crate::ext_types::WithExtTypes;
+    // {1: "a", "b": {[1, 2]: nil, 1.5: true}, "c": {"d": 1}}
+    let bytes = [0x83, 0x01, 0xa1, b'a', 0xa1, b'b', 0x82, 0x92, 0x01, 0x02, 0xc0, 0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0xc3, 0xa1, b'c', 0x81, 0xa1, b'd', 0x01];
+    let registry = ExtRegistry::default();
+    let pairs = Some(KeyForm::Pairs);
+    let (value, _) = decode_value_with(&bytes, 0, &options(false, &registry, None, pairs), &mut Vec::new()).unwrap();
+    assert_eq!(value, serde_json::json!({"$map": [[1, "a"], ["b", {"$map": [[[1, 2], null], [1.5, true]]}], ["c", {"d": 1}]]}));
+    // And back, keys and order as they were
+    assert_eq!(rmp_serde::to_vec(&WithExtTypes::new(&value, &registry).key_form(pairs)).unwrap(), bytes);
+    // Without the form the pairs stay a map with the one key "$map"
+    let plain = rmp_serde::to_vec(&WithExtTypes::new(&value, &registry)).unwrap();
+    assert_eq!(plain, rmp_serde::to_vec(&value).unwrap());
+
+    let mut warnings = Vec::new();
+    let (value, _) = decode_value_with(&bytes, 0, &options(false, &registry, None, Some(KeyForm::Stringify)), &mut warnings).unwrap();
+    assert_eq!(value, serde_json::json!({"1": "a", "b": {"[1,2]": null, "1.5": true}, "c": {"d": 1}}));
+    assert!(warnings.is_empty());
+    let err = decode_value_with(&bytes, 0, &options(false, &registry, None, None), &mut Vec::new()).unwrap_err();
+    assert!(matches!(err, ConvertError::Unsupported { offset: 1, what: UnsupportedKind::MapKey, .. }), "{:?}", err);
+
+    // Cut short inside the map, pairs fail where plain decoding would
+    assert!(matches!(decode_value_with(&bytes[..8], 0, &options(false, &registry, None, pairs), &mut Vec::new()), Err(ConvertError::MsgpackDecode { .. })));
+    // Not quite pairs, so a map with the one key "$map"
+    let other = serde_json::json!({"$map": [[1, 2, 3]]});
+    assert_eq!(rmp_serde::to_vec(&WithExtTypes::new(&other, &registry).key_form(pairs)).unwrap(), rmp_serde::to_vec(&other).unwrap());
+}
+
+#[test]
+fn test_maps_turn_into_pairs_at_the_first_other_key() {
+    // {"a": bin "\xff", "b": {"c": 1}, 2: "x"}
+    let bytes = [0x83, 0xa1, b'a', 0xc4, 0x01, 0xff, 0xa1, b'b', 0x81, 0xa1, b'c', 0x01, 0x02, 0xa1, b'x'];
+    let mut warnings = Vec::new();
+    let (value, _) = decode_value_with(&bytes, 0, &options(true, &ExtRegistry::default(), None, Some(KeyForm::Pairs)), &mut warnings).unwrap();
+    assert_eq!(value, serde_json::json!({"$map": [["a", "/w=="], ["b", {"c": 1}], [2, "x"]]}));
+    // The entries decoded before the key 2 moved along with what was said about them
+    assert_eq!(warnings.iter().map(|warning| warning.path.as_deref()).collect::<Vec<_>>(), [Some("/$map/0/1")]);
+
+    let (_, _, spans) = decode_with_spans_until(&bytes, &options(true, &ExtRegistry::default(), Some(BinForm::Hex), Some(KeyForm::Pairs)), &AtomicBool::new(false), &AtomicUsize::new(0), &mut Vec::new()).unwrap();
+    let expected = [("", 0..15), ("/$map/0/0", 1..3), ("/$map/0/1", 3..6), ("/$map/1/0", 6..8), ("/$map/1/1", 8..12), ("/$map/1/1/c", 11..12), ("/$map/2/0", 12..13), ("/$map/2/1", 13..15)];
+    assert_eq!(spans, expected.into_iter().map(|(path, span)| (path.to_string(), span)).collect::<SpanMap>());
+}
+
+#[test]
+fn test_timestamps_decode_to_date_times_or_numbers() {
+    use crate::timestamp::TimestampForm;
+    // [timestamp 32 of 1714559400, timestamp 64 with 250 ms more, ext -1 of one byte]
+    let bytes = [&[0x93, 0xd6, 0xff][..], &1_714_559_400_u32.to_be_bytes(), &[0xd7, 0xff], &((250_000_000_u64 << 34) | 1_714_559_400).to_be_bytes(), &[0xd4, 0xff, 0x00]].concat();
+    let (value, _) = decode_value_with(&bytes, 0, &options(true, &ExtRegistry::default(), None, None), &mut Vec::new()).unwrap();
+    assert_eq!(value, serde_json::json!(["2024-05-01T10:30:00Z", "2024-05-01T10:30:00.25Z", "AA=="]));
+    let mut seconds = ExtRegistry::default();
+    seconds.timestamp_form = TimestampForm::Seconds;
+    let (value, _) = decode_value_with(&bytes, 0, &options(true, &seconds, None, None), &mut Vec::new()).unwrap();
+    assert_eq!(value, serde_json::json!([1_714_559_400, 1_714_559_400.25, "AA=="]));
+    // A payload that isn't a timestamp fails like any other ext value
+    let err = decode_value_with(&bytes, 0, &options(false, &ExtRegistry::default(), None, None), &mut Vec::new()).unwrap_err();
+    assert!(matches!(err, ConvertError::Unsupported { offset: 17, what: UnsupportedKind::Extension(-1), .. }), "{:?}", err);
+    // A decoder registered for -1 comes first
+    let registered = ExtRegistry::parse("-1 = \"base64\"").unwrap();
+    assert_eq!(decode_value_with(&bytes[1..7], 0, &options(false, &registered, None, None), &mut Vec::new()).unwrap().0, serde_json::json!({"$ext": -1, "value": "ZjIZqA=="}));
+
+    // And back to the same bytes with date-times encoded as timestamps
+    let mut encoding = ExtRegistry::default();
+    encoding.timestamps = true;
+    let json = serde_json::json!(["2024-05-01T10:30:00Z", "2024-05-01T10:30:00.25Z"]);
+    let encoded = rmp_serde::to_vec(&crate::ext_types::WithExtTypes::new(&json, &encoding)).unwrap();
+    assert_eq!([&[0x92][..], &bytes[1..17]].concat(), encoded);
+}
+
+#[test]
+fn test_unknown_ext_types_round_trip_tagged() {
+    let mut tagged = ExtRegistry::parse("2 = \"string\"").unwrap();
+    tagged.tag_unknown = true;
+    // [ext 2 "hi", ext 5 "a", fixext 16 of type 100, ext 8 of -3 with 20 bytes, ext -1 of one byte]
+    let bytes = [&[0x95, 0xd5, 0x02, b'h', b'i', 0xd4, 0x05, b'a', 0xd8, 0x64][..], &[0xab; 16], &[0xc7, 0x14, 0xfd], &[0x01; 20], &[0xd4, 0xff, 0x00]].concat();
+    let (value, _) = decode_value_with(&bytes, 0, &options(false, &tagged, None, None), &mut Vec::new()).unwrap();
+    assert_eq!(value, serde_json::json!([
+        {"$ext": 2, "value": "hi"},
+        {"$ext": 5, "value": "YQ=="},
+        {"$ext": 100, "value": "q6urq6urq6urq6urq6urqw=="},
+        {"$ext": -3, "value": "AQEBAQEBAQEBAQEBAQEBAQEBAQE="},
+        {"$ext": -1, "value": "AA=="},
+    ]));
+    let encoded = rmp_serde::to_vec(&crate::ext_types::WithExtTypes::new(&value, &tagged)).unwrap();
+    assert_eq!(encoded, bytes);
+    // Without it they are plain objects again
+    let plain = rmp_serde::to_vec(&crate::ext_types::WithExtTypes::new(&value[1], &ExtRegistry::default())).unwrap();
+    assert_eq!(plain, rmp_serde::to_vec(&value[1]).unwrap());
+    // A timestamp with a whole second of nanoseconds isn't one, so it's kept like any other
+    let invalid = [&[0xd7, 0xff][..], &(1_000_000_000_u64 << 34).to_be_bytes()].concat();
+    let (value, _) = decode_value_with(&invalid, 0, &options(false, &tagged, None, None), &mut Vec::new()).unwrap();
+    assert_eq!(value, serde_json::json!({"$ext": -1, "value": "7msoAAAAAAA="}));
+}
+
+#[test]
+fn test_decode_with_spans_records_every_node() {
+    let (value, spans) = decode_with_spans(&alice_bytes()).unwrap();
+    assert_eq!(value["name"], "Alice");
+    assert_eq!(spans.get(""), Some(&(0..33)));
+    assert_eq!(spans.get("/age"), Some(&(5..6)));
+    assert_eq!(spans.get("/city"), Some(&(11..22)));
+    assert_eq!(spans.get("/name"), Some(&(27..33)));
+    assert_eq!(spans.len(), 4);
+}
+
+#[test]
+fn test_decode_with_spans_nested_paths() {
+    // {"a/b": [1, {"c": nil}]}
+    let bytes = [0x81, 0xa3, b'a', b'/', b'b', 0x92, 0x01, 0x81, 0xa1, b'c', 0xc0];
+    let (_, spans) = decode_with_spans(&bytes).unwrap();
+    assert_eq!(spans.get("/a~1b"), Some(&(5..11)));
+    assert_eq!(spans.get("/a~1b/0"), Some(&(6..7)));
+    assert_eq!(spans.get("/a~1b/1"), Some(&(7..11)));
+    assert_eq!(spans.get("/a~1b/1/c"), Some(&(10..11)));
+
+    assert_eq!(path_at_offset(&spans, 10), Some("/a~1b/1/c"));
+    // The key "c" belongs to the map that holds it
+    assert_eq!(path_at_offset(&spans, 8), Some("/a~1b/1"));
+    assert_eq!(path_at_offset(&spans, 42), None);
+}
+
+#[test]
+fn test_decode_errors_carry_offsets() {
+    // Integer map key
+    let err = decode(&[0x81, 0x01, 0x02]).unwrap_err();
+    assert_eq!(err, ConvertError::Unsupported { offset: 1, path: String::new(), what: UnsupportedKind::MapKey });
+
+    // Truncated string inside an array
+    let err = decode(&[0x92, 0x01, 0xa4, b'a']).unwrap_err();
+    assert!(matches!(err, ConvertError::MsgpackDecode { offset: 2, .. }));
+
+    // {"a": [bin]}
+    let err = decode(&[0x81, 0xa1, b'a', 0x91, 0xc4, 0x00]).unwrap_err();
+    assert_eq!(err, ConvertError::Unsupported { offset: 4, path: "/a/0".to_string(), what: UnsupportedKind::Binary });
+
+    let mut deep = vec![0x91; MAX_DEPTH + 2];
+    deep.push(0xc0);
+    assert_eq!(decode(&deep).unwrap_err(), ConvertError::TooDeep { offset: MAX_DEPTH + 1, depth: MAX_DEPTH });
+}
+
+#[test]
+fn test_decode_stops_once_cancelled() {
+    let value = Value::Array(vec![Value::from(1); 3 * CANCEL_CHECK_INTERVAL]);
+    let bytes = rmp_serde::to_vec(&value).unwrap();
+    let progress = AtomicUsize::new(0);
+    let err = decode_with_spans_until(&bytes, &ConvertOptions::default(), &AtomicBool::new(true), &progress, &mut Vec::new()).unwrap_err();
+    assert_eq!(err, ConvertError::Cancelled);
+    assert!(progress.load(Ordering::Relaxed) < bytes.len());
+
+    assert!(decode_with_spans_until(&bytes, &ConvertOptions::default(), &AtomicBool::new(false), &progress, &mut Vec::new()).is_ok());
+    assert_eq!(progress.load(Ordering::Relaxed), bytes.len());
+}
+
+#[test]
+fn test_lossy_decoding_keeps_going() {
+    // {1: bin "ab", "c": ext -1 "ab", "d": invalid UTF-8} followed by a second value
+    let bytes = [0x83, 0x01, 0xc4, 0x02, b'a', b'b', 0xa1, b'c', 0xd5, 0xff, b'a', b'b', 0xa1, b'd', 0xa1, 0xff, 0xc0];
+    assert!(decode_value_at(&bytes, 0, false).is_err());
+    let (value, end) = decode_value_at(&bytes, 0, true).unwrap();
+    assert_eq!(value, serde_json::json!({"1": "YWI=", "c": "YWI=", "d": "\u{fffd}"}));
+    assert_eq!(end, bytes.len() - 1);
+    assert_eq!(decode_value_at(&bytes, end, false).unwrap(), (Value::Null, bytes.len()));
+}
@@ -0,0 +1,105 @@
+use crate::cbor::is_cbor;
+use crate::compress::Compression;
+use crate::files::hex_digits;
+use crate::locale::tr;
+use crate::msgpack::walk;
+use base64::{engine::general_purpose, Engine};
+
+// What a pasted text is, for the smart input mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputKind {
+    Json,
+    Hex,
+    Base64,
+}
+
+// Text that is valid as more than one kind is taken as the first of them in this order. Hex
+// goes before base64 because most hex strings are also valid base64, but rarely the other way
+// round.
+pub const PREFERENCE: [InputKind; 3] = [InputKind::Json, InputKind::Hex, InputKind::Base64];
+
+impl InputKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            InputKind::Json => "JSON",
+            InputKind::Hex => tr("Hex MessagePack"),
+            InputKind::Base64 => tr("Base64 MessagePack"),
+        }
+    }
+}
+
+// Every kind the text could be, in preference order, so the first one is the detected kind. JSON is recognized by its first character
+// even when it doesn't parse yet, so half-typed JSON still shows JSON errors; bare scalars only
+// count when they parse. Hex and base64 only count when they decode to valid MessagePack or CBOR.
+pub fn candidates(text: &str) -> Vec<InputKind> {
+    let text = text.trim();
+    PREFERENCE
+        .into_iter()
+        .filter(|kind| match kind {
+            InputKind::Json => looks_like_json(text),
+            InputKind::Hex | InputKind::Base64 => messagepack_bytes(text, *kind).is_some(),
+        })
+        .collect()
+}
+
+// The MessagePack or CBOR bytes behind hex or base64 text, if it is that and they are well-formed
+pub fn messagepack_bytes(text: &str, kind: InputKind) -> Option<Vec<u8>> {
+    let text = text.trim();
+    let bytes = match kind {
+        InputKind::Json => return None,
+        InputKind::Hex => hex::decode(hex_digits(text)?).ok()?,
+        InputKind::Base64 => general_purpose::STANDARD.decode(text).ok()?,
+    };
+    is_well_formed(&bytes).then_some(bytes)
+}
+
+// Whether `bytes` are MessagePack or CBOR all the way through, or compressed
+pub fn is_well_formed(bytes: &[u8]) -> bool {
+    !bytes.is_empty() && (walk(bytes, |_, _| {}).is_ok() || is_cbor(bytes) || Compression::detect(bytes).is_some())
+}
+
+fn looks_like_json(text: &str) -> bool {
+    match text.chars().next() {
+        Some('{' | '[' | '"') => true,
+        Some(_) => serde_json::from_str::<serde_json::Value>(text).is_ok(),
+        None => false,
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_detect_input_kind() {
+    use InputKind::*;
+    let cases: &[(&str, Option<InputKind>, &[InputKind])] = &[
+        (r#"{"name": "Alice"}"#, Some(Json), &[Json]),
+        ("  [1, 2, 3]\n", Some(Json), &[Json]),
+        (r#""just a string""#, Some(Json), &[Json]),
+        // Still JSON while it's being typed
+        (r#"{"name": "#, Some(Json), &[Json]),
+        ("true", Some(Json), &[Json]),
+        ("83a36167651ea463697479aa576f6e6465726c616e64a46e616d65a5416c696365", Some(Hex), &[Hex]),
+        ("g6NhZ2UepGNpdHmqV29uZGVybGFuZKRuYW1lpUFsaWNl", Some(Base64), &[Base64]),
+        // A number that is also two positive fixints in hex
+        ("1234", Some(Json), &[Json, Hex]),
+        // Valid hex and valid base64, both well-formed MessagePack
+        ("c0c0c0c0", Some(Hex), &[Hex, Base64]),
+        // Hex of a map cut short is not MessagePack
+        ("83a36167", None, &[]),
+        ("hello world", None, &[]),
+        ("", None, &[]),
+    ];
+    for (text, detected, all) in cases {
+        assert_eq!(candidates(text).first(), detected.as_ref(), "{:?}", text);
+        assert_eq!(candidates(text), *all, "{:?}", text);
+    }
+}
+
+#[test]
+fn test_messagepack_bytes() {
+    assert_eq!(messagepack_bytes(" c0\n", InputKind::Hex), Some(vec![0xc0]));
+    assert_eq!(messagepack_bytes("wA==", InputKind::Base64), Some(vec![0xc0]));
+    assert_eq!(messagepack_bytes("{}", InputKind::Json), None);
+    assert_eq!(messagepack_bytes("c1", InputKind::Hex), None);
+    assert_eq!(messagepack_bytes("0x92, 0x01,\n0xc0", InputKind::Hex), Some(vec![0x92, 0x01, 0xc0]));
+}
@@ -0,0 +1,374 @@
+use crate::locale::{tr, trf};
+use crate::schema_check::escape_pointer_token;
+use serde_json::{Number, Value};
+
+// Above this many element pairs arrays are compared index by index instead of aligned
+const MAX_ALIGNMENT_CELLS: usize = 1_000_000;
+
+// What counts as a difference. The defaults compare values exactly.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DiffOptions {
+    // Numbers at most this far apart are equal, an integer and a float of the same value too.
+    // None compares them exactly and flags an integer against an equal float.
+    pub tolerance: Option<f64>,
+    // A key that's null on one side may be missing on the other
+    pub null_is_missing: bool,
+    // Arrays holding the same items in another order are equal
+    pub ignore_array_order: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+    // Equal numbers, one encoded as an integer and the other as a float
+    NumberType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    // JSON Pointer into the left document, or into the right one for additions
+    pub path: String,
+    pub kind: ChangeKind,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+impl Change {
+    // One line for the diff list, e.g. "~ /user/name: "Ann" → "Anne""
+    pub fn describe(&self) -> String {
+        let path = if self.path.is_empty() { tr("(root)") } else { self.path.as_str() };
+        match (self.kind, &self.left, &self.right) {
+            (ChangeKind::Added, _, Some(right)) => format!("+ {}: {}", path, preview(right)),
+            (ChangeKind::Removed, Some(left), _) => format!("- {}: {}", path, preview(left)),
+            (ChangeKind::NumberType, Some(left), Some(right)) => {
+                trf("~ {}: {} → {} (integer vs float)", &[&path, &preview(left), &preview(right)])
+            }
+            (_, left, right) => format!(
+                "~ {}: {} → {}",
+                path,
+                left.as_ref().map(preview).unwrap_or_default(),
+                right.as_ref().map(preview).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+// Compact JSON, cut short so one huge value doesn't swamp the list
+fn preview(value: &Value) -> String {
+    const MAX_CHARS: usize = 120;
+    let text = value.to_string();
+    match text.char_indices().nth(MAX_CHARS) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text,
+    }
+}
+
+pub fn diff(left: &Value, right: &Value) -> Vec<Change> {
+    diff_with(left, right, &DiffOptions::default())
+}
+
+pub fn diff_with(left: &Value, right: &Value, options: &DiffOptions) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_at(&mut String::new(), left, right, options, &mut changes);
+    changes
+}
+
+// Whether `diff_with` would find no changes
+pub fn equal(left: &Value, right: &Value, options: &DiffOptions) -> bool {
+    let absent = |value: &Value| options.null_is_missing && value.is_null();
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => numbers_equal(a, b, options),
+        (Value::Object(left_map), Value::Object(right_map)) => {
+            left_map.iter().all(|(key, left_value)| match right_map.get(key) {
+                Some(right_value) => equal(left_value, right_value, options),
+                None => absent(left_value),
+            }) && right_map.iter().all(|(key, right_value)| left_map.contains_key(key) || absent(right_value))
+        }
+        (Value::Array(left_items), Value::Array(right_items)) if options.ignore_array_order => {
+            let (left_over, right_over) = unmatched(left_items, right_items, options);
+            left_over.is_empty() && right_over.is_empty()
+        }
+        (Value::Array(left_items), Value::Array(right_items)) => {
+            left_items.len() == right_items.len() && left_items.iter().zip(right_items).all(|(a, b)| equal(a, b, options))
+        }
+        _ => left == right,
+    }
+}
+
+fn numbers_equal(a: &Number, b: &Number, options: &DiffOptions) -> bool {
+    match (options.tolerance, a.as_f64(), b.as_f64()) {
+        // Integers too large for a float to hold exactly are only equal to themselves
+        (Some(_), _, _) if !a.is_f64() && !b.is_f64() => a == b,
+        (Some(tolerance), Some(a), Some(b)) => (a - b).abs() <= tolerance,
+        _ => a == b,
+    }
+}
+
+fn diff_at(path: &mut String, left: &Value, right: &Value, options: &DiffOptions, changes: &mut Vec<Change>) {
+    let absent = |value: &Value| options.null_is_missing && value.is_null();
+    match (left, right) {
+        _ if equal(left, right, options) => {}
+        (Value::Object(left_map), Value::Object(right_map)) => {
+            for (key, left_value) in left_map {
+                with_child(path, key, |path| match right_map.get(key) {
+                    Some(right_value) => diff_at(path, left_value, right_value, options, changes),
+                    None if absent(left_value) => {}
+                    None => changes.push(removed(path, left_value)),
+                });
+            }
+            for (key, right_value) in right_map.iter().filter(|(key, value)| !left_map.contains_key(*key) && !absent(value)) {
+                with_child(path, key, |path| changes.push(added(path, right_value)));
+            }
+        }
+        (Value::Array(left_items), Value::Array(right_items)) if options.ignore_array_order => {
+            diff_unordered(path, left_items, right_items, options, changes);
+        }
+        (Value::Array(left_items), Value::Array(right_items)) => diff_arrays(path, left_items, right_items, options, changes),
+        (Value::Number(a), Value::Number(b)) if options.tolerance.is_none() && a.as_f64() == b.as_f64() && a.is_f64() != b.is_f64() => {
+            changes.push(change(path, ChangeKind::NumberType, left, right));
+        }
+        _ => changes.push(change(path, ChangeKind::Changed, left, right)),
+    }
+}
+
+// Items equal on both sides anchor the comparison, so an insertion shows up as one added item
+// rather than every later item being reported as changed. Runs between anchors are compared
+// pairwise and the remainder is added or removed.
+fn diff_arrays(path: &mut String, left: &[Value], right: &[Value], options: &DiffOptions, changes: &mut Vec<Change>) {
+    let anchors = if left.len().saturating_mul(right.len()) <= MAX_ALIGNMENT_CELLS {
+        common_subsequence(left, right, options)
+    } else {
+        Vec::new()
+    };
+
+    let (mut i, mut j) = (0, 0);
+    for (anchor_i, anchor_j) in anchors.into_iter().chain(std::iter::once((left.len(), right.len()))) {
+        while i < anchor_i && j < anchor_j {
+            with_child(path, &i.to_string(), |path| diff_at(path, &left[i], &right[j], options, changes));
+            i += 1;
+            j += 1;
+        }
+        for (index, item) in left.iter().enumerate().take(anchor_i).skip(i) {
+            with_child(path, &index.to_string(), |path| changes.push(removed(path, item)));
+        }
+        for (index, item) in right.iter().enumerate().take(anchor_j).skip(j) {
+            with_child(path, &index.to_string(), |path| changes.push(added(path, item)));
+        }
+        i = anchor_i + 1;
+        j = anchor_j + 1;
+    }
+}
+
+// Items without an equal one anywhere on the other side are compared pairwise in the order they
+// come, the remainder is added or removed
+fn diff_unordered(path: &mut String, left: &[Value], right: &[Value], options: &DiffOptions, changes: &mut Vec<Change>) {
+    let (left_over, right_over) = unmatched(left, right, options);
+    for pair in 0..left_over.len().max(right_over.len()) {
+        match (left_over.get(pair), right_over.get(pair)) {
+            (Some(&i), Some(&j)) => with_child(path, &i.to_string(), |path| diff_at(path, &left[i], &right[j], options, changes)),
+            (Some(&i), None) => with_child(path, &i.to_string(), |path| changes.push(removed(path, &left[i]))),
+            (None, Some(&j)) => with_child(path, &j.to_string(), |path| changes.push(added(path, &right[j]))),
+            (None, None) => {}
+        }
+    }
+}
+
+// Indices of the items on either side left over once each item took the first equal one on the
+// other side that wasn't taken yet
+fn unmatched(left: &[Value], right: &[Value], options: &DiffOptions) -> (Vec<usize>, Vec<usize>) {
+    let mut taken = vec![false; right.len()];
+    let left_over = (0..left.len()).filter(|&i| {
+        match (0..right.len()).find(|&j| !taken[j] && equal(&left[i], &right[j], options)) {
+            Some(j) => {
+                taken[j] = true;
+                false
+            }
+            None => true,
+        }
+    }).collect();
+    let right_over = (0..right.len()).filter(|&j| !taken[j]).collect();
+    (left_over, right_over)
+}
+
+// Index pairs of a longest common subsequence of equal items
+fn common_subsequence(left: &[Value], right: &[Value], options: &DiffOptions) -> Vec<(usize, usize)> {
+    let width = right.len() + 1;
+    // lengths[i * width + j]: LCS length of left[i..] and right[j..]
+    let mut lengths = vec![0usize; (left.len() + 1) * width];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            lengths[i * width + j] = if equal(&left[i], &right[j], options) {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        if equal(&left[i], &right[j], options) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+fn with_child<T>(path: &mut String, token: &str, f: impl FnOnce(&mut String) -> T) -> T {
+    let parent_len = path.len();
+    path.push('/');
+    path.push_str(&escape_pointer_token(token));
+    let result = f(path);
+    path.truncate(parent_len);
+    result
+}
+
+fn added(path: &str, value: &Value) -> Change {
+    Change { path: path.to_string(), kind: ChangeKind::Added, left: None, right: Some(value.clone()) }
+}
+
+fn removed(path: &str, value: &Value) -> Change {
+    Change { path: path.to_string(), kind: ChangeKind::Removed, left: Some(value.clone()), right: None }
+}
+
+fn change(path: &str, kind: ChangeKind, left: &Value, right: &Value) -> Change {
+    Change { path: path.to_string(), kind, left: Some(left.clone()), right: Some(right.clone()) }
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn json(text: &str) -> Value {
+    serde_json::from_str(text).unwrap()
+}
+
+#[cfg(test)]
+fn summary(changes: &[Change]) -> Vec<(&str, ChangeKind)> {
+    changes.iter().map(|change| (change.path.as_str(), change.kind)).collect()
+}
+
+#[test]
+fn test_diff_identical_documents() {
+    let value = json(r#"{"a": [1, {"b": null}], "c": "x"}"#);
+    assert!(diff(&value, &value).is_empty());
+    // Key order is not a difference
+    assert!(diff(&json(r#"{"a": 1, "b": 2}"#), &json(r#"{"b": 2, "a": 1}"#)).is_empty());
+}
+
+#[test]
+fn test_diff_object_keys() {
+    let changes = diff(&json(r#"{"a": 1, "b": 2}"#), &json(r#"{"b": 3, "c": 4}"#));
+    assert_eq!(summary(&changes), vec![
+        ("/a", ChangeKind::Removed),
+        ("/b", ChangeKind::Changed),
+        ("/c", ChangeKind::Added),
+    ]);
+    assert_eq!(changes[1].left, Some(json("2")));
+    assert_eq!(changes[1].right, Some(json("3")));
+    assert_eq!(changes[2].left, None);
+}
+
+#[test]
+fn test_diff_nested_changes_and_escaped_paths() {
+    let changes = diff(
+        &json(r#"{"user": {"name": "Ann", "tags/x": ["a"]}}"#),
+        &json(r#"{"user": {"name": "Anne", "tags/x": ["a", "b"]}}"#),
+    );
+    assert_eq!(summary(&changes), vec![
+        ("/user/name", ChangeKind::Changed),
+        ("/user/tags~1x/1", ChangeKind::Added),
+    ]);
+}
+
+#[test]
+fn test_diff_array_insertion_and_removal() {
+    let changes = diff(&json("[1, 2, 3, 4]"), &json("[1, 9, 2, 3, 4]"));
+    assert_eq!(summary(&changes), vec![("/1", ChangeKind::Added)]);
+    assert_eq!(changes[0].right, Some(json("9")));
+
+    let changes = diff(&json("[1, 2, 3, 4]"), &json("[1, 3]"));
+    assert_eq!(summary(&changes), vec![("/1", ChangeKind::Removed), ("/3", ChangeKind::Removed)]);
+}
+
+#[test]
+fn test_diff_array_items_changed_in_place() {
+    let changes = diff(
+        &json(r#"[{"id": 1, "v": "a"}, {"id": 2}, "end"]"#),
+        &json(r#"[{"id": 1, "v": "b"}, {"id": 2}, "end"]"#),
+    );
+    assert_eq!(summary(&changes), vec![("/0/v", ChangeKind::Changed)]);
+}
+
+#[test]
+fn test_diff_type_changes() {
+    let changes = diff(&json(r#"{"a": [1], "b": "1", "c": null}"#), &json(r#"{"a": {"0": 1}, "b": 1, "c": false}"#));
+    assert_eq!(summary(&changes), vec![
+        ("/a", ChangeKind::Changed),
+        ("/b", ChangeKind::Changed),
+        ("/c", ChangeKind::Changed),
+    ]);
+}
+
+#[test]
+fn test_diff_flags_integer_float_mismatch() {
+    let changes = diff(&json(r#"{"n": 1, "m": 2.5, "k": -3}"#), &json(r#"{"n": 1.0, "m": 2.5, "k": -3.5}"#));
+    assert_eq!(summary(&changes), vec![("/n", ChangeKind::NumberType), ("/k", ChangeKind::Changed)]);
+}
+
+#[test]
+fn test_describe_change() {
+    let changes = diff(&json(r#"{"a": 1, "b": "x", "n": 2}"#), &json(r#"{"b": "y", "c": [true], "n": 2.0}"#));
+    let lines: Vec<String> = changes.iter().map(Change::describe).collect();
+    assert_eq!(lines, vec![
+        "- /a: 1",
+        "~ /b: \"x\" → \"y\"",
+        "~ /n: 2 → 2.0 (integer vs float)",
+        "+ /c: [true]",
+    ]);
+    let long = diff(&json("1"), &Value::String("a".repeat(500)))[0].describe();
+    assert!(long.starts_with("~ (root): 1 → \"aaa"));
+    assert!(long.ends_with('…'));
+}
+
+#[test]
+fn test_diff_root_change() {
+    assert_eq!(summary(&diff(&json("1"), &json("2"))), vec![("", ChangeKind::Changed)]);
+}
+
+#[test]
+fn test_diff_with_float_tolerance() {
+    let tolerant = DiffOptions { tolerance: Some(0.01), ..Default::default() };
+    let (expected, actual) = (json(r#"{"t": 20.5, "n": 1, "big": 18446744073709551615}"#), json(r#"{"t": 20.505, "n": 1.0, "big": 18446744073709551614}"#));
+    assert_eq!(summary(&diff_with(&expected, &actual, &tolerant)), vec![("/big", ChangeKind::Changed)]);
+    assert_eq!(summary(&diff(&expected, &actual)), vec![("/t", ChangeKind::Changed), ("/n", ChangeKind::NumberType), ("/big", ChangeKind::Changed)]);
+    assert!(!equal(&json("20.5"), &json("20.52"), &tolerant));
+    assert!(equal(&json("[1.0, 2]"), &json("[1, 2.001]"), &tolerant));
+}
+
+#[test]
+fn test_diff_array_order_and_missing_nulls() {
+    let (expected, actual) = (json(r#"[{"id": 1}, {"id": 2}, 3]"#), json(r#"[3, {"id": 2}, {"id": 1}]"#));
+    assert!(!diff(&expected, &actual).is_empty());
+    let unordered = DiffOptions { ignore_array_order: true, ..Default::default() };
+    assert!(diff_with(&expected, &actual, &unordered).is_empty());
+    // What's left over on both sides is compared in order
+    let changes = diff_with(&json(r#"[1, {"id": 1}, 2]"#), &json(r#"[2, 1, {"id": 9}, 4]"#), &unordered);
+    assert_eq!(summary(&changes), vec![("/1/id", ChangeKind::Changed), ("/3", ChangeKind::Added)]);
+    assert!(!equal(&json("[1, 1, 2]"), &json("[1, 2, 2]"), &unordered));
+
+    let (expected, actual) = (json(r#"{"a": 1, "b": null}"#), json(r#"{"a": 1, "c": null}"#));
+    assert_eq!(summary(&diff(&expected, &actual)), vec![("/b", ChangeKind::Removed), ("/c", ChangeKind::Added)]);
+    let lenient = DiffOptions { null_is_missing: true, ..Default::default() };
+    assert!(diff_with(&expected, &actual, &lenient).is_empty());
+    assert!(equal(&expected, &actual, &lenient));
+    assert_eq!(summary(&diff_with(&json(r#"{"a": null}"#), &json(r#"{"a": 0}"#), &lenient)), vec![("/a", ChangeKind::Changed)]);
+}
@@ -0,0 +1,206 @@
+use crate::locale::tr;
+use eframe::egui;
+use std::ops::Range;
+
+pub struct EditorOptions<'a> {
+    pub height: f32,
+    pub font: egui::TextStyle,
+    pub wrap: bool,
+    pub line_numbers: bool,
+    pub highlights: Option<Highlights<'a>>,
+}
+
+// Byte ranges painted with a background, e.g. find matches
+pub struct Highlights<'a> {
+    pub ranges: &'a [Range<usize>],
+    pub active: Option<usize>,
+    pub scroll_to_active: bool,
+    // Painted fainter, for marks that stay on while reading such as lines changed since the last
+    // conversion
+    pub subtle: bool,
+}
+
+pub struct PaneHeader<T> {
+    // Whatever the pane-specific controls reported, e.g. which of its buttons was clicked
+    pub controls: T,
+    pub cleared: bool,
+}
+
+pub struct LabeledEditor<T> {
+    pub header: PaneHeader<T>,
+    pub response: egui::Response,
+}
+
+// Title row of a pane: its label, any pane-specific controls and a Clear button at the far end
+pub fn pane_header<T>(ui: &mut egui::Ui, title: &str, controls: impl FnOnce(&mut egui::Ui) -> T) -> PaneHeader<T> {
+    ui.horizontal(|ui| {
+        ui.label(title);
+        let controls = controls(ui);
+        let cleared = ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.small_button(tr("Clear")).on_hover_text(tr("Clear this pane")).clicked()
+        }).inner;
+        PaneHeader { controls, cleared }
+    }).inner
+}
+
+// A pane header directly followed by its editor
+pub fn labeled_editor<T>(
+    ui: &mut egui::Ui,
+    id: &str,
+    title: &str,
+    text: &mut dyn egui::TextBuffer,
+    options: &EditorOptions,
+    controls: impl FnOnce(&mut egui::Ui) -> T,
+) -> LabeledEditor<T> {
+    let header = pane_header(ui, title, controls);
+    let response = text_editor(ui, id, text, options);
+    LabeledEditor { header, response }
+}
+
+// Shared builder for the four panes so they all pick up the same font and wrapping preferences
+// Pass a `&mut &str` for a read-only view: it can still be selected and copied from
+pub fn text_editor(ui: &mut egui::Ui, id: &str, text: &mut dyn egui::TextBuffer, options: &EditorOptions) -> egui::Response {
+    let font_id = options.font.resolve(ui.style());
+    let text_color = ui.visuals().override_text_color
+        .unwrap_or_else(|| ui.visuals().widgets.inactive.text_color());
+    let wrap = options.wrap;
+    let highlight_color = ui.visuals().selection.bg_fill.gamma_multiply(highlight_strength(&options.highlights));
+    let active_color = ui.visuals().warn_fg_color.gamma_multiply(0.5);
+
+    let gutter_padding = ui.spacing().item_spacing.x;
+    let gutter_width = if options.line_numbers {
+        let digits = gutter_digits(text.as_str().lines().count());
+        let digit_width = ui.fonts(|f| f.glyph_width(&font_id, '0'));
+        digits as f32 * digit_width + 2.0 * gutter_padding
+    } else {
+        0.0
+    };
+    let width = ui.available_width() - gutter_width;
+
+    // Without wrapping every line is laid out at full length and the pane scrolls horizontally instead
+    let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+        let mut job = egui::text::LayoutJob::default();
+        job.wrap.max_width = if wrap { wrap_width } else { f32::INFINITY };
+        let format = |background| egui::TextFormat {
+            font_id: font_id.clone(),
+            color: text_color,
+            background,
+            ..Default::default()
+        };
+
+        let mut position = 0;
+        if let Some(highlights) = &options.highlights {
+            for (index, range) in highlights.ranges.iter().enumerate() {
+                // The text may have been edited since the ranges were computed
+                if range.start < position || range.end > text.len()
+                    || !text.is_char_boundary(range.start) || !text.is_char_boundary(range.end) {
+                    continue;
+                }
+                let background = if highlights.active == Some(index) { active_color } else { highlight_color };
+                job.append(&text[position..range.start], 0.0, format(egui::Color32::TRANSPARENT));
+                job.append(&text[range.clone()], 0.0, format(background));
+                position = range.end;
+            }
+        }
+        job.append(&text[position..], 0.0, format(egui::Color32::TRANSPARENT));
+        ui.fonts(|f| f.layout_job(job))
+    };
+
+    ui.push_id(id, |ui| {
+        egui::ScrollArea::new([!wrap, true])
+            .min_scrolled_height(options.height)
+            .max_height(options.height)
+            .show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    let (gutter_rect, _) = ui.allocate_exact_size(egui::vec2(gutter_width, options.height), egui::Sense::hover());
+
+                    let output = egui::TextEdit::multiline(text)
+                        .font(options.font.clone())
+                        .frame(true)
+                        .desired_width(width)
+                        .min_size(egui::vec2(0.0, options.height))
+                        .cursor_at_end(false)
+                        .layouter(&mut layouter)
+                        .show(ui);
+
+                    if let Some(highlights) = options.highlights.as_ref().filter(|h| h.scroll_to_active) {
+                        if let Some(range) = highlights.active.and_then(|i| highlights.ranges.get(i)) {
+                            if let Some(prefix) = text.as_str().get(..range.start) {
+                                let ccursor = egui::text::CCursor::new(prefix.chars().count());
+                                let rect = output.galley.pos_from_ccursor(ccursor).translate(output.galley_pos.to_vec2());
+                                ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                            }
+                        }
+                    }
+
+                    if options.line_numbers {
+                        // The gutter lives inside the scroll area, so it scrolls together with the text
+                        let ends_with_newline: Vec<bool> = output.galley.rows.iter().map(|row| row.ends_with_newline).collect();
+                        let painter = ui.painter();
+                        let gutter_color = ui.visuals().weak_text_color();
+                        for (row_index, line_number) in numbered_rows(&ends_with_newline) {
+                            let row = &output.galley.rows[row_index];
+                            painter.text(
+                                egui::pos2(gutter_rect.right() - gutter_padding, output.galley_pos.y + row.rect.min.y),
+                                egui::Align2::RIGHT_TOP,
+                                line_number.to_string(),
+                                font_id.clone(),
+                                gutter_color,
+                            );
+                        }
+                    }
+
+                    output.response
+                }).inner
+            }).inner
+    }).inner
+}
+
+// How much of the selection color highlights are painted with
+pub fn highlight_strength(highlights: &Option<Highlights>) -> f32 {
+    if highlights.as_ref().is_some_and(|h| h.subtle) { 0.2 } else { 0.5 }
+}
+
+pub fn gutter_digits(line_count: usize) -> usize {
+    line_count.max(1).to_string().len()
+}
+
+// Maps galley rows to logical line numbers. Only the first row of a wrapped line gets a number.
+fn numbered_rows(ends_with_newline: &[bool]) -> Vec<(usize, usize)> {
+    let mut rows = Vec::new();
+    let mut line_number = 1;
+    let mut starts_line = true;
+    for (row_index, &ends_line) in ends_with_newline.iter().enumerate() {
+        if starts_line {
+            rows.push((row_index, line_number));
+            line_number += 1;
+        }
+        starts_line = ends_line;
+    }
+    rows
+}
+
+
+/* Tests */
+#[test]
+fn test_numbered_rows_skips_wrapped_rows() {
+    // Line 1 wraps over two rows, line 2 fits on one, line 3 wraps over three rows
+    let ends_with_newline = [false, true, true, false, false, false];
+    assert_eq!(numbered_rows(&ends_with_newline), vec![(0, 1), (2, 2), (3, 3)]);
+}
+
+#[test]
+fn test_numbered_rows_counts_trailing_empty_line() {
+    // "a\n" is laid out as two rows: "a" and the empty row after the newline
+    assert_eq!(numbered_rows(&[true, false]), vec![(0, 1), (1, 2)]);
+    assert_eq!(numbered_rows(&[]), vec![]);
+}
+
+#[test]
+fn test_gutter_digits() {
+    assert_eq!(gutter_digits(0), 1);
+    assert_eq!(gutter_digits(9), 1);
+    assert_eq!(gutter_digits(10), 2);
+    assert_eq!(gutter_digits(12345), 5);
+}
@@ -0,0 +1,220 @@
+use crate::files::MEGABYTE;
+use crate::locale::{tr, trf};
+use crate::msgpack::DecodeError;
+use crate::schema_check::pointer_name;
+use crate::worker::CANCELLED;
+use std::fmt;
+use std::path::PathBuf;
+use thiserror::Error;
+
+// Why a conversion failed. The message is only put together when the error is shown, in the
+// language the UI is in at that point.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ConvertError {
+    // `msg` is serde_json's description without the position, which only speaks English
+    #[error("{}", trf("Failed to parse JSON: {} at line {} column {}", &[.msg, .line, .column]))]
+    JsonParse { line: usize, column: usize, msg: String },
+    #[error("{}", trf("Failed to parse YAML: {} at line {} column {}", &[.msg, .line, .column]))]
+    YamlParse { line: usize, column: usize, msg: String },
+    #[error("{}", trf("Failed to serialize to JSON: {}", &[.0]))]
+    SerializeJson(String),
+    #[error("{}", trf("Failed to serialize to MessagePack: {}", &[.0]))]
+    SerializeMessagePack(String),
+    #[error("{}", trf("Failed to decode Base64: {}", &[.0]))]
+    Base64Decode(base64::DecodeError),
+    #[error("{}", trf("Failed to decode Hex: {}", &[.0]))]
+    HexDecode(hex::FromHexError),
+    // A backslash in escaped bytes that doesn't start \xHH or one of the single letter escapes,
+    // `position` counts characters from the start of the text
+    #[error("{}", trf("Failed to decode escaped bytes: no valid escape at character {}", &[.position]))]
+    EscapeDecode { position: usize },
+    // An item of a byte array literal that isn't a number from 0 to 255
+    #[error("{}", trf("Failed to read the byte array: {} is not a byte", &[.0]))]
+    ByteArray(String),
+    // A line of a hexdump that isn't an offset followed by bytes, counting from 1
+    #[error("{}", trf("Failed to read the hexdump at line {}", &[.line]))]
+    Hexdump { line: usize },
+    #[error("{}", trf("Failed to deserialize MessagePack: {}", &[&at_offset(.msg, *.offset)]))]
+    MsgpackDecode { offset: usize, msg: String },
+    // A value JSON has no counterpart for, `path` is the JSON Pointer of the node holding it
+    #[error("{}", unsupported(*.offset, .path, *.what))]
+    Unsupported { offset: usize, path: String, what: UnsupportedKind },
+    #[error("{}", trf("Failed to deserialize MessagePack: {}", &[&at_offset(&trf("Nesting deeper than {} levels", &[.depth]), *.offset)]))]
+    TooDeep { offset: usize, depth: usize },
+    // Past the depth a conversion's options allow, `path` is the JSON Pointer of the first value
+    // too far down
+    #[error("{}", trf("Nesting deeper than {} levels at {}", &[.limit, &pointer_name(.path)]))]
+    DepthLimit { path: String, limit: usize },
+    // `msg` names the node it happened in, if it isn't the top level. No larger than the other
+    // variants, as the decoders' stack frames grow with the error.
+    #[error("{}", trf("Failed to decode CBOR: {}", &[&at_offset(.msg, *.offset)]))]
+    CborDecode { offset: usize, msg: String },
+    // A gzip or zlib wrapper that's corrupt, or inflates to more than allowed
+    #[error("{}", trf("Failed to decompress: {}", &[.0]))]
+    Decompress(String),
+    // Bytes after the end of the one value that was expected
+    #[error("{}", trf("{} bytes left over after the value", &[.0]))]
+    TrailingBytes(usize),
+    // A length prefix declaring more than is left, `offset` is where the prefix and `start` where
+    // the payload begins
+    #[error("{}", trf("Frame {} at offset {} declares {} bytes from offset {}, but only {} are left", &[.index, &format_args!("{:#x}", .offset), .declared, &format_args!("{:#x}", .start), .available]))]
+    ShortFrame { index: usize, offset: usize, start: usize, declared: usize, available: usize },
+    #[error("{}", trf("Frame {} at offset {} has no complete length prefix", &[.index, &format_args!("{:#x}", .offset)]))]
+    FramePrefix { index: usize, offset: usize },
+    #[error("{}", trf("A record of {} bytes is longer than the length prefix can hold, {} at most", &[.length, .limit]))]
+    FrameTooLong { length: usize, limit: usize },
+    // Reading the input or writing the output, e.g. a closed pipe
+    #[error("{}", trf("Failed to read the input: {}", &[.0]))]
+    Read(String),
+    #[error("{}", trf("Failed to write the output: {}", &[.0]))]
+    Write(String),
+    // Same for a file by name, `msg` is what the file system said
+    #[error("{}", trf("Failed to read {}: {}", &[&.path.display(), .msg]))]
+    ReadFile { path: PathBuf, msg: String },
+    #[error("{}", trf("Failed to write {}: {}", &[&.path.display(), .msg]))]
+    WriteFile { path: PathBuf, msg: String },
+    // A conversion from a file into itself, which would lose the input
+    #[error("{}", trf("{} would be overwritten by its own output", &[&.0.display()]))]
+    SameFile(PathBuf),
+    // Pasted input over the input size limit, `size` from the length of the text
+    #[error("{}", trf("The input is about {} MB, more than the {} MB input size limit. Open it as a binary file or convert it with the command line instead, or raise the limit in Settings.", &[&.size.div_ceil(MEGABYTE), &(.limit / MEGABYTE)]))]
+    InputTooLarge { size: usize, limit: usize },
+    #[error("{}", tr(CANCELLED))]
+    Cancelled,
+    // A job on the worker that ended without a result, which only a panic does
+    #[error("{}", tr("The conversion stopped unexpectedly"))]
+    JobStopped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnsupportedKind {
+    Binary,
+    Extension(i8),
+    // Anything but a string as a map key
+    MapKey,
+}
+
+impl fmt::Display for UnsupportedKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            UnsupportedKind::Binary => tr("Binary values are not supported").to_string(),
+            UnsupportedKind::Extension(ext_type) => trf("Extension type {} is not supported", &[ext_type]),
+            UnsupportedKind::MapKey => tr("Map keys must be strings").to_string(),
+        };
+        f.write_str(&message)
+    }
+}
+
+impl ConvertError {
+    pub fn parse_json(e: &serde_json::Error) -> ConvertError {
+        let text = e.to_string();
+        let position = format!(" at line {} column {}", e.line(), e.column());
+        let msg = text.strip_suffix(&position).unwrap_or(&text).to_string();
+        ConvertError::JsonParse { line: e.line(), column: e.column(), msg }
+    }
+
+    // Name of the variant, for reporting the error to programs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ConvertError::JsonParse { .. } => "JsonParse",
+            ConvertError::YamlParse { .. } => "YamlParse",
+            ConvertError::SerializeJson(_) => "SerializeJson",
+            ConvertError::SerializeMessagePack(_) => "SerializeMessagePack",
+            ConvertError::Base64Decode(_) => "Base64Decode",
+            ConvertError::HexDecode(_) => "HexDecode",
+            ConvertError::EscapeDecode { .. } => "EscapeDecode",
+            ConvertError::ByteArray(_) => "ByteArray",
+            ConvertError::Hexdump { .. } => "Hexdump",
+            ConvertError::MsgpackDecode { .. } => "MsgpackDecode",
+            ConvertError::Unsupported { .. } => "Unsupported",
+            ConvertError::TooDeep { .. } => "TooDeep",
+            ConvertError::DepthLimit { .. } => "DepthLimit",
+            ConvertError::CborDecode { .. } => "CborDecode",
+            ConvertError::Decompress(_) => "Decompress",
+            ConvertError::TrailingBytes(_) => "TrailingBytes",
+            ConvertError::ShortFrame { .. } => "ShortFrame",
+            ConvertError::FramePrefix { .. } => "FramePrefix",
+            ConvertError::FrameTooLong { .. } => "FrameTooLong",
+            ConvertError::Read(_) => "Read",
+            ConvertError::Write(_) => "Write",
+            ConvertError::ReadFile { .. } => "ReadFile",
+            ConvertError::WriteFile { .. } => "WriteFile",
+            ConvertError::SameFile(_) => "SameFile",
+            ConvertError::InputTooLarge { .. } => "InputTooLarge",
+            ConvertError::Cancelled => "Cancelled",
+            ConvertError::JobStopped => "JobStopped",
+        }
+    }
+
+    // Where in the MessagePack input it went wrong, for errors that know
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            ConvertError::MsgpackDecode { offset, .. }
+            | ConvertError::Unsupported { offset, .. }
+            | ConvertError::TooDeep { offset, .. }
+            | ConvertError::CborDecode { offset, .. }
+            | ConvertError::ShortFrame { offset, .. }
+            | ConvertError::FramePrefix { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    // Same error with its offset moved along, for input that was decoded in pieces
+    pub fn shifted(mut self, by: usize) -> ConvertError {
+        if let ConvertError::MsgpackDecode { offset, .. }
+        | ConvertError::Unsupported { offset, .. }
+        | ConvertError::TooDeep { offset, .. }
+        | ConvertError::CborDecode { offset, .. }
+        | ConvertError::FramePrefix { offset, .. } = &mut self
+        {
+            *offset += by;
+        }
+        if let ConvertError::ShortFrame { offset, start, .. } = &mut self {
+            *offset += by;
+            *start += by;
+        }
+        self
+    }
+}
+
+impl From<DecodeError> for ConvertError {
+    fn from(e: DecodeError) -> ConvertError {
+        ConvertError::MsgpackDecode { offset: e.offset, msg: e.message }
+    }
+}
+
+// Most of the UI still passes errors around as text
+impl From<ConvertError> for String {
+    fn from(e: ConvertError) -> String {
+        e.to_string()
+    }
+}
+
+fn at_offset(message: &str, offset: usize) -> String {
+    trf("{} at offset {}", &[&message, &format_args!("{:#x}", offset)])
+}
+
+fn unsupported(offset: usize, path: &str, what: UnsupportedKind) -> String {
+    if path.is_empty() {
+        return trf("Failed to deserialize MessagePack: {}", &[&at_offset(&what.to_string(), offset)]);
+    }
+    trf("Failed to deserialize MessagePack: {} at {}, offset {}", &[&what, &path, &format_args!("{:#x}", offset)])
+}
+
+
+/* Tests */
+#[test]
+fn test_messages_keep_their_details() {
+    let e = serde_json::from_str::<serde_json::Value>("{\"a\": }").unwrap_err();
+    let error = ConvertError::parse_json(&e);
+    assert_eq!(error, ConvertError::JsonParse { line: 1, column: 7, msg: "expected value".to_string() });
+    assert_eq!(error.to_string(), "Failed to parse JSON: expected value at line 1 column 7");
+
+    let error = ConvertError::Unsupported { offset: 5, path: "/a/0".to_string(), what: UnsupportedKind::Binary };
+    assert_eq!(error.to_string(), "Failed to deserialize MessagePack: Binary values are not supported at /a/0, offset 0x5");
+    assert_eq!(error.clone().shifted(16).offset(), Some(21));
+    let error = ConvertError::ReadFile { path: PathBuf::from("in.msgpack"), msg: "No such file or directory".to_string() };
+    assert_eq!(error.to_string(), "Failed to read in.msgpack: No such file or directory");
+    assert_eq!((error.kind(), error.offset()), ("ReadFile", None));
+    assert_eq!(ConvertError::TooDeep { offset: 512, depth: 512 }.to_string(), "Failed to deserialize MessagePack: Nesting deeper than 512 levels at offset 0x200");
+}
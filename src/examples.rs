@@ -0,0 +1,97 @@
+use crate::locale::{tr, trf};
+
+// Ready-made payloads for trying the app out, each with what converting it should do
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExampleInput {
+    // Goes into the JSON Input pane
+    Json(&'static str),
+    // Hex text for the MessagePack Input pane
+    MessagePack(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Converts,
+    // Conversion fails with an error containing this text
+    Fails(&'static str),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Example {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub input: ExampleInput,
+    pub outcome: Outcome,
+}
+
+impl Example {
+    pub fn hover_text(&self) -> String {
+        match self.outcome {
+            Outcome::Converts => tr(self.description).to_string(),
+            Outcome::Fails(_) => trf("{}. Converting it fails on purpose.", &[&tr(self.description)]),
+        }
+    }
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "Flat object",
+        description: "A few scalar fields",
+        input: ExampleInput::Json(r#"{
+  "name": "Alice",
+  "age": 30,
+  "active": true,
+  "score": 97.5,
+  "nickname": null
+}"#),
+        outcome: Outcome::Converts,
+    },
+    Example {
+        name: "Nested structures",
+        description: "Objects inside objects inside arrays",
+        input: ExampleInput::Json(r#"{
+  "order": {
+    "id": "A-1001",
+    "customer": {"name": "Bob", "address": {"city": "Wonderland", "zip": "12345"}},
+    "lines": [
+      {"sku": "tea", "qty": 2, "price": {"amount": 3.5, "currency": "EUR"}},
+      {"sku": "cake", "qty": 1, "price": {"amount": 12, "currency": "EUR"}}
+    ]
+  }
+}"#),
+        outcome: Outcome::Converts,
+    },
+    Example {
+        name: "Array-heavy telemetry",
+        description: "Long numeric arrays, the kind of payload where MessagePack saves the most",
+        input: ExampleInput::Json(r#"{
+  "device": "sensor-7",
+  "readings": [
+    {"t": 0, "temp": [20.5, 20.6, 20.6, 20.7, 20.9], "hum": [41, 41, 42, 42, 43]},
+    {"t": 60, "temp": [21.0, 21.1, 21.1, 21.0, 20.9], "hum": [43, 44, 44, 43, 43]},
+    {"t": 120, "temp": [20.8, 20.8, 20.7, 20.7, 20.6], "hum": [42, 42, 41, 41, 40]}
+  ],
+  "flags": [[true, false], [false, false], [true, true]]
+}"#),
+        outcome: Outcome::Converts,
+    },
+    Example {
+        name: "Large integers",
+        description: "[uint64 max, int64 min], beyond what JavaScript numbers hold exactly",
+        input: ExampleInput::MessagePack("92cfffffffffffffffffd38000000000000000"),
+        outcome: Outcome::Converts,
+    },
+    Example {
+        name: "Binary, ext and timestamp",
+        description: "A bin8 value and a timestamp ext, which have no JSON equivalent",
+        input: ExampleInput::MessagePack("82a362696ec403010203a27473d6ff00000000"),
+        outcome: Outcome::Fails("Binary values are not supported"),
+    },
+    Example {
+        name: "Broken MessagePack",
+        description: "A map cut off in the middle, to show how errors are reported",
+        input: ExampleInput::MessagePack("83a36167651ea463697479aa576f6e"),
+        outcome: Outcome::Fails("Unexpected end of input"),
+    },
+];
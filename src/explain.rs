@@ -0,0 +1,183 @@
+use crate::msgpack::{marker_name, walk, DecodeError, Token, TokenKind};
+use eframe::egui;
+use std::ops::Range;
+
+// Bytes shown per row in the hex column before it is cut off with an ellipsis
+const HEX_PREVIEW_BYTES: usize = 8;
+// Longest string value quoted in a description
+const STRING_PREVIEW_CHARS: usize = 40;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub range: Range<usize>,
+    pub description: String,
+    pub depth: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Explanation {
+    pub rows: Vec<Annotation>,
+    // Where annotating stopped, if the input is corrupt
+    pub error: Option<DecodeError>,
+}
+
+// Walks the encoded bytes marker by marker. Back-to-back top level values are annotated one
+// after another, and corrupt input is annotated up to the failing offset.
+pub fn explain(bytes: &[u8]) -> Explanation {
+    let mut explanation = Explanation::default();
+    let result = walk(bytes, |token, depth| {
+        explanation.rows.push(Annotation {
+            range: token.start..token.end,
+            description: describe(bytes, token),
+            depth,
+        });
+    });
+    explanation.error = result.err();
+    explanation
+}
+
+pub fn describe(bytes: &[u8], token: &Token) -> String {
+    let name = marker_name(token.marker);
+    match &token.kind {
+        TokenKind::Nil | TokenKind::Bool(_) => name.to_string(),
+        TokenKind::Uint(n) => format!("{} {}", name, n),
+        TokenKind::Int(n) => format!("{} {}", name, n),
+        TokenKind::F32(n) => format!("{} {}", name, n),
+        TokenKind::F64(n) => format!("{} {}", name, n),
+        TokenKind::Str(range) => format!("{}({}) {}", name, range.len(), quote(&bytes[range.clone()])),
+        TokenKind::Bin(range) => format!("{}({})", name, range.len()),
+        TokenKind::Ext(ext_type, range) => format!("{}({}) type {}", name, range.len(), ext_type),
+        TokenKind::Array(n) | TokenKind::Map(n) => format!("{}({})", name, n),
+    }
+}
+
+fn quote(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(s) if s.chars().count() > STRING_PREVIEW_CHARS => {
+            let preview: String = s.chars().take(STRING_PREVIEW_CHARS).collect();
+            format!("{}…", serde_json::Value::from(preview))
+        }
+        Ok(s) => serde_json::Value::from(s).to_string(),
+        Err(_) => "<invalid UTF-8>".to_string(),
+    }
+}
+
+pub fn hex_preview(bytes: &[u8]) -> String {
+    let shown = bytes.len().min(HEX_PREVIEW_BYTES);
+    let mut hex: Vec<String> = bytes[..shown].iter().map(|b| format!("{:02x}", b)).collect();
+    if bytes.len() > shown {
+        hex.push("…".to_string());
+    }
+    hex.join(" ")
+}
+
+// One printable line per annotation, e.g. `0x0001  a3 61 67 65  fixstr(3) "age"`
+pub fn format_row(bytes: &[u8], row: &Annotation) -> String {
+    format!(
+        "{:#06x}  {:<width$}  {}{}",
+        row.range.start,
+        hex_preview(&bytes[row.range.clone()]),
+        "  ".repeat(row.depth),
+        row.description,
+        width = HEX_PREVIEW_BYTES * 3 + 1,
+    )
+}
+
+// Rows inside `selected` are highlighted, clicking a row returns its offset
+pub fn show_explanation(
+    ui: &mut egui::Ui,
+    bytes: &[u8],
+    explanation: &Explanation,
+    height: f32,
+    selected: Option<&Range<usize>>,
+    scroll_to_selected: bool,
+) -> Option<usize> {
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    let row_count = explanation.rows.len() + usize::from(explanation.error.is_some());
+    let mut scroll_area = egui::ScrollArea::both()
+        .min_scrolled_height(height)
+        .max_height(height)
+        .auto_shrink([false, false]);
+    if let Some(range) = selected.filter(|_| scroll_to_selected) {
+        let first_row = explanation.rows.iter().position(|row| row.range.start >= range.start).unwrap_or(0);
+        scroll_area = scroll_area.vertical_scroll_offset(first_row as f32 * (row_height + ui.spacing().item_spacing.y));
+    }
+
+    let mut clicked = None;
+    scroll_area.show_rows(ui, row_height, row_count, |ui, visible| {
+        ui.style_mut().wrap = Some(false);
+        for index in visible {
+            match explanation.rows.get(index) {
+                Some(row) => {
+                    let is_selected = selected.is_some_and(|range| range.start <= row.range.start && row.range.end <= range.end);
+                    let text = egui::RichText::new(format_row(bytes, row)).monospace();
+                    if ui.selectable_label(is_selected, text).clicked() {
+                        clicked = Some(row.range.start);
+                    }
+                }
+                None => {
+                    if let Some(e) = &explanation.error {
+                        let text = format!("{:#06x}  {}", e.offset, e.message);
+                        ui.label(egui::RichText::new(text).monospace().color(ui.visuals().error_fg_color));
+                    }
+                }
+            }
+        }
+    });
+    clicked
+}
+
+
+/* Tests */
+#[test]
+fn test_explain_alice_fixture() {
+    let bytes = hex::decode("83a36167651ea463697479aa576f6e6465726c616e64a46e616d65a5416c696365").unwrap();
+    let explanation = explain(&bytes);
+    assert_eq!(explanation.error, None);
+
+    let rows: Vec<(Range<usize>, &str, usize)> = explanation.rows.iter()
+        .map(|row| (row.range.clone(), row.description.as_str(), row.depth))
+        .collect();
+    assert_eq!(rows, vec![
+        (0..1, "fixmap(3)", 0),
+        (1..5, r#"fixstr(3) "age""#, 1),
+        (5..6, "positive fixint 30", 1),
+        (6..11, r#"fixstr(4) "city""#, 1),
+        (11..22, r#"fixstr(10) "Wonderland""#, 1),
+        (22..27, r#"fixstr(4) "name""#, 1),
+        (27..33, r#"fixstr(5) "Alice""#, 1),
+    ]);
+
+    assert_eq!(format_row(&bytes, &explanation.rows[0]), "0x0000  83                         fixmap(3)");
+    assert_eq!(format_row(&bytes, &explanation.rows[1]), r#"0x0001  a3 61 67 65                  fixstr(3) "age""#);
+    assert_eq!(format_row(&bytes, &explanation.rows[4]), r#"0x000b  aa 57 6f 6e 64 65 72 6c …    fixstr(10) "Wonderland""#);
+}
+
+#[test]
+fn test_explain_nested_depths() {
+    // {"a": [1, {"b": nil}]}
+    let bytes = [0x81, 0xa1, b'a', 0x92, 0x01, 0x81, 0xa1, b'b', 0xc0];
+    let depths: Vec<usize> = explain(&bytes).rows.iter().map(|row| row.depth).collect();
+    assert_eq!(depths, vec![0, 1, 1, 2, 2, 3, 3]);
+}
+
+#[test]
+fn test_explain_corrupt_input_annotates_up_to_error() {
+    // fixmap(1) with a key whose string runs past the end of the input
+    let bytes = [0x81, 0xa5, b'a', b'b'];
+    let explanation = explain(&bytes);
+    assert_eq!(explanation.rows.len(), 1);
+    assert_eq!(explanation.error.as_ref().map(|e| e.offset), Some(1));
+
+    // Container that ends before all of its items arrived
+    let explanation = explain(&[0x92, 0x01]);
+    assert_eq!(explanation.rows.len(), 2);
+    assert_eq!(explanation.error.as_ref().map(|e| e.offset), Some(2));
+}
+
+#[test]
+fn test_explain_back_to_back_values() {
+    let explanation = explain(&[0x01, 0x02, 0xc3]);
+    assert_eq!(explanation.rows.len(), 3);
+    assert!(explanation.rows.iter().all(|row| row.depth == 0));
+}
@@ -0,0 +1,720 @@
+use crate::decode::float;
+use crate::locale::{tr, trf};
+use crate::timestamp::{Timestamp, TimestampForm, TIMESTAMP_EXT};
+use base64::{engine::general_purpose, Engine};
+use serde::ser::{Error as _, SerializeMap, SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// Extension types the user told us about. An ext value of a registered type decodes to
+// {"$ext": 7, "value": ...} with its payload rendered by the type's decoder, and an object of
+// exactly that shape is encoded as that ext type again. Types that aren't registered are left
+// to the usual handling, or with tag_unknown on take the same form with a base64 payload.
+pub const EXT_KEY: &str = "$ext";
+pub const EXT_VALUE_KEY: &str = "value";
+
+// Bins in the tagged form are {"$bin": "<base64>"}, which encodes back to a bin
+pub const BIN_KEY: &str = "$bin";
+
+// Maps with keys other than strings as pairs are {"$map": [[key, value], …]}, which encodes back
+// to a map with those keys
+pub const MAP_KEY: &str = "$map";
+
+// One type per line, the way a small TOML file writes them:
+//
+//     # type = "decoder"
+//     2 = "uuid"
+//     7 = "struct lat:f64 lon:f64"
+//
+// Decoders are "base64", "uuid", "string" for UTF-8 text, a number type such as "i128" for a
+// payload that is one big-endian number, or "struct" followed by name:type fields, which read the
+// payload as big-endian primitives one after the other.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExtRegistry {
+    types: Vec<(i8, ExtDecoder)>,
+    // 16-byte bins, and 16-byte ext values of uuid_ext_type, decode to plain UUID strings, and
+    // strings in the UUID pattern encode back to 16 bytes: an ext value of that type when there
+    // is one, a bin otherwise. Off by default, a hash is 16 bytes too.
+    pub uuids: bool,
+    pub uuid_ext_type: Option<i8>,
+    // RFC 3339 date-time strings encode to the timestamp ext type, see Timestamp::parse
+    pub timestamps: bool,
+    // What timestamps decode to, unless a decoder is registered for their type
+    pub timestamp_form: TimestampForm,
+    // Ext values of types without a decoder decode to the tagged form with their payload as
+    // base64, as if base64 were registered for every type, so they make it back unchanged
+    pub tag_unknown: bool,
+}
+
+// What bins decode to, unless they are UUIDs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BinForm {
+    // {"$bin": "<base64>"}, the one form that encodes back to a bin
+    #[default]
+    Tagged,
+    Base64,
+    Hex,
+    // [129, 161, 97], one number per byte
+    Numbers,
+}
+
+// What maps with keys other than strings decode to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum KeyForm {
+    // {"$map": [[1, "a"], ["b", 2]]} for the whole map, in its order, when any of its keys isn't a
+    // string. Maps of only string keys are still objects.
+    #[default]
+    Pairs,
+    // Each such key as its JSON text, so 1 is "1" and [1, 2] is "[1,2]", which doesn't encode back
+    Stringify,
+}
+
+impl KeyForm {
+    pub const ALL: [KeyForm; 2] = [KeyForm::Pairs, KeyForm::Stringify];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyForm::Pairs => tr("Pairs {\"$map\": [[key, value], …]}"),
+            KeyForm::Stringify => tr("Keys as strings"),
+        }
+    }
+}
+
+impl BinForm {
+    pub const ALL: [BinForm; 4] = [BinForm::Tagged, BinForm::Base64, BinForm::Hex, BinForm::Numbers];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BinForm::Tagged => tr("Tagged {\"$bin\": base64}"),
+            BinForm::Base64 => "Base64",
+            BinForm::Hex => "Hex",
+            BinForm::Numbers => tr("Array of numbers"),
+        }
+    }
+
+    pub fn decode(self, data: &[u8]) -> Value {
+        match self {
+            BinForm::Tagged => Value::Object(Map::from_iter([(BIN_KEY.to_string(), Value::String(general_purpose::STANDARD.encode(data)))])),
+            BinForm::Base64 => Value::String(general_purpose::STANDARD.encode(data)),
+            BinForm::Hex => Value::String(hex::encode(data)),
+            BinForm::Numbers => Value::Array(data.iter().map(|&byte| Value::from(byte)).collect()),
+        }
+    }
+}
+
+// The bytes of an object in the tagged bin form
+fn tagged_bin(value: &Value) -> Option<Result<Vec<u8>, String>> {
+    let object = value.as_object().filter(|object| object.len() == 1)?;
+    let text = object.get(BIN_KEY)?.as_str()?;
+    Some(general_purpose::STANDARD.decode(text).map_err(|e| format!("{}: {}", BIN_KEY, e)))
+}
+
+// What tag_unknown reads the types that aren't registered with
+static UNKNOWN_DECODER: ExtDecoder = ExtDecoder::Base64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtDecoder {
+    Base64,
+    Uuid,
+    Utf8,
+    // The payload is exactly one primitive, which decodes to a plain number or bool
+    Primitive(Primitive),
+    Struct(Vec<(String, Primitive)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Primitive {
+    U8,
+    U16,
+    U32,
+    U64,
+    // 128-bit integers decode to numbers where JSON readers take them exactly, within the 64-bit
+    // range, and to decimal strings beyond it
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    Bool,
+}
+
+impl ExtRegistry {
+    // Blank lines and lines starting with "#" are ignored. A type given twice keeps the last one.
+    pub fn parse(text: &str) -> Result<ExtRegistry, String> {
+        let mut types: Vec<(i8, ExtDecoder)> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry = parse_entry(line).map_err(|e| trf("Line {}: {}", &[&(index + 1), &e]))?;
+            types.retain(|(ext_type, _)| *ext_type != entry.0);
+            types.push(entry);
+        }
+        Ok(ExtRegistry { types, ..Default::default() })
+    }
+
+    // The registry text for a config file, which is either in the line format already or a JSON
+    // object like {"2": "uuid"}
+    pub fn from_file(text: &str) -> Result<String, String> {
+        if !text.trim_start().starts_with('{') {
+            ExtRegistry::parse(text)?;
+            return Ok(text.to_string());
+        }
+        let object: Map<String, Value> = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let mut lines = String::new();
+        for (ext_type, decoder) in &object {
+            let Some(decoder) = decoder.as_str() else {
+                return Err(trf("Ext type {}: the decoder is not a string", &[ext_type]));
+            };
+            lines.push_str(&format!("{} = \"{}\"\n", ext_type, decoder));
+        }
+        ExtRegistry::parse(&lines)?;
+        Ok(lines)
+    }
+
+    // `decoder` for `ext_type`, in place of one registered before, for callers that build a
+    // registry in code rather than parse one
+    pub fn with_decoder(mut self, ext_type: i8, decoder: ExtDecoder) -> ExtRegistry {
+        self.types.retain(|(registered, _)| *registered != ext_type);
+        self.types.push((ext_type, decoder));
+        self
+    }
+
+    pub fn with_uuids(self, uuids: bool, uuid_ext_type: Option<i8>) -> ExtRegistry {
+        ExtRegistry { uuids, uuid_ext_type, ..self }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && !self.uuids && !self.timestamps && !self.tag_unknown
+    }
+
+    // A bin payload (no ext type) or ext payload as a plain UUID string, if UUIDs are on for it
+    pub fn uuid(&self, ext_type: Option<i8>, data: &[u8]) -> Option<Value> {
+        let wanted = self.uuids && data.len() == 16 && (ext_type.is_none() || ext_type == self.uuid_ext_type);
+        wanted.then(|| Value::String(format_uuid(data)))
+    }
+
+    // A timestamp ext value in the registry's form, None for other types and for payloads that
+    // aren't a timestamp
+    pub fn timestamp(&self, ext_type: i8, data: &[u8]) -> Option<Value> {
+        if ext_type != TIMESTAMP_EXT || self.decoder(ext_type).is_some() {
+            return None;
+        }
+        Timestamp::decode(data).map(|timestamp| timestamp.to_json(self.timestamp_form))
+    }
+
+    pub fn decoder(&self, ext_type: i8) -> Option<&ExtDecoder> {
+        self.types.iter().find(|(registered, _)| *registered == ext_type).map(|(_, decoder)| decoder)
+    }
+
+    // The decoder of a registered type, or base64 for any other with tag_unknown on
+    fn decoder_or_unknown(&self, ext_type: i8) -> Option<&ExtDecoder> {
+        self.decoder(ext_type).or(self.tag_unknown.then_some(&UNKNOWN_DECODER))
+    }
+
+    // The payload of a registered ext value in the tagged form
+    pub fn decode(&self, ext_type: i8, data: &[u8]) -> Option<Result<Value, String>> {
+        let decoded = self.decoder_or_unknown(ext_type)?.decode(data).map(|value| {
+            let mut tagged = Map::new();
+            tagged.insert(EXT_KEY.to_string(), Value::from(ext_type));
+            tagged.insert(EXT_VALUE_KEY.to_string(), value);
+            Value::Object(tagged)
+        });
+        Some(decoded.map_err(|e| trf("Ext type {}: {}", &[&ext_type, &e])))
+    }
+
+    // The type and payload bytes of an object in the tagged form whose type is registered
+    pub fn encode(&self, value: &Value) -> Option<Result<(i8, Vec<u8>), String>> {
+        let object = value.as_object().filter(|object| object.len() == 2)?;
+        let ext_type = i8::try_from(object.get(EXT_KEY)?.as_i64()?).ok()?;
+        let payload = object.get(EXT_VALUE_KEY)?;
+        let encoded = self.decoder_or_unknown(ext_type)?.encode(payload);
+        Some(encoded.map(|data| (ext_type, data)).map_err(|e| trf("Ext type {}: {}", &[&ext_type, &e])))
+    }
+}
+
+fn parse_entry(line: &str) -> Result<(i8, ExtDecoder), String> {
+    let Some((ext_type, decoder)) = line.split_once('=') else {
+        return Err(tr("expected type = \"decoder\"").to_string());
+    };
+    let ext_type = ext_type.trim();
+    let ext_type = ext_type.parse::<i8>()
+        .map_err(|_| trf("{} is not an ext type, those go from -128 to 127", &[&ext_type]))?;
+    let decoder = decoder.trim();
+    let decoder = decoder.strip_prefix('"').and_then(|d| d.strip_suffix('"')).unwrap_or(decoder);
+    Ok((ext_type, ExtDecoder::parse(decoder)?))
+}
+
+impl ExtDecoder {
+    // A decoder as a line of the registry names it, e.g. "struct lat:f64 lon:f64"
+    pub fn parse(text: &str) -> Result<ExtDecoder, String> {
+        let mut words = text.split_whitespace();
+        let decoder = match words.next().unwrap_or_default() {
+            "base64" => ExtDecoder::Base64,
+            "uuid" => ExtDecoder::Uuid,
+            "string" => ExtDecoder::Utf8,
+            "struct" => {
+                let mut fields = Vec::new();
+                for field in words.by_ref() {
+                    let (name, primitive) = field.split_once(':')
+                        .ok_or_else(|| trf("{}: a struct field is name:type", &[&field]))?;
+                    let primitive = Primitive::parse(primitive)
+                        .ok_or_else(|| trf("{}: unknown field type, use u8-u128, i8-i128, f32, f64 or bool", &[&field]))?;
+                    if fields.iter().any(|(taken, _)| taken == name) {
+                        return Err(trf("the field {} is given twice", &[&name]));
+                    }
+                    fields.push((name.to_string(), primitive));
+                }
+                if fields.is_empty() {
+                    return Err(tr("a struct needs at least one name:type field").to_string());
+                }
+                ExtDecoder::Struct(fields)
+            }
+            other => match Primitive::parse(other) {
+                Some(primitive) => ExtDecoder::Primitive(primitive),
+                None => return Err(trf("unknown decoder {}, use base64, uuid, string, a number type or struct", &[&format_args!("{:?}", other)])),
+            },
+        };
+        match words.next() {
+            Some(extra) => Err(trf("unexpected {} after the decoder", &[&format_args!("{:?}", extra)])),
+            None => Ok(decoder),
+        }
+    }
+
+    pub fn decode(&self, data: &[u8]) -> Result<Value, String> {
+        Ok(match self {
+            ExtDecoder::Base64 => Value::String(general_purpose::STANDARD.encode(data)),
+            ExtDecoder::Uuid => {
+                if data.len() != 16 {
+                    return Err(trf("a UUID is 16 bytes, this payload is {}", &[&data.len()]));
+                }
+                Value::String(format_uuid(data))
+            }
+            ExtDecoder::Utf8 => Value::String(
+                String::from_utf8(data.to_vec()).map_err(|e| trf("Invalid UTF-8 in string: {}", &[&e.utf8_error()]))?,
+            ),
+            ExtDecoder::Primitive(primitive) => {
+                if data.len() != primitive.size() {
+                    return Err(trf("the number takes {} bytes, this payload is {}", &[&primitive.size(), &data.len()]));
+                }
+                primitive.read(data)
+            }
+            ExtDecoder::Struct(fields) => {
+                let size: usize = fields.iter().map(|(_, primitive)| primitive.size()).sum();
+                if data.len() != size {
+                    return Err(trf("the struct fields take {} bytes, this payload is {}", &[&size, &data.len()]));
+                }
+                let mut object = Map::new();
+                let mut offset = 0;
+                for (name, primitive) in fields {
+                    object.insert(name.clone(), primitive.read(&data[offset..offset + primitive.size()]));
+                    offset += primitive.size();
+                }
+                Value::Object(object)
+            }
+        })
+    }
+
+    // The inverse of decode
+    pub fn encode(&self, value: &Value) -> Result<Vec<u8>, String> {
+        match (self, value) {
+            (ExtDecoder::Base64, Value::String(text)) => general_purpose::STANDARD.decode(text).map_err(|e| e.to_string()),
+            (ExtDecoder::Uuid, Value::String(text)) => {
+                let digits: String = text.chars().filter(|&c| c != '-').collect();
+                match hex::decode(&digits) {
+                    Ok(bytes) if bytes.len() == 16 => Ok(bytes),
+                    _ => Err(trf("{} is not a UUID", &[&format_args!("{:?}", text)])),
+                }
+            }
+            (ExtDecoder::Utf8, Value::String(text)) => Ok(text.as_bytes().to_vec()),
+            (ExtDecoder::Struct(fields), Value::Object(object)) => {
+                // The payload has no room for them, they would be lost without a word
+                if let Some(extra) = object.keys().find(|key| fields.iter().all(|(name, _)| name != *key)) {
+                    return Err(trf("the struct has no field {}", &[extra]));
+                }
+                let mut data = Vec::new();
+                for (name, primitive) in fields {
+                    let field = object.get(name).ok_or_else(|| trf("the field {} is missing", &[name]))?;
+                    primitive.write(field, &mut data).map_err(|e| format!("{}: {}", name, e))?;
+                }
+                Ok(data)
+            }
+            (ExtDecoder::Primitive(primitive), value) => {
+                let mut data = Vec::new();
+                primitive.write(value, &mut data)?;
+                Ok(data)
+            }
+            (ExtDecoder::Struct(_), _) => Err(tr("expected an object of the struct fields").to_string()),
+            _ => Err(tr("expected a string").to_string()),
+        }
+    }
+}
+
+impl Primitive {
+    fn parse(name: &str) -> Option<Primitive> {
+        Some(match name {
+            "u8" => Primitive::U8,
+            "u16" => Primitive::U16,
+            "u32" => Primitive::U32,
+            "u64" => Primitive::U64,
+            "i8" => Primitive::I8,
+            "i16" => Primitive::I16,
+            "i32" => Primitive::I32,
+            "i64" => Primitive::I64,
+            "u128" => Primitive::U128,
+            "i128" => Primitive::I128,
+            "f32" => Primitive::F32,
+            "f64" => Primitive::F64,
+            "bool" => Primitive::Bool,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Primitive::U8 | Primitive::I8 | Primitive::Bool => 1,
+            Primitive::U16 | Primitive::I16 => 2,
+            Primitive::U32 | Primitive::I32 | Primitive::F32 => 4,
+            Primitive::U64 | Primitive::I64 | Primitive::F64 => 8,
+            Primitive::U128 | Primitive::I128 => 16,
+        }
+    }
+
+    // `bytes` is exactly size() long
+    fn read(self, bytes: &[u8]) -> Value {
+        let mut be = [0u8; 16];
+        be[16 - bytes.len()..].copy_from_slice(bytes);
+        let unsigned = u128::from_be_bytes(be);
+        // Sign-extended from the field's width
+        let signed = (unsigned << (128 - 8 * bytes.len())) as i128 >> (128 - 8 * bytes.len());
+        match self {
+            Primitive::U8 | Primitive::U16 | Primitive::U32 | Primitive::U64 => Value::from(unsigned as u64),
+            Primitive::I8 | Primitive::I16 | Primitive::I32 | Primitive::I64 => Value::from(signed as i64),
+            Primitive::U128 => u64::try_from(unsigned).map_or_else(|_| Value::String(unsigned.to_string()), Value::from),
+            Primitive::I128 => i64::try_from(signed).map_or_else(|_| Value::String(signed.to_string()), Value::from),
+            Primitive::F32 => float(f32::from_bits(unsigned as u32) as f64),
+            Primitive::F64 => float(f64::from_bits(unsigned as u64)),
+            Primitive::Bool => Value::Bool(unsigned != 0),
+        }
+    }
+
+    fn write(self, value: &Value, out: &mut Vec<u8>) -> Result<(), String> {
+        let size = self.size();
+        let out_of_range = || trf("{} doesn't fit the field", &[value]);
+        let bits = match self {
+            Primitive::Bool => value.as_bool().ok_or_else(out_of_range)? as u128,
+            // Only what an f32 holds exactly, as decoding gives, rather than rounding the rest
+            Primitive::F32 => {
+                let n = value.as_f64().ok_or_else(out_of_range)?;
+                if n as f32 as f64 != n {
+                    return Err(out_of_range());
+                }
+                (n as f32).to_bits() as u128
+            }
+            Primitive::F64 => value.as_f64().ok_or_else(out_of_range)?.to_bits() as u128,
+            // A number, or the decimal string decoding gives beyond the 64-bit range
+            Primitive::U128 => match value {
+                Value::String(text) => text.parse::<u128>().map_err(|_| out_of_range())?,
+                value => value.as_u64().ok_or_else(out_of_range)? as u128,
+            },
+            Primitive::I128 => match value {
+                Value::String(text) => text.parse::<i128>().map_err(|_| out_of_range())? as u128,
+                value => value.as_i64().ok_or_else(out_of_range)? as u128,
+            },
+            Primitive::U8 | Primitive::U16 | Primitive::U32 | Primitive::U64 => {
+                let n = value.as_u64().ok_or_else(out_of_range)?;
+                if size < 8 && n >> (8 * size) != 0 {
+                    return Err(out_of_range());
+                }
+                n as u128
+            }
+            Primitive::I8 | Primitive::I16 | Primitive::I32 | Primitive::I64 => {
+                let n = value.as_i64().ok_or_else(out_of_range)?;
+                let bits = 8 * size as u32;
+                if size < 8 && (n < -(1 << (bits - 1)) || n >= 1 << (bits - 1)) {
+                    return Err(out_of_range());
+                }
+                n as u128
+            }
+        };
+        out.extend_from_slice(&bits.to_be_bytes()[16 - size..]);
+        Ok(())
+    }
+}
+
+// Canonical hyphenated lowercase form of 16 bytes
+fn format_uuid(data: &[u8]) -> String {
+    let hex = hex::encode(data);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+// The 16 bytes of a string in the 8-4-4-4-12 hex digit pattern, in either case
+pub fn parse_uuid(text: &str) -> Option<[u8; 16]> {
+    let groups: Vec<&str> = text.split('-').collect();
+    let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+    if lengths != [8, 4, 4, 4, 12] {
+        return None;
+    }
+    let mut bytes = [0u8; 16];
+    hex::decode_to_slice(groups.concat(), &mut bytes).ok()?;
+    Some(bytes)
+}
+
+// The entries of an object in the pairs form
+fn pairs(value: &Value) -> Option<Vec<(&Value, &Value)>> {
+    let object = value.as_object().filter(|object| object.len() == 1)?;
+    let pairs = object.get(MAP_KEY)?.as_array()?;
+    pairs.iter().map(|pair| match pair.as_array()?.as_slice() {
+        [key, value] => Some((key, value)),
+        _ => None,
+    }).collect()
+}
+
+// Serializes `value` the way serde_json would, except that tagged objects of a registered type
+// go to rmp_serde as ext values, and with UUIDs or timestamps on so do UUID strings, or as bins,
+// and date-time strings. With the tagged bin form, tagged bins go as bins, with the pairs key
+// form pairs go as maps, and with single floats, floats that float 32 holds exactly go as float 32.
+#[derive(Clone, Copy)]
+pub struct WithExtTypes<'a> {
+    value: &'a Value,
+    registry: &'a ExtRegistry,
+    single_floats: bool,
+    bin_form: Option<BinForm>,
+    key_form: Option<KeyForm>,
+}
+
+impl<'a> WithExtTypes<'a> {
+    pub fn new(value: &'a Value, registry: &'a ExtRegistry) -> WithExtTypes<'a> {
+        WithExtTypes { value, registry, single_floats: false, bin_form: None, key_form: None }
+    }
+
+    pub fn single_floats(self, single_floats: bool) -> WithExtTypes<'a> {
+        WithExtTypes { single_floats, ..self }
+    }
+
+    pub fn bin_form(self, bin_form: Option<BinForm>) -> WithExtTypes<'a> {
+        WithExtTypes { bin_form, ..self }
+    }
+
+    pub fn key_form(self, key_form: Option<KeyForm>) -> WithExtTypes<'a> {
+        WithExtTypes { key_form, ..self }
+    }
+}
+
+impl Serialize for WithExtTypes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let child = |value| WithExtTypes { value, ..*self };
+        match self.value {
+            Value::Number(n) if self.single_floats && n.is_f64() => {
+                let n = n.as_f64().unwrap_or_default();
+                match n as f32 as f64 == n {
+                    true => serializer.serialize_f32(n as f32),
+                    false => serializer.serialize_f64(n),
+                }
+            }
+            Value::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(&child(item))?;
+                }
+                seq.end()
+            }
+            Value::Object(object) => {
+                if let Some(bin) = tagged_bin(self.value).filter(|_| self.bin_form == Some(BinForm::Tagged)) {
+                    return serializer.serialize_bytes(&bin.map_err(S::Error::custom)?);
+                }
+                if let Some(pairs) = pairs(self.value).filter(|_| self.key_form == Some(KeyForm::Pairs)) {
+                    let mut map = serializer.serialize_map(Some(pairs.len()))?;
+                    for (key, value) in pairs {
+                        map.serialize_entry(&child(key), &child(value))?;
+                    }
+                    return map.end();
+                }
+                match self.registry.encode(self.value) {
+                    Some(Ok((ext_type, data))) => {
+                        serializer.serialize_newtype_struct(rmp_serde::MSGPACK_EXT_STRUCT_NAME, &(ext_type, Bytes(&data)))
+                    }
+                    Some(Err(e)) => Err(S::Error::custom(e)),
+                    None => {
+                        let mut map = serializer.serialize_map(Some(object.len()))?;
+                        for (key, value) in object {
+                            map.serialize_entry(key, &child(value))?;
+                        }
+                        map.end()
+                    }
+                }
+            }
+            Value::String(text) => match parse_uuid(text).filter(|_| self.registry.uuids) {
+                Some(data) => match self.registry.uuid_ext_type {
+                    Some(ext_type) => serializer.serialize_newtype_struct(rmp_serde::MSGPACK_EXT_STRUCT_NAME, &(ext_type, Bytes(&data))),
+                    None => serializer.serialize_bytes(&data),
+                },
+                None => match Timestamp::parse(text).and_then(Result::ok).filter(|_| self.registry.timestamps) {
+                    Some(timestamp) => {
+                        serializer.serialize_newtype_struct(rmp_serde::MSGPACK_EXT_STRUCT_NAME, &(TIMESTAMP_EXT, Bytes(&timestamp.encode())))
+                    }
+                    None => serializer.serialize_str(text),
+                },
+            },
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_registry_parses_lines_and_json_files() {
+    let registry = ExtRegistry::parse("# ours\n2 = \"uuid\"\n\n7 = \"struct lat:f64 lon:f64\"\n-3 = string\n2 = \"base64\"").unwrap();
+    assert_eq!(registry.decoder(2), Some(&ExtDecoder::Base64));
+    assert_eq!(registry.decoder(7), Some(&ExtDecoder::Struct(vec![("lat".to_string(), Primitive::F64), ("lon".to_string(), Primitive::F64)])));
+    assert_eq!(registry.decoder(-3), Some(&ExtDecoder::Utf8));
+    assert_eq!(registry.decoder(1), None);
+    assert!(ExtRegistry::parse("").unwrap().is_empty());
+
+    assert_eq!(ExtRegistry::parse("1 = \"uuid\"\n200 = \"uuid\""), Err("Line 2: 200 is not an ext type, those go from -128 to 127".to_string()));
+    assert_eq!(ExtRegistry::parse("1 = \"struct x\""), Err("Line 1: x: a struct field is name:type".to_string()));
+    assert_eq!(ExtRegistry::parse("1 = \"struct x:u8 x:u16\""), Err("Line 1: the field x is given twice".to_string()));
+    assert_eq!(ExtRegistry::parse("1 = \"struct x:f16\""), Err("Line 1: x:f16: unknown field type, use u8-u128, i8-i128, f32, f64 or bool".to_string()));
+    assert_eq!(ExtRegistry::parse("1 = \"date\""), Err("Line 1: unknown decoder \"date\", use base64, uuid, string, a number type or struct".to_string()));
+    assert!(ExtRegistry::parse("uuid").is_err());
+
+    assert_eq!(ExtRegistry::from_file(r#"{"2": "uuid", "7": "struct lat:f64 lon:f64"}"#).unwrap(), "2 = \"uuid\"\n7 = \"struct lat:f64 lon:f64\"\n");
+    assert_eq!(ExtRegistry::from_file("2 = \"uuid\"\n").unwrap(), "2 = \"uuid\"\n");
+    assert!(ExtRegistry::from_file(r#"{"2": 1}"#).is_err());
+    assert!(ExtRegistry::from_file("2 = \"ulid\"").is_err());
+}
+
+#[test]
+fn test_decoders_render_their_payloads() {
+    let uuid = hex::decode("123e4567e89b12d3a456426614174000").unwrap();
+    assert_eq!(ExtDecoder::Uuid.decode(&uuid), Ok(Value::from("123e4567-e89b-12d3-a456-426614174000")));
+    assert!(ExtDecoder::Uuid.decode(&uuid[..15]).is_err());
+    assert_eq!(ExtDecoder::Base64.decode(b"ab"), Ok(Value::from("YWI=")));
+    assert_eq!(ExtDecoder::Utf8.decode("Grüße".as_bytes()), Ok(Value::from("Grüße")));
+    assert!(ExtDecoder::Utf8.decode(&[0xff]).is_err());
+
+    let point = ExtDecoder::parse("struct lat:f64 lon:f64 zone:i16 ok:bool").unwrap();
+    let data = [&51.5f64.to_be_bytes()[..], &(-0.125f64).to_be_bytes(), &(-2i16).to_be_bytes(), &[1]].concat();
+    assert_eq!(point.decode(&data), Ok(serde_json::json!({"lat": 51.5, "lon": -0.125, "zone": -2, "ok": true})));
+    assert_eq!(point.decode(&data[1..]), Err("the struct fields take 19 bytes, this payload is 18".to_string()));
+    let wide = ExtDecoder::parse("struct a:u8 b:u32 c:i64 d:f32").unwrap();
+    let data = [&[0xff][..], &0xdead_beefu32.to_be_bytes(), &i64::MIN.to_be_bytes(), &1.5f32.to_be_bytes()].concat();
+    assert_eq!(wide.decode(&data), Ok(serde_json::json!({"a": 255, "b": 0xdead_beefu32, "c": i64::MIN, "d": 1.5})));
+}
+
+#[test]
+fn test_number_decoders_take_128_bits() {
+    let registry = ExtRegistry::default()
+        .with_decoder(7, ExtDecoder::parse("i128").unwrap())
+        .with_decoder(8, ExtDecoder::Primitive(Primitive::U128))
+        .with_decoder(9, ExtDecoder::parse("u16").unwrap())
+        .with_decoder(9, ExtDecoder::parse("i16").unwrap());
+    assert_eq!(registry.decoder(9), Some(&ExtDecoder::Primitive(Primitive::I16)));
+    let payloads = [
+        (7, (-5i128).to_be_bytes().to_vec(), serde_json::json!(-5)),
+        (7, i128::MIN.to_be_bytes().to_vec(), serde_json::json!("-170141183460469231731687303715884105728")),
+        (8, u128::MAX.to_be_bytes().to_vec(), serde_json::json!("340282366920938463463374607431768211455")),
+        (8, (u64::MAX as u128).to_be_bytes().to_vec(), serde_json::json!(u64::MAX)),
+        (9, (-2i16).to_be_bytes().to_vec(), serde_json::json!(-2)),
+    ];
+    for (ext_type, data, value) in payloads {
+        let tagged = registry.decode(ext_type, &data).unwrap().unwrap();
+        assert_eq!(tagged[EXT_VALUE_KEY], value);
+        assert_eq!(registry.encode(&tagged), Some(Ok((ext_type, data))));
+    }
+    assert_eq!(registry.decode(7, &[0; 8]), Some(Err("Ext type 7: the number takes 16 bytes, this payload is 8".to_string())));
+    assert!(registry.encode(&serde_json::json!({"$ext": 8, "value": -1})).unwrap().is_err());
+    assert!(registry.encode(&serde_json::json!({"$ext": 8, "value": "2e3"})).unwrap().is_err());
+    assert_eq!(ExtDecoder::parse("i128 x"), Err("unexpected \"x\" after the decoder".to_string()));
+}
+
+#[test]
+fn test_encoding_undoes_decoding() {
+    let registry = ExtRegistry::parse("2 = \"uuid\"\n7 = \"struct lat:f64 lon:f64 n:i8\"\n9 = \"string\"").unwrap();
+    let payloads: [(i8, Vec<u8>); 3] = [
+        (2, hex::decode("123e4567e89b12d3a456426614174000").unwrap()),
+        (7, [&51.5f64.to_be_bytes()[..], &(-0.125f64).to_be_bytes(), &[0x80]].concat()),
+        (9, b"hi".to_vec()),
+    ];
+    for (ext_type, data) in payloads {
+        let tagged = registry.decode(ext_type, &data).unwrap().unwrap();
+        assert_eq!(tagged[EXT_KEY], ext_type);
+        assert_eq!(registry.encode(&tagged), Some(Ok((ext_type, data))));
+    }
+    // Not the tagged form, or a type that isn't registered
+    assert_eq!(registry.encode(&serde_json::json!({"$ext": 2, "value": "x", "more": 1})), None);
+    assert_eq!(registry.encode(&serde_json::json!({"$ext": 3, "value": "x"})), None);
+    assert_eq!(registry.decode(3, b"x"), None);
+    assert_eq!(registry.encode(&serde_json::json!({"$ext": 2, "value": "x"})), Some(Err("Ext type 2: \"x\" is not a UUID".to_string())));
+    assert_eq!(registry.encode(&serde_json::json!({"$ext": 7, "value": {"lat": 1, "lon": 2, "n": 128}})), Some(Err("Ext type 7: n: 128 doesn't fit the field".to_string())));
+    assert_eq!(registry.encode(&serde_json::json!({"$ext": 7, "value": {"lat": 1}})), Some(Err("Ext type 7: the field lon is missing".to_string())));
+    assert_eq!(registry.encode(&serde_json::json!({"$ext": 7, "value": {"lat": 1, "lon": 2, "n": 3, "alt": 4}})), Some(Err("Ext type 7: the struct has no field alt".to_string())));
+    let single = ExtDecoder::parse("struct x:f32").unwrap();
+    assert_eq!(single.encode(&serde_json::json!({"x": 1.5})), Ok(1.5f32.to_be_bytes().to_vec()));
+    assert_eq!(single.encode(&serde_json::json!({"x": 0.1})), Err("x: 0.1 doesn't fit the field".to_string()));
+    let decoded = single.decode(&0.1f32.to_be_bytes()).unwrap();
+    assert_eq!(single.encode(&decoded), Ok(0.1f32.to_be_bytes().to_vec()));
+
+    // Nested anywhere, they come out of rmp_serde as ext values
+    let value = serde_json::json!({"id": {"$ext": 9, "value": "hi"}, "list": [{"$ext": 4, "value": "x"}]});
+    let bytes = rmp_serde::to_vec(&WithExtTypes::new(&value, &registry)).unwrap();
+    assert_eq!(hex::encode(bytes), "82a26964d5096869a46c6973749182a42465787404a576616c7565a178");
+}
+
+#[test]
+fn test_uuids_round_trip_as_bins_and_ext_values() {
+    let nil = "00000000-0000-0000-0000-000000000000";
+    let max = "ffffffff-ffff-ffff-ffff-ffffffffffff";
+    assert_eq!(parse_uuid(nil), Some([0; 16]));
+    assert_eq!(parse_uuid("FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF"), Some([0xff; 16]));
+    for text in ["550e8400e29b41d4a716446655440000", "550e8400-e29b-41d4-a716-44665544000", "550e8400-e29b-41d4-a716-44665544000g", "{550e8400-e29b-41d4-a716-446655440000}"] {
+        assert_eq!(parse_uuid(text), None, "{}", text);
+    }
+
+    let value = serde_json::json!({"nil": nil, "max": [max], "name": "not-a-uuid"});
+    let off = ExtRegistry::default();
+    assert!(off.is_empty());
+    assert_eq!(off.uuid(None, &[0; 16]), None);
+    assert_eq!(rmp_serde::to_vec(&WithExtTypes::new(&value, &off)).unwrap(), rmp_serde::to_vec(&value).unwrap());
+
+    let bins = ExtRegistry::default().with_uuids(true, None);
+    let bytes = rmp_serde::to_vec(&WithExtTypes::new(&value, &bins)).unwrap();
+    assert_eq!(hex::encode(&bytes), format!("83a36e696cc410{}a36d617891c410{}a46e616d65aa6e6f742d612d75756964", "00".repeat(16), "ff".repeat(16)));
+    assert_eq!(bins.uuid(None, &[0; 16]), Some(Value::from(nil)));
+    assert_eq!(bins.uuid(None, &[0xff; 16]), Some(Value::from(max)));
+    assert_eq!(bins.uuid(None, &[0; 15]), None);
+    assert_eq!(bins.uuid(Some(3), &[0; 16]), None);
+
+    let exts = ExtRegistry::default().with_uuids(true, Some(3));
+    let bytes = rmp_serde::to_vec(&WithExtTypes::new(&Value::from(max), &exts)).unwrap();
+    assert_eq!(hex::encode(&bytes), format!("d803{}", "ff".repeat(16)));
+    assert_eq!(exts.uuid(Some(3), &[0xff; 16]), Some(Value::from(max)));
+    assert_eq!(exts.uuid(None, &[0xff; 16]), Some(Value::from(max)));
+}
+
+#[test]
+fn test_date_times_encode_as_timestamps_when_asked() {
+    let value = serde_json::json!(["2024-05-01T10:30:00Z", "2024-05-01T10:30:00.5Z", "1900-01-01T00:00:00Z", "2016-12-31T23:59:60Z", "2024-05-01"]);
+    let plain = rmp_serde::to_vec(&value).unwrap();
+    assert_eq!(rmp_serde::to_vec(&WithExtTypes::new(&value, &ExtRegistry::default())).unwrap(), plain);
+    let timestamps = ExtRegistry { timestamps: true, ..Default::default() };
+    assert!(!timestamps.is_empty());
+    let bytes = rmp_serde::to_vec(&WithExtTypes::new(&value, &timestamps)).unwrap();
+    assert_eq!(
+        hex::encode(bytes),
+        "95d6ff663219a8d7ff77359400663219a8c70cff00000000ffffffff7c558180b4323031362d31322d33315432333a35393a36305aaa323032342d30352d3031",
+    );
+}
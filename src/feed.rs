@@ -0,0 +1,190 @@
+use crate::decode::decode_value_at;
+use crate::error::ConvertError;
+use crate::format::JsonFormat;
+use crate::locale::{tr, trf};
+use crate::log::clock_time;
+use crate::rpc::{label, message, Message};
+use crate::stats;
+use eframe::egui;
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+pub const DEFAULT_MAX_FRAMES: usize = 500;
+
+// One message as the list shows it
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameContent {
+    Json(String),
+    Text(String),
+    // Hex of the frame that didn't decode
+    Failed { error: String, hex: String },
+}
+
+// A message that came in over the network, decoded as it arrived
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedFrame {
+    pub time: SystemTime,
+    // Address of the sender, where there can be more than one
+    pub source: Option<String>,
+    // Anything else worth showing in the heading, such as the QoS of an MQTT message
+    pub note: Option<String>,
+    pub size: usize,
+    pub content: FrameContent,
+    // What it is as msgpack-RPC, for feeds that look for that
+    pub rpc: Option<Message>,
+}
+
+impl ReceivedFrame {
+    // With `rpc`, msgpack-RPC messages are labeled
+    pub fn binary(bytes: &[u8], source: Option<String>, json_format: &JsonFormat, rpc: bool) -> ReceivedFrame {
+        let mut rpc_message = None;
+        let content = decode_value_at(bytes, 0, false)
+            .and_then(|(mut value, end)| {
+                // A datagram or message holds one value, anything after it is a sign of corruption
+                if end < bytes.len() {
+                    return Err(ConvertError::TrailingBytes(bytes.len() - end));
+                }
+                if rpc {
+                    rpc_message = message(&value);
+                    value = label(value);
+                }
+                json_format.order_keys(&mut value);
+                json_format.pretty(&value)
+            })
+            .map_or_else(|e| FrameContent::Failed { error: e.to_string(), hex: hex::encode(bytes) }, FrameContent::Json);
+        ReceivedFrame { time: SystemTime::now(), source, note: None, size: bytes.len(), content, rpc: rpc_message }
+    }
+
+    pub fn text(text: String) -> ReceivedFrame {
+        ReceivedFrame { time: SystemTime::now(), source: None, note: None, size: text.len(), content: FrameContent::Text(text), rpc: None }
+    }
+}
+
+// The last few received frames, newest last. While paused the list stays as it is and new frames
+// wait, within the same cap.
+pub struct FrameLog {
+    // Numbered in the order they came in, so a frame keeps its collapsed state as older ones go
+    frames: VecDeque<(u64, ReceivedFrame)>,
+    waiting: VecDeque<(u64, ReceivedFrame)>,
+    next_id: u64,
+    pub max_frames: usize,
+    pub paused: bool,
+}
+
+impl Default for FrameLog {
+    fn default() -> Self {
+        FrameLog { frames: VecDeque::new(), waiting: VecDeque::new(), next_id: 0, max_frames: DEFAULT_MAX_FRAMES, paused: false }
+    }
+}
+
+impl FrameLog {
+    pub fn take(&mut self, incoming: impl IntoIterator<Item = ReceivedFrame>) {
+        for frame in incoming {
+            self.waiting.push_back((self.next_id, frame));
+            self.next_id += 1;
+        }
+        if !self.paused {
+            self.frames.append(&mut self.waiting);
+        }
+        for frames in [&mut self.frames, &mut self.waiting] {
+            let excess = frames.len().saturating_sub(self.max_frames);
+            frames.drain(..excess);
+        }
+    }
+
+    #[cfg(test)]
+    pub fn frames(&self) -> impl Iterator<Item = &ReceivedFrame> {
+        self.frames.iter().map(|(_, frame)| frame)
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.waiting.clear();
+    }
+
+    // Pause/Resume, the cap and Clear
+    pub fn controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let pause = if self.paused { tr("Resume") } else { tr("Pause") };
+            if ui.button(pause).on_hover_text(tr("While paused the list stays as it is, new frames wait")).clicked() {
+                self.paused = !self.paused;
+            }
+            ui.label(tr("Keep the last"));
+            ui.add(egui::DragValue::new(&mut self.max_frames).clamp_range(1..=100_000));
+            ui.label(tr("frames"));
+            if ui.button(tr("Clear")).clicked() {
+                self.clear();
+            }
+            if !self.waiting.is_empty() {
+                ui.weak(trf("{} waiting", &[&self.waiting.len()]));
+            }
+        });
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) {
+        self.show_where(ui, |_| true);
+    }
+
+    // Scrolls along as frames come in. JSON and failures fold open, text is shown as is. Frames
+    // `keep` turns down stay in the log, just out of sight.
+    pub fn show_where(&self, ui: &mut egui::Ui, keep: impl Fn(&ReceivedFrame) -> bool) {
+        egui::ScrollArea::vertical().max_height(420.0).auto_shrink([false, true]).stick_to_bottom(true).show(ui, |ui| {
+            for (id, frame) in self.frames.iter().filter(|(_, frame)| keep(frame)) {
+                let mut heading = format!("{} · {}", clock_time(frame.time), trf("{} bytes", &[&stats::group_thousands(frame.size)]));
+                for detail in [&frame.source, &frame.note].into_iter().flatten() {
+                    heading = format!("{} · {}", heading, detail);
+                }
+                match &frame.content {
+                    FrameContent::Json(json) => {
+                        egui::CollapsingHeader::new(heading).id_source(("frame", id)).show(ui, |ui| {
+                            ui.monospace(json);
+                        });
+                    }
+                    FrameContent::Text(text) => {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.weak(heading);
+                            ui.weak(tr("text"));
+                            ui.monospace(text);
+                        });
+                    }
+                    FrameContent::Failed { error, hex } => {
+                        egui::CollapsingHeader::new(egui::RichText::new(heading).color(ui.visuals().error_fg_color))
+                            .id_source(("frame", id))
+                            .show(ui, |ui| {
+                                ui.colored_label(ui.visuals().error_fg_color, error);
+                                ui.monospace(hex);
+                            });
+                    }
+                }
+            }
+        });
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_received_frames_decode_or_show_hex() {
+    let frame = ReceivedFrame::binary(&[0x81, 0xa1, 0x61, 0x01], None, &JsonFormat::default(), false);
+    assert_eq!(frame.content, FrameContent::Json("{\n  \"a\": 1\n}".to_string()));
+    for corrupt in [&[0x82, 0xa1, 0x61][..], &[0xc0, 0xc0]] {
+        let FrameContent::Failed { hex, .. } = ReceivedFrame::binary(corrupt, None, &JsonFormat::default(), false).content else {
+            panic!("decoded a corrupt frame");
+        };
+        assert_eq!(hex, hex::encode(corrupt));
+    }
+}
+
+#[test]
+fn test_paused_log_keeps_its_frames() {
+    let text = |text: &str| ReceivedFrame::text(text.to_string());
+    let mut log = FrameLog { max_frames: 2, ..Default::default() };
+    log.take([text("a")]);
+    log.paused = true;
+    log.take([text("b"), text("c"), text("d")]);
+    assert_eq!(log.frames().count(), 1);
+    log.paused = false;
+    log.take([]);
+    let texts: Vec<_> = log.frames().map(|frame| frame.content.clone()).collect();
+    assert_eq!(texts, [FrameContent::Text("c".to_string()), FrameContent::Text("d".to_string())]);
+}
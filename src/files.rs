@@ -0,0 +1,764 @@
+use crate::byte_text::{hexdump, read_hexdump};
+use crate::detect::{candidates, is_well_formed, InputKind};
+use crate::error::ConvertError;
+use crate::ext_types::ExtRegistry;
+use crate::locale::{tr, trf};
+use crate::stats;
+use base64::engine::{general_purpose, DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::{alphabet, Engine};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+
+pub const MEGABYTE: usize = 1024 * 1024;
+
+// Dropped files larger than this are only read after asking
+pub const ASK_ABOVE_BYTES: u64 = 16 * MEGABYTE as u64;
+
+// Binary files larger than this are mapped into memory instead of read into it
+#[cfg(not(target_arch = "wasm32"))]
+const MAP_ABOVE_BYTES: u64 = ASK_ABOVE_BYTES;
+
+// Base64 with or without its padding, in either alphabet
+const PADDING_OPTIONAL: GeneralPurposeConfig = GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const BASE64_ANY_PADDING: [GeneralPurpose; 2] = [
+    GeneralPurpose::new(&alphabet::STANDARD, PADDING_OPTIONAL),
+    GeneralPurpose::new(&alphabet::URL_SAFE, PADDING_OPTIONAL),
+];
+
+// Raw MessagePack whatever the bytes look like
+const BINARY_EXTENSIONS: [&str; 3] = ["msgpack", "mpk", "bin"];
+
+// How a file is read into the panes: by its content when dropped, or as the pane it was opened
+// from asked for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileTarget {
+    Sniff,
+    Json,
+    MessagePack(Encoding),
+    // Into the JSON Schema the JSON input is checked against
+    Schema,
+    // Into the ext types setting
+    ExtTypes,
+}
+
+// Text form of raw MessagePack bytes in the input pane
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Encoding {
+    #[default]
+    Base64,
+    // - and _ in place of + and /, written without padding as in JWTs and URLs
+    Base64Url,
+    Hex,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 3] = [Encoding::Base64, Encoding::Base64Url, Encoding::Hex];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Base64 => "Base64",
+            Encoding::Base64Url => "Base64url",
+            Encoding::Hex => "Hex",
+        }
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Base64 => general_purpose::STANDARD.encode(bytes),
+            Encoding::Base64Url => general_purpose::URL_SAFE_NO_PAD.encode(bytes),
+            Encoding::Hex => hex::encode(bytes),
+        }
+    }
+
+    // Whitespace is skipped, so text wrapped over several lines still decodes, base64 may leave
+    // out its padding, and hex may be written as hex_digits reads it
+    pub fn decode(self, text: &[u8]) -> Result<Vec<u8>, ConvertError> {
+        let text: Vec<u8> = text.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+        match self {
+            Encoding::Base64 => BASE64_ANY_PADDING[0].decode(text).map_err(ConvertError::Base64Decode),
+            Encoding::Base64Url => BASE64_ANY_PADDING[1].decode(text).map_err(ConvertError::Base64Decode),
+            Encoding::Hex => match std::str::from_utf8(&text).ok().and_then(hex_digits) {
+                Some(digits) => hex::decode(digits).map_err(ConvertError::HexDecode),
+                None => hex::decode(text).map_err(ConvertError::HexDecode),
+            },
+        }
+    }
+
+    // Whichever of them `text` is, copied from a hex dump or a base64 field. Hex is read as
+    // hex_digits reads it, base64 may leave out its padding. Text that reads as both is hex, as
+    // in the input pane.
+    pub fn decode_any(text: &str) -> Result<(Encoding, Vec<u8>), ConvertError> {
+        let digits = hex_digits(text);
+        let all_hex = digits.is_some();
+        let digits = digits.unwrap_or_default();
+        if all_hex && digits.len().is_multiple_of(2) {
+            return hex::decode(&digits).map(|bytes| (Encoding::Hex, bytes)).map_err(ConvertError::HexDecode);
+        }
+        let base64: String = text.split_whitespace().collect();
+        let decoded = [Encoding::Base64, Encoding::Base64Url]
+            .into_iter()
+            .zip(&BASE64_ANY_PADDING)
+            .find_map(|(encoding, engine)| engine.decode(&base64).ok().map(|bytes| (encoding, bytes)));
+        match decoded {
+            Some(found) => Ok(found),
+            // Odd hex is more likely a digit short than base64
+            None if all_hex => hex::decode(&digits).map(|bytes| (Encoding::Hex, bytes)).map_err(ConvertError::HexDecode),
+            None => general_purpose::STANDARD.decode(&base64).map(|bytes| (Encoding::Base64, bytes)).map_err(ConvertError::Base64Decode),
+        }
+    }
+}
+
+// The digits of `text` if it is hex, as copied from a debugger, a hex dump or C source: 0x in
+// front of the whole or of each byte, and whitespace, commas and newlines between bytes, are
+// skipped. None if anything else is left.
+pub fn hex_digits(text: &str) -> Option<String> {
+    let mut digits = String::with_capacity(text.len());
+    for word in text.split(|c: char| c.is_whitespace() || c == ',') {
+        let word = word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")).unwrap_or(word);
+        if !word.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        digits.push_str(word);
+    }
+    Some(digits)
+}
+
+// How the text in the MessagePack input pane is read
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum InputEncoding {
+    // Each of the others in turn, see decode
+    #[default]
+    Auto,
+    Hex,
+    Base64,
+    // - and _ in place of + and /, padded or not, as in JWTs and URLs
+    Base64Url,
+    // \x81\xa1a\x01 as Python prints bytes and C and Rust write them in string literals, or
+    // \u0081\u00a1a\u0001 as in JSON from Node and loggers
+    Escaped,
+    // {0x81, 0xa1, 0x61, 0x01} or [129, 161, 97, 1], copied out of C, Rust or Python source
+    Array,
+    // As xxd or hexdump -C print it
+    Hexdump,
+}
+
+impl InputEncoding {
+    pub const ALL: [InputEncoding; 7] = [
+        InputEncoding::Auto,
+        InputEncoding::Hex,
+        InputEncoding::Base64,
+        InputEncoding::Base64Url,
+        InputEncoding::Escaped,
+        InputEncoding::Array,
+        InputEncoding::Hexdump,
+    ];
+
+    // The order Auto tries them in, so text that is both hex and base64 is hex. An array needs a
+    // bracket or a comma before Auto takes it for one, 12 alone is hex rather than twelve.
+    const DETECTED: [InputEncoding; 6] = [
+        InputEncoding::Escaped,
+        InputEncoding::Hexdump,
+        InputEncoding::Array,
+        InputEncoding::Hex,
+        InputEncoding::Base64,
+        InputEncoding::Base64Url,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputEncoding::Auto => tr("Detect"),
+            InputEncoding::Hex => "Hex",
+            InputEncoding::Base64 => "Base64",
+            InputEncoding::Base64Url => "Base64url",
+            InputEncoding::Escaped => tr("Escaped bytes"),
+            InputEncoding::Array => tr("Byte array"),
+            InputEncoding::Hexdump => tr("Hexdump"),
+        }
+    }
+
+    // The bytes behind `text` and the encoding they were read in. Auto takes the first encoding
+    // that gives well-formed MessagePack or CBOR, failing that the first that decodes at all, so
+    // base64 that happens to be all hex digits is still read as base64 when it only makes sense
+    // that way.
+    pub fn decode(self, text: &str) -> Result<(InputEncoding, Vec<u8>), ConvertError> {
+        let compact = || text.split_whitespace().collect::<String>();
+        let bytes = match self {
+            InputEncoding::Auto => {
+                let array = text.contains(['[', '{', ',']);
+                let mut decoded = InputEncoding::DETECTED.into_iter()
+                    .filter(|encoding| *encoding != InputEncoding::Array || array)
+                    .filter_map(|encoding| encoding.decode(text).ok());
+                let first = decoded.next();
+                if first.as_ref().is_some_and(|(_, bytes)| is_well_formed(bytes)) {
+                    return Ok(first.unwrap_or_default());
+                }
+                return match decoded.find(|(_, bytes)| is_well_formed(bytes)).or(first) {
+                    Some(found) => Ok(found),
+                    None if text.contains('\\') => InputEncoding::Escaped.decode(text),
+                    None if text.contains(['[', '{']) => InputEncoding::Array.decode(text),
+                    None if hex_digits(text).is_some() => InputEncoding::Hex.decode(text),
+                    None => InputEncoding::Base64.decode(text),
+                };
+            }
+            InputEncoding::Hex => hex::decode(hex_digits(text).unwrap_or_else(compact)).map_err(ConvertError::HexDecode)?,
+            InputEncoding::Base64 => BASE64_ANY_PADDING[0].decode(compact()).map_err(ConvertError::Base64Decode)?,
+            InputEncoding::Base64Url => BASE64_ANY_PADDING[1].decode(compact()).map_err(ConvertError::Base64Decode)?,
+            InputEncoding::Escaped => unescape(text)?,
+            InputEncoding::Array => byte_array(text)?,
+            InputEncoding::Hexdump => read_hexdump(text)?,
+        };
+        Ok((self, bytes))
+    }
+}
+
+// The numbers of a byte array literal, in hex with 0x or in decimal, with or without a u8 suffix.
+// Whatever is outside the last pair of brackets, such as `static const uint8_t data[] =` or
+// `let data: &[u8] = &`, is left out.
+fn byte_array(text: &str) -> Result<Vec<u8>, ConvertError> {
+    let end = text.rfind(['}', ']']).unwrap_or(text.len());
+    let start = text[..end].rfind(['{', '[']).map_or(0, |start| start + 1);
+    text[start..end]
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let number = item.strip_suffix("u8").unwrap_or(item);
+            let byte = match number.strip_prefix("0x").or_else(|| number.strip_prefix("0X")) {
+                Some(digits) => u8::from_str_radix(digits, 16),
+                None => number.parse(),
+            };
+            byte.map_err(|_| ConvertError::ByteArray(item.to_string()))
+        })
+        .collect()
+}
+
+// Escaped bytes to the bytes, optionally in the quotes of a b"…" or b'…' literal or a JSON
+// string, as Python, Node and loggers print them. Characters other than escapes stand for their
+// UTF-8 bytes, and text without a single escape of a byte by its number is not taken as escaped,
+// since it would be just as well read as plain text.
+fn unescape(text: &str) -> Result<Vec<u8>, ConvertError> {
+    let text = text.trim();
+    let text = text.strip_prefix('b').unwrap_or(text);
+    let text = ['"', '\'']
+        .into_iter()
+        .find_map(|quote| text.strip_prefix(quote).and_then(|inner| inner.strip_suffix(quote)))
+        .unwrap_or(text);
+    let numbered = text.match_indices('\\').any(|(at, _)| matches!(text[at + 1..].chars().next(), Some('x' | 'u' | '0'..='7')));
+    if !numbered {
+        return Err(ConvertError::EscapeDecode { position: 0 });
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut bytes = Vec::with_capacity(text.len() / 4);
+    let mut at = 0;
+    while let Some(&c) = chars.get(at) {
+        if c != '\\' {
+            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            at += 1;
+            continue;
+        }
+        let (byte, len) = escaped_byte(&chars[at + 1..]).ok_or(ConvertError::EscapeDecode { position: at })?;
+        bytes.push(byte);
+        at += 1 + len;
+    }
+    Ok(bytes)
+}
+
+// The byte the escape after a backslash stands for, and how many characters it takes up: \xHH,
+// \uHHHH and \u{H…} as far as ff, octal \NNN as far as 377, and the single letter ones
+fn escaped_byte(after: &[char]) -> Option<(u8, usize)> {
+    let number = |digits: &[char], radix| match digits.is_empty() {
+        true => None,
+        false => u32::from_str_radix(&digits.iter().collect::<String>(), radix).ok(),
+    };
+    let (value, len) = match *after.first()? {
+        'x' => (number(after.get(1..3)?, 16)?, 3),
+        'u' if after.get(1) == Some(&'{') => {
+            let close = after.iter().position(|&c| c == '}')?;
+            (number(&after[2..close], 16)?, close + 1)
+        }
+        'u' => (number(after.get(1..5)?, 16)?, 5),
+        '0'..='7' => {
+            let len = after.iter().take(3).take_while(|c| ('0'..='7').contains(*c)).count();
+            (number(&after[..len], 8)?, len)
+        }
+        'n' => (0x0a, 1),
+        'r' => (0x0d, 1),
+        't' => (0x09, 1),
+        'a' => (0x07, 1),
+        'b' => (0x08, 1),
+        'f' => (0x0c, 1),
+        'v' => (0x0b, 1),
+        c @ ('\\' | '\'' | '"' | '/') => (c as u32, 1),
+        _ => return None,
+    };
+    Some((u8::try_from(value).ok()?, len))
+}
+
+// How the MessagePack output pane writes the bytes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OutputEncoding {
+    #[default]
+    Base64,
+    // Without padding, see Encoding::Base64Url
+    Base64Url,
+    Hex,
+    UpperHex,
+    // A space between bytes, as hex dumps and debuggers show them
+    SpacedHex,
+    SpacedUpperHex,
+    // Offsets, bytes and ASCII, as xxd prints them
+    Hexdump,
+}
+
+impl OutputEncoding {
+    pub const ALL: [OutputEncoding; 7] = [
+        OutputEncoding::Base64,
+        OutputEncoding::Base64Url,
+        OutputEncoding::Hex,
+        OutputEncoding::UpperHex,
+        OutputEncoding::SpacedHex,
+        OutputEncoding::SpacedUpperHex,
+        OutputEncoding::Hexdump,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputEncoding::Base64 => "Base64",
+            OutputEncoding::Base64Url => "Base64url",
+            OutputEncoding::Hex => "hex",
+            OutputEncoding::UpperHex => "HEX",
+            OutputEncoding::SpacedHex => tr("hex, spaced"),
+            OutputEncoding::SpacedUpperHex => tr("HEX, spaced"),
+            OutputEncoding::Hexdump => tr("Hexdump"),
+        }
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            OutputEncoding::Base64 => general_purpose::STANDARD.encode(bytes),
+            OutputEncoding::Base64Url => Encoding::Base64Url.encode(bytes),
+            OutputEncoding::Hex => hex::encode(bytes),
+            OutputEncoding::UpperHex => hex::encode_upper(bytes),
+            OutputEncoding::SpacedHex => spaced(&hex::encode(bytes)),
+            OutputEncoding::SpacedUpperHex => spaced(&hex::encode_upper(bytes)),
+            OutputEncoding::Hexdump => hexdump(bytes),
+        }
+    }
+}
+
+// "81a161" as "81 a1 61"
+fn spaced(hex: &str) -> String {
+    let mut out = String::with_capacity(hex.len() * 3 / 2);
+    for (index, pair) in hex.as_bytes().chunks(2).enumerate() {
+        if index > 0 {
+            out.push(' ');
+        }
+        out.push_str(std::str::from_utf8(pair).unwrap_or_default());
+    }
+    out
+}
+
+// What an output pane's Save… button writes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SaveTarget {
+    Json,
+    // The base64 text as the pane shows it
+    MessagePackText,
+    MessagePackBytes,
+}
+
+impl SaveTarget {
+    pub fn extension(self) -> &'static str {
+        match self {
+            SaveTarget::Json => "json",
+            SaveTarget::MessagePackText => "txt",
+            SaveTarget::MessagePackBytes => "msgpack",
+        }
+    }
+
+    // Named after the file the input was loaded from, if it was, next to it
+    pub fn default_name(self, input: Option<&Path>) -> String {
+        match input.filter(|input| input.file_stem().is_some()) {
+            Some(input) => input.with_extension(self.extension()).display().to_string(),
+            None => format!("output.{}", self.extension()),
+        }
+    }
+}
+
+// Raw MessagePack loaded from a file, converted straight from its bytes. The input pane only
+// shows a summary until its text is asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryFile {
+    pub name: String,
+    pub bytes: FileBytes,
+    // How the bytes are shown once they go into the pane as text
+    pub encoding: Encoding,
+}
+
+impl BinaryFile {
+    pub fn new(name: &str, bytes: Vec<u8>, encoding: Encoding) -> BinaryFile {
+        BinaryFile { name: name.to_string(), bytes: bytes.into(), encoding }
+    }
+
+    // e.g. "file: capture.msgpack, 41,238,112 bytes"
+    pub fn summary(&self) -> String {
+        trf("file: {}, {} bytes", &[&self.name, &stats::group_thousands(self.bytes.len())])
+    }
+
+    pub fn text(&self) -> String {
+        self.encoding.encode(&self.bytes)
+    }
+}
+
+// The bytes of a binary file, shared with the worker thread instead of copied. A large file is
+// mapped, so the OS pages it in as it's decoded rather than it being read up front. It must not
+// shrink while mapped, which is why watched files, rewritten in place, are always read.
+#[derive(Clone)]
+pub struct FileBytes(Arc<Backing>);
+
+enum Backing {
+    Read(Vec<u8>),
+    #[cfg(not(target_arch = "wasm32"))]
+    Mapped(memmap2::Mmap),
+}
+
+impl From<Vec<u8>> for FileBytes {
+    fn from(bytes: Vec<u8>) -> FileBytes {
+        FileBytes(Arc::new(Backing::Read(bytes)))
+    }
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &*self.0 {
+            Backing::Read(bytes) => bytes,
+            #[cfg(not(target_arch = "wasm32"))]
+            Backing::Mapped(map) => map,
+        }
+    }
+}
+
+impl PartialEq for FileBytes {
+    fn eq(&self, other: &FileBytes) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl fmt::Debug for FileBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileBytes({} bytes)", self.len())
+    }
+}
+
+// Pane a file's content goes to, as the text that pane holds
+#[derive(Debug, PartialEq)]
+pub enum FileInput {
+    Json(String),
+    // Base64 or hex, as the MessagePack input pane reads it
+    MessagePack(String),
+    Binary(BinaryFile),
+    Schema(String),
+    // Ext types in the line format, see ExtRegistry::from_file
+    ExtTypes(String),
+}
+
+// Decides by content: text is JSON unless it is hex or base64 of valid MessagePack, anything
+// that isn't UTF-8 is raw MessagePack. Text that is neither still goes to the JSON pane, where
+// converting it reports why it doesn't parse.
+pub fn classify(name: &str, bytes: Vec<u8>) -> FileInput {
+    if has_binary_extension(name) {
+        return FileInput::Binary(BinaryFile::new(name, bytes, Encoding::default()));
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => match candidates(&text).first() {
+            Some(InputKind::Hex | InputKind::Base64) => FileInput::MessagePack(text.trim().to_string()),
+            _ => FileInput::Json(text),
+        },
+        Err(e) => FileInput::Binary(BinaryFile::new(name, e.into_bytes(), Encoding::default())),
+    }
+}
+
+// The pane text for a file that was already read
+pub fn file_input(name: &str, bytes: Vec<u8>, target: FileTarget) -> Result<FileInput, String> {
+    match target {
+        FileTarget::Sniff => Ok(classify(name, bytes)),
+        FileTarget::Json => String::from_utf8(bytes).map(FileInput::Json).map_err(|e| {
+            trf("{} is not UTF-8 text (invalid byte at offset {}), open it as MessagePack instead", &[
+                &name,
+                &e.utf8_error().valid_up_to(),
+            ])
+        }),
+        FileTarget::Schema => String::from_utf8(bytes).map(FileInput::Schema).map_err(|e| {
+            trf("{} is not UTF-8 text (invalid byte at offset {})", &[&name, &e.utf8_error().valid_up_to()])
+        }),
+        FileTarget::ExtTypes => {
+            let text = String::from_utf8(bytes).map_err(|e| {
+                trf("{} is not UTF-8 text (invalid byte at offset {})", &[&name, &e.utf8_error().valid_up_to()])
+            })?;
+            ExtRegistry::from_file(&text).map(FileInput::ExtTypes).map_err(|e| format!("{}: {}", name, e))
+        }
+        FileTarget::MessagePack(encoding) => Ok(FileInput::Binary(BinaryFile::new(name, bytes, encoding))),
+    }
+}
+
+fn has_binary_extension(name: &str) -> bool {
+    let extension = Path::new(name).extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    extension.is_some_and(|e| BINARY_EXTENSIONS.contains(&e.as_str()))
+}
+
+pub fn open_file(path: &Path, target: FileTarget) -> Result<FileInput, String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if file_size(path)? > MAP_ABOVE_BYTES {
+        if let Some(input) = map_binary_file(path, target)? {
+            return Ok(input);
+        }
+    }
+    file_input(&file_name(path), read_file(path)?, target)
+}
+
+// A large file that goes in as raw MessagePack, mapped. None for one that goes into a pane as
+// text, which needs a String of its own anyway.
+#[cfg(not(target_arch = "wasm32"))]
+fn map_binary_file(path: &Path, target: FileTarget) -> Result<Option<FileInput>, String> {
+    let encoding = match target {
+        FileTarget::MessagePack(encoding) => encoding,
+        FileTarget::Sniff => Encoding::default(),
+        FileTarget::Json | FileTarget::Schema | FileTarget::ExtTypes => return Ok(None),
+    };
+    let failed = |e| String::from(read_failed(path, e));
+    let file = fs::File::open(path).map_err(failed)?;
+    // SAFETY: the map is only ever read. Another program truncating the file while it's open
+    // would fault the reads, the same risk every tool that maps its input takes.
+    let map = unsafe { memmap2::Mmap::map(&file) }.map_err(failed)?;
+    let name = file_name(path);
+    if target == FileTarget::Sniff && !has_binary_extension(&name) && std::str::from_utf8(&map).is_ok() {
+        return Ok(None);
+    }
+    Ok(Some(FileInput::Binary(BinaryFile { name, bytes: FileBytes(Arc::new(Backing::Mapped(map))), encoding })))
+}
+
+pub fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+// Read as bytes, binary files never go through a String
+pub fn read_file(path: &Path) -> Result<Vec<u8>, ConvertError> {
+    fs::read(path).map_err(|e| read_failed(path, e))
+}
+
+pub fn write_file(path: &Path, bytes: &[u8]) -> Result<(), ConvertError> {
+    fs::write(path, bytes).map_err(|e| write_failed(path, e))
+}
+
+pub fn file_size(path: &Path) -> Result<u64, ConvertError> {
+    fs::metadata(path).map(|metadata| metadata.len()).map_err(|e| read_failed(path, e))
+}
+
+pub fn read_failed(path: &Path, e: std::io::Error) -> ConvertError {
+    ConvertError::ReadFile { path: path.to_path_buf(), msg: e.to_string() }
+}
+
+pub fn write_failed(path: &Path, e: std::io::Error) -> ConvertError {
+    ConvertError::WriteFile { path: path.to_path_buf(), msg: e.to_string() }
+}
+
+
+/* Tests */
+#[test]
+fn test_classify_by_content() {
+    assert_eq!(classify("data.json", br#"{"a": 1}"#.to_vec()), FileInput::Json(r#"{"a": 1}"#.to_string()));
+    assert_eq!(classify("payload.txt", b"81a16101\n".to_vec()), FileInput::MessagePack("81a16101".to_string()));
+    assert_eq!(classify("payload.txt", b"gaFhAQ==".to_vec()), FileInput::MessagePack("gaFhAQ==".to_string()));
+    // Not UTF-8, so raw bytes
+    let FileInput::Binary(file) = classify("payload", vec![0x81, 0xa1, 0x61, 0x01]) else {
+        panic!("not binary");
+    };
+    assert_eq!(file.text(), "gaFhAQ==");
+    // Broken JSON stays JSON so its parse error shows up
+    assert_eq!(classify("notes.txt", b"{\"a\": ".to_vec()), FileInput::Json("{\"a\": ".to_string()));
+}
+
+#[cfg(test)]
+fn fixture(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("messagepack_to_json_{}_{}", std::process::id(), name));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[test]
+fn test_open_file_reads_the_pane_it_was_opened_from() {
+    let alice = hex::decode("83a36167651ea463697479aa576f6e6465726c616e64a46e616d65a5416c696365").unwrap();
+    let binary = fixture("alice.msgpack", &alice);
+    let Ok(FileInput::Binary(file)) = open_file(&binary, FileTarget::MessagePack(Encoding::Base64)) else {
+        panic!("not binary");
+    };
+    assert_eq!(&*file.bytes, &alice[..]);
+    assert!(file.name.ends_with("alice.msgpack"));
+    assert!(file.summary().ends_with("alice.msgpack, 33 bytes"));
+    assert_eq!(file.text(), "g6NhZ2UepGNpdHmqV29uZGVybGFuZKRuYW1lpUFsaWNl");
+    let Ok(FileInput::Binary(file)) = open_file(&binary, FileTarget::MessagePack(Encoding::Hex)) else {
+        panic!("not binary");
+    };
+    assert_eq!(file.text(), hex::encode(&alice));
+    let error = open_file(&binary, FileTarget::Json).unwrap_err();
+    assert!(error.contains("is not UTF-8 text (invalid byte at offset 0)"), "{}", error);
+
+    let json = fixture("alice.json", br#"{"name": "Alice"}"#);
+    assert_eq!(open_file(&json, FileTarget::Json), Ok(FileInput::Json(r#"{"name": "Alice"}"#.to_string())));
+    fs::remove_file(binary).unwrap();
+    fs::remove_file(json).unwrap();
+
+    let missing = std::env::temp_dir().join("messagepack_to_json_missing.json");
+    assert!(open_file(&missing, FileTarget::Json).unwrap_err().starts_with("Failed to read"));
+    assert!(file_size(&missing).is_err());
+}
+
+#[test]
+fn test_classify_binary_extensions_skip_sniffing() {
+    // A lone positive fixint is valid UTF-8 and even valid JSON
+    assert_eq!(classify("one.msgpack", b"1".to_vec()), FileInput::Binary(BinaryFile::new("one.msgpack", b"1".to_vec(), Encoding::Base64)));
+    assert_eq!(classify("one.BIN", b"1".to_vec()), FileInput::Binary(BinaryFile::new("one.BIN", b"1".to_vec(), Encoding::Base64)));
+    assert_eq!(classify("one.txt", b"1".to_vec()), FileInput::Json("1".to_string()));
+}
+
+#[test]
+fn test_saved_outputs_are_named_after_their_input() {
+    assert_eq!(SaveTarget::Json.default_name(None), "output.json");
+    assert_eq!(SaveTarget::Json.default_name(Some(Path::new("captures/login.msgpack"))), Path::new("captures/login.json").display().to_string());
+    assert_eq!(SaveTarget::MessagePackBytes.default_name(Some(Path::new("config.json"))), "config.msgpack");
+}
+
+#[test]
+fn test_output_encodings() {
+    let bytes = [0x81, 0xa1, 0x61, 0x01];
+    let written = OutputEncoding::ALL.map(|encoding| encoding.encode(&bytes));
+    assert_eq!(written[..6], ["gaFhAQ==", "gaFhAQ", "81a16101", "81A16101", "81 a1 61 01", "81 A1 61 01"]);
+    assert_eq!(written[6], "00000000: 81a1 6101                                ..a.\n");
+    assert_eq!(OutputEncoding::SpacedHex.encode(&[]), "");
+    // Each reads back as the bytes in the input pane, and all but the hexdump as either encoding
+    for text in &written {
+        assert_eq!(InputEncoding::Auto.decode(text).unwrap().1, bytes);
+    }
+    for text in &written[..6] {
+        assert_eq!(Encoding::decode_any(text).unwrap().1, bytes);
+    }
+}
+
+#[test]
+fn test_hex_digits_skip_what_debuggers_and_c_add() {
+    assert_eq!(hex_digits("81a1"), Some("81a1".to_string()));
+    assert_eq!(hex_digits("0x81a1"), Some("81a1".to_string()));
+    assert_eq!(hex_digits("0x81, 0xA1,\n0x61,0x01"), Some("81A16101".to_string()));
+    assert_eq!(hex_digits(" 81 a1\r\n61 01 "), Some("81a16101".to_string()));
+    assert_eq!(hex_digits(""), Some(String::new()));
+    assert_eq!(hex_digits("0x81; 0xa1"), None);
+    assert_eq!(hex_digits("gaFhAQ=="), None);
+    assert_eq!(Encoding::Hex.decode(b"0x81, 0xa1,\n0x61, 0x01").unwrap(), [0x81, 0xa1, b'a', 0x01]);
+}
+
+#[test]
+fn test_input_encodings_are_detected_or_forced() {
+    use InputEncoding::*;
+    // {"a": 1}
+    let bytes = vec![0x81, 0xa1, b'a', 0x01];
+    assert_eq!(Auto.decode("81a16101").unwrap(), (Hex, bytes.clone()));
+    assert_eq!(Auto.decode("gaFhAQ==").unwrap(), (Base64, bytes.clone()));
+    assert_eq!(Auto.decode(r"b'\x81\xa1a\x01'").unwrap(), (Escaped, bytes.clone()));
+    assert_eq!(Auto.decode(r#""\x81\xa1\x61\x01""#).unwrap(), (Escaped, bytes.clone()));
+    // [-1, -1] in base64url, and not standard base64
+    assert_eq!(Auto.decode("kv__").unwrap(), (Base64Url, vec![0x92, 0xff, 0xff]));
+    // All hex digits, but only MessagePack read as base64: as hex it's a string cut short, as
+    // base64 three fixints. Text that is neither is read as the first that decodes.
+    assert_eq!(Auto.decode("ADAD").unwrap(), (Base64, vec![0x00, 0x30, 0x03]));
+    assert_eq!(Auto.decode("dead").unwrap(), (Hex, vec![0xde, 0xad]));
+    assert_eq!(Auto.decode("00ff1"), Err(ConvertError::HexDecode(hex::FromHexError::OddLength)));
+    assert!(matches!(Auto.decode("not base64!"), Err(ConvertError::Base64Decode(_))));
+    assert_eq!(Auto.decode(r"\x81\q"), Err(ConvertError::EscapeDecode { position: 4 }));
+
+    // Forced, text is read the one way even when another gives MessagePack
+    assert_eq!(Base64.decode("c0c0").unwrap(), (Base64, vec![0x73, 0x47, 0x34]));
+    assert_eq!(Hex.decode("0xc0 0xc0").unwrap(), (Hex, vec![0xc0, 0xc0]));
+    assert!(Base64.decode("kv__").is_err());
+    assert_eq!(Base64.decode("gaFhAQ").unwrap().1, bytes);
+    assert_eq!(Base64Url.decode("gaFhAQ==").unwrap().1, bytes);
+    assert!(Escaped.decode("c0").is_err());
+    assert_eq!(Escaped.decode(r"\x00\n\\é").unwrap().1, [0, b'\n', b'\\', 0xc3, 0xa9]);
+    assert_eq!(unescape(r"\x8"), Err(ConvertError::EscapeDecode { position: 0 }));
+}
+
+#[test]
+fn test_escaped_strings_from_python_node_and_logs() {
+    // {"age": 30}
+    let bytes = vec![0x81, 0xa3, b'a', b'g', b'e', 0x1e];
+    for text in [
+        r"\x81\xa3age\x1e",
+        r"b'\x81\xa3age\x1e'",
+        r#""\u0081\u00a3age\u001e""#,
+        r"\u{81}\u{a3}age\u{1e}",
+        r"\201\243age\036",
+    ] {
+        assert_eq!(InputEncoding::Auto.decode(text).unwrap(), (InputEncoding::Escaped, bytes.clone()), "{}", text);
+    }
+    assert_eq!(unescape(r"\0\12\a\b\f\v\/\u0000").unwrap(), [0, 0x0a, 0x07, 0x08, 0x0c, 0x0b, b'/', 0]);
+    // Past a byte, or cut short
+    assert_eq!(unescape(r"\u0100"), Err(ConvertError::EscapeDecode { position: 0 }));
+    assert_eq!(unescape(r"a\400"), Err(ConvertError::EscapeDecode { position: 1 }));
+    assert_eq!(unescape(r"\x81\u{81"), Err(ConvertError::EscapeDecode { position: 4 }));
+    assert_eq!(unescape(r"\x81\u00"), Err(ConvertError::EscapeDecode { position: 4 }));
+    // Only letter escapes are no reason to take text as escaped bytes
+    assert!(unescape(r"a\nb").is_err());
+}
+
+#[test]
+fn test_byte_array_literals_are_read() {
+    use InputEncoding::*;
+    // {"a": 1}
+    let bytes = vec![0x81, 0xa1, b'a', 0x01];
+    for text in [
+        "{0x81, 0xa1, 0x61, 0x01}",
+        "static const uint8_t data[] = {\n    0x81, 0xA1, 0x61, 0x1,\n};",
+        "let data: &[u8] = &[129, 161, 97, 1];",
+        "vec![0x81u8, 161u8, 0x61, 1]",
+        "[129, 161, 97, 1]",
+        "129, 161, 97, 1",
+    ] {
+        assert_eq!(Auto.decode(text).unwrap(), (Array, bytes.clone()), "{:?}", text);
+    }
+    assert_eq!(Array.decode("[]").unwrap(), (Array, Vec::new()));
+    // Without a bracket or comma it's hex to Auto, but can still be read as one number
+    assert_eq!(Auto.decode("12").unwrap(), (Hex, vec![0x12]));
+    assert_eq!(Array.decode("12").unwrap(), (Array, vec![12]));
+    assert_eq!(Auto.decode("[129, 256]"), Err(ConvertError::ByteArray("256".to_string())));
+    assert_eq!(Array.decode("{0x81, 0xg1}"), Err(ConvertError::ByteArray("0xg1".to_string())));
+}
+
+#[test]
+fn test_hexdumps_are_detected() {
+    // {"a": 1} as xxd and hexdump -C print it
+    let bytes = vec![0x81, 0xa1, b'a', 0x01];
+    assert_eq!(InputEncoding::Auto.decode("00000000: 81a1 6101                                ..a.\n").unwrap(), (InputEncoding::Hexdump, bytes.clone()));
+    assert_eq!(InputEncoding::Auto.decode("00000000  81 a1 61 01  |..a.|\n00000004\n").unwrap(), (InputEncoding::Hexdump, bytes));
+}
+
+#[test]
+fn test_decode_any_takes_either_encoding() {
+    // Not MessagePack, which doesn't matter here
+    let bytes = [0x00, 0xff, 0xc1, 0x10, 0x7e];
+    for from in Encoding::ALL {
+        for to in Encoding::ALL {
+            let (detected, decoded) = Encoding::decode_any(&from.encode(&bytes)).unwrap();
+            assert_eq!((detected, &decoded[..]), (from, &bytes[..]));
+            assert_eq!(Encoding::decode_any(&to.encode(&decoded)).unwrap().1, bytes, "{:?} to {:?}", from, to);
+        }
+    }
+    assert_eq!(Encoding::decode_any("0x00 0xFF 0xc1\n0x10 7e").unwrap(), (Encoding::Hex, bytes.to_vec()));
+    assert_eq!(Encoding::decode_any(" AP/B\nEH4 ").unwrap(), (Encoding::Base64, bytes.to_vec()));
+    assert_eq!(Encoding::decode_any("AP_BEH4").unwrap(), (Encoding::Base64Url, bytes.to_vec()));
+    assert!(matches!(Encoding::decode_any("00ff1"), Err(ConvertError::HexDecode(_))));
+    assert!(matches!(Encoding::decode_any("not base64!"), Err(ConvertError::Base64Decode(_))));
+}
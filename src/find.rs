@@ -0,0 +1,175 @@
+use crate::locale::{tr, trf};
+use std::ops::Range;
+
+#[derive(Default)]
+pub struct FindState {
+    pub open: bool,
+    pub query: String,
+    pub case_sensitive: bool,
+    matches: Vec<Range<usize>>,
+    active: usize,
+    // What the matches were computed from, so they are only recomputed when something changed
+    searched: Option<(String, bool, usize, u64)>,
+    // Set when the active match moved and the pane should scroll to it on the next frame
+    pub scroll_pending: bool,
+}
+
+impl FindState {
+    pub fn update(&mut self, text: &str) {
+        let key = (self.query.clone(), self.case_sensitive, text.len(), text_fingerprint(text));
+        if self.searched.as_ref() == Some(&key) {
+            return;
+        }
+        self.matches = find_matches(text, &self.query, self.case_sensitive);
+        self.active = 0;
+        self.scroll_pending = !self.matches.is_empty();
+        self.searched = Some(key);
+    }
+
+    pub fn matches(&self) -> &[Range<usize>] {
+        &self.matches
+    }
+
+    pub fn active_index(&self) -> Option<usize> {
+        if self.matches.is_empty() {
+            None
+        } else {
+            Some(self.active)
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.matches.is_empty() {
+            self.active = (self.active + 1) % self.matches.len();
+            self.scroll_pending = true;
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.active = (self.active + self.matches.len() - 1) % self.matches.len();
+            self.scroll_pending = true;
+        }
+    }
+
+    // "3 of 12", or "No matches" once there is a query to match
+    pub fn summary(&self) -> String {
+        match self.active_index() {
+            Some(active) => trf("{} of {}", &[&(active + 1), &self.matches.len()]),
+            None if self.query.is_empty() => String::new(),
+            None => tr("No matches").to_string(),
+        }
+    }
+}
+
+// Cheap change detection for large outputs without keeping a second copy of the text around
+pub fn text_fingerprint(text: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Non-overlapping byte ranges of every occurrence of `query` in `text`
+pub fn find_matches(text: &str, query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    if case_sensitive {
+        return text.match_indices(query).map(|(start, m)| start..start + m.len()).collect();
+    }
+
+    // Lowercasing can change byte lengths, so compare char by char to keep offsets into the original text
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let mut matches = Vec::new();
+    let mut search_from = 0;
+    for (start, _) in text.char_indices() {
+        if start < search_from {
+            continue;
+        }
+        if let Some(end) = match_at(text, start, &query) {
+            matches.push(start..end);
+            search_from = end;
+        }
+    }
+    matches
+}
+
+fn match_at(text: &str, start: usize, query: &[char]) -> Option<usize> {
+    let mut remaining = query;
+    for (offset, c) in text[start..].char_indices() {
+        for lower in c.to_lowercase() {
+            match remaining.split_first() {
+                Some((&expected, rest)) if expected == lower => remaining = rest,
+                _ => return None,
+            }
+        }
+        if remaining.is_empty() {
+            return Some(start + offset + c.len_utf8());
+        }
+    }
+    None
+}
+
+
+/* Tests */
+#[test]
+fn test_find_matches_case_insensitive_by_default() {
+    let text = r#"{"Name": "name", "NAME": 1}"#;
+    assert_eq!(find_matches(text, "name", false), vec![2..6, 10..14, 18..22]);
+    assert_eq!(find_matches(text, "name", true), vec![10..14]);
+}
+
+#[test]
+fn test_find_matches_non_overlapping_and_empty_query() {
+    assert_eq!(find_matches("aaaa", "aa", false), vec![0..2, 2..4]);
+    assert!(find_matches("abc", "", false).is_empty());
+    assert!(find_matches("abc", "abcd", false).is_empty());
+}
+
+#[test]
+fn test_find_matches_keeps_byte_offsets_for_multibyte_text() {
+    let text = "Ünïcode ünïcode";
+    let matches = find_matches(text, "ÜNÏ", false);
+    assert_eq!(matches.len(), 2);
+    assert_eq!(&text[matches[0].clone()], "Ünï");
+    assert_eq!(&text[matches[1].clone()], "ünï");
+}
+
+#[test]
+fn test_find_state_navigation_wraps_around() {
+    let mut find = FindState {
+        query: "a".to_string(),
+        ..Default::default()
+    };
+    find.update("a b a b a");
+    assert_eq!(find.summary(), "1 of 3");
+    find.next();
+    find.next();
+    assert_eq!(find.active_index(), Some(2));
+    find.next();
+    assert_eq!(find.active_index(), Some(0));
+    find.previous();
+    assert_eq!(find.summary(), "3 of 3");
+}
+
+#[test]
+fn test_find_state_resets_when_text_or_query_changes() {
+    let mut find = FindState {
+        query: "x".to_string(),
+        ..Default::default()
+    };
+    find.update("x x");
+    find.next();
+    assert_eq!(find.active_index(), Some(1));
+
+    find.update("x x");
+    assert_eq!(find.active_index(), Some(1));
+
+    find.update("x x x");
+    assert_eq!(find.summary(), "1 of 3");
+
+    find.query = "y".to_string();
+    find.update("x x x");
+    assert_eq!(find.summary(), "No matches");
+}
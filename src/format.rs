@@ -0,0 +1,89 @@
+use crate::error::ConvertError;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{Read, Write};
+
+// How JSON text is laid out wherever the app writes it back out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JsonFormat {
+    pub indent: usize,
+    // Otherwise keys keep the order they were parsed or decoded in
+    pub sort_keys: bool,
+}
+
+impl Default for JsonFormat {
+    fn default() -> Self {
+        JsonFormat { indent: 2, sort_keys: true }
+    }
+}
+
+impl JsonFormat {
+    pub fn parse(&self, text: &str) -> Result<Value, ConvertError> {
+        let mut value: Value = serde_json::from_str(text)
+            .map_err(|e| ConvertError::parse_json(&e))?;
+        self.order_keys(&mut value);
+        Ok(value)
+    }
+
+    // Same as `parse`, for input that has to go through a reader, e.g. a cancellable one
+    pub fn parse_reader(&self, reader: impl Read) -> Result<Value, ConvertError> {
+        let mut value: Value = serde_json::from_reader(reader)
+            .map_err(|e| ConvertError::parse_json(&e))?;
+        self.order_keys(&mut value);
+        Ok(value)
+    }
+
+    pub fn order_keys(&self, value: &mut Value) {
+        if self.sort_keys {
+            sort_keys(value);
+        }
+    }
+
+    pub fn pretty(&self, value: &Value) -> Result<String, ConvertError> {
+        let mut out = Vec::new();
+        self.write_pretty(value, &mut out)?;
+        String::from_utf8(out).map_err(|e| ConvertError::SerializeJson(e.to_string()))
+    }
+
+    pub fn write_pretty(&self, value: &Value, writer: impl Write) -> Result<(), ConvertError> {
+        let indent = " ".repeat(self.indent);
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+        value.serialize(&mut serde_json::Serializer::with_formatter(writer, formatter))
+            .map_err(|e| ConvertError::SerializeJson(e.to_string()))
+    }
+
+    pub fn minified(&self, value: &Value) -> Result<String, ConvertError> {
+        serde_json::to_string(value).map_err(|e| ConvertError::SerializeJson(e.to_string()))
+    }
+}
+
+fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.sort_keys();
+            map.values_mut().for_each(sort_keys);
+        }
+        Value::Array(items) => items.iter_mut().for_each(sort_keys),
+        _ => {}
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_pretty_uses_indent_and_key_order() {
+    let text = r#"{"b": [1, {"d": 1, "c": 2}], "a": null}"#;
+    let sorted = JsonFormat { indent: 4, sort_keys: true };
+    let value = sorted.parse(text).unwrap();
+    assert_eq!(sorted.pretty(&value).unwrap(), "{\n    \"a\": null,\n    \"b\": [\n        1,\n        {\n            \"c\": 2,\n            \"d\": 1\n        }\n    ]\n}");
+
+    let unsorted = JsonFormat { indent: 2, sort_keys: false };
+    let value = unsorted.parse(text).unwrap();
+    assert_eq!(unsorted.minified(&value).unwrap(), r#"{"b":[1,{"d":1,"c":2}],"a":null}"#);
+}
+
+#[test]
+fn test_parse_reports_position() {
+    let err = JsonFormat::default().parse("{\"a\": }").unwrap_err();
+    assert!(matches!(err, ConvertError::JsonParse { line: 1, column: 7, .. }));
+}
@@ -0,0 +1,176 @@
+use crate::convert::{trailing_bytes, ConvertOptions};
+use crate::error::ConvertError;
+use crate::warning::Warning;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::ops::Range;
+
+// Records of a stream that each carry their length in front of them, the way many TCP protocols
+// frame their messages. The length counts the bytes after the prefix.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Framing {
+    // Values back to back, each one ending where the next starts
+    #[default]
+    None,
+    U16Be,
+    U32Be,
+    U32Le,
+    // Unsigned LEB128: seven bits a byte, the lowest first, as in Protocol Buffers
+    Varint,
+}
+
+// Bytes the varint of the longest length takes
+const MAX_VARINT: usize = 10;
+
+impl Framing {
+    pub const ALL: [Framing; 5] = [Framing::None, Framing::U16Be, Framing::U32Be, Framing::U32Le, Framing::Varint];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Framing::None => "None",
+            Framing::U16Be => "u16 big-endian",
+            Framing::U32Be => "u32 big-endian",
+            Framing::U32Le => "u32 little-endian",
+            Framing::Varint => "Varint",
+        }
+    }
+
+    // Where the payload of every frame in `bytes` is. Without framing that's all of them.
+    pub fn split(self, bytes: &[u8]) -> Result<Vec<Range<usize>>, ConvertError> {
+        let mut frames = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let index = frames.len();
+            let (declared, start) = self.length_at(bytes, offset).ok_or(ConvertError::FramePrefix { index, offset })?;
+            let available = bytes.len() - start;
+            if declared > available {
+                return Err(ConvertError::ShortFrame { index, offset, start, declared, available });
+            }
+            frames.push(start..start + declared);
+            offset = start + declared;
+        }
+        Ok(frames)
+    }
+
+    // The length the prefix at `offset` declares and where the payload starts after it. None
+    // when the prefix is cut short, or a varint runs on past any length.
+    fn length_at(self, bytes: &[u8], offset: usize) -> Option<(usize, usize)> {
+        let rest = &bytes[offset..];
+        let prefix = |width: usize| rest.get(..width).map(|prefix| (prefix, offset + width));
+        match self {
+            Framing::None => Some((rest.len(), offset)),
+            Framing::U16Be => prefix(2).map(|(p, start)| (u16::from_be_bytes([p[0], p[1]]) as usize, start)),
+            Framing::U32Be => prefix(4).map(|(p, start)| (u32::from_be_bytes([p[0], p[1], p[2], p[3]]) as usize, start)),
+            Framing::U32Le => prefix(4).map(|(p, start)| (u32::from_le_bytes([p[0], p[1], p[2], p[3]]) as usize, start)),
+            Framing::Varint => {
+                let mut length = 0u64;
+                for (index, &byte) in rest.iter().take(MAX_VARINT).enumerate() {
+                    // The last byte only has the top bit of a u64 left to give
+                    if index == MAX_VARINT - 1 && byte > 1 {
+                        return None;
+                    }
+                    length |= u64::from(byte & 0x7f) << (7 * index);
+                    if byte & 0x80 == 0 {
+                        return usize::try_from(length).ok().map(|length| (length, offset + index + 1));
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    // `payload` and the prefix in front of it, onto the end of `out`
+    pub fn frame(self, payload: &[u8], out: &mut Vec<u8>) -> Result<(), ConvertError> {
+        let too_long = |limit: u32| ConvertError::FrameTooLong { length: payload.len(), limit: limit as usize };
+        match self {
+            Framing::None => {}
+            Framing::U16Be => out.extend(u16::try_from(payload.len()).map_err(|_| too_long(u16::MAX.into()))?.to_be_bytes()),
+            Framing::U32Be => out.extend(u32::try_from(payload.len()).map_err(|_| too_long(u32::MAX))?.to_be_bytes()),
+            Framing::U32Le => out.extend(u32::try_from(payload.len()).map_err(|_| too_long(u32::MAX))?.to_le_bytes()),
+            Framing::Varint => {
+                let mut length = payload.len();
+                while length >= 0x80 {
+                    out.push(length as u8 | 0x80);
+                    length >>= 7;
+                }
+                out.push(length as u8);
+            }
+        }
+        out.extend_from_slice(payload);
+        Ok(())
+    }
+}
+
+// One value from each frame, decoded the way `options` say. Errors keep their offsets into all of
+// `bytes`, and a value that doesn't fill its frame is an error unless it's lossy.
+pub fn decode_frames(bytes: &[u8], frames: &[Range<usize>], options: &ConvertOptions, warnings: &mut Vec<Warning>) -> Result<Vec<Value>, ConvertError> {
+    frames.iter().map(|frame| {
+        let (value, end) = options.decode(&bytes[..frame.end], frame.start, warnings)?;
+        trailing_bytes(end, frame.end, options.lossy, warnings)?;
+        Ok(value)
+    }).collect()
+}
+
+
+/* Tests */
+#[test]
+fn test_every_framing_splits_what_it_frames() {
+    let records: [&[u8]; 3] = [&[0x81, 0xa1, 0x61, 0x01], &[], &[0xc0; 300]];
+    for framing in Framing::ALL {
+        let mut framed = Vec::new();
+        for record in records {
+            framing.frame(record, &mut framed).unwrap();
+        }
+        let frames = framing.split(&framed).unwrap();
+        if framing == Framing::None {
+            assert_eq!(frames, vec![0..framed.len()]);
+            continue;
+        }
+        let payloads: Vec<_> = frames.into_iter().map(|frame| &framed[frame]).collect();
+        assert_eq!(payloads, records, "{:?}", framing);
+    }
+    let mut framed = Vec::new();
+    Framing::U32Be.frame(&[0x01, 0x02], &mut framed).unwrap();
+    Framing::U32Le.frame(&[0x03], &mut framed).unwrap();
+    Framing::Varint.frame(&[0xc0; 300], &mut framed).unwrap();
+    assert_eq!(framed[..11], [0, 0, 0, 2, 1, 2, 1, 0, 0, 0, 3]);
+    assert_eq!(framed[11..13], [0xac, 0x02]);
+    assert_eq!(Framing::U16Be.frame(&[0; 70_000], &mut Vec::new()), Err(ConvertError::FrameTooLong { length: 70_000, limit: 65_535 }));
+}
+
+#[test]
+fn test_a_short_last_frame_is_reported_with_its_offsets() {
+    let mut framed = Vec::new();
+    Framing::U32Be.frame(&[0xc3], &mut framed).unwrap();
+    framed.extend([0, 0, 0, 9, 0x92, 0x01]);
+    assert_eq!(Framing::U32Be.split(&framed), Err(ConvertError::ShortFrame { index: 1, offset: 5, start: 9, declared: 9, available: 2 }));
+    assert_eq!(
+        Framing::U32Be.split(&framed).unwrap_err().to_string(),
+        "Frame 1 at offset 0x5 declares 9 bytes from offset 0x9, but only 2 are left"
+    );
+    assert_eq!(Framing::U16Be.split(&[0x00, 0x01, 0xc0, 0x00]), Err(ConvertError::FramePrefix { index: 1, offset: 3 }));
+    assert_eq!(Framing::Varint.split(&[0x80; 11]), Err(ConvertError::FramePrefix { index: 0, offset: 0 }));
+
+    // Frames found further into the input report where they are in all of it
+    let error = Framing::U32Be.split(&framed).unwrap_err().shifted(16);
+    assert_eq!(error, ConvertError::ShortFrame { index: 1, offset: 21, start: 25, declared: 9, available: 2 });
+    assert_eq!(error.offset(), Some(21));
+    assert_eq!(Framing::U16Be.split(&[0x00, 0x01, 0xc0, 0x00]).unwrap_err().shifted(16).offset(), Some(19));
+}
+
+#[test]
+fn test_decode_frames() {
+    let mut framed = Vec::new();
+    Framing::Varint.frame(&[0x81, 0xa1, 0x61, 0x01], &mut framed).unwrap();
+    Framing::Varint.frame(&[0x01, 0x02], &mut framed).unwrap();
+    let frames = Framing::Varint.split(&framed).unwrap();
+    let mut warnings = Vec::new();
+    assert_eq!(decode_frames(&framed, &frames, &ConvertOptions::default().lossy(true), &mut warnings), Ok(vec![serde_json::json!({"a": 1}), 1.into()]));
+    assert_eq!(warnings, [Warning::new(crate::warning::WarningKind::TrailingBytes, "1 bytes after the value ignored".to_string()).at_offset(7)]);
+    assert_eq!(decode_frames(&framed, &frames, &ConvertOptions::default(), &mut Vec::new()), Err(ConvertError::TrailingBytes(1)));
+    // A value running past its frame fails there instead of reading into the next one
+    framed[0] = 3;
+    let frames = Framing::Varint.split(&framed).unwrap();
+    assert!(matches!(decode_frames(&framed, &frames, &ConvertOptions::default(), &mut Vec::new()), Err(ConvertError::MsgpackDecode { .. })));
+}
@@ -0,0 +1,467 @@
+use crate::decode::decode_value_at;
+use crate::files::{BinaryFile, Encoding, FileInput};
+use crate::format::JsonFormat;
+use crate::locale::{tr, trf};
+
+// Random MessagePack for benchmarking and fuzzing decoders. The same seed and config always give
+// the same bytes, which is why the random numbers come from a SplitMix64 of our own rather than a
+// crate whose streams may change between versions.
+
+// Values in one payload at most, containers come out shorter once they're used up
+const MAX_VALUES: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Types {
+    // Every width from fixint to 64 bits, signed and unsigned
+    pub ints: bool,
+    // float 32 and float 64
+    pub floats: bool,
+    pub bools: bool,
+    pub nil: bool,
+    pub strings: bool,
+    pub bins: bool,
+    pub exts: bool,
+    pub timestamps: bool,
+    pub arrays: bool,
+    pub maps: bool,
+}
+
+impl Types {
+    // Payloads with these go to the MessagePack pane as they are, JSON can't hold them
+    pub fn beyond_json(&self) -> bool {
+        self.bins || self.exts || self.timestamps
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenerateConfig {
+    pub seed: u64,
+    // Levels of containers at most, the root counting as the first
+    pub max_depth: usize,
+    // Inclusive ranges
+    pub map_width: (usize, usize),
+    pub array_length: (usize, usize),
+    // In characters for strings and map keys, in bytes for bins and exts
+    pub string_length: (usize, usize),
+    pub types: Types,
+}
+
+impl Default for GenerateConfig {
+    fn default() -> Self {
+        GenerateConfig {
+            seed: 1,
+            max_depth: 3,
+            map_width: (1, 6),
+            array_length: (0, 8),
+            string_length: (0, 16),
+            types: Types {
+                ints: true,
+                floats: true,
+                bools: true,
+                nil: true,
+                strings: true,
+                bins: false,
+                exts: false,
+                timestamps: false,
+                arrays: true,
+                maps: true,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Nil,
+    Bool,
+    Int,
+    Float,
+    String,
+    Bin,
+    Ext,
+    Timestamp,
+    Array,
+    Map,
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Anything from `min` to `max`, both included
+    fn between(&mut self, min: u64, max: u64) -> u64 {
+        match (max - min).checked_add(1) {
+            Some(span) => min + self.next() % span,
+            None => self.next(),
+        }
+    }
+
+    fn range(&mut self, (min, max): (usize, usize)) -> usize {
+        self.between(min as u64, max.max(min) as u64) as usize
+    }
+
+    fn pick<T: Copy>(&mut self, choices: &[T]) -> T {
+        choices[self.next() as usize % choices.len()]
+    }
+}
+
+struct Generator<'a> {
+    config: &'a GenerateConfig,
+    rng: Rng,
+    out: Vec<u8>,
+    // Values still to be written before containers have to stay empty
+    budget: usize,
+}
+
+pub fn generate(config: &GenerateConfig) -> Vec<u8> {
+    let mut generator = Generator { config, rng: Rng(config.seed), out: Vec::new(), budget: MAX_VALUES - 1 };
+    // The root is a container whenever one is allowed, a payload of one scalar tests little
+    let root = if config.max_depth == 0 {
+        None
+    } else if config.types.maps {
+        Some(Kind::Map)
+    } else if config.types.arrays {
+        Some(Kind::Array)
+    } else {
+        None
+    };
+    match root {
+        Some(kind) => generator.write(kind, 1),
+        None => generator.value(1),
+    }
+    generator.out
+}
+
+impl Generator<'_> {
+    fn value(&mut self, depth: usize) {
+        let types = &self.config.types;
+        let nests = depth <= self.config.max_depth && self.budget > 0;
+        let kinds: Vec<Kind> = [
+            (types.nil, Kind::Nil),
+            (types.bools, Kind::Bool),
+            (types.ints, Kind::Int),
+            (types.floats, Kind::Float),
+            (types.strings, Kind::String),
+            (types.bins, Kind::Bin),
+            (types.exts, Kind::Ext),
+            (types.timestamps, Kind::Timestamp),
+            (types.arrays && nests, Kind::Array),
+            (types.maps && nests, Kind::Map),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, kind)| enabled.then_some(kind))
+        .collect();
+        let kind = if kinds.is_empty() { Kind::Nil } else { self.rng.pick(&kinds) };
+        self.write(kind, depth);
+    }
+
+    fn write(&mut self, kind: Kind, depth: usize) {
+        match kind {
+            Kind::Nil => self.out.push(0xc0),
+            Kind::Bool => self.out.push(if self.rng.next() & 1 == 1 { 0xc3 } else { 0xc2 }),
+            Kind::Int => self.int(),
+            Kind::Float => self.float(),
+            Kind::String => {
+                let text = self.text(self.config.string_length);
+                self.string(&text);
+            }
+            Kind::Bin => {
+                let bytes = self.bytes(self.config.string_length);
+                self.header(&BIN, bytes.len());
+                self.out.extend_from_slice(&bytes);
+            }
+            Kind::Ext => {
+                let ext_type = self.rng.between(0, 127) as u8;
+                let bytes = self.bytes(self.config.string_length);
+                self.ext(ext_type, &bytes);
+            }
+            Kind::Timestamp => self.timestamp(),
+            Kind::Array => {
+                let length = self.rng.range(self.config.array_length).min(self.budget);
+                self.budget -= length;
+                self.header(&ARRAY, length);
+                for _ in 0..length {
+                    self.value(depth + 1);
+                }
+            }
+            Kind::Map => {
+                let width = self.rng.range(self.config.map_width).min(self.budget);
+                self.budget -= width;
+                self.header(&MAP, width);
+                // Keys stay unique so the map means the same as a JSON object
+                let mut keys = Vec::with_capacity(width);
+                for index in 0..width {
+                    let mut key = self.text((self.config.string_length.0.max(1), self.config.string_length.1.max(1)));
+                    while keys.contains(&key) {
+                        key = format!("{}_{}", key, index);
+                    }
+                    self.string(&key);
+                    keys.push(key);
+                    self.value(depth + 1);
+                }
+            }
+        }
+    }
+
+    // A width first, then a value that needs exactly that width, so every int marker turns up
+    fn int(&mut self) {
+        let rng = &mut self.rng;
+        let (marker, bytes) = match rng.between(0, 9) {
+            0 => (rng.between(0, 0x7f) as u8, Vec::new()),
+            1 => ((rng.between(0, 31) as i8 - 32) as u8, Vec::new()),
+            2 => (0xcc, vec![rng.between(0x80, 0xff) as u8]),
+            3 => (0xcd, (rng.between(0x100, 0xffff) as u16).to_be_bytes().to_vec()),
+            4 => (0xce, (rng.between(0x1_0000, u32::MAX as u64) as u32).to_be_bytes().to_vec()),
+            5 => (0xcf, rng.between(u32::MAX as u64 + 1, u64::MAX).to_be_bytes().to_vec()),
+            6 => (0xd0, vec![(-33 - rng.between(0, 95) as i64) as i8 as u8]),
+            7 => (0xd1, ((-129 - rng.between(0, 32_639) as i64) as i16).to_be_bytes().to_vec()),
+            8 => (0xd2, ((-32_769 - rng.between(0, i32::MAX as u64 - 32_768) as i64) as i32).to_be_bytes().to_vec()),
+            _ => (0xd3, (i32::MIN as i64 - 1 - rng.between(0, i64::MAX as u64 - i32::MAX as u64 - 1) as i64).to_be_bytes().to_vec()),
+        };
+        self.number(marker, &bytes);
+    }
+
+    // Anything from about -1e6 to 1e6, never NaN or infinite so JSON can hold it
+    fn float(&mut self) {
+        let unit = (self.rng.next() >> 11) as f64 / (1u64 << 53) as f64;
+        let value = (unit * 2.0 - 1.0) * 10f64.powi(self.rng.between(0, 6) as i32);
+        match self.rng.next() & 1 {
+            1 => self.number(0xca, &(value as f32).to_be_bytes()),
+            _ => self.number(0xcb, &value.to_be_bytes()),
+        }
+    }
+
+    // The three timestamp layouts, each with what only it can hold
+    fn timestamp(&mut self) {
+        let mut bytes = Vec::new();
+        match self.rng.between(0, 2) {
+            0 => bytes.extend((self.rng.next() as u32).to_be_bytes()),
+            1 => {
+                let nanoseconds = self.rng.between(1, 999_999_999);
+                let seconds = self.rng.between(0, (1 << 34) - 1);
+                bytes.extend((nanoseconds << 34 | seconds).to_be_bytes());
+            }
+            _ => {
+                bytes.extend((self.rng.between(0, 999_999_999) as u32).to_be_bytes());
+                bytes.extend((self.rng.next() as i64).to_be_bytes());
+            }
+        }
+        self.ext(0xff, &bytes);
+    }
+
+    fn ext(&mut self, ext_type: u8, bytes: &[u8]) {
+        match bytes.len() {
+            1 => self.out.push(0xd4),
+            2 => self.out.push(0xd5),
+            4 => self.out.push(0xd6),
+            8 => self.out.push(0xd7),
+            16 => self.out.push(0xd8),
+            length => self.header(&EXT, length),
+        }
+        self.out.push(ext_type);
+        self.out.extend_from_slice(bytes);
+    }
+
+    fn string(&mut self, text: &str) {
+        self.header(&STR, text.len());
+        self.out.extend_from_slice(text.as_bytes());
+    }
+
+    // Mostly ASCII, with the odd character that takes two, three or four bytes
+    fn text(&mut self, length: (usize, usize)) -> String {
+        const ASCII: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 _-";
+        const WIDE: [char; 6] = ['é', 'ß', 'Ж', '€', '日', '😀'];
+        (0..self.rng.range(length))
+            .map(|_| if self.rng.between(0, 15) == 0 { self.rng.pick(&WIDE) } else { self.rng.pick(ASCII) as char })
+            .collect()
+    }
+
+    fn bytes(&mut self, length: (usize, usize)) -> Vec<u8> {
+        (0..self.rng.range(length)).map(|_| self.rng.next() as u8).collect()
+    }
+
+    fn number(&mut self, marker: u8, bytes: &[u8]) {
+        self.out.push(marker);
+        self.out.extend_from_slice(bytes);
+    }
+
+    // The header of a length-prefixed value, in the shortest form that holds the length
+    fn header(&mut self, header: &Header, length: usize) {
+        match (header.fix, header.bits8) {
+            (Some((marker, limit)), _) if length < limit => self.out.push(marker | length as u8),
+            (_, Some(marker)) if length <= 0xff => self.out.extend([marker, length as u8]),
+            _ if length <= 0xffff => self.number(header.bits16, &(length as u16).to_be_bytes()),
+            _ => self.number(header.bits32, &(length as u32).to_be_bytes()),
+        }
+    }
+}
+
+// The markers of a length-prefixed type: the fix form and the lengths it holds, then the 8, 16
+// and 32 bit forms
+struct Header {
+    fix: Option<(u8, usize)>,
+    bits8: Option<u8>,
+    bits16: u8,
+    bits32: u8,
+}
+
+const STR: Header = Header { fix: Some((0xa0, 32)), bits8: Some(0xd9), bits16: 0xda, bits32: 0xdb };
+const BIN: Header = Header { fix: None, bits8: Some(0xc4), bits16: 0xc5, bits32: 0xc6 };
+const EXT: Header = Header { fix: None, bits8: Some(0xc7), bits16: 0xc8, bits32: 0xc9 };
+const ARRAY: Header = Header { fix: Some((0x90, 16)), bits8: None, bits16: 0xdc, bits32: 0xdd };
+const MAP: Header = Header { fix: Some((0x80, 16)), bits8: None, bits16: 0xde, bits32: 0xdf };
+
+// What the generator made, ready for the input pane, and a line about its size
+pub fn generated_input(config: &GenerateConfig, json_format: &JsonFormat) -> Result<(FileInput, String), String> {
+    let messagepack = generate(config);
+    if config.types.beyond_json() {
+        let summary = trf("{} bytes of MessagePack", &[&messagepack.len()]);
+        return Ok((FileInput::Binary(BinaryFile::new("generated.msgpack", messagepack, Encoding::Base64)), summary));
+    }
+    let (mut value, _) = decode_value_at(&messagepack, 0, false).map_err(String::from)?;
+    json_format.order_keys(&mut value);
+    let json = json_format.pretty(&value).map_err(String::from)?;
+    let summary = trf("{} bytes of JSON, {} bytes as MessagePack", &[&json.len(), &messagepack.len()]);
+    Ok((FileInput::Json(json), summary))
+}
+
+#[derive(Default)]
+pub struct GenerateState {
+    pub open: bool,
+    config: GenerateConfig,
+    // Size of the last payload, or why it couldn't be made
+    summary: Option<Result<String, String>>,
+}
+
+// The payload to load into the active tab once Generate is clicked
+pub fn generate_window(ctx: &egui::Context, state: &mut GenerateState, json_format: &JsonFormat) -> Option<FileInput> {
+    let mut generated = None;
+    let mut open = state.open;
+    egui::Window::new(tr("Generate payload"))
+        .id(egui::Id::new("generate"))
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let config = &mut state.config;
+            egui::Grid::new("generate_options").num_columns(2).show(ui, |ui| {
+                ui.label(tr("Seed:"));
+                ui.add(egui::DragValue::new(&mut config.seed)).on_hover_text(tr("The same seed and options give the same payload"));
+                ui.end_row();
+                ui.label(tr("Depth:"));
+                ui.add(egui::DragValue::new(&mut config.max_depth).clamp_range(0..=8)).on_hover_text(tr("Levels of nested maps and arrays at most"));
+                ui.end_row();
+                range_row(ui, tr("Map width:"), &mut config.map_width, 64);
+                range_row(ui, tr("Array length:"), &mut config.array_length, 64);
+                range_row(ui, tr("String length:"), &mut config.string_length, 4096);
+            });
+            ui.label(tr("Types:"));
+            let types = &mut config.types;
+            ui.horizontal_wrapped(|ui| {
+                ui.checkbox(&mut types.ints, tr("Integers")).on_hover_text(tr("Every width from fixint to 64 bits, signed and unsigned"));
+                ui.checkbox(&mut types.floats, tr("Floats"));
+                ui.checkbox(&mut types.bools, tr("Booleans"));
+                ui.checkbox(&mut types.nil, "nil");
+                ui.checkbox(&mut types.strings, tr("Strings"));
+                ui.checkbox(&mut types.arrays, tr("Arrays"));
+                ui.checkbox(&mut types.maps, tr("Maps"));
+            });
+            ui.horizontal_wrapped(|ui| {
+                ui.checkbox(&mut types.bins, tr("Bins"));
+                ui.checkbox(&mut types.exts, tr("Exts"));
+                ui.checkbox(&mut types.timestamps, tr("Timestamps"));
+            });
+            if types.beyond_json() {
+                ui.weak(tr("JSON can't hold these, the payload goes into the MessagePack input as bytes"));
+            }
+            ui.horizontal(|ui| {
+                if ui.button(tr("Generate")).clicked() {
+                    state.summary = Some(generated_input(&state.config, json_format).map(|(input, summary)| {
+                        generated = Some(input);
+                        summary
+                    }));
+                }
+                if ui.button(tr("New seed")).clicked() {
+                    state.config.seed = Rng(state.config.seed).next();
+                }
+                match &state.summary {
+                    Some(Ok(summary)) => {
+                        ui.label(summary);
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                    None => {}
+                }
+            });
+        });
+    state.open = open;
+    generated
+}
+
+fn range_row(ui: &mut egui::Ui, label: &str, (min, max): &mut (usize, usize), limit: usize) {
+    ui.label(label);
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(min).clamp_range(0..=limit));
+        ui.label(tr("to"));
+        ui.add(egui::DragValue::new(max).clamp_range(*min..=limit));
+    });
+    ui.end_row();
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn small_config(seed: u64) -> GenerateConfig {
+    GenerateConfig { seed, max_depth: 2, map_width: (2, 3), array_length: (1, 2), string_length: (1, 4), ..Default::default() }
+}
+
+#[test]
+fn test_same_seed_same_payload() {
+    assert_eq!(generate(&small_config(7)), generate(&small_config(7)));
+    assert_ne!(generate(&small_config(7)), generate(&small_config(8)));
+    // {"i": [float 32, float 64], "5wW": uint 16, "U": float 64}
+    assert_eq!(hex::encode(generate(&small_config(7))), "83a16992cac8a7e4d1cb412c11884e692d30a3357757cd0bffa155cb412886d4a0e34124");
+}
+
+#[test]
+fn test_every_type_and_width_turns_up() {
+    let mut all = GenerateConfig { seed: 3, max_depth: 4, map_width: (4, 8), array_length: (4, 8), string_length: (0, 40), ..Default::default() };
+    all.types.bins = true;
+    all.types.exts = true;
+    all.types.timestamps = true;
+    let mut markers = std::collections::HashSet::new();
+    for seed in 0..10 {
+        crate::msgpack::walk(&generate(&GenerateConfig { seed, ..all }), |token, _| {
+            markers.insert(token.marker.to_u8());
+        }).unwrap();
+    }
+    for marker in [0xcc, 0xcd, 0xce, 0xcf, 0xd0, 0xd1, 0xd2, 0xd3, 0xca, 0xcb, 0xc0, 0xc2, 0xc3, 0xc4, 0xd6, 0xd7, 0xc7, 0xd9] {
+        assert!(markers.contains(&marker), "no {:#04x}", marker);
+    }
+    assert!(markers.iter().any(|&marker| marker <= 0x7f) && markers.iter().any(|&marker| marker >= 0xe0));
+}
+
+#[test]
+fn test_generated_json_and_bytes() {
+    let (input, summary) = generated_input(&small_config(7), &JsonFormat::default()).unwrap();
+    let FileInput::Json(json) = input else { panic!("{:?}", input) };
+    assert!(serde_json::from_str::<serde_json::Value>(&json).unwrap().is_object());
+    assert!(summary.starts_with(&format!("{} bytes of JSON", json.len())), "{}", summary);
+
+    let mut config = small_config(7);
+    config.types.bins = true;
+    let (input, _) = generated_input(&config, &JsonFormat::default()).unwrap();
+    assert!(matches!(input, FileInput::Binary(_)));
+    // Without containers the payload is one scalar
+    config.types.maps = false;
+    config.types.arrays = false;
+    let bytes = generate(&config);
+    assert_eq!(decode_value_at(&bytes, 0, true).unwrap().1, bytes.len());
+}
@@ -0,0 +1,170 @@
+use crate::files::MEGABYTE;
+use crate::locale::tr;
+use eframe::egui;
+
+// Snapshots above this are dropped oldest first, counted over the undo and redo side together
+pub const MAX_HISTORY_BYTES: usize = 16 * MEGABYTE;
+pub const MAX_SNAPSHOTS: usize = 50;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Step {
+    Undo,
+    Redo,
+}
+
+// Whole-pane snapshots taken before the app replaces a pane's text, e.g. by Format, an example
+// or a conversion result. Typing is left to the editor's own undo.
+#[derive(Default)]
+pub struct PaneHistory {
+    undo: Vec<String>,
+    // Nearest snapshot last, like `undo`
+    redo: Vec<String>,
+    // The pane's text was last changed by the app rather than by typing, which is when Ctrl+Z
+    // goes to this history instead of the editor
+    pub programmatic: bool,
+}
+
+impl PaneHistory {
+    // A history carried over from elsewhere, such as an exported session
+    pub fn from_snapshots(undo: Vec<String>, redo: Vec<String>) -> PaneHistory {
+        let mut history = PaneHistory { undo, redo, programmatic: false };
+        history.trim();
+        history
+    }
+
+    // Undo and redo snapshots, the nearest last in both
+    pub fn snapshots(&self) -> (&[String], &[String]) {
+        (&self.undo, &self.redo)
+    }
+
+    // Called with the text that is about to be replaced
+    pub fn record(&mut self, previous: &str) {
+        self.programmatic = true;
+        self.redo.clear();
+        // Trimming would drop the snapshot and everything before it, so it isn't copied at all
+        if previous.len() > MAX_HISTORY_BYTES {
+            self.undo.clear();
+            return;
+        }
+        if self.undo.last().is_none_or(|last| last != previous) {
+            self.undo.push(previous.to_string());
+        }
+        self.trim();
+    }
+
+    // Swaps `text` with the previous (or next) snapshot, false when there is none
+    pub fn step(&mut self, step: Step, text: &mut String) -> bool {
+        let (from, to) = match step {
+            Step::Undo => (&mut self.undo, &mut self.redo),
+            Step::Redo => (&mut self.redo, &mut self.undo),
+        };
+        let Some(snapshot) = from.pop() else {
+            return false;
+        };
+        to.push(std::mem::replace(text, snapshot));
+        self.programmatic = true;
+        self.trim();
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    fn bytes(&self) -> usize {
+        self.undo.iter().chain(&self.redo).map(String::len).sum()
+    }
+
+    fn trim(&mut self) {
+        // A snapshot over the limit can't be kept, and neither can anything beyond it
+        for stack in [&mut self.undo, &mut self.redo] {
+            if let Some(at) = stack.iter().rposition(|snapshot| snapshot.len() > MAX_HISTORY_BYTES) {
+                stack.drain(..=at);
+            }
+        }
+        while self.undo.len() + self.redo.len() > MAX_SNAPSHOTS || self.bytes() > MAX_HISTORY_BYTES {
+            if self.undo.is_empty() {
+                self.redo.remove(0);
+            } else {
+                self.undo.remove(0);
+            }
+        }
+    }
+}
+
+// Undo/Redo buttons for a pane header, returning the one that was clicked
+pub fn history_buttons(ui: &mut egui::Ui, history: &PaneHistory) -> Option<Step> {
+    let mut clicked = None;
+    if ui.add_enabled(history.can_undo(), egui::Button::new(tr("Undo")).small())
+        .on_hover_text(tr("Bring back what the last Format, example or conversion replaced"))
+        .clicked()
+    {
+        clicked = Some(Step::Undo);
+    }
+    if ui.add_enabled(history.can_redo(), egui::Button::new(tr("Redo")).small()).clicked() {
+        clicked = Some(Step::Redo);
+    }
+    clicked
+}
+
+
+/* Tests */
+#[test]
+fn test_history_undo_and_redo() {
+    let mut history = PaneHistory::default();
+    let mut text = "typed".to_string();
+    history.record(&text);
+    text = "formatted".to_string();
+    history.record(&text);
+    text = "example".to_string();
+
+    assert!(history.step(Step::Undo, &mut text));
+    assert_eq!(text, "formatted");
+    assert!(history.step(Step::Undo, &mut text));
+    assert_eq!(text, "typed");
+    assert!(!history.step(Step::Undo, &mut text));
+    assert!(history.step(Step::Redo, &mut text));
+    assert_eq!(text, "formatted");
+
+    // A new replacement forgets what could have been redone
+    history.record(&text);
+    text = "converted".to_string();
+    assert!(!history.can_redo());
+    assert!(history.step(Step::Undo, &mut text));
+    assert_eq!(text, "formatted");
+}
+
+#[test]
+fn test_history_skips_repeated_snapshots() {
+    let mut history = PaneHistory::default();
+    history.record("");
+    history.record("");
+    let mut text = "loaded".to_string();
+    assert!(history.step(Step::Undo, &mut text));
+    assert!(!history.can_undo());
+}
+
+#[test]
+fn test_history_is_capped() {
+    let mut history = PaneHistory::default();
+    for i in 0..MAX_SNAPSHOTS + 10 {
+        history.record(&i.to_string());
+    }
+    assert_eq!(history.undo.len(), MAX_SNAPSHOTS);
+    assert_eq!(history.undo[0], "10");
+
+    let big = "x".repeat(MAX_HISTORY_BYTES / 2 + 1);
+    history.record(&big);
+    history.record(&format!("{}y", big));
+    // Only the newest of the two fits, and everything older went first
+    assert_eq!(history.undo.len(), 1);
+    assert!(history.bytes() <= MAX_HISTORY_BYTES);
+
+    // Too big to keep at all, so nothing before it can be undone either
+    history.record(&"z".repeat(MAX_HISTORY_BYTES + 1));
+    assert!(!history.can_undo());
+}
@@ -0,0 +1,99 @@
+//! The conversion core of messagepack_to_json, without the window around it: MessagePack and
+//! CBOR to JSON or YAML and back, streams of records, framing, compression and ext types.
+//!
+//! The two functions below cover the common case. Everything else goes through
+//! [`convert::convert`] with [`convert::ConvertOptions`], the same path the command line and
+//! batch conversion take. The window's conversions run [`convert::convert_until`], which can be
+//! cancelled and hands back the converted values as well:
+//!
+//! ```
+//! use messagepack_to_json::convert::{convert, ConvertOptions, Floats};
+//!
+//! let options = ConvertOptions::to_messagepack().floats(Floats::Single).max_depth(8);
+//! let (messagepack, warnings) = convert(br#"{"id": 7}"#, &options).unwrap();
+//! assert_eq!(messagepack, [0x81, 0xa2, b'i', b'd', 0x07]);
+//! assert!(warnings.is_empty());
+//! ```
+//!
+//! Build with `default-features = false` to leave out the GUI and its dependencies.
+
+pub mod byte_text;
+pub mod cbor;
+pub mod checksum;
+pub mod compress;
+pub mod convert;
+pub mod decode;
+pub mod detect;
+pub mod error;
+pub mod examples;
+pub mod ext_types;
+pub mod files;
+pub mod format;
+pub mod framing;
+pub mod locale;
+pub mod msgpack;
+pub mod nested;
+pub mod rpc;
+pub mod schema;
+pub mod schema_check;
+pub mod shape;
+pub mod stats;
+pub mod template;
+pub mod timestamp;
+pub mod warning;
+pub mod worker;
+pub mod yaml;
+pub mod zstd;
+
+use convert::{convert, ConvertOptions};
+use error::ConvertError;
+use std::path::Path;
+
+/// JSON text to MessagePack bytes, with map keys sorted.
+///
+/// ```
+/// let messagepack = messagepack_to_json::json_to_messagepack(r#"[1, "a"]"#).unwrap();
+/// assert_eq!(messagepack, [0x92, 0x01, 0xa1, b'a']);
+/// ```
+pub fn json_to_messagepack(json: &str) -> Result<Vec<u8>, ConvertError> {
+    convert(json.as_bytes(), &ConvertOptions::to_messagepack()).map(|(messagepack, _)| messagepack)
+}
+
+/// One MessagePack value to pretty-printed JSON, ignoring any bytes after it. Fails on binary
+/// and ext values JSON has no way to write; [`convert::ConvertOptions::lossy`] turns those into
+/// base64 strings instead.
+///
+/// ```
+/// let json = messagepack_to_json::messagepack_to_json(&[0x81, 0xa2, b'i', b'd', 0x07]).unwrap();
+/// assert_eq!(json, "{\n  \"id\": 7\n}");
+/// ```
+pub fn messagepack_to_json(messagepack: &[u8]) -> Result<String, ConvertError> {
+    let (json, _) = convert(messagepack, &ConvertOptions::to_json().ignore_trailing(true))?;
+    // Written from a serde_json value, so always UTF-8
+    Ok(String::from_utf8(json).unwrap_or_default())
+}
+
+/// The raw MessagePack bytes of a file, such as a `.msgpack` one, to pretty-printed JSON, as
+/// [`messagepack_to_json`] does with bytes already in memory.
+pub fn messagepack_file_to_json(path: &Path) -> Result<String, ConvertError> {
+    messagepack_to_json(&files::read_file(path)?)
+}
+
+
+/* Tests */
+#[test]
+fn test_library_round_trip() {
+    let json = r#"{"name": "x", "list": [1, 2.5, null, true]}"#;
+    let messagepack = json_to_messagepack(json).unwrap();
+    let back: serde_json::Value = serde_json::from_str(&messagepack_to_json(&messagepack).unwrap()).unwrap();
+    assert_eq!(back, serde_json::from_str::<serde_json::Value>(json).unwrap());
+    assert!(json_to_messagepack("{").is_err());
+    assert!(messagepack_to_json(&[0x92, 0x01]).is_err());
+    assert_eq!(messagepack_to_json(&[0x01, 0xc0]).unwrap(), "1");
+
+    let path = std::env::temp_dir().join(format!("messagepack_to_json_lib_{}.msgpack", std::process::id()));
+    std::fs::write(&path, &messagepack).unwrap();
+    assert_eq!(messagepack_file_to_json(&path), messagepack_to_json(&messagepack));
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(messagepack_file_to_json(&path), Err(ConvertError::ReadFile { .. })));
+}
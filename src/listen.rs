@@ -0,0 +1,368 @@
+use crate::convert::split_records;
+use crate::feed::{FrameLog, ReceivedFrame};
+use crate::format::JsonFormat;
+use crate::locale::{tr, trf};
+use crate::rpc::Latencies;
+use eframe::egui;
+use std::io::{self, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// How long the threads wait for data before looking whether they should stop, which bounds how
+// long stopping takes
+const STOP_CHECK: Duration = Duration::from_millis(100);
+
+// The largest payload a UDP datagram can carry
+const MAX_DATAGRAM: usize = 65_535;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    // Every datagram is one value
+    #[default]
+    Udp,
+    // Values back to back on each connection
+    Tcp,
+}
+
+impl Protocol {
+    pub const ALL: [Protocol; 2] = [Protocol::Udp, Protocol::Tcp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Protocol::Udp => "UDP",
+            Protocol::Tcp => "TCP",
+        }
+    }
+}
+
+// A bound socket and the thread receiving on it
+struct Listener {
+    address: SocketAddr,
+    stop: Arc<AtomicBool>,
+    // Taken when stopping
+    thread: Option<JoinHandle<io::Result<()>>>,
+    frames: Receiver<ReceivedFrame>,
+}
+
+impl Listener {
+    // Binds on the calling thread, so a port that's taken is reported right away
+    // With `rpc`, msgpack-RPC messages are labeled
+    fn start(protocol: Protocol, address: &str, json_format: &JsonFormat, rpc: bool, ctx: &egui::Context) -> io::Result<Listener> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, frames) = mpsc::channel();
+        let json_format = *json_format;
+        let ctx = ctx.clone();
+        let thread_stop = stop.clone();
+        let (address, thread) = match protocol {
+            Protocol::Udp => {
+                let socket = UdpSocket::bind(address)?;
+                socket.set_broadcast(true)?;
+                socket.set_read_timeout(Some(STOP_CHECK))?;
+                let address = socket.local_addr()?;
+                (address, thread::spawn(move || receive_datagrams(&socket, &thread_stop, &sender, &json_format, rpc, &ctx)))
+            }
+            Protocol::Tcp => {
+                let listener = TcpListener::bind(address)?;
+                listener.set_nonblocking(true)?;
+                let address = listener.local_addr()?;
+                (address, thread::spawn(move || accept_connections(listener, &thread_stop, &sender, &json_format, rpc, &ctx)))
+            }
+        };
+        Ok(Listener { address, stop, thread: Some(thread), frames })
+    }
+
+    // Returns once the socket is closed and the port free again
+    fn stop(&mut self) -> io::Result<()> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.take().map_or(Ok(()), |thread| thread.join().unwrap_or(Ok(())))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(|thread| thread.is_finished())
+    }
+}
+
+fn receive_datagrams(socket: &UdpSocket, stop: &AtomicBool, frames: &Sender<ReceivedFrame>, json_format: &JsonFormat, rpc: bool, ctx: &egui::Context) -> io::Result<()> {
+    let mut buffer = vec![0; MAX_DATAGRAM];
+    while !stop.load(Ordering::Relaxed) {
+        match socket.recv_from(&mut buffer) {
+            Ok((size, from)) => {
+                if frames.send(ReceivedFrame::binary(&buffer[..size], Some(from.to_string()), json_format, rpc)).is_err() {
+                    return Ok(());
+                }
+                ctx.request_repaint();
+            }
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => {}
+            // Windows reports an earlier send that bounced this way, it says nothing about this socket
+            Err(e) if e.kind() == io::ErrorKind::ConnectionReset => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+// Takes connections until stopped, each read on a thread of its own. Only returns once those are
+// done too, so no connection outlives the listener.
+fn accept_connections(listener: TcpListener, stop: &AtomicBool, frames: &Sender<ReceivedFrame>, json_format: &JsonFormat, rpc: bool, ctx: &egui::Context) -> io::Result<()> {
+    thread::scope(|scope| {
+        while !stop.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, from)) => {
+                    stream.set_nonblocking(false)?;
+                    stream.set_read_timeout(Some(STOP_CHECK))?;
+                    let frames = frames.clone();
+                    scope.spawn(move || receive_stream(stream, from, stop, &frames, json_format, rpc, ctx));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(STOP_CHECK),
+                // The client gave up before it was accepted
+                Err(e) if matches!(e.kind(), io::ErrorKind::ConnectionAborted | io::ErrorKind::Interrupted) => {}
+                Err(e) => {
+                    stop.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+// Logs every value on a connection until it closes. A value that doesn't decode is logged as hex
+// and ends the connection, as there's no telling where the next one would start.
+fn receive_stream(stream: TcpStream, from: SocketAddr, stop: &AtomicBool, frames: &Sender<ReceivedFrame>, json_format: &JsonFormat, rpc: bool, ctx: &egui::Context) {
+    let source = from.to_string();
+    // Errors reading are the connection going away, which is the end of its log either way
+    let _ = split_records(Connection { stream, stop }, false, |record, _| {
+        let _ = frames.send(ReceivedFrame::binary(record, Some(source.clone()), json_format, rpc));
+        ctx.request_repaint();
+        Ok(())
+    });
+}
+
+// A connection that reads as ended once the listener is stopped
+struct Connection<'a> {
+    stream: TcpStream,
+    stop: &'a AtomicBool,
+}
+
+impl Read for Connection<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.stream.read(buf) {
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    if self.stop.load(Ordering::Relaxed) {
+                        return Ok(0);
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+pub struct ListenState {
+    pub open: bool,
+    protocol: Protocol,
+    // 0.0.0.0 so broadcasts on the local network come in too
+    host: String,
+    port: u16,
+    listener: Option<Listener>,
+    error: Option<String>,
+    log: FrameLog,
+    // Label msgpack-RPC messages and time responses against their requests
+    rpc: bool,
+    latencies: Latencies,
+}
+
+impl Default for ListenState {
+    fn default() -> Self {
+        ListenState {
+            open: false,
+            protocol: Protocol::default(),
+            host: "0.0.0.0".to_string(),
+            port: 9000,
+            listener: None,
+            error: None,
+            log: FrameLog::default(),
+            rpc: false,
+            latencies: Latencies::default(),
+        }
+    }
+}
+
+impl ListenState {
+    fn start(&mut self, ctx: &egui::Context, json_format: &JsonFormat) {
+        self.stop();
+        self.latencies = Latencies::default();
+        match Listener::start(self.protocol, &format!("{}:{}", self.host, self.port), json_format, self.rpc, ctx) {
+            Ok(listener) => self.listener = Some(listener),
+            Err(e) => self.error = Some(trf("Failed to listen on {}: {}", &[&format_args!("{}:{}", self.host, self.port), &e])),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.error = None;
+        if let Some(mut listener) = self.listener.take() {
+            if let Err(e) = listener.stop() {
+                self.error = Some(e.to_string());
+            }
+            // Whatever came in before it stopped
+            let incoming: Vec<_> = listener.frames.try_iter().collect();
+            self.take(incoming);
+        }
+    }
+
+    // Moves what came in to the log, and notices a listener that failed on its own
+    fn take_incoming(&mut self) {
+        let Some(listener) = &self.listener else {
+            return;
+        };
+        let incoming: Vec<_> = listener.frames.try_iter().collect();
+        self.take(incoming);
+        if self.listener.as_ref().is_some_and(Listener::is_finished) {
+            self.stop();
+        }
+    }
+
+    // Into the log, a response with how long it took since its request
+    fn take(&mut self, mut incoming: Vec<ReceivedFrame>) {
+        for frame in &mut incoming {
+            if let Some(latency) = frame.rpc.and_then(|message| self.latencies.observe(message, frame.time)) {
+                frame.note = Some(trf("answered after {} ms", &[&format_args!("{:.1}", latency.as_secs_f64() * 1000.0)]));
+            }
+        }
+        self.log.take(incoming);
+    }
+}
+
+impl Drop for ListenState {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Binds a UDP or TCP port and lists every MessagePack value that arrives on it, decoded
+pub fn listen_window(ctx: &egui::Context, state: &mut ListenState, json_format: &JsonFormat) {
+    state.take_incoming();
+    let mut open = state.open;
+    egui::Window::new(tr("Listen"))
+        .id(egui::Id::new("listen"))
+        .open(&mut open)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            let listening = state.listener.is_some();
+            ui.add_enabled_ui(!listening, |ui| {
+                egui::Grid::new("listen_options").num_columns(2).show(ui, |ui| {
+                    ui.label(tr("Protocol:"));
+                    ui.horizontal(|ui| {
+                        for protocol in Protocol::ALL {
+                            ui.radio_value(&mut state.protocol, protocol, protocol.name());
+                        }
+                    })
+                    .response
+                    .on_hover_text(tr("UDP decodes every datagram on its own, TCP decodes the values each connection sends back to back"));
+                    ui.end_row();
+                    ui.label(tr("Address:"));
+                    ui.add(egui::TextEdit::singleline(&mut state.host).desired_width(160.0));
+                    ui.end_row();
+                    ui.label(tr("Port:"));
+                    ui.add(egui::DragValue::new(&mut state.port));
+                    ui.end_row();
+                    ui.label("");
+                    ui.checkbox(&mut state.rpc, tr("msgpack-RPC"))
+                        .on_hover_text(tr("Labels requests, responses and notifications, and times each response against its request"));
+                    ui.end_row();
+                });
+            });
+            ui.horizontal(|ui| {
+                if listening {
+                    if ui.button(tr("Stop")).clicked() {
+                        state.stop();
+                    }
+                } else if ui.button(tr("Start")).clicked() {
+                    state.start(ctx, json_format);
+                }
+                if let Some(listener) = &state.listener {
+                    ui.label(trf("Listening on {} {}", &[&state.protocol.name(), &listener.address]));
+                }
+            });
+            if let Some(error) = &state.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            state.log.controls(ui);
+            ui.separator();
+            state.log.show(ui);
+        });
+    if state.open && !open {
+        state.stop();
+    }
+    state.open = open;
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn wait_for_frames(listener: &Listener, count: usize) -> Vec<ReceivedFrame> {
+    (0..count).map(|_| listener.frames.recv_timeout(Duration::from_secs(5)).unwrap()).collect()
+}
+
+#[test]
+fn test_udp_datagrams_decode_one_by_one() {
+    use crate::feed::FrameContent;
+    let mut listener = Listener::start(Protocol::Udp, "127.0.0.1:0", &JsonFormat::default(), false, &egui::Context::default()).unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender.send_to(&[0x81, 0xa1, 0x61, 0x01], listener.address).unwrap();
+    sender.send_to(&[0x81, 0xa1], listener.address).unwrap();
+    let frames = wait_for_frames(&listener, 2);
+    assert_eq!(frames[0].content, FrameContent::Json("{\n  \"a\": 1\n}".to_string()));
+    assert_eq!(frames[0].source, Some(sender.local_addr().unwrap().to_string()));
+    assert!(matches!(&frames[1].content, FrameContent::Failed { hex, .. } if hex == "81a1"));
+
+    // The port can be bound again as soon as the listener stops
+    let address = listener.address;
+    listener.stop().unwrap();
+    UdpSocket::bind(address).unwrap();
+}
+
+#[test]
+fn test_tcp_streams_split_into_values() {
+    use crate::feed::FrameContent;
+    use std::io::Write;
+    let mut listener = Listener::start(Protocol::Tcp, "127.0.0.1:0", &JsonFormat::default(), false, &egui::Context::default()).unwrap();
+    let mut client = TcpStream::connect(listener.address).unwrap();
+    // Two values in one write, then one cut off by the connection closing
+    client.write_all(&[0x01, 0x92, 0xc3, 0xc0, 0x82, 0xa1]).unwrap();
+    drop(client);
+    let frames = wait_for_frames(&listener, 3);
+    let contents: Vec<_> = frames.iter().map(|frame| &frame.content).collect();
+    assert_eq!(contents[..2], [&FrameContent::Json("1".to_string()), &FrameContent::Json("[\n  true,\n  null\n]".to_string())]);
+    assert!(matches!(contents[2], FrameContent::Failed { hex, .. } if hex == "82a1"));
+
+    // A connection still open doesn't keep the listener from stopping
+    let address = listener.address;
+    let _open = TcpStream::connect(address).unwrap();
+    listener.stop().unwrap();
+    TcpListener::bind(address).unwrap();
+}
+
+#[test]
+fn test_rpc_responses_are_timed_against_their_requests() {
+    use crate::feed::FrameContent;
+    use crate::rpc::Message;
+    let listener = Listener::start(Protocol::Udp, "127.0.0.1:0", &JsonFormat::default(), true, &egui::Context::default()).unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    // [0, 7, "ping", []] and then [1, 7, nil, "pong"]
+    sender.send_to(&[0x94, 0x00, 0x07, 0xa4, b'p', b'i', b'n', b'g', 0x90], listener.address).unwrap();
+    sender.send_to(&[0x94, 0x01, 0x07, 0xc0, 0xa4, b'p', b'o', b'n', b'g'], listener.address).unwrap();
+    let frames = wait_for_frames(&listener, 2);
+    assert_eq!(frames[0].rpc, Some(Message::Request(7)));
+    assert!(matches!(&frames[1].content, FrameContent::Json(json) if json.contains("\"type\": \"response\"")));
+    let mut state = ListenState::default();
+    state.take(frames);
+    let notes: Vec<_> = state.log.frames().map(|frame| frame.note.clone()).collect();
+    assert_eq!(notes[0], None);
+    assert!(notes[1].as_ref().is_some_and(|note| note.starts_with("answered after ")), "{:?}", notes);
+}
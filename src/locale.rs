@@ -0,0 +1,918 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// Language of the UI. Strings are looked up by their English text, which doubles as the
+// fallback for anything a locale doesn't translate.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    // Each language is listed under its own name so it can be found without reading the current one
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::German => "Deutsch",
+        }
+    }
+
+    fn table(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => &[],
+            Language::German => GERMAN,
+        }
+    }
+}
+
+// Read from the conversion threads as well as the UI, so it lives outside the app state
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_language(language: Language) {
+    CURRENT.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    Language::ALL.get(CURRENT.load(Ordering::Relaxed) as usize).copied().unwrap_or_default()
+}
+
+// `text` in the current language
+pub fn tr(text: &str) -> &str {
+    translate(language(), text)
+}
+
+pub fn translate(language: Language, text: &str) -> &str {
+    language.table().iter().find(|(english, _)| *english == text).map_or(text, |(_, translated)| translated)
+}
+
+// `text` in the current language with each "{}" replaced by the next argument
+pub fn trf(text: &str, args: &[&dyn Display]) -> String {
+    fill(tr(text), args)
+}
+
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut parts = template.split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    for (part, arg) in parts.zip(args.iter().map(|arg| arg.to_string()).chain(std::iter::repeat(String::new()))) {
+        filled.push_str(&arg);
+        filled.push_str(part);
+    }
+    filled
+}
+
+const GERMAN: &[(&str, &str)] = &[
+    // Toolbar
+    ("JSON <-> MessagePack Converter", "JSON <-> MessagePack Konverter"),
+    ("Clear All", "Alles leeren"),
+    ("Clear every pane of the current tab", "Alle Felder des aktuellen Tabs leeren"),
+    ("Examples", "Beispiele"),
+    ("Convert on load", "Beim Laden konvertieren"),
+    ("⚙ Settings", "⚙ Einstellungen"),
+    // Status bar and error log
+    ("Converting {}… {}%", "Konvertiere {}… {} %"),
+    ("No conversion yet", "Noch nichts konvertiert"),
+    ("Log ({})", "Protokoll ({})"),
+    ("Errors of this session, with when and where they happened", "Fehler dieser Sitzung, mit Zeitpunkt und Ort"),
+    ("Log", "Protokoll"),
+    ("{} error", "{} Fehler"),
+    ("{} errors", "{} Fehler"),
+    ("Copy", "Kopieren"),
+    ("Errors from conversions and other actions are listed here.", "Fehler aus Konvertierungen und anderen Aktionen werden hier aufgeführt."),
+    ("Dismiss", "Schließen"),
+    ("Restore session", "Sitzung wiederherstellen"),
+    // Dropped files
+    ("Drop a JSON or MessagePack file to load it", "JSON- oder MessagePack-Datei hier ablegen, um sie zu laden"),
+    ("Load large file?", "Große Datei laden?"),
+    ("{} is {}, loading it may take a while and use a lot of memory.", "{} ist {} groß, das Laden kann dauern und viel Speicher belegen."),
+    ("Load anyway", "Trotzdem laden"),
+    ("Open file", "Datei öffnen"),
+    ("Open…", "Öffnen…"),
+    ("Load a file into this pane", "Eine Datei in diesen Bereich laden"),
+    ("Load a raw MessagePack file such as a .msgpack, or base64 or hex text, into this pane", "Eine rohe MessagePack-Datei wie eine .msgpack oder Base64- oder Hex-Text in diesen Bereich laden"),
+    ("Open JSON file", "JSON-Datei öffnen"),
+    ("Open MessagePack file", "MessagePack-Datei öffnen"),
+    ("Path:", "Pfad:"),
+    ("Path of the file to open", "Pfad der zu öffnenden Datei"),
+    ("Show the bytes as:", "Bytes anzeigen als:"),
+    ("Open", "Öffnen"),
+    ("Save…", "Speichern…"),
+    ("Write this pane to a file", "Diesen Bereich in eine Datei schreiben"),
+    ("Save JSON output", "JSON-Ausgabe speichern"),
+    ("Save MessagePack output", "MessagePack-Ausgabe speichern"),
+    ("A file of that name exists and will be overwritten", "Eine Datei dieses Namens existiert und wird überschrieben"),
+    ("Save as:", "Speichern als:"),
+    ("Save", "Speichern"),
+    ("Save file", "Datei speichern"),
+    ("Save to file", "In Datei speichern"),
+    ("file: {}, {} bytes", "Datei: {}, {} Bytes"),
+    ("Converted straight from the file's bytes, the pane only gets them as text when asked to", "Wird direkt aus den Bytes der Datei konvertiert, als Text kommen sie nur auf Wunsch in den Bereich"),
+    ("Show as {}", "Als {} anzeigen"),
+    ("Put the bytes into the pane as text to edit them, which is slow for large files", "Die Bytes als Text in den Bereich übernehmen, um sie zu bearbeiten, bei großen Dateien langsam"),
+    ("{} is not UTF-8 text (invalid byte at offset {}), open it as MessagePack instead", "{} ist kein UTF-8-Text (ungültiges Byte an Offset {}), stattdessen als MessagePack öffnen"),
+    ("Watch the file", "Datei beobachten"),
+    ("Convert it again whenever it changes on disk", "Erneut konvertieren, sobald sie sich auf der Festplatte ändert"),
+    ("Watching {}", "Beobachte {}"),
+    ("Stop watching", "Beobachten beenden"),
+    ("Watch file", "Datei beobachten"),
+    ("Recent", "Zuletzt geöffnet"),
+    ("No longer exists", "Existiert nicht mehr"),
+    ("Remove missing files", "Fehlende Dateien entfernen"),
+    ("Clear list", "Liste leeren"),
+    // Batch conversion
+    ("Batch…", "Stapel…"),
+    ("Convert every matching file in a folder", "Jede passende Datei eines Ordners konvertieren"),
+    ("Batch conversion", "Stapelkonvertierung"),
+    ("Folder:", "Ordner:"),
+    ("Path of the folder", "Pfad des Ordners"),
+    ("Direction:", "Richtung:"),
+    ("Files:", "Dateien:"),
+    ("* matches any characters, ? a single one", "* steht für beliebige Zeichen, ? für ein einzelnes"),
+    ("Output:", "Ausgabe:"),
+    ("Replace the extension", "Endung ersetzen"),
+    ("Append the extension", "Endung anhängen"),
+    ("Start", "Starten"),
+    ("{} converted, {} failed", "{} konvertiert, {} fehlgeschlagen"),
+    ("No files in the folder match {}", "Keine Datei im Ordner passt zu {}"),
+    ("{} would be overwritten by its own output", "{} würde von der eigenen Ausgabe überschrieben"),
+    // Settings window
+    ("Settings", "Einstellungen"),
+    ("Appearance", "Darstellung"),
+    ("Zoom:", "Zoom:"),
+    ("Reset Zoom", "Zoom zurücksetzen"),
+    ("Monospace editors", "Editoren in Festbreitenschrift"),
+    ("JSON indent:", "JSON-Einrückung:"),
+    ("Sort keys", "Schlüssel sortieren"),
+    ("Restore panes on start", "Felder beim Start wiederherstellen"),
+    ("Keeps pane contents in the config directory between runs", "Speichert Feldinhalte zwischen den Starts im Konfigurationsverzeichnis"),
+    ("Output limit:", "Ausgabelimit:"),
+    ("Larger outputs can't be edited and are shown in the read-only viewer", "Größere Ausgaben lassen sich nicht bearbeiten und erscheinen in der schreibgeschützten Ansicht"),
+    ("Language:", "Sprache:"),
+    ("JSON", "JSON"),
+    ("Outputs", "Ausgaben"),
+    ("Wrap MessagePack output", "MessagePack-Ausgabe umbrechen"),
+    ("Wrap JSON output", "JSON-Ausgabe umbrechen"),
+    ("Startup", "Start"),
+    ("Convert examples and dropped files on load", "Beispiele und abgelegte Dateien beim Laden konvertieren"),
+    ("Restore defaults", "Standardwerte wiederherstellen"),
+    // Tabs and modes
+    ("Tab {}", "Tab {}"),
+    ("Double-click to rename, middle-click to close", "Doppelklick zum Umbenennen, Mittelklick zum Schließen"),
+    ("Close tab", "Tab schließen"),
+    ("New tab", "Neuer Tab"),
+    ("Convert", "Konvertieren"),
+    ("Smart input", "Smarte Eingabe"),
+    ("Diff", "Vergleich"),
+    ("Two columns", "Zwei Spalten"),
+    ("Single pane", "Ein Feld"),
+    ("One input and one output pane with a direction toggle", "Ein Eingabe- und ein Ausgabefeld mit umschaltbarer Richtung"),
+    // Panes
+    ("JSON to MessagePack", "JSON nach MessagePack"),
+    ("MessagePack to JSON", "MessagePack nach JSON"),
+    ("JSON → MessagePack", "JSON → MessagePack"),
+    ("MessagePack → JSON", "MessagePack → JSON"),
+    ("JSON Input:", "JSON-Eingabe:"),
+    ("JSON Output:", "JSON-Ausgabe:"),
+    ("MessagePack Input:", "MessagePack-Eingabe:"),
+    ("Detect", "Erkennen"),
+    ("Detect ({})", "Erkennen ({})"),
+    ("Escaped bytes", "Escapte Bytes"),
+    ("Byte array", "Byte-Array"),
+    ("Hexdump", "Hexdump"),
+    ("How the input text is read, for when detecting it guesses wrong", "Wie der Eingabetext gelesen wird, falls die Erkennung falsch liegt"),
+    ("MessagePack Output:", "MessagePack-Ausgabe:"),
+    ("How the output writes the bytes", "Wie die Ausgabe die Bytes schreibt"),
+    ("hex, spaced", "hex, mit Leerzeichen"),
+    ("HEX, spaced", "HEX, mit Leerzeichen"),
+    ("Text as shown", "Text wie angezeigt"),
+    ("JSON Input", "JSON-Eingabe"),
+    ("JSON Output", "JSON-Ausgabe"),
+    ("MessagePack Input", "MessagePack-Eingabe"),
+    ("MessagePack Output", "MessagePack-Ausgabe"),
+    ("Diff Left", "Vergleich links"),
+    ("Diff Right", "Vergleich rechts"),
+    ("Clear", "Leeren"),
+    ("Clear this pane", "Dieses Feld leeren"),
+    ("Format", "Formatieren"),
+    ("Minify", "Minimieren"),
+    ("Undo", "Rückgängig"),
+    ("Redo", "Wiederholen"),
+    ("Bring back what the last Format, example or conversion replaced", "Holt zurück, was das letzte Formatieren, Beispiel oder Konvertieren ersetzt hat"),
+    ("Validate", "Prüfen"),
+    ("Check the JSON without converting it", "JSON prüfen, ohne es zu konvertieren"),
+    ("Check the MessagePack without converting it", "MessagePack prüfen, ohne es zu konvertieren"),
+    ("Convert to MessagePack", "In MessagePack konvertieren"),
+    ("Convert to JSON", "In JSON konvertieren"),
+    ("Verify round trip", "Rückweg prüfen"),
+    ("Decode the output again and compare it with the input", "Die Ausgabe wieder dekodieren und mit der Eingabe vergleichen"),
+    ("Encode the output again and compare it with the input bytes", "Die Ausgabe wieder kodieren und mit den Eingabebytes vergleichen"),
+    ("Cancel", "Abbrechen"),
+    ("Stop the conversion and keep the current output", "Die Konvertierung stoppen und die aktuelle Ausgabe behalten"),
+    ("Wrap", "Umbrechen"),
+    ("Edit", "Bearbeiten"),
+    ("Explain", "Erklären"),
+    ("Text", "Text"),
+    ("Tree", "Baum"),
+    ("Copy as…", "Kopieren als…"),
+    ("Copy the output bytes written out for source code, a terminal or a chat", "Die Ausgabebytes ausgeschrieben für Quellcode, ein Terminal oder einen Chat kopieren"),
+    ("C array", "C-Array"),
+    ("Python bytes", "Python-bytes"),
+    ("Hexdump (xxd)", "Hexdump (xxd)"),
+    ("Copy JSON", "JSON kopieren"),
+    ("Copy redacted", "Geschwärzt kopieren"),
+    ("Copy with the redaction rules applied, whether or not Redact is on", "Mit angewendeten Schwärzungsregeln kopieren, unabhängig davon, ob Schwärzen an ist"),
+    ("Redact", "Schwärzen"),
+    ("Scrub matching values from the output view", "Passende Werte in der Ausgabe unkenntlich machen"),
+    ("Rules…", "Regeln…"),
+    ("{} value redacted", "{} Wert geschwärzt"),
+    ("{} values redacted", "{} Werte geschwärzt"),
+    ("Stats", "Statistik"),
+    ("Click to copy", "Zum Kopieren klicken"),
+    ("{}: bytes {}, {} encoded", "{}: Bytes {}, {} kodiert"),
+    ("(root)", "(Wurzel)"),
+    ("{} is over the {} edit limit, shown read-only", "{} liegt über der Bearbeitungsgrenze von {}, schreibgeschützt angezeigt"),
+    ("Copy all", "Alles kopieren"),
+    ("Viewer", "Ansicht"),
+    ("Read-only view that only renders the visible lines, for very large outputs", "Schreibgeschützte Ansicht, die nur die sichtbaren Zeilen darstellt, für sehr große Ausgaben"),
+    ("Copy lines", "Zeilen kopieren"),
+    ("{} chars", "{} Zeichen"),
+    ("{} chars · {} bytes decoded", "{} Zeichen · {} Bytes dekodiert"),
+    ("Convert a MessagePack payload to browse it here.", "Konvertiere MessagePack-Daten, um sie hier zu durchsuchen."),
+    ("Copy value as JSON", "Wert als JSON kopieren"),
+    ("Copy path", "Pfad kopieren"),
+    ("Show more ({} remaining)", "Mehr anzeigen ({} übrig)"),
+    // Query and redaction
+    ("Query:", "Abfrage:"),
+    ("/payload/readings/3/temp or $.payload.readings[*].temp", "/payload/readings/3/temp oder $.payload.readings[*].temp"),
+    ("Show the whole document", "Das ganze Dokument anzeigen"),
+    ("Nothing matches {}", "Nichts passt zu {}"),
+    ("Invalid query: start with \"/\" for a JSON Pointer or \"$\" for a JSONPath", "Ungültige Abfrage: beginne mit \"/\" für einen JSON Pointer oder mit \"$\" für einen JSONPath"),
+    ("Invalid query: \"~\" in \"{}\" must be followed by 0 or 1", "Ungültige Abfrage: auf \"~\" in \"{}\" muss 0 oder 1 folgen"),
+    ("Invalid query: expected a key after \".\" at position {}", "Ungültige Abfrage: Schlüssel nach \".\" an Position {} erwartet"),
+    ("Invalid query: unclosed or malformed \"[\" at position {}", "Ungültige Abfrage: nicht geschlossenes oder fehlerhaftes \"[\" an Position {}"),
+    ("Invalid query: expected \".\" or \"[\" at position {}", "Ungültige Abfrage: \".\" oder \"[\" an Position {} erwartet"),
+    ("Redaction rules", "Schwärzungsregeln"),
+    ("One rule per line: a JSON Pointer such as /user/email, where * matches any key or index, or a key name pattern such as password or *token*, matched at any depth and ignoring case.", "Eine Regel pro Zeile: ein JSON Pointer wie /user/email, wobei * auf jeden Schlüssel oder Index passt, oder ein Schlüsselmuster wie password oder *token*, das in jeder Tiefe und ohne Beachtung der Groß- und Kleinschreibung passt."),
+    ("Keep shape", "Form erhalten"),
+    ("Replace strings with asterisks of the same length, numbers with 0 and booleans with false", "Zeichenketten durch gleich viele Sternchen ersetzen, Zahlen durch 0 und Wahrheitswerte durch false"),
+    // Find bar
+    ("Find:", "Suchen:"),
+    ("Match case", "Groß-/Kleinschreibung beachten"),
+    ("Prev", "Zurück"),
+    ("Previous match (Shift+Enter)", "Vorheriger Treffer (Umschalt+Enter)"),
+    ("Next", "Weiter"),
+    ("Next match (Enter)", "Nächster Treffer (Enter)"),
+    ("Close", "Schließen"),
+    ("{} of {}", "{} von {}"),
+    ("No matches", "Keine Treffer"),
+    // Round trips
+    ("Lossless", "Verlustfrei"),
+    ("{} changed value", "{} geänderter Wert"),
+    ("{} changed values", "{} geänderte Werte"),
+    ("{} differing byte", "{} abweichendes Byte"),
+    ("{} differing bytes", "{} abweichende Bytes"),
+    ("Show the differences", "Die Unterschiede anzeigen"),
+    ("Round trip report", "Rückweg-Bericht"),
+    ("Original {}, re-encoded {}", "Original {}, neu kodiert {}"),
+    ("Changed values", "Geänderte Werte"),
+    ("Differing bytes", "Abweichende Bytes"),
+    ("Failed to decode the {} output: {}", "{}-Ausgabe konnte nicht dekodiert werden: {}"),
+    ("Failed to decode the {} input: {}", "{}-Eingabe konnte nicht dekodiert werden: {}"),
+    ("Failed to parse the {} output: {}", "{}-Ausgabe konnte nicht gelesen werden: {}"),
+    // Diff
+    ("Diff MessagePack payloads", "MessagePack-Daten vergleichen"),
+    ("Left (Base64 or Hex):", "Links (Base64 oder Hex):"),
+    ("Right (Base64 or Hex):", "Rechts (Base64 oder Hex):"),
+    ("Compare", "Vergleichen"),
+    ("The payloads decode to the same JSON", "Beide Daten ergeben dasselbe JSON"),
+    ("{} differences", "{} Unterschiede"),
+    ("Left: {}", "Links: {}"),
+    ("Right: {}", "Rechts: {}"),
+    ("~ {}: {} → {} (integer vs float)", "~ {}: {} → {} (Ganzzahl gegen Gleitkommazahl)"),
+    // Smart input and single pane
+    ("Paste JSON, or MessagePack as Base64 or Hex:", "JSON oder MessagePack als Base64 oder Hex einfügen:"),
+    ("Auto", "Automatisch"),
+    ("Hex MessagePack", "Hex-MessagePack"),
+    ("Base64 MessagePack", "Base64-MessagePack"),
+    ("Not recognized as JSON or as MessagePack in Base64 or Hex", "Weder als JSON noch als MessagePack in Base64 oder Hex erkannt"),
+    ("Detected: {}", "Erkannt: {}"),
+    ("Treated as {}", "Behandelt als {}"),
+    (" (also valid as {})", " (auch gültig als {})"),
+    ("⇄ Swap", "⇄ Tauschen"),
+    ("Move the output into the input and flip the direction", "Die Ausgabe in die Eingabe verschieben und die Richtung umkehren"),
+    // Examples
+    ("{}. Converting it fails on purpose.", "{}. Die Konvertierung schlägt absichtlich fehl."),
+    ("Flat object", "Flaches Objekt"),
+    ("A few scalar fields", "Ein paar skalare Felder"),
+    ("Nested structures", "Verschachtelte Strukturen"),
+    ("Objects inside objects inside arrays", "Objekte in Objekten in Arrays"),
+    ("Array-heavy telemetry", "Telemetrie mit vielen Arrays"),
+    ("Long numeric arrays, the kind of payload where MessagePack saves the most", "Lange Zahlenarrays, die Art von Daten, bei der MessagePack am meisten spart"),
+    ("Large integers", "Große Ganzzahlen"),
+    ("[uint64 max, int64 min], beyond what JavaScript numbers hold exactly", "[uint64 max, int64 min], mehr als JavaScript-Zahlen exakt darstellen"),
+    ("Binary, ext and timestamp", "Binär, Ext und Zeitstempel"),
+    ("A bin8 value and a timestamp ext, which have no JSON equivalent", "Ein bin8-Wert und eine Zeitstempel-Ext, die es in JSON nicht gibt"),
+    ("Broken MessagePack", "Kaputtes MessagePack"),
+    ("A map cut off in the middle, to show how errors are reported", "Eine mittendrin abgeschnittene Map, um zu zeigen, wie Fehler gemeldet werden"),
+    // Stats
+    ("Payload stats", "Datenstatistik"),
+    ("Type", "Typ"),
+    ("Count", "Anzahl"),
+    ("Encoded bytes", "Kodierte Bytes"),
+    ("String contents: {}", "Inhalt der Zeichenketten: {}"),
+    ("Longest string: {}", "Längste Zeichenkette: {}"),
+    ("Largest binary: {}", "Größter Binärwert: {}"),
+    ("Max depth: {}", "Maximale Tiefe: {}"),
+    ("{} byte", "{} Byte"),
+    ("{} bytes", "{} Bytes"),
+    ("JSON {} B → MessagePack {} B ({}%), CBOR {} B ({}%), base64 {} B", "JSON {} B → MessagePack {} B ({} %), CBOR {} B ({} %), Base64 {} B"),
+    (" · {} records, avg JSON {} B → MessagePack {} B", " · {} Datensätze, im Schnitt JSON {} B → MessagePack {} B"),
+    ("{} records", "{} Datensätze"),
+    // Validation
+    ("Valid JSON: {}", "Gültiges JSON: {}"),
+    ("Invalid JSON: {}", "Ungültiges JSON: {}"),
+    ("boolean", "Wahrheitswert"),
+    ("number", "Zahl"),
+    ("string of {} chars", "Zeichenkette mit {} Zeichen"),
+    ("array with {}", "Array mit {}"),
+    ("object with {}", "Objekt mit {}"),
+    ("{} item", "{} Element"),
+    ("{} items", "{} Elementen"),
+    ("{} key", "{} Schlüssel"),
+    ("{} keys", "{} Schlüsseln"),
+    ("{} entry", "{} Eintrag"),
+    ("{} entries", "{} Einträgen"),
+    ("{} with {}", "{} mit {}"),
+    ("Valid MessagePack: {}, {}", "Gültiges MessagePack: {}, {}"),
+    (" ({} back-to-back values, only the first is converted)", " ({} aufeinanderfolgende Werte, nur der erste wird konvertiert)"),
+    ("Invalid MessagePack: no input", "Ungültiges MessagePack: keine Eingabe"),
+    ("Invalid MessagePack: {}", "Ungültiges MessagePack: {}"),
+    // QR codes
+    ("QR", "QR"),
+    ("Show the output as QR codes", "Die Ausgabe als QR-Codes anzeigen"),
+    ("Level {}", "Stufe {}"),
+    ("Max version:", "Höchste Version:"),
+    ("Larger versions hold more per code but are harder to scan", "Größere Versionen fassen mehr pro Code, sind aber schwerer zu scannen"),
+    ("Raw bytes", "Rohe Bytes"),
+    ("Encode the MessagePack bytes instead of their Base64 text", "Die MessagePack-Bytes statt ihres Base64-Texts kodieren"),
+    ("◀ Prev", "◀ Zurück"),
+    ("Next ▶", "Weiter ▶"),
+    ("Code {} of {}", "Code {} von {}"),
+    ("Version {}, {}×{} modules", "Version {}, {}×{} Module"),
+    ("Save PNG", "PNG speichern"),
+    ("Saved {}", "{} gespeichert"),
+    ("Failed to make a QR code: {} bytes don't fit in version {} at level {}, which holds {}", "QR-Code konnte nicht erstellt werden: {} Bytes passen nicht in Version {} auf Stufe {}, die {} fasst"),
+    ("Failed to make QR codes: {} bytes would take {} codes, more than the {} allowed", "QR-Codes konnten nicht erstellt werden: {} Bytes bräuchten {} Codes, mehr als die erlaubten {}"),
+    ("Failed to create {}: {}", "{} konnte nicht erstellt werden: {}"),
+    ("Failed to write PNG: {}", "PNG konnte nicht geschrieben werden: {}"),
+    // Conversion errors
+    ("Failed to parse JSON: {} at line {} column {}", "JSON konnte nicht gelesen werden: {} in Zeile {}, Spalte {}"),
+    ("Failed to serialize to JSON: {}", "JSON konnte nicht geschrieben werden: {}"),
+    ("Failed to serialize to MessagePack: {}", "MessagePack konnte nicht geschrieben werden: {}"),
+    ("Failed to decode Hex: {}", "Hex konnte nicht dekodiert werden: {}"),
+    ("Failed to read the hexdump at line {}", "Hexdump konnte in Zeile {} nicht gelesen werden"),
+    ("Failed to read the byte array: {} is not a byte", "Byte-Array konnte nicht gelesen werden: {} ist kein Byte"),
+    ("Failed to decode escaped bytes: no valid escape at character {}", "Escapte Bytes konnten nicht dekodiert werden: kein gültiges Escape an Zeichen {}"),
+    ("Failed to decode Base64: {}", "Base64 konnte nicht dekodiert werden: {}"),
+    ("Failed to deserialize MessagePack: {}", "MessagePack konnte nicht gelesen werden: {}"),
+    ("Failed to deserialize MessagePack: {} at {}, offset {}", "MessagePack konnte nicht gelesen werden: {} bei {}, Offset {}"),
+    ("{} bytes left over after the value", "{} Bytes nach dem Wert übrig"),
+    ("Failed to read the input: {}", "Die Eingabe konnte nicht gelesen werden: {}"),
+    ("Failed to write the output: {}", "Die Ausgabe konnte nicht geschrieben werden: {}"),
+    ("Failed to read {}: {}", "{} konnte nicht gelesen werden: {}"),
+    ("Failed to write {}: {}", "{} konnte nicht geschrieben werden: {}"),
+    ("Conversion cancelled", "Konvertierung abgebrochen"),
+    ("Cancelled", "Abgebrochen"),
+    ("The conversion stopped unexpectedly", "Die Konvertierung wurde unerwartet beendet"),
+    ("{} at offset {}", "{} bei Offset {}"),
+    ("Nesting deeper than {} levels", "Verschachtelung tiefer als {} Ebenen"),
+    ("Nesting deeper than {} levels at {}", "Verschachtelung tiefer als {} Ebenen bei {}"),
+    ("Binary values are not supported", "Binärwerte werden nicht unterstützt"),
+    ("Extension type {} is not supported", "Erweiterungstyp {} wird nicht unterstützt"),
+    ("Map keys must be strings", "Map-Schlüssel müssen Zeichenketten sein"),
+    ("Invalid UTF-8 in string: {}", "Ungültiges UTF-8 in Zeichenkette: {}"),
+    ("Reserved marker 0xc1", "Reservierter Marker 0xc1"),
+    ("Unexpected end of input: {} more items expected", "Unerwartetes Ende der Eingabe: {} weitere Elemente erwartet"),
+    ("Unexpected end of input: needed {} more bytes but only {} remain", "Unerwartetes Ende der Eingabe: {} weitere Bytes nötig, aber nur {} übrig"),
+    // Session
+    ("{} ({} bytes)", "{} ({} Bytes)"),
+    ("Not restored, larger than the {} byte limit: {}", "Nicht wiederhergestellt, größer als das Limit von {} Byte: {}"),
+    // WebSocket
+    ("WebSocket…", "WebSocket…"),
+    ("Decode the MessagePack frames a WebSocket sends", "Die MessagePack-Frames eines WebSockets dekodieren"),
+    ("WebSocket frames", "WebSocket-Frames"),
+    ("URL:", "URL:"),
+    ("Connect", "Verbinden"),
+    ("Disconnect", "Trennen"),
+    ("Not connected", "Nicht verbunden"),
+    ("Connecting…", "Verbinde…"),
+    ("Connected", "Verbunden"),
+    ("Connection closed: {}", "Verbindung beendet: {}"),
+    ("Pause", "Anhalten"),
+    ("Resume", "Fortsetzen"),
+    ("While paused the list stays as it is, new frames wait", "Angehalten bleibt die Liste, wie sie ist, neue Frames warten"),
+    ("Keep the last", "Die letzten"),
+    ("frames", "Frames behalten"),
+    ("text", "Text"),
+    ("Only ws:// URLs are supported", "Nur ws://-URLs werden unterstützt"),
+    ("Invalid port {}", "Ungültiger Port {}"),
+    ("The URL has no host", "Die URL hat keinen Host"),
+    ("The server refused the upgrade: {}", "Der Server hat das Upgrade abgelehnt: {}"),
+    ("The server closed the connection", "Der Server hat die Verbindung beendet"),
+    ("Unknown opcode {}", "Unbekannter Opcode {}"),
+    ("Frame of {} bytes is too large", "Frame mit {} Bytes ist zu groß"),
+    ("Message of {} bytes is too large", "Nachricht mit {} Bytes ist zu groß"),
+    ("Continuation without a message", "Fortsetzung ohne Nachricht"),
+    ("{} waiting", "{} warten"),
+    // Listen
+    ("Listen…", "Lauschen…"),
+    ("Receive MessagePack over UDP or TCP", "MessagePack über UDP oder TCP empfangen"),
+    ("Listen", "Lauschen"),
+    ("Protocol:", "Protokoll:"),
+    ("UDP decodes every datagram on its own, TCP decodes the values each connection sends back to back", "UDP dekodiert jedes Datagramm für sich, TCP die Werte, die eine Verbindung hintereinander sendet"),
+    ("Address:", "Adresse:"),
+    ("Port:", "Port:"),
+    ("Stop", "Stoppen"),
+    ("Listening on {} {}", "Lausche auf {} {}"),
+    ("Failed to listen on {}: {}", "Lauschen auf {} fehlgeschlagen: {}"),
+    // MQTT
+    ("MQTT…", "MQTT…"),
+    ("Subscribe to an MQTT broker and decode what it sends", "Ein MQTT-Thema abonnieren und dekodieren, was der Broker sendet"),
+    ("MQTT", "MQTT"),
+    ("Broker:", "Broker:"),
+    ("User name:", "Benutzername:"),
+    ("Password:", "Passwort:"),
+    ("Topic filter:", "Themenfilter:"),
+    ("+ matches one level, # any number of levels at the end", "+ steht für eine Ebene, # am Ende für beliebig viele"),
+    ("QoS {}", "QoS {}"),
+    ("QoS {}, retained", "QoS {}, gespeichert"),
+    ("Reconnecting (attempt {}): {}", "Verbinde neu (Versuch {}): {}"),
+    ("Topic:", "Thema:"),
+    ("Retain", "Speichern"),
+    ("Publish JSON input", "JSON-Eingabe veröffentlichen"),
+    ("Encode the JSON input as MessagePack and publish it", "Die JSON-Eingabe als MessagePack kodieren und veröffentlichen"),
+    ("Published {} bytes to {}", "{} Bytes an {} veröffentlicht"),
+    ("Show topics:", "Themen zeigen:"),
+    ("Invalid filter", "Ungültiger Filter"),
+    ("Invalid topic filter {}", "Ungültiger Themenfilter {}"),
+    ("Only mqtt:// URLs are supported", "Nur mqtt://-URLs werden unterstützt"),
+    ("Packet of {} bytes is too large", "Paket mit {} Bytes ist zu groß"),
+    ("Malformed packet length", "Fehlerhafte Paketlänge"),
+    ("Packet cut short", "Paket ist abgeschnitten"),
+    ("The broker doesn't speak MQTT 3.1.1", "Der Broker spricht kein MQTT 3.1.1"),
+    ("The broker rejected the client identifier", "Der Broker hat die Client-Kennung abgelehnt"),
+    ("The broker is unavailable", "Der Broker ist nicht verfügbar"),
+    ("Bad user name or password", "Falscher Benutzername oder falsches Passwort"),
+    ("Not authorized", "Nicht berechtigt"),
+    ("The broker refused the connection ({})", "Der Broker hat die Verbindung abgelehnt ({})"),
+    ("The broker closed the connection", "Der Broker hat die Verbindung beendet"),
+    ("The broker didn't answer with CONNACK", "Der Broker hat nicht mit CONNACK geantwortet"),
+    ("The broker didn't answer", "Der Broker hat nicht geantwortet"),
+    ("The broker stopped answering", "Der Broker antwortet nicht mehr"),
+    ("The broker refused the subscription to {}", "Der Broker hat das Abonnement von {} abgelehnt"),
+    // CBOR
+    ("Failed to decode CBOR: {}", "CBOR konnte nicht dekodiert werden: {}"),
+    ("{} at {}", "{} bei {}"),
+    ("Unexpected break", "Unerwartetes Break"),
+    ("Simple value {} is not supported", "Einfacher Wert {} wird nicht unterstützt"),
+    ("Unexpected end of input", "Unerwartetes Ende der Eingabe"),
+    ("Reserved additional information {}", "Reservierte Zusatzinformation {}"),
+    ("Invalid chunk in an indefinite length string", "Ungültiger Teil in einem String unbestimmter Länge"),
+    ("Binary format:", "Binärformat:"),
+    ("Detect when decoding", "Beim Dekodieren erkennen"),
+    ("What the MessagePack panes hold, MessagePack or CBOR", "Was die MessagePack-Felder enthalten, MessagePack oder CBOR"),
+    // YAML
+    ("Failed to parse YAML: {} at line {} column {}", "YAML konnte nicht gelesen werden: {} in Zeile {} Spalte {}"),
+    ("{} documents where one was expected", "{} Dokumente, erwartet war eines"),
+    ("{} documents were combined into an array", "{} Dokumente wurden zu einem Array zusammengefasst"),
+    ("Unexpected {}", "Unerwartetes {}"),
+    ("Line {}: {}", "Zeile {}: {}"),
+    ("Unexpected indentation", "Unerwartete Einrückung"),
+    ("Expected a key", "Schlüssel erwartet"),
+    ("<< merges a mapping or a list of mappings", "<< führt eine Map oder eine Liste von Maps zusammen"),
+    ("the key {} became a string", "der Schlüssel {} wurde zur Zeichenkette"),
+    ("Duplicate key {}", "Doppelter Schlüssel {}"),
+    ("the tag {} is ignored", "der Tag {} wird ignoriert"),
+    ("Unknown alias *{}", "Unbekannter Alias *{}"),
+    ("Aliases expand to more than {} values", "Aliase ergeben mehr als {} Werte"),
+    ("{} has no JSON counterpart", "{} hat keine Entsprechung in JSON"),
+    ("{} is a string here, YAML 1.1 reads it as a boolean", "{} ist hier eine Zeichenkette, YAML 1.1 liest es als Boolean"),
+    ("Unterminated string", "Nicht abgeschlossene Zeichenkette"),
+    ("Unknown escape \\{}", "Unbekannte Escape-Sequenz \\{}"),
+    ("Invalid escape", "Ungültige Escape-Sequenz"),
+    ("Text format:", "Textformat:"),
+    ("What the JSON panes hold, JSON or YAML", "Was die JSON-Felder enthalten, JSON oder YAML"),
+    // Compression
+    ("Failed to decompress: {}", "Entpacken fehlgeschlagen: {}"),
+    ("Decompresses to more than the limit of {} bytes", "Entpackt mehr als die Grenze von {} Bytes"),
+    (" · {} {} B, {} B uncompressed, ratio {}", " · {} {} B, {} B entpackt, Verhältnis {}"),
+    ("Decompression limit:", "Grenze beim Entpacken:"),
+    ("gzip, zlib and zstd input is unpacked before decoding, up to this size", "gzip-, zlib- und zstd-Eingaben werden vor dem Dekodieren entpackt, bis zu dieser Größe"),
+    ("Compress output:", "Ausgabe komprimieren:"),
+    ("None", "Keine"),
+    ("Level:", "Stufe:"),
+    ("Wraps the MessagePack output, ready to send as a compressed body", "Verpackt die MessagePack-Ausgabe, bereit zum Senden als komprimierter Body"),
+    // zstd
+    ("Corrupt zstd data", "Beschädigte zstd-Daten"),
+    ("zstd dictionaries aren't supported", "zstd-Wörterbücher werden nicht unterstützt"),
+    ("The zstd checksum doesn't match", "Die zstd-Prüfsumme stimmt nicht"),
+    // Rust types
+    ("Generate Rust types", "Rust-Typen erzeugen"),
+    ("serde structs that fit the decoded value", "serde-Structs, die zum dekodierten Wert passen"),
+    ("Rust types", "Rust-Typen"),
+    ("Generate TypeScript", "TypeScript erzeugen"),
+    ("Interfaces that fit the decoded value", "Interfaces, die zum dekodierten Wert passen"),
+    ("TypeScript types", "TypeScript-Typen"),
+    // JSON Schema
+    ("JSON Schema", "JSON-Schema"),
+    ("The types, required properties and likely enums of the decoded value", "Die Typen, Pflichtfelder und vermutlichen Enums des dekodierten Werts"),
+    // JSON Schema validation
+    ("Validate against schema", "Gegen Schema prüfen"),
+    ("Check the input against the JSON Schema", "Die Eingabe gegen das JSON-Schema prüfen"),
+    ("Paste or open a JSON Schema in the Schema window first", "Zuerst im Schema-Fenster ein JSON-Schema einfügen oder öffnen"),
+    ("Schema", "Schema"),
+    ("The input doesn't match the JSON Schema, see the Schema window", "Die Eingabe passt nicht zum JSON-Schema, siehe Schema-Fenster"),
+    ("Open JSON Schema file", "JSON-Schema-Datei öffnen"),
+    ("Block conversion on violations", "Konvertierung bei Verstößen sperren"),
+    ("Convert to MessagePack refuses input that doesn't match the schema", "In MessagePack konvertieren lehnt Eingaben ab, die nicht zum Schema passen"),
+    ("Paste a JSON Schema (draft 2020-12) here", "JSON-Schema (Draft 2020-12) hier einfügen"),
+    ("Matches the schema", "Passt zum Schema"),
+    ("{} schema violation", "{} Schema-Verstoß"),
+    ("{} schema violations", "{} Schema-Verstöße"),
+    ("Schema check failed", "Schema-Prüfung fehlgeschlagen"),
+    ("{} is not UTF-8 text (invalid byte at offset {})", "{} ist kein UTF-8-Text (ungültiges Byte an Offset {})"),
+    (" or ", " oder "),
+    ("Matches none of the anyOf schemas", "Passt zu keinem der anyOf-Schemas"),
+    ("Matches none of the oneOf schemas", "Passt zu keinem der oneOf-Schemas"),
+    ("Matches {} of the oneOf schemas instead of one", "Passt zu {} der oneOf-Schemas statt zu einem"),
+    ("Matches the schema under not", "Passt zum Schema unter not"),
+    ("No value is allowed here", "Hier ist kein Wert erlaubt"),
+    ("Not one of the allowed values", "Keiner der erlaubten Werte"),
+    ("The schema refers to itself without end", "Das Schema verweist endlos auf sich selbst"),
+    ("Doesn't match the pattern {}", "Passt nicht zum Muster {}"),
+    ("Expected {}, found {}", "{} erwartet, {} gefunden"),
+    ("Fewer than {} items match contains", "Weniger als {} Elemente passen zu contains"),
+    ("More than {} items match contains", "Mehr als {} Elemente passen zu contains"),
+    ("Fewer than {} items", "Weniger als {} Elemente"),
+    ("More than {} items", "Mehr als {} Elemente"),
+    ("Fewer than {} properties", "Weniger als {} Eigenschaften"),
+    ("More than {} properties", "Mehr als {} Eigenschaften"),
+    ("Items {} and {} are the same", "Die Elemente {} und {} sind gleich"),
+    ("Less than the minimum of {}", "Kleiner als das Minimum {}"),
+    ("More than the maximum of {}", "Größer als das Maximum {}"),
+    ("Not more than {}", "Nicht größer als {}"),
+    ("Not less than {}", "Nicht kleiner als {}"),
+    ("Not a multiple of {}", "Kein Vielfaches von {}"),
+    ("Shorter than {} characters", "Kürzer als {} Zeichen"),
+    ("Longer than {} characters", "Länger als {} Zeichen"),
+    ("Missing required property {}", "Pflichtfeld {} fehlt"),
+    ("Missing property {}, which {} needs", "Feld {} fehlt, das {} braucht"),
+    ("Property {} isn't allowed", "Feld {} ist nicht erlaubt"),
+    ("Should be {}", "Sollte {} sein"),
+    ("The schema isn't valid JSON: {}", "Das Schema ist kein gültiges JSON: {}"),
+    ("Invalid schema at {}: {}", "Ungültiges Schema bei {}: {}"),
+    ("{} has to be {}", "{} muss {} sein"),
+    ("a schema has to be an object or true or false", "ein Schema muss ein Objekt oder true oder false sein"),
+    ("one of null, boolean, integer, number, string, array and object, or a list of them", "einer von null, boolean, integer, number, string, array und object oder eine Liste davon"),
+    ("an object of schemas", "ein Objekt aus Schemas"),
+    ("a list of schemas", "eine Liste von Schemas"),
+    ("a list of strings", "eine Liste von Strings"),
+    ("an object of lists of strings", "ein Objekt aus Listen von Strings"),
+    ("a list", "eine Liste"),
+    ("a whole number, 0 or more", "eine ganze Zahl ab 0"),
+    ("a number", "eine Zahl"),
+    ("a number above 0", "eine Zahl über 0"),
+    ("true or false", "true oder false"),
+    ("a string", "ein String"),
+    ("{} points nowhere in the schema", "{} zeigt auf nichts im Schema"),
+    ("only a $ref into the same schema, starting with #, is supported", "nur ein $ref in dasselbe Schema, beginnend mit #, wird unterstützt"),
+    ("isn't supported", "wird nicht unterstützt"),
+    ("{} isn't a regular expression this app understands: {}", "{} ist kein regulärer Ausdruck, den diese App versteht: {}"),
+    // Generator
+    ("Generate…", "Erzeugen…"),
+    ("Random payloads for testing a decoder, the same for the same seed", "Zufällige Nutzdaten zum Testen eines Decoders, gleich für denselben Seed"),
+    ("Generate payload", "Nutzdaten erzeugen"),
+    ("Seed:", "Seed:"),
+    ("The same seed and options give the same payload", "Derselbe Seed und dieselben Optionen ergeben dieselben Nutzdaten"),
+    ("Depth:", "Tiefe:"),
+    ("Levels of nested maps and arrays at most", "Höchstens so viele Ebenen verschachtelter Maps und Arrays"),
+    ("Map width:", "Map-Breite:"),
+    ("Array length:", "Array-Länge:"),
+    ("String length:", "String-Länge:"),
+    ("to", "bis"),
+    ("Types:", "Typen:"),
+    ("Integers", "Ganzzahlen"),
+    ("Every width from fixint to 64 bits, signed and unsigned", "Jede Breite von fixint bis 64 Bit, mit und ohne Vorzeichen"),
+    ("Floats", "Gleitkommazahlen"),
+    ("Booleans", "Wahrheitswerte"),
+    ("Strings", "Strings"),
+    ("Arrays", "Arrays"),
+    ("Maps", "Maps"),
+    ("Bins", "Bins"),
+    ("Exts", "Exts"),
+    ("Timestamps", "Zeitstempel"),
+    ("Timestamps:", "Zeitstempel:"),
+    ("RFC 3339 date-time", "RFC-3339-Datum und -Uhrzeit"),
+    ("Seconds since 1970", "Sekunden seit 1970"),
+    ("Nanoseconds since 1970", "Nanosekunden seit 1970"),
+    ("What MessagePack timestamps decode to, a date-time in UTC or a number", "Was MessagePack-Zeitstempel beim Dekodieren werden, Datum und Uhrzeit in UTC oder eine Zahl"),
+    ("JSON can't hold these, the payload goes into the MessagePack input as bytes", "JSON kann diese nicht aufnehmen, die Nutzdaten kommen als Bytes in die MessagePack-Eingabe"),
+    ("Generate", "Erzeugen"),
+    ("New seed", "Neuer Seed"),
+    ("{} bytes of MessagePack", "{} Bytes MessagePack"),
+    ("{} bytes of JSON, {} bytes as MessagePack", "{} Bytes JSON, {} Bytes als MessagePack"),
+
+    // Framing
+    ("Framing:", "Framing:"),
+    ("Records that each start with their length, as many TCP protocols send them", "Datensätze, die jeweils mit ihrer Länge beginnen, wie viele TCP-Protokolle sie senden"),
+    ("u16 big-endian", "u16 Big-Endian"),
+    ("u32 big-endian", "u32 Big-Endian"),
+    ("u32 little-endian", "u32 Little-Endian"),
+    ("Varint", "Varint"),
+    ("Frame {} at offset {} declares {} bytes from offset {}, but only {} are left", "Frame {} bei Offset {} gibt {} Bytes ab Offset {} an, es sind aber nur noch {} übrig"),
+    ("Frame {} at offset {} has no complete length prefix", "Frame {} bei Offset {} hat kein vollständiges Längenpräfix"),
+    ("A record of {} bytes is longer than the length prefix can hold, {} at most", "Ein Datensatz mit {} Bytes ist länger, als das Längenpräfix fassen kann, höchstens {}"),
+
+    // msgpack-RPC
+    ("Label msgpack-RPC messages", "msgpack-RPC-Nachrichten beschriften"),
+    ("Requests, responses and notifications decode to objects naming their parts", "Anfragen, Antworten und Benachrichtigungen werden zu Objekten, die ihre Teile benennen"),
+    ("msgpack-RPC", "msgpack-RPC"),
+    ("Labels requests, responses and notifications, and times each response against its request", "Beschriftet Anfragen, Antworten und Benachrichtigungen und misst jede Antwort an ihrer Anfrage"),
+    ("answered after {} ms", "beantwortet nach {} ms"),
+
+    // Compare
+    ("Compare JSON documents", "JSON-Dokumente vergleichen"),
+    ("Expected (JSON):", "Erwartet (JSON):"),
+    ("Actual (JSON):", "Tatsächlich (JSON):"),
+    ("Float tolerance:", "Toleranz für Gleitkommazahlen:"),
+    ("Numbers at most this far apart are equal", "Zahlen, die höchstens so weit auseinanderliegen, gelten als gleich"),
+    ("null equals a missing key", "null entspricht einem fehlenden Schlüssel"),
+    ("Ignore array order", "Reihenfolge in Arrays ignorieren"),
+    ("The documents are equal", "Die Dokumente sind gleich"),
+    ("Not equal, {} differences", "Nicht gleich, {} Unterschiede"),
+    ("Expected: {}", "Erwartet: {}"),
+    ("Actual: {}", "Tatsächlich: {}"),
+    ("Compare Expected", "Vergleich erwartet"),
+    ("Compare Actual", "Vergleich tatsächlich"),
+    // Re-encode
+    ("Re-encode", "Neu kodieren"),
+    ("Write the input bytes out again in another encoding, without decoding them", "Die Eingabebytes in einer anderen Kodierung ausgeben, ohne sie zu dekodieren"),
+    ("{} bytes as {}", "{} Bytes als {}"),
+    // NDJSON
+    ("Records as NDJSON", "Datensätze als NDJSON"),
+    ("Each value of the input, or each frame, on a line of its own instead of in an array", "Jeder Wert der Eingabe oder jeder Frame in einer eigenen Zeile statt in einem Array"),
+    // Session export
+    ("Export session…", "Sitzung exportieren…"),
+    ("Import session…", "Sitzung importieren…"),
+    ("Every tab, the settings and the undo history in one file to pass on", "Alle Tabs, die Einstellungen und der Rückgängig-Verlauf in einer Datei zum Weitergeben"),
+    ("Open the tabs of an exported session and take on its settings", "Die Tabs einer exportierten Sitzung öffnen und ihre Einstellungen übernehmen"),
+    ("Export session", "Sitzung exportieren"),
+    ("Import session", "Sitzung importieren"),
+    ("Written as MessagePack, or as JSON when the name ends in .json", "Als MessagePack geschrieben, oder als JSON, wenn der Name auf .json endet"),
+    ("Export", "Exportieren"),
+    ("Import", "Importieren"),
+    ("Not an exported session", "Keine exportierte Sitzung"),
+    ("Exported in format {}, newer than the {} this version reads", "Im Format {} exportiert, neuer als das Format {}, das diese Version liest"),
+    ("Settings left out: {}", "Einstellungen ausgelassen: {}"),
+    ("Tab {} left out: {}", "Tab {} ausgelassen: {}"),
+    // Binary clipboard
+    ("Paste binary", "Binär einfügen"),
+    ("Decode raw MessagePack bytes from the clipboard", "Rohe MessagePack-Bytes aus der Zwischenablage dekodieren"),
+    ("clipboard", "Zwischenablage"),
+    ("Failed to read the clipboard: {}", "Zwischenablage konnte nicht gelesen werden: {}"),
+    ("The clipboard holds no binary data, paste text into the pane instead", "Die Zwischenablage enthält keine Binärdaten, Text stattdessen in das Feld einfügen"),
+    ("Binary clipboard not supported here", "Binäre Zwischenablage wird hier nicht unterstützt"),
+    // Input size limit
+    ("Input size limit:", "Grenze für Eingaben:"),
+    ("Larger pasted input is turned down instead of converted. Binary files can be any size.", "Größere eingefügte Eingaben werden abgelehnt statt konvertiert. Binärdateien dürfen beliebig groß sein."),
+    ("The input is about {} MB, more than the {} MB input size limit. Open it as a binary file or convert it with the command line instead, or raise the limit in Settings.", "Die Eingabe ist etwa {} MB groß, mehr als die Grenze von {} MB. Öffnen Sie sie als Binärdatei oder konvertieren Sie sie über die Kommandozeile, oder heben Sie die Grenze in den Einstellungen an."),
+    // Field name templates
+    ("Field names", "Feldnamen"),
+    ("Name the fields of structs that were encoded as arrays", "Die Felder von Structs benennen, die als Arrays kodiert wurden"),
+    ("Structs that rmp_serde wrote as arrays decode to objects with these field names, in order. Arrays of another length are left as they are.", "Structs, die rmp_serde als Arrays geschrieben hat, werden zu Objekten mit diesen Feldnamen in ihrer Reihenfolge dekodiert. Arrays anderer Länge bleiben, wie sie sind."),
+    ("A JSON skeleton like {\"age\": 0, \"name\": \"\"}, or a list of names like [\"age\", \"name\"]", "Ein JSON-Gerüst wie {\"age\": 0, \"name\": \"\"} oder eine Liste von Namen wie [\"age\", \"name\"]"),
+    ("Field name template: {}", "Feldnamen-Vorlage: {}"),
+    ("{}: an array in the template holds field names, or one template for every element", "{}: Ein Array in der Vorlage enthält Feldnamen oder eine Vorlage für jedes Element"),
+    ("{} items where the template names {} fields, left as an array", "{} Elemente, wo die Vorlage {} Felder nennt, als Array belassen"),
+    // Changes since the last conversion
+    ("Highlight changes", "Änderungen hervorheben"),
+    ("Tint the JSON lines and count the MessagePack bytes that changed since the last conversion", "Die JSON-Zeilen einfärben und die MessagePack-Bytes zählen, die sich seit der letzten Konvertierung geändert haben"),
+    ("Same bytes as the last conversion", "Dieselben Bytes wie bei der letzten Konvertierung"),
+    ("{} bytes differ from the last conversion, the first at {}", "{} Bytes weichen von der letzten Konvertierung ab, das erste bei {}"),
+    ("Open both payloads in the Diff view", "Beide Payloads in der Diff-Ansicht öffnen"),
+    // Ext type registry
+    ("Ext types:", "Ext-Typen:"),
+    ("One ext type per line with its decoder: base64, uuid, string, a number type such as i128, or struct with name:type fields. Values of these types decode to {\"$ext\": type, \"value\": …} and encode back.", "Ein Ext-Typ pro Zeile mit seinem Decoder: base64, uuid, string, ein Zahlentyp wie i128 oder struct mit name:typ-Feldern. Werte dieser Typen werden zu {\"$ext\": Typ, \"value\": …} dekodiert und wieder zurück kodiert."),
+    ("Load…", "Laden…"),
+    ("Read the ext types from a TOML or JSON file", "Die Ext-Typen aus einer TOML- oder JSON-Datei lesen"),
+    ("Keep other types as base64", "Andere Typen als Base64 behalten"),
+    ("Bins:", "Binärwerte:"),
+    ("Not converted", "Nicht konvertiert"),
+    ("What MessagePack binary values decode to. Only the tagged form encodes back to a bin, the others become strings and arrays.", "Wozu MessagePack-Binärwerte dekodiert werden. Nur die markierte Form wird wieder zu einem Binärwert kodiert, die anderen werden zu Strings und Arrays."),
+    ("Tagged {\"$bin\": base64}", "Markiert {\"$bin\": base64}"),
+    ("Array of numbers", "Array von Zahlen"),
+    ("Other map keys:", "Andere Map-Schlüssel:"),
+    ("What maps with number, binary or other keys that aren't strings decode to. Pairs encode back to the same map, keys as strings don't.", "Wozu Maps mit Zahlen-, Binär- oder anderen Schlüsseln, die keine Strings sind, dekodiert werden. Paare werden wieder zur selben Map kodiert, Schlüssel als Strings nicht."),
+    ("Pairs {\"$map\": [[key, value], …]}", "Paare {\"$map\": [[Schlüssel, Wert], …]}"),
+    ("Keys as strings", "Schlüssel als Strings"),
+    ("Ext values of any other type decode to {\"$ext\": type, \"value\": \"<base64>\"} and encode back to the same bytes, instead of failing to convert", "Ext-Werte aller anderen Typen werden zu {\"$ext\": Typ, \"value\": \"<Base64>\"} dekodiert und zurück zu denselben Bytes kodiert, statt die Konvertierung scheitern zu lassen"),
+    ("Open ext types file", "Ext-Typen-Datei öffnen"),
+    ("Ext type {}: {}", "Ext-Typ {}: {}"),
+    ("Ext type {}: the decoder is not a string", "Ext-Typ {}: Der Decoder ist kein String"),
+    ("expected type = \"decoder\"", "erwartet: Typ = \"Decoder\""),
+    ("{} is not an ext type, those go from -128 to 127", "{} ist kein Ext-Typ, diese reichen von -128 bis 127"),
+    ("{}: a struct field is name:type", "{}: Ein Struct-Feld ist name:typ"),
+    ("{}: unknown field type, use u8-u128, i8-i128, f32, f64 or bool", "{}: unbekannter Feldtyp, u8-u128, i8-i128, f32, f64 oder bool verwenden"),
+    ("a struct needs at least one name:type field", "ein Struct braucht mindestens ein name:typ-Feld"),
+    ("unknown decoder {}, use base64, uuid, string, a number type or struct", "unbekannter Decoder {}, base64, uuid, string, einen Zahlentyp oder struct verwenden"),
+    ("the number takes {} bytes, this payload is {}", "die Zahl belegt {} Bytes, diese Nutzdaten haben {}"),
+    ("unexpected {} after the decoder", "unerwartetes {} nach dem Decoder"),
+    ("a UUID is 16 bytes, this payload is {}", "eine UUID hat 16 Bytes, diese Nutzdaten haben {}"),
+    ("the struct fields take {} bytes, this payload is {}", "die Struct-Felder belegen {} Bytes, diese Nutzdaten haben {}"),
+    ("{} is not a UUID", "{} ist keine UUID"),
+    ("the field {} is missing", "das Feld {} fehlt"),
+    ("the field {} is given twice", "das Feld {} ist doppelt angegeben"),
+    ("the struct has no field {}", "das Struct hat kein Feld {}"),
+    ("expected an object of the struct fields", "erwartet wurde ein Objekt mit den Struct-Feldern"),
+    // UUIDs
+    ("UUIDs:", "UUIDs:"),
+    ("16-byte bins as UUIDs", "16-Byte-Bins als UUIDs"),
+    ("Decode every 16-byte bin to a UUID string, and encode UUID strings back to 16 bytes. A hash of 16 bytes looks the same, so this is off by default.", "Jedes 16-Byte-Bin zu einem UUID-String dekodieren und UUID-Strings wieder zu 16 Bytes kodieren. Ein 16-Byte-Hash sieht genauso aus, daher ist dies standardmäßig aus."),
+    ("and ext type", "und Ext-Typ"),
+    // Date-times as timestamps
+    ("Date-times as timestamps", "Datumszeiten als Zeitstempel"),
+    ("Encode strings like 2024-05-01T12:30:00+02:00 as MessagePack timestamps, in UTC. Not every date-looking string is meant as one, so this is off by default.", "Strings wie 2024-05-01T12:30:00+02:00 als MessagePack-Zeitstempel in UTC kodieren. Nicht jeder String, der wie ein Datum aussieht, ist als solcher gemeint, daher ist dies standardmäßig aus."),
+    ("not a date in the calendar", "kein Datum im Kalender"),
+    ("not a time of day", "keine Uhrzeit"),
+    ("a leap second, which timestamps can't hold", "eine Schaltsekunde, die Zeitstempel nicht fassen können"),
+    ("finer than the nanoseconds timestamps hold", "feiner als die Nanosekunden, die Zeitstempel fassen"),
+    ("{} is left a string, {}", "{} bleibt ein String, {}"),
+    // Bookmarks
+    ("Bookmarks…", "Lesezeichen…"),
+    ("Payloads kept under a name, to load again later", "Unter einem Namen aufbewahrte Nutzdaten, um sie später wieder zu laden"),
+    ("Bookmarks", "Lesezeichen"),
+    ("Bookmark", "Lesezeichen"),
+    ("Name:", "Name:"),
+    ("e.g. login request v2", "z. B. Login-Anfrage v2"),
+    ("Notes:", "Notizen:"),
+    ("Bookmark current", "Aktuelles merken"),
+    ("Keep the input and output of this direction in the active tab", "Eingabe und Ausgabe dieser Richtung im aktiven Tab aufbewahren"),
+    ("Bookmarked as {}", "Als {} gemerkt"),
+    ("No bookmarks yet", "Noch keine Lesezeichen"),
+    ("Delete", "Löschen"),
+    ("Move down", "Nach unten"),
+    ("Move up", "Nach oben"),
+    ("Rename", "Umbenennen"),
+    ("File:", "Datei:"),
+    ("Write every bookmark to the file, to share them", "Alle Lesezeichen in die Datei schreiben, um sie weiterzugeben"),
+    ("Exported {} bookmarks to {}", "{} Lesezeichen nach {} exportiert"),
+    ("Add the bookmarks of an exported file that aren't here yet", "Die Lesezeichen einer exportierten Datei hinzufügen, die noch nicht hier sind"),
+    ("{} bookmark added", "{} Lesezeichen hinzugefügt"),
+    ("{} bookmarks added", "{} Lesezeichen hinzugefügt"),
+    ("Not a bookmarks file", "Keine Lesezeichendatei"),
+    ("A bookmark needs a name", "Ein Lesezeichen braucht einen Namen"),
+    ("There is a bookmark named {} already", "Es gibt schon ein Lesezeichen namens {}"),
+    // Byte diff
+    ("Values", "Werte"),
+    ("What the decoded payloads differ in", "Worin sich die dekodierten Nutzdaten unterscheiden"),
+    ("Bytes", "Bytes"),
+    ("Which bytes differ, encoding choices such as the width of an int included", "Welche Bytes sich unterscheiden, einschließlich Kodierungsentscheidungen wie der Breite eines Ints"),
+    ("The payloads are the same bytes", "Die Nutzdaten sind dieselben Bytes"),
+    ("{} range of bytes differs", "{} Bytebereich unterscheidet sich"),
+    ("{} ranges of bytes differ", "{} Bytebereiche unterscheiden sich"),
+    ("(too different to align, compared offset by offset)", "(zu verschieden zum Ausrichten, Offset für Offset verglichen)"),
+    ("{} has nothing there", "{} hat dort nichts"),
+    ("{} has {}", "{} hat {}"),
+    ("{} has bytes that don't decode", "{} hat Bytes, die sich nicht dekodieren lassen"),
+    ("left", "links"),
+    ("right", "rechts"),
+    // Code snippets
+    ("Copy as code", "Als Code kopieren"),
+    ("A snippet that encodes the input the same way, to hand on with it", "Ein Snippet, das die Eingabe genauso kodiert, zum Weitergeben mit ihr"),
+    ("A snippet that decodes the input the same way, to hand on with it", "Ein Snippet, das die Eingabe genauso dekodiert, zum Weitergeben mit ihr"),
+    ("The snippets decode MessagePack", "Die Snippets dekodieren MessagePack"),
+    ("The input is {}, so these read it from {}", "Die Eingabe ist {} groß, daher lesen diese sie aus {}"),
+    ("Embed anyway", "Trotzdem einbetten"),
+    // Nested MessagePack
+    ("Expand nested MessagePack", "Verschachteltes MessagePack aufklappen"),
+    ("Decode strings that hold base64 or hex MessagePack of a map or an array in place, as {\"$nested\": …, \"$original\": …}, and encode those back to strings", "Strings, die eine Map oder ein Array als Base64- oder Hex-MessagePack enthalten, an Ort und Stelle als {\"$nested\": …, \"$original\": …} dekodieren und diese wieder zu Strings kodieren"),
+    // Conversion warnings
+    ("{} warning", "{} Warnung"),
+    ("{} warnings", "{} Warnungen"),
+    ("{} at offset {}: {}", "{} bei Offset {}: {}"),
+    ("At offset {}: {}", "Bei Offset {}: {}"),
+    ("Base64 payload", "Base64-Nutzdaten"),
+    ("Key as string", "Schlüssel als String"),
+    ("Invalid UTF-8", "Ungültiges UTF-8"),
+    ("Simple value", "Einfacher Wert"),
+    ("Trailing bytes", "Überzählige Bytes"),
+    ("Timestamp", "Zeitstempel"),
+    ("binary value as base64", "Binärwert als Base64"),
+    ("ext type {} value as base64", "Wert des Ext-Typs {} als Base64"),
+    ("map key {} as a string", "Map-Schlüssel {} als String"),
+    ("invalid UTF-8 replaced", "ungültiges UTF-8 ersetzt"),
+    ("simple value {} as null", "einfacher Wert {} als null"),
+    ("{} bytes after the value ignored", "{} Bytes nach dem Wert ignoriert"),
+    ("16-byte values of this ext type are UUIDs too, and UUID strings encode to it instead of to bins", "16-Byte-Werte dieses Ext-Typs sind ebenfalls UUIDs, und UUID-Strings werden zu ihm statt zu Bins kodiert"),
+    ("expected a string", "erwartet wurde ein String"),
+    ("{} doesn't fit the field", "{} passt nicht in das Feld"),
+];
+
+
+/* Tests */
+#[test]
+fn test_fill_placeholders_in_order() {
+    assert_eq!(fill("{} of {}", &[&3, &"12"]), "3 of 12");
+    assert_eq!(fill("No placeholders", &[&1]), "No placeholders");
+    assert_eq!(fill("{} and {}", &[&1]), "1 and ");
+    assert_eq!(translate(Language::German, "Not a known string"), "Not a known string");
+    assert_eq!(translate(Language::English, "Convert to JSON"), "Convert to JSON");
+}
+
+#[test]
+fn test_german_table_is_consistent() {
+    for (index, (english, german)) in GERMAN.iter().enumerate() {
+        assert!(!GERMAN[..index].iter().any(|(other, _)| other == english), "{:?} is listed twice", english);
+        assert_eq!(english.matches("{}").count(), german.matches("{}").count(), "{:?}", english);
+    }
+}
+
+// Every string the sources look up by a literal has a German translation, as do the examples
+#[test]
+fn test_german_covers_every_looked_up_string() {
+    let sources = [
+        include_str!("batch.rs"),
+        include_str!("binary_clipboard.rs"),
+        include_str!("bookmarks.rs"),
+        include_str!("byte_text.rs"),
+        include_str!("byte_diff.rs"),
+        include_str!("cbor.rs"),
+        include_str!("cli.rs"),
+        include_str!("compress.rs"),
+        include_str!("convert.rs"),
+        include_str!("counter.rs"),
+        include_str!("decode.rs"),
+        include_str!("detect.rs"),
+        include_str!("diff.rs"),
+        include_str!("editor.rs"),
+        include_str!("error.rs"),
+        include_str!("examples.rs"),
+        include_str!("ext_types.rs"),
+        include_str!("feed.rs"),
+        include_str!("files.rs"),
+        include_str!("find.rs"),
+        include_str!("generate.rs"),
+        include_str!("history.rs"),
+        include_str!("listen.rs"),
+        include_str!("log.rs"),
+        include_str!("main.rs"),
+        include_str!("mqtt.rs"),
+        include_str!("msgpack.rs"),
+        include_str!("qr.rs"),
+        include_str!("query.rs"),
+        include_str!("recent.rs"),
+        include_str!("roundtrip.rs"),
+        include_str!("schema_check.rs"),
+        include_str!("session.rs"),
+        include_str!("settings.rs"),
+        include_str!("stats.rs"),
+        include_str!("template.rs"),
+        include_str!("timestamp.rs"),
+        include_str!("tree.rs"),
+        include_str!("validate.rs"),
+        include_str!("viewer.rs"),
+        include_str!("warning.rs"),
+        include_str!("websocket.rs"),
+        include_str!("worker.rs"),
+        include_str!("yaml.rs"),
+        include_str!("zstd.rs"),
+    ];
+    let translated = |text: &str| GERMAN.iter().any(|(english, _)| *english == text);
+    for source in sources {
+        for call in ["tr(", "trf("] {
+            for (at, _) in source.match_indices(call) {
+                let Some(literal) = source[at + call.len()..].trim_start().strip_prefix('"') else {
+                    continue;
+                };
+                // Up to the closing quote, undoing escaped quotes
+                let mut text = String::new();
+                let mut chars = literal.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => text.extend(chars.next()),
+                        '"' => break,
+                        _ => text.push(c),
+                    }
+                }
+                assert!(translated(&text), "No German for {:?}", text);
+            }
+        }
+    }
+    for example in crate::examples::EXAMPLES {
+        assert!(translated(example.name) && translated(example.description), "{}", example.name);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::locale::{tr, trf};
+use eframe::egui;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Oldest entries are dropped beyond this
+pub const MAX_LOG_ENTRIES: usize = 300;
+pub const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+// An error raised by something done in a tab, queued there until the app logs it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorEvent {
+    // English name of the action that failed, e.g. "Convert to JSON", translated when shown
+    pub operation: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub time: SystemTime,
+    pub tab: String,
+    pub operation: &'static str,
+    pub message: String,
+}
+
+impl LogEntry {
+    // e.g. "14:03:27 Tab 1 · Convert to JSON: Failed to decode Base64: Invalid padding"
+    pub fn line(&self) -> String {
+        format!("{} {} · {}: {}", clock_time(self.time), self.tab, tr(self.operation), self.message)
+    }
+}
+
+// Every error of the session, newest last, and the ones recent enough to still show as a toast
+#[derive(Default)]
+pub struct ErrorLog {
+    entries: VecDeque<LogEntry>,
+    toasts: Vec<(LogEntry, Instant)>,
+    pub open: bool,
+}
+
+impl ErrorLog {
+    pub fn push(&mut self, tab: &str, event: ErrorEvent, now: Instant) {
+        let entry = LogEntry {
+            time: SystemTime::now(),
+            tab: tab.to_string(),
+            operation: event.operation,
+            message: event.message,
+        };
+        self.toasts.push((entry.clone(), now));
+        self.entries.push_back(entry);
+        if self.entries.len() > MAX_LOG_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    // The whole log as text, one entry per line, for pasting into a bug report
+    pub fn text(&self) -> String {
+        self.entries.iter().map(LogEntry::line).collect::<Vec<_>>().join("\n")
+    }
+
+    // Drops the toasts that have been up long enough, returns how long until the next one goes
+    pub fn expire_toasts(&mut self, now: Instant) -> Option<Duration> {
+        self.toasts.retain(|(_, shown)| now.duration_since(*shown) < TOAST_DURATION);
+        self.toasts.iter().map(|(_, shown)| TOAST_DURATION - now.duration_since(*shown)).min()
+    }
+
+    // Stacked in the bottom right corner, newest at the bottom, each with its own dismiss button
+    pub fn show_toasts(&mut self, ctx: &egui::Context) {
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("error_toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -36.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (index, (entry, _)) in self.toasts.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(360.0);
+                        ui.horizontal(|ui| {
+                            ui.vertical(|ui| {
+                                ui.weak(format!("{} · {}", entry.tab, tr(entry.operation)));
+                                ui.label(egui::RichText::new(&entry.message).color(ui.visuals().error_fg_color));
+                            });
+                            if ui.small_button("×").on_hover_text(tr("Dismiss")).clicked() {
+                                dismissed = Some(index);
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(index) = dismissed {
+            self.toasts.remove(index);
+        }
+    }
+
+    // Contents of the Log panel, returns the text to copy when Copy was clicked
+    pub fn show_panel(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        let mut copied = None;
+        ui.horizontal(|ui| {
+            ui.strong(tr("Log"));
+            ui.weak(trf(if self.entries.len() == 1 { "{} error" } else { "{} errors" }, &[&self.entries.len()]));
+            if ui.add_enabled(!self.entries.is_empty(), egui::Button::new(tr("Copy"))).clicked() {
+                copied = Some(self.text());
+            }
+            if ui.add_enabled(!self.entries.is_empty(), egui::Button::new(tr("Clear"))).clicked() {
+                self.entries.clear();
+            }
+        });
+        egui::ScrollArea::vertical().auto_shrink([false, false]).stick_to_bottom(true).show(ui, |ui| {
+            if self.entries.is_empty() {
+                ui.weak(tr("Errors from conversions and other actions are listed here."));
+            }
+            for entry in &self.entries {
+                ui.horizontal_wrapped(|ui| {
+                    ui.monospace(clock_time(entry.time));
+                    ui.weak(format!("{} · {}", entry.tab, tr(entry.operation)));
+                    ui.label(&entry.message);
+                });
+            }
+        });
+        copied
+    }
+}
+
+// Time of day in UTC as HH:MM:SS, there is no time zone database to go by
+pub fn clock_time(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs()) % (24 * 60 * 60);
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn event(message: &str) -> ErrorEvent {
+    ErrorEvent { operation: "Convert to JSON", message: message.to_string() }
+}
+
+#[test]
+fn test_log_is_capped_and_keeps_the_newest() {
+    let mut log = ErrorLog::default();
+    let now = Instant::now();
+    for i in 0..MAX_LOG_ENTRIES + 5 {
+        log.push("Tab 1", event(&i.to_string()), now);
+    }
+    assert_eq!(log.entry_count(), MAX_LOG_ENTRIES);
+    assert_eq!(log.entries.front().unwrap().message, "5");
+    assert!(log.text().lines().last().unwrap().ends_with(&format!("Tab 1 · Convert to JSON: {}", MAX_LOG_ENTRIES + 4)));
+}
+
+#[test]
+fn test_toasts_expire_but_stay_in_the_log() {
+    let mut log = ErrorLog::default();
+    let start = Instant::now();
+    log.push("Tab 1", event("first"), start);
+    log.push("Tab 2", event("second"), start + Duration::from_secs(2));
+
+    assert_eq!(log.expire_toasts(start + Duration::from_secs(1)), Some(Duration::from_secs(5)));
+    assert_eq!(log.expire_toasts(start + Duration::from_secs(7)), Some(Duration::from_secs(1)));
+    assert_eq!(log.toasts.len(), 1);
+    assert_eq!(log.expire_toasts(start + Duration::from_secs(8)), None);
+    assert_eq!(log.entry_count(), 2);
+}
+
+#[test]
+fn test_clock_time() {
+    assert_eq!(clock_time(UNIX_EPOCH), "00:00:00");
+    assert_eq!(clock_time(UNIX_EPOCH + Duration::from_secs(3 * 86_400 + 13 * 3600 + 62)), "13:01:02");
+}
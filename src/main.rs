@@ -1,189 +1,3469 @@
+mod batch;
+mod binary_clipboard;
+mod bookmarks;
+mod byte_diff;
+mod changes;
+mod cli;
+mod counter;
+mod diff;
+mod editor;
+mod explain;
+mod find;
+mod feed;
+mod generate;
+mod history;
+mod listen;
+mod log;
+mod mqtt;
+mod qr;
+mod query;
+mod recent;
+mod redact;
+mod roundtrip;
+mod rust_types;
+mod serve;
+mod session;
+mod settings;
+mod snippet;
+mod tree;
+mod typescript;
+mod validate;
+mod viewer;
+mod watch;
+mod websocket;
+
+use messagepack_to_json::{byte_text, checksum, compress, convert, decode, detect, error, examples, ext_types, files, format, framing, locale, msgpack, rpc, schema, schema_check, shape, stats, template, timestamp, warning, worker, yaml, zstd};
 use eframe::egui;
-use serde_json;
-use rmp_serde;
 use base64::{engine::general_purpose, Engine};
-use std::sync::{Arc, Mutex};
+#[cfg(test)]
+use messagepack_to_json::{json_to_messagepack, messagepack_to_json};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
 use clipboard::{ClipboardProvider, ClipboardContext};
-use hex;
+use batch::{batch_window, BatchState};
+use binary_clipboard::paste_binary;
+use bookmarks::{bookmarks_window, Bookmark, BookmarksState};
+use byte_diff::{format_row, ByteDiff, Cell};
+use byte_text::CopyAs;
+use changes::{changed_lines, ByteChanges};
+use cli::Launch;
+use checksum::Checksums;
+use compress::{compress, decompress, Compressed, Compression};
+use convert::{convert_until, BinaryFormat, ConvertOptions, Direction, TextFormat};
+use counter::PaneCounter;
+use decode::{path_at_offset, SpanMap};
+use detect::{candidates, messagepack_bytes, InputKind, PREFERENCE};
+use diff::{diff, diff_with, Change, ChangeKind, DiffOptions};
+use editor::{labeled_editor, pane_header, text_editor, EditorOptions, Highlights};
+use error::ConvertError;
+use examples::{Example, ExampleInput, EXAMPLES};
+use explain::{explain, show_explanation, Explanation};
+use files::{file_input, file_name, file_size, hex_digits, open_file, read_file, write_file, BinaryFile, Encoding, FileInput, InputEncoding, FileTarget, OutputEncoding, SaveTarget, ASK_ABOVE_BYTES};
+use find::FindState;
+use format::JsonFormat;
+use framing::Framing;
+use generate::{generate_window, GenerateState};
+use history::{history_buttons, PaneHistory, Step};
+use listen::{listen_window, ListenState};
+use locale::{tr, trf};
+use log::{ErrorEvent, ErrorLog};
+use mqtt::{mqtt_window, MqttState};
+use qr::{show_qr, QrState};
+use query::query_output;
+use recent::recent_menu;
+use redact::Redaction;
+use roundtrip::{verify_decoding, verify_encoding, RoundTrip};
+use rust_types::rust_types;
+use schema::json_schema;
+use schema_check::{pointer_name, CompiledSchema, Violation};
+use session::{Base64Bytes, ExportedFile, ExportedTab, PaneSnapshots, Session, SessionExport, TabSession, EXPORT_VERSION};
+use settings::{settings_window, Settings};
+use snippet::{decode_snippet, encode_snippet, Language, Source, JSON_FILE, MAX_INLINE_BYTES, MESSAGEPACK_FILE};
+use stats::{type_stats, SizeStats, TypeStats};
+use template::Template;
+use tree::{show_tree, TreeState};
+use typescript::typescript;
+use validate::{validate_json, validate_messagepack};
+use viewer::{show_viewer, LineViewer};
+use warning::Warning;
+use watch::{FileWatch, POLL_INTERVAL};
+use websocket::{websocket_window, WebSocketState};
+use worker::{JobToken, Worker};
+use yaml::parse_yaml;
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum Section {
+    #[default]
+    JsonToMessagePack,
+    MessagePackToJson,
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum TabMode {
+    #[default]
+    Convert,
+    // One input box whose content is recognized as JSON or MessagePack and converted accordingly
+    Smart,
+    Diff,
+    // Two JSON documents checked for meaning the same, whatever their key order and layout
+    Compare,
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum OutputPane {
+    MessagePack,
+    #[default]
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pane {
+    JsonInput,
+    MessagePackOutput,
+    MessagePackInput,
+    JsonOutput,
+}
+
+impl Pane {
+    const ALL: [Pane; 4] = [Pane::JsonInput, Pane::MessagePackOutput, Pane::MessagePackInput, Pane::JsonOutput];
+}
+
+#[derive(Clone, PartialEq)]
+enum JsonInputAction {
+    Format,
+    Minify,
+    Open,
+    OpenRecent(PathBuf),
+    History(Step),
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum MessagePackInputView {
+    #[default]
+    Edit,
+    Explain,
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum JsonOutputView {
+    #[default]
+    Text,
+    Tree,
+}
+
+// What the Diff view compares: the decoded values, or the encoded bytes
+#[derive(Default, Clone, Copy, PartialEq)]
+enum DiffView {
+    #[default]
+    Values,
+    Bytes,
+}
+
+// Query and redaction applied to the JSON Output text, both derived from the decoded value
+// rather than the text so the full output is never touched
+#[derive(Default)]
+struct JsonOutputFilter {
+    // Narrows the output to a part of the document
+    query: String,
+    // Result of the query, None while it has to be recomputed
+    query_output: Option<Result<String, String>>,
+    redact: bool,
+    // Redacted document, its text and how many values were replaced, for the rules it was made with
+    redacted: Option<(Redaction, serde_json::Value, String, usize)>,
+}
+
+impl JsonOutputFilter {
+    // Forgets everything derived from the previous decoded value
+    fn invalidate(&mut self) {
+        self.query_output = None;
+        self.redacted = None;
+    }
+
+    fn refresh(&mut self, value: Option<&serde_json::Value>, settings: &Settings) {
+        let Some(value) = value else {
+            return;
+        };
+        let redaction = settings.redaction();
+        let stale = self.redacted.as_ref().map(|(rules, ..)| rules) != Some(&redaction);
+        if self.redact && stale {
+            let (redacted, count) = redaction.apply(value);
+            let text = settings.json_format().pretty(&redacted).unwrap_or_default();
+            self.redacted = Some((redaction, redacted, text, count));
+            self.query_output = None;
+        } else if !self.redact && self.redacted.is_some() {
+            self.redacted = None;
+            self.query_output = None;
+        }
+        if self.query_output.is_none() && !self.query.trim().is_empty() {
+            let value = self.redacted.as_ref().map_or(value, |(_, redacted, ..)| redacted);
+            self.query_output = Some(query_output(value, &self.query, &settings.json_format()));
+        }
+    }
+
+    // What the JSON Output pane shows in place of the full text, if anything
+    fn shown(&self) -> Option<&str> {
+        match (&self.query_output, &self.redacted) {
+            (Some(Ok(filtered)), _) if !self.query.trim().is_empty() => Some(filtered),
+            (_, Some((_, _, text, _))) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn query_error(&self) -> Option<&str> {
+        match &self.query_output {
+            Some(Err(e)) if !self.query.trim().is_empty() => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// One independent set of the four panes
+#[derive(Default)]
+struct Tab {
+    // Keeps widget state such as scroll positions apart between tabs
+    id: u64,
+    title: String,
+    mode: TabMode,
+    json_input: String,
+    // Snapshots of each pane from before the app replaced its text, indexed by Pane
+    histories: [PaneHistory; 4],
+    // Editor pane that had keyboard focus in the last frame
+    focused_pane: Option<Pane>,
+    // Result of the last Validate click, dropped as soon as the input is edited
+    json_validation: Option<Result<String, String>>,
+    // JSON Schema pasted or opened into the schema window, for checking the JSON input
+    schema_text: String,
+    show_schema: bool,
+    // Violations found by the last check against schema_text, or why it couldn't run
+    schema_check: Option<Result<Vec<Violation>, String>>,
+    messagepack_output: String,
+    encode_worker: Worker<(Encoded, String)>,
+    // Row-based read-only view, used for outputs above the display limit
+    messagepack_viewer: LineViewer,
+    // Shown in place of the MessagePack output text while open
+    qr: QrState,
+    encode_stats: Option<SizeStats>,
+    encode_checksums: Option<Checksums>,
+    // What the last JSON → MessagePack conversion changed or couldn't do, under its output
+    encode_warnings: Vec<Warning>,
+    // How the bytes of the last conversion differ from those of the one before, kept for Compare
+    byte_changes: Option<(ByteChanges, Vec<u8>)>,
+    messagepack_input: String,
+    // How messagepack_input is read, and what Detect last read it as
+    messagepack_input_encoding: InputEncoding,
+    messagepack_read_as: Option<InputEncoding>,
+    // Binary file converted from its bytes, messagepack_input stays empty until its text is asked for
+    messagepack_file: Option<BinaryFile>,
+    // File that messagepack_file is read from again whenever it changes
+    watch: Option<FileWatch>,
+    // Where each input pane was last loaded from, which saved outputs are named after
+    opened_json: Option<PathBuf>,
+    opened_messagepack: Option<PathBuf>,
+    messagepack_input_view: MessagePackInputView,
+    messagepack_validation: Option<Result<String, String>>,
+    // Field names for structs rmp_serde wrote as arrays, see Template
+    field_template: String,
+    show_field_template: bool,
+    reencode_target: Encoding,
+    // Bytes the last Re-encode put into the JSON output pane, and as what
+    reencoded: Option<(usize, Encoding)>,
+    // Annotated bytes of messagepack_input as of the last conversion or switch to the Explain view
+    explanation: Option<(Vec<u8>, Explanation)>,
+    json_output: String,
+    decode_worker: Worker<DecodeOutput>,
+    json_viewer: LineViewer,
+    json_filter: JsonOutputFilter,
+    // Lines of json_output that weren't in the output of the conversion before
+    json_changes: Vec<std::ops::Range<usize>>,
+    show_redaction_rules: bool,
+    // The value behind json_output, kept so the tree view doesn't have to parse the text again
+    decoded_value: Option<serde_json::Value>,
+    // Where each node of decoded_value sits in the bytes of the explanation
+    spans: SpanMap,
+    decode_stats: Option<SizeStats>,
+    decode_checksums: Option<Checksums>,
+    decode_warnings: Vec<Warning>,
+    type_stats: Option<TypeStats>,
+    show_type_stats: bool,
+    // Types generated from decoded_value and the title of the window that shows them until it's
+    // closed
+    generated_types: Option<(String, String)>,
+    explanation_scroll_pending: bool,
+    json_output_view: JsonOutputView,
+    tree_state: TreeState,
+    // Errors raised since the last frame, the app moves them to its log
+    error_events: Vec<ErrorEvent>,
+    find: FindState,
+    // Output pane the find bar searches, follows whichever output pane was focused last
+    find_pane: OutputPane,
+    find_focus_requested: bool,
+    json_input_counter: PaneCounter,
+    messagepack_output_counter: PaneCounter,
+    messagepack_input_counter: PaneCounter,
+    json_output_counter: PaneCounter,
+    // Last "Verify round trip" result for each direction, reset by the next conversion
+    encode_round_trip: Option<RoundTrip>,
+    decode_round_trip: Option<RoundTrip>,
+    // Direction whose round trip report window is open
+    round_trip_report: Option<Section>,
+    // Shown in the status bar until the next conversion of either direction finishes
+    last_conversion: Option<ConversionSummary>,
+    smart_input: String,
+    // Kind forced from the dropdown, otherwise the first candidate is used
+    smart_override: Option<InputKind>,
+    smart_candidates: Vec<InputKind>,
+    diff_left: String,
+    diff_right: String,
+    diff_view: DiffView,
+    // Changes from the last Compare, None until both sides decoded
+    diff: Option<Vec<Change>>,
+    // Same for the bytes, None until both sides were base64 or hex
+    byte_diff: Option<ByteDiff>,
+    compare_expected: String,
+    compare_actual: String,
+    compare_options: DiffOptions,
+    // None until both sides parsed
+    comparison: Option<Vec<Change>>,
+    // Set by an input pane's Open… button, the app asks for the file
+    open_request: Option<FileTarget>,
+    // A file picked from an input pane's Recent menu, opened as if its path had been entered
+    open_recent: Option<(FileTarget, PathBuf)>,
+    // Likewise for an output pane's Save…
+    save_request: Option<SaveTarget>,
+    // Raw bytes of the last conversion behind messagepack_output, dropped once the pane changes
+    // some other way
+    messagepack_bytes: Option<Vec<u8>>,
+}
+
+// A file to read into the active tab, waiting for its path or, when it is large, for the user to
+// confirm loading it
+struct FilePrompt {
+    target: FileTarget,
+    path: String,
+    // Size of the file once it was found to be over ASK_ABOVE_BYTES
+    large: Option<u64>,
+    // Keep converting the file whenever it changes
+    watch: bool,
+}
+
+// Where to write an output pane, waiting for the path
+struct SavePrompt {
+    target: SaveTarget,
+    path: String,
+}
+
+// Where to export the session to, or import one from, waiting for the path
+struct SessionPrompt {
+    import: bool,
+    path: String,
+}
+
+#[derive(Default)]
+struct MessagePackJsonConverterApp {
+    // Never empty, closing the last tab replaces it with a fresh one
+    tabs: Vec<Tab>,
+    active_tab: usize,
+    next_tab_id: u64,
+    // Tab whose title is being edited in place of its label
+    renaming_tab: Option<usize>,
+    settings: Settings,
+    // Section shown when the window is too narrow for both columns, and the direction of the
+    // single pane layout
+    narrow_section: Section,
+    // What was last written to the session file and when, so unchanged panes aren't rewritten
+    saved_session: Option<Session>,
+    last_autosave: Option<Instant>,
+    show_settings: bool,
+    error_log: ErrorLog,
+    file_prompt: Option<FilePrompt>,
+    save_prompt: Option<SavePrompt>,
+    session_prompt: Option<SessionPrompt>,
+    batch: BatchState,
+    bookmarks: BookmarksState,
+    // Dropping it closes the connection, so closing the app disconnects as well
+    websocket: WebSocketState,
+    listen: ListenState,
+    mqtt: MqttState,
+    generate: GenerateState,
+}
+
+// Pane contents are also saved periodically so a crash loses at most this much work
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+impl MessagePackJsonConverterApp {
+    fn new(cc: &eframe::CreationContext<'_>, file: Option<PathBuf>) -> Self {
+        let settings = Settings::load();
+        cc.egui_ctx.set_zoom_factor(settings.zoom);
+        locale::set_language(settings.language);
+        let mut app = MessagePackJsonConverterApp {
+            settings,
+            last_autosave: Some(Instant::now()),
+            ..Default::default()
+        };
+        if app.settings.restore_session {
+            if let Some(session) = Session::load() {
+                app.restore_session(session);
+            }
+        }
+        if let Some(path) = file {
+            app.open_argument(&path, &cc.egui_ctx);
+        }
+        if app.tabs.is_empty() {
+            app.open_tab();
+        }
+        app
+    }
+
+    // A file named on the command line goes into a tab of its own, next to any the session
+    // brought back, and is converted straight away. One that can't be read is reported there.
+    fn open_argument(&mut self, path: &Path, ctx: &egui::Context) {
+        self.open_tab();
+        self.load_file(open_file(path, FileTarget::Sniff), Some(path), ctx);
+        let tab = &mut self.tabs[self.active_tab];
+        if self.settings.auto_convert_examples || !tab.error_events.is_empty() {
+            return;
+        }
+        match self.narrow_section {
+            Section::JsonToMessagePack => tab.start_encoding(ctx, &self.settings),
+            Section::MessagePackToJson => tab.start_decoding(ctx, &self.settings),
+        }
+    }
+
+    fn open_tab(&mut self) {
+        self.next_tab_id += 1;
+        self.tabs.push(Tab {
+            id: self.next_tab_id,
+            title: trf("Tab {}", &[&self.next_tab_id]),
+            ..Default::default()
+        });
+        self.active_tab = self.tabs.len() - 1;
+    }
+
+    fn close_tab(&mut self, index: usize) {
+        self.tabs.remove(index);
+        self.renaming_tab = None;
+        if self.tabs.is_empty() {
+            self.open_tab();
+        } else if self.active_tab > index || self.active_tab == self.tabs.len() {
+            self.active_tab -= 1;
+        }
+    }
+
+    fn restore_session(&mut self, session: Session) {
+        for saved in &session.tabs {
+            self.open_tab();
+            let tab = self.tabs.last_mut().unwrap();
+            if let Some(notice) = saved.skipped_notice() {
+                tab.report_error("Restore session", notice);
+            }
+            tab.restore_panes(saved);
+        }
+        self.active_tab = session.active_tab.min(self.tabs.len().saturating_sub(1));
+        self.saved_session = Some(session);
+    }
+
+    fn session(&self) -> Session {
+        let tabs = self.tabs.iter().map(|tab| {
+            let mut saved = tab.panes();
+            saved.limit_pane_sizes();
+            saved
+        }).collect();
+        Session { tabs, active_tab: self.active_tab }
+    }
+
+    fn save_session(&mut self) -> Result<(), String> {
+        if !self.settings.restore_session {
+            self.saved_session = None;
+            return Session::delete();
+        }
+        let session = self.session();
+        if self.saved_session.as_ref() == Some(&session) {
+            return Ok(());
+        }
+        session.save()?;
+        self.saved_session = Some(session);
+        Ok(())
+    }
+
+    // Every tab in full, with the bytes behind its panes and the undo history of each
+    fn export_session(&self) -> SessionExport {
+        let tabs = self.tabs.iter().map(|tab| ExportedTab {
+            panes: tab.panes(),
+            messagepack_file: tab.messagepack_file.as_ref().map(|file| {
+                ExportedFile { name: file.name.clone(), bytes: Base64Bytes(file.bytes.to_vec()), encoding: file.encoding }
+            }),
+            messagepack_bytes: tab.messagepack_bytes.clone().map(Base64Bytes),
+            histories: tab.histories.iter().map(|history| {
+                let (undo, redo) = history.snapshots();
+                PaneSnapshots { undo: undo.to_vec(), redo: redo.to_vec() }
+            }).collect(),
+        }).collect();
+        SessionExport { version: EXPORT_VERSION, settings: Some(self.settings.clone()), tabs, active_tab: self.active_tab }
+    }
+
+    // The exported tabs open next to the ones already there, and the exported settings replace
+    // these. What couldn't be imported is logged.
+    fn import_session(&mut self, ctx: &egui::Context, export: SessionExport, notes: Vec<String>) {
+        if let Some(mut settings) = export.settings {
+            // Recent files are paths on the machine the session came from
+            settings.recent_json_files = std::mem::take(&mut self.settings.recent_json_files);
+            settings.recent_messagepack_files = std::mem::take(&mut self.settings.recent_messagepack_files);
+            ctx.set_zoom_factor(settings.zoom);
+            locale::set_language(settings.language);
+            self.settings = settings;
+        }
+        let first = self.tabs.len();
+        for exported in export.tabs {
+            self.open_tab();
+            let tab = self.tabs.last_mut().unwrap();
+            tab.restore_panes(&exported.panes);
+            tab.messagepack_file = exported.messagepack_file.map(|file| BinaryFile::new(&file.name, file.bytes.0, file.encoding));
+            tab.messagepack_bytes = exported.messagepack_bytes.map(|bytes| bytes.0);
+            for (history, snapshots) in tab.histories.iter_mut().zip(exported.histories) {
+                *history = PaneHistory::from_snapshots(snapshots.undo, snapshots.redo);
+            }
+        }
+        if self.tabs.len() > first {
+            self.active_tab = (first + export.active_tab).min(self.tabs.len() - 1);
+        }
+        for note in notes {
+            self.tabs[self.active_tab].report_error("Import session", note);
+        }
+    }
+
+    // Asks for the file to export the session to or import one from
+    fn session_prompt_window(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = &mut self.session_prompt else {
+            return;
+        };
+        let title = if prompt.import { tr("Import session") } else { tr("Export session") };
+        let mut run = None;
+        egui::Window::new(title)
+            .id(egui::Id::new("session_prompt"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(tr("Path:"));
+                    let response = ui.add(egui::TextEdit::singleline(&mut prompt.path).desired_width(360.0));
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        run = Some(true);
+                    }
+                });
+                if !prompt.import {
+                    ui.weak(tr("Written as MessagePack, or as JSON when the name ends in .json"));
+                }
+                ui.horizontal(|ui| {
+                    let label = if prompt.import { tr("Import") } else { tr("Export") };
+                    if ui.add_enabled(!prompt.path.trim().is_empty(), egui::Button::new(label)).clicked() {
+                        run = Some(true);
+                    }
+                    if ui.button(tr("Cancel")).clicked() {
+                        run = Some(false);
+                    }
+                });
+            });
+        match run {
+            Some(true) => self.run_session_prompt(ctx),
+            Some(false) => self.session_prompt = None,
+            None => {}
+        }
+    }
+
+    // A path that can't be read or written leaves the prompt open to correct it
+    fn run_session_prompt(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = &self.session_prompt else {
+            return;
+        };
+        let import = prompt.import;
+        let path = PathBuf::from(prompt.path.trim());
+        let result = if import {
+            read_file(&path)
+                .map_err(String::from)
+                .and_then(|bytes| SessionExport::read(&bytes))
+                .map(|(export, notes)| self.import_session(ctx, export, notes))
+        } else {
+            let json = path.extension().is_some_and(|extension| extension == "json");
+            self.export_session().write(json).and_then(|bytes| Ok(write_file(&path, &bytes)?))
+        };
+        match result {
+            Ok(()) => self.session_prompt = None,
+            Err(e) => self.tabs[self.active_tab].report_error(if import { "Import session" } else { "Export session" }, e),
+        }
+    }
+
+    fn drop_file(&mut self, file: egui::DroppedFile, ctx: &egui::Context) {
+        // Web builds hand over the content instead of a path
+        if let Some(bytes) = file.bytes {
+            self.load_file(file_input(&file.name, bytes.to_vec(), FileTarget::Sniff), None, ctx);
+            return;
+        }
+        let Some(path) = file.path else {
+            return;
+        };
+        match file_size(&path) {
+            Ok(size) if size > ASK_ABOVE_BYTES => {
+                self.file_prompt = Some(FilePrompt {
+                    target: FileTarget::Sniff,
+                    path: path.display().to_string(),
+                    large: Some(size),
+                    watch: false,
+                });
+            }
+            Ok(_) => self.load_file(open_file(&path, FileTarget::Sniff), Some(&path), ctx),
+            Err(e) => self.tabs[self.active_tab].report_error("Open file", e.into()),
+        }
+    }
+
+    // Files read from a path are remembered for the Recent menu of the pane they went into
+    fn load_file(&mut self, input: Result<FileInput, String>, path: Option<&Path>, ctx: &egui::Context) {
+        let tab = &mut self.tabs[self.active_tab];
+        match input {
+            Ok(FileInput::ExtTypes(ext_types)) => {
+                self.settings.ext_types = ext_types;
+                self.show_settings = true;
+            }
+            Ok(input) => {
+                match (&input, path) {
+                    (FileInput::Json(_), Some(path)) => self.settings.recent_json_files.add(path),
+                    (FileInput::Binary(_), Some(path)) => self.settings.recent_messagepack_files.add(path),
+                    // Hex or base64 text, which the Recent menu would open as raw bytes
+                    _ => {}
+                }
+                match &input {
+                    FileInput::Json(_) => tab.opened_json = path.map(Path::to_path_buf),
+                    _ => tab.opened_messagepack = path.map(Path::to_path_buf),
+                }
+                self.narrow_section = tab.load_input(input, ctx, &self.settings);
+            }
+            Err(e) => tab.report_error("Open file", e),
+        }
+    }
+
+    // Reads the prompted file, unless it turns out to be large and hasn't been confirmed yet. A
+    // path that can't be read leaves the prompt open to correct it.
+    fn open_prompted_file(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = &mut self.file_prompt else {
+            return;
+        };
+        let path = PathBuf::from(prompt.path.trim());
+        match file_size(&path) {
+            Ok(size) if size > ASK_ABOVE_BYTES && prompt.large.is_none() => prompt.large = Some(size),
+            Ok(_) if prompt.watch => {
+                self.file_prompt = None;
+                self.settings.recent_messagepack_files.add(&path);
+                let tab = &mut self.tabs[self.active_tab];
+                self.narrow_section = tab.watch_file(path, ctx, &self.settings);
+            }
+            Ok(_) => {
+                let target = prompt.target;
+                self.file_prompt = None;
+                self.load_file(open_file(&path, target), Some(&path), ctx);
+            }
+            Err(e) => self.tabs[self.active_tab].report_error("Open file", e.into()),
+        }
+    }
+
+    // Asks for the path of a file to open, and before reading one over the size limit
+    fn file_prompt_window(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = &mut self.file_prompt else {
+            return;
+        };
+        let title = match (prompt.large, prompt.target) {
+            (Some(_), _) => tr("Load large file?"),
+            (None, FileTarget::MessagePack(_)) => tr("Open MessagePack file"),
+            (None, FileTarget::Schema) => tr("Open JSON Schema file"),
+            (None, FileTarget::ExtTypes) => tr("Open ext types file"),
+            (None, _) => tr("Open JSON file"),
+        };
+        let mut open = None;
+        egui::Window::new(title)
+            .id(egui::Id::new("file_prompt"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                if let Some(size) = prompt.large {
+                    let name = file_name(Path::new(prompt.path.trim()));
+                    ui.label(trf("{} is {}, loading it may take a while and use a lot of memory.", &[&name, &format_megabytes(size as usize)]));
+                } else {
+                    ui.horizontal(|ui| {
+                        ui.label(tr("Path:"));
+                        let response = ui.add(egui::TextEdit::singleline(&mut prompt.path)
+                            .desired_width(360.0)
+                            .hint_text(tr("Path of the file to open")));
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            open = Some(true);
+                        }
+                    });
+                    if let FileTarget::MessagePack(encoding) = &mut prompt.target {
+                        ui.horizontal(|ui| {
+                            ui.label(tr("Show the bytes as:"));
+                            for option in Encoding::ALL {
+                                ui.radio_value(encoding, option, option.name());
+                            }
+                        });
+                        ui.checkbox(&mut prompt.watch, tr("Watch the file"))
+                            .on_hover_text(tr("Convert it again whenever it changes on disk"));
+                    }
+                }
+                ui.horizontal(|ui| {
+                    let label = if prompt.large.is_some() { tr("Load anyway") } else { tr("Open") };
+                    if ui.add_enabled(!prompt.path.trim().is_empty(), egui::Button::new(label)).clicked() {
+                        open = Some(true);
+                    }
+                    if ui.button(tr("Cancel")).clicked() {
+                        open = Some(false);
+                    }
+                });
+            });
+        match open {
+            Some(true) => self.open_prompted_file(ctx),
+            Some(false) => self.file_prompt = None,
+            None => {}
+        }
+    }
+
+    // Asks where to save an output pane, the MessagePack one either as raw bytes or as its text
+    fn save_prompt_window(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = &mut self.save_prompt else {
+            return;
+        };
+        let title = match prompt.target {
+            SaveTarget::Json => tr("Save JSON output"),
+            _ => tr("Save MessagePack output"),
+        };
+        let mut save = None;
+        egui::Window::new(title)
+            .id(egui::Id::new("save_prompt"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(tr("Path:"));
+                    let response = ui.add(egui::TextEdit::singleline(&mut prompt.path).desired_width(360.0));
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        save = Some(true);
+                    }
+                });
+                if prompt.target != SaveTarget::Json {
+                    ui.horizontal(|ui| {
+                        ui.label(tr("Save as:"));
+                        let previous = prompt.target;
+                        ui.radio_value(&mut prompt.target, SaveTarget::MessagePackBytes, tr("Raw bytes"));
+                        ui.radio_value(&mut prompt.target, SaveTarget::MessagePackText, tr("Text as shown"));
+                        if prompt.target != previous {
+                            // Keeps the extension in line with what gets written
+                            let path = Path::new(prompt.path.trim()).with_extension(prompt.target.extension());
+                            prompt.path = path.display().to_string();
+                        }
+                    });
+                }
+                // Typed in rather than picked from a native dialog, which would take a toolkit
+                // for each platform
+                if Path::new(prompt.path.trim()).is_file() {
+                    ui.colored_label(ui.visuals().warn_fg_color, tr("A file of that name exists and will be overwritten"));
+                }
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!prompt.path.trim().is_empty(), egui::Button::new(tr("Save"))).clicked() {
+                        save = Some(true);
+                    }
+                    if ui.button(tr("Cancel")).clicked() {
+                        save = Some(false);
+                    }
+                });
+            });
+        match save {
+            Some(true) => self.save_prompted_file(),
+            Some(false) => self.save_prompt = None,
+            None => {}
+        }
+    }
+
+    // A path that can't be written leaves the prompt open to correct it
+    fn save_prompted_file(&mut self) {
+        let Some(prompt) = &self.save_prompt else {
+            return;
+        };
+        let tab = &mut self.tabs[self.active_tab];
+        let path = PathBuf::from(prompt.path.trim());
+        match tab.save_contents(prompt.target).and_then(|bytes| Ok(write_file(&path, &bytes)?)) {
+            Ok(()) => self.save_prompt = None,
+            Err(e) => tab.report_error("Save file", e),
+        }
+    }
+
+    fn tab_bar(&mut self, ui: &mut egui::Ui) {
+        let mut close = None;
+        ui.horizontal_wrapped(|ui| {
+            for (index, tab) in self.tabs.iter_mut().enumerate() {
+                if self.renaming_tab == Some(index) {
+                    let response = ui.add(egui::TextEdit::singleline(&mut tab.title).desired_width(120.0));
+                    if !response.has_focus() && !response.lost_focus() {
+                        response.request_focus();
+                    }
+                    if response.lost_focus() {
+                        if tab.title.trim().is_empty() {
+                            tab.title = trf("Tab {}", &[&tab.id]);
+                        }
+                        self.renaming_tab = None;
+                    }
+                } else {
+                    let response = ui.selectable_label(self.active_tab == index, &tab.title)
+                        .on_hover_text(tr("Double-click to rename, middle-click to close"));
+                    if response.clicked() {
+                        self.active_tab = index;
+                    }
+                    if response.double_clicked() {
+                        self.renaming_tab = Some(index);
+                    }
+                    if response.middle_clicked() {
+                        close = Some(index);
+                    }
+                }
+                if ui.small_button("×").on_hover_text(tr("Close tab")).clicked() {
+                    close = Some(index);
+                }
+                ui.separator();
+            }
+            if ui.button("+").on_hover_text(tr("New tab")).clicked() {
+                self.open_tab();
+            }
+        });
+        if let Some(index) = close {
+            self.close_tab(index);
+        }
+    }
+
+    // Everything below the toolbar for the active tab
+    fn tab_panels(&mut self, ctx: &egui::Context) {
+        let settings = &mut self.settings;
+        let tab = &mut self.tabs[self.active_tab];
+
+        let error_log = &mut self.error_log;
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| status_bar(ui, tab, error_log));
+        if error_log.open {
+            egui::TopBottomPanel::bottom("log").resizable(true).default_height(160.0).show(ctx, |ui| {
+                if let Some(text) = error_log.show_panel(ui) {
+                    copy_to_clipboard(&text);
+                }
+            });
+        }
+
+        if tab.mode == TabMode::Diff {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.push_id(tab.id, |ui| tab.diff_section(ui, settings));
+            });
+            return;
+        }
+        if tab.mode == TabMode::Compare {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.push_id(tab.id, |ui| tab.compare_section(ui, settings));
+            });
+            return;
+        }
+        if tab.mode == TabMode::Smart {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.push_id(tab.id, |ui| tab.smart_section(ui, settings));
+            });
+            return;
+        }
+
+        if settings.single_pane {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.push_id(tab.id, |ui| tab.single_pane_section(ui, settings, &mut self.narrow_section));
+            });
+            return;
+        }
+
+        let available_width = ctx.available_rect().width();
+        if available_width < STACKED_LAYOUT_BREAKPOINT {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.narrow_section, Section::JsonToMessagePack, tr("JSON to MessagePack"));
+                    ui.selectable_value(&mut self.narrow_section, Section::MessagePackToJson, tr("MessagePack to JSON"));
+                });
+                ui.separator();
+                ui.push_id(tab.id, |ui| match self.narrow_section {
+                    Section::JsonToMessagePack => tab.json_to_messagepack_section(ui, settings),
+                    Section::MessagePackToJson => tab.messagepack_to_json_section(ui, settings),
+                });
+            });
+            return;
+        }
+
+        // The side panel border doubles as a draggable splitter between the two sections
+        let half_width = available_width / 2.0;
+        egui::SidePanel::left("json_to_messagepack")
+            .resizable(true)
+            .default_width(half_width)
+            .width_range(MIN_SECTION_WIDTH..=(2.0 * half_width - MIN_SECTION_WIDTH).max(MIN_SECTION_WIDTH))
+            .show(ctx, |ui| {
+                ui.push_id(tab.id, |ui| tab.json_to_messagepack_section(ui, settings));
+            });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.push_id(tab.id, |ui| tab.messagepack_to_json_section(ui, settings));
+        });
+    }
+
+    // Moves the errors the tabs raised into the log and shows the recent ones as toasts
+    fn collect_errors(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        for tab in &mut self.tabs {
+            for event in tab.error_events.drain(..) {
+                self.error_log.push(&tab.title, event, now);
+            }
+        }
+        if let Some(remaining) = self.error_log.expire_toasts(now) {
+            ctx.request_repaint_after(remaining);
+        }
+        self.error_log.show_toasts(ctx);
+    }
+}
+
+impl Tab {
+    fn json_to_messagepack_section(&mut self, ui: &mut egui::Ui, settings: &mut Settings) {
+        ui.heading(tr("JSON to MessagePack"));
+        let editor_height = editor_height(ui, self.output_extra_rows(OutputPane::MessagePack, settings));
+        let input_options = EditorOptions {
+            height: editor_height,
+            font: settings.editor_font(),
+            wrap: true,
+            line_numbers: true,
+            highlights: None,
+        };
+
+        self.history_shortcuts(ui, Pane::JsonInput);
+        let history = &self.histories[Pane::JsonInput as usize];
+        let pane = labeled_editor(ui, "json_input", tr("JSON Input:"), &mut self.json_input, &input_options, |ui| {
+            let mut action = None;
+            if ui.button(tr("Format")).clicked() {
+                action = Some(JsonInputAction::Format);
+            }
+            if ui.button(tr("Minify")).clicked() {
+                action = Some(JsonInputAction::Minify);
+            }
+            if open_button(ui, FileTarget::Json) {
+                action = Some(JsonInputAction::Open);
+            }
+            if let Some(path) = recent_menu(ui, &mut settings.recent_json_files) {
+                action = Some(JsonInputAction::OpenRecent(path));
+            }
+            history_buttons(ui, history).map(JsonInputAction::History).or(action)
+        });
+        self.track_focus(Pane::JsonInput, &pane.response);
+        if pane.response.changed() {
+            self.json_validation = None;
+            self.schema_check = None;
+        }
+        let json_format = settings.json_format();
+        match pane.header.controls {
+            Some(JsonInputAction::Format) => self.reformat_json_input("Format", &json_format, |value| json_format.pretty(value)),
+            Some(JsonInputAction::Minify) => self.reformat_json_input("Minify", &json_format, |value| json_format.minified(value)),
+            Some(JsonInputAction::Open) => self.open_request = Some(open_target(Pane::JsonInput)),
+            Some(JsonInputAction::OpenRecent(path)) => self.open_recent = Some((open_target(Pane::JsonInput), path)),
+            Some(JsonInputAction::History(step)) => self.step_history(Pane::JsonInput, step),
+            None => {}
+        }
+        if pane.header.cleared {
+            self.clear_pane(Pane::JsonInput);
+        }
+        ui.weak(self.json_input_counter.text(&self.json_input));
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Validate")).on_hover_text(tr("Check the JSON without converting it")).clicked() {
+                self.json_validation = Some(validate_json(&self.json_input));
+            }
+            let has_schema = !self.schema_text.trim().is_empty();
+            if ui.add_enabled(has_schema, egui::Button::new(tr("Validate against schema")))
+                .on_hover_text(tr("Check the input against the JSON Schema"))
+                .on_disabled_hover_text(tr("Paste or open a JSON Schema in the Schema window first"))
+                .clicked()
+            {
+                self.check_against_schema(settings);
+            }
+            ui.toggle_value(&mut self.show_schema, tr("Schema"));
+            show_validation(ui, &self.json_validation);
+            show_schema_summary(ui, &self.schema_check);
+        });
+        self.schema_window(ui.ctx(), settings);
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Convert to MessagePack")).clicked() {
+                self.start_encoding(ui.ctx(), settings);
+            }
+            if ui.button(tr("Verify round trip")).on_hover_text(tr("Decode the output again and compare it with the input")).clicked() {
+                let result = self.parsed_json_input(settings).map_err(String::from).and_then(|input| {
+                    let bytes = decode_encoded(&self.messagepack_output)?;
+                    let bytes = decompress(&bytes, settings.max_decompressed())?.map_or(bytes, |(_, inflated)| inflated);
+                    verify_encoding(&input, &bytes, settings.binary_format.unwrap_or_default())
+                });
+                self.set_round_trip(Section::JsonToMessagePack, result);
+            }
+            ui.menu_button(tr("Copy as code"), |ui| {
+                match self.parsed_json_input(settings).and_then(|input| settings.json_format().minified(&input)) {
+                    Ok(json) => snippet_buttons(ui, json.len(), JSON_FILE, |language, source| encode_snippet(language, &json, source)),
+                    Err(e) => {
+                        ui.colored_label(egui::Color32::RED, e.to_string());
+                    }
+                }
+            })
+            .response
+            .on_hover_text(tr("A snippet that encodes the input the same way, to hand on with it"));
+            show_progress(ui, &mut self.encode_worker);
+            self.round_trip_badge(ui, Section::JsonToMessagePack);
+        });
+
+        let history = &self.histories[Pane::MessagePackOutput as usize];
+        let has_output = !self.messagepack_output.is_empty();
+        let header = pane_header(ui, tr("MessagePack Output:"), |ui| {
+            let reencode = output_encoding_menu(ui, &mut settings.messagepack_output_encoding);
+            ui.toggle_value(&mut self.qr.open, tr("QR")).on_hover_text(tr("Show the output as QR codes"));
+            if !self.qr.open {
+                ui.checkbox(&mut settings.wrap_messagepack_output, tr("Wrap"));
+                viewer_toggle(ui, &mut self.messagepack_viewer);
+            }
+            (reencode, save_button(ui, has_output), history_buttons(ui, history))
+        });
+        let (reencode, save, step) = header.controls;
+        if reencode {
+            self.reencode_output(settings);
+        }
+        if save {
+            self.save_request = Some(SaveTarget::MessagePackBytes);
+        }
+        if let Some(step) = step {
+            self.step_history(Pane::MessagePackOutput, step);
+        }
+        if header.cleared {
+            self.clear_pane(Pane::MessagePackOutput);
+        }
+        if self.qr.open {
+            ui.push_id("messagepack_qr", |ui| {
+                egui::ScrollArea::vertical().max_height(editor_height).auto_shrink([false, true]).show(ui, |ui| {
+                    show_qr(ui, &mut self.qr, &self.messagepack_output, |text| Ok(decode_encoded(text)?), editor_height - 3.0 * ui.spacing().interact_size.y);
+                });
+            });
+        } else {
+            self.messagepack_output_text(ui, input_options, settings);
+        }
+        ui.weak(self.messagepack_output_counter.encoded(&self.messagepack_output, decoded_len));
+
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(has_output, |ui| {
+                ui.menu_button(tr("Copy as…"), |ui| match self.messagepack_output_bytes() {
+                    Ok(bytes) => {
+                        for copy_as in CopyAs::ALL {
+                            if ui.button(copy_as.name()).clicked() {
+                                copy_to_clipboard(&copy_as.write(&bytes));
+                                ui.close_menu();
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        ui.colored_label(egui::Color32::RED, e.to_string());
+                    }
+                })
+                .response
+                .on_hover_text(tr("Copy the output bytes written out for source code, a terminal or a chat"));
+            });
+            if let Some(stats) = &self.encode_stats {
+                ui.weak(stats.summary());
+            }
+            if let Some((changes, _)) = self.byte_changes.as_ref().filter(|_| settings.highlight_changes) {
+                match changes.first {
+                    None => ui.weak(tr("Same bytes as the last conversion")),
+                    Some(first) => ui.weak(trf("{} bytes differ from the last conversion, the first at {}", &[&changes.differing, &format_args!("{:#06x}", first)])),
+                };
+                if changes.first.is_some() && ui.small_button(tr("Compare")).on_hover_text(tr("Open both payloads in the Diff view")).clicked() {
+                    self.compare_with_previous();
+                }
+            }
+        });
+        if let Some(checksums) = &self.encode_checksums {
+            show_checksums(ui, checksums);
+        }
+        show_warnings(ui, "encode_warnings", &self.encode_warnings);
+    }
+
+    fn messagepack_output_text(&mut self, ui: &mut egui::Ui, input_options: EditorOptions, settings: &Settings) {
+        self.history_shortcuts(ui, Pane::MessagePackOutput);
+        let searching = self.find_bar(ui, OutputPane::MessagePack);
+        if searching {
+            self.find.update(&self.messagepack_output);
+        }
+        let output_options = EditorOptions {
+            // A hexdump is laid out in lines of its own
+            wrap: settings.wrap_messagepack_output && settings.messagepack_output_encoding != OutputEncoding::Hexdump,
+            line_numbers: false,
+            highlights: searching.then(|| Highlights {
+                ranges: self.find.matches(),
+                active: self.find.active_index(),
+                scroll_to_active: self.find.scroll_pending,
+                subtle: false,
+            }),
+            ..input_options
+        };
+        let mut save = false;
+        let response = output_editor(
+            ui,
+            "messagepack_output",
+            &mut self.messagepack_output,
+            &output_options,
+            settings.output_display_limit(),
+            &mut self.messagepack_viewer,
+            &mut save,
+        );
+        if save {
+            self.save_request = Some(SaveTarget::MessagePackBytes);
+        }
+        if searching {
+            self.find.scroll_pending = false;
+        }
+        if response.has_focus() {
+            self.find_pane = OutputPane::MessagePack;
+        }
+        self.track_focus(Pane::MessagePackOutput, &response);
+    }
+
+    fn messagepack_to_json_section(&mut self, ui: &mut egui::Ui, settings: &mut Settings) {
+        ui.heading(tr("MessagePack to JSON"));
+        let editor_height = editor_height(ui, self.output_extra_rows(OutputPane::Json, settings));
+        let input_options = EditorOptions {
+            height: editor_height,
+            font: settings.editor_font(),
+            wrap: true,
+            line_numbers: false,
+            highlights: None,
+        };
+
+        let history = &self.histories[Pane::MessagePackInput as usize];
+        let header = pane_header(ui, tr("MessagePack Input:"), |ui| {
+            let reread = input_encoding_menu(ui, &mut self.messagepack_input_encoding, self.messagepack_read_as);
+            ui.selectable_value(&mut self.messagepack_input_view, MessagePackInputView::Edit, tr("Edit"));
+            let explain = ui.selectable_value(&mut self.messagepack_input_view, MessagePackInputView::Explain, tr("Explain")).clicked();
+            let open = open_button(ui, open_target(Pane::MessagePackInput));
+            let paste = ui.small_button(tr("Paste binary")).on_hover_text(tr("Decode raw MessagePack bytes from the clipboard")).clicked();
+            let recent = recent_menu(ui, &mut settings.recent_messagepack_files);
+            (reread, explain, open, paste, recent, history_buttons(ui, history))
+        });
+        let (reread, explain, open, paste, recent, step) = header.controls;
+        if reread {
+            self.reread_input(ui.ctx(), settings);
+        }
+        if open {
+            self.open_request = Some(open_target(Pane::MessagePackInput));
+        }
+        if paste {
+            self.paste_binary(ui.ctx(), settings);
+        }
+        if let Some(path) = recent {
+            self.open_recent = Some((open_target(Pane::MessagePackInput), path));
+        }
+        if let Some(step) = step {
+            self.step_history(Pane::MessagePackInput, step);
+        }
+        if explain {
+            self.refresh_explanation();
+        }
+        if header.cleared {
+            self.clear_pane(Pane::MessagePackInput);
+        }
+        match (&self.messagepack_input_view, &self.explanation) {
+            (MessagePackInputView::Explain, Some((bytes, explanation))) => {
+                let selected = self.tree_state.selected.as_ref().and_then(|path| self.spans.get(path));
+                let scroll = std::mem::take(&mut self.explanation_scroll_pending);
+                let clicked = ui.push_id("messagepack_explanation", |ui| {
+                    show_explanation(ui, bytes, explanation, editor_height, selected, scroll)
+                }).inner;
+                if let Some(path) = clicked.and_then(|offset| path_at_offset(&self.spans, offset)) {
+                    self.tree_state.selected = Some(path.to_string());
+                    self.tree_state.reveal = true;
+                }
+            }
+            _ if self.messagepack_file.is_some() => self.binary_file_pane(ui),
+            _ => {
+                self.history_shortcuts(ui, Pane::MessagePackInput);
+                let response = text_editor(ui, "messagepack_input", &mut self.messagepack_input, &input_options);
+                if response.changed() {
+                    self.messagepack_validation = None;
+                }
+                self.track_focus(Pane::MessagePackInput, &response);
+            }
+        }
+        if self.messagepack_file.is_none() {
+            ui.weak(self.messagepack_input_counter.encoded(&self.messagepack_input, decoded_len));
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Validate")).on_hover_text(tr("Check the MessagePack without converting it")).clicked() {
+                self.messagepack_validation = Some(self.inflated_input(settings).map_err(String::from).and_then(|bytes| validate_messagepack(&bytes)));
+            }
+            ui.toggle_value(&mut self.show_field_template, tr("Field names"))
+                .on_hover_text(tr("Name the fields of structs that were encoded as arrays"));
+            show_validation(ui, &self.messagepack_validation);
+        });
+        self.field_template_window(ui.ctx());
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Re-encode")).on_hover_text(tr("Write the input bytes out again in another encoding, without decoding them")).clicked() {
+                self.reencode_input();
+            }
+            for option in Encoding::ALL {
+                ui.radio_value(&mut self.reencode_target, option, option.name());
+            }
+            if let Some((len, encoding)) = self.reencoded {
+                ui.weak(trf("{} bytes as {}", &[&len, &encoding.name()]));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Convert to JSON")).clicked() {
+                self.start_decoding(ui.ctx(), settings);
+            }
+            if ui.button(tr("Verify round trip")).on_hover_text(tr("Encode the output again and compare it with the input bytes")).clicked() {
+                let result = self.inflated_input(settings)
+                    .map_err(String::from)
+                    .and_then(|bytes| {
+                        let format = settings.binary_format.unwrap_or_else(|| BinaryFormat::detect(&bytes));
+                        verify_decoding(&bytes, &self.json_output, format, settings.text_format)
+                    });
+                self.set_round_trip(Section::MessagePackToJson, result);
+            }
+            ui.add_enabled_ui(settings.binary_format != Some(BinaryFormat::Cbor), |ui| {
+                ui.menu_button(tr("Copy as code"), |ui| match self.messagepack_input_bytes() {
+                    Ok(bytes) => {
+                        let file = self.messagepack_file.as_ref().map_or(MESSAGEPACK_FILE, |file| file.name.as_str());
+                        snippet_buttons(ui, bytes.len(), file, |language, source| decode_snippet(language, &bytes, source));
+                    }
+                    Err(e) => {
+                        ui.colored_label(egui::Color32::RED, e.to_string());
+                    }
+                })
+                .response
+                .on_hover_text(tr("A snippet that decodes the input the same way, to hand on with it"))
+                .on_disabled_hover_text(tr("The snippets decode MessagePack"));
+            });
+            show_progress(ui, &mut self.decode_worker);
+            self.round_trip_badge(ui, Section::MessagePackToJson);
+        });
+
+        let history = &self.histories[Pane::JsonOutput as usize];
+        let has_output = !self.json_output.is_empty();
+        let header = pane_header(ui, tr("JSON Output:"), |ui| {
+            ui.selectable_value(&mut self.json_output_view, JsonOutputView::Text, tr("Text"));
+            ui.selectable_value(&mut self.json_output_view, JsonOutputView::Tree, tr("Tree"));
+            if self.json_output_view == JsonOutputView::Text {
+                ui.checkbox(&mut settings.wrap_json_output, tr("Wrap"));
+                viewer_toggle(ui, &mut self.json_viewer);
+            }
+            if let Some((path, range)) = self.tree_state.selected.as_ref().and_then(|path| Some((path, self.spans.get(path)?))) {
+                let path = if path.is_empty() { tr("(root)") } else { path.as_str() };
+                let range_text = format!("{:#06x}..{:#06x}", range.start, range.end);
+                ui.weak(trf("{}: bytes {}, {} encoded", &[&path, &range_text, &format_size(range.len())]));
+            }
+            (save_button(ui, has_output), history_buttons(ui, history))
+        });
+        let (save, step) = header.controls;
+        if save {
+            self.save_request = Some(SaveTarget::Json);
+        }
+        if let Some(step) = step {
+            self.step_history(Pane::JsonOutput, step);
+        }
+        if header.cleared {
+            self.clear_pane(Pane::JsonOutput);
+        }
+
+        self.json_filter.refresh(self.decoded_value.as_ref(), settings);
+        if self.json_output_view == JsonOutputView::Tree {
+            self.json_output_tree(ui, editor_height);
+        } else {
+            self.json_query_bar(ui);
+            self.json_output_text(ui, input_options, settings);
+        }
+        let shown = self.json_filter.shown().unwrap_or(&self.json_output);
+        ui.weak(self.json_output_counter.text(shown));
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Copy JSON")).clicked() {
+                copy_to_clipboard(self.shown_json_output());
+            }
+            if ui.button(tr("Copy redacted")).on_hover_text(tr("Copy with the redaction rules applied, whether or not Redact is on")).clicked() {
+                if let Some(text) = self.redacted_copy(settings) {
+                    copy_to_clipboard(&text);
+                }
+            }
+            ui.toggle_value(&mut self.json_filter.redact, tr("Redact")).on_hover_text(tr("Scrub matching values from the output view"));
+            if ui.small_button(tr("Rules…")).clicked() {
+                self.show_redaction_rules = true;
+            }
+            if let Some((.., count)) = &self.json_filter.redacted {
+                ui.weak(trf(if *count == 1 { "{} value redacted" } else { "{} values redacted" }, &[count]));
+            }
+            if self.type_stats.is_some() {
+                ui.toggle_value(&mut self.show_type_stats, tr("Stats"));
+            }
+            if let Some(value) = &self.decoded_value {
+                if ui.button(tr("Generate Rust types")).on_hover_text(tr("serde structs that fit the decoded value")).clicked() {
+                    self.generated_types = Some((tr("Rust types").to_string(), rust_types(value, "Root")));
+                }
+                if ui.button(tr("Generate TypeScript")).on_hover_text(tr("Interfaces that fit the decoded value")).clicked() {
+                    self.generated_types = Some((tr("TypeScript types").to_string(), typescript(value, "Root")));
+                }
+                if ui.button(tr("JSON Schema")).on_hover_text(tr("The types, required properties and likely enums of the decoded value")).clicked() {
+                    let schema = settings.json_format().pretty(&json_schema(std::slice::from_ref(value))).unwrap_or_default();
+                    self.generated_types = Some((tr("JSON Schema").to_string(), schema));
+                }
+            }
+            if let Some(stats) = &self.decode_stats {
+                ui.weak(stats.summary());
+            }
+        });
+        if let Some(checksums) = &self.decode_checksums {
+            show_checksums(ui, checksums);
+        }
+        show_warnings(ui, "decode_warnings", &self.decode_warnings);
+
+        egui::Window::new(tr("Redaction rules"))
+            .id(egui::Id::new("redaction_rules"))
+            .open(&mut self.show_redaction_rules)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                ui.label(tr("One rule per line: a JSON Pointer such as /user/email, where * matches any key or index, or a key name pattern such as password or *token*, matched at any depth and ignoring case."));
+                ui.add(egui::TextEdit::multiline(&mut settings.redaction_rules).code_editor().desired_rows(6));
+                ui.checkbox(&mut settings.redact_keep_shape, tr("Keep shape"))
+                    .on_hover_text(tr("Replace strings with asterisks of the same length, numbers with 0 and booleans with false"));
+            });
+
+        if let Some(stats) = &self.type_stats {
+            egui::Window::new(tr("Payload stats"))
+                .id(egui::Id::new("payload_stats"))
+                .open(&mut self.show_type_stats)
+                .resizable(false)
+                .show(ui.ctx(), |ui| show_type_stats(ui, stats));
+        }
+
+        if let Some((title, types)) = &self.generated_types {
+            let mut open = true;
+            egui::Window::new(title.as_str())
+                .id(egui::Id::new("generated_types"))
+                .open(&mut open)
+                .default_width(480.0)
+                .show(ui.ctx(), |ui| {
+                    if ui.button(tr("Copy")).clicked() {
+                        copy_to_clipboard(types);
+                    }
+                    egui::ScrollArea::vertical().max_height(480.0).show(ui, |ui| {
+                        ui.add(egui::TextEdit::multiline(&mut types.as_str()).code_editor().desired_width(f32::INFINITY));
+                    });
+                });
+            if !open {
+                self.generated_types = None;
+            }
+        }
+    }
+
+    fn json_output_tree(&mut self, ui: &mut egui::Ui, height: f32) {
+        ui.push_id("json_output_tree", |ui| {
+            egui::ScrollArea::both()
+                .min_scrolled_height(height)
+                .max_height(height)
+                .auto_shrink([false, false])
+                .show(ui, |ui| match self.json_filter.redacted.as_ref().map(|(_, redacted, ..)| redacted).or(self.decoded_value.as_ref()) {
+                    Some(value) => {
+                        if let Some(text) = show_tree(ui, value, &mut self.tree_state) {
+                            copy_to_clipboard(&text);
+                        }
+                        if std::mem::take(&mut self.tree_state.selection_changed) {
+                            self.explanation_scroll_pending = true;
+                        }
+                    }
+                    None => {
+                        ui.weak(tr("Convert a MessagePack payload to browse it here."));
+                    }
+                });
+        });
+    }
+
+    fn json_query_bar(&mut self, ui: &mut egui::Ui) {
+        let filter = &mut self.json_filter;
+        ui.horizontal(|ui| {
+            ui.label(tr("Query:"));
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut filter.query)
+                    .hint_text(tr("/payload/readings/3/temp or $.payload.readings[*].temp"))
+                    .desired_width(ui.available_width() - 60.0),
+            );
+            if response.changed() {
+                filter.query_output = None;
+            }
+            if !filter.query.is_empty() && ui.small_button("×").on_hover_text(tr("Show the whole document")).clicked() {
+                filter.query.clear();
+                filter.query_output = None;
+            }
+        });
+        if let Some(e) = filter.query_error() {
+            ui.weak(e);
+        }
+    }
+
+    // Redacted output for sharing, narrowed by the query when there is one
+    fn redacted_copy(&self, settings: &Settings) -> Option<String> {
+        let (redacted, _) = settings.redaction().apply(self.decoded_value.as_ref()?);
+        if self.json_filter.query.trim().is_empty() {
+            settings.json_format().pretty(&redacted).ok()
+        } else {
+            query_output(&redacted, &self.json_filter.query, &settings.json_format()).ok()
+        }
+    }
+
+    // The filtered or redacted text when there is one, the whole output otherwise
+    fn shown_json_output(&self) -> &str {
+        self.json_filter.shown().unwrap_or(&self.json_output)
+    }
+
+    fn json_output_text(&mut self, ui: &mut egui::Ui, options: EditorOptions, settings: &mut Settings) {
+        self.history_shortcuts(ui, Pane::JsonOutput);
+        let searching = self.find_bar(ui, OutputPane::Json);
+        if searching {
+            self.find.update(self.json_filter.shown().unwrap_or(&self.json_output));
+        }
+        let tint_changes = settings.highlight_changes && !self.json_changes.is_empty() && self.json_filter.shown().is_none();
+        let highlights = if searching {
+            Some(Highlights {
+                ranges: self.find.matches(),
+                active: self.find.active_index(),
+                scroll_to_active: self.find.scroll_pending,
+                subtle: false,
+            })
+        } else {
+            tint_changes.then(|| Highlights { ranges: &self.json_changes, active: None, scroll_to_active: false, subtle: true })
+        };
+        let output_options = EditorOptions {
+            wrap: settings.wrap_json_output,
+            line_numbers: true,
+            highlights,
+            ..options
+        };
+        let mut save = false;
+        let response = match self.json_filter.shown() {
+            Some(mut filtered) => text_editor(ui, "json_output", &mut filtered, &output_options),
+            None => output_editor(
+                ui,
+                "json_output",
+                &mut self.json_output,
+                &output_options,
+                settings.output_display_limit(),
+                &mut self.json_viewer,
+                &mut save,
+            ),
+        };
+        if save {
+            self.save_request = Some(SaveTarget::Json);
+        }
+        if searching {
+            self.find.scroll_pending = false;
+        }
+        if response.changed() {
+            self.json_changes.clear();
+        }
+        if response.has_focus() {
+            self.find_pane = OutputPane::Json;
+        }
+        self.track_focus(Pane::JsonOutput, &response);
+    }
+}
+
+impl Tab {
+    fn start_encoding(&mut self, ctx: &egui::Context, settings: &Settings) {
+        if let Err(e) = check_input_size(self.json_input.len(), settings) {
+            self.report_error("Convert to MessagePack", e.to_string());
+            return;
+        }
+        if settings.block_on_schema_violations && !self.schema_text.trim().is_empty() && !self.check_against_schema(settings) {
+            self.report_error("Convert to MessagePack", tr("The input doesn't match the JSON Schema, see the Schema window").to_string());
+            return;
+        }
+        self.encode_round_trip = None;
+        let json_input = self.json_input.clone();
+        let options = settings.convert_options(Direction::ToMessagePack);
+        let compression = settings.output_compression();
+        let output_encoding = settings.messagepack_output_encoding;
+        let ctx = ctx.clone();
+        self.encode_worker.start(json_input.len(), move |token| {
+            let encoded = encode_job(&json_input, &options, compression, token)?;
+            let text = output_encoding.encode(&encoded.messagepack);
+            Ok((encoded, text))
+        }, move || ctx.request_repaint());
+    }
+
+    fn start_decoding(&mut self, ctx: &egui::Context, settings: &Settings) {
+        // Progress counts decoded bytes, which is about three quarters of the base64 text
+        let total = match &self.messagepack_file {
+            Some(file) => file.bytes.len(),
+            None => estimated_decoded_len(&self.messagepack_input),
+        };
+        if self.messagepack_file.is_none() {
+            if let Err(e) = check_input_size(total, settings) {
+                self.report_error("Convert to JSON", e.to_string());
+                return;
+            }
+        }
+        let template = match self.field_template.trim() {
+            "" => None,
+            text => match Template::parse(text) {
+                Ok(template) => Some(template),
+                Err(e) => {
+                    self.show_field_template = true;
+                    self.report_error("Convert to JSON", trf("Field name template: {}", &[&e]));
+                    return;
+                }
+            },
+        };
+        self.decode_round_trip = None;
+        let messagepack_input = self.messagepack_input.clone();
+        let encoding = self.messagepack_input_encoding;
+        let file = self.messagepack_file.as_ref().map(|file| file.bytes.clone());
+        let job = DecodeJob::of(settings, template, self.messagepack_input_view == MessagePackInputView::Explain);
+        let ctx = ctx.clone();
+        self.decode_worker.start(total, move |token| match &file {
+            Some(bytes) => job.run(bytes, token),
+            None => job.run_on_text(&messagepack_input, encoding, token),
+        }, move || ctx.request_repaint());
+    }
+
+    // The input text read again in a newly picked encoding, if there is any to read
+    fn reread_input(&mut self, ctx: &egui::Context, settings: &Settings) {
+        self.messagepack_read_as = None;
+        if self.messagepack_file.is_none() && !self.messagepack_input.trim().is_empty() {
+            self.start_decoding(ctx, settings);
+        }
+    }
+
+    // Clipboard bytes go in like a loaded file and are decoded right away
+    fn paste_binary(&mut self, ctx: &egui::Context, settings: &Settings) {
+        match paste_binary() {
+            Ok(bytes) => {
+                self.mode = TabMode::Convert;
+                self.clear_pane(Pane::MessagePackInput);
+                self.messagepack_file = Some(BinaryFile::new(tr("clipboard"), bytes, Encoding::default()));
+                self.start_decoding(ctx, settings);
+            }
+            Err(e) => self.report_error("Paste binary", e),
+        }
+    }
+
+    // Fills the input pane the example is meant for, returns the section it went into
+    fn load_example(&mut self, example: &Example, ctx: &egui::Context, settings: &Settings) -> Section {
+        let input = match example.input {
+            ExampleInput::Json(json) => FileInput::Json(json.to_string()),
+            ExampleInput::MessagePack(hex) => FileInput::MessagePack(hex.to_string()),
+        };
+        self.load_input(input, ctx, settings)
+    }
+
+    // Same for examples and dropped files, converting right away when the setting says so
+    fn load_input(&mut self, input: FileInput, ctx: &egui::Context, settings: &Settings) -> Section {
+        self.mode = TabMode::Convert;
+        match input {
+            FileInput::Json(json) => {
+                self.clear_pane(Pane::JsonInput);
+                self.json_input = json;
+                if settings.auto_convert_examples {
+                    self.start_encoding(ctx, settings);
+                }
+                Section::JsonToMessagePack
+            }
+            FileInput::MessagePack(text) => {
+                self.clear_pane(Pane::MessagePackInput);
+                self.messagepack_input = text;
+                if settings.auto_convert_examples {
+                    self.start_decoding(ctx, settings);
+                }
+                Section::MessagePackToJson
+            }
+            FileInput::Binary(file) => {
+                self.clear_pane(Pane::MessagePackInput);
+                self.messagepack_file = Some(file);
+                if settings.auto_convert_examples {
+                    self.start_decoding(ctx, settings);
+                }
+                Section::MessagePackToJson
+            }
+            FileInput::Schema(schema) => {
+                self.schema_text = schema;
+                self.schema_check = None;
+                self.show_schema = true;
+                Section::JsonToMessagePack
+            }
+            // The app takes these into its settings before they get here
+            FileInput::ExtTypes(_) => Section::MessagePackToJson,
+        }
+    }
+
+    // The input and output of one direction, for a bookmark. A binary file goes in as base64.
+    fn bookmarked_panes(&self, direction: Direction) -> (String, String) {
+        match direction {
+            Direction::ToMessagePack => (self.json_input.clone(), self.messagepack_output.clone()),
+            Direction::ToJson => {
+                let input = match &self.messagepack_file {
+                    Some(file) => general_purpose::STANDARD.encode(&file.bytes[..]),
+                    None => self.messagepack_input.clone(),
+                };
+                (input, self.json_output.clone())
+            }
+        }
+    }
+
+    // Into the panes of its direction as they were, without converting again
+    fn load_bookmark(&mut self, bookmark: &Bookmark) -> Section {
+        self.mode = TabMode::Convert;
+        let (input, output, section) = match bookmark.direction {
+            Direction::ToMessagePack => (Pane::JsonInput, Pane::MessagePackOutput, Section::JsonToMessagePack),
+            Direction::ToJson => {
+                self.messagepack_file = None;
+                (Pane::MessagePackInput, Pane::JsonOutput, Section::MessagePackToJson)
+            }
+        };
+        for (pane, text) in [(input, &bookmark.input), (output, &bookmark.output)] {
+            self.replace_pane(pane, text.clone());
+            self.forget_derived(pane);
+        }
+        if output == Pane::JsonOutput {
+            self.decoded_value = serde_json::from_str(&self.json_output).ok();
+        }
+        section
+    }
+
+    // Converts the file now and again whenever it changes, see reload_watched
+    fn watch_file(&mut self, path: PathBuf, ctx: &egui::Context, settings: &Settings) -> Section {
+        self.mode = TabMode::Convert;
+        self.clear_pane(Pane::MessagePackInput);
+        self.watch = Some(FileWatch::new(path));
+        self.reload_watched(ctx, settings);
+        Section::MessagePackToJson
+    }
+
+    // A file caught halfway through being written fails to decode like any other input: the
+    // error is reported and the JSON output keeps the last version that did decode
+    fn reload_watched(&mut self, ctx: &egui::Context, settings: &Settings) {
+        let Some(watch) = &mut self.watch else {
+            return;
+        };
+        match read_file(&watch.path) {
+            Ok(bytes) => {
+                watch.reloaded = Some(Instant::now());
+                let encoding = self.messagepack_file.as_ref().map_or_else(Encoding::default, |file| file.encoding);
+                self.messagepack_file = Some(BinaryFile::new(&file_name(&watch.path), bytes, encoding));
+                self.messagepack_validation = None;
+                self.messagepack_input_view = MessagePackInputView::Edit;
+                self.explanation = None;
+                self.start_decoding(ctx, settings);
+            }
+            Err(e) => self.report_error("Watch file", e.into()),
+        }
+    }
+
+    // What the session keeps of the tab, all of every pane
+    fn panes(&self) -> TabSession {
+        TabSession {
+            title: self.title.clone(),
+            json_input: self.json_input.clone(),
+            messagepack_output: self.messagepack_output.clone(),
+            messagepack_input: self.messagepack_input.clone(),
+            json_output: self.json_output.clone(),
+            diff_left: self.diff_left.clone(),
+            diff_right: self.diff_right.clone(),
+            compare_expected: self.compare_expected.clone(),
+            compare_actual: self.compare_actual.clone(),
+            skipped: Vec::new(),
+        }
+    }
+
+    fn restore_panes(&mut self, saved: &TabSession) {
+        if !saved.title.is_empty() {
+            self.title = saved.title.clone();
+        }
+        self.json_input = saved.json_input.clone();
+        self.messagepack_output = saved.messagepack_output.clone();
+        self.messagepack_input = saved.messagepack_input.clone();
+        self.json_output = saved.json_output.clone();
+        self.diff_left = saved.diff_left.clone();
+        self.diff_right = saved.diff_right.clone();
+        self.compare_expected = saved.compare_expected.clone();
+        self.compare_actual = saved.compare_actual.clone();
+    }
+
+    // The bytes the MessagePack → JSON side works on, from the loaded file or the input text
+    fn messagepack_input_bytes(&self) -> Result<Cow<'_, [u8]>, ConvertError> {
+        match &self.messagepack_file {
+            Some(file) => Ok(Cow::Borrowed(&file.bytes)),
+            None => self.messagepack_input_encoding.decode(&self.messagepack_input).map(|(_, bytes)| Cow::Owned(bytes)),
+        }
+    }
+
+    // The input bytes as they are, gzip and all, into the JSON output pane in the target encoding.
+    // Pasted text can be either encoding, with whitespace and 0x prefixes in it.
+    fn reencode_input(&mut self) {
+        let bytes = match &self.messagepack_file {
+            Some(file) => Ok(Cow::Borrowed(&file.bytes[..])),
+            None => Encoding::decode_any(&self.messagepack_input).map(|(_, bytes)| Cow::Owned(bytes)),
+        };
+        match bytes.map(|bytes| (bytes.len(), self.reencode_target.encode(&bytes))) {
+            Ok((len, text)) => {
+                self.forget_derived(Pane::JsonOutput);
+                self.replace_pane(Pane::JsonOutput, text);
+                self.reencoded = Some((len, self.reencode_target));
+            }
+            Err(e) => self.report_error("Re-encode", e.to_string()),
+        }
+    }
+
+    // The bytes behind the MessagePack output, as converted or as edited since
+    fn messagepack_output_bytes(&self) -> Result<Cow<'_, [u8]>, ConvertError> {
+        match &self.messagepack_bytes {
+            Some(bytes) => Ok(Cow::Borrowed(&bytes[..])),
+            None => decode_encoded(self.messagepack_output.trim()).map(Cow::Owned),
+        }
+    }
+
+    // The MessagePack output written again in the encoding just picked. Output that doesn't
+    // decode, e.g. half edited, stays as it is until the next conversion.
+    fn reencode_output(&mut self, settings: &Settings) {
+        let Ok(bytes) = self.messagepack_output_bytes() else {
+            return;
+        };
+        let text = settings.messagepack_output_encoding.encode(&bytes);
+        self.replace_pane(Pane::MessagePackOutput, text);
+        self.messagepack_viewer.invalidate();
+    }
+
+    // Same with a gzip or zlib wrapper taken off, as Convert to JSON sees them
+    fn inflated_input(&self, settings: &Settings) -> Result<Vec<u8>, ConvertError> {
+        let bytes = self.messagepack_input_bytes()?;
+        Ok(match decompress(&bytes, settings.max_decompressed())? {
+            Some((_, inflated)) => inflated,
+            None => bytes.into_owned(),
+        })
+    }
+
+    // The JSON input pane as a value, read as YAML when that's what the panes hold
+    fn parsed_json_input(&self, settings: &Settings) -> Result<serde_json::Value, ConvertError> {
+        match settings.text_format {
+            TextFormat::Json => settings.json_format().parse(&self.json_input),
+            TextFormat::Yaml => parse_yaml_input(&self.json_input, &settings.json_format()).map(|(input, _)| input),
+        }
+    }
+
+    // True when the input matches the schema. Otherwise the schema window opens on the why.
+    fn check_against_schema(&mut self, settings: &Settings) -> bool {
+        let result = CompiledSchema::compile(&self.schema_text).and_then(|schema| {
+            let input = self.parsed_json_input(settings).map_err(String::from)?;
+            Ok(schema.validate(&input))
+        });
+        let matches = matches!(&result, Ok(violations) if violations.is_empty());
+        self.show_schema |= !matches;
+        self.schema_check = Some(result);
+        matches
+    }
+
+    fn schema_window(&mut self, ctx: &egui::Context, settings: &mut Settings) {
+        let mut open = self.show_schema;
+        egui::Window::new(tr("JSON Schema"))
+            .id(egui::Id::new("json_schema"))
+            .open(&mut open)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if open_button(ui, FileTarget::Schema) {
+                        self.open_request = Some(FileTarget::Schema);
+                    }
+                    ui.checkbox(&mut settings.block_on_schema_violations, tr("Block conversion on violations"))
+                        .on_hover_text(tr("Convert to MessagePack refuses input that doesn't match the schema"));
+                });
+                let editor = egui::TextEdit::multiline(&mut self.schema_text)
+                    .code_editor()
+                    .desired_rows(10)
+                    .desired_width(f32::INFINITY)
+                    .hint_text(tr("Paste a JSON Schema (draft 2020-12) here"));
+                if ui.add(editor).changed() {
+                    self.schema_check = None;
+                }
+                match &self.schema_check {
+                    Some(Ok(violations)) if !violations.is_empty() => {
+                        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                            egui::Grid::new("schema_violations").striped(true).show(ui, |ui| {
+                                for violation in violations {
+                                    ui.monospace(pointer_name(&violation.instance_path));
+                                    ui.label(&violation.message);
+                                    ui.weak(&violation.keyword);
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                    _ => {}
+                }
+            });
+        self.show_schema = open;
+    }
+
+    fn field_template_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr("Field names"))
+            .id(egui::Id::new("field_template"))
+            .open(&mut self.show_field_template)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(tr("Structs that rmp_serde wrote as arrays decode to objects with these field names, in order. Arrays of another length are left as they are."));
+                let editor = egui::TextEdit::multiline(&mut self.field_template)
+                    .code_editor()
+                    .desired_rows(8)
+                    .desired_width(f32::INFINITY)
+                    .hint_text(tr("A JSON skeleton like {\"age\": 0, \"name\": \"\"}, or a list of names like [\"age\", \"name\"]"));
+                ui.add(editor);
+            });
+    }
+
+    // Stands in for the MessagePack input editor while a binary file is loaded
+    fn binary_file_pane(&mut self, ui: &mut egui::Ui) {
+        let Some(file) = &self.messagepack_file else {
+            return;
+        };
+        let mut show_text = false;
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.label(file.summary());
+            ui.weak(tr("Converted straight from the file's bytes, the pane only gets them as text when asked to"));
+            show_text = ui.button(trf("Show as {}", &[&file.encoding.name()]))
+                .on_hover_text(tr("Put the bytes into the pane as text to edit them, which is slow for large files"))
+                .clicked();
+        });
+        if show_text {
+            if let Some(file) = self.messagepack_file.take() {
+                self.messagepack_input = file.text();
+            }
+        }
+    }
+
+    // Applies the results of conversions that finished since the last frame
+    fn poll_workers(&mut self, settings: &Settings) {
+        match self.encode_worker.poll() {
+            Some(Ok((encoded, text))) => {
+                self.byte_changes = match self.messagepack_bytes.take() {
+                    Some(previous) if settings.highlight_changes => Some((ByteChanges::between(&previous, &encoded.messagepack), previous)),
+                    _ => None,
+                };
+                self.last_conversion = Some(encoded.summary);
+                self.replace_pane(Pane::MessagePackOutput, text);
+                self.messagepack_bytes = Some(encoded.messagepack);
+                self.messagepack_viewer.invalidate();
+                self.encode_stats = Some(encoded.stats);
+                self.encode_checksums = Some(encoded.checksums);
+                self.encode_warnings = encoded.warnings;
+            }
+            Some(Err(e)) => self.report_error("Convert to MessagePack", e.to_string()),
+            None => {}
+        }
+
+        match self.decode_worker.poll() {
+            Some(Ok(output)) => {
+                if output.summary.is_some() {
+                    self.last_conversion = output.summary;
+                }
+                self.explanation = output.explanation;
+                // Switched to while the conversion ran without annotating
+                if self.explanation.is_none() && self.messagepack_input_view == MessagePackInputView::Explain {
+                    self.refresh_explanation();
+                }
+                self.decode_warnings = output.warnings;
+                self.messagepack_read_as = output.read_as;
+                match output.json {
+                    Ok((json, decoded)) => {
+                        self.json_changes = match settings.highlight_changes && !self.json_output.is_empty() {
+                            true => changed_lines(&self.json_output, &json),
+                            false => Vec::new(),
+                        };
+                        self.replace_pane(Pane::JsonOutput, json);
+                        self.json_viewer.invalidate();
+                        self.json_filter.invalidate();
+                        self.decoded_value = Some(decoded.value);
+                        self.spans = decoded.spans;
+                        self.decode_stats = Some(decoded.stats);
+                        self.decode_checksums = Some(decoded.checksums);
+                        self.type_stats = decoded.type_stats;
+                        self.tree_state.reset();
+                    }
+                    Err(e) => self.report_error("Convert to JSON", e.to_string()),
+                }
+            }
+            Some(Err(e)) => self.report_error("Convert to JSON", e.to_string()),
+            None => {}
+        }
+    }
+
+    // What Save… writes for `target`
+    fn save_contents(&self, target: SaveTarget) -> Result<Vec<u8>, String> {
+        Ok(match target {
+            SaveTarget::Json => self.json_output.clone().into_bytes(),
+            SaveTarget::MessagePackText => self.messagepack_output.clone().into_bytes(),
+            SaveTarget::MessagePackBytes => match &self.messagepack_bytes {
+                Some(bytes) => bytes.clone(),
+                None => decode_encoded(self.messagepack_output.trim())?,
+            },
+        })
+    }
+
+    // The Diff view on the payload of the conversion before against the current one
+    fn compare_with_previous(&mut self) {
+        let Some((_, previous)) = &self.byte_changes else {
+            return;
+        };
+        self.diff_left = general_purpose::STANDARD.encode(previous);
+        self.diff_right = self.messagepack_output.clone();
+        self.diff = decode_diff_sides(&self.diff_left, &self.diff_right).ok().map(|(left, right)| diff(&left, &right));
+        self.byte_diff = diff_bytes(&self.diff_left, &self.diff_right).ok();
+        self.mode = TabMode::Diff;
+    }
+
+    fn report_error(&mut self, operation: &'static str, message: String) {
+        self.error_events.push(ErrorEvent { operation, message });
+    }
+
+    // Empties one pane along with everything derived from it, leaving the other panes alone. What
+    // it held can be brought back with Undo.
+    fn clear_pane(&mut self, pane: Pane) {
+        self.replace_pane(pane, String::new());
+        self.forget_derived(pane);
+    }
+
+    // Drops whatever was worked out from the pane's text, such as stats and validation results
+    fn forget_derived(&mut self, pane: Pane) {
+        match pane {
+            Pane::JsonInput => {
+                self.json_validation = None;
+                self.schema_check = None;
+                self.encode_round_trip = None;
+            }
+            Pane::MessagePackOutput => {
+                self.encode_worker.cancel();
+                self.messagepack_viewer.invalidate();
+                self.messagepack_bytes = None;
+                self.encode_stats = None;
+                self.encode_checksums = None;
+                self.encode_warnings.clear();
+                self.byte_changes = None;
+                self.encode_round_trip = None;
+            }
+            Pane::MessagePackInput => {
+                self.messagepack_file = None;
+                self.watch = None;
+                self.messagepack_validation = None;
+                self.messagepack_input_view = MessagePackInputView::Edit;
+                self.explanation = None;
+                self.decode_round_trip = None;
+            }
+            Pane::JsonOutput => {
+                self.decode_worker.cancel();
+                self.json_viewer.invalidate();
+                self.json_filter.invalidate();
+                self.json_changes.clear();
+                self.decoded_value = None;
+                self.spans.clear();
+                self.decode_stats = None;
+                self.decode_checksums = None;
+                self.decode_warnings.clear();
+                self.type_stats = None;
+                self.show_type_stats = false;
+                self.generated_types = None;
+                self.tree_state.reset();
+                self.decode_round_trip = None;
+                self.reencoded = None;
+            }
+        }
+    }
+
+    // Back to a fresh tab with the same name, keeping what each pane held for Undo
+    fn clear_all(&mut self) {
+        for pane in Pane::ALL {
+            self.replace_pane(pane, String::new());
+        }
+        *self = Tab {
+            id: self.id,
+            title: std::mem::take(&mut self.title),
+            histories: std::mem::take(&mut self.histories),
+            ..Default::default()
+        };
+    }
+
+    fn pane_history(&mut self, pane: Pane) -> (&mut PaneHistory, &mut String) {
+        let text = match pane {
+            Pane::JsonInput => &mut self.json_input,
+            Pane::MessagePackOutput => &mut self.messagepack_output,
+            Pane::MessagePackInput => &mut self.messagepack_input,
+            Pane::JsonOutput => &mut self.json_output,
+        };
+        (&mut self.histories[pane as usize], text)
+    }
+
+    // Puts `text` into the pane, keeping what it held for Undo
+    fn replace_pane(&mut self, pane: Pane, text: String) {
+        let (history, current) = self.pane_history(pane);
+        history.record(current);
+        *current = text;
+    }
+
+    fn step_history(&mut self, pane: Pane, step: Step) {
+        let (history, text) = self.pane_history(pane);
+        if !history.step(step, text) {
+            return;
+        }
+        self.forget_derived(pane);
+        if pane == Pane::JsonOutput {
+            // The tree, query and redaction work on the value, so it's read back from the text
+            self.decoded_value = serde_json::from_str(&self.json_output).ok();
+        }
+    }
+
+    // Ctrl+Z and Ctrl+Shift+Z step through the snapshots of the focused pane while its last change
+    // came from the app. Once the user types they go to the editor's own undo again.
+    fn history_shortcuts(&mut self, ui: &egui::Ui, pane: Pane) {
+        if self.focused_pane != Some(pane) || !self.histories[pane as usize].programmatic {
+            return;
+        }
+        if ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z)) {
+            self.step_history(pane, Step::Redo);
+        } else if ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z)) {
+            self.step_history(pane, Step::Undo);
+        }
+    }
+
+    fn track_focus(&mut self, pane: Pane, response: &egui::Response) {
+        if response.changed() {
+            self.histories[pane as usize].programmatic = false;
+            if pane == Pane::MessagePackOutput {
+                // Edited by hand, so saving reads the bytes back from the text
+                self.messagepack_bytes = None;
+            }
+        }
+        if response.has_focus() {
+            self.focused_pane = Some(pane);
+        } else if self.focused_pane == Some(pane) {
+            self.focused_pane = None;
+        }
+    }
+
+    fn set_round_trip(&mut self, direction: Section, result: Result<RoundTrip, String>) {
+        let round_trip = match result {
+            Ok(round_trip) => Some(round_trip),
+            Err(e) => {
+                self.report_error("Verify round trip", e);
+                None
+            }
+        };
+        match direction {
+            Section::JsonToMessagePack => self.encode_round_trip = round_trip,
+            Section::MessagePackToJson => self.decode_round_trip = round_trip,
+        }
+    }
+
+    // Green "Lossless" badge, or a red summary that opens the full report
+    fn round_trip_badge(&mut self, ui: &mut egui::Ui, direction: Section) {
+        let round_trip = match direction {
+            Section::JsonToMessagePack => &self.encode_round_trip,
+            Section::MessagePackToJson => &self.decode_round_trip,
+        };
+        let Some(round_trip) = round_trip else { return };
+        if round_trip.is_lossless() {
+            ui.label(egui::RichText::new(round_trip.summary()).color(VALID_COLOR));
+            return;
+        }
+        let summary = egui::RichText::new(round_trip.summary()).color(egui::Color32::RED);
+        let open = self.round_trip_report == Some(direction);
+        if ui.selectable_label(open, summary).on_hover_text(tr("Show the differences")).clicked() {
+            self.round_trip_report = if open { None } else { Some(direction) };
+        }
+
+        if !open {
+            return;
+        }
+        let mut keep_open = true;
+        egui::Window::new(tr("Round trip report"))
+            .id(egui::Id::new("round_trip_report"))
+            .open(&mut keep_open)
+            .default_width(420.0)
+            .show(ui.ctx(), |ui| show_round_trip(ui, round_trip));
+        if !keep_open {
+            self.round_trip_report = None;
+        }
+    }
+
+    fn diff_section(&mut self, ui: &mut egui::Ui, settings: &Settings) {
+        ui.heading(tr("Diff MessagePack payloads"));
+        let options = EditorOptions {
+            height: editor_height(ui, 0),
+            font: settings.editor_font(),
+            wrap: true,
+            line_numbers: false,
+            highlights: None,
+        };
+        ui.columns(2, |columns| {
+            if labeled_editor(&mut columns[0], "diff_left", tr("Left (Base64 or Hex):"), &mut self.diff_left, &options, |_| ()).header.cleared {
+                self.diff_left.clear();
+                self.diff = None;
+                self.byte_diff = None;
+            }
+            if labeled_editor(&mut columns[1], "diff_right", tr("Right (Base64 or Hex):"), &mut self.diff_right, &options, |_| ()).header.cleared {
+                self.diff_right.clear();
+                self.diff = None;
+                self.byte_diff = None;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.diff_view, DiffView::Values, tr("Values"))
+                .on_hover_text(tr("What the decoded payloads differ in"));
+            ui.selectable_value(&mut self.diff_view, DiffView::Bytes, tr("Bytes"))
+                .on_hover_text(tr("Which bytes differ, encoding choices such as the width of an int included"));
+            let compare = ui.button(tr("Compare")).clicked();
+            match self.diff_view {
+                DiffView::Values if compare => match decode_diff_sides(&self.diff_left, &self.diff_right) {
+                    Ok((left, right)) => self.diff = Some(diff(&left, &right)),
+                    Err(e) => {
+                        self.diff = None;
+                        self.report_error("Compare", e);
+                    }
+                },
+                DiffView::Bytes if compare => match diff_bytes(&self.diff_left, &self.diff_right) {
+                    Ok(byte_diff) => self.byte_diff = Some(byte_diff),
+                    Err(e) => {
+                        self.byte_diff = None;
+                        self.report_error("Compare", e);
+                    }
+                },
+                _ => {}
+            }
+            match (self.diff_view, &self.diff, &self.byte_diff) {
+                (DiffView::Values, Some(changes), _) if changes.is_empty() => {
+                    ui.weak(tr("The payloads decode to the same JSON"));
+                }
+                (DiffView::Values, Some(changes), _) => {
+                    ui.weak(trf("{} differences", &[&stats::group_thousands(changes.len())]));
+                }
+                (DiffView::Bytes, _, Some(byte_diff)) if byte_diff.ranges.is_empty() => {
+                    ui.weak(tr("The payloads are the same bytes"));
+                }
+                (DiffView::Bytes, _, Some(byte_diff)) => {
+                    let ranges = byte_diff.ranges.len();
+                    ui.weak(trf(if ranges == 1 { "{} range of bytes differs" } else { "{} ranges of bytes differ" }, &[&stats::group_thousands(ranges)]));
+                    if byte_diff.by_offset {
+                        ui.weak(tr("(too different to align, compared offset by offset)"));
+                    }
+                }
+                _ => {}
+            }
+        });
+
+        if self.diff_view == DiffView::Bytes {
+            if let Some(byte_diff) = &self.byte_diff {
+                show_byte_diff(ui, byte_diff);
+            }
+            return;
+        }
+        let Some(changes) = &self.diff else { return };
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::both()
+            .id_source("diff_changes")
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, changes.len(), |ui, rows| {
+                for change in &changes[rows] {
+                    let text = egui::RichText::new(change.describe()).monospace().color(change_color(change.kind));
+                    ui.add(egui::Label::new(text).wrap(false));
+                }
+            });
+    }
+
+    fn compare_section(&mut self, ui: &mut egui::Ui, settings: &Settings) {
+        ui.heading(tr("Compare JSON documents"));
+        let options = EditorOptions {
+            height: editor_height(ui, 1),
+            font: settings.editor_font(),
+            wrap: true,
+            line_numbers: false,
+            highlights: None,
+        };
+        ui.columns(2, |columns| {
+            if labeled_editor(&mut columns[0], "compare_expected", tr("Expected (JSON):"), &mut self.compare_expected, &options, |_| ()).header.cleared {
+                self.compare_expected.clear();
+                self.comparison = None;
+            }
+            if labeled_editor(&mut columns[1], "compare_actual", tr("Actual (JSON):"), &mut self.compare_actual, &options, |_| ()).header.cleared {
+                self.compare_actual.clear();
+                self.comparison = None;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Float tolerance:"));
+            let tolerance = self.compare_options.tolerance.get_or_insert(0.0);
+            ui.add(egui::DragValue::new(tolerance).speed(0.001).clamp_range(0.0..=f64::MAX))
+                .on_hover_text(tr("Numbers at most this far apart are equal"));
+            ui.checkbox(&mut self.compare_options.null_is_missing, tr("null equals a missing key"));
+            ui.checkbox(&mut self.compare_options.ignore_array_order, tr("Ignore array order"));
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Compare")).clicked() {
+                match parse_compare_sides(&self.compare_expected, &self.compare_actual, &settings.json_format()) {
+                    Ok((expected, actual)) => self.comparison = Some(diff_with(&expected, &actual, &self.compare_options)),
+                    Err(e) => {
+                        self.comparison = None;
+                        self.report_error("Compare", e);
+                    }
+                }
+            }
+            match &self.comparison {
+                Some(changes) if changes.is_empty() => {
+                    ui.colored_label(VALID_COLOR, tr("The documents are equal"));
+                }
+                Some(changes) => {
+                    ui.colored_label(ui.visuals().error_fg_color, trf("Not equal, {} differences", &[&stats::group_thousands(changes.len())]));
+                }
+                None => {}
+            }
+        });
+
+        let Some(changes) = &self.comparison else { return };
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::both()
+            .id_source("compare_changes")
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, changes.len(), |ui, rows| {
+                for change in &changes[rows] {
+                    let text = egui::RichText::new(change.describe()).monospace().color(change_color(change.kind));
+                    ui.add(egui::Label::new(text).wrap(false));
+                }
+            });
+    }
+
+    fn smart_section(&mut self, ui: &mut egui::Ui, settings: &mut Settings) {
+        ui.heading(tr("Smart input"));
+        let height = editor_height(ui, 1);
+        let options = EditorOptions {
+            height,
+            font: settings.editor_font(),
+            wrap: true,
+            line_numbers: false,
+            highlights: None,
+        };
+        let pane = labeled_editor(ui, "smart_input", tr("Paste JSON, or MessagePack as Base64 or Hex:"), &mut self.smart_input, &options, |ui| {
+            let mut changed = false;
+            egui::ComboBox::from_id_source("smart_override")
+                .selected_text(self.smart_override.map_or(tr("Auto"), InputKind::name))
+                .show_ui(ui, |ui| {
+                    changed |= ui.selectable_value(&mut self.smart_override, None, tr("Auto")).clicked();
+                    for kind in PREFERENCE {
+                        changed |= ui.selectable_value(&mut self.smart_override, Some(kind), kind.name()).clicked();
+                    }
+                });
+            changed
+        });
+        if pane.header.cleared {
+            self.smart_input.clear();
+            self.smart_candidates.clear();
+        } else if pane.response.changed() || pane.header.controls {
+            self.run_smart_input(ui.ctx(), settings);
+        }
+
+        let kind = self.smart_kind();
+        ui.horizontal(|ui| {
+            match (kind, self.smart_candidates.as_slice()) {
+                (None, _) if self.smart_input.trim().is_empty() => {}
+                (None, _) => {
+                    ui.weak(tr("Not recognized as JSON or as MessagePack in Base64 or Hex"));
+                }
+                (Some(kind), candidates) => {
+                    let others: Vec<&str> = candidates.iter().filter(|other| **other != kind).map(|other| other.name()).collect();
+                    let mut text = trf("Detected: {}", &[&kind.name()]);
+                    if self.smart_override.is_some() {
+                        text = trf("Treated as {}", &[&kind.name()]);
+                    } else if !others.is_empty() {
+                        text.push_str(&trf(" (also valid as {})", &[&others.join(", ")]));
+                    }
+                    ui.label(text);
+                }
+            }
+            match kind {
+                Some(InputKind::Json) => show_progress(ui, &mut self.encode_worker),
+                Some(_) => show_progress(ui, &mut self.decode_worker),
+                None => {}
+            }
+        });
+
+        let limit = settings.output_display_limit();
+        let mut save = false;
+        match kind {
+            Some(InputKind::Json) => {
+                let reencode = ui.horizontal(|ui| {
+                    ui.label(tr("MessagePack Output:"));
+                    output_encoding_menu(ui, &mut settings.messagepack_output_encoding)
+                });
+                if reencode.inner {
+                    self.reencode_output(settings);
+                }
+                output_editor(ui, "smart_output", &mut self.messagepack_output, &options, limit, &mut self.messagepack_viewer, &mut save);
+                if save {
+                    self.save_request = Some(SaveTarget::MessagePackBytes);
+                }
+            }
+            Some(_) => {
+                ui.label(tr("JSON Output:"));
+                let options = EditorOptions { line_numbers: true, ..options };
+                output_editor(ui, "smart_output", &mut self.json_output, &options, limit, &mut self.json_viewer, &mut save);
+                if save {
+                    self.save_request = Some(SaveTarget::Json);
+                }
+            }
+            None => {}
+        }
+    }
+
+    // One input and one output pane for the chosen direction, over the same panes as the two
+    // column layout
+    fn single_pane_section(&mut self, ui: &mut egui::Ui, settings: &mut Settings, direction: &mut Section) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(direction, Section::JsonToMessagePack, tr("JSON → MessagePack"));
+            ui.selectable_value(direction, Section::MessagePackToJson, tr("MessagePack → JSON"));
+            if ui.button(tr("⇄ Swap")).on_hover_text(tr("Move the output into the input and flip the direction")).clicked() {
+                *direction = self.swap_direction(*direction);
+            }
+        });
+        ui.separator();
+
+        let options = EditorOptions {
+            height: editor_height(ui, 0),
+            font: settings.editor_font(),
+            wrap: true,
+            line_numbers: *direction == Section::JsonToMessagePack,
+            highlights: None,
+        };
+        let limit = settings.output_display_limit();
+        match direction {
+            Section::JsonToMessagePack => {
+                self.single_pane_input(ui, Pane::JsonInput, &options, settings);
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Convert to MessagePack")).clicked() {
+                        self.start_encoding(ui.ctx(), settings);
+                    }
+                    show_progress(ui, &mut self.encode_worker);
+                });
+                let history = &self.histories[Pane::MessagePackOutput as usize];
+                let has_output = !self.messagepack_output.is_empty();
+                let header = pane_header(ui, tr("MessagePack Output:"), |ui| {
+                    let reencode = output_encoding_menu(ui, &mut settings.messagepack_output_encoding);
+                    ui.checkbox(&mut settings.wrap_messagepack_output, tr("Wrap"));
+                    viewer_toggle(ui, &mut self.messagepack_viewer);
+                    (reencode, save_button(ui, has_output), history_buttons(ui, history))
+                });
+                let (reencode, save, step) = header.controls;
+                if reencode {
+                    self.reencode_output(settings);
+                }
+                if save {
+                    self.save_request = Some(SaveTarget::MessagePackBytes);
+                }
+                if let Some(step) = step {
+                    self.step_history(Pane::MessagePackOutput, step);
+                }
+                if header.cleared {
+                    self.clear_pane(Pane::MessagePackOutput);
+                }
+                let options = EditorOptions { wrap: settings.wrap_messagepack_output && settings.messagepack_output_encoding != OutputEncoding::Hexdump, line_numbers: false, ..options };
+                self.history_shortcuts(ui, Pane::MessagePackOutput);
+                let mut save = false;
+                let response = output_editor(ui, "messagepack_output", &mut self.messagepack_output, &options, limit, &mut self.messagepack_viewer, &mut save);
+                if save {
+                    self.save_request = Some(SaveTarget::MessagePackBytes);
+                }
+                self.track_focus(Pane::MessagePackOutput, &response);
+            }
+            Section::MessagePackToJson => {
+                self.single_pane_input(ui, Pane::MessagePackInput, &options, settings);
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Convert to JSON")).clicked() {
+                        self.start_decoding(ui.ctx(), settings);
+                    }
+                    show_progress(ui, &mut self.decode_worker);
+                });
+                let history = &self.histories[Pane::JsonOutput as usize];
+                let has_output = !self.json_output.is_empty();
+                let header = pane_header(ui, tr("JSON Output:"), |ui| {
+                    ui.checkbox(&mut settings.wrap_json_output, tr("Wrap"));
+                    viewer_toggle(ui, &mut self.json_viewer);
+                    (save_button(ui, has_output), history_buttons(ui, history))
+                });
+                let (save, step) = header.controls;
+                if save {
+                    self.save_request = Some(SaveTarget::Json);
+                }
+                if let Some(step) = step {
+                    self.step_history(Pane::JsonOutput, step);
+                }
+                if header.cleared {
+                    self.clear_pane(Pane::JsonOutput);
+                }
+                let options = EditorOptions { wrap: settings.wrap_json_output, line_numbers: true, ..options };
+                self.history_shortcuts(ui, Pane::JsonOutput);
+                let mut save = false;
+                let response = output_editor(ui, "json_output", &mut self.json_output, &options, limit, &mut self.json_viewer, &mut save);
+                if save {
+                    self.save_request = Some(SaveTarget::Json);
+                }
+                self.track_focus(Pane::JsonOutput, &response);
+            }
+        }
+    }
+
+    fn single_pane_input(&mut self, ui: &mut egui::Ui, pane: Pane, options: &EditorOptions, settings: &mut Settings) {
+        let (id, title) = match pane {
+            Pane::JsonInput => ("json_input", tr("JSON Input:")),
+            _ => ("messagepack_input", tr("MessagePack Input:")),
+        };
+        self.history_shortcuts(ui, pane);
+        let history = &self.histories[pane as usize];
+        let recent = match pane {
+            Pane::JsonInput => &mut settings.recent_json_files,
+            _ => &mut settings.recent_messagepack_files,
+        };
+        let header = pane_header(ui, title, |ui| {
+            let reread = pane == Pane::MessagePackInput && input_encoding_menu(ui, &mut self.messagepack_input_encoding, self.messagepack_read_as);
+            (reread, open_button(ui, open_target(pane)), recent_menu(ui, recent), history_buttons(ui, history))
+        });
+        let (reread, open, recent, step) = header.controls;
+        if reread {
+            self.reread_input(ui.ctx(), settings);
+        }
+        if open {
+            self.open_request = Some(open_target(pane));
+        }
+        if let Some(path) = recent {
+            self.open_recent = Some((open_target(pane), path));
+        }
+        if let Some(step) = step {
+            self.step_history(pane, step);
+        }
+        if header.cleared {
+            self.clear_pane(pane);
+        }
+        if pane == Pane::MessagePackInput && self.messagepack_file.is_some() {
+            self.binary_file_pane(ui);
+        } else {
+            let (_, text) = self.pane_history(pane);
+            let response = text_editor(ui, id, text, options);
+            self.track_focus(pane, &response);
+        }
+    }
+
+    // The output of `direction` becomes the input of the other direction, which is returned
+    fn swap_direction(&mut self, direction: Section) -> Section {
+        match direction {
+            Section::JsonToMessagePack => {
+                let output = self.messagepack_output.clone();
+                self.clear_pane(Pane::MessagePackInput);
+                self.messagepack_input = output;
+                Section::MessagePackToJson
+            }
+            Section::MessagePackToJson => {
+                let output = self.json_output.clone();
+                self.clear_pane(Pane::JsonInput);
+                self.json_input = output;
+                Section::JsonToMessagePack
+            }
+        }
+    }
+
+    fn smart_kind(&self) -> Option<InputKind> {
+        self.smart_override.or_else(|| self.smart_candidates.first().copied())
+    }
+
+    // Sends the smart input to the pane of its kind and converts it from there, so the Convert
+    // mode shows the same panes afterwards
+    fn run_smart_input(&mut self, ctx: &egui::Context, settings: &Settings) {
+        self.smart_candidates = candidates(&self.smart_input);
+        match self.smart_kind() {
+            Some(InputKind::Json) => {
+                self.clear_pane(Pane::JsonInput);
+                self.json_input = self.smart_input.clone();
+                self.start_encoding(ctx, settings);
+            }
+            Some(kind) => {
+                self.clear_pane(Pane::MessagePackInput);
+                // The MessagePack pane reads anything that looks like hex as hex
+                self.messagepack_input = match messagepack_bytes(&self.smart_input, kind) {
+                    Some(bytes) if kind == InputKind::Base64 && is_hex(self.smart_input.trim()) => hex::encode(bytes),
+                    _ => self.smart_input.trim().to_string(),
+                };
+                self.start_decoding(ctx, settings);
+            }
+            None => {}
+        }
+    }
+
+    // Rewrites the JSON input in place, leaving the MessagePack panes alone
+    fn reformat_json_input(
+        &mut self,
+        operation: &'static str,
+        json_format: &JsonFormat,
+        serialize: impl Fn(&serde_json::Value) -> Result<String, ConvertError>,
+    ) {
+        let result = json_format.parse(&self.json_input).and_then(|value| serialize(&value));
+        match result {
+            Ok(formatted) => {
+                if formatted != self.json_input {
+                    self.replace_pane(Pane::JsonInput, formatted);
+                }
+            }
+            Err(e) => self.report_error(operation, e.to_string()),
+        }
+    }
+
+    // Corrupt MessagePack still gets explained up to the failing offset,
+    // only text that can't be decoded to bytes at all ends up in the error area
+    fn refresh_explanation(&mut self) {
+        match self.messagepack_input_bytes().map(Cow::into_owned) {
+            Ok(bytes) => {
+                let explanation = explain(&bytes);
+                self.explanation = Some((bytes, explanation));
+            }
+            Err(e) => {
+                self.explanation = None;
+                self.messagepack_input_view = MessagePackInputView::Edit;
+                self.report_error("Explain", e.to_string());
+            }
+        }
+    }
+
+    // Rows shown above an output pane on top of its header: the find bar and the large output banner
+    fn output_extra_rows(&self, pane: OutputPane, settings: &Settings) -> usize {
+        let (text, checksums) = match pane {
+            OutputPane::MessagePack => (&self.messagepack_output, &self.encode_checksums),
+            OutputPane::Json => (&self.json_output, &self.decode_checksums),
+        };
+        let oversized = text.len() > settings.output_display_limit();
+        let query_rows = match (pane, self.json_output_view) {
+            (OutputPane::Json, JsonOutputView::Text) => 1 + usize::from(self.json_filter.query_error().is_some()),
+            _ => 0,
+        };
+        usize::from(self.find.open && self.find_pane == pane) + usize::from(oversized) + query_rows + usize::from(checksums.is_some())
+    }
+
+    // Shows the find bar above the given output pane if that pane is the one being searched
+    fn find_bar(&mut self, ui: &mut egui::Ui, pane: OutputPane) -> bool {
+        if !self.find.open || self.find_pane != pane {
+            return false;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Find:"));
+            let response = ui.add(egui::TextEdit::singleline(&mut self.find.query).desired_width(160.0));
+            if std::mem::take(&mut self.find_focus_requested) {
+                response.request_focus();
+            }
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                if ui.input(|i| i.modifiers.shift) {
+                    self.find.previous();
+                } else {
+                    self.find.next();
+                }
+                response.request_focus();
+            }
+            if response.has_focus() && ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                self.find.open = false;
+            }
+
+            ui.toggle_value(&mut self.find.case_sensitive, "Aa")
+                .on_hover_text(tr("Match case"));
+            if ui.button(tr("Prev")).on_hover_text(tr("Previous match (Shift+Enter)")).clicked() {
+                self.find.previous();
+            }
+            if ui.button(tr("Next")).on_hover_text(tr("Next match (Enter)")).clicked() {
+                self.find.next();
+            }
+            ui.label(self.find.summary());
+            if ui.button(tr("Close")).on_hover_text("Esc").clicked() {
+                self.find.open = false;
+            }
+        });
+
+        self.find.open
+    }
+}
+
+impl eframe::App for MessagePackJsonConverterApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Ctrl+= / Ctrl+- / Ctrl+0 are handled by egui itself, we only keep track of the result
+        self.settings.zoom = ctx.zoom_factor();
+
+        for tab in &mut self.tabs {
+            tab.poll_workers(&self.settings);
+        }
+        let now = Instant::now();
+        for tab in &mut self.tabs {
+            if tab.watch.as_mut().is_some_and(|watch| watch.poll(now)) {
+                tab.reload_watched(ctx, &self.settings);
+            }
+        }
+        if self.tabs.iter().any(|tab| tab.watch.is_some()) {
+            ctx.request_repaint_after(POLL_INTERVAL);
+        }
+
+        if self.last_autosave.is_none_or(|saved| saved.elapsed() >= AUTOSAVE_INTERVAL) {
+            self.last_autosave = Some(Instant::now());
+            if let Err(e) = self.save_session() {
+                eprintln!("{}", e);
+            }
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::PageDown)) {
+            self.active_tab = (self.active_tab + 1) % self.tabs.len();
+            self.renaming_tab = None;
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::PageUp)) {
+            self.active_tab = (self.active_tab + self.tabs.len() - 1) % self.tabs.len();
+            self.renaming_tab = None;
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::F)) {
+            let tab = &mut self.tabs[self.active_tab];
+            tab.find.open = true;
+            tab.find_focus_requested = true;
+        }
+
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading(tr("JSON <-> MessagePack Converter"));
+            });
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button(tr("Clear All")).on_hover_text(tr("Clear every pane of the current tab")).clicked() {
+                    self.tabs[self.active_tab].clear_all();
+                }
+
+                egui::ComboBox::from_id_source("examples")
+                    .selected_text(tr("Examples"))
+                    .show_ui(ui, |ui| {
+                        for example in EXAMPLES {
+                            if ui.selectable_label(false, tr(example.name)).on_hover_text(example.hover_text()).clicked() {
+                                let tab = &mut self.tabs[self.active_tab];
+                                self.narrow_section = tab.load_example(example, ctx, &self.settings);
+                            }
+                        }
+                        ui.separator();
+                        ui.checkbox(&mut self.settings.auto_convert_examples, tr("Convert on load"));
+                    });
+                if ui.button(tr("Generate…")).on_hover_text(tr("Random payloads for testing a decoder, the same for the same seed")).clicked() {
+                    self.generate.open = true;
+                }
+
+                if ui.button(tr("Bookmarks…")).on_hover_text(tr("Payloads kept under a name, to load again later")).clicked() {
+                    self.bookmarks.open = true;
+                }
+                if ui.button(tr("Batch…")).on_hover_text(tr("Convert every matching file in a folder")).clicked() {
+                    self.batch.open = true;
+                }
+                if ui.button(tr("WebSocket…")).on_hover_text(tr("Decode the MessagePack frames a WebSocket sends")).clicked() {
+                    self.websocket.open = true;
+                }
+                if ui.button(tr("Listen…")).on_hover_text(tr("Receive MessagePack over UDP or TCP")).clicked() {
+                    self.listen.open = true;
+                }
+                if ui.button(tr("MQTT…")).on_hover_text(tr("Subscribe to an MQTT broker and decode what it sends")).clicked() {
+                    self.mqtt.open = true;
+                }
+                if ui.button(tr("Export session…")).on_hover_text(tr("Every tab, the settings and the undo history in one file to pass on")).clicked() {
+                    self.session_prompt = Some(SessionPrompt { import: false, path: "session.msgpack".to_string() });
+                }
+                if ui.button(tr("Import session…")).on_hover_text(tr("Open the tabs of an exported session and take on its settings")).clicked() {
+                    self.session_prompt = Some(SessionPrompt { import: true, path: String::new() });
+                }
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.toggle_value(&mut self.show_settings, tr("⚙ Settings"));
+                });
+            });
+
+            ui.separator();
+
+            self.tab_bar(ui);
+
+            let tab = &mut self.tabs[self.active_tab];
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut tab.mode, TabMode::Convert, tr("Convert"));
+                ui.selectable_value(&mut tab.mode, TabMode::Smart, tr("Smart input"));
+                ui.selectable_value(&mut tab.mode, TabMode::Diff, tr("Diff"));
+                ui.selectable_value(&mut tab.mode, TabMode::Compare, tr("Compare"));
+                if tab.mode == TabMode::Convert {
+                    ui.separator();
+                    ui.selectable_value(&mut self.settings.single_pane, false, tr("Two columns"));
+                    ui.selectable_value(&mut self.settings.single_pane, true, tr("Single pane"))
+                        .on_hover_text(tr("One input and one output pane with a direction toggle"));
+                }
+            });
+        });
+
+        if settings_window(ctx, &mut self.show_settings, &mut self.settings) {
+            self.file_prompt = Some(FilePrompt { target: FileTarget::ExtTypes, path: String::new(), large: None, watch: false });
+        }
+        batch_window(ctx, &mut self.batch, &self.settings.json_format());
+        let tab = &self.tabs[self.active_tab];
+        if let Some(bookmark) = bookmarks_window(ctx, &mut self.bookmarks, |direction| tab.bookmarked_panes(direction)) {
+            self.narrow_section = self.tabs[self.active_tab].load_bookmark(&bookmark);
+        }
+        websocket_window(ctx, &mut self.websocket, &self.settings.json_format());
+        listen_window(ctx, &mut self.listen, &self.settings.json_format());
+        mqtt_window(ctx, &mut self.mqtt, &self.settings.json_format(), &self.tabs[self.active_tab].json_input);
+        if let Some(input) = generate_window(ctx, &mut self.generate, &self.settings.json_format()) {
+            let tab = &mut self.tabs[self.active_tab];
+            self.narrow_section = tab.load_input(input, ctx, &self.settings);
+        }
+
+        for file in ctx.input(|i| i.raw.dropped_files.clone()) {
+            self.drop_file(file, ctx);
+        }
+        self.file_prompt_window(ctx);
+        self.save_prompt_window(ctx);
+        self.session_prompt_window(ctx);
+
+        self.tab_panels(ctx);
+        if let Some(target) = self.tabs[self.active_tab].open_request.take() {
+            self.file_prompt = Some(FilePrompt { target, path: String::new(), large: None, watch: false });
+            ctx.request_repaint();
+        }
+        if let Some((target, path)) = self.tabs[self.active_tab].open_recent.take() {
+            self.file_prompt = Some(FilePrompt { target, path: path.display().to_string(), large: None, watch: false });
+            self.open_prompted_file(ctx);
+        }
+        let tab = &mut self.tabs[self.active_tab];
+        if let Some(target) = tab.save_request.take() {
+            let input = match target {
+                SaveTarget::Json => &tab.opened_messagepack,
+                _ => &tab.opened_json,
+            };
+            self.save_prompt = Some(SavePrompt { target, path: target.default_name(input.as_deref()) });
+            ctx.request_repaint();
+        }
+        self.collect_errors(ctx);
+        drop_hint(ctx);
+        #[cfg(target_arch = "wasm32")]
+        flush_clipboard(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Err(e) = self.settings.save() {
+            eprintln!("{}", e);
+        }
+        if let Err(e) = self.save_session() {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+struct Encoded {
+    messagepack: Vec<u8>,
+    stats: SizeStats,
+    checksums: Checksums,
+    summary: ConversionSummary,
+    // What YAML input may not have said the way it was meant, and date-times left as strings
+    warnings: Vec<Warning>,
+}
+
+// What a background MessagePack → JSON conversion hands back once the input text decoded to bytes
+struct DecodeOutput {
+    // The bytes and their annotations, only while the Explain view is open: a row for every
+    // token of a large input takes far more memory than the decoded value. Built even when the
+    // bytes don't decode, so Explain can show where they break.
+    explanation: Option<(Vec<u8>, Explanation)>,
+    json: Result<(String, Decoded), ConvertError>,
+    // None when `json` is an error
+    summary: Option<ConversionSummary>,
+    // What the conversion warned about, e.g. arrays the field name template didn't fit
+    warnings: Vec<Warning>,
+    // How the input text was read, None for a binary file
+    read_as: Option<InputEncoding>,
+}
+
+struct Decoded {
+    value: serde_json::Value,
+    spans: SpanMap,
+    stats: SizeStats,
+    // None for anything but a single MessagePack value
+    type_stats: Option<TypeStats>,
+    // Of the whole input buffer
+    checksums: Checksums,
+}
+
+impl Decoded {
+    // What `bytes` decoded to with `options`: the one value, or the records of a stream or of
+    // frames as an array. Only a single MessagePack value gets a type breakdown.
+    fn of(values: Vec<serde_json::Value>, spans: SpanMap, bytes: &[u8], options: &ConvertOptions, compressed: Option<Compressed>) -> Decoded {
+        let mut stats = SizeStats::measure(&values, options.format, bytes.len());
+        stats.compressed = compressed;
+        let records = options.stream || options.framing != Framing::None;
+        let type_stats = match (records, options.format) {
+            (false, BinaryFormat::MessagePack) => type_stats(bytes).ok(),
+            _ => None,
+        };
+        let value = match records {
+            true => serde_json::Value::Array(values),
+            false => values.into_iter().next().unwrap_or_default(),
+        };
+        Decoded { value, spans, stats, type_stats, checksums: Checksums::of(bytes) }
+    }
+}
+
+// What the status bar reports about a finished conversion. Sizes are of the JSON text and the
+// raw MessagePack bytes, whichever way round the conversion went.
+#[derive(Clone, PartialEq)]
+struct ConversionSummary {
+    direction: Section,
+    input_bytes: usize,
+    output_bytes: usize,
+    records: usize,
+    duration: Duration,
+}
+
+impl ConversionSummary {
+    fn new(direction: Section, input_bytes: usize, output_bytes: usize, records: usize, started: Instant) -> Self {
+        ConversionSummary { direction, input_bytes, output_bytes, records, duration: started.elapsed() }
+    }
+
+    // e.g. "MessagePack → JSON · 3.1 MB → 5.4 MB · 12,004 records · 840 ms"
+    fn describe(&self) -> String {
+        let mut parts = vec![
+            tr(direction_name(self.direction)).to_string(),
+            format!("{} → {}", format_amount(self.input_bytes), format_amount(self.output_bytes)),
+        ];
+        if self.records > 1 {
+            parts.push(trf("{} records", &[&stats::group_thousands(self.records)]));
+        }
+        parts.push(format_duration(self.duration));
+        parts.join(" · ")
+    }
+}
+
+fn direction_name(direction: Section) -> &'static str {
+    match direction {
+        Section::JsonToMessagePack => "JSON → MessagePack",
+        Section::MessagePackToJson => "MessagePack → JSON",
+    }
+}
+
+// The whole JSON → MessagePack job, from the input text to what poll_workers applies, the output
+// compressed afterwards if there is `compression`
+fn encode_job(json_str: &str, options: &ConvertOptions, compression: Option<(Compression, i32)>, token: &JobToken) -> Result<Encoded, ConvertError> {
+    let started = Instant::now();
+    let conversion = convert_until(json_str.as_bytes(), options, token)?;
+    let messagepack = conversion.output;
+    let mut stats = SizeStats::measure(&conversion.values, options.format, messagepack.len());
+    let messagepack = match compression {
+        Some((compression, level)) => {
+            let compressed = compress(&messagepack, compression, level)?;
+            token.check()?;
+            stats.compressed = Some(Compressed { compression, compressed_bytes: compressed.len(), uncompressed_bytes: messagepack.len() });
+            stats.base64_bytes = compressed.len().div_ceil(3) * 4;
+            compressed
+        }
+        None => messagepack,
+    };
+    let checksums = Checksums::of(&messagepack);
+    let summary = ConversionSummary::new(Section::JsonToMessagePack, json_str.len(), messagepack.len(), stats.records, started);
+    Ok(Encoded { messagepack, stats, checksums, summary, warnings: conversion.warnings })
+}
+
+// Keys that aren't strings and several documents are taken in as well, with a warning for each
+fn parse_yaml_input(text: &str, json_format: &JsonFormat) -> Result<(serde_json::Value, Vec<Warning>), ConvertError> {
+    let (mut value, warnings) = parse_yaml(text, true)?.combined();
+    json_format.order_keys(&mut value);
+    Ok((value, warnings))
+}
+
+// A MessagePack → JSON job: what it hands convert_until, and what the window does around that
+struct DecodeJob {
+    options: ConvertOptions,
+    // The binary format is detected from the bytes, or the first frame, instead
+    detect_format: bool,
+    max_decompressed: usize,
+    // Annotate the bytes for the Explain view as well
+    explain: bool,
+}
+
+impl DecodeJob {
+    fn of(settings: &Settings, template: Option<Template>, explain: bool) -> DecodeJob {
+        DecodeJob {
+            options: settings.convert_options(Direction::ToJson).template(template),
+            detect_format: settings.binary_format.is_none(),
+            max_decompressed: settings.max_decompressed(),
+            explain,
+        }
+    }
+
+    // The whole job from the input text to what poll_workers applies
+    fn run_on_text(&self, encoded_str: &str, encoding: InputEncoding, token: &JobToken) -> Result<DecodeOutput, ConvertError> {
+        let (read_as, bytes) = encoding.decode(encoded_str)?;
+        token.check()?;
+        let output = self.run(&bytes, token)?;
+        Ok(DecodeOutput { read_as: Some(read_as), ..output })
+    }
+
+    // Same from raw bytes, for binary files that never go through text. A gzip or zlib wrapper
+    // is taken off first.
+    fn run(&self, bytes: &[u8], token: &JobToken) -> Result<DecodeOutput, ConvertError> {
+        let started = Instant::now();
+        let input_len = bytes.len();
+        let inflated = decompress(bytes, self.max_decompressed)?;
+        token.check()?;
+        let compressed = inflated.as_ref().map(|(compression, inflated)| {
+            Compressed { compression: *compression, compressed_bytes: input_len, uncompressed_bytes: inflated.len() }
+        });
+        let bytes = inflated.as_ref().map_or(bytes, |(_, inflated)| &inflated[..]);
+        let options = match self.detect_format {
+            true => {
+                let frames = self.options.framing.split(bytes);
+                let first = frames.ok().and_then(|frames| frames.into_iter().next()).map_or(bytes, |frame| &bytes[frame]);
+                self.options.clone().format(BinaryFormat::detect(first))
+            }
+            false => self.options.clone(),
+        };
+        let converted = convert_until(bytes, &options, token);
+        token.check()?;
+        let mut warnings = Vec::new();
+        let json = converted.and_then(|conversion| {
+            warnings = conversion.warnings;
+            let json = String::from_utf8(conversion.output).map_err(|e| ConvertError::SerializeJson(e.to_string()))?;
+            Ok((json, Decoded::of(conversion.values, conversion.spans, bytes, &options, compressed)))
+        });
+        let summary = json.as_ref().ok().map(|(json, decoded)| {
+            ConversionSummary::new(Section::MessagePackToJson, input_len, json.len(), decoded.stats.records, started)
+        });
+        // Explain only knows MessagePack without length prefixes
+        let explanation = self.explain.then(|| match (options.framing, options.format) {
+            (Framing::None, BinaryFormat::MessagePack) => (bytes.to_vec(), explain(bytes)),
+            _ => (bytes.to_vec(), Explanation::default()),
+        });
+        Ok(DecodeOutput { explanation, json, summary, warnings, read_as: None })
+    }
+}
+
+// Ignoring bytes after the value like the window does
+#[cfg(test)]
+fn decoding(format: Option<BinaryFormat>, options: ConvertOptions) -> DecodeJob {
+    DecodeJob { options: options.format(format.unwrap_or_default()).ignore_trailing(true), detect_format: format.is_none(), max_decompressed: files::MEGABYTE, explain: true }
+}
+
+// Base64, hex or escaped text to the raw MessagePack bytes, in whichever of them it is
+fn decode_encoded(encoded_str: &str) -> Result<Vec<u8>, ConvertError> {
+    InputEncoding::Auto.decode(encoded_str).map(|(_, bytes)| bytes)
+}
+
+// Length of the bytes behind base64/hex text, None if it doesn't decode (yet)
+fn decoded_len(encoded_str: &str) -> Option<usize> {
+    decode_encoded(encoded_str).ok().map(|bytes| bytes.len())
+}
+
+// Same from the length of the text alone, without decoding it
+fn estimated_decoded_len(encoded_str: &str) -> usize {
+    if let Some(digits) = hex_digits(encoded_str) {
+        digits.len() / 2
+    } else {
+        encoded_str.len() / 4 * 3
+    }
+}
+
+// Input this size is turned down before converting, a paste of hundreds of megabytes would
+// otherwise take the app down with it
+fn check_input_size(size: usize, settings: &Settings) -> Result<(), ConvertError> {
+    let limit = settings.max_input();
+    if size > limit {
+        return Err(ConvertError::InputTooLarge { size, limit });
+    }
+    Ok(())
+}
 
-#[derive(Default)]
-struct MessagePackJsonConverterApp {
-    json_input: String,
-    messagepack_output: String,
-    messagepack_input: String,
-    json_output: String,
-    error_message: Arc<Mutex<String>>,
+// Laying out a huge galley every frame makes the whole UI crawl, so past `limit` bytes the text
+// is only shown in the line viewer, which lays out the visible rows alone. `save` is set when
+// the banner's Save to file was clicked.
+fn output_editor(
+    ui: &mut egui::Ui,
+    id: &str,
+    text: &mut String,
+    options: &EditorOptions,
+    limit: usize,
+    viewer: &mut LineViewer,
+    save: &mut bool,
+) -> egui::Response {
+    if text.len() > limit {
+        ui.horizontal(|ui| {
+            ui.label(trf("{} is over the {} edit limit, shown read-only", &[&format_megabytes(text.len()), &format_megabytes(limit)]));
+            if ui.button(tr("Copy all")).clicked() {
+                copy_to_clipboard(text);
+            }
+            if ui.button(tr("Save to file")).clicked() {
+                *save = true;
+            }
+        });
+    } else if !viewer.open {
+        return text_editor(ui, id, text, options);
+    }
+    let shown = show_viewer(ui, id, viewer, text, options);
+    if let Some(copied) = shown.copied {
+        copy_to_clipboard(&copied);
+    }
+    shown.response
 }
 
-impl eframe::App for MessagePackJsonConverterApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
+// Switches an output pane between the editor and the line viewer
+fn viewer_toggle(ui: &mut egui::Ui, viewer: &mut LineViewer) {
+    let response = ui.toggle_value(&mut viewer.open, tr("Viewer"))
+        .on_hover_text(tr("Read-only view that only renders the visible lines, for very large outputs"));
+    if response.clicked() {
+        // The text may have been edited since the viewer last indexed it
+        viewer.invalidate();
+    }
+}
 
-            ui.vertical_centered(|ui| {
-                ui.heading("JSON <-> MessagePack Converter");
-            });
+// Progress bar and Cancel button while the worker has a job running
+fn show_progress<T: Send + 'static>(ui: &mut egui::Ui, worker: &mut Worker<T>) {
+    let Some(progress) = worker.progress() else {
+        return;
+    };
+    // Animating also keeps the UI repainting, so the bar follows the job
+    ui.add(egui::ProgressBar::new(progress).desired_width(120.0).show_percentage().animate(true));
+    if ui.button(tr("Cancel")).on_hover_text(tr("Stop the conversion and keep the current output")).clicked() {
+        worker.cancel();
+    }
+}
 
+// Bottom line of the window: the running conversion of the tab if there is one, otherwise a
+// summary of the last one that finished, and the Log toggle
+fn status_bar(ui: &mut egui::Ui, tab: &mut Tab, error_log: &mut ErrorLog) {
+    let running = [(Section::JsonToMessagePack, tab.encode_worker.progress()), (Section::MessagePackToJson, tab.decode_worker.progress())]
+        .into_iter()
+        .find_map(|(direction, progress)| Some((direction, progress?)));
+    let text = match (running, &tab.last_conversion) {
+        (Some((direction, progress)), _) => {
+            trf("Converting {}… {}%", &[&tr(direction_name(direction)), &format!("{:.0}", progress * 100.0)])
+        }
+        (None, Some(summary)) => summary.describe(),
+        (None, None) => tr("No conversion yet").to_string(),
+    };
+    ui.horizontal(|ui| {
+        if let Some(watch) = &tab.watch {
+            let watching = trf("Watching {}", &[&file_name(&watch.path)]);
+            // Highlighted for a moment after each reload
+            if let Some(left) = watch.flash_left(Instant::now()) {
+                ui.label(egui::RichText::new(watching).background_color(ui.visuals().selection.bg_fill));
+                ui.ctx().request_repaint_after(left);
+            } else {
+                ui.label(watching);
+            }
+            if ui.small_button(tr("Stop watching")).clicked() {
+                tab.watch = None;
+            }
             ui.separator();
+        }
+        ui.weak(text);
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            let label = trf("Log ({})", &[&error_log.entry_count()]);
+            ui.toggle_value(&mut error_log.open, label)
+                .on_hover_text(tr("Errors of this session, with when and where they happened"));
+        });
+    });
+}
 
-            // Add the "Clear All" button at the top
-            ui.vertical_centered(|ui| {
-                if ui.button("Clear All").clicked() {
-                    self.json_input.clear();
-                    self.messagepack_output.clear();
-                    self.messagepack_input.clear();
-                    self.json_output.clear();
-                    *self.error_message.lock().unwrap() = String::new();
-                }
-            });
+// For the MessagePack output pane's header, true when another encoding was picked
+fn output_encoding_menu(ui: &mut egui::Ui, encoding: &mut OutputEncoding) -> bool {
+    let before = *encoding;
+    egui::ComboBox::from_id_source("messagepack_output_encoding")
+        .selected_text(tr(encoding.name()))
+        .show_ui(ui, |ui| {
+            for option in OutputEncoding::ALL {
+                ui.selectable_value(encoding, option, tr(option.name()));
+            }
+        })
+        .response
+        .on_hover_text(tr("How the output writes the bytes"));
+    *encoding != before
+}
 
-            ui.separator();
+// For the MessagePack input pane's header, true when another encoding was picked. Detect says
+// what it read the last input as.
+fn input_encoding_menu(ui: &mut egui::Ui, encoding: &mut InputEncoding, read_as: Option<InputEncoding>) -> bool {
+    let before = *encoding;
+    let selected = match (*encoding, read_as) {
+        (InputEncoding::Auto, Some(read_as)) => trf("Detect ({})", &[&read_as.name()]),
+        _ => encoding.name().to_string(),
+    };
+    egui::ComboBox::from_id_source("messagepack_input_encoding")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for option in InputEncoding::ALL {
+                ui.selectable_value(encoding, option, option.name());
+            }
+        })
+        .response
+        .on_hover_text(tr("How the input text is read, for when detecting it guesses wrong"));
+    *encoding != before
+}
 
-            ui.horizontal(|ui| {
-                // JSON to MessagePack Conversion Section
-                ui.vertical(|ui| {
-                    ui.heading("JSON to MessagePack");
-
-                    ui.label("JSON Input:");
-                    ui.push_id("json_input", |ui| {
-                        egui::ScrollArea::vertical()
-                            .min_scrolled_height(300.0)
-                            .max_height(300.0)
-                            .show(ui, |ui| {
-                                ui.add(egui::TextEdit::multiline(&mut self.json_input)
-                                    .frame(true)
-                                    .desired_width(400.0)
-                                    .desired_rows(12)
-                                    .min_size(egui::vec2(400.0, 300.0)));
-                            });
-                    });
+// For an input pane's header, true when clicked
+fn open_button(ui: &mut egui::Ui, target: FileTarget) -> bool {
+    let hover = match target {
+        FileTarget::MessagePack(_) => tr("Load a raw MessagePack file such as a .msgpack, or base64 or hex text, into this pane"),
+        _ => tr("Load a file into this pane"),
+    };
+    ui.small_button(tr("Open…")).on_hover_text(hover).clicked()
+}
 
-                    if ui.button("Convert to MessagePack").clicked() {
-                        match json_to_messagepack(&self.json_input) {
-                            Ok(mp) => {
-                                self.messagepack_output = mp;
-                                *self.error_message.lock().unwrap() = String::new();
-                            }
-                            Err(e) => {
-                                *self.error_message.lock().unwrap() = e;
-                            }
-                        }
-                    }
+// For an output pane's header, true when clicked
+fn save_button(ui: &mut egui::Ui, enabled: bool) -> bool {
+    ui.add_enabled(enabled, egui::Button::new(tr("Save…")).small()).on_hover_text(tr("Write this pane to a file")).clicked()
+}
 
-                    ui.label("MessagePack Output (Base64):");
-                    ui.push_id("messagepack_output", |ui| {
-                        egui::ScrollArea::vertical()
-                            .min_scrolled_height(300.0)
-                            .max_height(300.0)
-                            .show(ui, |ui| {
-                                ui.add(egui::TextEdit::multiline(&mut self.messagepack_output)
-                                    .frame(true)
-                                    .desired_width(400.0)
-                                    .desired_rows(12)
-                                    .min_size(egui::vec2(400.0, 300.0))
-                                    .cursor_at_end(false));
-                            });
-                    });
+// JSON is read as text, anything opened into the MessagePack pane as raw bytes
+fn open_target(pane: Pane) -> FileTarget {
+    match pane {
+        Pane::JsonInput => FileTarget::Json,
+        _ => FileTarget::MessagePack(Encoding::default()),
+    }
+}
 
-                    if ui.button("Copy MessagePack").clicked() {
-                        copy_to_clipboard(&self.messagepack_output);
-                    }
-                });
+// Dims the window while files are dragged over it
+fn drop_hint(ctx: &egui::Context) {
+    if ctx.input(|i| i.raw.hovered_files.is_empty()) {
+        return;
+    }
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_hint")));
+    let rect = ctx.screen_rect();
+    painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+    painter.text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        tr("Drop a JSON or MessagePack file to load it"),
+        egui::TextStyle::Heading.resolve(&ctx.style()),
+        egui::Color32::WHITE,
+    );
+}
 
-                // MessagePack to JSON Conversion Section
-                ui.vertical(|ui| {
-                    ui.heading("MessagePack to JSON");
-
-                    ui.label("MessagePack Input (Base64 or Hex):");
-                    ui.push_id("messagepack_input", |ui| {
-                        egui::ScrollArea::vertical()
-                            .min_scrolled_height(300.0)
-                            .max_height(300.0)
-                            .show(ui, |ui| {
-                                ui.add(egui::TextEdit::multiline(&mut self.messagepack_input)
-                                    .frame(true)
-                                    .desired_width(400.0)
-                                    .desired_rows(12)
-                                    .min_size(egui::vec2(400.0, 300.0)));
-                            });
-                    });
+// The entries of a Copy as code menu, one per language. Input over MAX_INLINE_BYTES would make a
+// snippet nobody wants to paste, so those read it from `file` unless asked to embed it anyway.
+fn snippet_buttons(ui: &mut egui::Ui, input_len: usize, file: &str, snippet: impl Fn(Language, Source) -> String) {
+    let large = input_len > MAX_INLINE_BYTES;
+    if large {
+        ui.colored_label(WARNING_COLOR, trf("The input is {}, so these read it from {}", &[&format_size(input_len), &file]));
+    }
+    let source = if large { Source::File(file) } else { Source::Inline };
+    for language in Language::ALL {
+        if ui.button(language.name()).clicked() {
+            copy_to_clipboard(&snippet(language, source));
+            ui.close_menu();
+        }
+    }
+    if large {
+        ui.separator();
+        ui.menu_button(tr("Embed anyway"), |ui| {
+            for language in Language::ALL {
+                if ui.button(language.name()).clicked() {
+                    copy_to_clipboard(&snippet(language, Source::Inline));
+                    ui.close_menu();
+                }
+            }
+        });
+    }
+}
 
-                    if ui.button("Convert to JSON").clicked() {
-                        match messagepack_to_json(&self.messagepack_input) {
-                            Ok(json) => {
-                                self.json_output = json;
-                                *self.error_message.lock().unwrap() = String::new();
-                            }
-                            Err(e) => {
-                                *self.error_message.lock().unwrap() = e;
-                            }
-                        }
-                    }
+// Digests of the MessagePack bytes, each copied by clicking it
+fn show_checksums(ui: &mut egui::Ui, checksums: &Checksums) {
+    ui.horizontal(|ui| {
+        for (name, digest) in [("SHA-256", &checksums.sha256), ("CRC32", &checksums.crc32)] {
+            let text = egui::RichText::new(format!("{} {}", name, digest)).monospace().small().weak();
+            if ui.add(egui::Label::new(text).sense(egui::Sense::click())).on_hover_text(tr("Click to copy")).clicked() {
+                copy_to_clipboard(digest);
+            }
+        }
+    });
+}
 
-                    ui.label("JSON Output:");
-                    ui.push_id("json_output", |ui| {
-                        egui::ScrollArea::vertical()
-                            .min_scrolled_height(300.0)
-                            .max_height(300.0)
-                            .show(ui, |ui| {
-                                ui.add(egui::TextEdit::multiline(&mut self.json_output)
-                                    .frame(true)
-                                    .desired_width(400.0)
-                                    .desired_rows(12)
-                                    .min_size(egui::vec2(400.0, 300.0))
-                                    .cursor_at_end(false));
-                            });
-                    });
+// Short size for the status bar, e.g. "812 B", "4.2 KB" or "3.1 MB"
+fn format_amount(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < files::MEGABYTE {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format_megabytes(bytes)
+    }
+}
 
-                    if ui.button("Copy JSON").clicked() {
-                        copy_to_clipboard(&self.json_output);
-                    }
-                });
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{} ms", duration.as_millis())
+    } else {
+        format!("{:.1} s", duration.as_secs_f64())
+    }
+}
+
+fn format_megabytes(bytes: usize) -> String {
+    format!("{:.1} MB", bytes as f64 / files::MEGABYTE as f64)
+}
+
+fn show_type_stats(ui: &mut egui::Ui, stats: &TypeStats) {
+    egui::Grid::new("type_stats").striped(true).show(ui, |ui| {
+        ui.strong(tr("Type"));
+        ui.strong(tr("Count"));
+        ui.strong(tr("Encoded bytes"));
+        ui.end_row();
+        for (name, family) in stats.rows().into_iter().filter(|(_, family)| family.count > 0) {
+            ui.label(name);
+            ui.label(stats::group_thousands(family.count));
+            ui.label(stats::group_thousands(family.bytes));
+            ui.end_row();
+        }
+    });
+    ui.separator();
+    ui.label(trf("String contents: {}", &[&format_size(stats.string_payload_bytes)]));
+    ui.label(trf("Longest string: {}", &[&format_size(stats.longest_string)]));
+    ui.label(trf("Largest binary: {}", &[&format_size(stats.largest_binary)]));
+    ui.label(trf("Max depth: {}", &[&stats.max_depth]));
+}
+
+fn decode_diff_sides(left: &str, right: &str) -> Result<(serde_json::Value, serde_json::Value), String> {
+    let decode = |text: &str| {
+        decode_encoded(text)
+            .and_then(|bytes| BinaryFormat::MessagePack.decode_at(&bytes, 0, false))
+            .map(|(value, _)| value)
+    };
+    let left = decode(left).map_err(|e| trf("Left: {}", &[&e]))?;
+    let right = decode(right).map_err(|e| trf("Right: {}", &[&e]))?;
+    Ok((left, right))
+}
+
+fn diff_bytes(left: &str, right: &str) -> Result<ByteDiff, String> {
+    let left = decode_encoded(left).map_err(|e| trf("Left: {}", &[&e]))?;
+    let right = decode_encoded(right).map_err(|e| trf("Right: {}", &[&e]))?;
+    Ok(ByteDiff::between(left, right))
+}
+
+// The differing ranges with what each side encodes there, over the side-by-side dump
+fn show_byte_diff(ui: &mut egui::Ui, byte_diff: &ByteDiff) {
+    let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+    if !byte_diff.ranges.is_empty() {
+        egui::ScrollArea::vertical()
+            .id_source("byte_diff_ranges")
+            .max_height(row_height * 6.0)
+            .auto_shrink([false, true])
+            .show_rows(ui, row_height, byte_diff.ranges.len(), |ui, rows| {
+                for range in &byte_diff.ranges[rows] {
+                    let text = egui::RichText::new(byte_diff.describe(range)).monospace().color(WARNING_COLOR);
+                    ui.add(egui::Label::new(text).wrap(false));
+                }
             });
+        ui.separator();
+    }
+    let rows = byte_diff.rows();
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let text_color = ui.visuals().text_color();
+    let changed = WARNING_COLOR.gamma_multiply(0.35);
+    egui::ScrollArea::both()
+        .id_source("byte_diff_rows")
+        .auto_shrink([false, false])
+        .show_rows(ui, row_height, rows.len(), |ui, rows_shown| {
+            for (left, right, cells) in &rows[rows_shown] {
+                // The text is the same as format_row's, with the differing cells tinted
+                let text = format_row(*left, *right, cells);
+                let mut job = egui::text::LayoutJob::default();
+                let format = |background| egui::TextFormat { font_id: font_id.clone(), color: text_color, background, ..Default::default() };
+                let mut position = 0;
+                let right_start = text.find(" | ").map_or(text.len(), |bar| bar + 3);
+                let cell_starts = |first: usize| (0..cells.len()).map(move |index| first + index * 3);
+                let tinted: Vec<(usize, &Cell)> = cell_starts(7).zip(cells.iter()).chain(cell_starts(right_start + 7).zip(cells.iter())).collect();
+                for (start, cell) in tinted {
+                    if !cell.differs() {
+                        continue;
+                    }
+                    job.append(&text[position..start], 0.0, format(egui::Color32::TRANSPARENT));
+                    job.append(&text[start..start + 2], 0.0, format(changed));
+                    position = start + 2;
+                }
+                job.append(&text[position..], 0.0, format(egui::Color32::TRANSPARENT));
+                ui.add(egui::Label::new(job).wrap(false));
+            }
+        });
+}
+
+fn parse_compare_sides(expected: &str, actual: &str, json_format: &JsonFormat) -> Result<(serde_json::Value, serde_json::Value), String> {
+    let expected = json_format.parse(expected).map_err(|e| trf("Expected: {}", &[&e]))?;
+    let actual = json_format.parse(actual).map_err(|e| trf("Actual: {}", &[&e]))?;
+    Ok((expected, actual))
+}
 
-            // Error Display Section
-            let error_message = self.error_message.lock().unwrap();
-            if !error_message.is_empty() {
-                ui.label(egui::RichText::new(&*error_message).color(egui::Color32::RED));
+fn show_round_trip(ui: &mut egui::Ui, round_trip: &RoundTrip) {
+    if round_trip.original_len != round_trip.reencoded_len {
+        ui.label(trf(
+            "Original {}, re-encoded {}",
+            &[&format_size(round_trip.original_len), &format_size(round_trip.reencoded_len)],
+        ));
+    }
+    egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+        if !round_trip.changes.is_empty() {
+            ui.strong(tr("Changed values"));
+            for change in &round_trip.changes {
+                let text = egui::RichText::new(change.describe()).monospace().color(change_color(change.kind));
+                ui.add(egui::Label::new(text).wrap(false));
+            }
+        }
+        if !round_trip.byte_ranges.is_empty() {
+            ui.strong(tr("Differing bytes"));
+            for range in &round_trip.byte_ranges {
+                ui.monospace(format!("{:#06x}..{:#06x}  ({})", range.start, range.end, format_size(range.len())));
             }
+        }
+    });
+}
+
+const WARNING_COLOR: egui::Color32 = egui::Color32::from_rgb(0xd0, 0x8c, 0x00);
+
+// A yellow strip saying how many warnings there are, which opens to list them
+fn show_warnings(ui: &mut egui::Ui, id: &str, warnings: &[Warning]) {
+    if warnings.is_empty() {
+        return;
+    }
+    egui::Frame::none()
+        .fill(WARNING_COLOR.gamma_multiply(0.15))
+        .inner_margin(egui::Margin::symmetric(4.0, 2.0))
+        .rounding(2.0)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            egui::CollapsingHeader::new(egui::RichText::new(warning::count(warnings)).color(WARNING_COLOR))
+                .id_source(id)
+                .show(ui, |ui| {
+                    egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                        for warning in warnings {
+                            ui.label(warning.to_string()).on_hover_text(tr(warning.kind.name()));
+                        }
+                    });
+                });
         });
+}
+
+fn change_color(kind: ChangeKind) -> egui::Color32 {
+    match kind {
+        ChangeKind::Added => VALID_COLOR,
+        ChangeKind::Removed => egui::Color32::RED,
+        ChangeKind::Changed => WARNING_COLOR,
+        ChangeKind::NumberType => egui::Color32::from_rgb(0x3b, 0x82, 0xf6),
     }
 }
 
-fn json_to_messagepack(json_str: &str) -> Result<String, String> {
-    let json_value: serde_json::Value = serde_json::from_str(json_str)
-        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-    let messagepack = rmp_serde::to_vec(&json_value)
-        .map_err(|e| format!("Failed to serialize to MessagePack: {}", e))?;
-    Ok(general_purpose::STANDARD.encode(&messagepack))
+const VALID_COLOR: egui::Color32 = egui::Color32::from_rgb(0x2e, 0xa0, 0x43);
+
+fn show_validation(ui: &mut egui::Ui, validation: &Option<Result<String, String>>) {
+    match validation {
+        Some(Ok(summary)) => {
+            ui.label(egui::RichText::new(summary).color(VALID_COLOR));
+        }
+        Some(Err(e)) => {
+            ui.label(egui::RichText::new(e).color(egui::Color32::RED));
+        }
+        None => {}
+    }
 }
 
-fn messagepack_to_json(encoded_str: &str) -> Result<String, String> {
-    let messagepack = if is_hex(encoded_str) {
-        hex::decode(encoded_str).map_err(|e| format!("Failed to decode Hex: {}", e))?
-    } else {
-        general_purpose::STANDARD.decode(encoded_str).map_err(|e| format!("Failed to decode Base64: {}", e))?
-    };
+fn show_schema_summary(ui: &mut egui::Ui, check: &Option<Result<Vec<Violation>, String>>) {
+    match check {
+        Some(Ok(violations)) if violations.is_empty() => {
+            ui.label(egui::RichText::new(tr("Matches the schema")).color(VALID_COLOR));
+        }
+        Some(Ok(violations)) => {
+            let text = trf(if violations.len() == 1 { "{} schema violation" } else { "{} schema violations" }, &[&violations.len()]);
+            ui.label(egui::RichText::new(text).color(egui::Color32::RED));
+        }
+        Some(Err(_)) => {
+            ui.label(egui::RichText::new(tr("Schema check failed")).color(egui::Color32::RED));
+        }
+        None => {}
+    }
+}
 
-    let json_value: serde_json::Value = rmp_serde::from_slice(&messagepack)
-        .map_err(|e| format!("Failed to deserialize MessagePack: {}", e))?;
-    serde_json::to_string_pretty(&json_value)
-        .map_err(|e| format!("Failed to serialize to JSON: {}", e))
+fn format_size(bytes: usize) -> String {
+    trf(if bytes == 1 { "{} byte" } else { "{} bytes" }, &[&bytes])
 }
 
 fn is_hex(s: &str) -> bool {
-    s.chars().all(|c| c.is_digit(16))
+    hex_digits(s).is_some()
+}
+
+const MIN_SECTION_WIDTH: f32 = 250.0;
+// Below this width only one section is shown at a time
+const STACKED_LAYOUT_BREAKPOINT: f32 = 850.0;
+const MIN_EDITOR_HEIGHT: f32 = 60.0;
+
+// Split the height left in a section between its two editors, keeping room for the
+// label and button rows below them (plus any extra toolbar rows) so the buttons never get pushed off-screen.
+fn editor_height(ui: &egui::Ui, extra_rows: usize) -> f32 {
+    let row_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y;
+    let reserved = (7 + extra_rows) as f32 * row_height;
+    ((ui.available_height() - reserved) / 2.0 - ui.spacing().item_spacing.y).max(MIN_EDITOR_HEIGHT)
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 fn copy_to_clipboard(text: &str) {
     let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
     ctx.set_contents(text.to_owned()).unwrap();
 }
 
+// The browser only lets egui write the clipboard, so the text waits for the end of the frame
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    static COPIED_TEXT: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+}
+
+#[cfg(target_arch = "wasm32")]
+fn copy_to_clipboard(text: &str) {
+    COPIED_TEXT.with(|copied| *copied.borrow_mut() = Some(text.to_owned()));
+}
+
+#[cfg(target_arch = "wasm32")]
+fn flush_clipboard(ctx: &egui::Context) {
+    if let Some(text) = COPIED_TEXT.with(|copied| copied.borrow_mut().take()) {
+        ctx.output_mut(|o| o.copied_text = text);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 fn main() {
-    let app = MessagePackJsonConverterApp::default();
+    // `convert` runs without a window, no arguments or a file to open open it
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let file = match cli::run(&args) {
+        Launch::Exit(code) => std::process::exit(code),
+        Launch::Window(file) => file,
+    };
 
     let custom_viewport = egui::ViewportBuilder {
-        min_inner_size: Some(egui::vec2(850.0, 800.0)),
+        min_inner_size: Some(egui::vec2(400.0, 500.0)),
         ..Default::default()
     };
     let options = eframe::NativeOptions {
@@ -194,10 +3474,25 @@ fn main() {
     let _ = eframe::run_native(
         "MessagePack <-> JSON Converter",
         options,
-        Box::new(|_cc| Box::new(app)),
+        Box::new(|cc| Box::new(MessagePackJsonConverterApp::new(cc, file))),
     );
 }
 
+// Started by trunk from index.html, files come in by drag and drop
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    wasm_bindgen_futures::spawn_local(async {
+        eframe::WebRunner::new()
+            .start(
+                "the_canvas_id",
+                eframe::WebOptions::default(),
+                Box::new(|cc| Box::new(MessagePackJsonConverterApp::new(cc, None))),
+            )
+            .await
+            .expect("failed to start eframe");
+    });
+}
+
 
 /* Tests */
 #[test]
@@ -206,8 +3501,7 @@ fn test_json_to_messagepack() {
     let result = json_to_messagepack(json_data);
     assert!(result.is_ok());
 
-    // Decode the base64 string into raw bytes
-    let actual_bytes = general_purpose::STANDARD.decode(result.unwrap()).expect("Failed to decode base64");
+    let actual_bytes = result.unwrap();
 
     // Convert the bytes into a hex string
     let actual_hex = hex::encode(actual_bytes);
@@ -221,8 +3515,8 @@ fn test_json_to_messagepack() {
 
 #[test]
 fn test_messagepack_to_json() {
-    let msgpack_data = "g6NhZ2UepGNpdHmqV29uZGVybGFuZKRuYW1lpUFsaWNl";
-    let result = messagepack_to_json(msgpack_data);
+    let msgpack_data = general_purpose::STANDARD.decode("g6NhZ2UepGNpdHmqV29uZGVybGFuZKRuYW1lpUFsaWNl").unwrap();
+    let result = messagepack_to_json(&msgpack_data);
     assert!(result.is_ok());
 
     let expected = r#"{
@@ -237,14 +3531,14 @@ fn test_messagepack_to_json() {
 fn test_invalid_json_to_messagepack() {
     let invalid_json = r#"{"name":"Alice","age":30,"city":Wonderland}"#; // Missing quotes around Wonderland
     let result = json_to_messagepack(invalid_json);
-    assert!(result.is_err());
+    assert!(matches!(result, Err(ConvertError::JsonParse { line: 1, column: 33, .. })));
 }
 
 #[test]
 fn test_invalid_messagepack_to_json() {
     let invalid_msgpack = "invalid_base64_string";
-    let result = messagepack_to_json(invalid_msgpack);
-    assert!(result.is_err());
+    let result = decode_encoded(invalid_msgpack).and_then(|bytes| messagepack_to_json(&bytes));
+    assert_eq!(result, Err(ConvertError::Base64Decode(base64::DecodeError::InvalidLength)));
 }
 
 #[test]
@@ -327,7 +3621,7 @@ fn test_is_hex_with_valid_and_invalid_input() {
 fn test_messagepack_to_json_with_hex_input() {
     let valid_messagepack_hex = "83a36167651ea463697479aa576f6e6465726c616e64a46e616d65a5416c696365";
 
-    let result = messagepack_to_json(valid_messagepack_hex);
+    let result = messagepack_to_json(&hex::decode(valid_messagepack_hex).unwrap());
     assert!(result.is_ok(), "Valid hex MessagePack should decode to JSON");
 
     let expected_json = r#"{"name":"Alice","age":30,"city":"Wonderland"}"#;
@@ -337,20 +3631,545 @@ fn test_messagepack_to_json_with_hex_input() {
     assert_eq!(expected_json_value, result_json_value);
 }
 
+#[test]
+fn test_hex_pasted_from_a_debugger_or_c_source_decodes() {
+    let bytes = [0x81, 0xa1, b'a', 0x01];
+    for text in ["0x81a16101", "81 a1 61 01", "0x81, 0xa1, 0x61, 0x01", "0x81,0xA1,\n  0x61,0x01,\n"] {
+        assert_eq!(decode_encoded(text).unwrap(), bytes, "{:?}", text);
+    }
+    assert_eq!(estimated_decoded_len("0x81, 0xa1, 0x61, 0x01"), 4);
+    assert!(matches!(decode_encoded("0x81 0xa"), Err(ConvertError::HexDecode(_))));
+}
+
 #[test]
 fn test_messagepack_to_json_and_back() {
     let original_messagepack_hex = "83a36167651ea463697479aa576f6e6465726c616e64a46e616d65a5416c696365";
 
     // Convert MessagePack hex to JSON
-    let json_data = messagepack_to_json(original_messagepack_hex).expect("Failed to convert MessagePack to JSON");
+    let json_data = messagepack_to_json(&hex::decode(original_messagepack_hex).unwrap()).expect("Failed to convert MessagePack to JSON");
 
-    // Convert JSON back to MessagePack (which will be base64 encoded)
-    let messagepack_b64 = json_to_messagepack(&json_data).expect("Failed to convert JSON back to MessagePack");
-
-    // Decode the base64 back into the original hex string
-    let new_messagepack_bytes = general_purpose::STANDARD.decode(&messagepack_b64).expect("Failed to decode base64 back to bytes");
+    // Convert JSON back to MessagePack
+    let new_messagepack_bytes = json_to_messagepack(&json_data).expect("Failed to convert JSON back to MessagePack");
     let new_messagepack_hex = hex::encode(new_messagepack_bytes);
 
     // Compare the original and new MessagePack hex values
     assert_eq!(original_messagepack_hex, new_messagepack_hex);
 }
+
+#[test]
+fn test_closing_tabs_keeps_a_valid_active_tab() {
+    let mut app = MessagePackJsonConverterApp::default();
+    app.open_tab();
+    app.open_tab();
+    app.open_tab();
+    assert_eq!(app.active_tab, 2);
+
+    app.close_tab(0);
+    assert_eq!(app.active_tab, 1);
+    assert_eq!(app.tabs[app.active_tab].title, "Tab 3");
+
+    app.close_tab(1);
+    assert_eq!(app.active_tab, 0);
+    app.close_tab(0);
+    assert_eq!(app.tabs.len(), 1);
+    assert_eq!(app.tabs[0].title, "Tab 4");
+}
+
+#[test]
+fn test_an_exported_session_imports_as_it_was() {
+    let mut app = MessagePackJsonConverterApp::default();
+    app.open_tab();
+    app.settings.json_indent = 3;
+    let tab = &mut app.tabs[0];
+    tab.replace_pane(Pane::JsonInput, r#"{"a": 1}"#.to_string());
+    tab.messagepack_output = "gaFhAQ==".to_string();
+    tab.messagepack_bytes = Some(vec![0x81, 0xa1, 0x61, 0x01]);
+    tab.messagepack_file = Some(BinaryFile::new("capture.msgpack", vec![0xc0, 0xc1], Encoding::Hex));
+    let bytes = app.export_session().write(false).unwrap();
+
+    let mut other = MessagePackJsonConverterApp::default();
+    other.open_tab();
+    let (export, notes) = SessionExport::read(&bytes).unwrap();
+    other.import_session(&egui::Context::default(), export, notes);
+    assert_eq!((other.tabs.len(), other.active_tab, other.settings.json_indent), (2, 1, 3));
+    let imported = &other.tabs[1];
+    assert_eq!(imported.panes(), app.tabs[0].panes());
+    assert_eq!(imported.messagepack_bytes, app.tabs[0].messagepack_bytes);
+    assert_eq!(imported.messagepack_file.as_ref().map(|file| (&file.name[..], &file.bytes[..])), Some(("capture.msgpack", &[0xc0, 0xc1][..])));
+    assert_eq!(imported.histories[Pane::JsonInput as usize].snapshots(), (&[String::new()][..], &[][..]));
+    assert!(other.tabs.iter().all(|tab| tab.error_events.is_empty()));
+}
+
+#[cfg(test)]
+fn converted_tab() -> Tab {
+    let mut tab = Tab {
+        json_input: r#"{"a": 1}"#.to_string(),
+        messagepack_output: "gaFhAQ==".to_string(),
+        messagepack_input: "gaFhAQ==".to_string(),
+        json_output: "{\n  \"a\": 1\n}".to_string(),
+        encode_stats: Some(SizeStats::default()),
+        decode_stats: Some(SizeStats::default()),
+        decoded_value: Some(serde_json::json!({"a": 1})),
+        ..Default::default()
+    };
+    tab.spans.insert(String::new(), 0..4);
+    tab
+}
+
+#[test]
+fn test_output_encoding_rewrites_the_output() {
+    let mut tab = converted_tab();
+    let mut settings = Settings { messagepack_output_encoding: OutputEncoding::SpacedUpperHex, ..Settings::default() };
+    tab.reencode_output(&settings);
+    assert_eq!(tab.messagepack_output, "81 A1 61 01");
+    assert!(tab.encode_stats.is_some());
+    // Spaced hex reads back as well, and Undo has the base64 again
+    assert_eq!(decode_encoded(&tab.messagepack_output).unwrap(), [0x81, 0xa1, 0x61, 0x01]);
+    settings.messagepack_output_encoding = OutputEncoding::Base64;
+    tab.reencode_output(&settings);
+    assert_eq!(tab.messagepack_output, "gaFhAQ==");
+    tab.step_history(Pane::MessagePackOutput, Step::Undo);
+    assert_eq!(tab.messagepack_output, "81 A1 61 01");
+
+    // Output that doesn't decode is left alone
+    tab.messagepack_output = "not mine".to_string();
+    tab.reencode_output(&settings);
+    assert_eq!(tab.messagepack_output, "not mine");
+}
+
+#[test]
+fn test_clear_pane_only_touches_that_pane() {
+    let mut tab = converted_tab();
+    tab.clear_pane(Pane::MessagePackOutput);
+    assert!(tab.messagepack_output.is_empty());
+    assert!(tab.encode_stats.is_none());
+    assert_eq!(tab.json_input, r#"{"a": 1}"#);
+    assert_eq!(tab.messagepack_input, "gaFhAQ==");
+    assert!(tab.decode_stats.is_some());
+
+    tab.clear_pane(Pane::JsonOutput);
+    assert!(tab.json_output.is_empty());
+    assert!(tab.decoded_value.is_none());
+    assert!(tab.spans.is_empty());
+    assert!(tab.decode_stats.is_none());
+    assert_eq!(tab.messagepack_input, "gaFhAQ==");
+}
+
+#[test]
+fn test_errors_are_queued_with_their_operation() {
+    let mut tab = converted_tab();
+    tab.json_input = "{".to_string();
+    let json_format = JsonFormat::default();
+    tab.reformat_json_input("Format", &json_format, |value| json_format.pretty(value));
+    assert_eq!(tab.json_input, "{");
+    assert_eq!(tab.error_events.len(), 1);
+    assert_eq!(tab.error_events[0].operation, "Format");
+    assert!(tab.error_events[0].message.starts_with("Failed to parse JSON"));
+
+    // Successes don't take earlier errors back
+    tab.json_input = "{}".to_string();
+    tab.reformat_json_input("Format", &json_format, |value| json_format.pretty(value));
+    assert_eq!(tab.error_events.len(), 1);
+}
+
+#[test]
+fn test_clear_json_input_drops_validation_but_can_be_undone() {
+    let mut tab = converted_tab();
+    tab.json_validation = Some(Ok("Valid JSON: object with 0 keys".to_string()));
+    tab.clear_pane(Pane::JsonInput);
+    assert!(tab.json_validation.is_none());
+    assert_eq!(tab.messagepack_output, "gaFhAQ==");
+
+    tab.step_history(Pane::JsonInput, Step::Undo);
+    assert_eq!(tab.json_input, r#"{"a": 1}"#);
+}
+
+#[test]
+fn test_clear_all_can_be_undone_pane_by_pane() {
+    let mut tab = converted_tab();
+    tab.clear_all();
+    assert!(tab.json_output.is_empty() && tab.decoded_value.is_none());
+
+    tab.step_history(Pane::JsonOutput, Step::Undo);
+    assert_eq!(tab.json_output, "{\n  \"a\": 1\n}");
+    // The tree view is rebuilt from the restored text
+    assert_eq!(tab.decoded_value, Some(serde_json::json!({"a": 1})));
+    assert!(tab.json_input.is_empty());
+    tab.step_history(Pane::JsonInput, Step::Undo);
+    assert_eq!(tab.json_input, r#"{"a": 1}"#);
+}
+
+#[test]
+fn test_binary_files_convert_from_their_bytes() {
+    let bytes = vec![0x81, 0xa1, 0x61, 0x01];
+    let mut tab = Tab { messagepack_input: "c1".to_string(), ..Default::default() };
+    let settings = Settings { auto_convert_examples: false, ..Default::default() };
+    let file = BinaryFile::new("a.msgpack", bytes.clone(), Encoding::Hex);
+    tab.load_input(FileInput::Binary(file), &egui::Context::default(), &settings);
+    assert!(tab.messagepack_input.is_empty());
+    assert_eq!(tab.messagepack_input_bytes().unwrap().as_ref(), &bytes[..]);
+
+    let from_bytes = decoding(Some(BinaryFormat::MessagePack), ConvertOptions::to_json()).run(&bytes, &JobToken::default()).unwrap();
+    let from_text = decoding(Some(BinaryFormat::MessagePack), ConvertOptions::to_json()).run_on_text("81a16101", InputEncoding::Auto, &JobToken::default()).unwrap();
+    assert_eq!(from_bytes.json.unwrap().0, from_text.json.unwrap().0);
+    assert_eq!(from_bytes.summary.unwrap().input_bytes, 4);
+
+    // Clearing the pane lets go of the file
+    tab.clear_pane(Pane::MessagePackInput);
+    assert!(tab.messagepack_file.is_none());
+    assert!(tab.messagepack_input_bytes().unwrap().is_empty());
+}
+
+#[test]
+fn test_gzipped_messagepack_decodes() {
+    let encoded = encode_job(r#"{"a": [1, 2, 3]}"#, &ConvertOptions::to_messagepack(), Some((Compression::Gzip, 0)), &JobToken::default()).unwrap();
+    let compressed = encoded.stats.compressed.unwrap();
+    assert_eq!(compressed.compressed_bytes, encoded.messagepack.len());
+    assert_eq!(encoded.summary.output_bytes, encoded.messagepack.len());
+
+    let decoded = decoding(None, ConvertOptions::to_json()).run(&encoded.messagepack, &JobToken::default()).unwrap();
+    let (json, decoded_json) = decoded.json.unwrap();
+    assert_eq!(json, "{\n  \"a\": [\n    1,\n    2,\n    3\n  ]\n}");
+    assert_eq!(decoded_json.stats.compressed, Some(compressed));
+    assert_eq!(decoded.explanation.unwrap().0.len(), compressed.uncompressed_bytes);
+    assert_eq!(decoded.summary.unwrap().input_bytes, encoded.messagepack.len());
+    assert!(DecodeJob { max_decompressed: 4, ..decoding(None, ConvertOptions::to_json()) }.run(&encoded.messagepack, &JobToken::default()).is_err());
+}
+
+#[test]
+fn test_framed_records_decode_to_an_array() {
+    let json_format = JsonFormat::default();
+    let encoded = encode_job(r#"[{"a": 1}, true]"#, &ConvertOptions::to_messagepack().framing(Framing::U32Be), None, &JobToken::default()).unwrap();
+    assert_eq!(encoded.messagepack, [0, 0, 0, 4, 0x81, 0xa1, 0x61, 0x01, 0, 0, 0, 1, 0xc3]);
+    assert_eq!(encoded.stats.records, 2);
+    let decoded = decoding(None, ConvertOptions::to_json().framing(Framing::U32Be)).run(&encoded.messagepack, &JobToken::default()).unwrap();
+    let (json, _) = decoded.json.unwrap();
+    assert_eq!(json_format.parse(&json).unwrap(), serde_json::json!([{"a": 1}, true]));
+    let short = decoding(None, ConvertOptions::to_json().framing(Framing::U32Be)).run(&encoded.messagepack[..12], &JobToken::default()).unwrap();
+    assert!(matches!(short.json, Err(ConvertError::ShortFrame { index: 1, offset: 8, .. })));
+
+    // [0, 5, "get", []] and its response [1, 5, nil, true], one in each frame
+    let messages = [0, 0, 0, 8, 0x94, 0x00, 0x05, 0xa3, b'g', b'e', b't', 0x90, 0, 0, 0, 5, 0x94, 0x01, 0x05, 0xc0, 0xc3];
+    let rpc = decoding(None, ConvertOptions::to_json().framing(Framing::U32Be).rpc(true));
+    let (_, decoded) = rpc.run(&messages, &JobToken::default()).unwrap().json.unwrap();
+    assert_eq!(decoded.value, serde_json::json!([
+        {"type": "request", "msgid": 5, "method": "get", "params": []},
+        {"type": "response", "msgid": 5, "method": "get", "error": null, "result": true},
+    ]));
+}
+
+#[test]
+fn test_ndjson_output_puts_each_record_on_a_line() {
+    let json_format = JsonFormat::default();
+    let ndjson = ConvertOptions::to_json().stream(true).ndjson(true);
+    let bytes = [0x81, 0xa1, 0x61, 0x01, 0xc3, 0x92, 0x01, 0x02];
+    let (json, decoded) = decoding(None, ndjson.clone()).run(&bytes, &JobToken::default()).unwrap().json.unwrap();
+    assert_eq!(json, "{\"a\":1}\ntrue\n[1,2]\n");
+    assert_eq!(decoded.stats.records, 3);
+    for line in json.lines() {
+        assert!(json_format.parse(line).is_ok(), "{}", line);
+    }
+    // Frames work the same, and without NDJSON only the first of the values is decoded
+    let mut framed = Vec::new();
+    Framing::Varint.frame(&bytes[..4], &mut framed).unwrap();
+    Framing::Varint.frame(&bytes[4..5], &mut framed).unwrap();
+    let (json, _) = decoding(None, ndjson.framing(Framing::Varint)).run(&framed, &JobToken::default()).unwrap().json.unwrap();
+    assert_eq!(json.lines().count(), 2);
+    let (_, decoded) = decoding(None, ConvertOptions::to_json()).run(&bytes, &JobToken::default()).unwrap().json.unwrap();
+    assert_eq!(decoded.value, serde_json::json!({"a": 1}));
+}
+
+#[test]
+fn test_field_name_template_labels_positional_structs() {
+    let template = Template::parse(r#"["age", "city", "name"]"#).unwrap();
+    // [30, "Wonderland", "Alice"]
+    let bytes = [0x93, 0x1e, 0xaa, b'W', b'o', b'n', b'd', b'e', b'r', b'l', b'a', b'n', b'd', 0xa5, b'A', b'l', b'i', b'c', b'e'];
+    let labeled = decoding(None, ConvertOptions::to_json().template(Some(template.clone())));
+    let output = labeled.run(&bytes, &JobToken::default()).unwrap();
+    assert!(output.warnings.is_empty());
+    let (_, decoded) = output.json.unwrap();
+    assert_eq!(decoded.value, serde_json::json!({"age": 30, "city": "Wonderland", "name": "Alice"}));
+    assert!(decoded.spans.is_empty());
+
+    // Each record is matched on its own, the one that's too short stays an array
+    let ndjson = decoding(None, ConvertOptions::to_json().stream(true).ndjson(true).template(Some(template)));
+    let records = [&bytes[..], &[0x91, 0x01]].concat();
+    let output = ndjson.run(&records, &JobToken::default()).unwrap();
+    let warnings: Vec<String> = output.warnings.iter().map(Warning::to_string).collect();
+    assert_eq!(warnings, ["/1: 1 items where the template names 3 fields, left as an array"]);
+    assert_eq!(output.json.unwrap().0.lines().nth(1), Some("[1]"));
+}
+
+#[test]
+fn test_ext_types_round_trip_through_the_conversions() {
+    let json_format = JsonFormat::default();
+    let ext_types = ext_types::ExtRegistry::parse("7 = \"struct lat:f64 lon:f64\"").unwrap();
+    let json = r#"{"at": {"$ext": 7, "value": {"lat": 51.5, "lon": -0.125}}, "other": {"$ext": 8, "value": 1}}"#;
+    let options = ConvertOptions::to_messagepack().ext_registry(ext_types.clone());
+    let encoded = encode_job(json, &options, None, &JobToken::default()).unwrap();
+    // fixext16 of type 7 in place of the tagged object, which isn't there for type 8
+    assert_eq!(encoded.messagepack[..6], [0x82, 0xa2, b'a', b't', 0xd8, 0x07]);
+    let untagged = encode_job(json, &ConvertOptions::to_messagepack(), None, &JobToken::default()).unwrap();
+    assert_eq!(untagged.messagepack[4], 0x82);
+
+    let with_ext_types = decoding(None, ConvertOptions::to_json().ext_registry(ext_types));
+    let (_, decoded) = with_ext_types.run(&encoded.messagepack, &JobToken::default()).unwrap().json.unwrap();
+    assert_eq!(decoded.value, json_format.parse(json).unwrap());
+    // Without the registry the ext value can't be decoded at all
+    assert!(decoding(None, ConvertOptions::to_json()).run(&encoded.messagepack, &JobToken::default()).unwrap().json.is_err());
+
+    let bad = r#"{"$ext": 7, "value": {"lat": 1}}"#;
+    assert!(encode_job(bad, &options, None, &JobToken::default()).is_err());
+}
+
+#[test]
+fn test_date_times_that_stay_strings_are_warned_about() {
+    let json = r#"{"at": "2024-05-01T12:30:00+02:00", "leap": "2016-12-31T23:59:60Z"}"#;
+    let options = Settings { encode_timestamps: true, ..Default::default() }.convert_options(Direction::ToMessagePack);
+    let encoded = encode_job(json, &options, None, &JobToken::default()).unwrap();
+    assert_eq!(encoded.messagepack[..6], [0x82, 0xa2, b'a', b't', 0xd6, 0xff]);
+    assert_eq!(encoded.warnings.len(), 1);
+    assert_eq!(encoded.warnings[0].to_string(), "/leap: \"2016-12-31T23:59:60Z\" is left a string, a leap second, which timestamps can't hold");
+    assert!(encode_job(json, &ConvertOptions::to_messagepack(), None, &JobToken::default()).unwrap().warnings.is_empty());
+}
+
+#[test]
+fn test_nested_messagepack_expands_and_collapses_with_the_setting() {
+    // {"body": "gaJpZAc="}, the string being {"id": 7} in base64
+    let envelope = rmp_serde::to_vec(&serde_json::json!({"body": "gaJpZAc="})).unwrap();
+    let settings = Settings { expand_nested: true, ..Default::default() };
+    let output = DecodeJob::of(&settings, None, false).run(&envelope, &JobToken::default()).unwrap();
+    let (json, _) = output.json.unwrap();
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&json).unwrap(), serde_json::json!({"body": {"$nested": {"id": 7}, "$original": "gaJpZAc="}}));
+
+    let encoded = encode_job(&json, &settings.convert_options(Direction::ToMessagePack), None, &JobToken::default()).unwrap();
+    assert_eq!(encoded.messagepack, envelope);
+    // Without the setting the object is just an object
+    let encoded = encode_job(&json, &ConvertOptions::to_messagepack(), None, &JobToken::default()).unwrap();
+    assert_ne!(encoded.messagepack, envelope);
+}
+
+#[test]
+fn test_conversions_carry_a_summary() {
+    let json = r#"{"a": 1}"#;
+    let encoded = encode_job(json, &ConvertOptions::to_messagepack(), None, &JobToken::default()).unwrap();
+    assert!(encoded.summary.direction == Section::JsonToMessagePack);
+    assert_eq!((encoded.summary.input_bytes, encoded.summary.output_bytes), (json.len(), 4));
+
+    let output = decoding(Some(BinaryFormat::MessagePack), ConvertOptions::to_json()).run_on_text("gaFhAQ==", InputEncoding::Auto, &JobToken::default()).unwrap();
+    let summary = output.summary.unwrap();
+    assert!(summary.direction == Section::MessagePackToJson);
+    assert_eq!((summary.input_bytes, summary.output_bytes), (4, "{\n  \"a\": 1\n}".len()));
+
+    // Bytes that don't decode still come back for Explain, but there is nothing to summarize
+    assert!(decoding(Some(BinaryFormat::MessagePack), ConvertOptions::to_json()).run_on_text("c1", InputEncoding::Auto, &JobToken::default()).unwrap().summary.is_none());
+}
+
+#[test]
+fn test_conversion_summary_text() {
+    let mut summary = ConversionSummary {
+        direction: Section::MessagePackToJson,
+        input_bytes: 3_250_000,
+        output_bytes: 812,
+        records: 1,
+        duration: Duration::from_millis(840),
+    };
+    assert_eq!(summary.describe(), "MessagePack → JSON · 3.1 MB → 812 B · 840 ms");
+    summary.records = 12_004;
+    summary.duration = Duration::from_millis(2_350);
+    assert_eq!(summary.describe(), "MessagePack → JSON · 3.1 MB → 812 B · 12,004 records · 2.4 s");
+}
+
+#[test]
+fn test_saved_messagepack_bytes_load_back() {
+    let json = r#"{"name": "Alice", "age": 30}"#;
+    let encoded = encode_job(json, &ConvertOptions::to_messagepack(), None, &JobToken::default()).unwrap();
+    let mut tab = Tab {
+        messagepack_output: general_purpose::STANDARD.encode(&encoded.messagepack),
+        messagepack_bytes: Some(encoded.messagepack.clone()),
+        ..Default::default()
+    };
+    let path = std::env::temp_dir().join(format!("messagepack_to_json_{}_saved.msgpack", std::process::id()));
+    let load_back = |tab: &Tab| {
+        write_file(&path, &tab.save_contents(SaveTarget::MessagePackBytes).unwrap()).unwrap();
+        let Ok(FileInput::Binary(file)) = open_file(&path, FileTarget::MessagePack(Encoding::Base64)) else {
+            panic!("not loaded as MessagePack");
+        };
+        let (decoded, _) = decoding(Some(BinaryFormat::MessagePack), ConvertOptions::to_json()).run(&file.bytes, &JobToken::default()).unwrap().json.unwrap();
+        serde_json::from_str::<serde_json::Value>(&decoded).unwrap()
+    };
+    assert_eq!(load_back(&tab), serde_json::from_str::<serde_json::Value>(json).unwrap());
+
+    // Without the buffer the bytes come from the pane's text
+    tab.messagepack_bytes = None;
+    tab.messagepack_output = hex::encode(&encoded.messagepack);
+    assert_eq!(load_back(&tab), serde_json::from_str::<serde_json::Value>(json).unwrap());
+    assert_eq!(tab.save_contents(SaveTarget::MessagePackText).unwrap(), tab.messagepack_output.as_bytes());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_cancelled_conversions_fail_instead_of_finishing() {
+    let token = JobToken::default();
+    token.cancel();
+    assert!(matches!(encode_job(r#"{"a": 1}"#, &ConvertOptions::to_messagepack(), None, &token), Err(ConvertError::Cancelled)));
+
+    let bytes = general_purpose::STANDARD.decode("g6NhZ2UepGNpdHmqV29uZGVybGFuZKRuYW1lpUFsaWNl").unwrap();
+    assert!(matches!(convert_until(&bytes, &ConvertOptions::to_json(), &token), Err(ConvertError::Cancelled)));
+    assert!(convert_until(&bytes, &ConvertOptions::to_json(), &JobToken::default()).is_ok());
+}
+
+#[test]
+fn test_examples_convert_or_fail_as_advertised() {
+    for example in EXAMPLES {
+        let result = match example.input {
+            ExampleInput::Json(json) => json_to_messagepack(json).map(drop),
+            ExampleInput::MessagePack(hex) => decode_encoded(hex).and_then(|bytes| messagepack_to_json(&bytes)).map(drop),
+        };
+        match (example.outcome, result) {
+            (examples::Outcome::Converts, Ok(_)) => {}
+            (examples::Outcome::Fails(expected), Err(e)) if e.to_string().contains(expected) => {}
+            (outcome, result) => panic!("{}: expected {:?}, got {:?}", example.name, outcome, result),
+        }
+    }
+}
+
+#[test]
+fn test_swap_direction_moves_output_into_the_other_input() {
+    let mut tab = converted_tab();
+    tab.json_output = "{\n  \"a\": 2\n}".to_string();
+    assert!(tab.swap_direction(Section::MessagePackToJson) == Section::JsonToMessagePack);
+    assert_eq!(tab.json_input, "{\n  \"a\": 2\n}");
+    // The output stays put until the next conversion
+    assert_eq!(tab.json_output, tab.json_input);
+
+    tab.messagepack_output = "gaFhAg==".to_string();
+    assert!(tab.swap_direction(Section::JsonToMessagePack) == Section::MessagePackToJson);
+    assert_eq!(tab.messagepack_input, "gaFhAg==");
+}
+
+#[test]
+fn test_a_file_named_on_the_command_line_opens_converted() {
+    let path = std::env::temp_dir().join(format!("messagepack_to_json_{}_argument.json", std::process::id()));
+    write_file(&path, br#"{"a": 1}"#).unwrap();
+    let mut app = MessagePackJsonConverterApp::default();
+    app.settings.auto_convert_examples = false;
+    app.open_argument(&path, &egui::Context::default());
+    std::fs::remove_file(&path).unwrap();
+    let tab = &mut app.tabs[app.active_tab];
+    assert_eq!(tab.json_input, r#"{"a": 1}"#);
+    // A saved MessagePack output is named after it
+    assert_eq!(SaveTarget::MessagePackBytes.default_name(tab.opened_json.as_deref()), path.with_extension("msgpack").display().to_string());
+    let started = Instant::now();
+    while tab.messagepack_output.is_empty() && started.elapsed() < Duration::from_secs(5) {
+        tab.poll_workers(&Settings::default());
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(tab.messagepack_output, "gaFhAQ==");
+
+    // A file that isn't there still opens the window, with the error in the new tab
+    app.open_argument(&path, &egui::Context::default());
+    assert_eq!(app.tabs.len(), 2);
+    assert_eq!(app.tabs[1].error_events.len(), 1);
+    assert_eq!(app.tabs[1].error_events[0].operation, "Open file");
+}
+
+#[test]
+fn test_consecutive_conversions_mark_what_changed() {
+    let ctx = egui::Context::default();
+    let settings = Settings::default();
+    let mut tab = Tab::default();
+    let finish = |tab: &mut Tab| {
+        let started = Instant::now();
+        while (tab.encode_worker.is_running() || tab.decode_worker.is_running()) && started.elapsed() < Duration::from_secs(5) {
+            tab.poll_workers(&settings);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        tab.poll_workers(&settings);
+    };
+
+    tab.json_input = r#"{"a": 1, "b": 2}"#.to_string();
+    tab.start_encoding(&ctx, &settings);
+    finish(&mut tab);
+    assert!(tab.byte_changes.is_none());
+    tab.json_input = r#"{"a": 1, "b": 3}"#.to_string();
+    tab.start_encoding(&ctx, &settings);
+    finish(&mut tab);
+    let (changes, previous) = tab.byte_changes.clone().unwrap();
+    assert_eq!(changes, ByteChanges { differing: 1, first: Some(6) });
+    tab.compare_with_previous();
+    assert!(tab.mode == TabMode::Diff);
+    assert_eq!(decode_encoded(&tab.diff_left).unwrap(), previous);
+    assert_eq!(tab.diff.as_ref().map(Vec::len), Some(1));
+
+    tab.messagepack_input = "gqFhAaFiAg==".to_string();
+    tab.start_decoding(&ctx, &settings);
+    finish(&mut tab);
+    assert!(tab.json_changes.is_empty());
+    tab.messagepack_input = "gqFhAaFiAw==".to_string();
+    tab.start_decoding(&ctx, &settings);
+    finish(&mut tab);
+    let changed: Vec<&str> = tab.json_changes.iter().map(|range| &tab.json_output[range.clone()]).collect();
+    assert_eq!(changed, ["  \"b\": 3"]);
+    tab.clear_pane(Pane::JsonOutput);
+    assert!(tab.json_changes.is_empty());
+}
+
+#[test]
+fn test_input_over_the_size_limit_is_turned_down_before_converting() {
+    assert_eq!(estimated_decoded_len("c0c0c0"), 3);
+    assert_eq!(estimated_decoded_len("wMDA"), 3);
+    let settings = Settings { max_input_mb: 1, ..Settings::default() };
+    let mut tab = Tab { messagepack_input: "c0".repeat(files::MEGABYTE + 1), ..Tab::default() };
+    tab.start_decoding(&egui::Context::default(), &settings);
+    assert!(!tab.decode_worker.is_running());
+    assert_eq!(tab.error_events[0].operation, "Convert to JSON");
+    assert!(tab.error_events[0].message.starts_with("The input is about 2 MB, more than the 1 MB input size limit"), "{}", tab.error_events[0].message);
+
+    tab.json_input = format!("[{}]", "0,".repeat(files::MEGABYTE));
+    tab.start_encoding(&egui::Context::default(), &settings);
+    assert!(!tab.encode_worker.is_running());
+    assert_eq!(tab.error_events[1].operation, "Convert to MessagePack");
+
+    // Just under it goes ahead
+    tab.messagepack_input = "c0".repeat(files::MEGABYTE);
+    tab.start_decoding(&egui::Context::default(), &settings);
+    assert_eq!(tab.error_events.len(), 2);
+}
+
+// A payload of about 100 MB, timed with the Explain view's copy and annotations and without them
+#[cfg(feature = "bench")]
+#[test]
+fn bench_a_large_payload_decodes() {
+    let records: Vec<_> = (0..1_000_000).map(|i| serde_json::json!({"id": i, "name": format!("record {}", i), "tags": ["a", "b", "c"], "score": i as f64 / 7.0, "payload": "x".repeat(40)})).collect();
+    let bytes = BinaryFormat::MessagePack.encode(&serde_json::Value::Array(records)).unwrap();
+    for explain in [true, false] {
+        let started = Instant::now();
+        let decoded = DecodeJob { explain, ..decoding(Some(BinaryFormat::MessagePack), ConvertOptions::to_json()) }.run(&bytes, &JobToken::default()).unwrap();
+        let json_bytes = decoded.json.map_or(0, |(json, _)| json.len());
+        eprintln!("{} MessagePack bytes to {} JSON bytes in {:?}, explained: {}", bytes.len(), json_bytes, started.elapsed(), explain);
+    }
+}
+
+#[test]
+fn test_input_encoding_is_detected_or_picked() {
+    let ctx = egui::Context::default();
+    let settings = Settings::default();
+    let mut tab = Tab { messagepack_input: "gaFhAQ==".to_string(), ..Tab::default() };
+    tab.start_decoding(&ctx, &settings);
+    let started = Instant::now();
+    while tab.decode_worker.is_running() && started.elapsed() < Duration::from_secs(5) {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    tab.poll_workers(&settings);
+    assert_eq!(tab.messagepack_read_as, Some(InputEncoding::Base64));
+    assert_eq!(tab.json_output, "{\n  \"a\": 1\n}");
+
+    // Two nils as hex, which is what Detect reads it as, or three fixints as base64
+    tab.messagepack_input = "c0c0".to_string();
+    assert_eq!(tab.messagepack_input_bytes().unwrap().as_ref(), [0xc0, 0xc0]);
+    tab.messagepack_input_encoding = InputEncoding::Base64;
+    tab.reread_input(&ctx, &settings);
+    assert_eq!(tab.messagepack_read_as, None);
+    assert_eq!(tab.messagepack_input_bytes().unwrap().as_ref(), [0x73, 0x47, 0x34]);
+}
@@ -0,0 +1,709 @@
+use crate::convert::{convert, ConvertOptions, Direction};
+use crate::feed::{FrameLog, ReceivedFrame};
+use crate::format::JsonFormat;
+use crate::locale::{tr, trf};
+use crate::websocket::random_bytes;
+use eframe::egui;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// MQTT 3.1.1 packet types, the high nibble of the first byte
+const CONNECT: u8 = 1;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const PUBACK: u8 = 4;
+const PUBREC: u8 = 5;
+const PUBREL: u8 = 6;
+const PUBCOMP: u8 = 7;
+const SUBSCRIBE: u8 = 8;
+const SUBACK: u8 = 9;
+const PINGREQ: u8 = 12;
+const PINGRESP: u8 = 13;
+const DISCONNECT: u8 = 14;
+
+const DEFAULT_PORT: u16 = 1883;
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+// How long the broker gets to answer CONNECT, and how often the thread looks whether it should stop
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const STOP_CHECK: Duration = Duration::from_millis(100);
+// Reconnects wait twice as long after every failure, up to this
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+// Packets larger than this close the connection instead of being buffered
+const MAX_PACKET: usize = 64 * 1024 * 1024;
+
+// Host and port of an mqtt:// URL. There is no TLS, so mqtts:// is refused.
+pub fn parse_url(url: &str) -> Result<(String, u16), String> {
+    let url = url.trim();
+    let Some(rest) = url.strip_prefix("mqtt://").or_else(|| url.strip_prefix("tcp://")) else {
+        return Err(tr("Only mqtt:// URLs are supported").to_string());
+    };
+    let authority = rest.trim_end_matches('/');
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| trf("Invalid port {}", &[&port]))?),
+        None => (authority, DEFAULT_PORT),
+    };
+    if host.is_empty() {
+        return Err(tr("The URL has no host").to_string());
+    }
+    Ok((host.to_string(), port))
+}
+
+// `+` stands for one level and `#` for any number at the end, but only as whole levels
+pub fn valid_filter(filter: &str) -> bool {
+    let levels: Vec<&str> = filter.split('/').collect();
+    !filter.is_empty()
+        && levels.iter().enumerate().all(|(i, level)| match *level {
+            "#" => i == levels.len() - 1,
+            "+" => true,
+            level => !level.contains(['+', '#']),
+        })
+}
+
+// Whether a topic falls under a subscription filter. Wildcards at the start don't match the
+// broker's own $SYS topics.
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && filter.starts_with(['+', '#']) {
+        return false;
+    }
+    let mut topic_levels = topic.split('/');
+    for level in filter.split('/') {
+        match (level, topic_levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (level, Some(topic_level)) if level == topic_level => {}
+            _ => return false,
+        }
+    }
+    topic_levels.next().is_none()
+}
+
+#[derive(Debug, PartialEq)]
+struct Packet {
+    kind: u8,
+    flags: u8,
+    body: Vec<u8>,
+}
+
+fn encode_packet(kind: u8, flags: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![kind << 4 | flags];
+    // Remaining length, seven bits at a time
+    let mut len = body.len();
+    loop {
+        let byte = (len % 128) as u8;
+        len /= 128;
+        packet.push(if len > 0 { byte | 0x80 } else { byte });
+        if len == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+// The packet at the start of `buffer` and how many bytes it took, None while it's incomplete
+fn parse_packet(buffer: &[u8]) -> io::Result<Option<(Packet, usize)>> {
+    let Some(&first) = buffer.first() else {
+        return Ok(None);
+    };
+    let mut len = 0;
+    for i in 0..4 {
+        let Some(&byte) = buffer.get(1 + i) else {
+            return Ok(None);
+        };
+        len |= ((byte & 0x7f) as usize) << (7 * i);
+        if byte & 0x80 != 0 {
+            continue;
+        }
+        if len > MAX_PACKET {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, trf("Packet of {} bytes is too large", &[&len])));
+        }
+        let start = 2 + i;
+        if buffer.len() < start + len {
+            return Ok(None);
+        }
+        let packet = Packet { kind: first >> 4, flags: first & 0x0f, body: buffer[start..start + len].to_vec() };
+        return Ok(Some((packet, start + len)));
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, tr("Malformed packet length").to_string()))
+}
+
+fn push_string(body: &mut Vec<u8>, text: &str) {
+    body.extend_from_slice(&(text.len() as u16).to_be_bytes());
+    body.extend_from_slice(text.as_bytes());
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> io::Result<u16> {
+    bytes
+        .get(at..at + 2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, tr("Packet cut short").to_string()))
+}
+
+// Always a clean session, so the broker doesn't queue messages for a client that went away
+fn connect_packet(client_id: &str, username: &str, password: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_string(&mut body, "MQTT");
+    body.push(4);
+    let mut flags = 0x02;
+    if !username.is_empty() {
+        flags |= 0x80;
+        if !password.is_empty() {
+            flags |= 0x40;
+        }
+    }
+    body.push(flags);
+    body.extend_from_slice(&(KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+    push_string(&mut body, client_id);
+    if flags & 0x80 != 0 {
+        push_string(&mut body, username);
+    }
+    if flags & 0x40 != 0 {
+        push_string(&mut body, password);
+    }
+    encode_packet(CONNECT, 0, &body)
+}
+
+fn subscribe_packet(packet_id: u16, filter: &str, qos: u8) -> Vec<u8> {
+    let mut body = packet_id.to_be_bytes().to_vec();
+    push_string(&mut body, filter);
+    body.push(qos);
+    encode_packet(SUBSCRIBE, 0x02, &body)
+}
+
+// Published with QoS 0, the broker doesn't confirm it
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_string(&mut body, topic);
+    body.extend_from_slice(payload);
+    encode_packet(PUBLISH, retain as u8, &body)
+}
+
+#[derive(Debug, PartialEq)]
+struct Publish {
+    topic: String,
+    qos: u8,
+    retain: bool,
+    // Only QoS 1 and 2 messages have one
+    packet_id: Option<u16>,
+    payload: Vec<u8>,
+}
+
+fn parse_publish(packet: &Packet) -> io::Result<Publish> {
+    let topic_len = read_u16(&packet.body, 0)? as usize;
+    let topic = packet
+        .body
+        .get(2..2 + topic_len)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, tr("Packet cut short").to_string()))?;
+    let topic = String::from_utf8_lossy(topic).into_owned();
+    let qos = (packet.flags >> 1) & 0x03;
+    let mut start = 2 + topic_len;
+    let packet_id = if qos > 0 {
+        start += 2;
+        Some(read_u16(&packet.body, 2 + topic_len)?)
+    } else {
+        None
+    };
+    Ok(Publish { topic, qos, retain: packet.flags & 0x01 != 0, packet_id, payload: packet.body[start..].to_vec() })
+}
+
+// Why a broker turned the connection down, from the CONNACK return code
+fn refusal(code: u8) -> String {
+    match code {
+        1 => tr("The broker doesn't speak MQTT 3.1.1").to_string(),
+        2 => tr("The broker rejected the client identifier").to_string(),
+        3 => tr("The broker is unavailable").to_string(),
+        4 => tr("Bad user name or password").to_string(),
+        5 => tr("Not authorized").to_string(),
+        code => trf("The broker refused the connection ({})", &[&code]),
+    }
+}
+
+// Collects bytes until they make up a packet. Reads time out, so the caller gets to look up now
+// and then.
+struct PacketReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> PacketReader<R> {
+    fn new(reader: R) -> Self {
+        PacketReader { reader, buffer: Vec::new() }
+    }
+
+    // The next packet, or None when nothing came in before the read timed out
+    fn next(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            if let Some((packet, len)) = parse_packet(&self.buffer)? {
+                self.buffer.drain(..len);
+                return Ok(Some(packet));
+            }
+            let mut chunk = [0; 8192];
+            match self.reader.read(&mut chunk) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, tr("The broker closed the connection").to_string())),
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => return Ok(None),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+enum Status {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected,
+    // Lost the connection, another attempt follows
+    Retrying { reason: String, attempt: u32 },
+    // Why the connection ended for good, such as a refused login
+    Closed(String),
+}
+
+// What the connection thread shares with the window
+#[derive(Default)]
+struct Shared {
+    status: Mutex<Status>,
+    // Write half of the current connection, the window publishes through it too
+    writer: Mutex<Option<TcpStream>>,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn send(&self, packet: &[u8]) -> io::Result<()> {
+        match &mut *self.writer.lock().unwrap() {
+            Some(stream) => stream.write_all(packet),
+            None => Err(io::Error::new(io::ErrorKind::NotConnected, tr("Not connected").to_string())),
+        }
+    }
+
+    fn set_status(&self, status: Status, ctx: &egui::Context) {
+        *self.status.lock().unwrap() = status;
+        ctx.request_repaint();
+    }
+}
+
+// What to connect to and subscribe to, fixed for the life of a connection
+#[derive(Debug, Clone, PartialEq)]
+struct Subscription {
+    host: String,
+    port: u16,
+    username: String,
+    password: String,
+    filter: String,
+    qos: u8,
+}
+
+// Refusals come back as PermissionDenied, trying those again wouldn't help
+fn refused(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, message)
+}
+
+// Connects, subscribes and passes on every message until stopped or the connection fails
+fn run_session(subscription: &Subscription, shared: &Shared, frames: &Sender<ReceivedFrame>, json_format: &JsonFormat, ctx: &egui::Context) -> io::Result<()> {
+    let stream = TcpStream::connect((subscription.host.as_str(), subscription.port))?;
+    stream.set_read_timeout(Some(STOP_CHECK))?;
+    *shared.writer.lock().unwrap() = Some(stream.try_clone()?);
+    let mut reader = PacketReader::new(stream);
+    let client_id = format!("messagepack_to_json-{}", hex::encode(random_bytes::<4>()));
+    shared.send(&connect_packet(&client_id, &subscription.username, &subscription.password))?;
+
+    let started = Instant::now();
+    let connack = loop {
+        if shared.stop.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        match reader.next()? {
+            Some(packet) if packet.kind == CONNACK => break packet,
+            Some(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, tr("The broker didn't answer with CONNACK").to_string())),
+            None if started.elapsed() > CONNECT_TIMEOUT => return Err(io::Error::new(io::ErrorKind::TimedOut, tr("The broker didn't answer").to_string())),
+            None => {}
+        }
+    };
+    match connack.body.get(1) {
+        Some(0) => {}
+        Some(&code) => return Err(refused(refusal(code))),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, tr("Packet cut short").to_string())),
+    }
+    shared.send(&subscribe_packet(1, &subscription.filter, subscription.qos))?;
+
+    let mut last_ping = Instant::now();
+    // When the unanswered ping went out
+    let mut ping_sent: Option<Instant> = None;
+    while !shared.stop.load(Ordering::Relaxed) {
+        let Some(packet) = reader.next()? else {
+            if ping_sent.is_some_and(|sent| sent.elapsed() > KEEP_ALIVE) {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, tr("The broker stopped answering").to_string()));
+            }
+            if last_ping.elapsed() >= KEEP_ALIVE / 2 {
+                shared.send(&encode_packet(PINGREQ, 0, &[]))?;
+                last_ping = Instant::now();
+                ping_sent.get_or_insert(last_ping);
+            }
+            continue;
+        };
+        match packet.kind {
+            SUBACK if packet.body.get(2) == Some(&0x80) => {
+                return Err(refused(trf("The broker refused the subscription to {}", &[&subscription.filter])));
+            }
+            SUBACK => shared.set_status(Status::Connected, ctx),
+            PUBLISH => {
+                let publish = parse_publish(&packet)?;
+                // QoS 2 is passed on right away and not held back until PUBREL, so a message the
+                // broker sends again shows up twice
+                match (publish.qos, publish.packet_id) {
+                    (1, Some(id)) => shared.send(&encode_packet(PUBACK, 0, &id.to_be_bytes()))?,
+                    (2, Some(id)) => shared.send(&encode_packet(PUBREC, 0, &id.to_be_bytes()))?,
+                    _ => {}
+                }
+                let mut frame = ReceivedFrame::binary(&publish.payload, Some(publish.topic), json_format, false);
+                frame.note = Some(if publish.retain { trf("QoS {}, retained", &[&publish.qos]) } else { trf("QoS {}", &[&publish.qos]) });
+                if frames.send(frame).is_err() {
+                    return Ok(());
+                }
+                ctx.request_repaint();
+            }
+            PUBREL => shared.send(&encode_packet(PUBCOMP, 0, &packet.body))?,
+            PINGRESP => ping_sent = None,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Keeps a session going, reconnecting after failures with a growing delay, until stopped or
+// refused
+fn keep_connected(subscription: &Subscription, shared: &Shared, frames: &Sender<ReceivedFrame>, json_format: &JsonFormat, ctx: &egui::Context) {
+    let mut attempt = 0;
+    loop {
+        if attempt == 0 {
+            shared.set_status(Status::Connecting, ctx);
+        }
+        let result = run_session(subscription, shared, frames, json_format, ctx);
+        if let Some(stream) = shared.writer.lock().unwrap().take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        let reason = match result {
+            _ if shared.stop.load(Ordering::Relaxed) => break,
+            Ok(()) => break,
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                shared.set_status(Status::Closed(e.to_string()), ctx);
+                return;
+            }
+            Err(e) => e.to_string(),
+        };
+        // Having been connected starts the count over
+        if *shared.status.lock().unwrap() == Status::Connected {
+            attempt = 0;
+        }
+        attempt += 1;
+        shared.set_status(Status::Retrying { reason, attempt }, ctx);
+        let delay = (Duration::from_secs(1) * 2u32.pow(attempt.min(6) - 1)).min(MAX_RETRY_DELAY);
+        let retry_at = Instant::now() + delay;
+        while Instant::now() < retry_at {
+            if shared.stop.load(Ordering::Relaxed) {
+                shared.set_status(Status::Disconnected, ctx);
+                return;
+            }
+            thread::sleep(STOP_CHECK);
+        }
+    }
+    shared.set_status(Status::Disconnected, ctx);
+}
+
+pub struct MqttState {
+    pub open: bool,
+    url: String,
+    username: String,
+    password: String,
+    filter: String,
+    qos: u8,
+    shared: Arc<Shared>,
+    frames: Option<Receiver<ReceivedFrame>>,
+    log: FrameLog,
+    // Narrows the list to matching topics, empty shows everything
+    view_filter: String,
+    publish_topic: String,
+    retain: bool,
+    // What the last Publish click did
+    published: Option<Result<String, String>>,
+}
+
+impl Default for MqttState {
+    fn default() -> Self {
+        MqttState {
+            open: false,
+            url: "mqtt://127.0.0.1:1883".to_string(),
+            username: String::new(),
+            password: String::new(),
+            filter: "#".to_string(),
+            qos: 0,
+            shared: Arc::default(),
+            frames: None,
+            log: FrameLog::default(),
+            view_filter: String::new(),
+            publish_topic: String::new(),
+            retain: false,
+            published: None,
+        }
+    }
+}
+
+impl MqttState {
+    fn connect(&mut self, ctx: &egui::Context, json_format: &JsonFormat) {
+        self.disconnect();
+        // A fresh one, so the old connection's thread can't report into this one
+        self.shared = Arc::new(Shared::default());
+        let (host, port) = match parse_url(&self.url) {
+            Ok(parts) => parts,
+            Err(e) => {
+                *self.shared.status.lock().unwrap() = Status::Closed(e);
+                return;
+            }
+        };
+        if !valid_filter(&self.filter) {
+            *self.shared.status.lock().unwrap() = Status::Closed(trf("Invalid topic filter {}", &[&self.filter]));
+            return;
+        }
+        let subscription = Subscription {
+            host,
+            port,
+            username: self.username.clone(),
+            password: self.password.clone(),
+            filter: self.filter.clone(),
+            qos: self.qos,
+        };
+        *self.shared.status.lock().unwrap() = Status::Connecting;
+        let (sender, receiver) = mpsc::channel();
+        self.frames = Some(receiver);
+        let shared = self.shared.clone();
+        let json_format = *json_format;
+        let ctx = ctx.clone();
+        thread::spawn(move || keep_connected(&subscription, &shared, &sender, &json_format, &ctx));
+    }
+
+    // Says goodbye with DISCONNECT, then shuts the socket. The thread notices within STOP_CHECK.
+    pub fn disconnect(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        if let Some(mut stream) = self.shared.writer.lock().unwrap().take() {
+            let _ = stream.write_all(&encode_packet(DISCONNECT, 0, &[]));
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        let mut status = self.shared.status.lock().unwrap();
+        if matches!(*status, Status::Connecting | Status::Connected | Status::Retrying { .. }) {
+            *status = Status::Disconnected;
+        }
+    }
+
+    fn publish(&mut self, json_input: &str, json_format: &JsonFormat) {
+        let options = ConvertOptions { direction: Direction::ToMessagePack, json_format: *json_format, ..Default::default() };
+        self.published = Some(
+            convert(json_input.as_bytes(), &options)
+                .map(|(payload, _)| payload)
+                .map_err(String::from)
+                .and_then(|payload| {
+                    self.shared.send(&publish_packet(&self.publish_topic, &payload, self.retain)).map_err(|e| e.to_string())?;
+                    Ok(trf("Published {} bytes to {}", &[&payload.len(), &self.publish_topic]))
+                }),
+        );
+    }
+}
+
+impl Drop for MqttState {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+// Subscribes to an MQTT broker and lists every message, decoded, and publishes the JSON input
+pub fn mqtt_window(ctx: &egui::Context, state: &mut MqttState, json_format: &JsonFormat, json_input: &str) {
+    if let Some(frames) = &state.frames {
+        state.log.take(frames.try_iter());
+    }
+    let mut open = state.open;
+    egui::Window::new(tr("MQTT"))
+        .id(egui::Id::new("mqtt"))
+        .open(&mut open)
+        .default_width(560.0)
+        .show(ctx, |ui| {
+            let status = state.shared.status.lock().unwrap().clone();
+            let connected = matches!(status, Status::Connecting | Status::Connected | Status::Retrying { .. });
+            ui.add_enabled_ui(!connected, |ui| {
+                egui::Grid::new("mqtt_options").num_columns(2).show(ui, |ui| {
+                    ui.label(tr("Broker:"));
+                    ui.add(egui::TextEdit::singleline(&mut state.url).desired_width(280.0));
+                    ui.end_row();
+                    ui.label(tr("User name:"));
+                    ui.add(egui::TextEdit::singleline(&mut state.username).desired_width(160.0));
+                    ui.end_row();
+                    ui.label(tr("Password:"));
+                    ui.add(egui::TextEdit::singleline(&mut state.password).password(true).desired_width(160.0));
+                    ui.end_row();
+                    ui.label(tr("Topic filter:"));
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut state.filter).desired_width(200.0))
+                            .on_hover_text(tr("+ matches one level, # any number of levels at the end"));
+                        for qos in 0..=2 {
+                            ui.radio_value(&mut state.qos, qos, trf("QoS {}", &[&qos]));
+                        }
+                    });
+                    ui.end_row();
+                });
+            });
+            ui.horizontal(|ui| {
+                if connected {
+                    if ui.button(tr("Disconnect")).clicked() {
+                        state.disconnect();
+                    }
+                } else if ui.button(tr("Connect")).clicked() {
+                    state.connect(ctx, json_format);
+                }
+                match &status {
+                    Status::Disconnected => ui.weak(tr("Not connected")),
+                    Status::Connecting => ui.label(tr("Connecting…")),
+                    Status::Connected => ui.label(tr("Connected")),
+                    Status::Retrying { reason, attempt } => {
+                        ui.colored_label(ui.visuals().warn_fg_color, trf("Reconnecting (attempt {}): {}", &[attempt, reason]))
+                    }
+                    Status::Closed(reason) => ui.colored_label(ui.visuals().error_fg_color, trf("Connection closed: {}", &[reason])),
+                };
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(tr("Topic:"));
+                ui.add(egui::TextEdit::singleline(&mut state.publish_topic).desired_width(200.0));
+                ui.checkbox(&mut state.retain, tr("Retain"));
+                let can_publish = status == Status::Connected && !state.publish_topic.is_empty() && !state.publish_topic.contains(['+', '#']);
+                if ui.add_enabled(can_publish, egui::Button::new(tr("Publish JSON input"))).on_hover_text(tr("Encode the JSON input as MessagePack and publish it")).clicked() {
+                    state.publish(json_input, json_format);
+                }
+            });
+            match &state.published {
+                Some(Ok(message)) => {
+                    ui.weak(message);
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                None => {}
+            }
+            ui.separator();
+            state.log.controls(ui);
+            ui.horizontal(|ui| {
+                ui.label(tr("Show topics:"));
+                ui.add(egui::TextEdit::singleline(&mut state.view_filter).hint_text("#").desired_width(200.0));
+                if !state.view_filter.is_empty() && !valid_filter(&state.view_filter) {
+                    ui.colored_label(ui.visuals().error_fg_color, tr("Invalid filter"));
+                }
+            });
+            let view_filter = state.view_filter.trim();
+            if view_filter.is_empty() || !valid_filter(view_filter) {
+                state.log.show(ui);
+            } else {
+                state.log.show_where(ui, |frame| frame.source.as_deref().is_some_and(|topic| topic_matches(view_filter, topic)));
+            }
+        });
+    if state.open && !open {
+        state.disconnect();
+    }
+    state.open = open;
+}
+
+
+/* Tests */
+#[test]
+fn test_topic_filters() {
+    assert!(topic_matches("sensors/+/temp", "sensors/a/temp"));
+    assert!(!topic_matches("sensors/+/temp", "sensors/a/b/temp"));
+    assert!(topic_matches("sensors/#", "sensors"));
+    assert!(topic_matches("sensors/#", "sensors/a/b"));
+    assert!(!topic_matches("sensors", "sensors/a"));
+    assert!(!topic_matches("#", "$SYS/uptime"));
+    assert!(valid_filter("a/+/#"));
+    assert!(!valid_filter("a/#/b"));
+    assert!(!valid_filter("a/b+"));
+}
+
+#[test]
+fn test_packets_round_trip() {
+    // 200 bytes of body take two bytes of remaining length
+    let packet = publish_packet("t", &[0x42; 197], true);
+    assert_eq!(packet[..3], [0x31, 0xc8, 0x01]);
+    assert_eq!(parse_packet(&packet[..100]).unwrap(), None);
+    let (parsed, len) = parse_packet(&packet).unwrap().unwrap();
+    assert_eq!(len, packet.len());
+    let publish = parse_publish(&parsed).unwrap();
+    assert_eq!(publish, Publish { topic: "t".to_string(), qos: 0, retain: true, packet_id: None, payload: vec![0x42; 197] });
+    assert!(parse_packet(&[0x30, 0xff, 0xff, 0xff, 0xff, 0x01]).is_err());
+}
+
+#[test]
+fn test_session_with_a_broker() {
+    use crate::feed::FrameContent;
+    use std::net::TcpListener;
+    let broker = TcpListener::bind("127.0.0.1:0").unwrap();
+    let subscription = Subscription {
+        host: "127.0.0.1".to_string(),
+        port: broker.local_addr().unwrap().port(),
+        username: "user".to_string(),
+        password: String::new(),
+        filter: "sensors/#".to_string(),
+        qos: 1,
+    };
+    let shared = Arc::new(Shared::default());
+    let (sender, frames) = mpsc::channel();
+    let session_shared = shared.clone();
+    let session = thread::spawn(move || run_session(&subscription, &session_shared, &sender, &JsonFormat::default(), &egui::Context::default()));
+
+    let (stream, _) = broker.accept().unwrap();
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = PacketReader::new(stream);
+    let connect = reader.next().unwrap().unwrap();
+    assert_eq!((connect.kind, connect.body[7]), (CONNECT, 0x82));
+    writer.write_all(&encode_packet(CONNACK, 0, &[0, 0])).unwrap();
+    let subscribe = reader.next().unwrap().unwrap();
+    assert_eq!(subscribe, Packet { kind: SUBSCRIBE, flags: 0x02, body: [&[0, 1, 0, 9][..], b"sensors/#", &[1]].concat() });
+    writer.write_all(&encode_packet(SUBACK, 0, &[0, 1, 1])).unwrap();
+
+    // QoS 1 with packet id 7
+    let body = [&[0, 9][..], b"sensors/a", &[0, 7], &[0x81, 0xa1, 0x74, 0x15]].concat();
+    writer.write_all(&encode_packet(PUBLISH, 0x02, &body)).unwrap();
+    assert_eq!(reader.next().unwrap().unwrap(), Packet { kind: PUBACK, flags: 0, body: vec![0, 7] });
+    let frame = frames.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!((frame.source.as_deref(), frame.note.as_deref()), (Some("sensors/a"), Some("QoS 1")));
+    assert_eq!(frame.content, FrameContent::Json("{\n  \"t\": 21\n}".to_string()));
+    assert_eq!(*shared.status.lock().unwrap(), Status::Connected);
+
+    shared.stop.store(true, Ordering::Relaxed);
+    session.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_refused_logins_are_not_retried() {
+    use std::net::TcpListener;
+    let broker = TcpListener::bind("127.0.0.1:0").unwrap();
+    let subscription = Subscription {
+        host: "127.0.0.1".to_string(),
+        port: broker.local_addr().unwrap().port(),
+        username: String::new(),
+        password: String::new(),
+        filter: "#".to_string(),
+        qos: 0,
+    };
+    let shared = Arc::new(Shared::default());
+    let thread_shared = shared.clone();
+    let (sender, _frames) = mpsc::channel();
+    let client = thread::spawn(move || keep_connected(&subscription, &thread_shared, &sender, &JsonFormat::default(), &egui::Context::default()));
+    let (stream, _) = broker.accept().unwrap();
+    let mut writer = stream.try_clone().unwrap();
+    PacketReader::new(stream).next().unwrap().unwrap();
+    writer.write_all(&encode_packet(CONNACK, 0, &[0, 4])).unwrap();
+    client.join().unwrap();
+    assert_eq!(*shared.status.lock().unwrap(), Status::Closed("Bad user name or password".to_string()));
+}
@@ -0,0 +1,288 @@
+use crate::locale::{tr, trf};
+use rmp::Marker;
+use std::fmt;
+use std::ops::Range;
+
+// One MessagePack marker together with its length/value bytes. Containers only cover their
+// header, their children follow as separate tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub marker: Marker,
+    pub start: usize,
+    pub end: usize,
+    pub kind: TokenKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Nil,
+    Bool(bool),
+    Uint(u64),
+    Int(i64),
+    F32(f32),
+    F64(f64),
+    // Byte ranges of the payload, not including the header
+    Str(Range<usize>),
+    Bin(Range<usize>),
+    Ext(i8, Range<usize>),
+    Array(usize),
+    Map(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&trf("{} at offset {}", &[&self.message, &format_args!("{:#x}", self.offset)]))
+    }
+}
+
+pub fn read_token(bytes: &[u8], start: usize) -> Result<Token, DecodeError> {
+    let mut reader = Reader { bytes, start, position: start };
+    let marker = Marker::from_u8(reader.take(1)?[0]);
+    let kind = match marker {
+        Marker::FixPos(n) => TokenKind::Uint(n as u64),
+        Marker::FixNeg(n) => TokenKind::Int(n as i64),
+        Marker::Null => TokenKind::Nil,
+        Marker::True => TokenKind::Bool(true),
+        Marker::False => TokenKind::Bool(false),
+        Marker::U8 => TokenKind::Uint(reader.uint(1)?),
+        Marker::U16 => TokenKind::Uint(reader.uint(2)?),
+        Marker::U32 => TokenKind::Uint(reader.uint(4)?),
+        Marker::U64 => TokenKind::Uint(reader.uint(8)?),
+        Marker::I8 => TokenKind::Int(reader.uint(1)? as u8 as i8 as i64),
+        Marker::I16 => TokenKind::Int(reader.uint(2)? as u16 as i16 as i64),
+        Marker::I32 => TokenKind::Int(reader.uint(4)? as u32 as i32 as i64),
+        Marker::I64 => TokenKind::Int(reader.uint(8)? as i64),
+        Marker::F32 => TokenKind::F32(f32::from_bits(reader.uint(4)? as u32)),
+        Marker::F64 => TokenKind::F64(f64::from_bits(reader.uint(8)?)),
+        Marker::FixStr(n) => TokenKind::Str(reader.payload(n as usize)?),
+        Marker::Str8 => TokenKind::Str(reader.sized_payload(1)?),
+        Marker::Str16 => TokenKind::Str(reader.sized_payload(2)?),
+        Marker::Str32 => TokenKind::Str(reader.sized_payload(4)?),
+        Marker::Bin8 => TokenKind::Bin(reader.sized_payload(1)?),
+        Marker::Bin16 => TokenKind::Bin(reader.sized_payload(2)?),
+        Marker::Bin32 => TokenKind::Bin(reader.sized_payload(4)?),
+        Marker::FixArray(n) => TokenKind::Array(n as usize),
+        Marker::Array16 => TokenKind::Array(reader.uint(2)? as usize),
+        Marker::Array32 => TokenKind::Array(reader.uint(4)? as usize),
+        Marker::FixMap(n) => TokenKind::Map(n as usize),
+        Marker::Map16 => TokenKind::Map(reader.uint(2)? as usize),
+        Marker::Map32 => TokenKind::Map(reader.uint(4)? as usize),
+        Marker::FixExt1 => reader.ext(1)?,
+        Marker::FixExt2 => reader.ext(2)?,
+        Marker::FixExt4 => reader.ext(4)?,
+        Marker::FixExt8 => reader.ext(8)?,
+        Marker::FixExt16 => reader.ext(16)?,
+        Marker::Ext8 | Marker::Ext16 | Marker::Ext32 => {
+            let width = match marker {
+                Marker::Ext8 => 1,
+                Marker::Ext16 => 2,
+                _ => 4,
+            };
+            let len = reader.uint(width)? as usize;
+            reader.ext(len)?
+        }
+        Marker::Reserved => {
+            return Err(DecodeError { offset: start, message: tr("Reserved marker 0xc1").to_string() });
+        }
+    };
+    Ok(Token { marker, start, end: reader.position, kind })
+}
+
+// Visits every token in order together with its nesting depth. Back-to-back top level values
+// are walked one after another, on corrupt input every token before the failing offset is visited.
+pub fn walk(bytes: &[u8], mut visit: impl FnMut(&Token, usize)) -> Result<(), DecodeError> {
+    // Items still expected by each open container
+    let mut open: Vec<usize> = Vec::new();
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let token = read_token(bytes, offset)?;
+        visit(&token, open.len());
+        offset = token.end;
+
+        if let Some(remaining) = open.last_mut() {
+            *remaining -= 1;
+        }
+        match token.kind {
+            TokenKind::Array(n) if n > 0 => open.push(n),
+            TokenKind::Map(n) if n > 0 => open.push(2 * n),
+            _ => {}
+        }
+        while open.last() == Some(&0) {
+            open.pop();
+        }
+    }
+
+    if !open.is_empty() {
+        let missing: usize = open.iter().sum();
+        return Err(DecodeError {
+            offset,
+            message: trf("Unexpected end of input: {} more items expected", &[&missing]),
+        });
+    }
+    Ok(())
+}
+
+// Where the value starting at `start` ends, or None while `bytes` stops before all of it is
+// there. Only headers are read, so asking again once more bytes arrived is cheap.
+pub fn value_end(bytes: &[u8], start: usize) -> Result<Option<usize>, DecodeError> {
+    // Values still to come before the one at `start` is complete
+    let mut remaining: usize = 1;
+    let mut offset = start;
+    while remaining > 0 {
+        if offset >= bytes.len() {
+            return Ok(None);
+        }
+        let token = match read_token(bytes, offset) {
+            Ok(token) => token,
+            Err(e) if Marker::from_u8(bytes[offset]) == Marker::Reserved => return Err(e),
+            // Anything else read_token rejects is a token cut short
+            Err(_) => return Ok(None),
+        };
+        offset = token.end;
+        remaining -= 1;
+        match token.kind {
+            TokenKind::Array(n) => remaining = remaining.saturating_add(n),
+            TokenKind::Map(n) => remaining = remaining.saturating_add(n.saturating_mul(2)),
+            _ => {}
+        }
+    }
+    Ok(Some(offset))
+}
+
+pub fn marker_name(marker: Marker) -> &'static str {
+    match marker {
+        Marker::FixPos(_) => "positive fixint",
+        Marker::FixNeg(_) => "negative fixint",
+        Marker::FixMap(_) => "fixmap",
+        Marker::FixArray(_) => "fixarray",
+        Marker::FixStr(_) => "fixstr",
+        Marker::Null => "nil",
+        Marker::Reserved => "reserved",
+        Marker::False => "false",
+        Marker::True => "true",
+        Marker::Bin8 => "bin8",
+        Marker::Bin16 => "bin16",
+        Marker::Bin32 => "bin32",
+        Marker::Ext8 => "ext8",
+        Marker::Ext16 => "ext16",
+        Marker::Ext32 => "ext32",
+        Marker::F32 => "float32",
+        Marker::F64 => "float64",
+        Marker::U8 => "uint8",
+        Marker::U16 => "uint16",
+        Marker::U32 => "uint32",
+        Marker::U64 => "uint64",
+        Marker::I8 => "int8",
+        Marker::I16 => "int16",
+        Marker::I32 => "int32",
+        Marker::I64 => "int64",
+        Marker::FixExt1 => "fixext1",
+        Marker::FixExt2 => "fixext2",
+        Marker::FixExt4 => "fixext4",
+        Marker::FixExt8 => "fixext8",
+        Marker::FixExt16 => "fixext16",
+        Marker::Str8 => "str8",
+        Marker::Str16 => "str16",
+        Marker::Str32 => "str32",
+        Marker::Array16 => "array16",
+        Marker::Array32 => "array32",
+        Marker::Map16 => "map16",
+        Marker::Map32 => "map32",
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    start: usize,
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self.position.checked_add(len).filter(|&end| end <= self.bytes.len());
+        match end {
+            Some(end) => {
+                let taken = &self.bytes[self.position..end];
+                self.position = end;
+                Ok(taken)
+            }
+            None => Err(DecodeError {
+                offset: self.start,
+                message: trf(
+                    "Unexpected end of input: needed {} more bytes but only {} remain",
+                    &[&len, &(self.bytes.len() - self.position)],
+                ),
+            }),
+        }
+    }
+
+    // Big-endian unsigned integer of the given width
+    fn uint(&mut self, width: usize) -> Result<u64, DecodeError> {
+        Ok(self.take(width)?.iter().fold(0, |acc, &b| (acc << 8) | b as u64))
+    }
+
+    fn payload(&mut self, len: usize) -> Result<Range<usize>, DecodeError> {
+        let start = self.position;
+        self.take(len)?;
+        Ok(start..self.position)
+    }
+
+    fn sized_payload(&mut self, width: usize) -> Result<Range<usize>, DecodeError> {
+        let len = self.uint(width)? as usize;
+        self.payload(len)
+    }
+
+    fn ext(&mut self, len: usize) -> Result<TokenKind, DecodeError> {
+        let ext_type = self.take(1)?[0] as i8;
+        let data = self.payload(len)?;
+        Ok(TokenKind::Ext(ext_type, data))
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_read_token_scalars() {
+    assert_eq!(read_token(&[0x1e], 0).unwrap().kind, TokenKind::Uint(30));
+    assert_eq!(read_token(&[0xff], 0).unwrap().kind, TokenKind::Int(-1));
+    assert_eq!(read_token(&[0xcd, 0x01, 0x00], 0).unwrap().kind, TokenKind::Uint(256));
+    assert_eq!(read_token(&[0xd1, 0xff, 0x00], 0).unwrap().kind, TokenKind::Int(-256));
+    assert_eq!(read_token(&[0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0], 0).unwrap().kind, TokenKind::F64(1.5));
+    assert_eq!(read_token(&[0xc3], 0).unwrap().kind, TokenKind::Bool(true));
+}
+
+#[test]
+fn test_read_token_payload_ranges() {
+    let token = read_token(&[0x00, 0xa3, b'a', b'g', b'e'], 1).unwrap();
+    assert_eq!((token.start, token.end), (1, 5));
+    assert_eq!(token.kind, TokenKind::Str(2..5));
+
+    let token = read_token(&[0xd6, 0xff, 0, 0, 0, 1], 0).unwrap();
+    assert_eq!(token.kind, TokenKind::Ext(-1, 2..6));
+}
+
+#[test]
+fn test_read_token_truncated_input() {
+    let err = read_token(&[0x00, 0xa5, b'a'], 1).unwrap_err();
+    assert_eq!(err.offset, 1);
+    assert!(read_token(&[0xc1], 0).is_err());
+}
+
+#[test]
+fn test_value_end_waits_for_the_whole_value() {
+    // {"a": [1, 2]} then nil
+    let bytes = [0x81, 0xa1, b'a', 0x92, 0x01, 0x02, 0xc0];
+    assert_eq!(value_end(&bytes, 0), Ok(Some(6)));
+    assert_eq!(value_end(&bytes, 6), Ok(Some(7)));
+    for cut in 0..6 {
+        assert_eq!(value_end(&bytes[..cut], 0), Ok(None));
+    }
+    assert!(value_end(&[0x91, 0xc1], 0).is_err());
+}
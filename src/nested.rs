@@ -0,0 +1,157 @@
+use crate::convert::ConvertOptions;
+use crate::decode::decode_value_with;
+use crate::error::ConvertError;
+use crate::ext_types::WithExtTypes;
+use base64::{engine::general_purpose, Engine};
+use serde_json::{Map, Value};
+
+// MessagePack that a string field carries as base64 or hex text, which services put inside an
+// envelope of their own. Expanded, such a string becomes
+// {"$nested": <the decoded value>, "$original": "<the string>"}, and collapsing turns that back
+// into a string in the same text form.
+
+pub const NESTED_KEY: &str = "$nested";
+pub const ORIGINAL_KEY: &str = "$original";
+
+// Shorter strings are words and numbers far more often than payloads
+const MIN_TEXT_LEN: usize = 8;
+
+// How the bytes were written in the string
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TextForm {
+    Hex { upper: bool },
+    Base64,
+}
+
+impl TextForm {
+    fn write(self, bytes: &[u8]) -> String {
+        match self {
+            TextForm::Hex { upper: true } => hex::encode_upper(bytes),
+            TextForm::Hex { upper: false } => hex::encode(bytes),
+            TextForm::Base64 => general_purpose::STANDARD.encode(bytes),
+        }
+    }
+}
+
+// The value a string is the MessagePack of, and how it was written. Only strings that decode
+// whole, with nothing after the value, to a map or an array count: every byte is the start of
+// some MessagePack value, so a lone scalar says nothing about the string being one. The ext types
+// and the bin and key forms come from `strict`, which doesn't decode lossily.
+fn decode_text(text: &str, strict: &ConvertOptions) -> Option<(Value, TextForm)> {
+    if text.len() < MIN_TEXT_LEN {
+        return None;
+    }
+    let hex = text.len().is_multiple_of(2) && text.bytes().all(|byte| byte.is_ascii_hexdigit());
+    let (bytes, form) = match hex {
+        true => (hex::decode(text).ok()?, TextForm::Hex { upper: text.bytes().any(|byte| byte.is_ascii_uppercase()) }),
+        false => (general_purpose::STANDARD.decode(text).ok()?, TextForm::Base64),
+    };
+    match decode_value_with(&bytes, 0, strict, &mut Vec::new()) {
+        Ok((value @ (Value::Array(_) | Value::Object(_)), end)) if end == bytes.len() => Some((value, form)),
+        _ => None,
+    }
+}
+
+// Every string in `value` that holds MessagePack expanded in place, and the strings nested in
+// those in turn. Returns how many were. The strings are decoded strictly whatever `options` say.
+pub fn expand(value: &mut Value, options: &ConvertOptions) -> usize {
+    expand_strictly(value, &options.clone().lossy(false))
+}
+
+fn expand_strictly(value: &mut Value, strict: &ConvertOptions) -> usize {
+    match value {
+        Value::String(text) => {
+            let Some((mut nested, _)) = decode_text(text, strict) else {
+                return 0;
+            };
+            let inner = expand_strictly(&mut nested, strict);
+            let original = Value::String(std::mem::take(text));
+            *value = Value::Object(Map::from_iter([(NESTED_KEY.to_string(), nested), (ORIGINAL_KEY.to_string(), original)]));
+            inner + 1
+        }
+        Value::Array(items) => items.iter_mut().map(|item| expand_strictly(item, strict)).sum(),
+        Value::Object(object) => object.values_mut().map(|item| expand_strictly(item, strict)).sum(),
+        _ => 0,
+    }
+}
+
+// Every expanded string in `value` back to a string, innermost first. One whose $nested was left
+// as it was decoded is its $original again byte for byte, an edited one is encoded anew in the
+// text form of its $original.
+pub fn collapse(value: &mut Value, options: &ConvertOptions) -> Result<(), ConvertError> {
+    collapse_strictly(value, &options.clone().lossy(false))
+}
+
+fn collapse_strictly(value: &mut Value, strict: &ConvertOptions) -> Result<(), ConvertError> {
+    match value {
+        Value::Array(items) => items.iter_mut().try_for_each(|item| collapse_strictly(item, strict)),
+        Value::Object(object) => {
+            object.values_mut().try_for_each(|item| collapse_strictly(item, strict))?;
+            let (Some(nested), Some(Value::String(original)), 2) = (object.get(NESTED_KEY), object.get(ORIGINAL_KEY), object.len()) else {
+                return Ok(());
+            };
+            let collapsed = match decode_text(original, strict) {
+                Some((decoded, _)) if decoded == *nested => original.clone(),
+                decoded => {
+                    let form = decoded.map_or(TextForm::Base64, |(_, form)| form);
+                    let bytes = rmp_serde::to_vec(&WithExtTypes::new(nested, &strict.ext_registry).bin_form(strict.bin_form).key_form(strict.key_form));
+                    form.write(&bytes.map_err(|e| ConvertError::SerializeMessagePack(e.to_string()))?)
+                }
+            };
+            *value = Value::String(collapsed);
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_only_whole_encoded_containers_are_expanded() {
+    let options = ConvertOptions::default();
+    // {"id": 7} as base64, and [1, 2, 3] as hex
+    let mut value = serde_json::json!({"inner": "gaJpZAc=", "list": ["93010203"], "name": "gaJpZAc"});
+    assert_eq!(expand(&mut value, &options), 2);
+    assert_eq!(value, serde_json::json!({
+        "inner": {"$nested": {"id": 7}, "$original": "gaJpZAc="},
+        "list": [{"$nested": [1, 2, 3], "$original": "93010203"}],
+        "name": "gaJpZAc",
+    }));
+
+    // Text that decodes but isn't a container on its own: a scalar, trailing bytes, too short,
+    // a value cut off, or just words and numbers
+    for text in ["AAAAAAAAAAA=", "930102030405", "910101", "93010203040", "9301", "deadbeef", "12345678", "password", "hello world"] {
+        let mut value = Value::String(text.to_string());
+        assert_eq!(expand(&mut value, &options), 0, "{}", text);
+    }
+}
+
+#[test]
+fn test_nested_payloads_collapse_back_to_their_strings() {
+    let options = ConvertOptions::default();
+    // A base64 envelope holding {"id": 7} as upper case hex, in an array
+    let inner = hex::encode_upper([0x81, 0xa2, b'i', b'd', 0x07]);
+    let outer = general_purpose::STANDARD.encode(rmp_serde::to_vec(&serde_json::json!([inner, 1])).unwrap());
+    let mut value = serde_json::json!({"payload": outer});
+    assert_eq!(expand(&mut value, &options), 2);
+    assert_eq!(value["payload"]["$nested"][0]["$nested"], serde_json::json!({"id": 7}));
+    let expanded = value.clone();
+    collapse(&mut value, &options).unwrap();
+    assert_eq!(value, serde_json::json!({"payload": outer}));
+
+    // Edited, the inner string stays upper case hex and the outer one base64
+    let mut edited = expanded;
+    edited["payload"]["$nested"][0]["$nested"]["id"] = serde_json::json!(8);
+    collapse(&mut edited, &options).unwrap();
+    let mut reexpanded = edited.clone();
+    expand(&mut reexpanded, &options);
+    assert_eq!(reexpanded["payload"]["$nested"][0], serde_json::json!({"$nested": {"id": 8}, "$original": "81A2696408"}));
+    assert_ne!(edited, serde_json::json!({"payload": outer}));
+
+    // Objects that only look a bit like expanded strings are left alone
+    let mut other = serde_json::json!({"a": {"$nested": [1], "$original": 1}, "b": {"$nested": [1], "$original": "x", "c": 0}});
+    let unchanged = other.clone();
+    collapse(&mut other, &options).unwrap();
+    assert_eq!(other, unchanged);
+}
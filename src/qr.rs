@@ -0,0 +1,767 @@
+use crate::find::text_fingerprint;
+use crate::locale::{tr, trf};
+use eframe::egui;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// QR Code Model 2 encoder for byte mode data, after ISO/IEC 18004 and Project Nayuki's reference
+// implementation. There is no QR crate to lean on here.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum EcLevel {
+    Low,
+    #[default]
+    Medium,
+    Quartile,
+    High,
+}
+
+impl EcLevel {
+    pub const ALL: [EcLevel; 4] = [EcLevel::Low, EcLevel::Medium, EcLevel::Quartile, EcLevel::High];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EcLevel::Low => "L (7%)",
+            EcLevel::Medium => "M (15%)",
+            EcLevel::Quartile => "Q (25%)",
+            EcLevel::High => "H (30%)",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    // The two bits the format information uses for this level
+    fn format_bits(self) -> u32 {
+        match self {
+            EcLevel::Low => 1,
+            EcLevel::Medium => 0,
+            EcLevel::Quartile => 3,
+            EcLevel::High => 2,
+        }
+    }
+}
+
+pub const MIN_VERSION: usize = 1;
+pub const MAX_VERSION: usize = 40;
+// Longer sequences are refused rather than making the user page through hundreds of codes
+pub const MAX_SEQUENCE_CODES: usize = 100;
+
+// Indexed by level, then version (index 0 unused)
+const ECC_CODEWORDS_PER_BLOCK: [[usize; 41]; 4] = [
+    [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28],
+    [0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30, 30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+];
+
+const NUM_ERROR_CORRECTION_BLOCKS: [[usize; 41]; 4] = [
+    [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25],
+    [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49],
+    [0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29, 34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68],
+    [0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32, 35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81],
+];
+
+#[derive(Debug)]
+pub struct QrCode {
+    pub version: usize,
+    pub size: usize,
+    // Row-major, true for dark modules
+    modules: Vec<bool>,
+    // Finder, timing, alignment, format and version modules, which masks and data skip
+    is_function: Vec<bool>,
+}
+
+impl QrCode {
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    // The smallest version from MIN_VERSION up to `max_version` that holds the data
+    pub fn encode(data: &[u8], level: EcLevel, max_version: usize) -> Result<QrCode, String> {
+        let max_version = max_version.clamp(MIN_VERSION, MAX_VERSION);
+        let version = (MIN_VERSION..=max_version)
+            .find(|&version| data.len() <= byte_capacity(version, level))
+            .ok_or_else(|| {
+                trf(
+                    "Failed to make a QR code: {} bytes don't fit in version {} at level {}, which holds {}",
+                    &[&data.len(), &max_version, &level.name(), &byte_capacity(max_version, level)],
+                )
+            })?;
+
+        let size = version * 4 + 17;
+        let mut code = QrCode { version, size, modules: vec![false; size * size], is_function: vec![false; size * size] };
+        code.draw_function_patterns(level);
+        code.draw_codewords(&interleave_with_ecc(&padded_codewords(data, version, level), version, level));
+
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                code.apply_mask(mask);
+                code.draw_format_bits(level, mask);
+                let penalty = code.penalty();
+                // Masks are their own inverse
+                code.apply_mask(mask);
+                penalty
+            })
+            .unwrap_or(0);
+        code.apply_mask(mask);
+        code.draw_format_bits(level, mask);
+        Ok(code)
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.is_function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, level: EcLevel) {
+        for i in 0..self.size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        self.draw_finder_pattern(3, 3);
+        self.draw_finder_pattern(self.size - 4, 3);
+        self.draw_finder_pattern(3, self.size - 4);
+
+        let positions = alignment_pattern_positions(self.version);
+        let last = positions.len().saturating_sub(1);
+        let finder_corner = |i: usize, j: usize| (i == 0 && (j == 0 || j == last)) || (i == last && j == 0);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                if !finder_corner(i, j) {
+                    self.draw_alignment_pattern(x, y);
+                }
+            }
+        }
+
+        // Reserves the format modules, they are drawn for real once the mask is known
+        self.draw_format_bits(level, 0);
+        self.draw_version();
+    }
+
+    fn draw_finder_pattern(&mut self, x: usize, y: usize) {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (xx, yy) = (x as i32 + dx, y as i32 + dy);
+                if (0..self.size as i32).contains(&xx) && (0..self.size as i32).contains(&yy) {
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function(xx as usize, yy as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment_pattern(&mut self, x: usize, y: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                self.set_function((x as i32 + dx) as usize, (y as i32 + dy) as usize, dark);
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, level: EcLevel, mask: u32) {
+        let data = level.format_bits() << 3 | mask;
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = (data << 10 | remainder) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+
+        // Around the top left finder
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        // Split between the other two finders
+        let size = self.size;
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        // Always dark
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+        let mut remainder = self.version as u32;
+        for _ in 0..12 {
+            remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1f25);
+        }
+        let bits = (self.version as u32) << 12 | remainder;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let (a, b) = (self.size - 11 + i % 3, i / 3);
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    // Zigzags through two-module columns from the bottom right, skipping function modules
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let total_bits = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = self.size as i32 - 1;
+        while right >= 1 {
+            if right == 6 {
+                // The vertical timing pattern
+                right = 5;
+            }
+            for vert in 0..self.size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { self.size - 1 - vert } else { vert };
+                    if !self.is_function[y * self.size + x] && i < total_bits {
+                        self.modules[y * self.size + x] = (codewords[i >> 3] >> (7 - (i & 7))) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let index = y * self.size + x;
+                self.modules[index] ^= invert && !self.is_function[index];
+            }
+        }
+    }
+
+    // The standard's penalty rules, lower is easier to scan. Only used to pick a mask, every mask
+    // gives a valid code.
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let lines = |transpose: bool| -> Vec<Vec<bool>> {
+            (0..size)
+                .map(|a| (0..size).map(|b| if transpose { self.is_dark(a, b) } else { self.is_dark(b, a) }).collect())
+                .collect()
+        };
+        let mut penalty = 0;
+        for line in lines(false).into_iter().chain(lines(true)) {
+            // Runs of five or more modules of one color
+            let mut run = 1;
+            for i in 1..=size {
+                if i < size && line[i] == line[i - 1] {
+                    run += 1;
+                    continue;
+                }
+                if run >= 5 {
+                    penalty += run - 2;
+                }
+                run = 1;
+            }
+            // Finder-like 1:1:3:1:1 patterns with four light modules on one side
+            const PATTERN: [bool; 7] = [true, false, true, true, true, false, true];
+            for start in 0..size.saturating_sub(6) {
+                if line[start..start + 7] == PATTERN {
+                    // Beyond the edge counts as light
+                    let light = |range: std::ops::Range<usize>| range.into_iter().all(|i| i >= size || !line[i]);
+                    if light(start.saturating_sub(4)..start) || light(start + 7..start + 11) {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+        // 2×2 blocks of one color
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.is_dark(x, y);
+                if dark == self.is_dark(x + 1, y) && dark == self.is_dark(x, y + 1) && dark == self.is_dark(x + 1, y + 1) {
+                    penalty += 3;
+                }
+            }
+        }
+        // Balance of dark and light modules
+        let dark = self.modules.iter().filter(|&&dark| dark).count();
+        let total = size * size;
+        let deviation = (dark * 20).abs_diff(total * 10);
+        penalty + deviation.div_ceil(total).saturating_sub(1) * 10
+    }
+}
+
+#[derive(Default)]
+struct BitBuffer {
+    bits: Vec<bool>,
+}
+
+impl BitBuffer {
+    fn push(&mut self, value: u32, count: usize) {
+        self.bits.extend((0..count).rev().map(|i| (value >> i) & 1 != 0));
+    }
+
+    fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.bits.chunks(8).map(|chunk| chunk.iter().fold(0u8, |byte, &bit| byte << 1 | u8::from(bit))).collect()
+    }
+}
+
+// Byte mode indicator, character count, the data, then padding up to the capacity
+fn padded_codewords(data: &[u8], version: usize, level: EcLevel) -> Vec<u8> {
+    let capacity_bits = data_codewords(version, level) * 8;
+    let mut bits = BitBuffer::default();
+    bits.push(0b0100, 4);
+    bits.push(data.len() as u32, count_bits(version));
+    for &byte in data {
+        bits.push(byte as u32, 8);
+    }
+    bits.push(0, (capacity_bits - bits.len()).min(4));
+    bits.push(0, (8 - bits.len() % 8) % 8);
+    for pad in [0xec, 0x11].into_iter().cycle() {
+        if bits.len() >= capacity_bits {
+            break;
+        }
+        bits.push(pad, 8);
+    }
+    bits.into_bytes()
+}
+
+fn count_bits(version: usize) -> usize {
+    if version <= 9 { 8 } else { 16 }
+}
+
+fn raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        result -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn data_codewords(version: usize, level: EcLevel) -> usize {
+    raw_data_modules(version) / 8
+        - ECC_CODEWORDS_PER_BLOCK[level.index()][version] * NUM_ERROR_CORRECTION_BLOCKS[level.index()][version]
+}
+
+// How many bytes one code of this version and level holds
+pub fn byte_capacity(version: usize, level: EcLevel) -> usize {
+    (data_codewords(version, level) * 8 - 4 - count_bits(version)) / 8
+}
+
+fn alignment_pattern_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let mut positions = vec![6; count];
+    let mut position = version * 4 + 17 - 7;
+    for slot in positions.iter_mut().skip(1).rev() {
+        *slot = position;
+        position -= step;
+    }
+    positions
+}
+
+// Splits the data into blocks, appends each block's error correction and interleaves them
+fn interleave_with_ecc(data: &[u8], version: usize, level: EcLevel) -> Vec<u8> {
+    let blocks = NUM_ERROR_CORRECTION_BLOCKS[level.index()][version];
+    let ecc_len = ECC_CODEWORDS_PER_BLOCK[level.index()][version];
+    let raw_codewords = raw_data_modules(version) / 8;
+    let short_blocks = blocks - raw_codewords % blocks;
+    let short_block_len = raw_codewords / blocks;
+    let divisor = reed_solomon_divisor(ecc_len);
+
+    let mut rest = data;
+    let all: Vec<Vec<u8>> = (0..blocks)
+        .map(|i| {
+            let data_len = short_block_len - ecc_len + usize::from(i >= short_blocks);
+            let (block_data, remainder) = rest.split_at(data_len);
+            rest = remainder;
+            let mut block = block_data.to_vec();
+            let ecc = reed_solomon_remainder(block_data, &divisor);
+            if i < short_blocks {
+                // Placeholder so all blocks line up, skipped below
+                block.push(0);
+            }
+            block.extend(ecc);
+            block
+        })
+        .collect();
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..=short_block_len {
+        for (j, block) in all.iter().enumerate() {
+            if i != short_block_len - ecc_len || j >= short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (slot, &coefficient) in result.iter_mut().zip(divisor) {
+            *slot ^= gf_multiply(coefficient, factor);
+        }
+    }
+    result
+}
+
+// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11d);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
+
+// Codes for data that may need more than one, each holding an equal share of at most the
+// capacity of `max_version`
+pub fn encode_sequence(data: &[u8], level: EcLevel, max_version: usize) -> Result<Vec<QrCode>, String> {
+    if data.is_empty() {
+        return Ok(vec![QrCode::encode(data, level, max_version)?]);
+    }
+    let capacity = byte_capacity(max_version.clamp(MIN_VERSION, MAX_VERSION), level);
+    let count = data.len().div_ceil(capacity);
+    if count > MAX_SEQUENCE_CODES {
+        return Err(trf(
+            "Failed to make QR codes: {} bytes would take {} codes, more than the {} allowed",
+            &[&data.len(), &count, &MAX_SEQUENCE_CODES],
+        ));
+    }
+    let chunk_len = data.len().div_ceil(count);
+    data.chunks(chunk_len).map(|chunk| QrCode::encode(chunk, level, max_version)).collect()
+}
+
+// Grayscale pixels of a rendered code, 0 for dark and 255 for light
+pub struct QrImage {
+    pub width: usize,
+    pub pixels: Vec<u8>,
+}
+
+// Each module becomes a `scale`×`scale` square, with the standard four-module quiet zone
+pub fn render(code: &QrCode, scale: usize) -> QrImage {
+    const QUIET_ZONE: usize = 4;
+    let scale = scale.max(1);
+    let width = (code.size + 2 * QUIET_ZONE) * scale;
+    let mut pixels = vec![255u8; width * width];
+    for y in 0..code.size {
+        for x in 0..code.size {
+            if !code.is_dark(x, y) {
+                continue;
+            }
+            for py in 0..scale {
+                let row = ((y + QUIET_ZONE) * scale + py) * width;
+                let start = row + (x + QUIET_ZONE) * scale;
+                pixels[start..start + scale].fill(0);
+            }
+        }
+    }
+    QrImage { width, pixels }
+}
+
+pub fn save_png(image: &QrImage, path: &Path) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| trf("Failed to create {}: {}", &[&path.display(), &e]))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), image.width as u32, image.width as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| trf("Failed to write PNG: {}", &[&e]))?;
+    writer.write_image_data(&image.pixels).map_err(|e| trf("Failed to write PNG: {}", &[&e]))
+}
+
+// Text length and fingerprint, raw_bytes, level and max version
+type CodesKey = (usize, u64, bool, EcLevel, usize);
+
+// Options and cached codes of the QR view of the MessagePack output
+pub struct QrState {
+    pub open: bool,
+    pub level: EcLevel,
+    pub max_version: usize,
+    // Encode the MessagePack bytes themselves rather than their base64 text
+    pub raw_bytes: bool,
+    index: usize,
+    // What the codes were made from, and the codes
+    codes: Option<(CodesKey, Result<Vec<QrCode>, String>)>,
+    texture: Option<(usize, egui::TextureHandle)>,
+    save_path: String,
+    saved: Option<Result<String, String>>,
+}
+
+impl Default for QrState {
+    fn default() -> Self {
+        QrState {
+            open: false,
+            level: EcLevel::default(),
+            max_version: 25,
+            raw_bytes: false,
+            index: 0,
+            codes: None,
+            texture: None,
+            save_path: "messagepack.png".to_string(),
+            saved: None,
+        }
+    }
+}
+
+const RENDER_SCALE: usize = 8;
+
+// Shows the text, or with raw_bytes the bytes `decode` makes of it, as one or more QR codes
+// with their options, in at most `height` points
+pub fn show_qr(
+    ui: &mut egui::Ui,
+    state: &mut QrState,
+    text: &str,
+    decode: impl Fn(&str) -> Result<Vec<u8>, String>,
+    height: f32,
+) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source("qr_level")
+            .selected_text(trf("Level {}", &[&state.level.name()]))
+            .show_ui(ui, |ui| {
+                for level in EcLevel::ALL {
+                    ui.selectable_value(&mut state.level, level, level.name());
+                }
+            });
+        ui.label(tr("Max version:"));
+        ui.add(egui::DragValue::new(&mut state.max_version).clamp_range(MIN_VERSION..=MAX_VERSION))
+            .on_hover_text(tr("Larger versions hold more per code but are harder to scan"));
+        ui.checkbox(&mut state.raw_bytes, tr("Raw bytes"))
+            .on_hover_text(tr("Encode the MessagePack bytes instead of their Base64 text"));
+    });
+
+    let key = (text.len(), text_fingerprint(text), state.raw_bytes, state.level, state.max_version);
+    if state.codes.as_ref().map(|(made_for, _)| made_for) != Some(&key) {
+        let codes = if state.raw_bytes {
+            decode(text).and_then(|bytes| encode_sequence(&bytes, state.level, state.max_version))
+        } else {
+            encode_sequence(text.as_bytes(), state.level, state.max_version)
+        };
+        state.codes = Some((key, codes));
+        state.index = 0;
+        state.texture = None;
+    }
+    let Some((_, codes)) = &state.codes else { return };
+    let codes = match codes {
+        Ok(codes) => codes,
+        Err(e) => {
+            ui.colored_label(egui::Color32::RED, e);
+            return;
+        }
+    };
+    state.index = state.index.min(codes.len() - 1);
+    let code = &codes[state.index];
+
+    ui.horizontal(|ui| {
+        if codes.len() > 1 {
+            if ui.add_enabled(state.index > 0, egui::Button::new(tr("◀ Prev"))).clicked() {
+                state.index -= 1;
+            }
+            ui.label(trf("Code {} of {}", &[&(state.index + 1), &codes.len()]));
+            if ui.add_enabled(state.index + 1 < codes.len(), egui::Button::new(tr("Next ▶"))).clicked() {
+                state.index += 1;
+            }
+            ui.separator();
+        }
+        ui.weak(trf("Version {}, {}×{} modules", &[&code.version, &code.size, &code.size]));
+    });
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut state.save_path).desired_width(200.0));
+        if ui.button(tr("Save PNG")).clicked() {
+            let path = Path::new(state.save_path.trim());
+            state.saved = Some(save_png(&render(code, RENDER_SCALE), path).map(|_| trf("Saved {}", &[&path.display()])));
+        }
+        match &state.saved {
+            Some(Ok(message)) => {
+                ui.weak(message);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            None => {}
+        }
+    });
+
+    if state.texture.as_ref().map(|(index, _)| *index) != Some(state.index) {
+        let image = render(code, RENDER_SCALE);
+        let color_image = egui::ColorImage::from_gray([image.width, image.width], &image.pixels);
+        let texture = ui.ctx().load_texture("messagepack_qr", color_image, egui::TextureOptions::NEAREST);
+        state.texture = Some((state.index, texture));
+    }
+    if let Some((_, texture)) = &state.texture {
+        let side = height.min(ui.available_width()).max(64.0);
+        ui.image((texture.id(), egui::vec2(side, side)));
+    }
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn format_information(code: &QrCode) -> u32 {
+    let mut bits = 0;
+    for i in 0..=5 {
+        bits |= u32::from(code.is_dark(8, i)) << i;
+    }
+    bits |= u32::from(code.is_dark(8, 7)) << 6;
+    bits |= u32::from(code.is_dark(8, 8)) << 7;
+    bits |= u32::from(code.is_dark(7, 8)) << 8;
+    for i in 9..15 {
+        bits |= u32::from(code.is_dark(14 - i, 8)) << i;
+    }
+    bits ^ 0x5412
+}
+
+#[test]
+fn test_capacities_match_the_standard() {
+    assert_eq!(byte_capacity(1, EcLevel::Low), 17);
+    assert_eq!(byte_capacity(1, EcLevel::High), 7);
+    assert_eq!(byte_capacity(10, EcLevel::Medium), 213);
+    assert_eq!(byte_capacity(40, EcLevel::Low), 2953);
+    assert_eq!(byte_capacity(40, EcLevel::Medium), 2331);
+    assert_eq!(byte_capacity(40, EcLevel::Quartile), 1663);
+    assert_eq!(byte_capacity(40, EcLevel::High), 1273);
+    assert_eq!(alignment_pattern_positions(7), vec![6, 22, 38]);
+    assert_eq!(alignment_pattern_positions(32), vec![6, 34, 60, 86, 112, 138]);
+}
+
+#[test]
+fn test_reed_solomon_matches_known_codewords() {
+    // "HELLO WORLD" as a 1-M code
+    let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+    assert_eq!(reed_solomon_remainder(&data, &reed_solomon_divisor(10)), vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]);
+}
+
+#[test]
+fn test_encode_picks_the_smallest_version_and_writes_format_bits() {
+    let code = QrCode::encode(&[b'a'; 17], EcLevel::Low, MAX_VERSION).unwrap();
+    assert_eq!((code.version, code.size), (1, 21));
+    let code = QrCode::encode(&[b'a'; 18], EcLevel::Low, MAX_VERSION).unwrap();
+    assert_eq!(code.version, 2);
+
+    let alice = hex::decode("83a36167651ea463697479aa576f6e6465726c616e64a46e616d65a5416c696365").unwrap();
+    let code = QrCode::encode(&alice, EcLevel::Quartile, MAX_VERSION).unwrap();
+    // Level bits, then a 10 bit BCH code that is a multiple of the generator
+    let format = format_information(&code);
+    assert_eq!(format >> 13, EcLevel::Quartile.format_bits());
+    let mut remainder = format;
+    for i in (10..15).rev() {
+        if remainder >> i & 1 != 0 {
+            remainder ^= 0x537 << (i - 10);
+        }
+    }
+    assert_eq!(remainder, 0);
+    // Finder corners and the dark module
+    assert!(code.is_dark(0, 0) && code.is_dark(code.size - 1, 0) && code.is_dark(0, code.size - 1));
+    assert!(!code.is_dark(7, 7));
+    assert!(code.is_dark(8, code.size - 8));
+}
+
+#[test]
+fn test_oversized_payloads_error_or_split() {
+    let data = vec![0x5a; 3000];
+    let err = QrCode::encode(&data, EcLevel::Medium, 10).unwrap_err();
+    assert!(err.contains("3000 bytes don't fit in version 10"), "{}", err);
+
+    let codes = encode_sequence(&data, EcLevel::Medium, 10).unwrap();
+    assert_eq!(codes.len(), 15);
+    assert!(codes.iter().all(|code| code.version <= 10));
+    assert_eq!(encode_sequence(&[], EcLevel::Medium, 10).unwrap().len(), 1);
+    assert!(encode_sequence(&vec![0; 300_000], EcLevel::Low, MAX_VERSION).is_err());
+}
+
+#[test]
+fn test_render_scales_modules_and_adds_quiet_zone() {
+    let code = QrCode::encode(b"hi", EcLevel::Low, 1).unwrap();
+    let image = render(&code, 2);
+    assert_eq!(image.width, (21 + 8) * 2);
+    assert_eq!(image.pixels.len(), image.width * image.width);
+    // Quiet zone is light, the top left finder corner dark
+    assert_eq!(image.pixels[0], 255);
+    assert_eq!(image.pixels[8 * image.width + 8], 0);
+}
+
+#[test]
+fn test_data_reads_back_from_the_modules() {
+    let data = b"https://example.com/a-payload-long-enough-for-two-blocks-at-level-h";
+    let code = QrCode::encode(data, EcLevel::High, MAX_VERSION).unwrap();
+    let mask = (format_information(&code) >> 10) & 0b111;
+
+    // Undo the mask and walk the zigzag, which must give back the interleaved codewords
+    let mut unmasked = QrCode { modules: code.modules.clone(), is_function: code.is_function.clone(), ..code };
+    unmasked.apply_mask(mask);
+    let mut bits = BitBuffer::default();
+    let mut right = unmasked.size as i32 - 1;
+    while right >= 1 {
+        if right == 6 {
+            right = 5;
+        }
+        for vert in 0..unmasked.size {
+            for j in 0..2 {
+                let x = (right - j) as usize;
+                let y = if (right + 1) & 2 == 0 { unmasked.size - 1 - vert } else { vert };
+                if !unmasked.is_function[y * unmasked.size + x] {
+                    bits.push(u32::from(unmasked.is_dark(x, y)), 1);
+                }
+            }
+        }
+        right -= 2;
+    }
+    let version = unmasked.version;
+    let codewords = bits.into_bytes();
+    // Plus a partial byte of remainder bits for some versions
+    assert_eq!(codewords.len(), raw_data_modules(version).div_ceil(8));
+    assert_eq!(codewords[..raw_data_modules(version) / 8], interleave_with_ecc(&padded_codewords(data, version, EcLevel::High), version, EcLevel::High)[..]);
+
+    // The first block starts with the mode, the 8 bit length and then the data
+    let blocks = NUM_ERROR_CORRECTION_BLOCKS[EcLevel::High.index()][version];
+    let first_block: Vec<u8> = codewords.iter().step_by(blocks).take(3).copied().collect();
+    assert_eq!(first_block[0] >> 4, 0b0100);
+    assert_eq!(usize::from(first_block[0] & 0x0f) << 4 | usize::from(first_block[1] >> 4), data.len());
+    assert_eq!((first_block[1] & 0x0f) << 4 | first_block[2] >> 4, data[0]);
+}
@@ -0,0 +1,207 @@
+use crate::format::JsonFormat;
+use crate::locale::{tr, trf};
+use crate::schema_check::escape_pointer_token;
+use serde_json::Value;
+
+// One step of a query: a key or array index, or every child at that level
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Child(String),
+    Wildcard,
+}
+
+// A match and the JSON Pointer it was found at
+pub type Match<'a> = (String, &'a Value);
+
+// Accepts a JSON Pointer ("/payload/readings/3/temp") or a small subset of JSONPath
+// ("$.payload.readings[*].temp", "$['odd key'][0]"). Only JSONPath has "*" for every key or
+// item, in a pointer it is the key "*", and the empty pointer is the whole document.
+fn parse_query(query: &str) -> Result<Vec<Segment>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        Ok(Vec::new())
+    } else if let Some(pointer) = query.strip_prefix('/') {
+        pointer.split('/').map(parse_pointer_token).collect()
+    } else if let Some(path) = query.strip_prefix('$') {
+        parse_json_path(path)
+    } else {
+        Err(tr("Invalid query: start with \"/\" for a JSON Pointer or \"$\" for a JSONPath").to_string())
+    }
+}
+
+fn parse_pointer_token(token: &str) -> Result<Segment, String> {
+    let mut unescaped = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => unescaped.push('~'),
+            Some('1') => unescaped.push('/'),
+            _ => return Err(trf("Invalid query: \"~\" in \"{}\" must be followed by 0 or 1", &[&token])),
+        }
+    }
+    Ok(Segment::Child(unescaped))
+}
+
+fn parse_json_path(path: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        let position = path.len() - rest.len() + 1;
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            let name = &after_dot[..end];
+            segments.push(match name {
+                "" => return Err(trf("Invalid query: expected a key after \".\" at position {}", &[&position])),
+                "*" => Segment::Wildcard,
+                _ => Segment::Child(name.to_string()),
+            });
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let (segment, remainder) = parse_bracket(after_bracket)
+                .ok_or_else(|| trf("Invalid query: unclosed or malformed \"[\" at position {}", &[&position]))?;
+            segments.push(segment);
+            rest = remainder;
+        } else {
+            return Err(trf("Invalid query: expected \".\" or \"[\" at position {}", &[&position]));
+        }
+    }
+    Ok(segments)
+}
+
+// The inside of [*], [3] or ['key'], returns what follows the closing bracket
+fn parse_bracket(text: &str) -> Option<(Segment, &str)> {
+    for quote in ['\'', '"'] {
+        if let Some(quoted) = text.strip_prefix(quote) {
+            let end = quoted.find(quote)?;
+            let remainder = quoted[end + 1..].strip_prefix(']')?;
+            return Some((Segment::Child(quoted[..end].to_string()), remainder));
+        }
+    }
+    let end = text.find(']')?;
+    let inner = text[..end].trim();
+    let segment = match inner {
+        "*" => Segment::Wildcard,
+        _ if array_index(inner).is_some() => Segment::Child(inner.to_string()),
+        _ => return None,
+    };
+    Some((segment, &text[end + 1..]))
+}
+
+// An index as JSON Pointer writes them, in decimal digits without leading zeros, so "01" or "+1"
+// select nothing in an array
+fn array_index(token: &str) -> Option<usize> {
+    let canonical = token == "0" || (!token.starts_with('0') && !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit()));
+    canonical.then(|| token.parse().ok()).flatten()
+}
+
+// Every value the query selects, in document order
+pub fn select<'a>(value: &'a Value, query: &str) -> Result<Vec<Match<'a>>, String> {
+    let segments = parse_query(query)?;
+    let mut matches = vec![(String::new(), value)];
+    for segment in &segments {
+        matches = matches.into_iter().flat_map(|(path, value)| children(&path, value, segment)).collect();
+    }
+    Ok(matches)
+}
+
+fn children<'a>(path: &str, value: &'a Value, segment: &Segment) -> Vec<Match<'a>> {
+    let child_path = |token: &str| format!("{}/{}", path, escape_pointer_token(token));
+    match (segment, value) {
+        (Segment::Child(key), Value::Object(map)) => map.get(key).map(|child| (child_path(key), child)).into_iter().collect(),
+        (Segment::Child(index), Value::Array(items)) => array_index(index)
+            .and_then(|i| items.get(i))
+            .map(|child| (child_path(index), child))
+            .into_iter()
+            .collect(),
+        (Segment::Wildcard, Value::Object(map)) => map.iter().map(|(key, child)| (child_path(key), child)).collect(),
+        (Segment::Wildcard, Value::Array(items)) => {
+            items.iter().enumerate().map(|(i, child)| (child_path(&i.to_string()), child)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Text for the filtered JSON Output pane. A query with a wildcard always yields an array of its
+// matches so the shape doesn't change with the number of matches.
+pub fn query_output(value: &Value, query: &str, json_format: &JsonFormat) -> Result<String, String> {
+    let matches = select(value, query)?;
+    let has_wildcard = parse_query(query)?.contains(&Segment::Wildcard);
+    match (matches.as_slice(), has_wildcard) {
+        ([], _) => Err(trf("Nothing matches {}", &[&query.trim()])),
+        ([(_, value)], false) => Ok(json_format.pretty(value)?),
+        _ => Ok(json_format.pretty(&Value::Array(matches.into_iter().map(|(_, value)| value.clone()).collect()))?),
+    }
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn telemetry() -> Value {
+    serde_json::from_str(r#"{
+        "device": "a/b",
+        "payload": {"readings": [{"temp": 20.5}, {"temp": 21}, {"hum": 40}]},
+        "odd key": {"~x": true}
+    }"#).unwrap()
+}
+
+#[cfg(test)]
+fn paths(matches: &[Match]) -> Vec<String> {
+    matches.iter().map(|(path, _)| path.clone()).collect()
+}
+
+#[test]
+fn test_select_with_json_pointer() {
+    let value = telemetry();
+    let matches = select(&value, "/payload/readings/1/temp").unwrap();
+    assert_eq!(paths(&matches), vec!["/payload/readings/1/temp"]);
+    assert_eq!(matches[0].1, &Value::from(21));
+
+    assert_eq!(paths(&select(&value, "/odd key/~0x").unwrap()), vec!["/odd key/~0x"]);
+    // "*" is a key like any other in a pointer
+    assert!(select(&value, "/payload/readings/*/temp").unwrap().is_empty());
+    let starred = serde_json::json!({"*": 1, "a": 2});
+    assert_eq!(paths(&select(&starred, "/*").unwrap()), vec!["/*"]);
+    assert_eq!(paths(&select(&value, "").unwrap()), vec![""]);
+    assert!(select(&value, "/payload/readings/01").unwrap().is_empty());
+    assert!(select(&value, "/payload/readings/+1").unwrap().is_empty());
+    assert_eq!(paths(&select(&serde_json::json!({"01": true}), "/01").unwrap()), vec!["/01"]);
+    assert!(select(&value, "/payload/readings/7").unwrap().is_empty());
+    assert!(select(&value, "/device/0").unwrap().is_empty());
+}
+
+#[test]
+fn test_select_with_json_path() {
+    let value = telemetry();
+    assert_eq!(
+        paths(&select(&value, "$.payload.readings[*].temp").unwrap()),
+        vec!["/payload/readings/0/temp", "/payload/readings/1/temp"]
+    );
+    assert_eq!(paths(&select(&value, "$['odd key'][\"~x\"]").unwrap()), vec!["/odd key/~0x"]);
+    assert_eq!(paths(&select(&value, "$.payload.readings[2]").unwrap()), vec!["/payload/readings/2"]);
+    assert_eq!(select(&value, "$.*").unwrap().len(), 3);
+    assert_eq!(paths(&select(&value, "$").unwrap()), vec![""]);
+}
+
+#[test]
+fn test_invalid_queries() {
+    let value = telemetry();
+    for query in ["payload", "/a~2", "$.", "$[x]", "$[1", "$[01]", "$payload"] {
+        let err = select(&value, query).unwrap_err();
+        assert!(err.starts_with("Invalid query:"), "{}: {}", query, err);
+    }
+}
+
+#[test]
+fn test_query_output() {
+    let value = telemetry();
+    let json_format = JsonFormat::default();
+    assert_eq!(query_output(&value, "/payload/readings/0", &json_format).unwrap(), "{\n  \"temp\": 20.5\n}");
+    assert_eq!(query_output(&value, "$.payload.readings[*].temp", &json_format).unwrap(), "[\n  20.5,\n  21\n]");
+    // A wildcard with a single match still gives an array
+    assert_eq!(query_output(&value, "$.payload.readings[*].hum", &json_format).unwrap(), "[\n  40\n]");
+    assert_eq!(query_output(&value, " /missing ", &json_format).unwrap_err(), "Nothing matches /missing");
+}
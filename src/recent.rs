@@ -0,0 +1,103 @@
+use crate::files::file_name;
+use crate::locale::tr;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const MAX_RECENT_FILES: usize = 10;
+
+// Files last opened into one pane, most recent first and each path once
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct RecentFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl RecentFiles {
+    // Relative paths are made absolute, so they still point at the same file from another directory
+    pub fn add(&mut self, path: &Path) {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        self.paths.retain(|recent| *recent != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT_FILES);
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn remove_missing(&mut self) {
+        self.paths.retain(|path| path.exists());
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+}
+
+// "Recent" menu for an input pane's header, returning the file that was picked. Files that no
+// longer exist are listed greyed out until they are removed.
+pub fn recent_menu(ui: &mut egui::Ui, recent: &mut RecentFiles) -> Option<PathBuf> {
+    let mut picked = None;
+    ui.add_enabled_ui(!recent.is_empty(), |ui| {
+        ui.menu_button(tr("Recent"), |ui| {
+            for path in recent.paths() {
+                let exists = path.exists();
+                let response = ui.add_enabled(exists, egui::Button::new(file_name(path)))
+                    .on_hover_text(path.display().to_string())
+                    .on_disabled_hover_text(tr("No longer exists"));
+                if response.clicked() {
+                    picked = Some(path.clone());
+                    ui.close_menu();
+                }
+            }
+            ui.separator();
+            if ui.button(tr("Remove missing files")).clicked() {
+                recent.remove_missing();
+            }
+            if ui.button(tr("Clear list")).clicked() {
+                recent.clear();
+                ui.close_menu();
+            }
+        });
+    });
+    picked
+}
+
+
+/* Tests */
+#[test]
+fn test_recent_files_dedupe_and_cap() {
+    let mut recent = RecentFiles::default();
+    for i in 0..MAX_RECENT_FILES + 3 {
+        recent.add(Path::new(&format!("/captures/{}.msgpack", i)));
+    }
+    assert_eq!(recent.paths().len(), MAX_RECENT_FILES);
+    assert_eq!(recent.paths()[0], Path::new("/captures/12.msgpack"));
+    assert_eq!(recent.paths()[MAX_RECENT_FILES - 1], Path::new("/captures/3.msgpack"));
+
+    // Opening one again moves it to the front instead of listing it twice
+    recent.add(Path::new("/captures/7.msgpack"));
+    assert_eq!(recent.paths().len(), MAX_RECENT_FILES);
+    assert_eq!(recent.paths()[0], Path::new("/captures/7.msgpack"));
+    assert_eq!(recent.paths().iter().filter(|path| path.ends_with("7.msgpack")).count(), 1);
+}
+
+#[test]
+fn test_recent_files_are_absolute_and_round_trip() {
+    let mut recent = RecentFiles::default();
+    recent.add(Path::new("capture.msgpack"));
+    assert!(recent.paths()[0].is_absolute());
+    assert!(recent.paths()[0].ends_with("capture.msgpack"));
+
+    let text = serde_json::to_string(&recent).unwrap();
+    assert!(text.starts_with('['));
+    assert_eq!(serde_json::from_str::<RecentFiles>(&text).unwrap(), recent);
+
+    recent.remove_missing();
+    assert!(recent.is_empty());
+}
@@ -0,0 +1,178 @@
+use serde_json::Value;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+// Values to scrub before output is shared. Rules come one per line: a JSON Pointer like
+// "/user/email" (with "*" matching any key or index at its level) or a key name pattern like
+// "password" or "*token*", which matches that key at any depth, ignoring case.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redaction {
+    rules: Vec<Rule>,
+    // Replace strings with asterisks of the same length, numbers with 0 and so on, instead of
+    // putting "[REDACTED]" in place of every value
+    keep_shape: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Rule {
+    Pointer(Vec<String>),
+    Key(String),
+}
+
+impl Redaction {
+    // Blank lines and lines starting with "#" are ignored
+    pub fn parse(rules: &str, keep_shape: bool) -> Redaction {
+        let rules = rules
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.strip_prefix('/') {
+                Some(pointer) => Rule::Pointer(pointer.split('/').map(|token| token.replace("~1", "/").replace("~0", "~")).collect()),
+                None => Rule::Key(line.to_lowercase()),
+            })
+            .collect();
+        Redaction { rules, keep_shape }
+    }
+
+    // `path` holds the keys and indices leading to the value, `key` is set when its parent is an object
+    fn matches(&self, path: &[String], key: Option<&str>) -> bool {
+        self.rules.iter().any(|rule| match rule {
+            Rule::Pointer(tokens) => {
+                tokens.len() == path.len() && tokens.iter().zip(path).all(|(token, step)| token == "*" || token == step)
+            }
+            Rule::Key(pattern) => key.is_some_and(|key| glob_match(pattern, &key.to_lowercase())),
+        })
+    }
+
+    // A redacted copy of `value` and how many values were replaced. The original is left alone so
+    // redaction can be switched off again.
+    pub fn apply(&self, value: &Value) -> (Value, usize) {
+        let mut redacted = value.clone();
+        let mut count = 0;
+        self.redact_children(&mut redacted, &mut Vec::new(), &mut count);
+        (redacted, count)
+    }
+
+    fn redact_children(&self, value: &mut Value, path: &mut Vec<String>, count: &mut usize) {
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map.iter_mut() {
+                    path.push(key.clone());
+                    self.redact_child(child, path, Some(key), count);
+                    path.pop();
+                }
+            }
+            Value::Array(items) => {
+                for (index, child) in items.iter_mut().enumerate() {
+                    path.push(index.to_string());
+                    self.redact_child(child, path, None, count);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn redact_child(&self, child: &mut Value, path: &mut Vec<String>, key: Option<&str>, count: &mut usize) {
+        if self.matches(path, key) {
+            *count += 1;
+            if self.keep_shape {
+                mask(child);
+            } else {
+                *child = Value::String(REDACTED.to_string());
+            }
+        } else {
+            self.redact_children(child, path, count);
+        }
+    }
+}
+
+// Keeps the type and structure of the value but none of its content
+fn mask(value: &mut Value) {
+    match value {
+        Value::String(s) => *s = "*".repeat(s.chars().count()),
+        Value::Number(_) => *value = Value::from(0),
+        Value::Bool(b) => *b = false,
+        Value::Null => {}
+        Value::Array(items) => items.iter_mut().for_each(mask),
+        Value::Object(map) => map.values_mut().for_each(mask),
+    }
+}
+
+// "*" matches any run of characters, everything else matches itself
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No "*" at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn json(text: &str) -> Value {
+    serde_json::from_str(text).unwrap()
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("password", "password"));
+    assert!(!glob_match("password", "password2"));
+    assert!(glob_match("*token*", "access_token_v2"));
+    assert!(glob_match("*token*", "token"));
+    assert!(glob_match("api*", "api_key"));
+    assert!(glob_match("*_id", "user_id"));
+    assert!(!glob_match("*_id", "user_identity"));
+    assert!(glob_match("a*b*c", "aXbYc"));
+    assert!(!glob_match("a*b*c", "aXcYb"));
+}
+
+#[test]
+fn test_redact_keys_at_any_depth() {
+    let value = json(r#"{
+        "user": {"email": "ann@example.com", "Password": "hunter2"},
+        "sessions": [{"id": 1, "AuthToken": "abc"}, {"id": 2, "refresh_token": {"v": "def"}}]
+    }"#);
+    let redaction = Redaction::parse("password\n\n# comment\n*token*", false);
+    let (redacted, count) = redaction.apply(&value);
+    assert_eq!(count, 3);
+    assert_eq!(redacted, json(r#"{
+        "user": {"email": "ann@example.com", "Password": "[REDACTED]"},
+        "sessions": [{"id": 1, "AuthToken": "[REDACTED]"}, {"id": 2, "refresh_token": "[REDACTED]"}]
+    }"#));
+    // The input is untouched
+    assert_eq!(value["user"]["Password"], "hunter2");
+}
+
+#[test]
+fn test_redact_pointers_with_wildcards() {
+    let value = json(r#"{"users": [{"email": "a@x", "name": "A"}, {"email": "b@x"}], "a/b": 1}"#);
+    let (redacted, count) = Redaction::parse("/users/*/email\n/a~1b", false).apply(&value);
+    assert_eq!(count, 3);
+    assert_eq!(redacted, json(r#"{"users": [{"email": "[REDACTED]", "name": "A"}, {"email": "[REDACTED]"}], "a/b": "[REDACTED]"}"#));
+
+    // Key patterns don't match array indices
+    let (_, count) = Redaction::parse("0", false).apply(&json("[1, 2]"));
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn test_redact_keeping_shape() {
+    let value = json(r#"{"secret": {"pin": 1234, "word": "héllo", "ok": true, "list": [null, "ab"]}}"#);
+    let (redacted, _) = Redaction::parse("secret", true).apply(&value);
+    assert_eq!(redacted, json(r#"{"secret": {"pin": 0, "word": "*****", "ok": false, "list": [null, "**"]}}"#));
+    assert_eq!(Redaction::parse(" \n# nothing", true).apply(&value), (value, 0));
+}
@@ -0,0 +1,151 @@
+use crate::convert::{BinaryFormat, TextFormat};
+use crate::format::JsonFormat;
+use crate::diff::{diff, Change};
+use crate::locale::{tr, trf};
+use serde_json::Value;
+use std::ops::Range;
+
+// Outcome of converting a result back and comparing it with what it was converted from
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RoundTrip {
+    // Structural differences between the two sides as JSON
+    pub changes: Vec<Change>,
+    // Byte ranges of the original MessagePack or CBOR that the re-encoding doesn't reproduce
+    pub byte_ranges: Vec<Range<usize>>,
+    pub original_len: usize,
+    pub reencoded_len: usize,
+}
+
+impl RoundTrip {
+    pub fn is_lossless(&self) -> bool {
+        self.changes.is_empty() && self.byte_ranges.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        if self.is_lossless() {
+            return tr("Lossless").to_string();
+        }
+        let mut parts = Vec::new();
+        if !self.changes.is_empty() {
+            let n = self.changes.len();
+            parts.push(trf(if n == 1 { "{} changed value" } else { "{} changed values" }, &[&n]));
+        }
+        if !self.byte_ranges.is_empty() {
+            let bytes: usize = self.byte_ranges.iter().map(Range::len).sum();
+            parts.push(trf(if bytes == 1 { "{} differing byte" } else { "{} differing bytes" }, &[&bytes]));
+        }
+        parts.join(", ")
+    }
+}
+
+// JSON → MessagePack: decodes the produced bytes and compares them with the parsed input
+pub fn verify_encoding(input: &Value, encoded: &[u8], format: BinaryFormat) -> Result<RoundTrip, String> {
+    let (decoded, _) = format.decode_at(encoded, 0, false)
+        .map_err(|e| trf("Failed to decode the {} output: {}", &[&format.name(), &e]))?;
+    Ok(RoundTrip {
+        changes: diff(input, &decoded),
+        original_len: encoded.len(),
+        reencoded_len: encoded.len(),
+        ..Default::default()
+    })
+}
+
+// MessagePack → JSON: re-encodes the produced JSON or YAML text and compares the bytes with the
+// original
+pub fn verify_decoding(encoded: &[u8], text: &str, format: BinaryFormat, text_format: TextFormat) -> Result<RoundTrip, String> {
+    let (original, _) = format.decode_at(encoded, 0, false)
+        .map_err(|e| trf("Failed to decode the {} input: {}", &[&format.name(), &e]))?;
+    // Keys stay in the order the text has them, as they would for JSON
+    let unsorted = JsonFormat { sort_keys: false, ..Default::default() };
+    let produced = text_format.parse(text, &unsorted, false)
+        .map_err(|e| trf("Failed to parse the {} output: {}", &[&text_format.name(), &e]))?;
+    let reencoded = format.encode(&produced).map_err(String::from)?;
+    Ok(RoundTrip {
+        changes: diff(&original, &produced),
+        byte_ranges: differing_ranges(encoded, &reencoded),
+        original_len: encoded.len(),
+        reencoded_len: reencoded.len(),
+    })
+}
+
+// Maximal runs of offsets where the two buffers disagree, a length mismatch counts as one run
+// at the end of the longer buffer
+pub fn differing_ranges(a: &[u8], b: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut push = |offset: usize| match ranges.last_mut() {
+        Some(last) if last.end == offset => last.end += 1,
+        _ => ranges.push(offset..offset + 1),
+    };
+    for offset in 0..a.len().min(b.len()) {
+        if a[offset] != b[offset] {
+            push(offset);
+        }
+    }
+    let common = a.len().min(b.len());
+    let longest = a.len().max(b.len());
+    if longest > common {
+        match ranges.last_mut() {
+            Some(last) if last.end == common => last.end = longest,
+            _ => ranges.push(common..longest),
+        }
+    }
+    ranges
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn alice_bytes() -> Vec<u8> {
+    hex::decode("83a36167651ea463697479aa576f6e6465726c616e64a46e616d65a5416c696365").unwrap()
+}
+
+#[test]
+fn test_differing_ranges() {
+    assert!(differing_ranges(b"abc", b"abc").is_empty());
+    assert_eq!(differing_ranges(b"abcdef", b"aXYdeZ"), vec![1..3, 5..6]);
+    assert_eq!(differing_ranges(b"abc", b"abcde"), vec![3..5]);
+    assert_eq!(differing_ranges(b"abX", b"abcde"), vec![2..5]);
+}
+
+#[test]
+fn test_verify_encoding_is_lossless_for_plain_json() {
+    let input: Value = serde_json::from_str(r#"{"age": 30, "city": "Wonderland", "name": "Alice"}"#).unwrap();
+    let report = verify_encoding(&input, &alice_bytes(), BinaryFormat::MessagePack).unwrap();
+    assert!(report.is_lossless());
+    assert_eq!(report.summary(), "Lossless");
+}
+
+#[test]
+fn test_verify_decoding_catches_key_order_and_float_width() {
+    let bytes = alice_bytes();
+    assert!(verify_decoding(&bytes, r#"{"age": 30, "city": "Wonderland", "name": "Alice"}"#, BinaryFormat::MessagePack, TextFormat::Json).unwrap().is_lossless());
+
+    // Same values in another order re-encode to other bytes
+    let report = verify_decoding(&bytes, r#"{"name": "Alice", "age": 30, "city": "Wonderland"}"#, BinaryFormat::MessagePack, TextFormat::Json).unwrap();
+    assert!(report.changes.is_empty());
+    assert!(!report.byte_ranges.is_empty());
+
+    // float32 1.5 comes back as a float64
+    let report = verify_decoding(&[0xca, 0x3f, 0xc0, 0x00, 0x00], "1.5", BinaryFormat::MessagePack, TextFormat::Json).unwrap();
+    assert!(report.changes.is_empty());
+    assert_eq!((report.original_len, report.reencoded_len), (5, 9));
+    assert_eq!(report.summary(), "6 differing bytes");
+}
+
+#[test]
+fn test_verify_decoding_reports_edited_values() {
+    let report = verify_decoding(&alice_bytes(), r#"{"age": 31, "city": "Wonderland", "name": "Alice"}"#, BinaryFormat::MessagePack, TextFormat::Json).unwrap();
+    assert_eq!(report.changes.len(), 1);
+    assert_eq!(report.changes[0].path, "/age");
+    assert_eq!(report.byte_ranges, vec![5..6]);
+    assert_eq!(report.summary(), "1 changed value, 1 differing byte");
+}
+
+#[test]
+fn test_verify_cbor_round_trips() {
+    let input: Value = serde_json::from_str(r#"{"age": 30, "name": "Alice"}"#).unwrap();
+    let cbor = BinaryFormat::Cbor.encode(&input).unwrap();
+    assert!(verify_encoding(&input, &cbor, BinaryFormat::Cbor).unwrap().is_lossless());
+    assert!(verify_decoding(&cbor, r#"{"age": 30, "name": "Alice"}"#, BinaryFormat::Cbor, TextFormat::Json).unwrap().is_lossless());
+    assert!(verify_decoding(&cbor, "age: 30\nname: Alice\n", BinaryFormat::Cbor, TextFormat::Yaml).unwrap().is_lossless());
+}
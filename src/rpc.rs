@@ -0,0 +1,129 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+// msgpack-RPC messages are arrays of a fixed shape: [0, msgid, method, params] for a request,
+// [1, msgid, error, result] for its response and [2, method, params] for a notification.
+// Labeling turns them into objects that name each part, arrays of any other shape stay as they are.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Message {
+    Request(u32),
+    Response(u32),
+    Notification,
+}
+
+pub fn message(value: &Value) -> Option<Message> {
+    let items = value.as_array()?;
+    let kind = |item: &Value, kind: u64| item.as_u64() == Some(kind);
+    let msgid = |item: &Value| item.as_u64().and_then(|msgid| u32::try_from(msgid).ok());
+    match &items[..] {
+        [first, msgid_item, Value::String(_), Value::Array(_)] if kind(first, 0) => msgid(msgid_item).map(Message::Request),
+        [first, msgid_item, _, _] if kind(first, 1) => msgid(msgid_item).map(Message::Response),
+        [first, Value::String(_), Value::Array(_)] if kind(first, 2) => Some(Message::Notification),
+        _ => None,
+    }
+}
+
+// The message as an object, anything else as it was
+pub fn label(value: Value) -> Value {
+    let Some(message) = message(&value) else {
+        return value;
+    };
+    let mut parts = match value {
+        Value::Array(items) => items.into_iter().skip(1),
+        _ => return value,
+    };
+    let mut next = || parts.next().unwrap_or_default();
+    match message {
+        Message::Request(_) => json!({"type": "request", "msgid": next(), "method": next(), "params": next()}),
+        Message::Response(_) => json!({"type": "response", "msgid": next(), "error": next(), "result": next()}),
+        Message::Notification => json!({"type": "notification", "method": next(), "params": next()}),
+    }
+}
+
+// Labels the messages of a stream, giving a response the method of the request it answers. Only
+// requests still waiting for their response are kept.
+#[derive(Default)]
+pub struct Pairing {
+    methods: HashMap<u32, String>,
+}
+
+impl Pairing {
+    pub fn label(&mut self, value: Value) -> Value {
+        let message = message(&value);
+        let mut labeled = label(value);
+        match message {
+            Some(Message::Request(msgid)) => {
+                if let Some(method) = labeled["method"].as_str() {
+                    self.methods.insert(msgid, method.to_string());
+                }
+            }
+            Some(Message::Response(msgid)) => {
+                if let (Some(method), Some(object)) = (self.methods.remove(&msgid), labeled.as_object_mut()) {
+                    object.shift_insert(2, "method".to_string(), method.into());
+                }
+            }
+            _ => {}
+        }
+        labeled
+    }
+}
+
+// When the requests of a live feed went out, so their responses can tell how long they took
+#[derive(Default)]
+pub struct Latencies {
+    sent: HashMap<u32, SystemTime>,
+}
+
+impl Latencies {
+    // How long after its request a response came in, once for each request
+    pub fn observe(&mut self, message: Message, time: SystemTime) -> Option<Duration> {
+        match message {
+            Message::Request(msgid) => {
+                self.sent.insert(msgid, time);
+                None
+            }
+            Message::Response(msgid) => self.sent.remove(&msgid).and_then(|sent| time.duration_since(sent).ok()),
+            Message::Notification => None,
+        }
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_messages_of_each_kind_are_labeled() {
+    let request = json!([0, 42, "add", [1, 2]]);
+    let response = json!([1, 42, null, 3]);
+    let notification = json!([2, "log", ["started"]]);
+    assert_eq!(message(&request), Some(Message::Request(42)));
+    assert_eq!(message(&response), Some(Message::Response(42)));
+    assert_eq!(message(&notification), Some(Message::Notification));
+    assert_eq!(label(request), json!({"type": "request", "msgid": 42, "method": "add", "params": [1, 2]}));
+    assert_eq!(label(response), json!({"type": "response", "msgid": 42, "error": null, "result": 3}));
+    assert_eq!(label(notification), json!({"type": "notification", "method": "log", "params": ["started"]}));
+    // Arrays of any other shape are left alone
+    for value in [json!([0, 42, "add"]), json!([0, -1, "add", []]), json!([0, 42, 7, []]), json!([3, 1, 2]), json!([2, "log", "started"]), json!({"a": 1})] {
+        assert_eq!(message(&value), None, "{}", value);
+        assert_eq!(label(value.clone()), value);
+    }
+    assert_eq!(message(&json!([1, 4_294_967_296u64, null, 3])), None);
+}
+
+#[test]
+fn test_responses_pair_with_their_requests() {
+    let mut pairing = Pairing::default();
+    pairing.label(json!([0, 1, "add", [1, 2]]));
+    pairing.label(json!([2, "log", []]));
+    let labeled = pairing.label(json!([1, 1, null, 3]));
+    assert_eq!(serde_json::to_string(&labeled).unwrap(), r#"{"type":"response","msgid":1,"method":"add","error":null,"result":3}"#);
+    // Answered already
+    assert_eq!(pairing.label(json!([1, 1, null, 3]))["method"], Value::Null);
+
+    let mut latencies = Latencies::default();
+    let sent = SystemTime::UNIX_EPOCH;
+    assert_eq!(latencies.observe(Message::Request(7), sent), None);
+    assert_eq!(latencies.observe(Message::Response(7), sent + Duration::from_millis(12)), Some(Duration::from_millis(12)));
+    assert_eq!(latencies.observe(Message::Response(7), sent + Duration::from_millis(20)), None);
+}
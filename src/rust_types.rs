@@ -0,0 +1,169 @@
+use crate::shape::{infer, singular, words, Definitions, Field, Shape};
+use serde_json::Value;
+use std::collections::HashSet;
+
+// Struct definitions for a decoded value. A field some elements lack or hold null in becomes an
+// Option, and values that can't share a type become serde_json::Value.
+
+// Type names the output uses itself, a struct named after a key can't take them
+const TAKEN_TYPE_NAMES: [&str; 8] = ["Option", "Vec", "String", "Box", "Result", "Self", "Serialize", "Deserialize"];
+
+const KEYWORDS: [&str; 51] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while", "abstract", "become",
+    "box", "do", "final", "macro", "override", "priv", "typeof", "unsized", "virtual", "yield", "try", "gen",
+];
+// Keywords a raw identifier can't be made of
+const NOT_RAW: [&str; 3] = ["crate", "self", "super"];
+
+pub fn rust_types(value: &Value, root: &str) -> String {
+    let mut definitions = Definitions::new(&TAKEN_TYPE_NAMES);
+    let shape = infer(value);
+    let mut out = "use serde::{Deserialize, Serialize};\n".to_string();
+    if let Shape::Object(fields) = &shape {
+        struct_for(&mut definitions, root, fields);
+    } else {
+        let type_name = type_name(&mut definitions, &shape, root);
+        out += &format!("\npub type {} = {};\n", root, type_name);
+    }
+    for definition in &definitions.definitions {
+        out += &format!("\n#[derive(Debug, Serialize, Deserialize)]\npub struct {} {{\n{}}}\n", definition.name, definition.body);
+    }
+    out
+}
+
+// Named after the key the value sits under
+fn type_name(definitions: &mut Definitions, shape: &Shape, key: &str) -> String {
+    match shape {
+        Shape::Unknown => "serde_json::Value".to_string(),
+        Shape::Null => "Option<serde_json::Value>".to_string(),
+        Shape::Bool => "bool".to_string(),
+        Shape::Integer { negative: true, beyond_i64: true } | Shape::Float => "f64".to_string(),
+        Shape::Integer { beyond_i64: true, .. } => "u64".to_string(),
+        Shape::Integer { .. } => "i64".to_string(),
+        Shape::String(_) => "String".to_string(),
+        Shape::Array(item) => format!("Vec<{}>", type_name(definitions, item, &singular(key))),
+        Shape::Object(fields) => struct_for(definitions, key, fields),
+        // Null and one other kind is an Option of it, anything more takes a Value
+        Shape::Union(members) => match &members.iter().filter(|member| **member != Shape::Null).collect::<Vec<_>>()[..] {
+            [only] => format!("Option<{}>", type_name(definitions, only, key)),
+            _ => "serde_json::Value".to_string(),
+        },
+    }
+}
+
+fn struct_for(definitions: &mut Definitions, key: &str, fields: &[Field]) -> String {
+    let (name, index) = definitions.name(key, fields);
+    let Some(index) = index else {
+        return name;
+    };
+    let mut idents = HashSet::new();
+    let mut body = String::new();
+    for field in fields {
+        let ident = field_ident(&field.key, &idents);
+        idents.insert(ident.clone());
+        if ident.trim_start_matches("r#") != field.key {
+            body += &format!("    #[serde(rename = {:?})]\n", field.key);
+        }
+        let mut type_name = type_name(definitions, &field.shape, &field.key);
+        // A missing Value is an error to serde, a missing Option is None
+        if field.optional && !type_name.starts_with("Option<") {
+            type_name = format!("Option<{}>", type_name);
+        }
+        body += &format!("    pub {}: {},\n", ident, type_name);
+    }
+    definitions.definitions[index].body = body;
+    name
+}
+
+// A snake case identifier no other field of the struct has taken
+fn field_ident(key: &str, taken: &HashSet<String>) -> String {
+    let snake = words(key).join("_");
+    let base = if snake.is_empty() {
+        "field".to_string()
+    } else if snake.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("field_{}", snake)
+    } else if NOT_RAW.contains(&snake.as_str()) {
+        format!("{}_", snake)
+    } else if KEYWORDS.contains(&snake.as_str()) {
+        format!("r#{}", snake)
+    } else {
+        snake
+    };
+    (1..).map(|n| if n == 1 { base.clone() } else { format!("{}_{}", base, n) }).find(|ident| !taken.contains(ident)).unwrap()
+}
+
+/* Tests */
+#[test]
+fn test_missing_and_null_fields_are_optional() {
+    let value = serde_json::json!([
+        {"id": 1, "name": "a", "note": null, "score": 1},
+        {"id": 2, "note": "x", "score": 2.5, "extra": true},
+    ]);
+    assert_eq!(rust_types(&value, "Root"), r#"use serde::{Deserialize, Serialize};
+
+pub type Root = Vec<RootItem>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RootItem {
+    pub id: i64,
+    pub name: Option<String>,
+    pub note: Option<String>,
+    pub score: f64,
+    pub extra: Option<bool>,
+}
+"#);
+}
+
+#[test]
+fn test_nested_arrays_of_objects_merge() {
+    let value = serde_json::json!({
+        "users": [
+            {"id": 1, "tags": [], "addresses": [{"city": "Oslo"}]},
+            {"id": -2, "tags": ["a"], "addresses": [{"city": "Bergen", "zip": "5003"}, {"city": 3}]},
+        ],
+        "matrix": [[1, 2], [3]],
+        "categories": [{"id": 18446744073709551615u64}],
+        "mixed": [1, "two"],
+        "empty": [],
+    });
+    let types = rust_types(&value, "Root");
+    assert!(types.contains("pub struct Root {\n    pub users: Vec<User>,\n    pub matrix: Vec<Vec<i64>>,\n    pub categories: Vec<Category>,\n    pub mixed: Vec<serde_json::Value>,\n    pub empty: Vec<serde_json::Value>,\n}"), "{}", types);
+    assert!(types.contains("pub struct User {\n    pub id: i64,\n    pub tags: Vec<String>,\n    pub addresses: Vec<Address>,\n}"), "{}", types);
+    assert!(types.contains("pub struct Address {\n    pub city: serde_json::Value,\n    pub zip: Option<String>,\n}"), "{}", types);
+    assert!(types.contains("pub struct Category {\n    pub id: u64,\n}"), "{}", types);
+    assert_eq!(types.matches("pub struct").count(), 4);
+}
+
+#[test]
+fn test_names_are_sanitized_and_kept_apart() {
+    let value = serde_json::json!({
+        "userId": 1,
+        "user_id": 2,
+        "type": "a",
+        "self": "b",
+        "2fa": false,
+        "": 0,
+        "HTTPServer": "c",
+        "data": {"a": 1},
+        "meta": {"data": {"b": "x"}, "DATA": {"a": 2}},
+    });
+    let types = rust_types(&value, "Root");
+    for line in [
+        "    #[serde(rename = \"userId\")]\n    pub user_id: i64,\n    #[serde(rename = \"user_id\")]\n    pub user_id_2: i64,\n",
+        "    pub r#type: String,\n",
+        "    #[serde(rename = \"self\")]\n    pub self_: String,\n",
+        "    #[serde(rename = \"2fa\")]\n    pub field_2fa: bool,\n",
+        "    #[serde(rename = \"\")]\n    pub field: i64,\n",
+        "    #[serde(rename = \"HTTPServer\")]\n    pub http_server: String,\n",
+        "    pub data: Data,\n",
+        // A different object under the same name gets its own struct, the same one is shared
+        "pub struct Meta {\n    pub data: Data2,\n    #[serde(rename = \"DATA\")]\n    pub data_2: Data,\n}",
+        "pub struct Data2 {\n    pub b: String,\n}",
+    ] {
+        assert!(types.contains(line), "{:?} in {}", line, types);
+    }
+    assert_eq!(rust_types(&serde_json::json!({"a": {"b": 1}}), "Option"), rust_types(&serde_json::json!({"a": {"b": 1}}), "Option2"));
+    assert_eq!(rust_types(&serde_json::json!("text"), "Root"), "use serde::{Deserialize, Serialize};\n\npub type Root = String;\n");
+}
@@ -0,0 +1,68 @@
+use crate::shape::{infer, merge, Shape};
+use serde_json::{json, Map, Value};
+
+// A JSON Schema (draft 2020-12) every one of `records` fits, a document on its own or the records
+// of a stream. A property some records lack isn't required, integers are told apart from other
+// numbers, and strings that take a few values over and over get them as an enum.
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+pub fn json_schema(records: &[Value]) -> Value {
+    let shape = records.iter().map(infer).fold(Shape::Unknown, merge);
+    let mut schema = Map::new();
+    schema.insert("$schema".to_string(), DRAFT.into());
+    if let Value::Object(keywords) = schema_for(&shape) {
+        schema.extend(keywords);
+    }
+    Value::Object(schema)
+}
+
+fn schema_for(shape: &Shape) -> Value {
+    match shape {
+        // Allows anything, like the elements of an array that was always empty
+        Shape::Unknown => json!({}),
+        Shape::Null => json!({"type": "null"}),
+        Shape::Bool => json!({"type": "boolean"}),
+        Shape::Integer { .. } => json!({"type": "integer"}),
+        Shape::Float => json!({"type": "number"}),
+        Shape::String(strings) => match strings.enum_candidates() {
+            Some(values) => json!({"type": "string", "enum": values}),
+            None => json!({"type": "string"}),
+        },
+        Shape::Array(item) => json!({"type": "array", "items": schema_for(item)}),
+        Shape::Object(fields) => {
+            let properties: Map<String, Value> = fields.iter().map(|field| (field.key.clone(), schema_for(&field.shape))).collect();
+            let required: Vec<_> = fields.iter().filter(|field| !field.optional).map(|field| field.key.as_str()).collect();
+            let mut schema = json!({"type": "object", "properties": properties});
+            if !required.is_empty() {
+                schema["required"] = required.into();
+            }
+            schema
+        }
+        // Members that are nothing but a type share a list of them, as in ["string", "null"]
+        Shape::Union(members) => {
+            let schemas: Vec<_> = members.iter().map(schema_for).collect();
+            let types: Option<Vec<_>> = schemas.iter().map(|schema| match schema.as_object() {
+                Some(keywords) if keywords.len() == 1 => keywords.get("type").cloned(),
+                _ => None,
+            }).collect();
+            match types {
+                Some(types) => json!({"type": types}),
+                None => json!({"anyOf": schemas}),
+            }
+        }
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_json_schema_matches_the_golden_files() {
+    for (input, expected) in [
+        (include_str!("../tests/golden/users.json"), include_str!("../tests/golden/users.schema.json")),
+        (include_str!("../tests/golden/events.json"), include_str!("../tests/golden/events.schema.json")),
+    ] {
+        let value: Value = serde_json::from_str(input).unwrap();
+        assert_eq!(serde_json::to_string_pretty(&json_schema(&[value])).unwrap() + "\n", expected);
+    }
+}
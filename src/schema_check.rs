@@ -0,0 +1,466 @@
+use crate::locale::{tr, trf};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+// Checks a value against a JSON Schema (draft 2020-12) before it's encoded. Every keyword that
+// constrains a value is checked, annotations such as format and title are ignored, and $ref has to
+// point into the same document.
+
+const TYPES: [&str; 7] = ["null", "boolean", "integer", "number", "string", "array", "object"];
+
+// $refs followed one after the other without going into the value, beyond which the schema is
+// taken to loop
+const MAX_REF_DEPTH: usize = 64;
+
+pub struct CompiledSchema {
+    root: Value,
+    patterns: HashMap<String, Regex>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    // JSON Pointer to the offending value, empty for the document itself
+    pub instance_path: String,
+    pub keyword: String,
+    pub message: String,
+}
+
+impl CompiledSchema {
+    pub fn compile(text: &str) -> Result<CompiledSchema, String> {
+        let root: Value = serde_json::from_str(text).map_err(|e| trf("The schema isn't valid JSON: {}", &[&e]))?;
+        let mut patterns = HashMap::new();
+        check_schema(&root, &root, "", &mut patterns, &mut HashSet::new()).map_err(|(path, e)| trf("Invalid schema at {}: {}", &[&pointer_name(&path), &e]))?;
+        Ok(CompiledSchema { root, patterns })
+    }
+
+    pub fn validate(&self, instance: &Value) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(&self.root, instance, "", 0, &mut violations);
+        violations
+    }
+
+    fn is_valid(&self, schema: &Value, instance: &Value, path: &str, refs: usize) -> bool {
+        let mut violations = Vec::new();
+        self.check(schema, instance, path, refs, &mut violations);
+        violations.is_empty()
+    }
+
+    // `refs` counts the $refs followed since the last step into the value
+    fn check(&self, schema: &Value, instance: &Value, path: &str, refs: usize, violations: &mut Vec<Violation>) {
+        let mut fail = |keyword: &str, message: String| violations.push(violation(path, keyword, message));
+        let keywords = match schema {
+            Value::Object(keywords) => keywords,
+            Value::Bool(false) => return fail("false", tr("No value is allowed here").to_string()),
+            _ => return,
+        };
+        if let Some(types) = keywords.get("type") {
+            let names: Vec<&str> = match types {
+                Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                name => name.as_str().into_iter().collect(),
+            };
+            if !names.iter().any(|name| has_type(instance, name)) {
+                fail("type", trf("Expected {}, found {}", &[&names.join(tr(" or ")), &type_of(instance)]));
+            }
+        }
+        if let Some(Value::Array(values)) = keywords.get("enum") {
+            if !values.iter().any(|value| equal(value, instance)) {
+                fail("enum", tr("Not one of the allowed values").to_string());
+            }
+        }
+        if let Some(value) = keywords.get("const") {
+            if !equal(value, instance) {
+                fail("const", trf("Should be {}", &[value]));
+            }
+        }
+        match instance {
+            Value::String(text) => {
+                let length = text.chars().count() as f64;
+                if keywords.get("minLength").and_then(Value::as_f64).is_some_and(|min| length < min) {
+                    fail("minLength", trf("Shorter than {} characters", &[&keywords["minLength"]]));
+                }
+                if keywords.get("maxLength").and_then(Value::as_f64).is_some_and(|max| length > max) {
+                    fail("maxLength", trf("Longer than {} characters", &[&keywords["maxLength"]]));
+                }
+                if let Some(pattern) = keywords.get("pattern").and_then(Value::as_str) {
+                    if !self.patterns[pattern].is_match(text) {
+                        fail("pattern", trf("Doesn't match the pattern {}", &[&pattern]));
+                    }
+                }
+            }
+            Value::Number(number) => {
+                let n = number.as_f64().unwrap_or_default();
+                let bound = |keyword: &str| keywords.get(keyword).and_then(Value::as_f64);
+                if bound("minimum").is_some_and(|min| n < min) {
+                    fail("minimum", trf("Less than the minimum of {}", &[&keywords["minimum"]]));
+                }
+                if bound("maximum").is_some_and(|max| n > max) {
+                    fail("maximum", trf("More than the maximum of {}", &[&keywords["maximum"]]));
+                }
+                if bound("exclusiveMinimum").is_some_and(|min| n <= min) {
+                    fail("exclusiveMinimum", trf("Not more than {}", &[&keywords["exclusiveMinimum"]]));
+                }
+                if bound("exclusiveMaximum").is_some_and(|max| n >= max) {
+                    fail("exclusiveMaximum", trf("Not less than {}", &[&keywords["exclusiveMaximum"]]));
+                }
+                if bound("multipleOf").is_some_and(|factor| !is_multiple(number, factor)) {
+                    fail("multipleOf", trf("Not a multiple of {}", &[&keywords["multipleOf"]]));
+                }
+            }
+            Value::Object(map) => {
+                let count = map.len() as f64;
+                if keywords.get("minProperties").and_then(Value::as_f64).is_some_and(|min| count < min) {
+                    fail("minProperties", trf("Fewer than {} properties", &[&keywords["minProperties"]]));
+                }
+                if keywords.get("maxProperties").and_then(Value::as_f64).is_some_and(|max| count > max) {
+                    fail("maxProperties", trf("More than {} properties", &[&keywords["maxProperties"]]));
+                }
+                for key in keywords.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+                    if !map.contains_key(key) {
+                        fail("required", trf("Missing required property {}", &[&Value::from(key)]));
+                    }
+                }
+                for (key, needed) in keywords.get("dependentRequired").and_then(Value::as_object).into_iter().flatten() {
+                    for needed in needed.as_array().into_iter().flatten().filter_map(Value::as_str).filter(|_| map.contains_key(key)) {
+                        if !map.contains_key(needed) {
+                            fail("dependentRequired", trf("Missing property {}, which {} needs", &[&Value::from(needed), &Value::from(key.as_str())]));
+                        }
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let count = items.len() as f64;
+                if keywords.get("minItems").and_then(Value::as_f64).is_some_and(|min| count < min) {
+                    fail("minItems", trf("Fewer than {} items", &[&keywords["minItems"]]));
+                }
+                if keywords.get("maxItems").and_then(Value::as_f64).is_some_and(|max| count > max) {
+                    fail("maxItems", trf("More than {} items", &[&keywords["maxItems"]]));
+                }
+                if keywords.get("uniqueItems") == Some(&Value::Bool(true)) {
+                    if let Some((a, b)) = (0..items.len()).flat_map(|a| (a + 1..items.len()).map(move |b| (a, b))).find(|&(a, b)| equal(&items[a], &items[b])) {
+                        fail("uniqueItems", trf("Items {} and {} are the same", &[&a, &b]));
+                    }
+                }
+            }
+            Value::Null | Value::Bool(_) => {}
+        }
+        self.check_children(keywords, instance, path, violations);
+        self.check_applicators(keywords, instance, path, refs, violations);
+    }
+
+    // Keywords that check the properties or items of the value
+    fn check_children(&self, keywords: &Map<String, Value>, instance: &Value, path: &str, violations: &mut Vec<Violation>) {
+        match instance {
+            Value::Object(map) => {
+                let properties = keywords.get("properties").and_then(Value::as_object);
+                let pattern_properties = keywords.get("patternProperties").and_then(Value::as_object);
+                for (key, value) in map {
+                    let at = format!("{}/{}", path, escape_pointer_token(key));
+                    let mut matched = false;
+                    if let Some(schema) = properties.and_then(|properties| properties.get(key)) {
+                        matched = true;
+                        self.check(schema, value, &at, 0, violations);
+                    }
+                    for (_, schema) in pattern_properties.into_iter().flatten().filter(|(pattern, _)| self.patterns[pattern.as_str()].is_match(key)) {
+                        matched = true;
+                        self.check(schema, value, &at, 0, violations);
+                    }
+                    match keywords.get("additionalProperties") {
+                        Some(Value::Bool(false)) if !matched => {
+                            violations.push(violation(path, "additionalProperties", trf("Property {} isn't allowed", &[&Value::from(key.as_str())])));
+                        }
+                        Some(schema) if !matched => self.check(schema, value, &at, 0, violations),
+                        _ => {}
+                    }
+                    if let Some(schema) = keywords.get("propertyNames") {
+                        self.check(schema, &Value::from(key.as_str()), &at, 0, violations);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let prefix = keywords.get("prefixItems").and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+                for (index, item) in items.iter().enumerate() {
+                    let at = format!("{}/{}", path, index);
+                    if let Some(schema) = prefix.get(index).or_else(|| keywords.get("items")) {
+                        self.check(schema, item, &at, 0, violations);
+                    }
+                }
+                if let Some(schema) = keywords.get("contains") {
+                    let matching = items.iter().enumerate().filter(|(index, item)| self.is_valid(schema, item, &format!("{}/{}", path, index), 0)).count();
+                    let min = keywords.get("minContains").and_then(Value::as_u64).unwrap_or(1);
+                    if (matching as u64) < min {
+                        violations.push(violation(path, "contains", trf("Fewer than {} items match contains", &[&min])));
+                    }
+                    if let Some(max) = keywords.get("maxContains").and_then(Value::as_u64).filter(|&max| matching as u64 > max) {
+                        violations.push(violation(path, "maxContains", trf("More than {} items match contains", &[&max])));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Keywords that check the value itself against other schemas
+    fn check_applicators(&self, keywords: &Map<String, Value>, instance: &Value, path: &str, refs: usize, violations: &mut Vec<Violation>) {
+        if let Some(schema) = keywords.get("$ref").and_then(Value::as_str).and_then(|reference| resolve(&self.root, reference)) {
+            if refs >= MAX_REF_DEPTH {
+                violations.push(violation(path, "$ref", tr("The schema refers to itself without end").to_string()));
+            } else {
+                self.check(schema, instance, path, refs + 1, violations);
+            }
+        }
+        if let Value::Object(map) = instance {
+            for (_, schema) in keywords.get("dependentSchemas").and_then(Value::as_object).into_iter().flatten().filter(|(key, _)| map.contains_key(*key)) {
+                self.check(schema, instance, path, refs, violations);
+            }
+        }
+        let schemas = |keyword: &str| keywords.get(keyword).and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+        for schema in schemas("allOf") {
+            self.check(schema, instance, path, refs, violations);
+        }
+        if keywords.contains_key("anyOf") && !schemas("anyOf").iter().any(|schema| self.is_valid(schema, instance, path, refs)) {
+            violations.push(violation(path, "anyOf", tr("Matches none of the anyOf schemas").to_string()));
+        }
+        if keywords.contains_key("oneOf") {
+            match schemas("oneOf").iter().filter(|schema| self.is_valid(schema, instance, path, refs)).count() {
+                0 => violations.push(violation(path, "oneOf", tr("Matches none of the oneOf schemas").to_string())),
+                1 => {}
+                n => violations.push(violation(path, "oneOf", trf("Matches {} of the oneOf schemas instead of one", &[&n]))),
+            }
+        }
+        if keywords.get("not").is_some_and(|schema| self.is_valid(schema, instance, path, refs)) {
+            violations.push(violation(path, "not", tr("Matches the schema under not").to_string()));
+        }
+        if let Some(condition) = keywords.get("if") {
+            let branch = if self.is_valid(condition, instance, path, refs) { keywords.get("then") } else { keywords.get("else") };
+            if let Some(schema) = branch {
+                self.check(schema, instance, path, refs, violations);
+            }
+        }
+    }
+}
+
+fn violation(path: &str, keyword: &str, message: String) -> Violation {
+    Violation { instance_path: path.to_string(), keyword: keyword.to_string(), message }
+}
+
+// Makes sure every keyword the checks rely on has the form they expect, so a mistake in the schema
+// is reported as one instead of letting values through. Fails with where in the schema and what.
+// A $ref target is checked the first time it's referred to, wherever it sits in the schema.
+fn check_schema(schema: &Value, root: &Value, path: &str, patterns: &mut HashMap<String, Regex>, references: &mut HashSet<String>) -> Result<(), (String, String)> {
+    let keywords = match schema {
+        Value::Bool(_) => return Ok(()),
+        Value::Object(keywords) => keywords,
+        _ => return Err((path.to_string(), tr("a schema has to be an object or true or false").to_string())),
+    };
+    for (keyword, value) in keywords {
+        let at = format!("{}/{}", path, escape_pointer_token(keyword));
+        let expected = |what: &str| -> Result<(), (String, String)> { Err((at.clone(), trf("{} has to be {}", &[keyword, &what]))) };
+        match keyword.as_str() {
+            "type" => {
+                let names = match value {
+                    Value::Array(names) => names.iter().collect(),
+                    name => vec![name],
+                };
+                if !names.iter().all(|name| name.as_str().is_some_and(|name| TYPES.contains(&name))) {
+                    return expected(tr("one of null, boolean, integer, number, string, array and object, or a list of them"));
+                }
+            }
+            "properties" | "patternProperties" | "$defs" | "dependentSchemas" => {
+                let Some(schemas) = value.as_object() else {
+                    return expected(tr("an object of schemas"));
+                };
+                for (key, schema) in schemas {
+                    if keyword == "patternProperties" {
+                        compile_pattern(key, patterns).map_err(|e| (at.clone(), e))?;
+                    }
+                    check_schema(schema, root, &format!("{}/{}", at, escape_pointer_token(key)), patterns, references)?;
+                }
+            }
+            "items" | "additionalProperties" | "propertyNames" | "contains" | "not" | "if" | "then" | "else" => {
+                check_schema(value, root, &at, patterns, references)?;
+            }
+            "prefixItems" | "allOf" | "anyOf" | "oneOf" => match value.as_array() {
+                Some(schemas) if !schemas.is_empty() => {
+                    for (index, schema) in schemas.iter().enumerate() {
+                        check_schema(schema, root, &format!("{}/{}", at, index), patterns, references)?;
+                    }
+                }
+                _ => return expected(tr("a list of schemas")),
+            },
+            "required" if !value.as_array().is_some_and(|keys| keys.iter().all(Value::is_string)) => return expected(tr("a list of strings")),
+            "dependentRequired" if !value.as_object().is_some_and(|map| map.values().all(|keys| keys.as_array().is_some_and(|keys| keys.iter().all(Value::is_string)))) => {
+                return expected(tr("an object of lists of strings"));
+            }
+            "enum" if !value.is_array() => return expected(tr("a list")),
+            "minLength" | "maxLength" | "minItems" | "maxItems" | "minProperties" | "maxProperties" | "minContains" | "maxContains" if !is_count(value) => {
+                return expected(tr("a whole number, 0 or more"));
+            }
+            "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" if !value.is_number() => return expected(tr("a number")),
+            "multipleOf" if !value.as_f64().is_some_and(|factor| factor > 0.0) => return expected(tr("a number above 0")),
+            "uniqueItems" if !value.is_boolean() => return expected(tr("true or false")),
+            "pattern" => match value.as_str() {
+                Some(pattern) => compile_pattern(pattern, patterns).map_err(|e| (at.clone(), e))?,
+                None => return expected(tr("a string")),
+            },
+            "$ref" => match value.as_str() {
+                Some(reference) if reference.starts_with('#') => match resolve(root, reference) {
+                    Some(target) if references.insert(reference.to_string()) => check_schema(target, root, &reference[1..], patterns, references)?,
+                    Some(_) => {}
+                    None => return Err((at, trf("{} points nowhere in the schema", &[&reference]))),
+                },
+                Some(_) => return Err((at, tr("only a $ref into the same schema, starting with #, is supported").to_string())),
+                None => return expected(tr("a string")),
+            },
+            // Their results depend on what the other keywords looked at, which isn't tracked
+            "unevaluatedProperties" | "unevaluatedItems" | "$dynamicRef" => return Err((at, tr("isn't supported").to_string())),
+            // Annotations and keywords of other vocabularies
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn compile_pattern(pattern: &str, patterns: &mut HashMap<String, Regex>) -> Result<(), String> {
+    if !patterns.contains_key(pattern) {
+        let regex = Regex::new(pattern).map_err(|e| trf("{} isn't a regular expression this app understands: {}", &[&pattern, &e]))?;
+        patterns.insert(pattern.to_string(), regex);
+    }
+    Ok(())
+}
+
+// "#" is the whole schema, "#/$defs/name" a JSON Pointer into it
+fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    root.pointer(reference.strip_prefix('#')?)
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match (name, instance) {
+        ("null", Value::Null) | ("boolean", Value::Bool(_)) | ("number", Value::Number(_)) | ("string", Value::String(_)) => true,
+        ("array", Value::Array(_)) | ("object", Value::Object(_)) => true,
+        ("integer", Value::Number(n)) => n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|n| n.fract() == 0.0),
+        _ => false,
+    }
+}
+
+fn type_of(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) if has_type(instance, "integer") => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// Numbers are equal by value, so 1 is 1.0
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) if (a.is_f64() || b.is_f64()) => a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b)),
+        (Value::Object(a), Value::Object(b)) => a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| equal(a, b))),
+        (a, b) => a == b,
+    }
+}
+
+fn is_multiple(number: &serde_json::Number, factor: f64) -> bool {
+    match (number.as_i64(), factor.fract() == 0.0) {
+        (Some(n), true) => n % factor as i64 == 0,
+        _ => {
+            let quotient = number.as_f64().unwrap_or_default() / factor;
+            (quotient - quotient.round()).abs() < 1e-9
+        }
+    }
+}
+
+fn is_count(value: &Value) -> bool {
+    value.is_u64() || value.as_f64().is_some_and(|n| n >= 0.0 && n.fract() == 0.0)
+}
+
+// RFC 6901 escaping so that copied paths are valid JSON Pointers
+pub fn escape_pointer_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+pub fn pointer_name(path: &str) -> &str {
+    if path.is_empty() {
+        tr("(root)")
+    } else {
+        path
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_escape_pointer_token() {
+    assert_eq!(escape_pointer_token("name"), "name");
+    assert_eq!(escape_pointer_token("a/b"), "a~1b");
+    assert_eq!(escape_pointer_token("m~n"), "m~0n");
+}
+
+#[cfg(test)]
+fn violations(schema: &str, instance: Value) -> Vec<(String, String)> {
+    let schema = CompiledSchema::compile(schema).unwrap();
+    schema.validate(&instance).into_iter().map(|violation| (violation.instance_path, violation.keyword)).collect()
+}
+
+#[test]
+fn test_required_type_and_pattern_violations() {
+    let schema = r##"{
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "properties": {
+            "id": {"type": "integer", "minimum": 1},
+            "email": {"type": "string", "pattern": "^[^@]+@[^@]+$"},
+            "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}}
+        },
+        "required": ["id", "email"],
+        "additionalProperties": false,
+        "$defs": {"tag": {"type": "string", "enum": ["a", "b"]}}
+    }"##;
+    assert_eq!(violations(schema, serde_json::json!({"id": 1.0, "email": "a@b.c", "tags": ["a"]})), []);
+    assert_eq!(violations(schema, serde_json::json!({"id": "1", "tags": ["a", 3]})), [
+        (String::new(), "required".to_string()),
+        ("/id".to_string(), "type".to_string()),
+        ("/tags/1".to_string(), "type".to_string()),
+        ("/tags/1".to_string(), "enum".to_string()),
+    ]);
+    assert_eq!(violations(schema, serde_json::json!({"id": 1.5, "email": "nobody", "x/y": 0})), [
+        ("/id".to_string(), "type".to_string()),
+        ("/email".to_string(), "pattern".to_string()),
+        (String::new(), "additionalProperties".to_string()),
+    ]);
+    let schema = CompiledSchema::compile(schema).unwrap();
+    let messages: Vec<_> = schema.validate(&serde_json::json!({"id": 0})).into_iter().map(|violation| violation.message).collect();
+    assert_eq!(messages, ["Missing required property \"email\"", "Less than the minimum of 1"]);
+}
+
+#[test]
+fn test_applicators_and_recursion() {
+    let schema = r##"{"oneOf": [{"type": "integer"}, {"type": "number", "maximum": 10}], "not": {"const": 3}}"##;
+    assert_eq!(violations(schema, serde_json::json!(12)), []);
+    assert_eq!(violations(schema, serde_json::json!(5)), [(String::new(), "oneOf".to_string())]);
+    assert_eq!(violations(schema, serde_json::json!(3.0)), [(String::new(), "oneOf".to_string()), (String::new(), "not".to_string())]);
+    let tree = r##"{"type": "object", "properties": {"children": {"type": "array", "items": {"$ref": "#"}, "uniqueItems": true}}}"##;
+    assert_eq!(violations(tree, serde_json::json!({"children": [{"children": [{}, 1]}]})), [("/children/0/children/1".to_string(), "type".to_string())]);
+    let looping = CompiledSchema::compile(r##"{"$defs": {"a": {"$ref": "#/$defs/a"}}, "$ref": "#/$defs/a"}"##).unwrap();
+    assert_eq!(looping.validate(&Value::Null)[0].keyword, "$ref");
+}
+
+#[test]
+fn test_schema_mistakes_are_reported() {
+    for (schema, error) in [
+        ("{", "The schema isn't valid JSON"),
+        (r#"{"type": "text"}"#, "Invalid schema at /type: type has to be one of"),
+        (r#"{"properties": {"a": {"pattern": "("}}}"#, "Invalid schema at /properties/a/pattern: ( isn't a regular expression"),
+        (r##"{"items": {"$ref": "#/$defs/missing"}}"##, "Invalid schema at /items/$ref: #/$defs/missing points nowhere"),
+        (r#"{"required": "id"}"#, "Invalid schema at /required: required has to be a list of strings"),
+        ("[]", "Invalid schema at (root): a schema has to be"),
+    ] {
+        let Err(e) = CompiledSchema::compile(schema) else { panic!("{} compiled", schema) };
+        assert!(e.starts_with(error), "{} for {}", e, schema);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::convert::{convert, BinaryFormat, ConvertOptions, Direction};
+use crate::error::ConvertError;
+use crate::files::{Encoding, MEGABYTE};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+// Request bodies larger than this are refused unless --max-body says otherwise
+pub const DEFAULT_MAX_BODY: usize = 16 * MEGABYTE;
+
+// A client that stops sending halfway doesn't hold on to its thread forever
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Request line and headers together, separate from the body limit
+const MAX_HEAD: usize = 64 * 1024;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    // Names lowercased
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    // `?compact`, `?compact=1` and `?compact=true` all count
+    fn flag(&self, name: &str) -> bool {
+        self.param(name).is_some_and(|value| matches!(value, "" | "1" | "true"))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: &Value) -> Response {
+        Response { status, content_type: "application/json", body: value.to_string().into_bytes() }
+    }
+
+    // Errors are JSON too, e.g. {"error": {"kind": "MsgpackDecode", "message": "…", "offset": 3}}
+    fn error(status: u16, kind: &str, message: &str) -> Response {
+        Response::json(status, &json!({"error": {"kind": kind, "message": message}}))
+    }
+
+    fn conversion_error(e: &ConvertError) -> Response {
+        let mut error = json!({"kind": e.kind(), "message": e.to_string()});
+        if let Some(offset) = e.offset() {
+            error["offset"] = json!(offset);
+        }
+        if let ConvertError::JsonParse { line, column, .. } = e {
+            error["line"] = json!(line);
+            error["column"] = json!(column);
+        }
+        if let ConvertError::Unsupported { path, .. } = e {
+            error["path"] = json!(path);
+        }
+        Response::json(400, &json!({ "error": error }))
+    }
+}
+
+pub fn serve(address: &str, max_body: usize) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+    let local = listener.local_addr().map_err(|e| e.to_string())?;
+    eprintln!("Listening on http://{}", local);
+    serve_on(listener, max_body);
+    Ok(())
+}
+
+// One thread per connection, each connection answers a single request
+pub fn serve_on(listener: TcpListener, max_body: usize) {
+    for stream in listener.incoming().flatten() {
+        thread::spawn(move || handle_connection(stream, max_body));
+    }
+}
+
+fn handle_connection(stream: TcpStream, max_body: usize) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let response = match read_request(&mut BufReader::new(stream), max_body) {
+        Ok(request) => respond(&request),
+        Err(response) => response,
+    };
+    let _ = write_response(&mut writer, &response);
+}
+
+pub fn read_request(reader: &mut impl BufRead, max_body: usize) -> Result<Request, Response> {
+    let bad_request = |message: &str| Response::error(400, "BadRequest", message);
+    let mut head_len = 0;
+    let mut line = String::new();
+    let mut read_line = |line: &mut String| -> Result<(), Response> {
+        line.clear();
+        let read = reader.by_ref().take((MAX_HEAD - head_len) as u64).read_line(line)
+            .map_err(|e| bad_request(&e.to_string()))?;
+        head_len += read;
+        if read == 0 || !line.ends_with('\n') {
+            return Err(bad_request("Incomplete request head"));
+        }
+        line.truncate(line.trim_end().len());
+        Ok(())
+    };
+
+    read_line(&mut line)?;
+    let mut parts = line.split(' ');
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(bad_request("Malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.split('&').filter(|pair| !pair.is_empty()).map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key.to_string(), value.to_string())
+        }).collect(),
+        ..Default::default()
+    };
+    loop {
+        read_line(&mut line)?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(bad_request("Malformed header"));
+        };
+        request.headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    if request.header("transfer-encoding").is_some() {
+        return Err(Response::error(411, "LengthRequired", "Chunked bodies are not supported, send a Content-Length"));
+    }
+    let len = match request.header("content-length") {
+        Some(len) => len.parse::<usize>().map_err(|_| bad_request("Invalid Content-Length"))?,
+        None => 0,
+    };
+    if len > max_body {
+        return Err(Response::error(413, "TooLarge", &format!("The body is {} bytes, the limit is {}", len, max_body)));
+    }
+    request.body = vec![0; len];
+    reader.read_exact(&mut request.body).map_err(|e| bad_request(&e.to_string()))?;
+    Ok(request)
+}
+
+pub fn respond(request: &Request) -> Response {
+    let direction = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/healthz") => return Response::json(200, &json!({"status": "ok"})),
+        ("POST", "/to-json") => Direction::ToJson,
+        ("POST", "/to-msgpack") => Direction::ToMessagePack,
+        (_, "/healthz" | "/to-json" | "/to-msgpack") => {
+            return Response::error(405, "MethodNotAllowed", &format!("{} is not allowed on {}", request.method, request.path));
+        }
+        _ => return Response::error(404, "NotFound", &format!("No endpoint at {}", request.path)),
+    };
+    let encoding = match message_encoding(request, direction) {
+        Ok(encoding) => encoding,
+        Err(response) => return response,
+    };
+    let format = match request.param("format") {
+        None | Some("msgpack") => BinaryFormat::MessagePack,
+        Some("cbor") => BinaryFormat::Cbor,
+        Some(other) => return Response::error(400, "BadRequest", &format!("Unknown format {}, expected msgpack or cbor", other)),
+    };
+    let options = ConvertOptions::default()
+        .direction(direction)
+        .format(format)
+        .encoding(encoding)
+        .compact(request.flag("compact"))
+        .lossy(request.flag("lossy"))
+        .stream(request.flag("stream"));
+    match convert(&request.body, &options) {
+        Ok((body, _warnings)) => {
+            let content_type = match (direction, encoding, options.stream) {
+                (Direction::ToJson, _, true) => "application/x-ndjson",
+                (Direction::ToJson, _, false) => "application/json",
+                (Direction::ToMessagePack, None, _) if format == BinaryFormat::Cbor => "application/cbor",
+                (Direction::ToMessagePack, None, _) => "application/msgpack",
+                (Direction::ToMessagePack, Some(_), _) => "text/plain",
+            };
+            Response { status: 200, content_type, body }
+        }
+        Err(e) => Response::conversion_error(&e),
+    }
+}
+
+// The MessagePack side is raw bytes unless `?encoding=` says otherwise. Without one, text bodies
+// sent to /to-json are read as base64, and /to-msgpack answers with base64 when text is asked for.
+fn message_encoding(request: &Request, direction: Direction) -> Result<Option<Encoding>, Response> {
+    match request.param("encoding") {
+        Some("raw") => return Ok(None),
+        Some("base64") => return Ok(Some(Encoding::Base64)),
+        Some("base64url") => return Ok(Some(Encoding::Base64Url)),
+        Some("hex") => return Ok(Some(Encoding::Hex)),
+        Some(other) => {
+            return Err(Response::error(400, "BadRequest", &format!("Unknown encoding {}, expected raw, base64, base64url or hex", other)));
+        }
+        None => {}
+    }
+    let header = match direction {
+        Direction::ToJson => request.header("content-type"),
+        Direction::ToMessagePack => request.header("accept"),
+    };
+    Ok(header.filter(|value| value.starts_with("text/plain")).map(|_| Encoding::Base64))
+}
+
+fn write_response(writer: &mut impl Write, response: &Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        _ => "",
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len(),
+    )?;
+    writer.write_all(&response.body)?;
+    writer.flush()
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn request(head: &str, body: &[u8]) -> Result<Request, Response> {
+    let mut raw = format!("{}\r\nContent-Length: {}\r\n\r\n", head, body.len()).into_bytes();
+    raw.extend_from_slice(body);
+    read_request(&mut &raw[..], 1024)
+}
+
+#[test]
+fn test_endpoints_convert_both_ways() {
+    let response = respond(&request("POST /to-msgpack HTTP/1.1", br#"{"a": 1}"#).unwrap());
+    assert_eq!(response, Response { status: 200, content_type: "application/msgpack", body: vec![0x81, 0xa1, 0x61, 0x01] });
+    let response = respond(&request("POST /to-msgpack HTTP/1.1\r\nAccept: text/plain", br#"{"a": 1}"#).unwrap());
+    assert_eq!(response.body, b"gaFhAQ==");
+
+    let response = respond(&request("POST /to-json?compact=1 HTTP/1.1\r\nContent-Type: application/msgpack", &[0x81, 0xa1, 0x61, 0x01]).unwrap());
+    assert_eq!((response.status, response.body), (200, br#"{"a":1}"#.to_vec()));
+    let response = respond(&request("POST /to-json?encoding=hex&compact HTTP/1.1", b"81a16101").unwrap());
+    assert_eq!(response.body, br#"{"a":1}"#);
+    let response = respond(&request("POST /to-json?compact HTTP/1.1\r\nContent-Type: text/plain", b"gaFhAQ==").unwrap());
+    assert_eq!(response.body, br#"{"a":1}"#);
+
+    assert_eq!(respond(&request("GET /healthz HTTP/1.1", b"").unwrap()).status, 200);
+    assert_eq!(respond(&request("GET /to-json HTTP/1.1", b"").unwrap()).status, 405);
+    assert_eq!(respond(&request("GET /favicon.ico HTTP/1.1", b"").unwrap()).status, 404);
+}
+
+#[test]
+fn test_errors_come_back_as_json() {
+    let response = respond(&request("POST /to-json HTTP/1.1", &[0x82, 0xa1, 0x61]).unwrap());
+    assert_eq!(response.status, 400);
+    let error: Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(error["error"]["kind"], "MsgpackDecode");
+    assert_eq!(error["error"]["offset"], 3);
+
+    let response = respond(&request("POST /to-msgpack HTTP/1.1", b"{\"a\": }").unwrap());
+    let error: Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!((&error["error"]["kind"], &error["error"]["line"], &error["error"]["column"]), (&json!("JsonParse"), &json!(1), &json!(7)));
+
+    let Err(response) = request("POST /to-json HTTP/1.1", &[0; 2048]) else {
+        panic!("over the limit");
+    };
+    assert_eq!(response.status, 413);
+}
+
+#[test]
+fn test_serves_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || serve_on(listener, DEFAULT_MAX_BODY));
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(b"POST /to-json?compact HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4\r\n\r\n\x81\xa1\x61\x01").unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert!(reply.starts_with("HTTP/1.1 200 OK\r\n"), "{}", reply);
+    assert!(reply.contains("Content-Type: application/json\r\n"));
+    assert!(reply.ends_with("\r\n\r\n{\"a\":1}"));
+}
@@ -0,0 +1,301 @@
+use crate::convert::BinaryFormat;
+use crate::decode::decode_value_at;
+use crate::files::Encoding;
+use crate::locale::{tr, trf};
+use crate::settings::{config_file, Settings};
+use base64::{engine::general_purpose, Engine};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+// Panes bigger than this are left out of the session file instead of bloating it
+pub const MAX_PANE_BYTES: usize = 1024 * 1024;
+
+// Format of exported sessions, raised whenever an older build would read an export wrongly
+pub const EXPORT_VERSION: u64 = 1;
+
+// Open tabs restored on the next start
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct Session {
+    pub tabs: Vec<TabSession>,
+    pub active_tab: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct TabSession {
+    pub title: String,
+    pub json_input: String,
+    pub messagepack_output: String,
+    pub messagepack_input: String,
+    pub json_output: String,
+    pub diff_left: String,
+    pub diff_right: String,
+    pub compare_expected: String,
+    pub compare_actual: String,
+    // Panes that were too large to keep, with their size at the time
+    pub skipped: Vec<(String, usize)>,
+}
+
+impl TabSession {
+    // Empties any pane over the size limit and records it in `skipped`
+    pub fn limit_pane_sizes(&mut self) {
+        self.skipped.clear();
+        let panes = [
+            ("JSON Input", &mut self.json_input),
+            ("MessagePack Output", &mut self.messagepack_output),
+            ("MessagePack Input", &mut self.messagepack_input),
+            ("JSON Output", &mut self.json_output),
+            ("Diff Left", &mut self.diff_left),
+            ("Diff Right", &mut self.diff_right),
+            ("Compare Expected", &mut self.compare_expected),
+            ("Compare Actual", &mut self.compare_actual),
+        ];
+        for (name, text) in panes {
+            if text.len() > MAX_PANE_BYTES {
+                self.skipped.push((name.to_string(), text.len()));
+                text.clear();
+            }
+        }
+    }
+
+    // Logged after a restore that had to leave panes out
+    pub fn skipped_notice(&self) -> Option<String> {
+        if self.skipped.is_empty() {
+            return None;
+        }
+        let panes: Vec<String> = self.skipped.iter()
+            .map(|(name, len)| trf("{} ({} bytes)", &[&tr(name), len]))
+            .collect();
+        Some(trf("Not restored, larger than the {} byte limit: {}", &[&MAX_PANE_BYTES, &panes.join(", ")]))
+    }
+}
+
+impl Session {
+    pub fn load() -> Option<Session> {
+        let text = fs::read_to_string(session_path()?).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = session_path().ok_or("Failed to locate a config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let text = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize session: {}", e))?;
+        fs::write(&path, text).map_err(|e| format!("Failed to write session: {}", e))
+    }
+
+    // Used when restoring is switched off, so nothing from earlier runs stays on disk either
+    pub fn delete() -> Result<(), String> {
+        match session_path() {
+            Some(path) if path.exists() => {
+                fs::remove_file(path).map_err(|e| format!("Failed to delete session: {}", e))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn session_path() -> Option<PathBuf> {
+    config_file("session.json")
+}
+
+// A whole working state to hand to someone else: the settings, and every tab with all of its
+// panes, their undo history and the bytes behind them. Nothing is left out for its size.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct SessionExport {
+    pub version: u64,
+    // None after an import whose settings didn't read
+    pub settings: Option<Settings>,
+    pub tabs: Vec<ExportedTab>,
+    pub active_tab: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct ExportedTab {
+    #[serde(flatten)]
+    pub panes: TabSession,
+    // The binary file the MessagePack input was loaded from
+    pub messagepack_file: Option<ExportedFile>,
+    // Raw bytes behind the MessagePack output
+    pub messagepack_bytes: Option<Base64Bytes>,
+    // Undo and redo snapshots of each pane, in the order of Pane::ALL
+    pub histories: Vec<PaneSnapshots>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct ExportedFile {
+    pub name: String,
+    pub bytes: Base64Bytes,
+    pub encoding: Encoding,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct PaneSnapshots {
+    pub undo: Vec<String>,
+    pub redo: Vec<String>,
+}
+
+// Bytes written as base64 text, so the export reads the same as JSON
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Base64Bytes(pub Vec<u8>);
+
+impl Serialize for Base64Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&general_purpose::STANDARD.encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Base64Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Base64Bytes, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        general_purpose::STANDARD.decode(text).map(Base64Bytes).map_err(D::Error::custom)
+    }
+}
+
+impl SessionExport {
+    // MessagePack made by the app itself, or JSON when `json`
+    pub fn write(&self, json: bool) -> Result<Vec<u8>, String> {
+        let value = serde_json::to_value(self).map_err(|e| format!("Failed to serialize session: {}", e))?;
+        if json {
+            return serde_json::to_vec_pretty(&value).map_err(|e| format!("Failed to serialize session: {}", e));
+        }
+        Ok(BinaryFormat::MessagePack.encode(&value)?)
+    }
+
+    // An export in either form. Fields this build doesn't know are ignored, and the settings or
+    // a tab that don't read are left out of the import, with a note for each, instead of failing it.
+    pub fn read(bytes: &[u8]) -> Result<(SessionExport, Vec<String>), String> {
+        let value = match bytes.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{') => serde_json::from_slice(bytes).map_err(|e| e.to_string())?,
+            _ => decode_value_at(bytes, 0, false)?.0,
+        };
+        let Value::Object(mut fields) = value else {
+            return Err(tr("Not an exported session").to_string());
+        };
+        match fields.get("version").and_then(Value::as_u64) {
+            None | Some(0) => return Err(tr("Not an exported session").to_string()),
+            Some(version) if version > EXPORT_VERSION => {
+                return Err(trf("Exported in format {}, newer than the {} this version reads", &[&version, &EXPORT_VERSION]));
+            }
+            Some(_) => {}
+        }
+        let mut notes = Vec::new();
+        let settings = fields.remove("settings").and_then(|settings| match serde_json::from_value(settings) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                notes.push(trf("Settings left out: {}", &[&e]));
+                None
+            }
+        });
+        let tabs = match fields.remove("tabs") {
+            Some(Value::Array(tabs)) => tabs,
+            _ => Vec::new(),
+        };
+        let tabs: Vec<ExportedTab> = tabs.into_iter().enumerate().filter_map(|(index, tab)| match serde_json::from_value(tab) {
+            Ok(tab) => Some(tab),
+            Err(e) => {
+                notes.push(trf("Tab {} left out: {}", &[&(index + 1), &e]));
+                None
+            }
+        }).collect();
+        let active_tab = fields.get("active_tab").and_then(Value::as_u64).map_or(0, |active| active as usize);
+        Ok((SessionExport { version: EXPORT_VERSION, settings, tabs, active_tab }, notes))
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_session_skips_oversized_panes() {
+    let mut session = TabSession {
+        json_input: "{}".to_string(),
+        json_output: "x".repeat(MAX_PANE_BYTES + 1),
+        ..Default::default()
+    };
+    session.limit_pane_sizes();
+    assert_eq!(session.json_input, "{}");
+    assert!(session.json_output.is_empty());
+    assert_eq!(session.skipped, vec![("JSON Output".to_string(), MAX_PANE_BYTES + 1)]);
+    assert_eq!(
+        session.skipped_notice().unwrap(),
+        format!("Not restored, larger than the 1048576 byte limit: JSON Output ({} bytes)", MAX_PANE_BYTES + 1)
+    );
+}
+
+#[test]
+fn test_session_round_trips_through_json() {
+    let tab = TabSession {
+        title: "Tab 2".to_string(),
+        messagepack_input: "gw==".to_string(),
+        ..Default::default()
+    };
+    let session = Session { tabs: vec![TabSession::default(), tab], active_tab: 1 };
+    let text = serde_json::to_string(&session).unwrap();
+    assert_eq!(serde_json::from_str::<Session>(&text).unwrap(), session);
+    assert_eq!(serde_json::from_str::<Session>("{}").unwrap(), Session::default());
+}
+
+#[cfg(test)]
+fn populated_export() -> SessionExport {
+    let tab = ExportedTab {
+        panes: TabSession {
+            title: "Capture".to_string(),
+            json_input: "{\"a\": 1}".to_string(),
+            messagepack_output: "gaFhAQ==".to_string(),
+            json_output: "x".repeat(MAX_PANE_BYTES + 1),
+            compare_actual: "[]".to_string(),
+            ..Default::default()
+        },
+        messagepack_file: Some(ExportedFile { name: "capture.msgpack".to_string(), bytes: Base64Bytes(vec![0x81, 0xa1, 0x61, 0x01, 0xc1]), encoding: Encoding::Hex }),
+        messagepack_bytes: Some(Base64Bytes(vec![0x81, 0xa1, 0x61, 0x01])),
+        histories: vec![PaneSnapshots { undo: vec!["".to_string(), "{}".to_string()], redo: vec!["[1]".to_string()] }, PaneSnapshots::default()],
+    };
+    let settings = Settings { json_indent: 4, sort_keys: true, ..Default::default() };
+    SessionExport { version: EXPORT_VERSION, settings: Some(settings), tabs: vec![ExportedTab::default(), tab], active_tab: 1 }
+}
+
+#[test]
+fn test_session_export_round_trips() {
+    let export = populated_export();
+    for json in [false, true] {
+        let bytes = export.write(json).unwrap();
+        assert_eq!(bytes[0] == b'{', json);
+        assert_eq!(SessionExport::read(&bytes).unwrap(), (export.clone(), Vec::new()));
+    }
+    let value = serde_json::to_value(&export).unwrap();
+    assert_eq!(value["tabs"][1]["messagepack_file"]["bytes"], "gaFhAcE=");
+    assert_eq!(value["tabs"][1]["title"], "Capture");
+}
+
+#[test]
+fn test_session_import_checks_the_version_and_skips_what_it_cant_read() {
+    let mut value = serde_json::to_value(populated_export()).unwrap();
+    value["version"] = (EXPORT_VERSION + 1).into();
+    assert_eq!(
+        SessionExport::read(value.to_string().as_bytes()),
+        Err(format!("Exported in format {}, newer than the {} this version reads", EXPORT_VERSION + 1, EXPORT_VERSION))
+    );
+    assert!(SessionExport::read(b"{\"tabs\": []}").is_err());
+    assert!(SessionExport::read(&[0x93, 0x01, 0x02, 0x03]).is_err());
+
+    value["version"] = EXPORT_VERSION.into();
+    value["from_a_newer_build"] = true.into();
+    value["tabs"][1]["pinned"] = true.into();
+    value["tabs"][0]["messagepack_bytes"] = "not base64!".into();
+    value["settings"]["json_indent"] = "four".into();
+    let (export, notes) = SessionExport::read(&serde_json::to_vec(&value).unwrap()).unwrap();
+    assert_eq!(export.tabs, populated_export().tabs[1..]);
+    assert_eq!(export.settings, None);
+    assert_eq!(notes.len(), 2);
+    assert!(notes[0].starts_with("Settings left out: ") && notes[1].starts_with("Tab 1 left out: "), "{:?}", notes);
+}
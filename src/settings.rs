@@ -0,0 +1,598 @@
+use crate::compress::{Compression, DEFAULT_MAX_DECOMPRESSED_MB};
+use crate::convert::{BinaryFormat, ConvertOptions, Direction, TextFormat};
+use crate::ext_types::{BinForm, ExtRegistry, KeyForm};
+use crate::files::{OutputEncoding, MEGABYTE};
+use crate::format::JsonFormat;
+use crate::framing::Framing;
+use crate::locale::{self, tr, Language};
+use crate::recent::RecentFiles;
+use crate::redact::Redaction;
+use crate::timestamp::TimestampForm;
+use crate::zstd;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+pub const MIN_ZOOM: f32 = 0.5;
+pub const MAX_ZOOM: f32 = 3.0;
+pub const MAX_JSON_INDENT: usize = 8;
+pub const MAX_OUTPUT_DISPLAY_LIMIT_MB: usize = 1024;
+pub const MAX_DECOMPRESSED_LIMIT_MB: usize = 16 * 1024;
+pub const DEFAULT_MAX_INPUT_MB: usize = 256;
+pub const MAX_INPUT_LIMIT_MB: usize = 16 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub zoom: f32,
+    pub monospace: bool,
+    pub wrap_messagepack_output: bool,
+    pub messagepack_output_encoding: OutputEncoding,
+    pub wrap_json_output: bool,
+    pub json_indent: usize,
+    pub sort_keys: bool,
+    // Off for people who'd rather not have pasted payloads written to disk
+    pub restore_session: bool,
+    // Outputs above this many megabytes can't be edited and go to the line viewer
+    pub output_display_limit_mb: usize,
+    // One rule per line, see Redaction::parse
+    pub redaction_rules: String,
+    pub redact_keep_shape: bool,
+    // One input and one output pane with a direction toggle instead of both directions side by side
+    pub single_pane: bool,
+    // Picking an example or dropping a file also runs its conversion
+    pub auto_convert_examples: bool,
+    pub language: Language,
+    // What the MessagePack panes hold. None tells MessagePack and CBOR input apart by trying
+    // both, and encodes MessagePack.
+    pub binary_format: Option<BinaryFormat>,
+    // Decoding takes each length-prefixed frame as a record and gives an array of them, encoding
+    // frames each element of an array
+    pub framing: Framing,
+    // msgpack-RPC messages decode to objects naming their parts
+    pub label_rpc: bool,
+    // Decoders for the application's own ext types, see ExtRegistry
+    pub ext_types: String,
+    // 16-byte bins, and ext values of the one type, as UUID strings
+    pub uuids: bool,
+    pub uuid_ext_type: Option<i8>,
+    // Ext values of types without a decoder as {"$ext": type, "value": "<base64>"}, which encode
+    // back to the same ext value
+    pub tag_unknown_ext_types: bool,
+    // What bins decode to, None to refuse them
+    pub bin_form: Option<BinForm>,
+    // What maps with keys other than strings decode to, None to refuse them
+    pub key_form: Option<KeyForm>,
+    // RFC 3339 date-time strings encode to MessagePack timestamps
+    pub encode_timestamps: bool,
+    // What MessagePack timestamps decode to
+    pub timestamp_form: TimestampForm,
+    // Strings holding base64 or hex MessagePack decode to {"$nested", "$original"} objects, which
+    // encode back to strings
+    pub expand_nested: bool,
+    // Decoded records go one minified line each instead of into an array, and MessagePack or CBOR
+    // without framing is read as values back to back
+    pub ndjson_output: bool,
+    // What the JSON panes hold
+    pub text_format: TextFormat,
+    // The MessagePack output is compressed, ready to send as a compressed body
+    pub output_compression: Option<Compression>,
+    pub zstd_level: i32,
+    // Input that inflates to more than this many megabytes is turned down
+    pub max_decompressed_mb: usize,
+    // Pasted input that is more than this many megabytes, once decoded from base64 or hex, is
+    // turned down before converting. Files loaded as binary aren't held to it.
+    pub max_input_mb: usize,
+    // Lines of the JSON output that changed since the conversion before are tinted, and the
+    // MessagePack stats say how many bytes did
+    pub highlight_changes: bool,
+    // Convert to MessagePack refuses JSON that fails the tab's JSON Schema
+    pub block_on_schema_violations: bool,
+    // Files opened into the JSON and the MessagePack input pane
+    pub recent_json_files: RecentFiles,
+    pub recent_messagepack_files: RecentFiles,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            zoom: 1.0,
+            monospace: true,
+            wrap_messagepack_output: true,
+            messagepack_output_encoding: OutputEncoding::default(),
+            wrap_json_output: true,
+            json_indent: JsonFormat::default().indent,
+            sort_keys: JsonFormat::default().sort_keys,
+            restore_session: true,
+            output_display_limit_mb: 1,
+            redaction_rules: "password\n*token*\n*secret*".to_string(),
+            redact_keep_shape: false,
+            single_pane: false,
+            auto_convert_examples: true,
+            language: Language::default(),
+            binary_format: Some(BinaryFormat::MessagePack),
+            framing: Framing::None,
+            label_rpc: false,
+            ext_types: String::new(),
+            uuids: false,
+            uuid_ext_type: None,
+            tag_unknown_ext_types: true,
+            bin_form: Some(BinForm::Tagged),
+            key_form: Some(KeyForm::Pairs),
+            encode_timestamps: false,
+            timestamp_form: TimestampForm::default(),
+            expand_nested: false,
+            ndjson_output: false,
+            text_format: TextFormat::default(),
+            output_compression: None,
+            zstd_level: zstd::DEFAULT_LEVEL,
+            max_decompressed_mb: DEFAULT_MAX_DECOMPRESSED_MB,
+            max_input_mb: DEFAULT_MAX_INPUT_MB,
+            highlight_changes: true,
+            block_on_schema_violations: false,
+            recent_json_files: RecentFiles::default(),
+            recent_messagepack_files: RecentFiles::default(),
+        }
+    }
+}
+
+impl Settings {
+    // Missing or unreadable settings are not an error, we just start from the defaults.
+    pub fn load() -> Settings {
+        settings_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| Settings::from_json(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = settings_path().ok_or("Failed to locate a config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        fs::write(&path, text).map_err(|e| format!("Failed to write settings: {}", e))
+    }
+
+    pub fn editor_font(&self) -> egui::TextStyle {
+        if self.monospace {
+            egui::TextStyle::Monospace
+        } else {
+            egui::TextStyle::Body
+        }
+    }
+
+    pub fn output_display_limit(&self) -> usize {
+        self.output_display_limit_mb * MEGABYTE
+    }
+
+    pub fn output_compression(&self) -> Option<(Compression, i32)> {
+        self.output_compression.map(|compression| (compression, self.zstd_level))
+    }
+
+    pub fn max_decompressed(&self) -> usize {
+        self.max_decompressed_mb * MEGABYTE
+    }
+
+    pub fn max_input(&self) -> usize {
+        self.max_input_mb * MEGABYTE
+    }
+
+    pub fn json_format(&self) -> JsonFormat {
+        JsonFormat { indent: self.json_indent, sort_keys: self.sort_keys }
+    }
+
+    pub fn ext_types(&self) -> Result<ExtRegistry, String> {
+        ExtRegistry::parse(&self.ext_types)
+    }
+
+    // What conversions go by, leaving out the ext types if they don't parse
+    pub fn ext_registry(&self) -> ExtRegistry {
+        let mut registry = self.ext_types().unwrap_or_default().with_uuids(self.uuids, self.uuid_ext_type);
+        registry.tag_unknown = self.tag_unknown_ext_types;
+        registry.timestamps = self.encode_timestamps;
+        registry.timestamp_form = self.timestamp_form;
+        registry
+    }
+
+    // What the window converts with in `direction`. With NDJSON output it decodes a stream of
+    // records, and YAML input is read lossily: keys that aren't strings and several documents are
+    // taken in with a warning for each.
+    pub fn convert_options(&self, direction: Direction) -> ConvertOptions {
+        let to_json = direction == Direction::ToJson;
+        ConvertOptions::default()
+            .direction(direction)
+            .format(self.binary_format.unwrap_or_default())
+            .text(self.text_format)
+            .json_format(self.json_format())
+            .framing(self.framing)
+            .stream(to_json && self.ndjson_output)
+            .ndjson(to_json && self.ndjson_output)
+            .rpc(self.label_rpc)
+            .lossy(!to_json)
+            .ignore_trailing(true)
+            .nested(self.expand_nested)
+            .ext_registry(self.ext_registry())
+            .bin_form(self.bin_form)
+            .key_form(self.key_form)
+    }
+
+    pub fn redaction(&self) -> Redaction {
+        Redaction::parse(&self.redaction_rules, self.redact_keep_shape)
+    }
+
+    // Back to the defaults for everything in the Settings window. The layout, the redaction
+    // rules and the recent files are set elsewhere and stay as they are.
+    pub fn restore_defaults(&mut self) {
+        *self = Settings {
+            redaction_rules: std::mem::take(&mut self.redaction_rules),
+            redact_keep_shape: self.redact_keep_shape,
+            single_pane: self.single_pane,
+            recent_json_files: std::mem::take(&mut self.recent_json_files),
+            recent_messagepack_files: std::mem::take(&mut self.recent_messagepack_files),
+            ..Settings::default()
+        };
+    }
+
+    // A field that doesn't fit, e.g. after an older version wrote it with another type, falls
+    // back to its default without taking the other fields with it
+    fn from_json(text: &str) -> Settings {
+        let fields = match serde_json::from_str(text) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => serde_json::Map::new(),
+        };
+        let mut merged = match serde_json::to_value(Settings::default()) {
+            Ok(serde_json::Value::Object(defaults)) => defaults,
+            _ => serde_json::Map::new(),
+        };
+        for (key, value) in fields {
+            let previous = merged.insert(key.clone(), value);
+            if serde_json::from_value::<Settings>(serde_json::Value::Object(merged.clone())).is_err() {
+                match previous {
+                    Some(previous) => merged.insert(key, previous),
+                    None => merged.remove(&key),
+                };
+            }
+        }
+        let mut settings: Settings = serde_json::from_value(serde_json::Value::Object(merged)).unwrap_or_default();
+        settings.sanitize();
+        settings
+    }
+
+    fn sanitize(&mut self) {
+        if !self.zoom.is_finite() {
+            self.zoom = 1.0;
+        }
+        self.zoom = self.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.json_indent = self.json_indent.min(MAX_JSON_INDENT);
+        self.output_display_limit_mb = self.output_display_limit_mb.clamp(1, MAX_OUTPUT_DISPLAY_LIMIT_MB);
+    }
+}
+
+// Every preference in one place, opened from the gear button in the toolbar. True when an ext
+// types file was asked for.
+pub fn settings_window(ctx: &egui::Context, open: &mut bool, settings: &mut Settings) -> bool {
+    let mut open_ext_types = false;
+    egui::Window::new(tr("Settings"))
+        .id(egui::Id::new("settings"))
+        .open(open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::Grid::new("settings_grid").num_columns(2).spacing([16.0, 6.0]).show(ui, |ui| {
+                ui.strong(tr("Appearance"));
+                ui.end_row();
+
+                ui.label(tr("Zoom:"));
+                ui.horizontal(|ui| {
+                    let mut zoom = ctx.zoom_factor();
+                    let slider = egui::Slider::new(&mut zoom, MIN_ZOOM..=MAX_ZOOM)
+                        .step_by(0.1)
+                        .custom_formatter(|v, _| format!("{:.0}%", v * 100.0));
+                    if ui.add(slider).changed() {
+                        ctx.set_zoom_factor(zoom);
+                    }
+                    if ui.button(tr("Reset Zoom")).clicked() {
+                        ctx.set_zoom_factor(1.0);
+                    }
+                });
+                ui.end_row();
+
+                ui.label(tr("Language:"));
+                egui::ComboBox::from_id_source("language")
+                    .selected_text(settings.language.name())
+                    .show_ui(ui, |ui| {
+                        for language in Language::ALL {
+                            if ui.selectable_value(&mut settings.language, language, language.name()).clicked() {
+                                locale::set_language(language);
+                            }
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut settings.monospace, tr("Monospace editors"));
+                ui.end_row();
+
+                ui.strong(tr("JSON"));
+                ui.end_row();
+
+                ui.label(tr("JSON indent:"));
+                ui.add(egui::DragValue::new(&mut settings.json_indent).clamp_range(0..=MAX_JSON_INDENT));
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut settings.sort_keys, tr("Sort keys"));
+                ui.end_row();
+
+                ui.label(tr("Text format:"));
+                egui::ComboBox::from_id_source("text_format")
+                    .selected_text(settings.text_format.name())
+                    .show_ui(ui, |ui| {
+                        for format in TextFormat::ALL {
+                            ui.selectable_value(&mut settings.text_format, format, format.name());
+                        }
+                    })
+                    .response
+                    .on_hover_text(tr("What the JSON panes hold, JSON or YAML"));
+                ui.end_row();
+
+                ui.strong(tr("Outputs"));
+                ui.end_row();
+
+                ui.label(tr("Output limit:"));
+                ui.add(egui::DragValue::new(&mut settings.output_display_limit_mb)
+                    .clamp_range(1..=MAX_OUTPUT_DISPLAY_LIMIT_MB)
+                    .suffix(" MB"))
+                    .on_hover_text(tr("Larger outputs can't be edited and are shown in the read-only viewer"));
+                ui.end_row();
+
+                ui.label(tr("Binary format:"));
+                let format_name = |format: Option<BinaryFormat>| format.map_or(tr("Detect when decoding"), BinaryFormat::name);
+                egui::ComboBox::from_id_source("binary_format")
+                    .selected_text(format_name(settings.binary_format))
+                    .show_ui(ui, |ui| {
+                        for format in BinaryFormat::ALL.map(Some).into_iter().chain([None]) {
+                            ui.selectable_value(&mut settings.binary_format, format, format_name(format));
+                        }
+                    })
+                    .response
+                    .on_hover_text(tr("What the MessagePack panes hold, MessagePack or CBOR"));
+                ui.end_row();
+
+                ui.label(tr("Framing:"));
+                egui::ComboBox::from_id_source("framing")
+                    .selected_text(tr(settings.framing.name()))
+                    .show_ui(ui, |ui| {
+                        for framing in Framing::ALL {
+                            ui.selectable_value(&mut settings.framing, framing, tr(framing.name()));
+                        }
+                    })
+                    .response
+                    .on_hover_text(tr("Records that each start with their length, as many TCP protocols send them"));
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut settings.highlight_changes, tr("Highlight changes"))
+                    .on_hover_text(tr("Tint the JSON lines and count the MessagePack bytes that changed since the last conversion"));
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut settings.label_rpc, tr("Label msgpack-RPC messages"))
+                    .on_hover_text(tr("Requests, responses and notifications decode to objects naming their parts"));
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut settings.ndjson_output, tr("Records as NDJSON"))
+                    .on_hover_text(tr("Each value of the input, or each frame, on a line of its own instead of in an array"));
+                ui.end_row();
+
+                ui.label(tr("Ext types:"));
+                ui.vertical(|ui| {
+                    ui.add(egui::TextEdit::multiline(&mut settings.ext_types)
+                        .code_editor()
+                        .desired_rows(3)
+                        .hint_text("2 = \"uuid\"\n7 = \"i128\"\n8 = \"struct lat:f64 lon:f64\""))
+                        .on_hover_text(tr("One ext type per line with its decoder: base64, uuid, string, a number type such as i128, or struct with name:type fields. Values of these types decode to {\"$ext\": type, \"value\": …} and encode back."));
+                    ui.horizontal(|ui| {
+                        open_ext_types = ui.small_button(tr("Load…")).on_hover_text(tr("Read the ext types from a TOML or JSON file")).clicked();
+                        ui.checkbox(&mut settings.tag_unknown_ext_types, tr("Keep other types as base64"))
+                            .on_hover_text(tr("Ext values of any other type decode to {\"$ext\": type, \"value\": \"<base64>\"} and encode back to the same bytes, instead of failing to convert"));
+                        if let Err(e) = settings.ext_types() {
+                            ui.colored_label(egui::Color32::RED, e);
+                        }
+                    });
+                });
+                ui.end_row();
+
+                ui.label(tr("UUIDs:"));
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut settings.uuids, tr("16-byte bins as UUIDs"))
+                        .on_hover_text(tr("Decode every 16-byte bin to a UUID string, and encode UUID strings back to 16 bytes. A hash of 16 bytes looks the same, so this is off by default."));
+                    if settings.uuids {
+                        let mut by_ext_type = settings.uuid_ext_type.is_some();
+                        ui.checkbox(&mut by_ext_type, tr("and ext type"))
+                            .on_hover_text(tr("16-byte values of this ext type are UUIDs too, and UUID strings encode to it instead of to bins"));
+                        settings.uuid_ext_type = by_ext_type.then(|| {
+                            let mut ext_type = settings.uuid_ext_type.unwrap_or_default();
+                            ui.add(egui::DragValue::new(&mut ext_type));
+                            ext_type
+                        });
+                    }
+                });
+                ui.end_row();
+
+                ui.label(tr("Bins:"));
+                let bin_form_name = |bin_form: Option<BinForm>| bin_form.map_or(tr("Not converted"), BinForm::name);
+                egui::ComboBox::from_id_source("bin_form")
+                    .selected_text(bin_form_name(settings.bin_form))
+                    .show_ui(ui, |ui| {
+                        for bin_form in [None].into_iter().chain(BinForm::ALL.map(Some)) {
+                            ui.selectable_value(&mut settings.bin_form, bin_form, bin_form_name(bin_form));
+                        }
+                    })
+                    .response
+                    .on_hover_text(tr("What MessagePack binary values decode to. Only the tagged form encodes back to a bin, the others become strings and arrays."));
+                ui.end_row();
+
+                ui.label(tr("Other map keys:"));
+                let key_form_name = |key_form: Option<KeyForm>| key_form.map_or(tr("Not converted"), KeyForm::name);
+                egui::ComboBox::from_id_source("key_form")
+                    .selected_text(key_form_name(settings.key_form))
+                    .show_ui(ui, |ui| {
+                        for key_form in [None].into_iter().chain(KeyForm::ALL.map(Some)) {
+                            ui.selectable_value(&mut settings.key_form, key_form, key_form_name(key_form));
+                        }
+                    })
+                    .response
+                    .on_hover_text(tr("What maps with number, binary or other keys that aren't strings decode to. Pairs encode back to the same map, keys as strings don't."));
+                ui.end_row();
+
+                ui.label(tr("Timestamps:"));
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("timestamp_form")
+                        .selected_text(settings.timestamp_form.name())
+                        .show_ui(ui, |ui| {
+                            for form in TimestampForm::ALL {
+                                ui.selectable_value(&mut settings.timestamp_form, form, form.name());
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr("What MessagePack timestamps decode to, a date-time in UTC or a number"));
+                    ui.checkbox(&mut settings.encode_timestamps, tr("Date-times as timestamps"))
+                        .on_hover_text(tr("Encode strings like 2024-05-01T12:30:00+02:00 as MessagePack timestamps, in UTC. Not every date-looking string is meant as one, so this is off by default."));
+                });
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut settings.expand_nested, tr("Expand nested MessagePack"))
+                    .on_hover_text(tr("Decode strings that hold base64 or hex MessagePack of a map or an array in place, as {\"$nested\": …, \"$original\": …}, and encode those back to strings"));
+                ui.end_row();
+
+                ui.label(tr("Compress output:"));
+                ui.horizontal(|ui| {
+                    let compression_name = |compression: Option<Compression>| compression.map_or(tr("None"), Compression::name);
+                    egui::ComboBox::from_id_source("output_compression")
+                        .selected_text(compression_name(settings.output_compression))
+                        .show_ui(ui, |ui| {
+                            for compression in [None].into_iter().chain(Compression::ALL.map(Some)) {
+                                ui.selectable_value(&mut settings.output_compression, compression, compression_name(compression));
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr("Wraps the MessagePack output, ready to send as a compressed body"));
+                    if settings.output_compression == Some(Compression::Zstd) {
+                        ui.label(tr("Level:"));
+                        ui.add(egui::DragValue::new(&mut settings.zstd_level).clamp_range(1..=zstd::MAX_LEVEL));
+                    }
+                });
+                ui.end_row();
+
+                ui.label(tr("Decompression limit:"));
+                ui.add(egui::DragValue::new(&mut settings.max_decompressed_mb)
+                    .clamp_range(1..=MAX_DECOMPRESSED_LIMIT_MB)
+                    .suffix(" MB"))
+                    .on_hover_text(tr("gzip, zlib and zstd input is unpacked before decoding, up to this size"));
+                ui.end_row();
+
+                ui.label(tr("Input size limit:"));
+                ui.add(egui::DragValue::new(&mut settings.max_input_mb)
+                    .clamp_range(1..=MAX_INPUT_LIMIT_MB)
+                    .suffix(" MB"))
+                    .on_hover_text(tr("Larger pasted input is turned down instead of converted. Binary files can be any size."));
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut settings.wrap_messagepack_output, tr("Wrap MessagePack output"));
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut settings.wrap_json_output, tr("Wrap JSON output"));
+                ui.end_row();
+
+                ui.strong(tr("Startup"));
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut settings.restore_session, tr("Restore panes on start"))
+                    .on_hover_text(tr("Keeps pane contents in the config directory between runs"));
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut settings.auto_convert_examples, tr("Convert examples and dropped files on load"));
+                ui.end_row();
+            });
+
+            ui.separator();
+            if ui.button(tr("Restore defaults")).clicked() {
+                settings.restore_defaults();
+                ctx.set_zoom_factor(settings.zoom);
+                locale::set_language(settings.language);
+            }
+        });
+    open_ext_types
+}
+
+fn config_dir() -> Option<PathBuf> {
+    let env_path = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    if cfg!(windows) {
+        env_path("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_path("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_path("XDG_CONFIG_HOME").or_else(|| env_path("HOME").map(|home| home.join(".config")))
+    }
+}
+
+// Location of one of the app's own files in the per-user config directory
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("messagepack_to_json").join(name))
+}
+
+fn settings_path() -> Option<PathBuf> {
+    config_file("settings.json")
+}
+
+
+/* Tests */
+#[test]
+fn test_settings_missing_fields_use_defaults() {
+    assert_eq!(Settings::from_json("{}"), Settings::default());
+    assert_eq!(Settings::from_json("not json"), Settings::default());
+}
+
+#[test]
+fn test_settings_keep_valid_fields_next_to_invalid_ones() {
+    let settings = Settings::from_json(r#"{"zoom": "big", "json_indent": 4, "sort_keys": 1, "monospace": false}"#);
+    assert_eq!(settings.zoom, Settings::default().zoom);
+    assert_eq!(settings.sort_keys, Settings::default().sort_keys);
+    assert_eq!(settings.json_indent, 4);
+    assert!(!settings.monospace);
+}
+
+#[test]
+fn test_restore_defaults_keeps_rules_and_layout() {
+    let mut settings = Settings {
+        json_indent: 6,
+        monospace: false,
+        single_pane: true,
+        redaction_rules: "email".to_string(),
+        ..Settings::default()
+    };
+    settings.recent_messagepack_files.add(std::path::Path::new("/captures/a.msgpack"));
+    settings.restore_defaults();
+    assert!(!settings.recent_messagepack_files.is_empty());
+    assert_eq!(settings.json_indent, Settings::default().json_indent);
+    assert!(settings.monospace);
+    assert!(settings.single_pane);
+    assert_eq!(settings.redaction_rules, "email");
+}
+
+#[test]
+fn test_settings_zoom_is_clamped() {
+    assert_eq!(Settings::from_json(r#"{"zoom": 1.5}"#).zoom, 1.5);
+    assert_eq!(Settings::from_json(r#"{"zoom": 42.0}"#).zoom, MAX_ZOOM);
+    assert_eq!(Settings::from_json(r#"{"zoom": 0.0}"#).zoom, MIN_ZOOM);
+    assert_eq!(Settings::from_json(r#"{"json_indent": 100}"#).json_indent, MAX_JSON_INDENT);
+}
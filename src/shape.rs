@@ -0,0 +1,243 @@
+use serde_json::Value;
+use std::collections::HashSet;
+
+// The schema of a decoded value, for the Rust, TypeScript and JSON Schema generators. Array elements are merged
+// into one shape. Values of different kinds are kept apart in a union, and a field some elements
+// lack is optional.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    // Nothing seen yet, the elements of empty arrays
+    Unknown,
+    Null,
+    Bool,
+    Integer { negative: bool, beyond_i64: bool },
+    Float,
+    String(Strings),
+    Array(Box<Shape>),
+    Object(Vec<Field>),
+    // Values that don't merge, in the order they were first seen. Null is one of them for a
+    // nullable value.
+    Union(Vec<Shape>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub key: String,
+    pub shape: Shape,
+    // Missing from some of the objects
+    pub optional: bool,
+}
+
+// Distinct strings kept for an enum, and the longest one worth keeping
+const ENUM_VALUES: usize = 8;
+const ENUM_LENGTH: usize = 64;
+
+// The strings seen where a string goes, while there are few enough of them to be an enum
+#[derive(Debug, Clone, Default)]
+pub struct Strings {
+    pub seen: usize,
+    // In the order they were first seen, None once there were too many or one was too long
+    pub distinct: Option<Vec<String>>,
+}
+
+impl Strings {
+    fn of(text: &str) -> Strings {
+        Strings { seen: 1, distinct: (text.len() <= ENUM_LENGTH).then(|| vec![text.to_string()]) }
+    }
+
+    fn merge(self, other: Strings) -> Strings {
+        let seen = self.seen + other.seen;
+        let distinct = self.distinct.zip(other.distinct).and_then(|(mut distinct, others)| {
+            for text in others {
+                if !distinct.contains(&text) {
+                    distinct.push(text);
+                }
+            }
+            (distinct.len() <= ENUM_VALUES).then_some(distinct)
+        });
+        Strings { seen, distinct }
+    }
+
+    // A few values that keep coming back, which is what the members of an enum look like
+    pub fn enum_candidates(&self) -> Option<&[String]> {
+        self.distinct.as_deref().filter(|distinct| self.seen >= 2 * distinct.len())
+    }
+}
+
+// Which strings were seen doesn't make it another type
+impl PartialEq for Strings {
+    fn eq(&self, _: &Strings) -> bool {
+        true
+    }
+}
+
+pub fn infer(value: &Value) -> Shape {
+    match value {
+        Value::Null => Shape::Null,
+        Value::Bool(_) => Shape::Bool,
+        Value::Number(n) if n.is_i64() => Shape::Integer { negative: n.as_i64().is_some_and(|n| n < 0), beyond_i64: false },
+        Value::Number(n) if n.is_u64() => Shape::Integer { negative: false, beyond_i64: true },
+        Value::Number(_) => Shape::Float,
+        Value::String(text) => Shape::String(Strings::of(text)),
+        Value::Array(items) => Shape::Array(Box::new(items.iter().map(infer).fold(Shape::Unknown, merge))),
+        Value::Object(map) => Shape::Object(map.iter().map(|(key, value)| Field { key: key.clone(), shape: infer(value), optional: false }).collect()),
+    }
+}
+
+impl Shape {
+    fn merges_with(&self, other: &Shape) -> bool {
+        let number = |shape: &Shape| matches!(shape, Shape::Integer { .. } | Shape::Float);
+        match (self, other) {
+            (Shape::Array(_), Shape::Array(_)) | (Shape::Object(_), Shape::Object(_)) => true,
+            (a, b) if number(a) && number(b) => true,
+            (a, b) => a == b,
+        }
+    }
+}
+
+pub fn merge(a: Shape, b: Shape) -> Shape {
+    match (a, b) {
+        (Shape::Unknown, shape) | (shape, Shape::Unknown) => shape,
+        (Shape::Union(mut members), other) | (other, Shape::Union(mut members)) => {
+            for shape in match other {
+                Shape::Union(others) => others,
+                shape => vec![shape],
+            } {
+                match members.iter().position(|member| member.merges_with(&shape)) {
+                    Some(index) => members[index] = merge(members[index].clone(), shape),
+                    None => members.push(shape),
+                }
+            }
+            Shape::Union(members)
+        }
+        (Shape::Integer { negative, beyond_i64 }, Shape::Integer { negative: other_negative, beyond_i64: other_beyond }) => {
+            Shape::Integer { negative: negative || other_negative, beyond_i64: beyond_i64 || other_beyond }
+        }
+        (Shape::Integer { .. } | Shape::Float, Shape::Integer { .. } | Shape::Float) => Shape::Float,
+        (Shape::Array(a), Shape::Array(b)) => Shape::Array(Box::new(merge(*a, *b))),
+        (Shape::Object(a), Shape::Object(b)) => Shape::Object(merge_fields(a, b)),
+        (Shape::String(a), Shape::String(b)) => Shape::String(a.merge(b)),
+        (a, b) if a == b => a,
+        (a, b) => Shape::Union(vec![a, b]),
+    }
+}
+
+// Fields in the order they were first seen, those missing from either side become optional
+fn merge_fields(a: Vec<Field>, mut b: Vec<Field>) -> Vec<Field> {
+    let mut fields: Vec<_> = a.into_iter().map(|field| match b.iter().position(|other| other.key == field.key) {
+        Some(index) => {
+            let other = b.remove(index);
+            Field { shape: merge(field.shape, other.shape), optional: field.optional || other.optional, key: field.key }
+        }
+        None => Field { optional: true, ..field },
+    }).collect();
+    fields.extend(b.into_iter().map(|field| Field { optional: true, ..field }));
+    fields
+}
+
+// A named struct or interface
+pub struct Definition {
+    pub name: String,
+    // The name before a number told it apart from another
+    base: String,
+    fields: Vec<Field>,
+    pub body: String,
+}
+
+// The objects of a document by name, in the order they were reached so parents come first. An
+// object with the same fields under the same key shares its definition, one that differs gets a
+// number.
+pub struct Definitions {
+    pub definitions: Vec<Definition>,
+    names: HashSet<String>,
+}
+
+impl Definitions {
+    // `taken` are names the output uses itself
+    pub fn new(taken: &[&str]) -> Definitions {
+        Definitions { definitions: Vec::new(), names: taken.iter().map(|name| name.to_string()).collect() }
+    }
+
+    // The name for an object under `key`, with the index of its definition when the body is
+    // still to be written
+    pub fn name(&mut self, key: &str, fields: &[Field]) -> (String, Option<usize>) {
+        let base = match pascal_case(key) {
+            name if name.starts_with(|c: char| c.is_ascii_alphabetic()) => name,
+            name => format!("Item{}", name),
+        };
+        if let Some(existing) = self.definitions.iter().find(|definition| definition.base == base && definition.fields == fields) {
+            return (existing.name.clone(), None);
+        }
+        let name = (1..).map(|n| if n == 1 { base.clone() } else { format!("{}{}", base, n) }).find(|name| !self.names.contains(name)).unwrap();
+        self.names.insert(name.clone());
+        self.definitions.push(Definition { name: name.clone(), base, fields: fields.to_vec(), body: String::new() });
+        (name, Some(self.definitions.len() - 1))
+    }
+}
+
+// The words of a key, split at anything but letters and digits and where lower case turns upper
+pub fn words(key: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let chars: Vec<char> = key.chars().collect();
+    for (index, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+            continue;
+        }
+        let previous = index.checked_sub(1).map(|index| chars[index]);
+        let next = chars.get(index + 1);
+        // userId and the Server of HTTPServer start a word
+        let starts_word = c.is_ascii_uppercase()
+            && previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit() || (p.is_ascii_uppercase() && next.is_some_and(char::is_ascii_lowercase)));
+        if starts_word && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c.to_ascii_lowercase());
+    }
+    words.extend((!word.is_empty()).then_some(word));
+    words
+}
+
+fn pascal_case(key: &str) -> String {
+    words(key).iter().map(|word| word[..1].to_ascii_uppercase() + &word[1..]).collect()
+}
+
+// Elements of "users" are a User, those of a key that isn't a plural an Item of it
+pub fn singular(key: &str) -> String {
+    if let Some(stem) = key.strip_suffix("ies").filter(|stem| !stem.is_empty()) {
+        format!("{}y", stem)
+    } else if let Some(stem) = ["sses", "xes", "ches", "shes"].iter().find(|suffix| key.ends_with(*suffix)).map(|_| &key[..key.len() - 2]) {
+        stem.to_string()
+    } else if let Some(stem) = key.strip_suffix('s').filter(|stem| !stem.is_empty() && !stem.ends_with('s')) {
+        stem.to_string()
+    } else {
+        format!("{}_item", key)
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_shapes_merge_or_form_a_union() {
+    let shape = infer(&serde_json::json!([{"a": 1, "b": "x"}, {"a": 2.5, "c": null}, "text", null, 3]));
+    let Shape::Array(item) = shape else { panic!("{:?}", shape) };
+    assert_eq!(*item, Shape::Union(vec![
+        Shape::Object(vec![
+            Field { key: "a".to_string(), shape: Shape::Float, optional: false },
+            Field { key: "b".to_string(), shape: Shape::String(Strings::default()), optional: true },
+            Field { key: "c".to_string(), shape: Shape::Null, optional: true },
+        ]),
+        Shape::String(Strings::default()),
+        Shape::Null,
+        Shape::Integer { negative: false, beyond_i64: false },
+    ]));
+    let Shape::Array(item) = infer(&serde_json::json!(["on", "off", "on", "on"])) else { panic!() };
+    let Shape::String(strings) = *item else { panic!("{:?}", item) };
+    assert_eq!(strings.enum_candidates(), Some(&["on".to_string(), "off".to_string()][..]));
+    let Shape::Array(item) = infer(&serde_json::json!(["a", "b", "c", "a"])) else { panic!() };
+    assert!(matches!(*item, Shape::String(strings) if strings.enum_candidates().is_none()));
+    assert_eq!(singular("addresses"), "address");
+    assert_eq!(singular("data"), "data_item");
+}
@@ -0,0 +1,234 @@
+// Snippets that do a conversion without this app, for handing a payload to someone along with
+// how to read it. Each embeds the input, or reads it from a file when it's too large to paste
+// into source code.
+
+// Inputs larger than this are better read from a file than embedded
+pub const MAX_INLINE_BYTES: usize = 64 * 1024;
+
+// What the snippets read when they don't embed the input
+pub const MESSAGEPACK_FILE: &str = "payload.msgpack";
+pub const JSON_FILE: &str = "payload.json";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    // With the msgpack package
+    Python,
+    // For Node, with @msgpack/msgpack
+    JavaScript,
+    // With the rmp-serde and serde_json crates
+    Rust,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::Python, Language::JavaScript, Language::Rust];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Python => "Python",
+            Language::JavaScript => "JavaScript",
+            Language::Rust => "Rust",
+        }
+    }
+}
+
+// Where a snippet gets its input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source<'a> {
+    Inline,
+    // A path relative to where the snippet runs
+    File(&'a str),
+}
+
+// MessagePack `bytes` to a value, printed
+pub fn decode_snippet(language: Language, bytes: &[u8], source: Source) -> String {
+    let base64 = || base64::Engine::encode(&base64::engine::general_purpose::STANDARD, bytes);
+    match (language, source) {
+        (Language::Python, Source::Inline) => format!(
+            "import base64\n\nimport msgpack\n\ndata = base64.b64decode(\"{}\")\nvalue = msgpack.unpackb(data)\nprint(value)\n",
+            base64(),
+        ),
+        (Language::Python, Source::File(path)) => format!(
+            "import msgpack\n\nwith open({}, \"rb\") as file:\n    value = msgpack.unpackb(file.read())\nprint(value)\n",
+            quoted(path),
+        ),
+        (Language::JavaScript, Source::Inline) => format!(
+            "import {{ decode }} from \"@msgpack/msgpack\";\n\nconst data = Buffer.from(\"{}\", \"base64\");\nconst value = decode(data);\nconsole.log(value);\n",
+            base64(),
+        ),
+        (Language::JavaScript, Source::File(path)) => format!(
+            "import {{ readFileSync }} from \"node:fs\";\nimport {{ decode }} from \"@msgpack/msgpack\";\n\nconst data = readFileSync({});\nconst value = decode(data);\nconsole.log(value);\n",
+            quoted(path),
+        ),
+        (Language::Rust, source) => {
+            let data = match source {
+                Source::Inline => format!("let data: &[u8] = &[\n{}    ];", byte_lines(bytes)),
+                Source::File(path) => format!("let data = std::fs::read({}).unwrap();", quoted(path)),
+            };
+            format!(
+                "fn main() {{\n    {}\n    let value: serde_json::Value = rmp_serde::from_slice(&data).unwrap();\n    println!(\"{{}}\", serde_json::to_string_pretty(&value).unwrap());\n}}\n",
+                data,
+            )
+        }
+    }
+}
+
+// JSON text to MessagePack, printed as base64, or hex from Rust which has no base64 of its own
+pub fn encode_snippet(language: Language, json: &str, source: Source) -> String {
+    match (language, source) {
+        (Language::Python, source) => {
+            let value = match source {
+                Source::Inline => format!("value = json.loads({})", quoted(json)),
+                Source::File(path) => format!("with open({}) as file:\n    value = json.load(file)", quoted(path)),
+            };
+            format!("import base64\nimport json\n\nimport msgpack\n\n{}\ndata = msgpack.packb(value)\nprint(base64.b64encode(data).decode())\n", value)
+        }
+        (Language::JavaScript, source) => {
+            let (import, text) = match source {
+                Source::Inline => ("", quoted(json)),
+                Source::File(path) => ("import { readFileSync } from \"node:fs\";\n", format!("readFileSync({}, \"utf8\")", quoted(path))),
+            };
+            format!(
+                "{}import {{ encode }} from \"@msgpack/msgpack\";\n\nconst value = JSON.parse({});\nconst data = encode(value);\nconsole.log(Buffer.from(data).toString(\"base64\"));\n",
+                import, text,
+            )
+        }
+        (Language::Rust, source) => {
+            let text = match source {
+                Source::Inline => raw_string(json),
+                Source::File(path) => format!("&std::fs::read_to_string({}).unwrap()", quoted(path)),
+            };
+            format!(
+                "fn main() {{\n    let value: serde_json::Value = serde_json::from_str({}).unwrap();\n    let data = rmp_serde::to_vec(&value).unwrap();\n    println!(\"{{}}\", data.iter().map(|byte| format!(\"{{:02x}}\", byte)).collect::<String>());\n}}\n",
+                text,
+            )
+        }
+    }
+}
+
+// A double-quoted literal of `text`. JSON's escapes mean the same in Python, JavaScript and Rust
+// as long as there are no \u escapes, which JSON only needs for control characters.
+fn quoted(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_default()
+}
+
+// r#"…"# with one # more than any run in `text` that would end it
+fn raw_string(text: &str) -> String {
+    let longest = text.split('"').skip(1).map(|after| after.len() - after.trim_start_matches('#').len()).max();
+    let hashes = "#".repeat(longest.map_or(0, |run| run + 1));
+    format!("r{}\"{}\"{}", hashes, text, hashes)
+}
+
+// Rows of 16 for an array literal, each indented for the body of fn main
+fn byte_lines(bytes: &[u8]) -> String {
+    bytes.chunks(16)
+        .map(|row| format!("        {},\n", row.iter().map(|byte| format!("{:#04x}", byte)).collect::<Vec<_>>().join(", ")))
+        .collect()
+}
+
+
+/* Tests */
+#[test]
+fn test_decode_snippets_embed_or_read_the_input() {
+    // {"a": 1}
+    let bytes = [0x81, 0xa1, b'a', 0x01];
+    assert_eq!(decode_snippet(Language::Python, &bytes, Source::Inline), "\
+import base64
+
+import msgpack
+
+data = base64.b64decode(\"gaFhAQ==\")
+value = msgpack.unpackb(data)
+print(value)
+");
+    assert_eq!(decode_snippet(Language::Python, &bytes, Source::File("payload.msgpack")), "\
+import msgpack
+
+with open(\"payload.msgpack\", \"rb\") as file:
+    value = msgpack.unpackb(file.read())
+print(value)
+");
+    assert_eq!(decode_snippet(Language::JavaScript, &bytes, Source::Inline), "\
+import { decode } from \"@msgpack/msgpack\";
+
+const data = Buffer.from(\"gaFhAQ==\", \"base64\");
+const value = decode(data);
+console.log(value);
+");
+    assert_eq!(decode_snippet(Language::JavaScript, &bytes, Source::File("in.msgpack")), "\
+import { readFileSync } from \"node:fs\";
+import { decode } from \"@msgpack/msgpack\";
+
+const data = readFileSync(\"in.msgpack\");
+const value = decode(data);
+console.log(value);
+");
+    let bytes: Vec<u8> = (0..18).collect();
+    assert_eq!(decode_snippet(Language::Rust, &bytes, Source::Inline), "\
+fn main() {
+    let data: &[u8] = &[
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        0x10, 0x11,
+    ];
+    let value: serde_json::Value = rmp_serde::from_slice(&data).unwrap();
+    println!(\"{}\", serde_json::to_string_pretty(&value).unwrap());
+}
+");
+    assert_eq!(decode_snippet(Language::Rust, &bytes, Source::File("payload.msgpack")), "\
+fn main() {
+    let data = std::fs::read(\"payload.msgpack\").unwrap();
+    let value: serde_json::Value = rmp_serde::from_slice(&data).unwrap();
+    println!(\"{}\", serde_json::to_string_pretty(&value).unwrap());
+}
+");
+}
+
+#[test]
+fn test_encode_snippets_embed_or_read_the_input() {
+    let json = r##"{"say":"\"hi\"#"}"##;
+    assert_eq!(encode_snippet(Language::Python, json, Source::Inline), "\
+import base64
+import json
+
+import msgpack
+
+value = json.loads(\"{\\\"say\\\":\\\"\\\\\\\"hi\\\\\\\"#\\\"}\")
+data = msgpack.packb(value)
+print(base64.b64encode(data).decode())
+");
+    assert_eq!(encode_snippet(Language::Python, json, Source::File("payload.json")), "\
+import base64
+import json
+
+import msgpack
+
+with open(\"payload.json\") as file:
+    value = json.load(file)
+data = msgpack.packb(value)
+print(base64.b64encode(data).decode())
+");
+    assert_eq!(encode_snippet(Language::JavaScript, "[1]", Source::Inline), "\
+import { encode } from \"@msgpack/msgpack\";
+
+const value = JSON.parse(\"[1]\");
+const data = encode(value);
+console.log(Buffer.from(data).toString(\"base64\"));
+");
+    assert_eq!(encode_snippet(Language::JavaScript, "[1]", Source::File("payload.json")), "\
+import { readFileSync } from \"node:fs\";
+import { encode } from \"@msgpack/msgpack\";
+
+const value = JSON.parse(readFileSync(\"payload.json\", \"utf8\"));
+const data = encode(value);
+console.log(Buffer.from(data).toString(\"base64\"));
+");
+    // The raw string outlasts the "# in the JSON
+    assert_eq!(encode_snippet(Language::Rust, json, Source::Inline), "\
+fn main() {
+    let value: serde_json::Value = serde_json::from_str(r##\"{\"say\":\"\\\"hi\\\"#\"}\"##).unwrap();
+    let data = rmp_serde::to_vec(&value).unwrap();
+    println!(\"{}\", data.iter().map(|byte| format!(\"{:02x}\", byte)).collect::<String>());
+}
+");
+    assert!(encode_snippet(Language::Rust, "[1]", Source::File("payload.json")).contains("serde_json::from_str(&std::fs::read_to_string(\"payload.json\").unwrap())"));
+    assert_eq!(raw_string("[1]"), "r\"[1]\"");
+}
@@ -0,0 +1,313 @@
+use crate::cbor::cbor_len;
+use crate::compress::Compressed;
+use crate::convert::BinaryFormat;
+use crate::locale::trf;
+use crate::msgpack::{walk, DecodeError, TokenKind};
+use rmp::Marker;
+use serde_json::Value;
+use std::io::{self, Write};
+
+// A writer that only counts what goes through it
+struct ByteCount(usize);
+
+impl Write for ByteCount {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0 += bytes.len();
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Sizes of the same document in each representation
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SizeStats {
+    // Compact serialization, so that whitespace in the input doesn't skew the comparison
+    pub json_bytes: usize,
+    pub messagepack_bytes: usize,
+    pub cbor_bytes: usize,
+    // Of whichever binary format was converted to or from
+    pub base64_bytes: usize,
+    pub records: usize,
+    // When the binary side was or went through gzip or zlib
+    pub compressed: Option<Compressed>,
+}
+
+impl SizeStats {
+    // `encoded_bytes` is the size in `format`, the other binary format is measured by encoding
+    // the values in it
+    pub fn measure(values: &[Value], format: BinaryFormat, encoded_bytes: usize) -> SizeStats {
+        let sum = |size: fn(&Value) -> usize| values.iter().map(size).sum();
+        // Counted as they're written, a large document isn't serialized again only for its size
+        let json_bytes = sum(|value| {
+            let mut count = ByteCount(0);
+            serde_json::to_writer(&mut count, value).map_or(0, |()| count.0)
+        });
+        let (messagepack_bytes, cbor_bytes) = match format {
+            BinaryFormat::MessagePack => (encoded_bytes, sum(cbor_len)),
+            BinaryFormat::Cbor => (sum(|value| {
+                let mut count = ByteCount(0);
+                rmp_serde::encode::write(&mut count, value).map_or(0, |()| count.0)
+            }), encoded_bytes),
+        };
+        SizeStats {
+            json_bytes,
+            messagepack_bytes,
+            cbor_bytes,
+            base64_bytes: encoded_bytes.div_ceil(3) * 4,
+            records: values.len(),
+            compressed: None,
+        }
+    }
+
+    // MessagePack size as a percentage of the JSON size
+    pub fn ratio(&self) -> f64 {
+        self.percent_of_json(self.messagepack_bytes)
+    }
+
+    pub fn cbor_ratio(&self) -> f64 {
+        self.percent_of_json(self.cbor_bytes)
+    }
+
+    fn percent_of_json(&self, bytes: usize) -> f64 {
+        if self.json_bytes == 0 {
+            0.0
+        } else {
+            bytes as f64 * 100.0 / self.json_bytes as f64
+        }
+    }
+
+    // e.g. "JSON 1,204 B → MessagePack 812 B (67.4%), CBOR 820 B (68.1%), base64 1,084 B"
+    pub fn summary(&self) -> String {
+        let mut summary = trf("JSON {} B → MessagePack {} B ({}%), CBOR {} B ({}%), base64 {} B", &[
+            &group_thousands(self.json_bytes),
+            &group_thousands(self.messagepack_bytes),
+            &format!("{:.1}", self.ratio()),
+            &group_thousands(self.cbor_bytes),
+            &format!("{:.1}", self.cbor_ratio()),
+            &group_thousands(self.base64_bytes),
+        ]);
+        if self.records > 1 {
+            summary.push_str(&trf(" · {} records, avg JSON {} B → MessagePack {} B", &[
+                &group_thousands(self.records),
+                &group_thousands(self.json_bytes / self.records),
+                &group_thousands(self.messagepack_bytes / self.records),
+            ]));
+        }
+        if let Some(compressed) = &self.compressed {
+            summary.push_str(&trf(" · {} {} B, {} B uncompressed, ratio {}", &[
+                &compressed.compression.name(),
+                &group_thousands(compressed.compressed_bytes),
+                &group_thousands(compressed.uncompressed_bytes),
+                &format!("{:.2}", compressed.ratio()),
+            ]));
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FamilyStats {
+    pub count: usize,
+    // Encoded size including markers and length headers. Containers only count their header.
+    pub bytes: usize,
+}
+
+impl FamilyStats {
+    fn add(&mut self, bytes: usize) {
+        self.count += 1;
+        self.bytes += bytes;
+    }
+}
+
+// Composition of an encoded payload, measured on the raw bytes so per-type overhead is exact
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TypeStats {
+    pub nil: FamilyStats,
+    pub bool: FamilyStats,
+    pub fixint: FamilyStats,
+    pub int8: FamilyStats,
+    pub int16: FamilyStats,
+    pub int32: FamilyStats,
+    pub int64: FamilyStats,
+    pub float32: FamilyStats,
+    pub float64: FamilyStats,
+    pub str: FamilyStats,
+    pub bin: FamilyStats,
+    pub ext: FamilyStats,
+    pub array: FamilyStats,
+    pub map: FamilyStats,
+    // Bytes of string contents, without markers and length headers
+    pub string_payload_bytes: usize,
+    pub longest_string: usize,
+    pub largest_binary: usize,
+    pub max_depth: usize,
+}
+
+impl TypeStats {
+    pub fn rows(&self) -> Vec<(&'static str, FamilyStats)> {
+        vec![
+            ("nil", self.nil),
+            ("bool", self.bool),
+            ("fixint", self.fixint),
+            ("int 8-bit", self.int8),
+            ("int 16-bit", self.int16),
+            ("int 32-bit", self.int32),
+            ("int 64-bit", self.int64),
+            ("float32", self.float32),
+            ("float64", self.float64),
+            ("str", self.str),
+            ("bin", self.bin),
+            ("ext", self.ext),
+            ("array", self.array),
+            ("map", self.map),
+        ]
+    }
+}
+
+pub fn type_stats(bytes: &[u8]) -> Result<TypeStats, DecodeError> {
+    let mut stats = TypeStats::default();
+    walk(bytes, |token, depth| {
+        let size = token.end - token.start;
+        let family = match token.marker {
+            Marker::Null => &mut stats.nil,
+            Marker::True | Marker::False => &mut stats.bool,
+            Marker::FixPos(_) | Marker::FixNeg(_) => &mut stats.fixint,
+            Marker::U8 | Marker::I8 => &mut stats.int8,
+            Marker::U16 | Marker::I16 => &mut stats.int16,
+            Marker::U32 | Marker::I32 => &mut stats.int32,
+            Marker::U64 | Marker::I64 => &mut stats.int64,
+            Marker::F32 => &mut stats.float32,
+            Marker::F64 => &mut stats.float64,
+            Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => &mut stats.array,
+            Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => &mut stats.map,
+            _ => match token.kind {
+                TokenKind::Str(_) => &mut stats.str,
+                TokenKind::Bin(_) => &mut stats.bin,
+                _ => &mut stats.ext,
+            },
+        };
+        family.add(size);
+
+        match &token.kind {
+            TokenKind::Str(range) => {
+                stats.string_payload_bytes += range.len();
+                stats.longest_string = stats.longest_string.max(range.len());
+            }
+            TokenKind::Bin(range) => stats.largest_binary = stats.largest_binary.max(range.len()),
+            TokenKind::Array(_) | TokenKind::Map(_) => stats.max_depth = stats.max_depth.max(depth + 1),
+            _ => {}
+        }
+    })?;
+    Ok(stats)
+}
+
+pub fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+
+/* Tests */
+#[test]
+fn test_group_thousands() {
+    assert_eq!(group_thousands(0), "0");
+    assert_eq!(group_thousands(999), "999");
+    assert_eq!(group_thousands(1204), "1,204");
+    assert_eq!(group_thousands(41238112), "41,238,112");
+}
+
+#[test]
+fn test_size_stats_alice_fixture() {
+    use crate::compress::Compression;
+    let value: Value = serde_json::from_str(r#"{ "name": "Alice", "age": 30, "city": "Wonderland" }"#).unwrap();
+    let stats = SizeStats::measure(std::slice::from_ref(&value), BinaryFormat::MessagePack, 33);
+    // 30 fits a MessagePack fixint but takes an extra byte in CBOR
+    assert_eq!(stats, SizeStats { json_bytes: 45, messagepack_bytes: 33, cbor_bytes: 34, base64_bytes: 44, records: 1, compressed: None });
+    assert_eq!(stats.summary(), "JSON 45 B → MessagePack 33 B (73.3%), CBOR 34 B (75.6%), base64 44 B");
+    let gzipped = SizeStats { compressed: Some(Compressed { compression: Compression::Gzip, compressed_bytes: 1_204, uncompressed_bytes: 33 }), ..stats };
+    assert!(gzipped.summary().ends_with(" · gzip 1,204 B, 33 B uncompressed, ratio 0.03"));
+    let zstd = SizeStats { compressed: Some(Compressed { compression: Compression::Zstd, compressed_bytes: 400, uncompressed_bytes: 1_000 }), ..stats };
+    assert!(zstd.summary().ends_with(" · zstd 400 B, 1,000 B uncompressed, ratio 2.50"));
+    // The same document, measured from its CBOR side
+    let from_cbor = SizeStats::measure(&[value], BinaryFormat::Cbor, 34);
+    assert_eq!((from_cbor.messagepack_bytes, from_cbor.cbor_bytes, from_cbor.base64_bytes), (33, 34, 48));
+}
+
+#[test]
+fn test_size_stats_reports_per_record_averages() {
+    let values = vec![Value::from(1), Value::from(1000)];
+    let stats = SizeStats::measure(&values, BinaryFormat::MessagePack, 4);
+    assert_eq!(stats.json_bytes, 5);
+    // 1000 takes a 16-bit integer in both
+    assert_eq!(stats.summary(), "JSON 5 B → MessagePack 4 B (80.0%), CBOR 4 B (80.0%), base64 8 B · 2 records, avg JSON 2 B → MessagePack 2 B");
+}
+
+#[test]
+fn test_size_stats_empty_json() {
+    assert_eq!(SizeStats::measure(&[], BinaryFormat::MessagePack, 0).ratio(), 0.0);
+}
+
+#[test]
+fn test_type_stats_covers_every_family() {
+    let bytes = [
+        0xdf, 0, 0, 0, 2,                   // map32 with 2 entries
+        0xa1, b'a',                         // fixstr "a"
+        0x9e,                               // fixarray(14)
+        0xc0,                               // nil
+        0xc3,                               // true
+        0x05,                               // fixint
+        0xd0, 0x80,                         // int8
+        0xcd, 0x01, 0x00,                   // uint16
+        0xd2, 0, 0, 0, 1,                   // int32
+        0xcf, 0, 0, 0, 0, 0, 0, 0, 1,       // uint64
+        0xca, 0x3f, 0xc0, 0, 0,             // float32
+        0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, // float64
+        0xd9, 3, b'a', b'b', b'c',          // str8 "abc"
+        0xc4, 2, 0xde, 0xad,                // bin8
+        0xd6, 0xff, 0, 0, 0, 0,             // fixext4 timestamp
+        0x90,                               // empty fixarray
+        0x80,                               // empty fixmap
+        0xa1, b'b',                         // fixstr "b"
+        0x91, 0x91, 0xc2,                   // [[false]]
+    ];
+    let stats = type_stats(&bytes).unwrap();
+
+    assert_eq!(stats.nil, FamilyStats { count: 1, bytes: 1 });
+    assert_eq!(stats.bool, FamilyStats { count: 2, bytes: 2 });
+    assert_eq!(stats.fixint, FamilyStats { count: 1, bytes: 1 });
+    assert_eq!(stats.int8, FamilyStats { count: 1, bytes: 2 });
+    assert_eq!(stats.int16, FamilyStats { count: 1, bytes: 3 });
+    assert_eq!(stats.int32, FamilyStats { count: 1, bytes: 5 });
+    assert_eq!(stats.int64, FamilyStats { count: 1, bytes: 9 });
+    assert_eq!(stats.float32, FamilyStats { count: 1, bytes: 5 });
+    assert_eq!(stats.float64, FamilyStats { count: 1, bytes: 9 });
+    assert_eq!(stats.str, FamilyStats { count: 3, bytes: 9 });
+    assert_eq!(stats.bin, FamilyStats { count: 1, bytes: 4 });
+    assert_eq!(stats.ext, FamilyStats { count: 1, bytes: 6 });
+    assert_eq!(stats.array, FamilyStats { count: 4, bytes: 4 });
+    assert_eq!(stats.map, FamilyStats { count: 2, bytes: 6 });
+
+    assert_eq!(stats.string_payload_bytes, 5);
+    assert_eq!(stats.longest_string, 3);
+    assert_eq!(stats.largest_binary, 2);
+    assert_eq!(stats.max_depth, 3);
+
+    let total: usize = stats.rows().iter().map(|(_, family)| family.bytes).sum();
+    assert_eq!(total, bytes.len());
+}
+
+#[test]
+fn test_type_stats_rejects_truncated_payload() {
+    assert!(type_stats(&[0x92, 0x01]).is_err());
+    assert_eq!(type_stats(&[]).unwrap(), TypeStats::default());
+}
@@ -0,0 +1,143 @@
+use crate::locale::trf;
+use crate::schema_check::{escape_pointer_token, pointer_name};
+use crate::warning::{Warning, WarningKind};
+use serde_json::{Map, Value};
+
+// Field names for structs that rmp_serde wrote in its compact form, as arrays of their fields in
+// the order they were declared. The template is a JSON skeleton of the decoded value: the keys
+// of an object name the fields of the array found in its place, in order, and their values are
+// templates for those fields in turn. A list of strings only names the fields, and an array
+// holding one template is a sequence of values that each follow it.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Template {
+    // Left as it is, like the 0 in {"age": 0}
+    Any,
+    Struct(Vec<(String, Template)>),
+    Sequence(Box<Template>),
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Template, String> {
+        let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        Template::of(&value, &mut String::new())
+    }
+
+    fn of(value: &Value, path: &mut String) -> Result<Template, String> {
+        Ok(match value {
+            Value::Object(fields) => {
+                let mut templates = Vec::with_capacity(fields.len());
+                for (key, field) in fields {
+                    let len = path.len();
+                    path.push('/');
+                    path.push_str(&escape_pointer_token(key));
+                    templates.push((key.clone(), Template::of(field, path)?));
+                    path.truncate(len);
+                }
+                Template::Struct(templates)
+            }
+            // [""] is a sequence of strings, names are never empty
+            Value::Array(items) if !items.is_empty() && items.iter().all(|item| item.as_str().is_some_and(|name| !name.is_empty())) => {
+                Template::Struct(items.iter().filter_map(Value::as_str).map(|name| (name.to_string(), Template::Any)).collect())
+            }
+            Value::Array(items) if items.len() == 1 => {
+                path.push('/');
+                path.push('0');
+                Template::Sequence(Box::new(Template::of(&items[0], path)?))
+            }
+            Value::Array(_) => {
+                return Err(trf("{}: an array in the template holds field names, or one template for every element", &[&pointer_name(path)]));
+            }
+            _ => Template::Any,
+        })
+    }
+
+    // `value` with the positional arrays the template covers made into objects, and a warning for
+    // each one whose length doesn't match, which is left as it was
+    pub fn apply(&self, value: Value) -> (Value, Vec<Warning>) {
+        let mut warnings = Vec::new();
+        let value = self.apply_at(value, &mut String::new(), &mut warnings);
+        (value, warnings)
+    }
+
+    fn apply_at(&self, value: Value, path: &mut String, warnings: &mut Vec<Warning>) -> Value {
+        match (self, value) {
+            (Template::Struct(fields), Value::Array(items)) => {
+                if items.len() != fields.len() {
+                    let message = trf("{} items where the template names {} fields, left as an array", &[&items.len(), &fields.len()]);
+                    warnings.push(Warning::new(WarningKind::Template, message).at_path(path));
+                    return Value::Array(items);
+                }
+                let object: Map<String, Value> = fields.iter().zip(items)
+                    .map(|((key, template), item)| (key.clone(), template.apply_field(key, item, path, warnings)))
+                    .collect();
+                Value::Object(object)
+            }
+            // Structs rmp_serde wrote with their names still get their fields' templates applied
+            (Template::Struct(fields), Value::Object(mut object)) => {
+                for (key, template) in fields {
+                    if let Some(field) = object.get_mut(key) {
+                        *field = template.apply_field(key, std::mem::take(field), path, warnings);
+                    }
+                }
+                Value::Object(object)
+            }
+            (Template::Sequence(template), Value::Array(items)) => {
+                let items = items.into_iter().enumerate().map(|(index, item)| {
+                    let len = path.len();
+                    path.push('/');
+                    path.push_str(&index.to_string());
+                    let item = template.apply_at(item, path, warnings);
+                    path.truncate(len);
+                    item
+                });
+                Value::Array(items.collect())
+            }
+            (_, value) => value,
+        }
+    }
+
+    fn apply_field(&self, key: &str, value: Value, path: &mut String, warnings: &mut Vec<Warning>) -> Value {
+        let len = path.len();
+        path.push('/');
+        path.push_str(&escape_pointer_token(key));
+        let value = self.apply_at(value, path, warnings);
+        path.truncate(len);
+        value
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_templates_parse_from_skeletons_and_name_lists() {
+    assert_eq!(
+        Template::parse(r#"{"age": 0, "tags": [""], "home": ["city", "zip"]}"#),
+        Ok(Template::Struct(vec![
+            ("age".to_string(), Template::Any),
+            ("tags".to_string(), Template::Sequence(Box::new(Template::Any))),
+            ("home".to_string(), Template::Struct(vec![("city".to_string(), Template::Any), ("zip".to_string(), Template::Any)])),
+        ]))
+    );
+    assert_eq!(Template::parse(r#"[["id"]]"#), Ok(Template::Sequence(Box::new(Template::Struct(vec![("id".to_string(), Template::Any)])))));
+    assert_eq!(Template::parse(r#"{"a": {"b": [1, 2]}}"#), Err("/a/b: an array in the template holds field names, or one template for every element".to_string()));
+    assert!(Template::parse("{").is_err());
+}
+
+#[test]
+fn test_positional_arrays_take_the_template_names() {
+    use serde_json::json;
+    let template = Template::parse(r#"{"age": 0, "city": "", "name": "", "pets": [["kind", "name"]]}"#).unwrap();
+    let value = json!([30, "Wonderland", "Alice", [["cat", "Dinah"], ["rabbit"]]]);
+    let (labeled, warnings) = template.apply(value);
+    assert_eq!(serde_json::to_string(&labeled).unwrap(), r#"{"age":30,"city":"Wonderland","name":"Alice","pets":[{"kind":"cat","name":"Dinah"},["rabbit"]]}"#);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].to_string(), "/pets/1: 1 items where the template names 2 fields, left as an array");
+
+    // Named structs keep their names, and what doesn't fit the template is left alone
+    let (labeled, warnings) = template.apply(json!({"name": "Alice", "pets": [["cat", "Dinah"]]}));
+    assert_eq!(labeled, json!({"name": "Alice", "pets": [{"kind": "cat", "name": "Dinah"}]}));
+    assert!(warnings.is_empty());
+    assert_eq!(template.apply(json!("Alice")), (json!("Alice"), Vec::new()));
+    assert_eq!(template.apply(json!([1])).1[0].to_string(), "(root): 1 items where the template names 4 fields, left as an array");
+}
@@ -0,0 +1,281 @@
+use crate::locale::{tr, trf};
+use crate::schema_check::escape_pointer_token;
+use crate::warning::{Warning, WarningKind};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::OnceLock;
+
+// The MessagePack timestamp extension: seconds since 1970-01-01T00:00:00Z and nanoseconds, in
+// the smallest of three layouts that holds them. Timestamps decode to date-time strings or
+// numbers, and with the option on, RFC 3339 date-time strings are encoded as timestamps instead
+// of as strings.
+pub const TIMESTAMP_EXT: i8 = -1;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+// What a timestamp decodes to in JSON
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TimestampForm {
+    // "2024-05-01T10:30:00.25Z", which encodes back to a timestamp with the option on
+    #[default]
+    Rfc3339,
+    // 1714559400.25, a float when there are nanoseconds
+    Seconds,
+    // 1714559400250000000
+    Nanoseconds,
+}
+
+impl TimestampForm {
+    pub const ALL: [TimestampForm; 3] = [TimestampForm::Rfc3339, TimestampForm::Seconds, TimestampForm::Nanoseconds];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TimestampForm::Rfc3339 => tr("RFC 3339 date-time"),
+            TimestampForm::Seconds => tr("Seconds since 1970"),
+            TimestampForm::Nanoseconds => tr("Nanoseconds since 1970"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestamp {
+    pub seconds: i64,
+    // 0 to 999_999_999
+    pub nanoseconds: u32,
+}
+
+impl Timestamp {
+    // None for a string that isn't shaped like an RFC 3339 date-time, e.g.
+    // "2024-05-01T12:30:00.25+02:00", with the offset normalized away. A string that has the shape
+    // but no timestamp to go with it, a 30th of February, a leap second or a fraction finer than
+    // nanoseconds, is an error saying why. Without an offset a time isn't a point in time, so
+    // "2024-05-01T12:30:00" is just a string.
+    pub fn parse(text: &str) -> Option<Result<Timestamp, String>> {
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = PATTERN.get_or_init(|| {
+            Regex::new(r"^(\d{4})-(\d{2})-(\d{2})[Tt ](\d{2}):(\d{2}):(\d{2})(?:\.(\d+))?(?:([Zz])|([+-])(\d{2}):(\d{2}))$").unwrap()
+        });
+        pattern.captures(text).map(|captures| Timestamp::of(&captures))
+    }
+
+    fn of(captures: &Captures) -> Result<Timestamp, String> {
+        let number = |index: usize| captures.get(index).map_or(0, |m| m.as_str().parse::<i64>().unwrap_or_default());
+        let (year, month, day) = (number(1), number(2), number(3));
+        let (hour, minute, second) = (number(4), number(5), number(6));
+        let (offset_hours, offset_minutes) = (number(10), number(11));
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return Err(tr("not a date in the calendar").to_string());
+        }
+        if second == 60 {
+            return Err(tr("a leap second, which timestamps can't hold").to_string());
+        }
+        if hour > 23 || minute > 59 || second > 59 || offset_hours > 23 || offset_minutes > 59 {
+            return Err(tr("not a time of day").to_string());
+        }
+        let fraction = captures.get(7).map_or("", |m| m.as_str());
+        if fraction.len() > 9 && fraction[9..].bytes().any(|digit| digit != b'0') {
+            return Err(tr("finer than the nanoseconds timestamps hold").to_string());
+        }
+        let nanoseconds = format!("{:0<9}", &fraction[..fraction.len().min(9)]).parse::<u32>().unwrap_or_default();
+        let offset = match captures.get(9).map(|sign| sign.as_str()) {
+            Some("-") => -(offset_hours * 3600 + offset_minutes * 60),
+            _ => offset_hours * 3600 + offset_minutes * 60,
+        };
+        let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second - offset;
+        Ok(Timestamp { seconds, nanoseconds })
+    }
+
+    // The ext payload: 4 bytes of seconds when they fit and there are no nanoseconds, 8 bytes of
+    // 30-bit nanoseconds and 34-bit seconds when the seconds fit those, 12 bytes otherwise
+    pub fn encode(&self) -> Vec<u8> {
+        match u64::try_from(self.seconds) {
+            Ok(seconds) if self.nanoseconds == 0 && seconds <= u64::from(u32::MAX) => (seconds as u32).to_be_bytes().to_vec(),
+            Ok(seconds) if seconds >> 34 == 0 => ((u64::from(self.nanoseconds) << 34) | seconds).to_be_bytes().to_vec(),
+            _ => [&self.nanoseconds.to_be_bytes()[..], &self.seconds.to_be_bytes()].concat(),
+        }
+    }
+
+    // The inverse of encode, None for a payload that isn't one of the three layouts or has a
+    // second or more of nanoseconds, which the spec rules out
+    pub fn decode(data: &[u8]) -> Option<Timestamp> {
+        let timestamp = match data.len() {
+            4 => Timestamp { seconds: i64::from(u32::from_be_bytes(data.try_into().ok()?)), nanoseconds: 0 },
+            8 => {
+                let packed = u64::from_be_bytes(data.try_into().ok()?);
+                Timestamp { seconds: (packed & ((1 << 34) - 1)) as i64, nanoseconds: (packed >> 34) as u32 }
+            }
+            12 => Timestamp {
+                seconds: i64::from_be_bytes(data[4..].try_into().ok()?),
+                nanoseconds: u32::from_be_bytes(data[..4].try_into().ok()?),
+            },
+            _ => return None,
+        };
+        (timestamp.nanoseconds < 1_000_000_000).then_some(timestamp)
+    }
+
+    // In UTC, with as many fraction digits as the nanoseconds need
+    pub fn rfc3339(&self) -> String {
+        let days = self.seconds.div_euclid(SECONDS_PER_DAY);
+        let time = self.seconds.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        let mut text = format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60);
+        if self.nanoseconds != 0 {
+            text.push('.');
+            text.push_str(format!("{:09}", self.nanoseconds).trim_end_matches('0'));
+        }
+        text.push('Z');
+        text
+    }
+
+    // Years before 0 or after 9999 have no RFC 3339 date-time, and nanoseconds before 1677 or
+    // after 2262 no i64, so those come out as seconds
+    pub fn to_json(&self, form: TimestampForm) -> Value {
+        let year = civil_from_days(self.seconds.div_euclid(SECONDS_PER_DAY)).0;
+        let nanoseconds = i64::try_from(i128::from(self.seconds) * 1_000_000_000 + i128::from(self.nanoseconds));
+        match (form, nanoseconds) {
+            (TimestampForm::Rfc3339, _) if (0..=9999).contains(&year) => Value::String(self.rfc3339()),
+            (TimestampForm::Nanoseconds, Ok(nanoseconds)) => Value::from(nanoseconds),
+            _ if self.nanoseconds == 0 => Value::from(self.seconds),
+            _ => Value::from(self.seconds as f64 + f64::from(self.nanoseconds) / 1e9),
+        }
+    }
+}
+
+// One warning per string that looks like a date-time but stays a string, with its JSON Pointer
+pub fn warnings(value: &Value) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    collect_warnings(value, &mut String::new(), &mut warnings);
+    warnings
+}
+
+fn collect_warnings(value: &Value, path: &mut String, warnings: &mut Vec<Warning>) {
+    let parent_len = path.len();
+    match value {
+        Value::String(text) => {
+            if let Some(Err(e)) = Timestamp::parse(text) {
+                let message = trf("{} is left a string, {}", &[&format_args!("{:?}", text), &e]);
+                warnings.push(Warning::new(WarningKind::Timestamp, message).at_path(path));
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                path.push('/');
+                path.push_str(&index.to_string());
+                collect_warnings(item, path, warnings);
+                path.truncate(parent_len);
+            }
+        }
+        Value::Object(object) => {
+            for (key, item) in object {
+                path.push('/');
+                path.push_str(&escape_pointer_token(key));
+                collect_warnings(item, path, warnings);
+                path.truncate(parent_len);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar, after Howard Hinnant's algorithm
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+
+/* Tests */
+#[test]
+fn test_date_times_parse_to_utc() {
+    let parse = |text| Timestamp::parse(text).map(Result::unwrap);
+    assert_eq!(parse("1970-01-01T00:00:00Z"), Some(Timestamp { seconds: 0, nanoseconds: 0 }));
+    assert_eq!(parse("2024-05-01T12:30:00.25+02:00"), parse("2024-05-01T10:30:00.250Z"));
+    assert_eq!(parse("2024-05-01T10:30:00.25Z"), Some(Timestamp { seconds: 1_714_559_400, nanoseconds: 250_000_000 }));
+    assert_eq!(parse("1969-12-31t23:59:59.000000001-00:30"), Some(Timestamp { seconds: 1799, nanoseconds: 1 }));
+    assert_eq!(parse("2000-02-29 00:00:00.1234567890z"), Some(Timestamp { seconds: 951_782_400, nanoseconds: 123_456_789 }));
+
+    // Not date-times at all
+    for text in ["2024-05-01", "2024-05-01T12:30:00", "12:30:00Z", "on 2024-05-01T12:30:00Z", "2024-5-01T12:30:00Z"] {
+        assert_eq!(Timestamp::parse(text), None, "{}", text);
+    }
+    // Date-times without a timestamp
+    assert_eq!(Timestamp::parse("2023-02-29T00:00:00Z"), Some(Err("not a date in the calendar".to_string())));
+    assert_eq!(Timestamp::parse("2024-13-01T00:00:00Z"), Some(Err("not a date in the calendar".to_string())));
+    assert_eq!(Timestamp::parse("2024-01-01T24:00:00Z"), Some(Err("not a time of day".to_string())));
+    assert_eq!(Timestamp::parse("2016-12-31T23:59:60Z"), Some(Err("a leap second, which timestamps can't hold".to_string())));
+    assert_eq!(Timestamp::parse("2024-01-01T00:00:00.0000000001Z"), Some(Err("finer than the nanoseconds timestamps hold".to_string())));
+
+    let value = serde_json::json!({"ok": "2024-01-01T00:00:00Z", "list": ["x", "2016-12-31T23:59:60Z"]});
+    let warnings: Vec<String> = warnings(&value).iter().map(Warning::to_string).collect();
+    assert_eq!(warnings, ["/list/1: \"2016-12-31T23:59:60Z\" is left a string, a leap second, which timestamps can't hold"]);
+}
+
+#[test]
+fn test_timestamps_round_trip_in_all_three_layouts() {
+    let cases = [
+        // 32-bit: whole seconds up to 2106
+        ("1970-01-01T00:00:00Z", 4),
+        ("2106-02-07T06:28:15Z", 4),
+        // 64-bit: nanoseconds, or seconds up to 2514
+        ("2024-05-01T10:30:00.25Z", 8),
+        ("2106-02-07T06:28:16Z", 8),
+        ("2514-05-30T01:53:03.999999999Z", 8),
+        // 96-bit: anything else
+        ("2514-05-30T01:53:04Z", 12),
+        ("1969-12-31T23:59:59.999999999Z", 12),
+        ("0001-01-01T00:00:00Z", 12),
+        ("9999-12-31T23:59:59.5Z", 12),
+    ];
+    for (text, size) in cases {
+        let timestamp = Timestamp::parse(text).unwrap().unwrap();
+        let data = timestamp.encode();
+        assert_eq!(data.len(), size, "{}", text);
+        let decoded = Timestamp::decode(&data).unwrap();
+        assert_eq!(decoded, timestamp, "{}", text);
+        assert_eq!(decoded.rfc3339(), text);
+    }
+    assert_eq!(Timestamp::parse("2024-05-01T12:30:00.250+02:00").unwrap().unwrap().rfc3339(), "2024-05-01T10:30:00.25Z");
+    assert_eq!(Timestamp::decode(&[0; 5]), None);
+    // A whole second of nanoseconds, in the 64-bit and the 96-bit layout
+    assert_eq!(Timestamp::decode(&(1_000_000_000_u64 << 34).to_be_bytes()), None);
+    assert_eq!(Timestamp::decode(&[&u32::MAX.to_be_bytes()[..], &[0; 8]].concat()), None);
+    assert!(Timestamp::decode(&(999_999_999_u64 << 34).to_be_bytes()).is_some());
+}
+
+#[test]
+fn test_timestamps_to_json_in_each_form() {
+    let timestamp = Timestamp { seconds: 1_714_559_400, nanoseconds: 250_000_000 };
+    let forms = TimestampForm::ALL.map(|form| timestamp.to_json(form));
+    assert_eq!(forms, [Value::from("2024-05-01T10:30:00.25Z"), Value::from(1_714_559_400.25), Value::from(1_714_559_400_250_000_000_i64)]);
+    assert_eq!(Timestamp { seconds: -1, nanoseconds: 0 }.to_json(TimestampForm::Seconds), Value::from(-1));
+    // Outside the years RFC 3339 has, or the nanoseconds an i64 holds
+    let far = Timestamp { seconds: 400_000_000_000, nanoseconds: 0 };
+    assert_eq!(far.to_json(TimestampForm::Rfc3339), Value::from(400_000_000_000_i64));
+    assert_eq!(far.to_json(TimestampForm::Nanoseconds), Value::from(400_000_000_000_i64));
+}
@@ -0,0 +1,157 @@
+use crate::locale::{tr, trf};
+use crate::schema_check::escape_pointer_token;
+use eframe::egui;
+use serde_json::Value;
+use std::collections::HashMap;
+
+// Children shown per container before a "Show more" node
+pub const PAGE_SIZE: usize = 100;
+
+#[derive(Default)]
+pub struct TreeState {
+    // How many children are shown for each paginated container, keyed by JSON Pointer
+    shown: HashMap<String, usize>,
+    pub selected: Option<String>,
+    // Expand the ancestors of the selected node and scroll it into view on the next frame
+    pub reveal: bool,
+    // Set when the user picked a node in the tree itself
+    pub selection_changed: bool,
+}
+
+impl TreeState {
+    pub fn reset(&mut self) {
+        self.shown.clear();
+        self.selected = None;
+        self.reveal = false;
+    }
+
+    fn is_selected(&self, path: &str) -> bool {
+        self.selected.as_deref() == Some(path)
+    }
+
+    fn reveals(&self, path: &str) -> bool {
+        self.reveal && self.selected.as_deref().is_some_and(|selected| is_ancestor(path, selected))
+    }
+
+    fn select(&mut self, path: &str) {
+        self.selected = Some(path.to_string());
+        self.selection_changed = true;
+    }
+
+    fn shown(&self, path: &str) -> usize {
+        self.shown.get(path).copied().unwrap_or(PAGE_SIZE)
+    }
+}
+
+// Renders the value as collapsible nodes, returning text the user asked to copy
+pub fn show_tree(ui: &mut egui::Ui, value: &Value, state: &mut TreeState) -> Option<String> {
+    let mut copied = None;
+    show_node(ui, tr("(root)"), "", value, state, &mut copied);
+    state.reveal = false;
+    copied
+}
+
+fn show_node(ui: &mut egui::Ui, label: &str, path: &str, value: &Value, state: &mut TreeState, copied: &mut Option<String>) {
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        Value::Array(items) => items.iter().enumerate().map(|(i, v)| (i.to_string(), v)).collect(),
+        _ => {
+            let response = ui.selectable_label(state.is_selected(path), format!("{}: {}", label, value));
+            if response.clicked() {
+                state.select(path);
+            }
+            if state.reveal && state.is_selected(path) {
+                response.scroll_to_me(Some(egui::Align::Center));
+            }
+            node_context_menu(&response, path, value, copied);
+            return;
+        }
+    };
+
+    let mut header = egui::RichText::new(format!("{} {}", label, summarize(value)));
+    if state.is_selected(path) {
+        header = header.background_color(ui.visuals().selection.bg_fill);
+    }
+    let response = egui::CollapsingHeader::new(header)
+        .id_source(path)
+        .default_open(path.is_empty())
+        .open(state.reveals(path).then_some(true))
+        .show(ui, |ui| {
+            let shown = state.shown(path);
+            for (key, child) in children.iter().take(shown) {
+                let child_path = format!("{}/{}", path, escape_pointer_token(key));
+                let child_label = if value.is_array() { format!("[{}]", key) } else { key.clone() };
+                show_node(ui, &child_label, &child_path, child, state, copied);
+            }
+            if children.len() > shown {
+                let remaining = children.len() - shown;
+                if ui.button(trf("Show more ({} remaining)", &[&remaining])).clicked() {
+                    state.shown.insert(path.to_string(), shown + PAGE_SIZE);
+                }
+            }
+        });
+    if response.header_response.clicked() {
+        state.select(path);
+    }
+    if state.reveal && state.is_selected(path) {
+        response.header_response.scroll_to_me(Some(egui::Align::Center));
+    }
+    node_context_menu(&response.header_response, path, value, copied);
+}
+
+// Whether `path` is a proper ancestor of `descendant`, both being JSON Pointers
+fn is_ancestor(path: &str, descendant: &str) -> bool {
+    descendant.len() > path.len() && descendant.starts_with(path) && descendant[path.len()..].starts_with('/')
+}
+
+fn node_context_menu(response: &egui::Response, path: &str, value: &Value, copied: &mut Option<String>) {
+    response.context_menu(|ui| {
+        if ui.button(tr("Copy value as JSON")).clicked() {
+            *copied = serde_json::to_string_pretty(value).ok();
+            ui.close_menu();
+        }
+        if ui.button(tr("Copy path")).clicked() {
+            *copied = Some(path.to_string());
+            ui.close_menu();
+        }
+    });
+}
+
+// Child count shown next to a container node, e.g. "{3}" or "[100]"
+pub fn summarize(value: &Value) -> String {
+    match value {
+        Value::Object(map) => format!("{{{}}}", map.len()),
+        Value::Array(items) => format!("[{}]", items.len()),
+        _ => String::new(),
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_summarize() {
+    let value: Value = serde_json::from_str(r#"{"a": [1, 2, 3], "b": {}, "c": 1}"#).unwrap();
+    assert_eq!(summarize(&value), "{3}");
+    assert_eq!(summarize(&value["a"]), "[3]");
+    assert_eq!(summarize(&value["b"]), "{0}");
+    assert_eq!(summarize(&value["c"]), "");
+}
+
+#[test]
+fn test_is_ancestor() {
+    assert!(is_ancestor("", "/a"));
+    assert!(is_ancestor("/a", "/a/0"));
+    assert!(!is_ancestor("/a", "/a"));
+    assert!(!is_ancestor("/a", "/ab"));
+    assert!(!is_ancestor("/a/0", "/a"));
+}
+
+#[test]
+fn test_tree_state_pagination_defaults_to_one_page() {
+    let mut state = TreeState::default();
+    assert_eq!(state.shown("/items"), PAGE_SIZE);
+    state.shown.insert("/items".to_string(), 2 * PAGE_SIZE);
+    assert_eq!(state.shown("/items"), 2 * PAGE_SIZE);
+    state.reset();
+    assert_eq!(state.shown("/items"), PAGE_SIZE);
+}
@@ -0,0 +1,88 @@
+use crate::shape::{infer, singular, Definitions, Field, Shape};
+use serde_json::Value;
+
+// Interface declarations for a decoded value. Elements that disagree give a union, a property some
+// of them lack is optional.
+
+// Type names the output can't shadow
+const TAKEN_TYPE_NAMES: [&str; 15] = [
+    "Array", "Boolean", "Date", "Error", "Function", "Map", "Number", "Object", "Partial", "Promise", "Readonly",
+    "Record", "Set", "String", "Symbol",
+];
+
+pub fn typescript(value: &Value, root: &str) -> String {
+    let mut definitions = Definitions::new(&TAKEN_TYPE_NAMES);
+    let shape = infer(value);
+    let mut out = String::new();
+    if let Shape::Object(fields) = &shape {
+        interface_for(&mut definitions, root, fields);
+    } else {
+        let type_name = type_name(&mut definitions, &shape, root);
+        out += &format!("export type {} = {};\n", root, type_name);
+    }
+    for definition in &definitions.definitions {
+        if !out.is_empty() {
+            out += "\n";
+        }
+        out += &format!("export interface {} {{\n{}}}\n", definition.name, definition.body);
+    }
+    out
+}
+
+// Named after the key the value sits under
+fn type_name(definitions: &mut Definitions, shape: &Shape, key: &str) -> String {
+    match shape {
+        Shape::Unknown => "unknown".to_string(),
+        Shape::Null => "null".to_string(),
+        Shape::Bool => "boolean".to_string(),
+        Shape::Integer { .. } | Shape::Float => "number".to_string(),
+        Shape::String(_) => "string".to_string(),
+        Shape::Array(item) => match type_name(definitions, item, &singular(key)) {
+            union if matches!(**item, Shape::Union(_)) => format!("({})[]", union),
+            item => format!("{}[]", item),
+        },
+        Shape::Object(fields) => interface_for(definitions, key, fields),
+        // Null goes last, as in string | null
+        Shape::Union(members) => {
+            let mut names: Vec<_> = members.iter().filter(|member| **member != Shape::Null).map(|member| type_name(definitions, member, key)).collect();
+            if members.contains(&Shape::Null) {
+                names.push("null".to_string());
+            }
+            names.join(" | ")
+        }
+    }
+}
+
+fn interface_for(definitions: &mut Definitions, key: &str, fields: &[Field]) -> String {
+    let (name, index) = definitions.name(key, fields);
+    let Some(index) = index else {
+        return name;
+    };
+    let mut body = String::new();
+    for field in fields {
+        let property = if is_identifier(&field.key) { field.key.clone() } else { Value::from(field.key.as_str()).to_string() };
+        let optional = if field.optional { "?" } else { "" };
+        body += &format!("  {}{}: {};\n", property, optional, type_name(definitions, &field.shape, &field.key));
+    }
+    definitions.definitions[index].body = body;
+    name
+}
+
+// Keys that can go unquoted. Reserved words can, as property names.
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$') && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+
+/* Tests */
+#[test]
+fn test_typescript_matches_the_golden_files() {
+    for (input, expected) in [
+        (include_str!("../tests/golden/users.json"), include_str!("../tests/golden/users.ts")),
+        (include_str!("../tests/golden/events.json"), include_str!("../tests/golden/events.ts")),
+    ] {
+        let value: Value = serde_json::from_str(input).unwrap();
+        assert_eq!(typescript(&value, "Root"), expected);
+    }
+}
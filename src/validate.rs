@@ -0,0 +1,75 @@
+use crate::locale::{tr, trf};
+use crate::msgpack::{marker_name, read_token, walk, TokenKind};
+use serde_json::Value;
+
+// Parse-only check of the JSON input, describing the top-level value on success
+pub fn validate_json(text: &str) -> Result<String, String> {
+    let value: Value = serde_json::from_str(text)
+        .map_err(|e| trf("Invalid JSON: {}", &[&e]))?;
+    let description = match &value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => tr("boolean").to_string(),
+        Value::Number(_) => tr("number").to_string(),
+        Value::String(s) => trf("string of {} chars", &[&s.chars().count()]),
+        Value::Array(items) => trf("array with {}", &[&count(items.len(), "{} item", "{} items")]),
+        Value::Object(map) => trf("object with {}", &[&count(map.len(), "{} key", "{} keys")]),
+    };
+    Ok(trf("Valid JSON: {}", &[&description]))
+}
+
+// Structural check of the MessagePack bytes. This accepts everything the format allows, including
+// bin and ext values that the JSON conversion itself would reject.
+pub fn validate_messagepack(bytes: &[u8]) -> Result<String, String> {
+    if bytes.is_empty() {
+        return Err(tr("Invalid MessagePack: no input").to_string());
+    }
+    let mut values = 0;
+    walk(bytes, |_, depth| {
+        if depth == 0 {
+            values += 1;
+        }
+    }).map_err(|e| trf("Invalid MessagePack: {}", &[&e]))?;
+
+    let first = read_token(bytes, 0).map_err(|e| trf("Invalid MessagePack: {}", &[&e]))?;
+    let description = match first.kind {
+        TokenKind::Array(n) => trf("{} with {}", &[&marker_name(first.marker), &count(n, "{} item", "{} items")]),
+        TokenKind::Map(n) => trf("{} with {}", &[&marker_name(first.marker), &count(n, "{} entry", "{} entries")]),
+        _ => marker_name(first.marker).to_string(),
+    };
+    let mut summary = trf("Valid MessagePack: {}, {}", &[&description, &count(bytes.len(), "{} byte", "{} bytes")]);
+    if values > 1 {
+        summary.push_str(&trf(" ({} back-to-back values, only the first is converted)", &[&values]));
+    }
+    Ok(summary)
+}
+
+// `singular` and `plural` are templates such as "{} item", so both can be translated
+fn count(n: usize, singular: &str, plural: &str) -> String {
+    trf(if n == 1 { singular } else { plural }, &[&n])
+}
+
+
+/* Tests */
+#[test]
+fn test_validate_json_describes_top_level_value() {
+    assert_eq!(validate_json(r#"{"a": 1, "b": [1, 2]}"#).unwrap(), "Valid JSON: object with 2 keys");
+    assert_eq!(validate_json("[1]").unwrap(), "Valid JSON: array with 1 item");
+    assert_eq!(validate_json(r#""héllo""#).unwrap(), "Valid JSON: string of 5 chars");
+
+    let err = validate_json("{\n  \"a\": 1,\n}").unwrap_err();
+    assert!(err.contains("line 3 column 1"), "{}", err);
+}
+
+#[test]
+fn test_validate_messagepack() {
+    let alice = hex::decode("83a36167651ea463697479aa576f6e6465726c616e64a46e616d65a5416c696365").unwrap();
+    assert_eq!(validate_messagepack(&alice).unwrap(), "Valid MessagePack: fixmap with 3 entries, 33 bytes");
+    assert_eq!(
+        validate_messagepack(&[0xc0, 0x01]).unwrap(),
+        "Valid MessagePack: nil, 2 bytes (2 back-to-back values, only the first is converted)"
+    );
+
+    let err = validate_messagepack(&alice[..10]).unwrap_err();
+    assert!(err.starts_with("Invalid MessagePack: Unexpected end of input"), "{}", err);
+    assert!(validate_messagepack(&[]).is_err());
+}
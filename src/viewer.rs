@@ -0,0 +1,275 @@
+use crate::editor::{gutter_digits, highlight_strength, EditorOptions};
+use crate::locale::tr;
+use eframe::egui;
+use std::ops::{Range, RangeInclusive};
+
+// Longer lines are split over several rows, so a minified document or a base64 blob on a
+// single line is never laid out in one piece
+pub const MAX_ROW_BYTES: usize = 2048;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    // Without the line break
+    pub range: Range<usize>,
+    // 1-based line of the text the row belongs to
+    pub line: usize,
+}
+
+// Where each row of a text starts and ends, built once per text so the viewer only has to look
+// at the rows that are on screen
+#[derive(Debug, Default, PartialEq)]
+pub struct LineIndex {
+    rows: Vec<Row>,
+    // Of the text the index was built from
+    len: usize,
+}
+
+impl LineIndex {
+    pub fn build(text: &str, max_row_bytes: usize) -> LineIndex {
+        let mut rows = Vec::new();
+        let mut start = 0;
+        for (line, content) in text.split('\n').enumerate() {
+            let end = start + content.len();
+            let mut row_start = start;
+            loop {
+                let mut row_end = (row_start + max_row_bytes).min(end);
+                while !text.is_char_boundary(row_end) {
+                    row_end -= 1;
+                }
+                if row_end == row_start && row_start < end {
+                    // A single character wider than a row still gets one to itself
+                    row_end += text[row_start..].chars().next().map_or(0, char::len_utf8);
+                }
+                rows.push(Row { range: row_start..row_end, line: line + 1 });
+                if row_end >= end {
+                    break;
+                }
+                row_start = row_end;
+            }
+            start = end + 1;
+        }
+        LineIndex { rows, len: text.len() }
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn row(&self, index: usize) -> &Row {
+        &self.rows[index]
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.rows.last().map_or(0, |row| row.line)
+    }
+
+    // Row holding the byte at `offset`, a line break counts towards the row before it
+    pub fn row_at(&self, offset: usize) -> usize {
+        self.rows.partition_point(|row| row.range.start <= offset).saturating_sub(1)
+    }
+
+    // Text of the given rows, with a line break wherever the text had one
+    pub fn copy(&self, text: &str, rows: RangeInclusive<usize>) -> String {
+        let mut copied = String::new();
+        let mut previous_line = None;
+        for row in &self.rows[rows] {
+            if previous_line.is_some_and(|line| line != row.line) {
+                copied.push('\n');
+            }
+            copied.push_str(&text[row.range.clone()]);
+            previous_line = Some(row.line);
+        }
+        copied
+    }
+}
+
+// Read-only view of an output pane that only lays out the rows on screen, for outputs too large
+// for a TextEdit. Lines are selected by clicking (Shift+click extends) and copied with Ctrl+C or
+// from the context menu.
+#[derive(Default)]
+pub struct LineViewer {
+    pub open: bool,
+    index: Option<LineIndex>,
+    // Row where the selection started and row where it ends, in either order
+    selection: Option<(usize, usize)>,
+}
+
+impl LineViewer {
+    // Called whenever the pane gets new text, the index is also rebuilt when the length changed
+    pub fn invalidate(&mut self) {
+        self.index = None;
+        self.selection = None;
+    }
+}
+
+fn selected_rows(selection: Option<(usize, usize)>) -> Option<RangeInclusive<usize>> {
+    selection.map(|(anchor, end)| anchor.min(end)..=anchor.max(end))
+}
+
+pub struct ViewerOutput {
+    pub response: egui::Response,
+    // Set when the selected lines should go to the clipboard
+    pub copied: Option<String>,
+}
+
+pub fn show_viewer(ui: &mut egui::Ui, id: &str, viewer: &mut LineViewer, text: &str, options: &EditorOptions) -> ViewerOutput {
+    if viewer.index.as_ref().is_some_and(|index| index.len != text.len()) {
+        viewer.invalidate();
+    }
+    let index = viewer.index.get_or_insert_with(|| LineIndex::build(text, MAX_ROW_BYTES));
+    let selected = selected_rows(viewer.selection);
+
+    let font_id = options.font.resolve(ui.style());
+    let text_color = ui.visuals().override_text_color
+        .unwrap_or_else(|| ui.visuals().widgets.inactive.text_color());
+    let highlight_color = ui.visuals().selection.bg_fill.gamma_multiply(highlight_strength(&options.highlights));
+    let active_color = ui.visuals().warn_fg_color.gamma_multiply(0.5);
+    let selection_color = ui.visuals().selection.bg_fill.gamma_multiply(0.3);
+    let gutter_color = ui.visuals().weak_text_color();
+    let row_height = ui.fonts(|f| f.row_height(&font_id));
+    let row_spacing = row_height + ui.spacing().item_spacing.y;
+
+    let gutter_padding = ui.spacing().item_spacing.x;
+    let gutter_width = if options.line_numbers {
+        let digit_width = ui.fonts(|f| f.glyph_width(&font_id, '0'));
+        gutter_digits(index.line_count()) as f32 * digit_width + 2.0 * gutter_padding
+    } else {
+        0.0
+    };
+
+    let mut scroll_area = egui::ScrollArea::both()
+        .id_source(id)
+        .auto_shrink([false, false])
+        .min_scrolled_height(options.height)
+        .max_height(options.height);
+    if let Some(highlights) = options.highlights.as_ref().filter(|h| h.scroll_to_active) {
+        if let Some(range) = highlights.active.and_then(|i| highlights.ranges.get(i)) {
+            let offset = index.row_at(range.start) as f32 * row_spacing - options.height / 2.0;
+            scroll_area = scroll_area.vertical_scroll_offset(offset.max(0.0));
+        }
+    }
+
+    let frame = egui::Frame::none().fill(ui.visuals().extreme_bg_color);
+    let output = frame.show(ui, |ui| {
+        scroll_area.show_rows(ui, row_height, index.row_count(), |ui, rows| {
+            for row_index in rows {
+                let row = index.row(row_index);
+                let mut job = egui::text::LayoutJob::default();
+                let format = |background| egui::TextFormat {
+                    font_id: font_id.clone(),
+                    color: text_color,
+                    background,
+                    ..Default::default()
+                };
+                let mut position = row.range.start;
+                if let Some(highlights) = &options.highlights {
+                    // Matches are sorted, so only the ones overlapping this row need a look
+                    let first = highlights.ranges.partition_point(|range| range.end <= row.range.start);
+                    for (i, range) in highlights.ranges.iter().enumerate().skip(first) {
+                        if range.start >= row.range.end {
+                            break;
+                        }
+                        let start = range.start.max(position);
+                        let end = range.end.min(row.range.end);
+                        if !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+                            continue;
+                        }
+                        let background = if highlights.active == Some(i) { active_color } else { highlight_color };
+                        job.append(&text[position..start], 0.0, format(egui::Color32::TRANSPARENT));
+                        job.append(&text[start..end], 0.0, format(background));
+                        position = end;
+                    }
+                }
+                job.append(&text[position..row.range.end], 0.0, format(egui::Color32::TRANSPARENT));
+                let galley = ui.fonts(|f| f.layout_job(job));
+
+                let width = (gutter_width + galley.size().x).max(ui.available_width());
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(width, row_height), egui::Sense::hover());
+                let painter = ui.painter();
+                if selected.as_ref().is_some_and(|selected| selected.contains(&row_index)) {
+                    painter.rect_filled(rect, 0.0, selection_color);
+                }
+                if options.line_numbers && (row_index == 0 || index.row(row_index - 1).line != row.line) {
+                    painter.text(
+                        egui::pos2(rect.left() + gutter_width - gutter_padding, rect.top()),
+                        egui::Align2::RIGHT_TOP,
+                        row.line.to_string(),
+                        font_id.clone(),
+                        gutter_color,
+                    );
+                }
+                painter.galley(rect.min + egui::vec2(gutter_width, 0.0), galley, text_color);
+            }
+        })
+    }).inner;
+
+    let response = ui.interact(output.inner_rect, ui.id().with(id).with("viewer"), egui::Sense::click());
+    if response.clicked() || response.secondary_clicked() {
+        response.request_focus();
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let row = ((pointer.y - output.inner_rect.top() + output.state.offset.y) / row_spacing) as usize;
+            let row = row.min(index.row_count() - 1);
+            let extend = response.clicked() && ui.input(|i| i.modifiers.shift);
+            viewer.selection = match viewer.selection {
+                Some((anchor, _)) if extend => Some((anchor, row)),
+                // Right-clicking inside the selection keeps it for the context menu
+                Some(selection) if response.secondary_clicked() && selected.as_ref().is_some_and(|rows| rows.contains(&row)) => Some(selection),
+                _ => Some((row, row)),
+            };
+        }
+    }
+
+    let mut copy = response.has_focus() && ui.input(|i| i.events.iter().any(|event| matches!(event, egui::Event::Copy)));
+    response.context_menu(|ui| {
+        if ui.add_enabled(viewer.selection.is_some(), egui::Button::new(tr("Copy lines"))).clicked() {
+            copy = true;
+            ui.close_menu();
+        }
+    });
+    let copied = selected_rows(viewer.selection).filter(|_| copy).map(|rows| index.copy(text, rows));
+    ViewerOutput { response, copied }
+}
+
+
+/* Tests */
+#[test]
+fn test_line_index_rows_follow_lines() {
+    let text = "{\n  \"a\": 1\n}\n";
+    let index = LineIndex::build(text, MAX_ROW_BYTES);
+    assert_eq!(index.row_count(), 4);
+    assert_eq!(index.line_count(), 4);
+    assert_eq!(&text[index.row(1).range.clone()], "  \"a\": 1");
+    // The empty line after the final line break, like in the editor
+    assert_eq!(index.row(3), &Row { range: 13..13, line: 4 });
+
+    assert_eq!(index.row_at(0), 0);
+    assert_eq!(index.row_at(1), 0);
+    assert_eq!(index.row_at(5), 1);
+    assert_eq!(index.row_at(12), 2);
+    assert_eq!(LineIndex::build("", MAX_ROW_BYTES).row_count(), 1);
+}
+
+#[test]
+fn test_line_index_splits_long_lines_on_char_boundaries() {
+    let text = "abcdé\nxy";
+    let index = LineIndex::build(text, 4);
+    let rows: Vec<&str> = (0..index.row_count()).map(|i| &text[index.row(i).range.clone()]).collect();
+    // "é" would straddle the first row boundary, so it moves to the next row whole
+    assert_eq!(rows, vec!["abcd", "é", "xy"]);
+    assert_eq!(index.line_count(), 2);
+    assert_eq!(index.row(1).line, 1);
+
+    // A row narrower than a character still makes progress
+    let index = LineIndex::build("日本", 1);
+    assert_eq!(index.row_count(), 2);
+}
+
+#[test]
+fn test_line_index_copy_rejoins_split_lines() {
+    let text = "abcdef\nghi\njkl";
+    let index = LineIndex::build(text, 4);
+    assert_eq!(index.row_count(), 4);
+    assert_eq!(index.copy(text, 0..=2), "abcdef\nghi");
+    assert_eq!(index.copy(text, 1..=1), "ef");
+    assert_eq!(index.copy(text, 2..=3), "ghi\njkl");
+}
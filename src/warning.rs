@@ -0,0 +1,114 @@
+use crate::locale::trf;
+use crate::schema_check::pointer_name;
+use std::fmt;
+
+// Something a conversion did that isn't an error but shouldn't go unsaid, e.g. a binary value
+// lossy decoding turned into base64. The conversion goes on and hands these back with its output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    // JSON Pointer of the node it's about, None if it isn't about one
+    pub path: Option<String>,
+    // Where in the binary input it is, None on the text side
+    pub offset: Option<usize>,
+    // Says what happened, already translated
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WarningKind {
+    // A binary or extension payload that became a base64 string
+    Base64Payload,
+    // A map key that isn't a string and became its JSON text
+    StringifiedKey,
+    // Invalid UTF-8 that was replaced
+    LossyUtf8,
+    // A CBOR simple value JSON has nothing for, which became null
+    SimpleValue,
+    // Bytes after the value that were ignored
+    TrailingBytes,
+    // YAML input that may not have been read the way it was meant
+    Yaml,
+    // Records the field name template didn't fit
+    Template,
+    // A date-time string that stays a string
+    Timestamp,
+}
+
+impl WarningKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningKind::Base64Payload => "Base64 payload",
+            WarningKind::StringifiedKey => "Key as string",
+            WarningKind::LossyUtf8 => "Invalid UTF-8",
+            WarningKind::SimpleValue => "Simple value",
+            WarningKind::TrailingBytes => "Trailing bytes",
+            WarningKind::Yaml => "YAML",
+            WarningKind::Template => "Field names",
+            WarningKind::Timestamp => "Timestamp",
+        }
+    }
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, message: String) -> Warning {
+        Warning { kind, path: None, offset: None, message }
+    }
+
+    pub fn at_path(self, path: &str) -> Warning {
+        Warning { path: Some(path.to_string()), ..self }
+    }
+
+    pub fn at_offset(self, offset: usize) -> Warning {
+        Warning { offset: Some(offset), ..self }
+    }
+
+    // For a record decoded on its own from `by` bytes into the input
+    pub fn shifted(mut self, by: usize) -> Warning {
+        if let Some(offset) = &mut self.offset {
+            *offset += by;
+        }
+        self
+    }
+
+    // For a record of a stream, the `record`th, whose paths start at the record itself
+    pub fn in_record(mut self, record: usize) -> Warning {
+        if let Some(path) = &mut self.path {
+            path.insert_str(0, &format!("/{}", record));
+        }
+        self
+    }
+}
+
+// e.g. "/items/2 at offset 0x1f: binary value as base64"
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.as_deref().map(pointer_name);
+        match (path, self.offset) {
+            (Some(path), Some(offset)) => write!(f, "{}", trf("{} at offset {}: {}", &[&path, &format_args!("{:#x}", offset), &self.message])),
+            (None, Some(offset)) => write!(f, "{}", trf("At offset {}: {}", &[&format_args!("{:#x}", offset), &self.message])),
+            (Some(path), None) => write!(f, "{}: {}", path, self.message),
+            (None, None) => write!(f, "{}", self.message),
+        }
+    }
+}
+
+pub fn count(warnings: &[Warning]) -> String {
+    trf(if warnings.len() == 1 { "{} warning" } else { "{} warnings" }, &[&warnings.len()])
+}
+
+
+/* Tests */
+#[test]
+fn test_warnings_say_where_they_are() {
+    let warning = Warning::new(WarningKind::Base64Payload, "binary value as base64".to_string());
+    assert_eq!(warning.to_string(), "binary value as base64");
+    assert_eq!(warning.clone().at_path("/a/0").to_string(), "/a/0: binary value as base64");
+    assert_eq!(warning.clone().at_offset(31).to_string(), "At offset 0x1f: binary value as base64");
+    assert_eq!(warning.clone().at_path("").at_offset(31).to_string(), "(root) at offset 0x1f: binary value as base64");
+    assert_eq!(warning.clone().at_path("/a").at_offset(1).shifted(16).to_string(), "/a at offset 0x11: binary value as base64");
+    assert_eq!(warning.clone().at_path("/a").in_record(2).path.as_deref(), Some("/2/a"));
+    assert_eq!(warning.clone().in_record(2).path, None);
+    assert_eq!(count(std::slice::from_ref(&warning)), "1 warning");
+    assert_eq!(count(&[]), "0 warnings");
+}
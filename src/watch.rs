@@ -0,0 +1,120 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// How often the file is looked at, and how long a change has to hold still before it's loaded,
+// so a file written in several goes is read once it's complete
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+pub const DEBOUNCE: Duration = Duration::from_millis(400);
+// How long the status bar stays highlighted after a reload
+pub const FLASH_DURATION: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl Stamp {
+    // None while the file is missing, e.g. between a script deleting and rewriting it
+    pub fn of(path: &Path) -> Option<Stamp> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Stamp { modified: metadata.modified().ok()?, len: metadata.len() })
+    }
+}
+
+// Notices when a file changes by polling its modification time and size. There is no watcher
+// thread, so dropping the FileWatch is all it takes to stop.
+pub struct FileWatch {
+    pub path: PathBuf,
+    // Stamp of the version that was loaded last
+    loaded: Option<Stamp>,
+    // A newer stamp and when it was first seen, loaded once it has held still for DEBOUNCE
+    pending: Option<(Stamp, Instant)>,
+    last_poll: Option<Instant>,
+    pub reloaded: Option<Instant>,
+}
+
+impl FileWatch {
+    // The file as it is now counts as loaded
+    pub fn new(path: PathBuf) -> FileWatch {
+        let loaded = Stamp::of(&path);
+        FileWatch { path, loaded, pending: None, last_poll: None, reloaded: None }
+    }
+
+    // True when the file changed and should be read again
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if self.last_poll.is_some_and(|polled| now.duration_since(polled) < POLL_INTERVAL) {
+            return false;
+        }
+        self.last_poll = Some(now);
+        let stamp = Stamp::of(&self.path);
+        self.observe(stamp, now)
+    }
+
+    fn observe(&mut self, stamp: Option<Stamp>, now: Instant) -> bool {
+        let Some(stamp) = stamp.filter(|stamp| Some(*stamp) != self.loaded) else {
+            self.pending = None;
+            return false;
+        };
+        match self.pending {
+            Some((pending, since)) if pending == stamp => {
+                if now.duration_since(since) < DEBOUNCE {
+                    return false;
+                }
+                self.loaded = Some(stamp);
+                self.pending = None;
+                true
+            }
+            // Still being written, wait for it to hold still from here
+            _ => {
+                self.pending = Some((stamp, now));
+                false
+            }
+        }
+    }
+
+    // Whether the status bar should still be highlighted, and for how long
+    pub fn flash_left(&self, now: Instant) -> Option<Duration> {
+        let reloaded = self.reloaded?;
+        FLASH_DURATION.checked_sub(now.duration_since(reloaded)).filter(|left| !left.is_zero())
+    }
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn stamp(seconds: u64, len: u64) -> Option<Stamp> {
+    Some(Stamp { modified: SystemTime::UNIX_EPOCH + Duration::from_secs(seconds), len })
+}
+
+#[test]
+fn test_watch_waits_for_writes_to_settle() {
+    let mut watch = FileWatch { path: PathBuf::new(), loaded: stamp(1, 10), pending: None, last_poll: None, reloaded: None };
+    let start = Instant::now();
+    let at = |millis| start + Duration::from_millis(millis);
+    assert!(!watch.observe(stamp(1, 10), at(0)));
+
+    // Three writes in quick succession, only the last one is loaded and only once
+    assert!(!watch.observe(stamp(2, 4), at(0)));
+    assert!(!watch.observe(stamp(2, 8), at(250)));
+    assert!(!watch.observe(stamp(3, 12), at(500)));
+    assert!(!watch.observe(stamp(3, 12), at(750)));
+    assert!(watch.observe(stamp(3, 12), at(900)));
+    assert!(!watch.observe(stamp(3, 12), at(1500)));
+
+    // A file that is briefly missing is waited for rather than reported
+    assert!(!watch.observe(None, at(1600)));
+    assert!(!watch.observe(stamp(4, 12), at(1700)));
+    assert!(watch.observe(stamp(4, 12), at(2100)));
+}
+
+#[test]
+fn test_watch_flash_fades() {
+    let mut watch = FileWatch::new(PathBuf::from("missing.bin"));
+    let now = Instant::now();
+    assert_eq!(watch.flash_left(now), None);
+    watch.reloaded = Some(now);
+    assert_eq!(watch.flash_left(now + Duration::from_millis(500)), Some(Duration::from_millis(1000)));
+    assert_eq!(watch.flash_left(now + FLASH_DURATION), None);
+}
@@ -0,0 +1,406 @@
+use crate::feed::{FrameLog, ReceivedFrame};
+use crate::format::JsonFormat;
+use crate::locale::{tr, trf};
+use base64::{engine::general_purpose, Engine};
+use eframe::egui;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+// Messages larger than this close the connection instead of being buffered
+const MAX_MESSAGE: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    fn from_u8(code: u8) -> Option<Opcode> {
+        Some(match code {
+            0x0 => Opcode::Continuation,
+            0x1 => Opcode::Text,
+            0x2 => Opcode::Binary,
+            0x8 => Opcode::Close,
+            0x9 => Opcode::Ping,
+            0xa => Opcode::Pong,
+            _ => return None,
+        })
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            Opcode::Continuation => 0x0,
+            Opcode::Text => 0x1,
+            Opcode::Binary => 0x2,
+            Opcode::Close => 0x8,
+            Opcode::Ping => 0x9,
+            Opcode::Pong => 0xa,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Frame {
+    fin: bool,
+    opcode: Opcode,
+    payload: Vec<u8>,
+}
+
+// Host, port and path of a ws:// URL. There is no TLS, so wss:// is refused.
+pub fn parse_url(url: &str) -> Result<(String, u16, String), String> {
+    let Some(rest) = url.trim().strip_prefix("ws://") else {
+        return Err(tr("Only ws:// URLs are supported").to_string());
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| trf("Invalid port {}", &[&port]))?),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err(tr("The URL has no host").to_string());
+    }
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+// HTTP upgrade request, the server has to answer with 101 Switching Protocols
+fn handshake(reader: &mut impl BufRead, writer: &mut impl Write, host: &str, port: u16, path: &str) -> io::Result<()> {
+    let key = general_purpose::STANDARD.encode(random_bytes::<16>());
+    write!(
+        writer,
+        "GET {} HTTP/1.1\r\nHost: {}:{}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        path, host, port, key,
+    )?;
+    writer.flush()?;
+    let mut status = String::new();
+    reader.read_line(&mut status)?;
+    if status.split(' ').nth(1) != Some("101") {
+        return Err(io::Error::other(trf("The server refused the upgrade: {}", &[&status.trim()])));
+    }
+    // Headers are skipped, Sec-WebSocket-Accept isn't checked
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<Frame> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head)?;
+    let opcode = Opcode::from_u8(head[0] & 0x0f)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, trf("Unknown opcode {}", &[&(head[0] & 0x0f)])))?;
+    let len = match head[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+    if len > MAX_MESSAGE as u64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, trf("Frame of {} bytes is too large", &[&len])));
+    }
+    // Servers don't mask, but nothing stops one from doing it
+    let mask = if head[1] & 0x80 != 0 {
+        let mut mask = [0; 4];
+        reader.read_exact(&mut mask)?;
+        Some(mask)
+    } else {
+        None
+    };
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    if let Some(mask) = mask {
+        apply_mask(&mut payload, mask);
+    }
+    Ok(Frame { fin: head[0] & 0x80 != 0, opcode, payload })
+}
+
+// Client frames always go out masked
+fn write_frame(writer: &mut impl Write, opcode: Opcode, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode.code()];
+    match payload.len() {
+        len @ 0..=125 => frame.push(0x80 | len as u8),
+        len @ 126..=0xffff => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    let mask = random_bytes::<4>();
+    frame.extend_from_slice(&mask);
+    let start = frame.len();
+    frame.extend_from_slice(payload);
+    apply_mask(&mut frame[start..], mask);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+fn apply_mask(bytes: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+// Masks and handshake keys only have to be unpredictable, not cryptographically strong
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    for chunk in bytes.chunks_mut(8) {
+        let random = RandomState::new().hash_one(SystemTime::now()).to_le_bytes();
+        chunk.copy_from_slice(&random[..chunk.len()]);
+    }
+    bytes
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+enum Status {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected,
+    // Why the connection ended, when it wasn't asked to
+    Closed(String),
+}
+
+// What the connection thread shares with the window
+#[derive(Default)]
+struct Shared {
+    status: Mutex<Status>,
+    stop: AtomicBool,
+}
+
+// Connects, then reads frames and whole messages and answers pings until the server closes or
+// `stop` is set
+fn run_connection(
+    stream: TcpStream,
+    (host, port, path): (&str, u16, &str),
+    shared: &Shared,
+    frames: &Sender<ReceivedFrame>,
+    json_format: &JsonFormat,
+    ctx: &egui::Context,
+) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    // Frames the server sends right after its answer may already be in this buffer
+    let mut reader = BufReader::new(stream);
+    handshake(&mut reader, &mut writer, host, port, path)?;
+    *shared.status.lock().unwrap() = Status::Connected;
+    ctx.request_repaint();
+    // Payload and opcode of a message that arrives in fragments
+    let mut message: Option<(Opcode, Vec<u8>)> = None;
+    loop {
+        let frame = read_frame(&mut reader)?;
+        match frame.opcode {
+            Opcode::Ping => write_frame(&mut writer, Opcode::Pong, &frame.payload)?,
+            Opcode::Pong => {}
+            Opcode::Close => {
+                let _ = write_frame(&mut writer, Opcode::Close, &frame.payload);
+                return Ok(());
+            }
+            opcode => {
+                let (opcode, payload) = match (opcode, message.take()) {
+                    (Opcode::Continuation, Some((opcode, mut payload))) => {
+                        payload.extend_from_slice(&frame.payload);
+                        (opcode, payload)
+                    }
+                    (Opcode::Continuation, None) => {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, tr("Continuation without a message").to_string()));
+                    }
+                    (opcode, _) => (opcode, frame.payload),
+                };
+                if payload.len() > MAX_MESSAGE {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, trf("Message of {} bytes is too large", &[&payload.len()])));
+                }
+                if !frame.fin {
+                    message = Some((opcode, payload));
+                    continue;
+                }
+                let received = match opcode {
+                    Opcode::Text => ReceivedFrame::text(String::from_utf8_lossy(&payload).into_owned()),
+                    _ => ReceivedFrame::binary(&payload, None, json_format, false),
+                };
+                if frames.send(received).is_err() {
+                    return Ok(());
+                }
+                ctx.request_repaint();
+            }
+        }
+        if shared.stop.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+    }
+}
+
+pub struct WebSocketState {
+    pub open: bool,
+    url: String,
+    shared: Arc<Shared>,
+    // Kept to close the connection from the UI thread, which unblocks the reader
+    stream: Option<TcpStream>,
+    frames: Option<Receiver<ReceivedFrame>>,
+    log: FrameLog,
+}
+
+impl Default for WebSocketState {
+    fn default() -> Self {
+        WebSocketState {
+            open: false,
+            url: "ws://127.0.0.1:8080/".to_string(),
+            shared: Arc::default(),
+            stream: None,
+            frames: None,
+            log: FrameLog::default(),
+        }
+    }
+}
+
+impl WebSocketState {
+    fn connect(&mut self, ctx: &egui::Context, json_format: &JsonFormat) {
+        self.disconnect();
+        // A fresh one, so the old connection's thread can't report into this one
+        self.shared = Arc::new(Shared::default());
+        *self.shared.status.lock().unwrap() = Status::Connecting;
+        let (host, port, path) = match parse_url(&self.url) {
+            Ok(parts) => parts,
+            Err(e) => {
+                *self.shared.status.lock().unwrap() = Status::Closed(e);
+                return;
+            }
+        };
+        let (ui_side, thread_side) = match TcpStream::connect((host.as_str(), port)).and_then(|stream| Ok((stream.try_clone()?, stream))) {
+            Ok(streams) => streams,
+            Err(e) => {
+                *self.shared.status.lock().unwrap() = Status::Closed(e.to_string());
+                return;
+            }
+        };
+        self.stream = Some(ui_side);
+        let (sender, receiver) = mpsc::channel();
+        self.frames = Some(receiver);
+        let shared = self.shared.clone();
+        let json_format = *json_format;
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let result = run_connection(thread_side, (&host, port, &path), &shared, &sender, &json_format, &ctx);
+            let mut status = shared.status.lock().unwrap();
+            *status = match result {
+                _ if shared.stop.load(Ordering::Relaxed) => Status::Disconnected,
+                Ok(()) => Status::Closed(tr("The server closed the connection").to_string()),
+                Err(e) => Status::Closed(e.to_string()),
+            };
+            ctx.request_repaint();
+        });
+    }
+
+    // Says goodbye with a close frame, then shuts the socket so the reader stops waiting
+    pub fn disconnect(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        if let Some(mut stream) = self.stream.take() {
+            let _ = write_frame(&mut stream, Opcode::Close, &1000u16.to_be_bytes());
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        let mut status = self.shared.status.lock().unwrap();
+        if matches!(*status, Status::Connecting | Status::Connected) {
+            *status = Status::Disconnected;
+        }
+    }
+}
+
+impl Drop for WebSocketState {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+// Connects to a WebSocket and lists every message it sends, decoded
+pub fn websocket_window(ctx: &egui::Context, state: &mut WebSocketState, json_format: &JsonFormat) {
+    if let Some(frames) = &state.frames {
+        state.log.take(frames.try_iter());
+    }
+    let mut open = state.open;
+    egui::Window::new(tr("WebSocket frames"))
+        .id(egui::Id::new("websocket"))
+        .open(&mut open)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            let status = state.shared.status.lock().unwrap().clone();
+            let connected = matches!(status, Status::Connecting | Status::Connected);
+            ui.horizontal(|ui| {
+                ui.label(tr("URL:"));
+                ui.add_enabled(!connected, egui::TextEdit::singleline(&mut state.url).desired_width(280.0));
+                if connected {
+                    if ui.button(tr("Disconnect")).clicked() {
+                        state.disconnect();
+                    }
+                } else if ui.button(tr("Connect")).clicked() {
+                    state.connect(ctx, json_format);
+                }
+            });
+            match &status {
+                Status::Disconnected => ui.weak(tr("Not connected")),
+                Status::Connecting => ui.label(tr("Connecting…")),
+                Status::Connected => ui.label(tr("Connected")),
+                Status::Closed(reason) => ui.colored_label(ui.visuals().error_fg_color, trf("Connection closed: {}", &[reason])),
+            };
+            state.log.controls(ui);
+            ui.separator();
+            state.log.show(ui);
+        });
+    if state.open && !open {
+        state.disconnect();
+    }
+    state.open = open;
+}
+
+
+/* Tests */
+#[test]
+fn test_parse_url() {
+    assert_eq!(parse_url("ws://localhost:9000/feed?x=1"), Ok(("localhost".to_string(), 9000, "/feed?x=1".to_string())));
+    assert_eq!(parse_url("ws://example.com"), Ok(("example.com".to_string(), 80, "/".to_string())));
+    assert!(parse_url("wss://example.com").is_err());
+    assert!(parse_url("ws://:80/").is_err());
+}
+
+#[test]
+fn test_frames_round_trip() {
+    let mut written = Vec::new();
+    write_frame(&mut written, Opcode::Binary, &[0x81, 0xa1, 0x61, 0x01]).unwrap();
+    // Masked, so the payload doesn't appear as is
+    assert_eq!(written[..2], [0x82, 0x84]);
+    let frame = read_frame(&mut &written[..]).unwrap();
+    assert_eq!(frame, Frame { fin: true, opcode: Opcode::Binary, payload: vec![0x81, 0xa1, 0x61, 0x01] });
+
+    // Unmasked server frame with a 16-bit length
+    let mut server = vec![0x01, 126, 0x01, 0x00];
+    server.extend(std::iter::repeat_n(b'x', 256));
+    let frame = read_frame(&mut &server[..]).unwrap();
+    assert_eq!((frame.fin, frame.opcode, frame.payload.len()), (false, Opcode::Text, 256));
+}
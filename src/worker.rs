@@ -0,0 +1,319 @@
+use crate::error::ConvertError;
+use crate::locale::tr;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+#[cfg(any(test, not(target_arch = "wasm32")))]
+use std::thread;
+
+pub const CANCELLED: &str = "Conversion cancelled";
+
+// Shared between the UI and a running job. The job is expected to check for cancellation now
+// and then and give up once it's set, and to report how many input bytes it got through.
+#[derive(Clone, Default)]
+pub struct JobToken {
+    cancelled: Arc<AtomicBool>,
+    progress: Arc<AtomicUsize>,
+}
+
+impl JobToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancelled
+    }
+
+    pub fn progress_counter(&self) -> &AtomicUsize {
+        &self.progress
+    }
+
+    pub fn progress(&self) -> usize {
+        self.progress.load(Ordering::Relaxed)
+    }
+
+    // For use with `?` between the stages of a job
+    pub fn check(&self) -> Result<(), ConvertError> {
+        if self.is_cancelled() {
+            Err(ConvertError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Reader/writer adapter that starts failing once the job is cancelled, which turns every
+// read or write done by a parser or serializer into a cancellation checkpoint. Bytes read
+// through it count as progress.
+pub struct Checkpoint<T> {
+    inner: T,
+    token: JobToken,
+}
+
+impl<T> Checkpoint<T> {
+    pub fn new(inner: T, token: &JobToken) -> Self {
+        Checkpoint { inner, token: token.clone() }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn check(&self) -> io::Result<()> {
+        if self.token.is_cancelled() {
+            Err(io::Error::other(tr(CANCELLED)))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T: Read> Read for Checkpoint<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        let read = self.inner.read(buf)?;
+        self.token.progress.fetch_add(read, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+impl<T: Write> Write for Checkpoint<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check()?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum JobState {
+    #[default]
+    Idle,
+    Running,
+    Done,
+    Failed,
+}
+
+// Runs one job at a time off the UI thread. Starting a job while another one is running
+// supersedes it: the old job is cancelled and whatever it still produces is thrown away, so
+// the result that lands is always the one from the most recent start.
+pub struct Worker<T> {
+    state: JobState,
+    receiver: Option<Receiver<Result<T, ConvertError>>>,
+    token: JobToken,
+    // Input size of the running job, what its progress counts up to
+    total: usize,
+}
+
+impl<T> Default for Worker<T> {
+    fn default() -> Self {
+        Worker { state: JobState::Idle, receiver: None, token: JobToken::default(), total: 0 }
+    }
+}
+
+impl<T: Send + 'static> Worker<T> {
+    // `notify` is called from the worker thread once the result is ready, e.g. to request a repaint
+    pub fn start(
+        &mut self,
+        total: usize,
+        job: impl FnOnce(&JobToken) -> Result<T, ConvertError> + Send + 'static,
+        notify: impl FnOnce() + Send + 'static,
+    ) {
+        self.cancel();
+        let (sender, receiver) = mpsc::channel();
+        let token = JobToken::default();
+        self.receiver = Some(receiver);
+        self.token = token.clone();
+        self.total = total;
+        self.state = JobState::Running;
+        let run = move || {
+            let result = job(&token);
+            // The receiver is gone if the job was cancelled or superseded
+            if sender.send(result).is_ok() {
+                notify();
+            }
+        };
+        // No threads in the browser, the job runs before start returns and poll hands it out
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(run);
+        #[cfg(target_arch = "wasm32")]
+        run();
+    }
+
+    // Hands out the result of the current job exactly once, when it's ready. A job that panicked
+    // drops its sender without sending anything.
+    pub fn poll(&mut self) -> Option<Result<T, ConvertError>> {
+        let result = match self.receiver.as_ref()?.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => Err(ConvertError::JobStopped),
+        };
+        self.receiver = None;
+        self.state = if result.is_ok() { JobState::Done } else { JobState::Failed };
+        Some(result)
+    }
+
+    #[cfg(test)]
+    pub fn state(&self) -> JobState {
+        self.state
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state == JobState::Running
+    }
+
+    // Fraction of the input the running job got through so far
+    pub fn progress(&self) -> Option<f32> {
+        if !self.is_running() {
+            return None;
+        }
+        Some(progress_fraction(self.token.progress(), self.total))
+    }
+
+    // Tells the current job to stop and drops whatever it still produces
+    pub fn cancel(&mut self) {
+        self.token.cancel();
+        self.receiver = None;
+        self.state = JobState::Idle;
+    }
+}
+
+fn progress_fraction(done: usize, total: usize) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        (done as f64 / total as f64).min(1.0) as f32
+    }
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn wait<T: Send + 'static>(worker: &mut Worker<T>) -> Result<T, ConvertError> {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    loop {
+        if let Some(result) = worker.poll() {
+            return result;
+        }
+        assert!(std::time::Instant::now() < deadline, "job did not finish");
+        thread::sleep(std::time::Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn test_worker_goes_from_idle_to_done_or_failed() {
+    let mut worker = Worker::default();
+    assert_eq!(worker.state(), JobState::Idle);
+    assert!(worker.poll().is_none());
+
+    worker.start(0, |_| Ok(42), || {});
+    assert_eq!(worker.state(), JobState::Running);
+    assert_eq!(wait(&mut worker), Ok(42));
+    assert_eq!(worker.state(), JobState::Done);
+    // The result is only handed out once
+    assert!(worker.poll().is_none());
+
+    worker.start(0, |_| Err::<i32, _>(ConvertError::TrailingBytes(1)), || {});
+    assert_eq!(wait(&mut worker), Err(ConvertError::TrailingBytes(1)));
+    assert_eq!(worker.state(), JobState::Failed);
+
+    // A job that panics fails instead of leaving the worker running
+    worker.start(0, |_| -> Result<i32, ConvertError> { panic!("job panicked") }, || {});
+    assert_eq!(wait(&mut worker), Err(ConvertError::JobStopped));
+    assert_eq!(worker.state(), JobState::Failed);
+}
+
+#[test]
+fn test_worker_newer_job_supersedes_running_one() {
+    let (release, blocked) = mpsc::channel::<()>();
+    let (observed, observations) = mpsc::channel();
+    let mut worker = Worker::default();
+    worker.start(0, move |token: &JobToken| {
+        blocked.recv().ok();
+        observed.send(token.is_cancelled()).unwrap();
+        Ok("old")
+    }, || {});
+    worker.start(0, |_| Ok("new"), || {});
+
+    assert_eq!(wait(&mut worker), Ok("new"));
+    release.send(()).ok();
+    // The superseded job was told to stop
+    assert!(observations.recv_timeout(std::time::Duration::from_secs(10)).unwrap());
+    assert!(worker.poll().is_none());
+}
+
+#[test]
+fn test_worker_notifies_when_done() {
+    let (notified, notifications) = mpsc::channel();
+    let mut worker = Worker::default();
+    worker.start(0, |_| Ok(1), move || notified.send(()).unwrap());
+    notifications.recv_timeout(std::time::Duration::from_secs(10)).unwrap();
+    assert_eq!(worker.poll(), Some(Ok(1)));
+}
+
+#[test]
+fn test_worker_cancel_stops_the_job_and_drops_its_result() {
+    let (stopped, stops) = mpsc::channel();
+    let mut worker = Worker::default();
+    worker.start(0, move |token: &JobToken| {
+        while !token.is_cancelled() {
+            thread::sleep(std::time::Duration::from_millis(1));
+        }
+        stopped.send(()).unwrap();
+        token.check()?;
+        Ok(2)
+    }, || {});
+    assert!(worker.is_running());
+
+    worker.cancel();
+    assert_eq!(worker.state(), JobState::Idle);
+    stops.recv_timeout(std::time::Duration::from_secs(10)).unwrap();
+    assert!(worker.poll().is_none());
+}
+
+#[test]
+fn test_checkpoint_fails_reads_and_writes_after_cancel() {
+    let token = JobToken::default();
+    let mut reader = Checkpoint::new(&b"abc"[..], &token);
+    let mut buf = [0; 2];
+    assert_eq!(reader.read(&mut buf).unwrap(), 2);
+
+    let mut writer = Checkpoint::new(Vec::new(), &token);
+    writer.write_all(b"xy").unwrap();
+    token.cancel();
+    assert!(reader.read(&mut buf).is_err());
+    assert_eq!(writer.write(b"z").unwrap_err().to_string(), CANCELLED);
+    assert_eq!(writer.into_inner(), b"xy");
+}
+
+#[test]
+fn test_progress_counts_bytes_read_through_checkpoints() {
+    let (release, blocked) = mpsc::channel::<()>();
+    let (read_some, reads) = mpsc::channel();
+    let mut worker = Worker::default();
+    worker.start(10, move |token: &JobToken| {
+        let mut reader = Checkpoint::new(&b"0123456789"[..], token);
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        read_some.send(()).unwrap();
+        blocked.recv().ok();
+        Ok(())
+    }, || {});
+    reads.recv_timeout(std::time::Duration::from_secs(10)).unwrap();
+    assert_eq!(worker.progress(), Some(0.4));
+
+    release.send(()).unwrap();
+    wait(&mut worker).unwrap();
+    assert_eq!(worker.progress(), None);
+    assert_eq!(progress_fraction(5, 0), 0.0);
+    assert_eq!(progress_fraction(12, 10), 1.0);
+}
@@ -0,0 +1,1199 @@
+use crate::decode::MAX_DEPTH;
+use crate::error::ConvertError;
+use crate::locale::{tr, trf};
+use crate::warning::{Warning, WarningKind};
+use serde_json::{Map, Number, Value};
+use std::collections::{HashMap, HashSet};
+
+// Past this many values brought in through aliases a document is taken for an alias bomb
+const MAX_ALIASED_VALUES: usize = 1_000_000;
+
+// What YAML 1.1 readers take for booleans. YAML 1.2, and so this parser, reads them as strings.
+const YAML_1_1_BOOLEANS: [&str; 16] = ["y", "Y", "yes", "Yes", "YES", "n", "N", "no", "No", "NO", "on", "On", "ON", "off", "Off", "OFF"];
+
+// Every document in a YAML stream, along with what may not have come out the way its author meant
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ParsedYaml {
+    pub documents: Vec<Value>,
+    pub warnings: Vec<Warning>,
+    // Line each document starts on
+    lines: Vec<usize>,
+}
+
+impl ParsedYaml {
+    // The one document there should be, null for an empty stream
+    pub fn single(mut self) -> Result<Value, ConvertError> {
+        if self.documents.len() > 1 {
+            let msg = trf("{} documents where one was expected", &[&self.documents.len()]);
+            return Err(ConvertError::YamlParse { line: self.lines[1], column: 1, msg });
+        }
+        Ok(self.documents.pop().unwrap_or(Value::Null))
+    }
+
+    // Same, but more than one document becomes an array of them, with a warning saying so
+    pub fn combined(mut self) -> (Value, Vec<Warning>) {
+        let value = match self.documents.len() {
+            0 => Value::Null,
+            1 => self.documents.remove(0),
+            n => {
+                self.warnings.push(Warning::new(WarningKind::Yaml, trf("{} documents were combined into an array", &[&n])));
+                Value::Array(self.documents)
+            }
+        };
+        (value, self.warnings)
+    }
+}
+
+// Parses YAML 1.2 with the core schema, resolving anchors, aliases and `<<` merge keys. Keys
+// that aren't strings are an error unless `lossy`, which turns them into their JSON text, same
+// for .inf and .nan.
+pub fn parse_yaml(text: &str, lossy: bool) -> Result<ParsedYaml, ConvertError> {
+    let mut parser = Parser {
+        chars: text.strip_prefix('\u{feff}').unwrap_or(text).chars().collect(),
+        pos: 0,
+        lossy,
+        depth: 0,
+        anchors: HashMap::new(),
+        aliased: 0,
+        parsed: ParsedYaml::default(),
+    };
+    parser.stream()?;
+    Ok(parser.parsed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Context {
+    // The top of a document or the rest of a "- " line, where a mapping or sequence can start
+    // on the same line
+    Compact,
+    // After "key:", where a sequence may sit at the key's own indentation
+    MappingValue,
+}
+
+// Anchor and tag in front of a node
+#[derive(Debug, Default)]
+struct Properties {
+    anchor: Option<String>,
+    tag: Option<String>,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    lossy: bool,
+    depth: usize,
+    // Reset with every document
+    anchors: HashMap<String, Value>,
+    aliased: usize,
+    parsed: ParsedYaml,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.pos + ahead).copied()
+    }
+
+    fn line_start(&self) -> usize {
+        self.chars[..self.pos].iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1)
+    }
+
+    fn column(&self) -> usize {
+        self.pos - self.line_start()
+    }
+
+    fn line(&self) -> usize {
+        self.chars[..self.pos].iter().filter(|&&c| c == '\n').count() + 1
+    }
+
+    fn error(&self, msg: impl Into<String>) -> ConvertError {
+        ConvertError::YamlParse { line: self.line(), column: self.column() + 1, msg: msg.into() }
+    }
+
+    fn unexpected(&self) -> ConvertError {
+        match self.peek() {
+            Some(c) => self.error(trf("Unexpected {}", &[&format_args!("{:?}", c)])),
+            None => self.error(tr("Unexpected end of input")),
+        }
+    }
+
+    fn warn(&mut self, line: usize, warning: String) {
+        self.parsed.warnings.push(Warning::new(WarningKind::Yaml, trf("Line {}: {}", &[&line, &warning])));
+    }
+
+    // Only spaces between the start of the line and here
+    fn at_indentation(&self) -> bool {
+        self.chars[self.line_start()..self.pos].iter().all(|&c| c == ' ' || c == '\t')
+    }
+
+    fn skip_inline_space(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    // A # only starts a comment at the start of a line or after whitespace
+    fn skip_comment(&mut self) {
+        let after_space = self.pos == 0 || is_blank(self.chars.get(self.pos - 1).copied());
+        if self.peek() == Some('#') && after_space {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn at_line_end(&mut self) -> bool {
+        self.skip_inline_space();
+        self.skip_comment();
+        matches!(self.peek(), None | Some('\n' | '\r'))
+    }
+
+    // Moves to the first character of the next line with content and returns its column, None at
+    // the end of the input. Anything but a comment left on the current line is an error.
+    fn next_content(&mut self) -> Result<Option<usize>, ConvertError> {
+        if !self.at_indentation() && !self.at_line_end() {
+            return Err(self.unexpected());
+        }
+        loop {
+            self.skip_inline_space();
+            self.skip_comment();
+            match self.peek() {
+                None => return Ok(None),
+                Some('\n' | '\r') => self.pos += 1,
+                Some(_) => return Ok(Some(self.column())),
+            }
+        }
+    }
+
+    fn at_document_marker(&self) -> bool {
+        let marker: String = self.chars[self.pos..].iter().take(3).collect();
+        self.column() == 0 && (marker == "---" || marker == "...") && is_blank(self.peek_at(3))
+    }
+
+    fn at_sequence_entry(&self) -> bool {
+        self.peek() == Some('-') && is_blank(self.peek_at(1))
+    }
+
+    // Whether the rest of the line is a mapping entry, "key: value" or "? key"
+    fn at_mapping_key(&self) -> bool {
+        let line = &self.chars[self.pos..];
+        let mut i = 0;
+        if line.first() == Some(&'?') && is_blank(line.get(1).copied()) {
+            return true;
+        }
+        // Properties of the key
+        while matches!(line.get(i), Some('&' | '!')) {
+            while !is_blank(line.get(i).copied()) {
+                i += 1;
+            }
+            while matches!(line.get(i), Some(' ' | '\t')) {
+                i += 1;
+            }
+        }
+        match line.get(i) {
+            Some(&quote @ ('"' | '\'')) => {
+                i += 1;
+                loop {
+                    match line.get(i) {
+                        None | Some('\n') => return false,
+                        Some('\\') if quote == '"' => i += 2,
+                        Some('\'') if quote == '\'' && line.get(i + 1) == Some(&'\'') => i += 2,
+                        Some(&c) if c == quote => break,
+                        Some(_) => i += 1,
+                    }
+                }
+                i += 1;
+                while matches!(line.get(i), Some(' ' | '\t')) {
+                    i += 1;
+                }
+                line.get(i) == Some(&':') && is_blank(line.get(i + 1).copied())
+            }
+            Some('[' | '{' | '*' | '|' | '>') => false,
+            _ => {
+                while let Some(&c) = line.get(i) {
+                    match c {
+                        '\n' => return false,
+                        ':' if is_blank(line.get(i + 1).copied()) => return true,
+                        '#' if i > 0 && is_blank(line.get(i - 1).copied()) => return false,
+                        _ => i += 1,
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    fn stream(&mut self) -> Result<(), ConvertError> {
+        while let Some(column) = self.next_content()? {
+            // Directives such as %YAML 1.2 say nothing the parser needs
+            if column == 0 && self.peek() == Some('%') {
+                while !matches!(self.peek(), None | Some('\n')) {
+                    self.pos += 1;
+                }
+                continue;
+            }
+            if self.at_document_marker() && self.peek() == Some('.') {
+                self.pos += 3;
+                continue;
+            }
+            self.parsed.lines.push(self.line());
+            if self.at_document_marker() {
+                self.pos += 3;
+            }
+            self.anchors.clear();
+            let document = self.block_node(-1, Context::Compact)?;
+            self.parsed.documents.push(document);
+            if self.next_content()?.is_some() && !self.at_document_marker() {
+                return Err(self.unexpected());
+            }
+        }
+        Ok(())
+    }
+
+    fn deeper(&mut self) -> Result<(), ConvertError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(trf("Nesting deeper than {} levels", &[&MAX_DEPTH])));
+        }
+        Ok(())
+    }
+
+    // A node in block context, which starts on this line or the lines below. Its content has to
+    // be indented further than `parent`.
+    fn block_node(&mut self, parent: isize, context: Context) -> Result<Value, ConvertError> {
+        self.deeper()?;
+        self.skip_inline_space();
+        let compact = context == Context::Compact && !self.at_document_marker();
+        let value = if compact && self.at_sequence_entry() {
+            let column = self.column();
+            self.block_sequence(column)?
+        } else if compact && self.at_mapping_key() {
+            let column = self.column();
+            self.block_mapping(column)?
+        } else {
+            let properties = self.properties()?;
+            let value = if self.at_line_end() {
+                match self.next_content()? {
+                    Some(column) if !self.at_document_marker() => {
+                        let indented = column as isize > parent;
+                        if self.at_sequence_entry() && (indented || (column as isize == parent && context == Context::MappingValue)) {
+                            self.block_sequence(column)?
+                        } else if !indented {
+                            Value::Null
+                        } else if self.at_mapping_key() {
+                            self.block_mapping(column)?
+                        } else {
+                            self.inline_node(parent, Properties::default())?
+                        }
+                    }
+                    _ => Value::Null,
+                }
+            } else {
+                self.inline_node(parent, properties.clone_tag())?
+            };
+            self.anchor(properties.anchor, &value);
+            value
+        };
+        self.depth -= 1;
+        Ok(value)
+    }
+
+    fn block_sequence(&mut self, indent: usize) -> Result<Value, ConvertError> {
+        let mut items = Vec::new();
+        loop {
+            self.pos += 1;
+            items.push(self.block_node(indent as isize, Context::Compact)?);
+            match self.next_content()? {
+                Some(column) if column == indent && !self.at_document_marker() && self.at_sequence_entry() => {}
+                Some(column) if column > indent => return Err(self.error(tr("Unexpected indentation"))),
+                _ => return Ok(Value::Array(items)),
+            }
+        }
+    }
+
+    fn block_mapping(&mut self, indent: usize) -> Result<Value, ConvertError> {
+        let mut map = Map::new();
+        let mut merged = HashSet::new();
+        loop {
+            let at = (self.line(), self.column() + 1);
+            let (key, value) = if self.peek() == Some('?') {
+                self.pos += 1;
+                let key = self.block_node(indent as isize, Context::Compact)?;
+                let value = match self.next_content()? {
+                    Some(column) if column == indent && self.peek() == Some(':') && is_blank(self.peek_at(1)) => {
+                        self.pos += 1;
+                        self.block_node(indent as isize, Context::MappingValue)?
+                    }
+                    _ => Value::Null,
+                };
+                (key, value)
+            } else {
+                let key = self.key_node()?;
+                self.pos += 1;
+                (key, self.block_node(indent as isize, Context::MappingValue)?)
+            };
+            self.insert(&mut map, &mut merged, key, value, at)?;
+            match self.next_content()? {
+                Some(column) if column == indent && !self.at_document_marker() => {
+                    if !self.at_mapping_key() {
+                        return Err(self.error(tr("Expected a key")));
+                    }
+                }
+                Some(column) if column > indent => return Err(self.error(tr("Unexpected indentation"))),
+                _ => return Ok(Value::Object(map)),
+            }
+        }
+    }
+
+    // An implicit key up to its colon, which is left for the caller
+    fn key_node(&mut self) -> Result<Value, ConvertError> {
+        let properties = self.properties()?;
+        let line = self.line();
+        let key = match self.peek() {
+            Some('"') => Value::String(self.double_quoted()?),
+            Some('\'') => Value::String(self.single_quoted()?),
+            _ => {
+                let start = self.pos;
+                while self.peek().is_some() && !(self.peek() == Some(':') && is_blank(self.peek_at(1))) {
+                    self.pos += 1;
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                self.resolve(text.trim_end(), properties.tag.as_deref(), line)?
+            }
+        };
+        self.skip_inline_space();
+        if self.peek() != Some(':') {
+            return Err(self.unexpected());
+        }
+        self.anchor(properties.anchor, &key);
+        Ok(key)
+    }
+
+    fn insert(&mut self, map: &mut Map<String, Value>, merged: &mut HashSet<String>, key: Value, value: Value, (line, column): (usize, usize)) -> Result<(), ConvertError> {
+        if key == "<<" {
+            let sources = match value {
+                Value::Object(source) => vec![source],
+                Value::Array(items) if items.iter().all(Value::is_object) => {
+                    items.into_iter().filter_map(|item| if let Value::Object(source) = item { Some(source) } else { None }).collect()
+                }
+                _ => return Err(ConvertError::YamlParse { line, column, msg: tr("<< merges a mapping or a list of mappings").to_string() }),
+            };
+            // Keys of the mapping itself win over merged ones, earlier merged mappings over later
+            for (key, value) in sources.into_iter().flatten() {
+                if !map.contains_key(&key) {
+                    merged.insert(key.clone());
+                    map.insert(key, value);
+                }
+            }
+            return Ok(());
+        }
+        let key = match key {
+            Value::String(key) => key,
+            other if self.lossy => {
+                let text = other.to_string();
+                self.warn(line, trf("the key {} became a string", &[&text]));
+                text
+            }
+            _ => return Err(ConvertError::YamlParse { line, column, msg: tr("Map keys must be strings").to_string() }),
+        };
+        if map.contains_key(&key) && !merged.remove(&key) {
+            return Err(ConvertError::YamlParse { line, column, msg: trf("Duplicate key {}", &[&key]) });
+        }
+        map.insert(key, value);
+        Ok(())
+    }
+
+    fn properties(&mut self) -> Result<Properties, ConvertError> {
+        let mut properties = Properties::default();
+        loop {
+            self.skip_inline_space();
+            let line = self.line();
+            match self.peek() {
+                Some('&') => {
+                    self.pos += 1;
+                    properties.anchor = Some(self.name()?);
+                }
+                Some('!') => {
+                    let tag = self.name()?;
+                    if !tag.starts_with("!!") {
+                        self.warn(line, trf("the tag {} is ignored", &[&tag]));
+                    }
+                    properties.tag = Some(tag);
+                }
+                _ => return Ok(properties),
+            }
+        }
+    }
+
+    // Anchor, alias or tag name, which runs to the next blank or flow indicator
+    fn name(&mut self) -> Result<String, ConvertError> {
+        let start = self.pos;
+        while !is_blank(self.peek()) && !matches!(self.peek(), Some(',' | '[' | ']' | '{' | '}')) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.unexpected());
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn anchor(&mut self, anchor: Option<String>, value: &Value) {
+        if let Some(anchor) = anchor {
+            self.anchors.insert(anchor, value.clone());
+        }
+    }
+
+    fn alias(&mut self) -> Result<Value, ConvertError> {
+        self.pos += 1;
+        let start = self.pos;
+        let name = self.name()?;
+        let Some(value) = self.anchors.get(&name) else {
+            self.pos = start;
+            return Err(self.error(trf("Unknown alias *{}", &[&name])));
+        };
+        self.aliased += count_values(value);
+        if self.aliased > MAX_ALIASED_VALUES {
+            return Err(self.error(trf("Aliases expand to more than {} values", &[&MAX_ALIASED_VALUES])));
+        }
+        Ok(value.clone())
+    }
+
+    // A node that starts here and isn't a block collection
+    fn inline_node(&mut self, parent: isize, mut properties: Properties) -> Result<Value, ConvertError> {
+        let more = self.properties()?;
+        properties.anchor = more.anchor.or(properties.anchor);
+        properties.tag = more.tag.or(properties.tag);
+        let line = self.line();
+        let value = match self.peek() {
+            Some('[' | '{') => self.flow_node()?,
+            Some('"') => Value::String(self.double_quoted()?),
+            Some('\'') => Value::String(self.single_quoted()?),
+            Some('*') => self.alias()?,
+            Some('|' | '>') => Value::String(self.block_scalar(parent)?),
+            _ => {
+                let text = self.plain(parent, false)?;
+                self.resolve(&text, properties.tag.as_deref(), line)?
+            }
+        };
+        self.anchor(properties.anchor, &value);
+        Ok(value)
+    }
+
+    // A plain scalar, folded over the lines that continue it. In block context a continuation has
+    // to be indented further than `parent`, in flow context it just can't start with an indicator.
+    fn plain(&mut self, parent: isize, flow: bool) -> Result<String, ConvertError> {
+        if matches!(self.peek(), Some('-' | '?' | ':')) && is_blank(self.peek_at(1)) || matches!(self.peek(), Some(',' | ']' | '}' | '#' | '@' | '`' | '%')) {
+            return Err(self.unexpected());
+        }
+        let mut text = String::new();
+        loop {
+            let start = self.pos;
+            while let Some(c) = self.peek() {
+                let ends = match c {
+                    '\n' | '\r' => true,
+                    ':' => is_blank(self.peek_at(1)) || flow && matches!(self.peek_at(1), Some(',' | ']' | '}')),
+                    '#' => is_blank(self.chars.get(self.pos - 1).copied()),
+                    ',' | '[' | ']' | '{' | '}' => flow,
+                    _ => false,
+                };
+                if ends {
+                    break;
+                }
+                self.pos += 1;
+            }
+            let segment: String = self.chars[start..self.pos].iter().collect();
+            text.push_str(segment.trim_end());
+            if !matches!(self.peek(), Some('\n' | '\r')) {
+                return Ok(text);
+            }
+            // Look for a line that carries on
+            let end = self.pos;
+            let mut breaks = 0;
+            loop {
+                match self.peek() {
+                    Some('\n') => {
+                        breaks += 1;
+                        self.pos += 1;
+                    }
+                    Some(' ' | '\t' | '\r') => self.pos += 1,
+                    _ => break,
+                }
+            }
+            let continues = match self.peek() {
+                None | Some('#') => false,
+                Some(c) if flow => !matches!(c, ',' | ']' | '}' | ':'),
+                Some(_) => self.column() as isize > parent && !self.at_document_marker(),
+            };
+            if !continues {
+                self.pos = end;
+                return Ok(text);
+            }
+            if breaks == 1 {
+                text.push(' ');
+            } else {
+                text.extend(std::iter::repeat_n('\n', breaks - 1));
+            }
+        }
+    }
+
+    // What a plain scalar stands for in the core schema
+    fn resolve(&mut self, text: &str, tag: Option<&str>, line: usize) -> Result<Value, ConvertError> {
+        if tag == Some("!!str") {
+            return Ok(Value::String(text.to_string()));
+        }
+        let value = match text {
+            "" | "~" | "null" | "Null" | "NULL" => Value::Null,
+            "true" | "True" | "TRUE" => Value::Bool(true),
+            "false" | "False" | "FALSE" => Value::Bool(false),
+            _ => match number(text) {
+                Some(Ok(number)) => Value::Number(number),
+                Some(Err(())) if self.lossy => Value::String(text.to_string()),
+                Some(Err(())) => return Err(self.error(trf("{} has no JSON counterpart", &[&text]))),
+                None => {
+                    if YAML_1_1_BOOLEANS.contains(&text) {
+                        self.warn(line, trf("{} is a string here, YAML 1.1 reads it as a boolean", &[&text]));
+                    }
+                    Value::String(text.to_string())
+                }
+            },
+        };
+        Ok(value)
+    }
+
+    fn single_quoted(&mut self) -> Result<String, ConvertError> {
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error(tr("Unterminated string"))),
+                Some('\'') if self.peek_at(1) == Some('\'') => {
+                    text.push('\'');
+                    self.pos += 2;
+                }
+                Some('\'') => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\n' | '\r') => self.fold_quoted(&mut text),
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn double_quoted(&mut self) -> Result<String, ConvertError> {
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error(tr("Unterminated string"))),
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    let Some(escape) = self.peek() else {
+                        return Err(self.error(tr("Unterminated string")));
+                    };
+                    self.pos += 1;
+                    let c = match escape {
+                        '0' => '\0',
+                        'a' => '\x07',
+                        'b' => '\x08',
+                        't' | '\t' => '\t',
+                        'n' => '\n',
+                        'v' => '\x0b',
+                        'f' => '\x0c',
+                        'r' => '\r',
+                        'e' => '\x1b',
+                        'N' => '\u{85}',
+                        '_' => '\u{a0}',
+                        'L' => '\u{2028}',
+                        'P' => '\u{2029}',
+                        'x' => self.code_point(2)?,
+                        'u' => self.code_point(4)?,
+                        'U' => self.code_point(8)?,
+                        // An escaped line break joins the lines without a space
+                        '\n' | '\r' => {
+                            self.pos -= 1;
+                            while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                                self.pos += 1;
+                            }
+                            continue;
+                        }
+                        ' ' | '"' | '/' | '\\' => escape,
+                        _ => {
+                            self.pos -= 2;
+                            return Err(self.error(trf("Unknown escape \\{}", &[&escape])));
+                        }
+                    };
+                    text.push(c);
+                }
+                Some('\n' | '\r') => self.fold_quoted(&mut text),
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    // \u escapes in pairs of UTF-16 surrogates make one character, as in JSON
+    fn code_point(&mut self, digits: usize) -> Result<char, ConvertError> {
+        let start = self.pos - 2;
+        let hex = |parser: &mut Parser| {
+            let text: String = parser.chars.get(parser.pos..parser.pos + digits).map(|chars| chars.iter().collect()).unwrap_or_default();
+            parser.pos += digits;
+            u32::from_str_radix(&text, 16).ok().filter(|_| text.len() == digits)
+        };
+        let mut code = hex(self);
+        if let Some(high @ 0xd800..=0xdbff) = code {
+            if digits == 4 && self.peek() == Some('\\') && self.peek_at(1) == Some('u') {
+                self.pos += 2;
+                code = hex(self).filter(|low| (0xdc00..=0xdfff).contains(low)).map(|low| 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00));
+            }
+        }
+        code.and_then(char::from_u32).ok_or_else(|| {
+            self.pos = start;
+            self.error(tr("Invalid escape"))
+        })
+    }
+
+    // A line break inside quotes: one becomes a space, each further one a newline
+    fn fold_quoted(&mut self, text: &mut String) {
+        text.truncate(text.trim_end_matches([' ', '\t']).len());
+        let mut breaks = 0;
+        while let Some(c @ (' ' | '\t' | '\r' | '\n')) = self.peek() {
+            if c == '\n' {
+                breaks += 1;
+            }
+            self.pos += 1;
+        }
+        if breaks <= 1 {
+            text.push(' ');
+        } else {
+            text.extend(std::iter::repeat_n('\n', breaks - 1));
+        }
+    }
+
+    // | keeps the line breaks, > folds lines into spaces. The content is indented further than
+    // `parent`, by what its first line has or what the indicator says.
+    fn block_scalar(&mut self, parent: isize) -> Result<String, ConvertError> {
+        let literal = self.peek() == Some('|');
+        self.pos += 1;
+        let mut chomping = None;
+        let mut indent = None;
+        for _ in 0..2 {
+            match self.peek() {
+                Some(c @ ('-' | '+')) if chomping.is_none() => chomping = Some(c),
+                Some(c @ '1'..='9') if indent.is_none() => indent = Some(parent.max(0) as usize + c as usize - '0' as usize),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        if !self.at_line_end() {
+            return Err(self.unexpected());
+        }
+        if self.peek() == Some('\r') {
+            self.pos += 1;
+        }
+        if self.peek() == Some('\n') {
+            self.pos += 1;
+        }
+        let mut lines: Vec<String> = Vec::new();
+        while self.pos < self.chars.len() {
+            let start = self.pos;
+            let spaces = self.chars[start..].iter().take_while(|&&c| c == ' ').count();
+            let end = self.chars[start..].iter().position(|&c| c == '\n').map_or(self.chars.len(), |i| start + i);
+            let line: String = self.chars[start..end].iter().collect();
+            let line = line.strip_suffix('\r').unwrap_or(&line).to_string();
+            let blank = line.trim_start_matches(' ').is_empty();
+            if !blank && indent.is_none() {
+                if spaces as isize <= parent {
+                    break;
+                }
+                indent = Some(spaces);
+            }
+            if !blank && indent.is_some_and(|indent| spaces < indent) {
+                break;
+            }
+            self.pos = start;
+            if spaces == 0 && !blank && self.at_document_marker() {
+                break;
+            }
+            lines.push(indent.map_or(String::new(), |indent| line.get(indent..).unwrap_or("").to_string()));
+            self.pos = (end + 1).min(self.chars.len());
+        }
+        self.pos = self.pos.min(self.chars.len());
+        let trailing = lines.iter().rev().take_while(|line| line.is_empty()).count();
+        let content = &lines[..lines.len() - trailing];
+        let mut text = String::new();
+        if literal {
+            text = content.join("\n");
+        } else {
+            let mut blanks = 0;
+            let mut previous: Option<&str> = None;
+            for line in content {
+                if line.is_empty() {
+                    blanks += 1;
+                    continue;
+                }
+                match previous {
+                    None => text.extend(std::iter::repeat_n('\n', blanks)),
+                    Some(previous) => {
+                        let more_indented = |line: &str| line.starts_with([' ', '\t']);
+                        if blanks == 0 && !more_indented(previous) && !more_indented(line) {
+                            text.push(' ');
+                        } else if !more_indented(previous) && !more_indented(line) {
+                            text.extend(std::iter::repeat_n('\n', blanks));
+                        } else {
+                            text.extend(std::iter::repeat_n('\n', blanks + 1));
+                        }
+                    }
+                }
+                blanks = 0;
+                text.push_str(line);
+                previous = Some(line);
+            }
+        }
+        match chomping {
+            Some('-') => {}
+            Some(_) => text.extend(std::iter::repeat_n('\n', trailing + usize::from(!content.is_empty()))),
+            None if !content.is_empty() => text.push('\n'),
+            None => {}
+        }
+        Ok(text)
+    }
+
+    // [ ... ] or { ... }, which may spread over any number of lines
+    fn flow_node(&mut self) -> Result<Value, ConvertError> {
+        self.deeper()?;
+        self.skip_flow_space();
+        let properties = self.properties()?;
+        self.skip_flow_space();
+        let line = self.line();
+        let value = match self.peek() {
+            Some('[') => self.flow_sequence()?,
+            Some('{') => self.flow_mapping()?,
+            Some('"') => Value::String(self.double_quoted()?),
+            Some('\'') => Value::String(self.single_quoted()?),
+            Some('*') => self.alias()?,
+            _ => {
+                let text = self.plain(-1, true)?;
+                self.resolve(&text, properties.tag.as_deref(), line)?
+            }
+        };
+        self.anchor(properties.anchor, &value);
+        self.depth -= 1;
+        Ok(value)
+    }
+
+    fn skip_flow_space(&mut self) {
+        loop {
+            while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            self.skip_comment();
+            // A # right after a bracket or a comma isn't a comment, it's left for the caller to
+            // fail on
+            if self.pos == start {
+                return;
+            }
+        }
+    }
+
+    fn flow_sequence(&mut self) -> Result<Value, ConvertError> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_flow_space();
+            if self.peek() == Some(']') {
+                break;
+            }
+            let at = (self.line(), self.column() + 1);
+            let item = self.flow_node()?;
+            self.skip_flow_space();
+            // [key: value] is a list holding a mapping with that one entry
+            if self.peek() == Some(':') {
+                self.pos += 1;
+                let value = self.flow_value()?;
+                let mut map = Map::new();
+                self.insert(&mut map, &mut HashSet::new(), item, value, at)?;
+                items.push(Value::Object(map));
+            } else {
+                items.push(item);
+            }
+            self.skip_flow_space();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => break,
+                _ => return Err(self.unexpected()),
+            }
+        }
+        self.pos += 1;
+        Ok(Value::Array(items))
+    }
+
+    fn flow_mapping(&mut self) -> Result<Value, ConvertError> {
+        self.pos += 1;
+        let mut map = Map::new();
+        let mut merged = HashSet::new();
+        loop {
+            self.skip_flow_space();
+            if self.peek() == Some('}') {
+                break;
+            }
+            if self.peek() == Some('?') && is_blank(self.peek_at(1)) {
+                self.pos += 1;
+            }
+            let at = (self.line(), self.column() + 1);
+            let key = self.flow_node()?;
+            self.skip_flow_space();
+            let value = if self.peek() == Some(':') {
+                self.pos += 1;
+                self.flow_value()?
+            } else {
+                Value::Null
+            };
+            self.insert(&mut map, &mut merged, key, value, at)?;
+            self.skip_flow_space();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => break,
+                _ => return Err(self.unexpected()),
+            }
+        }
+        self.pos += 1;
+        Ok(Value::Object(map))
+    }
+
+    // What follows a colon in flow context, null when nothing does
+    fn flow_value(&mut self) -> Result<Value, ConvertError> {
+        self.skip_flow_space();
+        if matches!(self.peek(), Some(',' | ']' | '}')) {
+            return Ok(Value::Null);
+        }
+        self.flow_node()
+    }
+}
+
+impl Properties {
+    // The anchor is set once the whole node is known, by whoever parsed these
+    fn clone_tag(&self) -> Properties {
+        Properties { anchor: None, tag: self.tag.clone() }
+    }
+}
+
+fn is_blank(c: Option<char>) -> bool {
+    matches!(c, None | Some(' ' | '\t' | '\n' | '\r'))
+}
+
+fn count_values(value: &Value) -> usize {
+    1 + match value {
+        Value::Array(items) => items.iter().map(count_values).sum(),
+        Value::Object(map) => map.values().map(count_values).sum(),
+        _ => 0,
+    }
+}
+
+// The number a plain scalar is in the core schema, an error for one JSON can't hold such as .inf
+fn number(text: &str) -> Option<Result<Number, ()>> {
+    let digits = |s: &str, radix: u32| !s.is_empty() && s.chars().all(|c| c.is_digit(radix));
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    if let Some(hex) = text.strip_prefix("0x").filter(|hex| digits(hex, 16)) {
+        return Some(u64::from_str_radix(hex, 16).map(Number::from).map_err(|_| ()));
+    }
+    if let Some(octal) = text.strip_prefix("0o").filter(|octal| digits(octal, 8)) {
+        return Some(u64::from_str_radix(octal, 8).map(Number::from).map_err(|_| ()));
+    }
+    if digits(unsigned, 10) {
+        if let Ok(n) = text.parse::<i64>() {
+            return Some(Ok(Number::from(n)));
+        }
+        if let Ok(n) = text.parse::<u64>() {
+            return Some(Ok(Number::from(n)));
+        }
+    }
+    if matches!(unsigned, ".inf" | ".Inf" | ".INF") || matches!(text, ".nan" | ".NaN" | ".NAN") {
+        return Some(Err(()));
+    }
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent.strip_prefix(['+', '-']).unwrap_or(exponent))),
+        None => (unsigned, None),
+    };
+    let mantissa_ok = match mantissa.split_once('.') {
+        Some((whole, fraction)) => (whole.is_empty() || digits(whole, 10)) && (fraction.is_empty() || digits(fraction, 10)) && !(whole.is_empty() && fraction.is_empty()),
+        None => digits(mantissa, 10),
+    };
+    if !mantissa_ok || exponent.is_some_and(|exponent| !digits(exponent, 10)) {
+        return None;
+    }
+    Some(text.parse::<f64>().ok().and_then(Number::from_f64).ok_or(()))
+}
+
+// Block style YAML, `indent` spaces per level. Strings that would read back as something else, in
+// YAML 1.2 or 1.1, are quoted.
+pub fn to_yaml(value: &Value, indent: usize) -> String {
+    let mut out = String::new();
+    let step = indent.max(1);
+    match value {
+        Value::Object(map) if !map.is_empty() => write_mapping(&mut out, map, 0, step, false),
+        Value::Array(items) if !items.is_empty() => write_sequence(&mut out, items, 0, step, false),
+        _ => {
+            out.push_str(&scalar(value, 0, step));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+// `inline` when the first entry goes on a line already started by "- "
+fn write_mapping(out: &mut String, map: &Map<String, Value>, indent: usize, step: usize, inline: bool) {
+    for (i, (key, value)) in map.iter().enumerate() {
+        if i > 0 || !inline {
+            out.push_str(&" ".repeat(indent));
+        }
+        out.push_str(&quoted_if_needed(key));
+        out.push(':');
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                out.push('\n');
+                write_mapping(out, map, indent + step, step, false);
+            }
+            Value::Array(items) if !items.is_empty() => {
+                out.push('\n');
+                write_sequence(out, items, indent + step, step, false);
+            }
+            _ => {
+                out.push(' ');
+                out.push_str(&scalar(value, indent, step));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+fn write_sequence(out: &mut String, items: &[Value], indent: usize, step: usize, inline: bool) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 || !inline {
+            out.push_str(&" ".repeat(indent));
+        }
+        *out += "- ";
+        // Collections start on the dash's line, indented by the "- "
+        match item {
+            Value::Object(map) if !map.is_empty() => write_mapping(out, map, indent + 2, step, true),
+            Value::Array(items) if !items.is_empty() => write_sequence(out, items, indent + 2, step, true),
+            _ => {
+                out.push_str(&scalar(item, indent, step));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+// A value without entries, a string as a literal block where it has line breaks
+fn scalar(value: &Value, indent: usize, step: usize) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) if literal_block_fits(s) => {
+            let body = s.trim_end_matches('\n');
+            let chomping = match s.len() - body.len() {
+                0 => "-",
+                1 => "",
+                _ => "+",
+            };
+            let mut block = format!("|{}", chomping);
+            for line in s.strip_suffix('\n').unwrap_or(s).split('\n') {
+                block.push('\n');
+                if !line.is_empty() {
+                    block.push_str(&" ".repeat(indent + step));
+                    block.push_str(line);
+                }
+            }
+            block
+        }
+        Value::String(s) => quoted_if_needed(s),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+    }
+}
+
+fn literal_block_fits(s: &str) -> bool {
+    let first = s.split('\n').next().unwrap_or("");
+    s.contains('\n')
+        && !first.is_empty()
+        && !first.starts_with([' ', '\t'])
+        && s.chars().all(|c| c == '\n' || c == '\t' || !c.is_control())
+        && s.split('\n').all(|line| line.is_empty() || !line.trim_start_matches([' ', '\t']).is_empty())
+}
+
+// JSON's own quoting is valid YAML, so strings that can't go plain get that
+fn quoted_if_needed(s: &str) -> String {
+    if plain_fits(s) {
+        s.to_string()
+    } else {
+        serde_json::Value::String(s.to_string()).to_string()
+    }
+}
+
+fn plain_fits(s: &str) -> bool {
+    let reads_as_string = !matches!(s, "~" | "null" | "Null" | "NULL" | "true" | "True" | "TRUE" | "false" | "False" | "FALSE" | "<<")
+        && number(s).is_none()
+        && !YAML_1_1_BOOLEANS.contains(&s);
+    // YAML 1.1 has more number forms, such as 1_000 and 1:30, so anything that starts like a
+    // number stays quoted
+    let starts_like_number = s.trim_start_matches(['+', '-', '.']).starts_with(|c: char| c.is_ascii_digit());
+    reads_as_string
+        && !starts_like_number
+        && !s.is_empty()
+        && s.trim() == s
+        && !s.starts_with(['-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%', '@', '`'])
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.ends_with(':')
+        && s.chars().all(|c| !c.is_control() && c != '\u{feff}')
+}
+
+
+/* Tests */
+#[cfg(test)]
+fn parse(text: &str) -> Value {
+    parse_yaml(text, false).unwrap().single().unwrap()
+}
+
+#[test]
+fn test_parse_block_and_flow_collections() {
+    let text = "\
+# A config file
+name: Alice
+age: 30
+tags: [admin, 'ops team', \"x\\ty\"]
+pets:
+- kind: cat
+  lives: 9
+- - nested
+  - 1.5e3
+empty:
+limits: {cpu: 2, memory: null}
+";
+    assert_eq!(parse(text), serde_json::json!({
+        "name": "Alice",
+        "age": 30,
+        "tags": ["admin", "ops team", "x\ty"],
+        "pets": [{"kind": "cat", "lives": 9}, ["nested", 1500.0]],
+        "empty": null,
+        "limits": {"cpu": 2, "memory": null},
+    }));
+    assert_eq!(parse("- 0x1f\n- 0o17\n- -12\n- .5\n- ~\n- TRUE\n- 1.2.3"), serde_json::json!([31, 15, -12, 0.5, null, true, "1.2.3"]));
+}
+
+#[test]
+fn test_parse_scalars() {
+    let text = "\
+literal: |
+  line one
+    indented
+
+folded: >-
+  folded
+  into one
+
+  paragraph
+plain: carries
+  on here
+quoted: 'it''s'
+escaped: \"\\u00e9\\ud83d\\ude00 \\x41\"
+";
+    assert_eq!(parse(text), serde_json::json!({
+        "literal": "line one\n  indented\n",
+        "folded": "folded into one\nparagraph",
+        "plain": "carries on here",
+        "quoted": "it's",
+        "escaped": "é😀 A",
+    }));
+}
+
+#[test]
+fn test_anchors_aliases_and_merge_keys() {
+    let text = "\
+base: &base
+  host: localhost
+  port: 80
+dev:
+  <<: *base
+  port: 8080
+copy: *base
+";
+    assert_eq!(parse(text), serde_json::json!({
+        "base": {"host": "localhost", "port": 80},
+        "dev": {"host": "localhost", "port": 8080},
+        "copy": {"host": "localhost", "port": 80},
+    }));
+    let err = parse_yaml("a: *missing", false).unwrap_err();
+    assert!(matches!(err, ConvertError::YamlParse { line: 1, column: 5, .. }));
+    // Each level doubles the one before
+    let mut bomb = "k0: &a0 [x, x]\n".to_string();
+    for level in 1..30 {
+        bomb.push_str(&format!("k{0}: &a{0} [*a{1}, *a{1}]\n", level, level - 1));
+    }
+    assert!(parse_yaml(&bomb, false).is_err());
+}
+
+#[test]
+fn test_hazards_are_handled_or_warned_about() {
+    // Keys that aren't strings
+    assert!(matches!(parse_yaml("200: ok", false), Err(ConvertError::YamlParse { line: 1, column: 1, .. })));
+    let parsed = parse_yaml("200: ok\ntrue: yes\n", true).unwrap();
+    assert_eq!(parsed.documents, [serde_json::json!({"200": "ok", "true": "yes"})]);
+    let warnings: Vec<String> = parsed.warnings.iter().map(Warning::to_string).collect();
+    assert_eq!(warnings, [
+        "Line 1: the key 200 became a string",
+        "Line 2: yes is a string here, YAML 1.1 reads it as a boolean",
+        "Line 2: the key true became a string",
+    ]);
+
+    // Several documents
+    let parsed = parse_yaml("--- 1\n--- [a]\n...\n", false).unwrap();
+    assert_eq!(parsed.documents, [serde_json::json!(1), serde_json::json!(["a"])]);
+    assert!(matches!(parsed.clone().single(), Err(ConvertError::YamlParse { line: 2, .. })));
+    let (value, warnings) = parsed.combined();
+    assert_eq!(value, serde_json::json!([1, ["a"]]));
+    assert_eq!(warnings, [Warning::new(WarningKind::Yaml, "2 documents were combined into an array".to_string())]);
+
+    assert!(parse_yaml("a: .inf", false).is_err());
+    assert_eq!(parse_yaml("a: .inf", true).unwrap().documents, [serde_json::json!({"a": ".inf"})]);
+    assert!(matches!(parse_yaml("a: 1\na: 2", false), Err(ConvertError::YamlParse { line: 2, .. })));
+    assert!(matches!(parse_yaml("a: 1\n  b: 2", false), Err(ConvertError::YamlParse { line: 2, .. })));
+    // A # that follows a bracket without a space is an error, not a comment to skip
+    for text in ["[[]#]", "[{}#]", "[{c: d}#]"] {
+        assert!(matches!(parse_yaml(text, false), Err(ConvertError::YamlParse { line: 1, .. })), "{}", text);
+    }
+    assert_eq!(parse("[[] # a comment\n]"), serde_json::json!([[]]));
+}
+
+#[test]
+fn test_to_yaml_round_trips() {
+    let value = serde_json::json!({
+        "name": "Alice",
+        "empty": {},
+        "list": [1, [true, null], {"a": "b", "c": []}],
+        "tricky": ["no", "on", "123", "1_000", "", " padded", "a: b", "- x", "null", "<<"],
+        "text": "two\nlines\n",
+        "unicode": "é\u{1}",
+    });
+    let yaml = to_yaml(&value, 2);
+    assert!(yaml.starts_with("name: Alice\nempty: {}\nlist:\n  - 1\n  - - true\n    - null\n  - a: b\n    c: []\n"), "{}", yaml);
+    assert!(yaml.contains("  - \"no\"\n  - \"on\"\n  - \"123\"\n"));
+    assert!(yaml.contains("text: |\n  two\n  lines\n"));
+    assert_eq!(parse(&yaml), value);
+    assert_eq!(to_yaml(&serde_json::json!("plain"), 2), "plain\n");
+}
@@ -0,0 +1,949 @@
+use crate::compress::over_limit;
+use crate::error::ConvertError;
+use crate::locale::tr;
+
+// Zstandard frames as RFC 8878 describes them. Decoding covers the whole format except
+// dictionaries. Encoding finds matches with a hash chain, searched deeper at higher levels, and
+// writes them with the predefined tables and the literals uncompressed.
+
+pub const MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+pub const DEFAULT_LEVEL: i32 = 3;
+pub const MAX_LEVEL: i32 = 19;
+
+const MAX_BLOCK_SIZE: usize = 128 * 1024;
+// Skippable frames carry anything after a magic number from 0x184d2a50 to 0x184d2a5f
+const SKIPPABLE_MAGIC: u32 = 0x184d_2a50;
+
+// Literal lengths, match lengths and offsets are coded as a symbol plus extra bits. For the
+// lengths the symbol picks a baseline and a number of extra bits.
+const LITERAL_LENGTHS: [(u32, u32); 36] = [
+    (0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0), (11, 0),
+    (12, 0), (13, 0), (14, 0), (15, 0), (16, 1), (18, 1), (20, 1), (22, 1), (24, 2), (28, 2), (32, 3),
+    (40, 3), (48, 4), (64, 6), (128, 7), (256, 8), (512, 9), (1024, 10), (2048, 11), (4096, 12),
+    (8192, 13), (16384, 14), (32768, 15), (65536, 16),
+];
+const MATCH_LENGTHS: [(u32, u32); 53] = [
+    (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0), (11, 0), (12, 0), (13, 0), (14, 0),
+    (15, 0), (16, 0), (17, 0), (18, 0), (19, 0), (20, 0), (21, 0), (22, 0), (23, 0), (24, 0), (25, 0),
+    (26, 0), (27, 0), (28, 0), (29, 0), (30, 0), (31, 0), (32, 0), (33, 0), (34, 0), (35, 1), (37, 1),
+    (39, 1), (41, 1), (43, 2), (47, 2), (51, 3), (59, 3), (67, 4), (83, 4), (99, 5), (131, 7), (259, 8),
+    (515, 9), (1027, 10), (2051, 11), (4099, 12), (8195, 13), (16387, 14), (32771, 15), (65539, 16),
+];
+const MAX_OFFSET_CODE: u8 = 31;
+
+// The distributions a block can use without describing its own
+const PREDEFINED_LITERAL_LENGTHS: (&[i16], u32) = (&[
+    4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1, -1, -1,
+    -1, -1,
+], 6);
+const PREDEFINED_MATCH_LENGTHS: (&[i16], u32) = (&[
+    1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+], 6);
+const PREDEFINED_OFFSETS: (&[i16], u32) = (&[
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+], 5);
+
+// The encoder stays within offsets the predefined offset table can code
+const MAX_MATCH_OFFSET: usize = 1 << 22;
+const MIN_MATCH: usize = 4;
+const HASH_LOG: u32 = 17;
+
+fn corrupt() -> ConvertError {
+    ConvertError::Decompress(tr("Corrupt zstd data").to_string())
+}
+
+/* Decoding */
+
+// Everything in the frames of `bytes`, one after the other. Producing more than `max_size` bytes
+// is an error, checked after every block.
+pub fn decompress(bytes: &[u8], max_size: usize) -> Result<Vec<u8>, ConvertError> {
+    let mut out = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let magic = u32::from_le_bytes(rest.get(..4).ok_or_else(corrupt)?.try_into().unwrap());
+        let used = if rest[..4] == MAGIC {
+            decode_frame(rest, &mut out, max_size)?
+        } else if magic & 0xffff_fff0 == SKIPPABLE_MAGIC {
+            let size = u32::from_le_bytes(rest.get(4..8).ok_or_else(corrupt)?.try_into().unwrap());
+            8 + size as usize
+        } else {
+            return Err(corrupt());
+        };
+        rest = rest.get(used..).ok_or_else(corrupt)?;
+    }
+    Ok(out)
+}
+
+// Appends one frame to `out` and says how many bytes it took up
+fn decode_frame(bytes: &[u8], out: &mut Vec<u8>, max_size: usize) -> Result<usize, ConvertError> {
+    let descriptor = *bytes.get(4).ok_or_else(corrupt)?;
+    let single_segment = descriptor & 0x20 != 0;
+    let has_checksum = descriptor & 0x04 != 0;
+    if descriptor & 0x08 != 0 {
+        return Err(corrupt());
+    }
+    let mut position = if single_segment { 5 } else { 6 };
+    let dictionary_size = [0, 1, 2, 4][usize::from(descriptor & 3)];
+    let dictionary = read_le(bytes, position, dictionary_size)?;
+    position += dictionary_size;
+    if dictionary != 0 {
+        return Err(ConvertError::Decompress(tr("zstd dictionaries aren't supported").to_string()));
+    }
+    let content_size_size = match descriptor >> 6 {
+        0 => usize::from(single_segment),
+        1 => 2,
+        2 => 4,
+        _ => 8,
+    };
+    let content_size = match content_size_size {
+        0 => None,
+        2 => Some(read_le(bytes, position, 2)? + 256),
+        size => Some(read_le(bytes, position, size)?),
+    };
+    position += content_size_size;
+    // Said up front, no need to inflate anything to know
+    if content_size.is_some_and(|size| size > (max_size - out.len()) as u64) {
+        return Err(over_limit(max_size));
+    }
+
+    let start = out.len();
+    let mut state = FrameState::default();
+    loop {
+        let header = read_le(bytes, position, 3)? as usize;
+        position += 3;
+        let (last, kind, size) = (header & 1 != 0, (header >> 1) & 3, header >> 3);
+        if size > MAX_BLOCK_SIZE {
+            return Err(corrupt());
+        }
+        match kind {
+            0 => {
+                out.extend_from_slice(bytes.get(position..position + size).ok_or_else(corrupt)?);
+                position += size;
+            }
+            1 => {
+                let byte = *bytes.get(position).ok_or_else(corrupt)?;
+                out.resize(out.len() + size, byte);
+                position += 1;
+            }
+            2 => {
+                let block = bytes.get(position..position + size).ok_or_else(corrupt)?;
+                state.decode_block(block, out)?;
+                position += size;
+            }
+            _ => return Err(corrupt()),
+        }
+        if out.len() > max_size {
+            return Err(over_limit(max_size));
+        }
+        if last {
+            break;
+        }
+    }
+    if content_size.is_some_and(|size| size != (out.len() - start) as u64) {
+        return Err(corrupt());
+    }
+    if has_checksum {
+        let checksum = read_le(bytes, position, 4)?;
+        position += 4;
+        if checksum != xxh64(&out[start..]) & 0xffff_ffff {
+            return Err(ConvertError::Decompress(tr("The zstd checksum doesn't match").to_string()));
+        }
+    }
+    Ok(position)
+}
+
+fn read_le(bytes: &[u8], position: usize, size: usize) -> Result<u64, ConvertError> {
+    let field = bytes.get(position..position + size).ok_or_else(corrupt)?;
+    Ok(field.iter().rev().fold(0, |value, &byte| value << 8 | u64::from(byte)))
+}
+
+// What later blocks of a frame can refer back to
+struct FrameState {
+    huffman: Option<Huffman>,
+    literal_lengths: Option<Fse>,
+    offsets: Option<Fse>,
+    match_lengths: Option<Fse>,
+    repeat_offsets: [usize; 3],
+}
+
+impl Default for FrameState {
+    fn default() -> Self {
+        FrameState { huffman: None, literal_lengths: None, offsets: None, match_lengths: None, repeat_offsets: [1, 4, 8] }
+    }
+}
+
+struct Sequence {
+    literal_length: usize,
+    offset_value: usize,
+    match_length: usize,
+}
+
+impl FrameState {
+    fn decode_block(&mut self, block: &[u8], out: &mut Vec<u8>) -> Result<(), ConvertError> {
+        let (literals, used) = self.literals(block)?;
+        let sequences = self.sequences(block.get(used..).ok_or_else(corrupt)?)?;
+        let limit = out.len() + MAX_BLOCK_SIZE;
+        let mut literals = &literals[..];
+        for sequence in sequences {
+            let (copied, rest) = literals.split_at_checked(sequence.literal_length).ok_or_else(corrupt)?;
+            out.extend_from_slice(copied);
+            literals = rest;
+
+            let repeats = self.repeat_offsets;
+            let offset = if sequence.offset_value > 3 {
+                let offset = sequence.offset_value - 3;
+                self.repeat_offsets = [offset, repeats[0], repeats[1]];
+                offset
+            } else {
+                // Without literals before it the first repeat is skipped
+                match sequence.offset_value - 1 + usize::from(sequence.literal_length == 0) {
+                    0 => repeats[0],
+                    1 => {
+                        self.repeat_offsets = [repeats[1], repeats[0], repeats[2]];
+                        repeats[1]
+                    }
+                    index => {
+                        let offset = if index == 2 { repeats[2] } else { repeats[0] - 1 };
+                        self.repeat_offsets = [offset, repeats[0], repeats[1]];
+                        offset
+                    }
+                }
+            };
+            if offset == 0 || offset > out.len() || out.len() + sequence.match_length > limit {
+                return Err(corrupt());
+            }
+            let from = out.len() - offset;
+            if offset >= sequence.match_length {
+                out.extend_from_within(from..from + sequence.match_length);
+            } else {
+                // The match overlaps what it writes
+                for index in from..from + sequence.match_length {
+                    out.push(out[index]);
+                }
+            }
+        }
+        if out.len() + literals.len() > limit {
+            return Err(corrupt());
+        }
+        out.extend_from_slice(literals);
+        Ok(())
+    }
+
+    // The literals section and its length
+    fn literals(&mut self, block: &[u8]) -> Result<(Vec<u8>, usize), ConvertError> {
+        let first = *block.first().ok_or_else(corrupt)?;
+        let (kind, size_format) = (first & 3, (first >> 2) & 3);
+        if kind < 2 {
+            let (size, header) = match size_format {
+                0 | 2 => (usize::from(first >> 3), 1),
+                1 => (read_le(block, 0, 2)? as usize >> 4, 2),
+                _ => (read_le(block, 0, 3)? as usize >> 4, 3),
+            };
+            if size > MAX_BLOCK_SIZE {
+                return Err(corrupt());
+            }
+            return if kind == 0 {
+                Ok((block.get(header..header + size).ok_or_else(corrupt)?.to_vec(), header + size))
+            } else {
+                Ok((vec![*block.get(header).ok_or_else(corrupt)?; size], header + 1))
+            };
+        }
+
+        let (header, size_bits, streams) = match size_format {
+            0 => (3, 10, 1),
+            1 => (3, 10, 4),
+            2 => (4, 14, 4),
+            _ => (5, 18, 4),
+        };
+        let sizes = read_le(block, 0, header)? >> 4;
+        let regenerated = (sizes & ((1 << size_bits) - 1)) as usize;
+        let compressed = (sizes >> size_bits) as usize;
+        if regenerated > MAX_BLOCK_SIZE {
+            return Err(corrupt());
+        }
+        let mut data = block.get(header..header + compressed).ok_or_else(corrupt)?;
+        // The other kind reuses the tree of the block before
+        if kind == 2 {
+            let (huffman, used) = Huffman::read(data)?;
+            self.huffman = Some(huffman);
+            data = &data[used..];
+        }
+        let huffman = self.huffman.as_ref().ok_or_else(corrupt)?;
+        let mut literals = Vec::with_capacity(regenerated);
+        if streams == 1 {
+            huffman.decode(data, regenerated, &mut literals)?;
+        } else {
+            let segment = regenerated.div_ceil(4);
+            let last = regenerated.checked_sub(3 * segment).ok_or_else(corrupt)?;
+            let mut position = 6;
+            for stream in 0..4 {
+                let size = if stream < 3 { read_le(data, stream * 2, 2)? as usize } else { data.len().saturating_sub(position) };
+                let bytes = data.get(position..position + size).ok_or_else(corrupt)?;
+                huffman.decode(bytes, if stream < 3 { segment } else { last }, &mut literals)?;
+                position += size;
+            }
+        }
+        Ok((literals, header + compressed))
+    }
+
+    fn sequences(&mut self, section: &[u8]) -> Result<Vec<Sequence>, ConvertError> {
+        let first = *section.first().ok_or_else(corrupt)?;
+        let (count, mut position) = match first {
+            0 => return Ok(Vec::new()),
+            1..=127 => (usize::from(first), 1),
+            128..=254 => ((usize::from(first) - 128) << 8 | read_le(section, 1, 1)? as usize, 2),
+            _ => (read_le(section, 1, 2)? as usize + 0x7f00, 3),
+        };
+        let modes = *section.get(position).ok_or_else(corrupt)?;
+        position += 1;
+        let tables = [
+            (6, PREDEFINED_LITERAL_LENGTHS, 9, LITERAL_LENGTHS.len() - 1, &mut self.literal_lengths),
+            (4, PREDEFINED_OFFSETS, 8, usize::from(MAX_OFFSET_CODE), &mut self.offsets),
+            (2, PREDEFINED_MATCH_LENGTHS, 9, MATCH_LENGTHS.len() - 1, &mut self.match_lengths),
+        ];
+        for (shift, (probabilities, log), max_log, max_symbol, table) in tables {
+            match (modes >> shift) & 3 {
+                0 => *table = Some(Fse::build(probabilities, log)),
+                1 => {
+                    let symbol = *section.get(position).ok_or_else(corrupt)?;
+                    if usize::from(symbol) > max_symbol {
+                        return Err(corrupt());
+                    }
+                    *table = Some(Fse::single(symbol));
+                    position += 1;
+                }
+                2 => {
+                    let (read, used) = Fse::read(section.get(position..).ok_or_else(corrupt)?, max_log, max_symbol)?;
+                    *table = Some(read);
+                    position += used;
+                }
+                _ => {
+                    table.as_ref().ok_or_else(corrupt)?;
+                }
+            }
+        }
+
+        let (Some(literal_lengths), Some(offsets), Some(match_lengths)) = (&self.literal_lengths, &self.offsets, &self.match_lengths) else {
+            return Err(corrupt());
+        };
+        let mut bits = BackwardBits::new(section.get(position..).ok_or_else(corrupt)?)?;
+        let mut literal_length_state = literal_lengths.start(&mut bits);
+        let mut offset_state = offsets.start(&mut bits);
+        let mut match_length_state = match_lengths.start(&mut bits);
+        let mut sequences = Vec::with_capacity(count.min(MAX_BLOCK_SIZE));
+        for index in 0..count {
+            let offset_code = offsets.symbol(offset_state);
+            let (match_base, match_bits) = MATCH_LENGTHS[usize::from(match_lengths.symbol(match_length_state))];
+            let (literal_base, literal_bits) = LITERAL_LENGTHS[usize::from(literal_lengths.symbol(literal_length_state))];
+            let offset_value = (1 << offset_code) + bits.read(u32::from(offset_code)) as usize;
+            let match_length = match_base as usize + bits.read(match_bits) as usize;
+            let literal_length = literal_base as usize + bits.read(literal_bits) as usize;
+            sequences.push(Sequence { literal_length, offset_value, match_length });
+            if index + 1 < count {
+                literal_length_state = literal_lengths.next(literal_length_state, &mut bits);
+                match_length_state = match_lengths.next(match_length_state, &mut bits);
+                offset_state = offsets.next(offset_state, &mut bits);
+            }
+        }
+        if bits.remaining != 0 {
+            return Err(corrupt());
+        }
+        Ok(sequences)
+    }
+}
+
+// `count` bits from bit `position` on, least significant first, zeros past the end
+fn bits_at(bytes: &[u8], position: usize, count: u32) -> u64 {
+    let start = position / 8;
+    let mut word = [0; 8];
+    let available = bytes.len().saturating_sub(start).min(8);
+    word[..available].copy_from_slice(bytes.get(start..start + available).unwrap_or(&[]));
+    (u64::from_le_bytes(word) >> (position % 8)) & ((1 << count) - 1)
+}
+
+// Entropy-coded streams are read from their end back. The last byte's highest set bit marks
+// where they start. Reading past the beginning gives zeros and takes `remaining` below zero.
+struct BackwardBits<'a> {
+    bytes: &'a [u8],
+    remaining: isize,
+}
+
+impl<'a> BackwardBits<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self, ConvertError> {
+        let last = *bytes.last().ok_or_else(corrupt)?;
+        if last == 0 {
+            return Err(corrupt());
+        }
+        Ok(BackwardBits { bytes, remaining: (bytes.len() * 8) as isize - 1 - last.leading_zeros() as isize })
+    }
+
+    fn peek(&self, count: u32) -> u64 {
+        let position = self.remaining - count as isize;
+        if position >= 0 {
+            bits_at(self.bytes, position as usize, count)
+        } else if position > -(count as isize) {
+            bits_at(self.bytes, 0, (count as isize + position) as u32) << -position
+        } else {
+            0
+        }
+    }
+
+    fn read(&mut self, count: u32) -> u64 {
+        let value = self.peek(count);
+        self.remaining -= count as isize;
+        value
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct FseCell {
+    symbol: u8,
+    bits: u8,
+    base: u16,
+}
+
+// A finite state entropy table. The state is an index into it.
+#[derive(Debug, Clone)]
+struct Fse {
+    log: u32,
+    cells: Vec<FseCell>,
+}
+
+impl Fse {
+    // From the normalized probabilities, where -1 is a symbol rarer than the rest
+    fn build(probabilities: &[i16], log: u32) -> Fse {
+        let size = 1 << log;
+        let mut cells = vec![FseCell::default(); size];
+        let mut next = vec![0u32; probabilities.len()];
+        let mut high = size;
+        for (symbol, &probability) in probabilities.iter().enumerate() {
+            if probability == -1 {
+                high -= 1;
+                cells[high].symbol = symbol as u8;
+                next[symbol] = 1;
+            } else {
+                next[symbol] = probability.max(0) as u32;
+            }
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &probability) in probabilities.iter().enumerate() {
+            for _ in 0..probability.max(0) {
+                cells[position].symbol = symbol as u8;
+                position = (position + step) & (size - 1);
+                while position >= high {
+                    position = (position + step) & (size - 1);
+                }
+            }
+        }
+        for cell in &mut cells {
+            let state = next[usize::from(cell.symbol)];
+            next[usize::from(cell.symbol)] += 1;
+            let bits = log - (31 - state.leading_zeros());
+            cell.bits = bits as u8;
+            cell.base = ((state << bits) - size as u32) as u16;
+        }
+        Fse { log, cells }
+    }
+
+    // The same symbol every time, for the RLE mode
+    fn single(symbol: u8) -> Fse {
+        Fse { log: 0, cells: vec![FseCell { symbol, bits: 0, base: 0 }] }
+    }
+
+    // A table description and how many bytes it took up
+    fn read(bytes: &[u8], max_log: u32, max_symbol: usize) -> Result<(Fse, usize), ConvertError> {
+        let log = bits_at(bytes, 0, 4) as u32 + 5;
+        if log > max_log {
+            return Err(corrupt());
+        }
+        let mut position = 4;
+        let mut remaining = (1 << log) + 1;
+        let mut threshold = 1 << log;
+        let mut bits = log + 1;
+        let mut probabilities = Vec::new();
+        while remaining > 1 {
+            if probabilities.len() > max_symbol {
+                return Err(corrupt());
+            }
+            let max = 2 * threshold - 1 - remaining;
+            let read = bits_at(bytes, position, bits) as i32;
+            let value = if read & (threshold - 1) < max {
+                position += bits as usize - 1;
+                read & (threshold - 1)
+            } else {
+                position += bits as usize;
+                let value = read & (2 * threshold - 1);
+                if value >= threshold { value - max } else { value }
+            };
+            let probability = value - 1;
+            remaining -= probability.abs();
+            probabilities.push(probability as i16);
+            if probability == 0 {
+                loop {
+                    let repeat = bits_at(bytes, position, 2);
+                    position += 2;
+                    probabilities.extend(std::iter::repeat_n(0, repeat as usize));
+                    if repeat < 3 {
+                        break;
+                    }
+                }
+            }
+            if remaining < 1 {
+                return Err(corrupt());
+            }
+            while remaining < threshold {
+                bits -= 1;
+                threshold >>= 1;
+            }
+        }
+        let used = position.div_ceil(8);
+        if remaining != 1 || probabilities.len() > max_symbol + 1 || used > bytes.len() {
+            return Err(corrupt());
+        }
+        Ok((Fse::build(&probabilities, log), used))
+    }
+
+    fn start(&self, bits: &mut BackwardBits) -> usize {
+        bits.read(self.log) as usize
+    }
+
+    fn symbol(&self, state: usize) -> u8 {
+        self.cells[state].symbol
+    }
+
+    fn next(&self, state: usize, bits: &mut BackwardBits) -> usize {
+        let cell = self.cells[state];
+        usize::from(cell.base) + bits.read(u32::from(cell.bits)) as usize
+    }
+}
+
+// Literals are Huffman coded, the table is indexed by the next `max_bits` bits
+#[derive(Debug)]
+struct Huffman {
+    max_bits: u32,
+    // Symbol and code length
+    table: Vec<(u8, u8)>,
+}
+
+impl Huffman {
+    // A tree description and how many bytes it took up. It lists a weight per symbol but the last,
+    // which is whatever completes the tree.
+    fn read(bytes: &[u8]) -> Result<(Huffman, usize), ConvertError> {
+        let header = usize::from(*bytes.first().ok_or_else(corrupt)?);
+        let (mut weights, used) = if header < 128 {
+            let data = bytes.get(1..1 + header).ok_or_else(corrupt)?;
+            let (fse, table_size) = Fse::read(data, 6, 12)?;
+            let mut bits = BackwardBits::new(&data[table_size..])?;
+            let mut states = [fse.start(&mut bits), fse.start(&mut bits)];
+            let mut weights = Vec::new();
+            // Two states take turns until the bits run out, then the other one has the last weight
+            'weights: loop {
+                for turn in 0..2 {
+                    weights.push(fse.symbol(states[turn]));
+                    states[turn] = fse.next(states[turn], &mut bits);
+                    if bits.remaining < 0 {
+                        weights.push(fse.symbol(states[1 - turn]));
+                        break 'weights;
+                    }
+                    if weights.len() > 255 {
+                        return Err(corrupt());
+                    }
+                }
+            }
+            (weights, 1 + header)
+        } else {
+            let count = header - 127;
+            let data = bytes.get(1..1 + count.div_ceil(2)).ok_or_else(corrupt)?;
+            let weights = (0..count).map(|index| if index % 2 == 0 { data[index / 2] >> 4 } else { data[index / 2] & 0x0f }).collect();
+            (weights, 1 + count.div_ceil(2))
+        };
+        if weights.len() > 255 || weights.iter().any(|&weight| weight > 11) {
+            return Err(corrupt());
+        }
+
+        let total: u32 = weights.iter().filter(|&&weight| weight > 0).map(|&weight| 1 << (weight - 1)).sum();
+        if total == 0 {
+            return Err(corrupt());
+        }
+        let max_bits = 32 - total.leading_zeros();
+        let left = (1 << max_bits) - total;
+        if max_bits > 11 || !left.is_power_of_two() {
+            return Err(corrupt());
+        }
+        weights.push(left.trailing_zeros() as u8 + 1);
+
+        // Lighter weights, longer codes, come first
+        let mut starts = [0usize; 13];
+        let mut position = 0;
+        for (weight, start) in starts.iter_mut().enumerate().take(max_bits as usize + 1).skip(1) {
+            *start = position;
+            position += weights.iter().filter(|&&other| usize::from(other) == weight).count() << (weight - 1);
+        }
+        let mut table = vec![(0, 0); 1 << max_bits];
+        for (symbol, &weight) in weights.iter().enumerate().filter(|(_, &weight)| weight > 0) {
+            let length = 1 << (weight - 1);
+            let start = starts[usize::from(weight)];
+            table[start..start + length].fill((symbol as u8, (max_bits + 1) as u8 - weight));
+            starts[usize::from(weight)] += length;
+        }
+        Ok((Huffman { max_bits, table }, used))
+    }
+
+    fn decode(&self, bytes: &[u8], count: usize, out: &mut Vec<u8>) -> Result<(), ConvertError> {
+        let mut bits = BackwardBits::new(bytes)?;
+        for _ in 0..count {
+            let (symbol, length) = self.table[bits.peek(self.max_bits) as usize];
+            bits.remaining -= isize::from(length);
+            out.push(symbol);
+        }
+        if bits.remaining != 0 {
+            return Err(corrupt());
+        }
+        Ok(())
+    }
+}
+
+// XXH64 with seed 0, the low half ends a frame as its checksum
+fn xxh64(bytes: &[u8]) -> u64 {
+    const PRIMES: [u64; 5] = [
+        0x9e37_79b1_85eb_ca87, 0xc2b2_ae3d_27d4_eb4f, 0x1656_67b1_9e37_79f9, 0x85eb_ca77_c2b2_ae63, 0x27d4_eb2f_1656_67c5,
+    ];
+    let round = |acc: u64, lane: u64| acc.wrapping_add(lane.wrapping_mul(PRIMES[1])).rotate_left(31).wrapping_mul(PRIMES[0]);
+    let word = |bytes: &[u8]| u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let mut rest = bytes;
+    let mut hash = if bytes.len() >= 32 {
+        let mut lanes = [PRIMES[0].wrapping_add(PRIMES[1]), PRIMES[1], 0, PRIMES[0].wrapping_neg()];
+        while rest.len() >= 32 {
+            for (index, lane) in lanes.iter_mut().enumerate() {
+                *lane = round(*lane, word(&rest[index * 8..]));
+            }
+            rest = &rest[32..];
+        }
+        let mut hash = lanes[0].rotate_left(1)
+            .wrapping_add(lanes[1].rotate_left(7))
+            .wrapping_add(lanes[2].rotate_left(12))
+            .wrapping_add(lanes[3].rotate_left(18));
+        for lane in lanes {
+            hash = (hash ^ round(0, lane)).wrapping_mul(PRIMES[0]).wrapping_add(PRIMES[3]);
+        }
+        hash
+    } else {
+        PRIMES[4]
+    };
+    hash = hash.wrapping_add(bytes.len() as u64);
+    while rest.len() >= 8 {
+        hash = (hash ^ round(0, word(rest))).rotate_left(27).wrapping_mul(PRIMES[0]).wrapping_add(PRIMES[3]);
+        rest = &rest[8..];
+    }
+    if rest.len() >= 4 {
+        let half = u64::from(u32::from_le_bytes(rest[..4].try_into().unwrap()));
+        hash = (hash ^ half.wrapping_mul(PRIMES[0])).rotate_left(23).wrapping_mul(PRIMES[1]).wrapping_add(PRIMES[2]);
+        rest = &rest[4..];
+    }
+    for &byte in rest {
+        hash = (hash ^ u64::from(byte).wrapping_mul(PRIMES[4])).rotate_left(11).wrapping_mul(PRIMES[0]);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIMES[1]);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIMES[2]);
+    hash ^ hash >> 32
+}
+
+/* Encoding */
+
+// One frame with the content size and a checksum. `level` runs from 1 to MAX_LEVEL and trades
+// speed for size.
+pub fn compress(bytes: &[u8], level: i32) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    let (size_flag, size_bytes) = match bytes.len() {
+        0..=255 => (0, 1),
+        256..=65_791 => (1, 2),
+        size if size <= u32::MAX as usize => (2, 4),
+        _ => (3, 8),
+    };
+    // A single segment, whose window is the whole content
+    out.push(size_flag << 6 | 0x20 | 0x04);
+    let content_size = if size_bytes == 2 { bytes.len() - 256 } else { bytes.len() };
+    out.extend_from_slice(&(content_size as u64).to_le_bytes()[..size_bytes]);
+
+    let mut matcher = Matcher::new(bytes, level);
+    let mut start = 0;
+    loop {
+        let end = (start + MAX_BLOCK_SIZE).min(bytes.len());
+        let block = &bytes[start..end];
+        let last = u32::from(end == bytes.len());
+        if block.len() > 1 && block.iter().all(|&byte| byte == block[0]) {
+            out.extend_from_slice(&(last | 1 << 1 | (block.len() as u32) << 3).to_le_bytes()[..3]);
+            out.push(block[0]);
+        } else {
+            let compressed = encode_block(&mut matcher, start, end);
+            if compressed.len() < block.len() {
+                out.extend_from_slice(&(last | 2 << 1 | (compressed.len() as u32) << 3).to_le_bytes()[..3]);
+                out.extend_from_slice(&compressed);
+            } else {
+                out.extend_from_slice(&(last | (block.len() as u32) << 3).to_le_bytes()[..3]);
+                out.extend_from_slice(block);
+            }
+        }
+        if end == bytes.len() {
+            break;
+        }
+        start = end;
+    }
+    out.extend_from_slice(&xxh64(bytes).to_le_bytes()[..4]);
+    out
+}
+
+// Earlier positions with the same four bytes ahead, newest first
+struct Matcher<'a> {
+    bytes: &'a [u8],
+    heads: Vec<u32>,
+    chain: Vec<u32>,
+    depth: usize,
+    inserted: usize,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(bytes: &'a [u8], level: i32) -> Matcher<'a> {
+        let level = level.clamp(1, MAX_LEVEL) as u32;
+        Matcher { bytes, heads: vec![u32::MAX; 1 << HASH_LOG], chain: vec![u32::MAX; bytes.len()], depth: 1 << ((level - 1) / 2), inserted: 0 }
+    }
+
+    fn hash(&self, position: usize) -> usize {
+        let word = u32::from_le_bytes(self.bytes[position..position + 4].try_into().unwrap());
+        (word.wrapping_mul(0x9e37_79b1) >> (32 - HASH_LOG)) as usize
+    }
+
+    // Takes in every position before `position`
+    fn insert_until(&mut self, position: usize) {
+        while self.inserted < position.min(self.bytes.len().saturating_sub(MIN_MATCH - 1)) {
+            let hash = self.hash(self.inserted);
+            self.chain[self.inserted] = self.heads[hash];
+            self.heads[hash] = self.inserted as u32;
+            self.inserted += 1;
+        }
+    }
+
+    // The longest match for `position` that ends by `end`, as offset and length
+    fn find(&mut self, position: usize, end: usize) -> Option<(usize, usize)> {
+        if position + MIN_MATCH > end {
+            return None;
+        }
+        self.insert_until(position);
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.heads[self.hash(position)];
+        for _ in 0..self.depth {
+            if candidate == u32::MAX || position - candidate as usize > MAX_MATCH_OFFSET {
+                break;
+            }
+            let from = candidate as usize;
+            let length = self.bytes[from..end].iter().zip(&self.bytes[position..end]).take_while(|(a, b)| a == b).count();
+            if length >= MIN_MATCH && best.is_none_or(|(_, best)| length > best) {
+                best = Some((position - from, length));
+            }
+            candidate = self.chain[from];
+        }
+        best
+    }
+}
+
+// A compressed block for `start..end`
+fn encode_block(matcher: &mut Matcher, start: usize, end: usize) -> Vec<u8> {
+    let bytes = matcher.bytes;
+    let mut literals = Vec::new();
+    let mut sequences = Vec::new();
+    let mut position = start;
+    let mut literal_start = start;
+    while position < end {
+        match matcher.find(position, end) {
+            Some((offset, length)) => {
+                literals.extend_from_slice(&bytes[literal_start..position]);
+                sequences.push(Sequence { literal_length: position - literal_start, offset_value: offset + 3, match_length: length });
+                position += length;
+                literal_start = position;
+            }
+            None => position += 1,
+        }
+    }
+    literals.extend_from_slice(&bytes[literal_start..end]);
+
+    let mut out = Vec::new();
+    // Raw literals
+    match literals.len() {
+        size @ 0..=31 => out.push((size << 3) as u8),
+        size @ 32..=4095 => out.extend_from_slice(&((size << 4 | 1 << 2) as u16).to_le_bytes()),
+        size => out.extend_from_slice(&((size << 4 | 3 << 2) as u32).to_le_bytes()[..3]),
+    }
+    out.extend_from_slice(&literals);
+    match sequences.len() {
+        0 => {
+            out.push(0);
+            return out;
+        }
+        count @ 1..=127 => out.push(count as u8),
+        count @ 128..=0x7eff => out.extend_from_slice(&[(count >> 8) as u8 + 128, count as u8]),
+        count => out.extend_from_slice(&[255, (count - 0x7f00) as u8, ((count - 0x7f00) >> 8) as u8]),
+    }
+    // Predefined tables for all three
+    out.push(0);
+    out.extend_from_slice(&encode_sequences(&sequences));
+    out
+}
+
+// Writes the bits for a decoder to read back to front, so the last sequence goes first. Each
+// state is picked so that the next one is reachable from it.
+fn encode_sequences(sequences: &[Sequence]) -> Vec<u8> {
+    let tables = [PREDEFINED_LITERAL_LENGTHS, PREDEFINED_MATCH_LENGTHS, PREDEFINED_OFFSETS].map(|(probabilities, log)| Fse::build(probabilities, log));
+    let coded: Vec<[(u8, u64, u32); 3]> = sequences.iter().map(|sequence| {
+        let (literal_code, literal_extra, literal_bits) = length_code(&LITERAL_LENGTHS, sequence.literal_length);
+        let (match_code, match_extra, match_bits) = length_code(&MATCH_LENGTHS, sequence.match_length);
+        let offset_code = 63 - (sequence.offset_value as u64).leading_zeros();
+        let offset_extra = sequence.offset_value as u64 - (1 << offset_code);
+        [(literal_code, literal_extra, literal_bits), (match_code, match_extra, match_bits), (offset_code as u8, offset_extra, offset_code)]
+    }).collect();
+
+    // states[k] for each table, from the last sequence back
+    let mut states = vec![[0usize; 3]; coded.len()];
+    let mut transitions = vec![[(0u64, 0u32); 3]; coded.len()];
+    for index in (0..coded.len()).rev() {
+        for (table_index, table) in tables.iter().enumerate() {
+            let symbol = coded[index][table_index].0;
+            let mut cells = table.cells.iter().enumerate().filter(|(_, cell)| cell.symbol == symbol);
+            if index + 1 == coded.len() {
+                states[index][table_index] = cells.next().unwrap().0;
+                continue;
+            }
+            let next = states[index + 1][table_index];
+            let (state, cell) = cells.find(|(_, cell)| (usize::from(cell.base)..usize::from(cell.base) + (1 << cell.bits)).contains(&next)).unwrap();
+            states[index][table_index] = state;
+            transitions[index][table_index] = ((next - usize::from(cell.base)) as u64, u32::from(cell.bits));
+        }
+    }
+
+    let mut bits = BitWriter::default();
+    for index in (0..coded.len()).rev() {
+        let [literal, matched, offset] = coded[index];
+        bits.write(literal.1, literal.2);
+        bits.write(matched.1, matched.2);
+        bits.write(offset.1, offset.2);
+        if index > 0 {
+            let [literal, matched, offset] = transitions[index - 1];
+            bits.write(offset.0, offset.1);
+            bits.write(matched.0, matched.1);
+            bits.write(literal.0, literal.1);
+        }
+    }
+    let [literal, matched, offset] = states[0];
+    bits.write(matched as u64, tables[1].log);
+    bits.write(offset as u64, tables[2].log);
+    bits.write(literal as u64, tables[0].log);
+    bits.finish()
+}
+
+// The code of a length and its extra bits
+fn length_code(codes: &[(u32, u32)], length: usize) -> (u8, u64, u32) {
+    let code = codes.iter().rposition(|&(base, _)| base as usize <= length).unwrap();
+    let (base, bits) = codes[code];
+    (code as u8, (length - base as usize) as u64, bits)
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    pending: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, count: u32) {
+        self.pending |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.pending as u8);
+            self.pending >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Ends with the bit a reader starts from
+    fn finish(mut self) -> Vec<u8> {
+        self.write(1, 1);
+        if self.count > 0 {
+            self.bytes.push(self.pending as u8);
+        }
+        self.bytes
+    }
+}
+
+
+/* Tests */
+#[test]
+fn test_zstd_round_trips_at_several_levels() {
+    let records: Vec<u8> = (0..5_000u32).flat_map(|i| {
+        let mut record = vec![0x83, 0xa2, b'i', b'd', 0xcd];
+        record.extend_from_slice(&(i as u16).to_be_bytes());
+        record.extend_from_slice(&[0xa4, b'n', b'a', b'm', b'e', 0xa6, b'u', b's', b'e', b'r']);
+        record.extend_from_slice(format!("{:02}", i % 97).as_bytes());
+        record.extend_from_slice(&[0xa5, b's', b'c', b'o', b'r', b'e', 0xcb]);
+        record.extend_from_slice(&(f64::from(i) / 7.0).to_be_bytes());
+        record
+    }).collect();
+    let sizes: Vec<usize> = [1, DEFAULT_LEVEL, MAX_LEVEL].iter().map(|&level| {
+        let compressed = compress(&records, level);
+        assert_eq!(decompress(&compressed, records.len()).unwrap(), records);
+        compressed.len()
+    }).collect();
+    assert!(sizes[0] < records.len() / 2, "{:?}", sizes);
+    assert!(sizes[2] <= sizes[0], "{:?}", sizes);
+
+    // Blocks of one byte repeated, too small to compress and none at all
+    for bytes in [vec![7; 300_000], b"x".to_vec(), Vec::new()] {
+        assert_eq!(decompress(&compress(&bytes, 1), bytes.len()).unwrap(), bytes);
+    }
+}
+
+#[test]
+fn test_zstd_frames_from_the_reference_encoder() {
+    // `zstd -19` of the text below, with Huffman-coded literals in four streams
+    let frame = hex::decode(concat!(
+        "28b52ffd6470024d0800a6502713b0a7d8c8c408698c7f986a734bda99b0b6641e2a001e001e002722aab2a759574343",
+        "9939d555cda2c610180646710700611c86444187808128c6400c00472100825108bd79ea5be7dccf7842365acd996397",
+        "a46a670d8d46eea93bfa8d6655663a253aaf6391a1ba69bf6b12f62e453a9bd355cd64b296e4c988ccef7356af422b7d",
+        "d61d5f9999793de1797f5e256ecb1a91b995b8a8f691d25fbb035fa82118db67ff37402bb20e1010c70d6f0dd7b0270c",
+        "5f95c00b59163b56650b95a7ef4cb9e061a0fd79e6a16ae08e46326274814485c02b9eb54f41e34eb9d20b06ae1b1767",
+        "1ee2d9e377921d74fc38ce8d6a7496118cbb682bee1315517fa1a84702d1878407a02a41c68258",
+    )).unwrap();
+    let words = ["name", "id", "tags", "score", "alpha", "beta", "gamma", "delta", "created", "updated", "owner", "status"];
+    let text: Vec<String> = (0..100).map(|i| format!("{}{}", words[(i * 7 + i / 5) % words.len()], i * i % 1009)).collect();
+    let text = text.join(" ");
+    assert_eq!(decompress(&frame, text.len()).unwrap(), text.as_bytes());
+    // Frames follow each other, skippable ones are left out
+    let mut frames = compress(b"one ", 3);
+    frames.extend_from_slice(&[0x50, 0x2a, 0x4d, 0x18, 2, 0, 0, 0, 0xaa, 0xbb]);
+    frames.extend_from_slice(&frame);
+    assert_eq!(decompress(&frames, 4 + text.len()).unwrap(), [b"one ", text.as_bytes()].concat());
+
+    let mut corrupt = frame.clone();
+    *corrupt.last_mut().unwrap() ^= 1;
+    assert_eq!(decompress(&corrupt, text.len()).unwrap_err().to_string(), "Failed to decompress: The zstd checksum doesn't match");
+    assert!(decompress(&frame[..frame.len() - 10], text.len()).is_err());
+    assert!(matches!(decompress(&frame, 100), Err(ConvertError::Decompress(_))));
+}
+
+#[test]
+fn test_corrupt_fse_tables_fail_without_panicking() {
+    // One compressed block with no literals and one sequence, whose literal length table
+    // description is the single byte 00: its probabilities run on past the end of the block
+    let frame = [0x28, 0xb5, 0x2f, 0xfd, 0x20, 0x01, 0x25, 0x00, 0x00, 0x00, 0x01, 0x80, 0x00];
+    assert!(matches!(decompress(&frame, 100), Err(ConvertError::Decompress(_))));
+}
+
+#[test]
+fn test_xxh64() {
+    assert_eq!(xxh64(b""), 0xef46_db37_51d8_e999);
+    assert_eq!(xxh64(b"abc"), 0x44bc_2cf5_ad77_0999);
+    assert_eq!(xxh64(b"Nobody inspects the spammish repetition"), 0xfbce_a83c_8a37_8bf1);
+}
@@ -0,0 +1,195 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn converter(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_messagepack_to_json")).args(args).output().unwrap()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("messagepack_to_json_cli_{}_{}", std::process::id(), name))
+}
+
+fn path_arg(path: &std::path::Path) -> &str {
+    path.to_str().unwrap()
+}
+
+#[test]
+fn test_convert_json_to_hex_and_back() {
+    let json = temp_path("alice.json");
+    let hex = temp_path("alice.hex");
+    let back = temp_path("alice_back.json");
+    fs::write(&json, r#"{"name": "Alice", "age": 30}"#).unwrap();
+
+    let output = converter(&["convert", "--from", "json", "--to", "msgpack", "--input", path_arg(&json), "--output", path_arg(&hex), "--encoding", "hex"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string(&hex).unwrap(), "82a36167651ea46e616d65a5416c696365");
+
+    let output = converter(&["convert", "--from", "msgpack", "--to", "json", "--input", path_arg(&hex), "--output", path_arg(&back), "--encoding", "hex", "--compact"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string(&back).unwrap(), r#"{"age":30,"name":"Alice"}"#);
+
+    for path in [json, hex, back] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_failures_exit_non_zero() {
+    let broken = temp_path("broken.msgpack");
+    let output_path = temp_path("broken.json");
+    // Truncated map
+    fs::write(&broken, [0x82, 0xa1, 0x61]).unwrap();
+    let output = converter(&["convert", "--from", "msgpack", "--to", "json", "--input", path_arg(&broken), "--output", path_arg(&output_path)]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to deserialize MessagePack"));
+    assert!(!output_path.exists());
+    fs::remove_file(broken).unwrap();
+
+    let output = converter(&["convert", "--from", "toml", "--to", "json"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown format toml"));
+}
+
+#[test]
+fn test_pipes_raw_bytes_through_stdin_and_stdout() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_messagepack_to_json"))
+        .args(["convert", "--from", "json", "--to", "msgpack", "--stream"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"{\"a\": 1}\n[true, null]\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, [0x81, 0xa1, 0x61, 0x01, 0x92, 0xc3, 0xc0]);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_messagepack_to_json"))
+        .args(["convert", "--from", "msgpack", "--to", "json", "--input", "-", "--output", "-", "--stream"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    // Each record comes out while the pipe is still open
+    let mut line = String::new();
+    for (record, json) in [(&[0x81, 0xa1, 0x61, 0x01][..], "{\"a\":1}\n"), (&[0x92, 0xc3, 0xc0][..], "[true,null]\n")] {
+        stdin.write_all(record).unwrap();
+        stdin.flush().unwrap();
+        line.clear();
+        stdout.read_line(&mut line).unwrap();
+        assert_eq!(line, json);
+    }
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn test_warnings_go_to_stderr() {
+    let input = temp_path("binary.msgpack");
+    // {"a": bin [1]}
+    fs::write(&input, [0x81, 0xa1, b'a', 0xc4, 0x01, 0x01]).unwrap();
+    let output = converter(&["convert", "--from", "msgpack", "--to", "json", "--input", path_arg(&input), "--lossy", "--compact"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, br#"{"a":"AQ=="}"#);
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "messagepack_to_json: warning: /a at offset 0x3: binary value as base64\n");
+    fs::remove_file(input).unwrap();
+}
+
+#[test]
+fn test_encode_and_decode_through_pipes() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_messagepack_to_json"))
+        .args(["encode", "--encoding", "base64"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(br#"{"id": 7}"#).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"gaJpZAc=");
+
+    let input = temp_path("id.msgpack");
+    fs::write(&input, [0x81, 0xa2, b'i', b'd', 0x07]).unwrap();
+    let output = converter(&["decode", path_arg(&input), "--compact"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output.stdout, br#"{"id":7}"#);
+    fs::remove_file(input).unwrap();
+}
+
+#[test]
+fn test_unregistered_ext_types_round_trip_tagged() {
+    let registry = temp_path("ext_types.toml");
+    let input = temp_path("ext.msgpack");
+    let json = temp_path("ext.json");
+    let back = temp_path("ext_back.msgpack");
+    fs::write(&registry, "# The ext type of the points\n7 = \"struct x:i8 y:i8\"\n").unwrap();
+    // [ext 5 [1, 2], ext 7 [1, -1]], type 5 isn't registered
+    let bytes = [0x92, 0xd5, 0x05, 0x01, 0x02, 0xd5, 0x07, 0x01, 0xff];
+    fs::write(&input, bytes).unwrap();
+
+    let output = converter(&["decode", path_arg(&input), "--output", path_arg(&json), "--compact", "--tag-unknown-exts", "--ext-types", path_arg(&registry)]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string(&json).unwrap(), r#"[{"$ext":5,"value":"AQI="},{"$ext":7,"value":{"x":1,"y":-1}}]"#);
+
+    let output = converter(&["encode", path_arg(&json), "--output", path_arg(&back), "--ext-types", path_arg(&registry), "--tag-unknown-exts"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read(&back).unwrap(), bytes);
+
+    // Strict decoding fails on the ext value without the flag
+    let output = converter(&["decode", path_arg(&input), "--ext-types", path_arg(&registry)]);
+    assert_eq!(output.status.code(), Some(1));
+    for path in [registry, input, json, back] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_tagged_bins_round_trip() {
+    let input = temp_path("bins.msgpack");
+    let json = temp_path("bins.json");
+    let back = temp_path("bins_back.msgpack");
+    // [bin "\x81\xa1a", 1]
+    let bytes = [0x92, 0xc4, 0x03, 0x81, 0xa1, b'a', 0x01];
+    fs::write(&input, bytes).unwrap();
+
+    let output = converter(&["decode", path_arg(&input), "--output", path_arg(&json), "--compact", "--bins", "tagged"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string(&json).unwrap(), r#"[{"$bin":"gaFh"},1]"#);
+
+    let output = converter(&["encode", path_arg(&json), "--output", path_arg(&back), "--bins=tagged"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read(&back).unwrap(), bytes);
+
+    let output = converter(&["decode", path_arg(&input), "--compact", "--bins", "hex"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), r#"["81a161",1]"#);
+    for path in [input, json, back] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_maps_with_other_keys_round_trip_as_pairs() {
+    let input = temp_path("pairs.msgpack");
+    let json = temp_path("pairs.json");
+    let back = temp_path("pairs_back.msgpack");
+    // {"a": 1, 2: "b"}
+    let bytes = [0x82, 0xa1, b'a', 0x01, 0x02, 0xa1, b'b'];
+    fs::write(&input, bytes).unwrap();
+
+    let output = converter(&["decode", path_arg(&input), "--output", path_arg(&json), "--compact", "--other-keys", "pairs"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string(&json).unwrap(), r#"{"$map":[["a",1],[2,"b"]]}"#);
+
+    let output = converter(&["encode", path_arg(&json), "--output", path_arg(&back), "--other-keys=pairs"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read(&back).unwrap(), bytes);
+
+    let output = converter(&["decode", path_arg(&input), "--compact", "--other-keys", "stringify"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), r#"{"2":"b","a":1}"#);
+    for path in [input, json, back] {
+        fs::remove_file(path).unwrap();
+    }
+}
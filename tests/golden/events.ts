@@ -0,0 +1,15 @@
+export type Root = (RootItem | string | null)[];
+
+export interface RootItem {
+  type: string;
+  at: number;
+  target?: Target | string;
+  key?: string;
+  modifiers?: (string | number)[];
+  delta?: number[][];
+}
+
+export interface Target {
+  id: string;
+  rect: number[];
+}
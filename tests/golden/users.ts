@@ -0,0 +1,40 @@
+export interface Root {
+  page: number;
+  "total-count": number;
+  users: User[];
+  meta: Meta;
+}
+
+export interface User {
+  id: number;
+  name: string;
+  email: string | null;
+  roles: string[];
+  address: Address;
+  settings?: Settings;
+  "last login"?: string;
+}
+
+export interface Address {
+  city: string;
+  zip: string | number;
+  geo?: Geo;
+}
+
+export interface Geo {
+  lat: number;
+  lng: number;
+}
+
+export interface Settings {
+  theme: string;
+  "2fa": boolean;
+}
+
+export interface Meta {
+  address: Address2;
+}
+
+export interface Address2 {
+  street: string;
+}